@@ -76,6 +76,11 @@ impl Ast {
     pub fn const0(&self) -> &Const {
         self.files.last().unwrap().elements[0].to_const().unwrap()
     }
+
+    #[cfg(test)]
+    pub fn enum0(&self) -> &Enum {
+        self.files.last().unwrap().elements[0].to_enum().unwrap()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -102,6 +107,7 @@ pub enum Elem {
     ElemImpl(Impl),
     ElemGlobal(Global),
     ElemConst(Const),
+    ElemEnum(Enum),
 }
 
 impl Elem {
@@ -114,6 +120,7 @@ impl Elem {
             &ElemImpl(ref i) => i.id,
             &ElemGlobal(ref g) => g.id,
             &ElemConst(ref c) => c.id,
+            &ElemEnum(ref e) => e.id,
         }
     }
 
@@ -165,6 +172,13 @@ impl Elem {
             _ => None,
         }
     }
+
+    pub fn to_enum(&self) -> Option<&Enum> {
+        match self {
+            &ElemEnum(ref xenum) => Some(xenum),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -186,6 +200,14 @@ pub struct Const {
     pub expr: Box<Expr>,
 }
 
+#[derive(Clone, Debug)]
+pub struct Enum {
+    pub id: NodeId,
+    pub pos: Position,
+    pub name: Name,
+    pub values: Vec<Name>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Struct {
     pub id: NodeId,
@@ -1563,22 +1585,13 @@ impl Expr {
         })
     }
 
-    pub fn create_lambda(
-        id: NodeId,
-        pos: Position,
-        span: Span,
-        params: Vec<Param>,
-        ret: Option<Box<Type>>,
-        block: Box<Stmt>,
-    ) -> Expr {
+    pub fn create_lambda(id: NodeId, pos: Position, span: Span, fct: Function) -> Expr {
         Expr::ExprLambda(ExprLambdaType {
             id: id,
             pos: pos,
             span: span,
 
-            params: params,
-            ret: ret,
-            block: block,
+            fct: Box::new(fct),
         })
     }
 
@@ -2129,9 +2142,7 @@ pub struct ExprLambdaType {
     pub pos: Position,
     pub span: Span,
 
-    pub params: Vec<Param>,
-    pub ret: Option<Box<Type>>,
-    pub block: Box<Stmt>,
+    pub fct: Box<Function>,
 }
 
 #[derive(Clone, Debug)]
@@ -76,6 +76,7 @@ impl<'a> AstDumper<'a> {
                 ElemImpl(ref ximpl) => self.dump_impl(ximpl),
                 ElemGlobal(ref global) => self.dump_global(global),
                 ElemConst(ref xconst) => self.dump_const(xconst),
+                ElemEnum(ref xenum) => self.dump_enum(xenum),
             }
         }
     }
@@ -132,6 +133,22 @@ impl<'a> AstDumper<'a> {
         });
     }
 
+    fn dump_enum(&mut self, xenum: &Enum) {
+        dump!(
+            self,
+            "enum {} @ {} {}",
+            self.str(xenum.name),
+            xenum.pos,
+            xenum.id
+        );
+
+        self.indent(|d| {
+            for &value in &xenum.values {
+                dump!(d, "value {}", d.str(value));
+            }
+        });
+    }
+
     fn dump_struct(&mut self, struc: &Struct) {
         dump!(
             self,
@@ -577,7 +594,7 @@ impl<'a> AstDumper<'a> {
 
     fn dump_expr_lambda(&mut self, expr: &ExprLambdaType) {
         dump!(self, "lambda @ {} {}", expr.pos, expr.id);
-        self.indent(|d| d.dump_stmt(&expr.block));
+        self.indent(|d| d.dump_fct(&expr.fct));
     }
 
     fn dump_expr_dot(&mut self, field: &ExprDotType) {
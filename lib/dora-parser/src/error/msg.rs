@@ -29,6 +29,8 @@ pub enum Msg {
     ShadowField(String),
     ShadowGlobal(String),
     ShadowConst(String),
+    ShadowEnum(String),
+    ShadowEnumValue(String),
     VarNeedsTypeInfo(String),
     ParamTypesIncompatible(String, Vec<String>, Vec<String>),
     WhileCondType(String),
@@ -57,6 +59,8 @@ pub enum Msg {
     ClassReassigned,
     FctUsedAsIdentifier,
     ClsUsedAsIdentifier,
+    EnumUsedAsIdentifier,
+    EnumReassigned,
     UnderivableType(String),
     CycleInHierarchy,
     SuperfluousOverride(String),
@@ -125,6 +129,9 @@ pub enum Msg {
     InvalidLeftSideOfSeparator,
     InvalidUseOfTypeParams,
     NameOfStaticMethodExpected,
+    UnknownEnumValue(String, String),
+    EnumValueExpected,
+    NoEnumValue,
 }
 
 impl Msg {
@@ -189,6 +196,8 @@ impl Msg {
             ShadowField(ref name) => format!("field with name `{}` already exists.", name),
             ShadowGlobal(ref name) => format!("can not shadow global variable `{}`.", name),
             ShadowConst(ref name) => format!("can not shadow const `{}`", name),
+            ShadowEnum(ref name) => format!("can not shadow enum `{}`.", name),
+            ShadowEnumValue(ref name) => format!("value with name `{}` already exists.", name),
             VarNeedsTypeInfo(ref name) => format!(
                 "variable `{}` needs either type declaration or expression.",
                 name
@@ -249,6 +258,8 @@ impl Msg {
             ClassReassigned => "class cannot be reassigned.".into(),
             FctUsedAsIdentifier => "function cannot be used as identifier.".into(),
             ClsUsedAsIdentifier => "class cannot be used as identifier.".into(),
+            EnumUsedAsIdentifier => "enum cannot be used as identifier.".into(),
+            EnumReassigned => "enum cannot be reassigned.".into(),
             UnderivableType(ref name) => format!("type `{}` cannot be used as super class.", name),
             CycleInHierarchy => "cycle in type hierarchy detected.".into(),
             SuperfluousOverride(_) => {
@@ -389,6 +400,11 @@ impl Msg {
             InvalidLeftSideOfSeparator => "left hand side of separator is not a class.".into(),
             InvalidUseOfTypeParams => "type params need to be used on class or function.".into(),
             NameOfStaticMethodExpected => "name of static method expected.".into(),
+            UnknownEnumValue(ref xenum, ref name) => {
+                format!("enum `{}` does not have value named `{}`.", xenum, name)
+            }
+            EnumValueExpected => "name of enum value expected.".into(),
+            NoEnumValue => "enum needs at least one value.".into(),
         }
     }
 }
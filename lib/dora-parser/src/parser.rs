@@ -108,6 +108,12 @@ impl<'a> Parser<'a> {
                 elements.push(ElemStruct(struc))
             }
 
+            TokenKind::Enum => {
+                self.ban_modifiers(&modifiers)?;
+                let xenum = self.parse_enum()?;
+                elements.push(ElemEnum(xenum));
+            }
+
             TokenKind::Trait => {
                 self.ban_modifiers(&modifiers)?;
                 let xtrait = self.parse_trait()?;
@@ -162,6 +168,21 @@ impl<'a> Parser<'a> {
         })
     }
 
+    fn parse_enum(&mut self) -> Result<Enum, MsgWithPos> {
+        let pos = self.expect_token(TokenKind::Enum)?.position;
+        let name = self.expect_identifier()?;
+
+        self.expect_token(TokenKind::LBrace)?;
+        let values = self.parse_comma_list(TokenKind::RBrace, |p| p.expect_identifier())?;
+
+        Ok(Enum {
+            id: self.generate_id(),
+            pos: pos,
+            name: name,
+            values: values,
+        })
+    }
+
     fn parse_impl(&mut self) -> Result<Impl, MsgWithPos> {
         let pos = self.expect_token(TokenKind::Impl)?.position;
         let type_params = self.parse_type_params()?;
@@ -840,12 +861,21 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_spawn(&mut self) -> StmtResult {
+        let start = self.token.span.start();
         let pos = self.expect_token(TokenKind::Spawn)?.position;
-        let expr = self.parse_expression()?;
+        let block = self.parse_block()?;
+        let span = self.span_from(start);
 
-        self.expect_semicolon()?;
+        // spawned block is executed as lambda without parameters
+        let name = self.interner.intern("<spawn>");
+        let fct = self.create_lambda_fct(name, pos, Vec::new(), None, block);
+        let lambda = Expr::create_lambda(self.generate_id(), pos, span, fct);
 
-        Ok(Box::new(Stmt::create_spawn(self.generate_id(), pos, expr)))
+        Ok(Box::new(Stmt::create_spawn(
+            self.generate_id(),
+            pos,
+            Box::new(lambda),
+        )))
     }
 
     fn parse_throw(&mut self) -> StmtResult {
@@ -1526,7 +1556,7 @@ impl<'a> Parser<'a> {
 
         let ret = if self.token.is(TokenKind::Arrow) {
             self.advance_token()?;
-            Some(Box::new(self.parse_type()?))
+            Some(self.parse_type()?)
         } else {
             None
         };
@@ -1534,16 +1564,47 @@ impl<'a> Parser<'a> {
         let block = self.parse_block()?;
         let span = self.span_from(start);
 
+        let name = self.interner.intern("<lambda>");
+        let fct = self.create_lambda_fct(name, tok.position, params, ret, block);
+
         Ok(Box::new(Expr::create_lambda(
             self.generate_id(),
             tok.position,
             span,
-            params,
-            ret,
-            block,
+            fct,
         )))
     }
 
+    fn create_lambda_fct(
+        &mut self,
+        name: Name,
+        pos: Position,
+        params: Vec<Param>,
+        ret: Option<Type>,
+        block: Box<Stmt>,
+    ) -> Function {
+        Function {
+            id: self.generate_id(),
+            name: name,
+            pos: pos,
+            method: false,
+            has_open: false,
+            has_override: false,
+            has_final: false,
+            has_optimize: false,
+            is_pub: false,
+            is_static: false,
+            internal: false,
+            is_abstract: false,
+            is_constructor: false,
+            params: params,
+            throws: false,
+            return_type: ret,
+            block: Some(block),
+            type_params: None,
+        }
+    }
+
     fn expect_identifier(&mut self) -> Result<Name, MsgWithPos> {
         let tok = self.advance_token()?;
 
@@ -3143,9 +3204,13 @@ mod tests {
 
     #[test]
     fn parse_spawn() {
-        let stmt = parse_stmt("spawn 1;");
+        let stmt = parse_stmt("spawn { 1; }");
         let spawn = stmt.to_spawn().unwrap();
-        assert!(spawn.expr.is_lit_int());
+        let lambda = spawn.expr.to_lambda().unwrap();
+
+        assert!(lambda.fct.params.is_empty());
+        assert!(lambda.fct.return_type.is_none());
+        assert!(lambda.fct.block.as_ref().unwrap().is_block());
     }
 
     #[test]
@@ -3213,6 +3278,25 @@ mod tests {
         assert_eq!("x", *interner.str(xconst.name));
     }
 
+    #[test]
+    fn parse_enum() {
+        let (prog, interner) = parse("enum Foo { A, B, C }");
+        let xenum = prog.enum0();
+
+        assert_eq!("Foo", *interner.str(xenum.name));
+        assert_eq!(3, xenum.values.len());
+        assert_eq!("A", *interner.str(xenum.values[0]));
+        assert_eq!("C", *interner.str(xenum.values[2]));
+    }
+
+    #[test]
+    fn parse_enum_trailing_comma() {
+        let (prog, _) = parse("enum Foo { A, B, }");
+        let xenum = prog.enum0();
+
+        assert_eq!(2, xenum.values.len());
+    }
+
     #[test]
     fn parse_generic_with_bound() {
         let (prog, _) = parse("class A[T: Foo]");
@@ -3246,14 +3330,14 @@ mod tests {
         let (expr, _) = parse_expr("|| {}");
         let lambda = expr.to_lambda().unwrap();
 
-        assert!(lambda.ret.is_none());
+        assert!(lambda.fct.return_type.is_none());
     }
 
     #[test]
     fn parse_lambda_no_params_unit_as_return_value() {
         let (expr, _) = parse_expr("|| -> () {}");
         let lambda = expr.to_lambda().unwrap();
-        let ret = lambda.ret.as_ref().unwrap();
+        let ret = lambda.fct.return_type.as_ref().unwrap();
 
         assert!(ret.is_unit());
     }
@@ -3262,7 +3346,7 @@ mod tests {
     fn parse_lambda_no_params_with_return_value() {
         let (expr, interner) = parse_expr("|| -> A {}");
         let lambda = expr.to_lambda().unwrap();
-        let ret = lambda.ret.as_ref().unwrap();
+        let ret = lambda.fct.return_type.as_ref().unwrap();
         let basic = ret.to_basic().unwrap();
 
         assert_eq!("A", *interner.str(basic.name));
@@ -3273,14 +3357,14 @@ mod tests {
         let (expr, interner) = parse_expr("|a: A| -> B {}");
         let lambda = expr.to_lambda().unwrap();
 
-        assert_eq!(1, lambda.params.len());
+        assert_eq!(1, lambda.fct.params.len());
 
-        let param = &lambda.params[0];
+        let param = &lambda.fct.params[0];
         assert_eq!("a", *interner.str(param.name));
         let basic = param.data_type.to_basic().unwrap();
         assert_eq!("A", *interner.str(basic.name));
 
-        let ret = lambda.ret.as_ref().unwrap();
+        let ret = lambda.fct.return_type.as_ref().unwrap();
         let basic = ret.to_basic().unwrap();
 
         assert_eq!("B", *interner.str(basic.name));
@@ -3291,19 +3375,19 @@ mod tests {
         let (expr, interner) = parse_expr("|a: A, b: B| -> C {}");
         let lambda = expr.to_lambda().unwrap();
 
-        assert_eq!(2, lambda.params.len());
+        assert_eq!(2, lambda.fct.params.len());
 
-        let param = &lambda.params[0];
+        let param = &lambda.fct.params[0];
         assert_eq!("a", *interner.str(param.name));
         let basic = param.data_type.to_basic().unwrap();
         assert_eq!("A", *interner.str(basic.name));
 
-        let param = &lambda.params[1];
+        let param = &lambda.fct.params[1];
         assert_eq!("b", *interner.str(param.name));
         let basic = param.data_type.to_basic().unwrap();
         assert_eq!("B", *interner.str(basic.name));
 
-        let ret = lambda.ret.as_ref().unwrap();
+        let ret = lambda.fct.return_type.as_ref().unwrap();
         let basic = ret.to_basic().unwrap();
 
         assert_eq!("C", *interner.str(basic.name));
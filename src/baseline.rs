@@ -1,12 +1,19 @@
 pub use crate::baseline::codegen::{generate, generate_fct};
 
-mod asm;
+pub mod asm;
 pub mod ast;
 pub mod cannon;
 pub mod codegen;
+pub mod deopt;
 pub mod dora_compile;
 pub mod dora_entry;
+pub mod dora_ic;
+pub mod dora_interp;
+pub mod dora_invoke;
 pub mod dora_native;
 pub mod dora_throw;
 pub mod fct;
+pub mod ic;
 pub mod map;
+pub mod queue;
+pub mod tier;
@@ -1,15 +1,23 @@
 use std::mem;
 
+use dora_parser::ast::NodeId;
 use dora_parser::lexer::position::Position;
 
 use crate::baseline::codegen::{ensure_native_stub, AllocationSize, CondCode, ExprStore};
+use crate::baseline::deopt;
 use crate::baseline::dora_native::{InternalFct, InternalFctDescriptor};
-use crate::baseline::fct::{CatchType, Comment, GcPoint, JitBaselineFct, JitDescriptor};
+use crate::baseline::fct::{
+    BailoutInfo, CatchType, Comment, DeoptInfo, GcPoint, JitBaselineFct, JitDescriptor,
+};
+use crate::baseline::tier;
 use crate::class::TypeParams;
-use crate::cpu::{FReg, Mem, Reg, FREG_RESULT, REG_PARAMS, REG_RESULT, REG_THREAD, REG_TMP1};
+use crate::cpu::{
+    FReg, Mem, Reg, FREG_RESULT, REG_FP, REG_PARAMS, REG_RESULT, REG_THREAD, REG_TMP1,
+};
 use crate::gc::tlab::TLAB_OBJECT_SIZE;
 use crate::gc::Address;
 use crate::masm::{Label, MacroAssembler, ScratchReg};
+use crate::opt::fct::JitOptFct;
 use crate::os::signal::Trap;
 use crate::stdlib;
 use crate::threads::ThreadLocalData;
@@ -20,7 +28,8 @@ use crate::vm::VM;
 pub struct BaselineAssembler<'a, 'ast: 'a> {
     masm: MacroAssembler,
     vm: &'a VM<'ast>,
-    slow_paths: Vec<SlowPathKind>,
+    // out-of-line code keeps the inlined functions of the code it belongs to
+    slow_paths: Vec<(SlowPathKind, Vec<(FctId, i32)>)>,
 }
 
 impl<'a, 'ast> BaselineAssembler<'a, 'ast>
@@ -43,6 +52,13 @@ where
         self.masm.prolog(stacksize);
     }
 
+    pub fn check_stack_limit(&mut self, pos: Position) {
+        let lbl_overflow = self.masm.create_label();
+        self.masm.check_stack_limit(lbl_overflow);
+        self.masm
+            .emit_bailout(lbl_overflow, Trap::STACK_OVERFLOW, pos, GcPoint::new());
+    }
+
     pub fn epilog_with_polling(&mut self, stacksize: i32, polling_page: Address) {
         self.masm.epilog_with_polling(stacksize, polling_page);
     }
@@ -103,8 +119,8 @@ where
         self.masm.test_and_jump_if(cond, reg, lbl);
     }
 
-    pub fn test_if_nil_bailout(&mut self, pos: Position, reg: Reg, trap: Trap) {
-        self.masm.test_if_nil_bailout(pos, reg, trap);
+    pub fn test_if_nil_bailout(&mut self, pos: Position, reg: Reg, trap: Trap, gcpoint: GcPoint) {
+        self.masm.test_if_nil_bailout(pos, reg, trap, gcpoint);
     }
 
     pub fn test_if_nil(&mut self, reg: Reg) -> Label {
@@ -135,12 +151,12 @@ where
         self.masm.load_false(dest);
     }
 
-    pub fn emit_bailout(&mut self, lbl: Label, trap: Trap, pos: Position) {
-        self.masm.emit_bailout(lbl, trap, pos);
+    pub fn emit_bailout(&mut self, lbl: Label, trap: Trap, pos: Position, gcpoint: GcPoint) {
+        self.masm.emit_bailout(lbl, trap, pos, gcpoint);
     }
 
-    pub fn emit_bailout_inplace(&mut self, trap: Trap, pos: Position) {
-        self.masm.emit_bailout_inplace(trap, pos)
+    pub fn emit_bailout_inplace(&mut self, trap: Trap, pos: Position, gcpoint: GcPoint) {
+        self.masm.emit_bailout_inplace(trap, pos, gcpoint)
     }
 
     pub fn emit_exception_handler(
@@ -198,12 +214,28 @@ where
         self.masm.int_mul(mode, dest, lhs, rhs);
     }
 
-    pub fn int_div(&mut self, mode: MachineMode, dest: Reg, lhs: Reg, rhs: Reg) {
-        self.masm.int_div(mode, dest, lhs, rhs);
+    pub fn int_div(
+        &mut self,
+        mode: MachineMode,
+        dest: Reg,
+        lhs: Reg,
+        rhs: Reg,
+        pos: Position,
+        gcpoint: GcPoint,
+    ) {
+        self.masm.int_div(mode, dest, lhs, rhs, pos, gcpoint);
     }
 
-    pub fn int_mod(&mut self, mode: MachineMode, dest: Reg, lhs: Reg, rhs: Reg) {
-        self.masm.int_mod(mode, dest, lhs, rhs);
+    pub fn int_mod(
+        &mut self,
+        mode: MachineMode,
+        dest: Reg,
+        lhs: Reg,
+        rhs: Reg,
+        pos: Position,
+        gcpoint: GcPoint,
+    ) {
+        self.masm.int_mod(mode, dest, lhs, rhs, pos, gcpoint);
     }
 
     pub fn int_neg(&mut self, mode: MachineMode, dest: Reg, src: Reg) {
@@ -303,8 +335,10 @@ where
         base: Reg,
         offset: i32,
         line: i32,
+        gcpoint: GcPoint,
     ) {
-        self.masm.load_field(mode, dest, base, offset, line);
+        self.masm
+            .load_field(mode, dest, base, offset, line, gcpoint);
     }
 
     pub fn store_field(
@@ -316,6 +350,7 @@ where
         line: i32,
         write_barrier: bool,
         card_table_offset: usize,
+        gcpoint: GcPoint,
     ) {
         self.masm.store_field(
             mode,
@@ -325,6 +360,7 @@ where
             line,
             write_barrier,
             card_table_offset,
+            gcpoint,
         );
     }
 
@@ -353,8 +389,15 @@ where
         self.masm.copy(mode, dest, src);
     }
 
-    pub fn check_index_out_of_bounds(&mut self, pos: Position, array: Reg, index: Reg) {
-        self.masm.check_index_out_of_bounds(pos, array, index);
+    pub fn check_index_out_of_bounds(
+        &mut self,
+        pos: Position,
+        array: Reg,
+        index: Reg,
+        gcpoint: GcPoint,
+    ) {
+        self.masm
+            .check_index_out_of_bounds(pos, array, index, gcpoint);
     }
 
     pub fn extend_byte(&mut self, mode: MachineMode, dest: Reg, src: Reg) {
@@ -417,6 +460,10 @@ where
         self.masm.emit_lineno(lineno);
     }
 
+    pub fn set_inlined_frames(&mut self, frames: Vec<(FctId, i32)>) {
+        self.masm.set_inlined_frames(frames);
+    }
+
     pub fn var_store(&mut self, offset: i32, ty: BuiltinType, src: ExprStore) {
         self.masm.store_mem(ty.mode(), Mem::Local(offset), src);
     }
@@ -430,6 +477,11 @@ where
         self.masm.jit(self.vm, stacksize, desc, throws)
     }
 
+    pub fn jit_opt(mut self, framesize: i32, fct_id: FctId, throws: bool) -> JitOptFct {
+        self.slow_paths();
+        self.masm.jit_opt(self.vm, framesize, fct_id, throws)
+    }
+
     pub fn native_call(
         &mut self,
         internal_fct: InternalFct,
@@ -459,6 +511,8 @@ where
         self.call_epilog(pos, ty, dest, gcpoint);
     }
 
+    // the receiver is checked for nil before its vtable is loaded, for lambda
+    // calls (index 0) the receiver is the closure
     pub fn indirect_call(
         &mut self,
         index: u32,
@@ -467,7 +521,23 @@ where
         ty: BuiltinType,
         dest: ExprStore,
     ) {
-        self.masm.indirect_call(pos.line as i32, index);
+        self.masm
+            .indirect_call(pos.line as i32, index, gcpoint.clone());
+        self.call_epilog(pos, ty, dest, gcpoint);
+    }
+
+    // virtual call through the inline cache at `cache`, see `baseline::ic`
+    pub fn inline_cache_call(
+        &mut self,
+        cache: Address,
+        pos: Position,
+        gcpoint: GcPoint,
+        ty: BuiltinType,
+        dest: ExprStore,
+    ) {
+        let stub = self.vm.inline_cache_stub();
+        self.masm
+            .inline_cache_call(pos.line as i32, cache, stub, gcpoint.clone());
         self.call_epilog(pos, ty, dest, gcpoint);
     }
 
@@ -526,8 +596,8 @@ where
             desc: InternalFctDescriptor::AllocThunk,
         };
 
-        self.native_call(internal_fct, pos, gcpoint, dest.into());
-        self.masm.test_if_nil_bailout(pos, dest, Trap::OOM);
+        self.native_call(internal_fct, pos, gcpoint.clone(), dest.into());
+        self.masm.test_if_nil_bailout(pos, dest, Trap::OOM, gcpoint);
     }
 
     pub fn verify_refs(&mut self, obj: Reg, value: Reg, pos: Position, gcpoint: GcPoint) {
@@ -550,6 +620,77 @@ where
         self.native_call(internal_fct, pos, gcpoint, REG_RESULT.into());
     }
 
+    // increments the counter at `offset` in the `HotnessCounters` at address `counters`
+    // and invokes the optimizing compiler when the counter reaches `threshold`. With `osr`
+    // set to the loop statement and the frame size the current frame is replaced with
+    // optimized code entered at the loop.
+    pub fn increment_counter(
+        &mut self,
+        counters: Address,
+        offset: i32,
+        threshold: i32,
+        osr: Option<(NodeId, i32)>,
+        pos: Position,
+        gcpoint: GcPoint,
+    ) {
+        let lbl_tier_up = self.masm.create_label();
+
+        {
+            let scratch = self.masm.get_scratch();
+            let disp = self.masm.add_addr(counters.to_ptr());
+            let code_pos = self.masm.pos() as i32;
+            self.masm.load_constpool(*scratch, disp + code_pos);
+
+            let counter = Mem::Base(*scratch, offset);
+            self.masm
+                .load_mem(MachineMode::Int64, REG_TMP1.into(), counter);
+            self.masm
+                .int_add_imm(MachineMode::Int64, REG_TMP1, REG_TMP1, 1);
+
+            let counter = Mem::Base(*scratch, offset);
+            self.masm
+                .store_mem(MachineMode::Int64, counter, REG_TMP1.into());
+        }
+
+        self.masm
+            .cmp_reg_imm(MachineMode::Int64, REG_TMP1, threshold);
+        self.masm.jump_if(CondCode::Equal, lbl_tier_up);
+
+        let lbl_return = self.masm.create_label();
+        self.masm.bind_label(lbl_return);
+
+        let origin = self.masm.pos();
+
+        let slow_path =
+            SlowPathKind::TierUp(origin, lbl_tier_up, lbl_return, counters, osr, pos, gcpoint);
+        self.push_slow_path(slow_path);
+    }
+
+    // leaves optimized code with the frame state in `info`, the runtime
+    // replaces the current frame with a frame of baseline code
+    pub fn deoptimize(&mut self, info: DeoptInfo, framesize: i32, pos: Position, gcpoint: GcPoint) {
+        self.masm.copy_reg(MachineMode::Ptr, REG_PARAMS[0], REG_FP);
+
+        // the runtime finds the function through the pc and the
+        // frame state through its offset
+        self.masm.copy_pc(REG_PARAMS[1]);
+        let offset = self.masm.pos() as i32;
+        self.masm.emit_bailout_info(BailoutInfo::Deopt(info));
+        self.masm
+            .load_int_const(MachineMode::Int32, REG_PARAMS[2], offset as i64);
+
+        let internal_fct = InternalFct {
+            ptr: Address::from_ptr(deopt::deoptimize as *const u8),
+            args: &[BuiltinType::Ptr, BuiltinType::Ptr, BuiltinType::Int],
+            return_type: BuiltinType::Ptr,
+            throws: false,
+            desc: InternalFctDescriptor::DeoptThunk,
+        };
+
+        self.native_call(internal_fct, pos, gcpoint, REG_RESULT.into());
+        self.replace_frame(framesize);
+    }
+
     pub fn tlab_allocate(
         &mut self,
         dest: Reg,
@@ -614,7 +755,10 @@ where
         let lbl_return = self.masm.create_label();
         self.masm.bind_label(lbl_return);
 
-        self.slow_paths.push(SlowPathKind::TlabAllocationFailure(
+        let origin = self.masm.pos();
+
+        let slow_path = SlowPathKind::TlabAllocationFailure(
+            origin,
             lbl_allocate,
             lbl_return,
             dest,
@@ -622,7 +766,8 @@ where
             pos,
             array_ref,
             gcpoint,
-        ));
+        );
+        self.push_slow_path(slow_path);
     }
 
     pub fn allocate(
@@ -653,12 +798,20 @@ where
         }
     }
 
+    fn push_slow_path(&mut self, slow_path: SlowPathKind) {
+        let inlined_frames = self.masm.inlined_frames().to_vec();
+        self.slow_paths.push((slow_path, inlined_frames));
+    }
+
     fn slow_paths(&mut self) {
         let slow_paths = mem::replace(&mut self.slow_paths, Vec::new());
 
-        for slow_path in slow_paths {
+        for (slow_path, inlined_frames) in slow_paths {
+            self.masm.set_inlined_frames(inlined_frames);
+
             match slow_path {
                 SlowPathKind::TlabAllocationFailure(
+                    origin,
                     lbl_start,
                     lbl_return,
                     dest,
@@ -667,9 +820,34 @@ where
                     array_ref,
                     gcpoint,
                 ) => {
+                    let start = self.masm.pos();
                     self.slow_path_tlab_allocation_failure(
                         lbl_start, lbl_return, dest, size, pos, array_ref, gcpoint,
                     );
+                    let end = self.masm.pos();
+                    self.masm.copy_exception_handlers(origin, (start, end));
+                }
+
+                SlowPathKind::TierUp(
+                    origin,
+                    lbl_start,
+                    lbl_return,
+                    counters,
+                    osr,
+                    pos,
+                    gcpoint,
+                ) => {
+                    let start = self.masm.pos();
+                    match osr {
+                        Some((loop_id, framesize)) => self.slow_path_osr(
+                            lbl_start, lbl_return, counters, loop_id, framesize, pos, gcpoint,
+                        ),
+                        None => {
+                            self.slow_path_tier_up(lbl_start, lbl_return, counters, pos, gcpoint)
+                        }
+                    }
+                    let end = self.masm.pos();
+                    self.masm.copy_exception_handlers(origin, (start, end));
                 }
             }
         }
@@ -691,8 +869,101 @@ where
         self.gc_allocate(dest, size, pos, array_ref, gcpoint);
         self.masm.jump(lbl_return);
     }
+
+    fn slow_path_tier_up(
+        &mut self,
+        lbl_start: Label,
+        lbl_return: Label,
+        counters: Address,
+        pos: Position,
+        gcpoint: GcPoint,
+    ) {
+        self.masm.bind_label(lbl_start);
+
+        let disp = self.masm.add_addr(counters.to_ptr());
+        let offset = self.masm.pos() as i32;
+        self.masm.load_constpool(REG_PARAMS[0], disp + offset);
+
+        let internal_fct = InternalFct {
+            ptr: Address::from_ptr(tier::tier_up as *const u8),
+            args: &[BuiltinType::Ptr],
+            return_type: BuiltinType::Unit,
+            throws: false,
+            desc: InternalFctDescriptor::TierUpThunk,
+        };
+
+        self.native_call(internal_fct, pos, gcpoint, REG_RESULT.into());
+        self.masm.jump(lbl_return);
+    }
+
+    fn slow_path_osr(
+        &mut self,
+        lbl_start: Label,
+        lbl_return: Label,
+        counters: Address,
+        loop_id: NodeId,
+        framesize: i32,
+        pos: Position,
+        gcpoint: GcPoint,
+    ) {
+        self.masm.bind_label(lbl_start);
+
+        let disp = self.masm.add_addr(counters.to_ptr());
+        let offset = self.masm.pos() as i32;
+        self.masm.load_constpool(REG_PARAMS[0], disp + offset);
+        self.masm
+            .load_int_const(MachineMode::Ptr, REG_PARAMS[1], loop_id.0 as i64);
+        self.masm.copy_reg(MachineMode::Ptr, REG_PARAMS[2], REG_FP);
+
+        let internal_fct = InternalFct {
+            ptr: Address::from_ptr(tier::osr as *const u8),
+            args: &[BuiltinType::Ptr, BuiltinType::Ptr, BuiltinType::Ptr],
+            return_type: BuiltinType::Ptr,
+            throws: false,
+            desc: InternalFctDescriptor::OsrThunk,
+        };
+
+        self.native_call(internal_fct, pos, gcpoint, REG_RESULT.into());
+
+        // continue in baseline code if the loop couldn't be optimized
+        self.masm.cmp_reg_imm(MachineMode::Ptr, REG_RESULT, 0);
+        self.masm.jump_if(CondCode::Equal, lbl_return);
+
+        // the optimized code takes over the frame and returns to our caller
+        self.replace_frame(framesize);
+    }
+
+    // removes the current frame and continues in the code whose address is stored in the
+    // first slot of the buffer in REG_RESULT, the buffer is passed in the first param
+    // register. The code behaves as if it was invoked by the caller of the current frame.
+    fn replace_frame(&mut self, framesize: i32) {
+        self.masm
+            .copy_reg(MachineMode::Ptr, REG_PARAMS[0], REG_RESULT);
+        self.masm
+            .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Base(REG_RESULT, 0));
+        self.masm.epilog_without_return(framesize);
+        self.masm.jump_reg(REG_TMP1);
+    }
 }
 
 enum SlowPathKind {
-    TlabAllocationFailure(Label, Label, Reg, AllocationSize, Position, bool, GcPoint),
+    TlabAllocationFailure(
+        usize,
+        Label,
+        Label,
+        Reg,
+        AllocationSize,
+        Position,
+        bool,
+        GcPoint,
+    ),
+    TierUp(
+        usize,
+        Label,
+        Label,
+        Address,
+        Option<(NodeId, i32)>,
+        Position,
+        GcPoint,
+    ),
 }
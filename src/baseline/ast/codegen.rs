@@ -10,16 +10,20 @@ use crate::baseline::codegen::{
     create_gcpoint, register_for_mode, should_emit_debug, CodeGen, CondCode, ExprStore, Scopes,
     TempOffsets,
 };
+use crate::baseline::dora_native::{InternalFct, InternalFctDescriptor};
 use crate::baseline::fct::{CatchType, Comment, JitBaselineFct, JitDescriptor};
+use crate::baseline::tier::HotnessCounters;
 use crate::class::{ClassDef, TypeParams};
 use crate::cpu::{Mem, FREG_PARAMS, FREG_RESULT, REG_PARAMS, REG_RESULT};
+use crate::gc::Address;
 use crate::masm::*;
 use crate::os::signal::Trap;
 use crate::semck::always_returns;
-use crate::semck::specialize::specialize_class_ty;
+use crate::semck::specialize::{specialize_class_ty, specialize_type};
+use crate::stdlib;
 use crate::ty::{BuiltinType, MachineMode};
 use crate::vm::VM;
-use crate::vm::{CallSite, Fct, FctParent, FctSrc};
+use crate::vm::{CallSite, Fct, FctParent, FctSrc, VarId};
 
 pub struct AstCodeGen<'a, 'ast: 'a> {
     pub vm: &'a VM<'ast>,
@@ -30,10 +34,13 @@ pub struct AstCodeGen<'a, 'ast: 'a> {
     pub src: &'a mut FctSrc,
     pub jit_info: JitInfo<'ast>,
 
+    // address of the counters if the function should be optimized when hot
+    pub hotness_counters: Option<Address>,
+
     pub lbl_break: Option<Label>,
     pub lbl_continue: Option<Label>,
 
-    // stores all active finally blocks
+    // stores all active finally blocks and defer statements
     pub active_finallys: Vec<&'ast Stmt>,
 
     // label to jump instead of emitting epilog for return
@@ -132,6 +139,7 @@ where
     fn emit_prolog(&mut self) {
         let stacksize = self.jit_info.stacksize();
         self.asm.prolog(stacksize);
+        self.asm.check_stack_limit(self.fct.pos);
         self.asm.emit_comment(Comment::Lit("prolog end"));
         self.asm.emit_comment(Comment::Newline);
     }
@@ -172,7 +180,7 @@ where
                 self.lbl_return = Some(lbl);
 
                 let finally = self.active_finallys[len - 1 - ind];
-                self.visit_stmt(finally);
+                self.emit_finally(finally);
 
                 self.asm.bind_label(lbl);
 
@@ -215,7 +223,7 @@ where
             // execute while body, then jump back to condition
             this.visit_stmt(&s.block);
 
-            this.emit_safepoint();
+            this.emit_safepoint(Some(s.id), s.pos);
             this.asm.jump(lbl_start);
         });
 
@@ -251,17 +259,13 @@ where
         let dest = self.emit_call_site(&for_info.next, s.pos);
 
         let for_var_id = *self.src.map_vars.get(s.id).unwrap();
-        self.asm.var_store(
-            self.jit_info.offset(for_var_id),
-            self.jit_info.ty(for_var_id),
-            dest,
-        );
+        self.emit_var_store(for_var_id, dest, s.pos);
 
         self.save_label_state(lbl_end, lbl_start, |this| {
             // execute while body, then jump back to condition
             this.visit_stmt(&s.block);
 
-            this.emit_safepoint();
+            this.emit_safepoint(None, s.pos);
             this.asm.jump(lbl_start);
         });
 
@@ -281,7 +285,7 @@ where
         self.save_label_state(lbl_end, lbl_start, |this| {
             this.visit_stmt(&s.block);
 
-            this.emit_safepoint();
+            this.emit_safepoint(Some(s.id), s.pos);
             this.asm.jump(lbl_start);
         });
 
@@ -289,13 +293,44 @@ where
         self.active_loop = saved_active_loop;
     }
 
-    fn emit_safepoint(&mut self) {
+    // `loop_id` is the loop statement of the back-edge if the frame
+    // can be replaced with optimized code
+    fn emit_safepoint(&mut self, loop_id: Option<NodeId>, pos: Position) {
         self.asm.emit_comment(Comment::ReadPollingPage);
         self.asm.check_polling_page(self.vm.polling_page.addr());
 
         let temps = TempOffsets::new();
         let gcpoint = create_gcpoint(&self.scopes, &temps);
-        self.asm.emit_gcpoint(gcpoint);
+        self.asm.emit_gcpoint(gcpoint.clone());
+
+        if let Some(counters) = self.hotness_counters {
+            let osr = loop_id.map(|loop_id| (loop_id, self.jit_info.stacksize()));
+
+            self.asm.increment_counter(
+                counters,
+                HotnessCounters::backedges_offset(),
+                self.vm.args.tier_loops(),
+                osr,
+                pos,
+                gcpoint,
+            );
+        }
+    }
+
+    fn emit_invocation_counter(&mut self) {
+        if let Some(counters) = self.hotness_counters {
+            let temps = TempOffsets::new();
+            let gcpoint = create_gcpoint(&self.scopes, &temps);
+
+            self.asm.increment_counter(
+                counters,
+                HotnessCounters::invocations_offset(),
+                self.vm.args.tier_calls(),
+                None,
+                self.fct.pos,
+                gcpoint,
+            );
+        }
     }
 
     fn save_label_state<F>(&mut self, lbl_break: Label, lbl_continue: Label, f: F)
@@ -374,7 +409,7 @@ where
             self.active_upper = Some(idx);
 
             let finally = self.active_finallys[idx];
-            self.visit_stmt(finally);
+            self.emit_finally(finally);
 
             ind += 1;
         }
@@ -382,18 +417,86 @@ where
         self.active_upper = saved_active_upper;
     }
 
+    fn emit_finally(&mut self, finally: &'ast Stmt) {
+        if let StmtDefer(ref defer) = *finally {
+            self.emit_expr(&defer.expr);
+        } else {
+            self.visit_stmt(finally);
+        }
+    }
+
     fn emit_stmt_expr(&mut self, s: &'ast StmtExprType) {
         self.emit_expr(&s.expr);
     }
 
     fn emit_stmt_block(&mut self, s: &'ast StmtBlockType) {
         self.scopes.push_scope();
+        self.emit_stmts(&s.stmts);
+        self.scopes.pop_scope();
+    }
+
+    fn emit_stmts(&mut self, stmts: &'ast [Box<Stmt>]) {
+        for (idx, stmt) in stmts.iter().enumerate() {
+            if let StmtDefer(ref defer) = **stmt {
+                // all remaining statements are guarded by the defer
+                self.emit_stmt_defer(defer, stmt, &stmts[idx + 1..]);
+                return;
+            }
 
-        for stmt in &s.stmts {
             self.visit_stmt(stmt);
         }
+    }
+
+    // a defer is compiled like a do-finally statement, where the statements
+    // after the defer form the do-block and the deferred call the finally-block
+    fn emit_stmt_defer(
+        &mut self,
+        s: &'ast StmtDeferType,
+        stmt: &'ast Stmt,
+        rest: &'ast [Box<Stmt>],
+    ) {
+        let lbl_after = self.asm.create_label();
+
+        self.active_finallys.push(stmt);
+        self.scopes.push_scope();
+
+        let start = self.asm.pos();
+        self.emit_stmts(rest);
+        let end = self.asm.pos();
 
         self.scopes.pop_scope();
+        self.active_finallys.pop();
+
+        if !rest.iter().any(|stmt| always_returns(stmt)) {
+            self.emit_expr(&s.expr);
+            self.asm.jump(lbl_after);
+        }
+
+        // exception handler: run deferred call and rethrow exception
+        let handler_start = self.asm.pos();
+        let offset = *self.jit_info.map_offsets.get(s.id).unwrap();
+
+        self.scopes.push_scope();
+        self.scopes.add_var_offset(offset);
+
+        self.emit_expr(&s.expr);
+
+        self.asm
+            .load_mem(MachineMode::Ptr, REG_RESULT.into(), Mem::Local(offset));
+        self.asm.throw(REG_RESULT, s.pos);
+
+        self.scopes.pop_scope();
+
+        self.asm.bind_label(lbl_after);
+
+        if start != end {
+            self.asm.emit_exception_handler(
+                (start, end),
+                handler_start,
+                Some(offset),
+                CatchType::Any,
+            );
+        }
     }
 
     fn emit_stmt_var(&mut self, s: &'ast StmtVarType) {
@@ -404,14 +507,17 @@ where
             let value = self.emit_expr(expr);
             initialized = true;
 
-            self.asm
-                .var_store(self.jit_info.offset(var), self.jit_info.ty(var), value);
+            self.emit_var_store(var, value, s.pos);
         }
 
+        // captured variables are not stored on the stack, the
+        // context object is already part of all gc points
+        let captured = self.src.context_slot(var).is_some();
+
         let reference_type = {
             let ty = self.jit_info.ty(var);
 
-            if ty.reference_type() {
+            if ty.reference_type() && !captured {
                 let offset = self.jit_info.offset(var);
                 self.scopes.add_var(var, offset);
             }
@@ -423,21 +529,71 @@ where
         // otherwise the GC  can't know if the stored value is a valid pointer
         if reference_type && !initialized {
             self.asm.load_nil(REG_RESULT);
-            self.asm.var_store(
-                self.jit_info.offset(var),
-                self.jit_info.ty(var),
-                REG_RESULT.into(),
-            );
+            self.emit_var_store(var, REG_RESULT.into(), s.pos);
         }
     }
 
+    fn emit_var_store(&mut self, var: VarId, value: ExprStore, pos: Position) {
+        let mut expr_gen = ExprGen::new(
+            self.vm,
+            self.fct,
+            self.src,
+            self.ast,
+            &mut self.asm,
+            &mut self.scopes,
+            &self.jit_info,
+            self.cls_type_params,
+            self.fct_type_params,
+        );
+
+        expr_gen.emit_var_store(var, value, pos);
+    }
+
+    fn emit_context_allocation(&mut self) {
+        let mut expr_gen = ExprGen::new(
+            self.vm,
+            self.fct,
+            self.src,
+            self.ast,
+            &mut self.asm,
+            &mut self.scopes,
+            &self.jit_info,
+            self.cls_type_params,
+            self.fct_type_params,
+        );
+
+        expr_gen.emit_context_allocation(self.fct.pos);
+    }
+
     fn emit_stmt_throw(&mut self, s: &'ast StmtThrowType) {
         self.emit_expr(&s.expr);
-        self.asm.test_if_nil_bailout(s.pos, REG_RESULT, Trap::NIL);
+        let temps = TempOffsets::new();
+        let gcpoint = create_gcpoint(&self.scopes, &temps);
+        self.asm
+            .test_if_nil_bailout(s.pos, REG_RESULT, Trap::NIL, gcpoint);
 
         self.asm.throw(REG_RESULT, s.pos);
     }
 
+    fn emit_stmt_spawn(&mut self, s: &'ast StmtSpawnType) {
+        self.emit_expr(&s.expr);
+        self.asm
+            .copy_reg(MachineMode::Ptr, REG_PARAMS[0], REG_RESULT);
+
+        let internal_fct = InternalFct {
+            ptr: Address::from_ptr(stdlib::spawn_lambda as *const u8),
+            args: &[BuiltinType::Ptr],
+            return_type: BuiltinType::Unit,
+            throws: false,
+            desc: InternalFctDescriptor::SpawnThunk,
+        };
+
+        let temps = TempOffsets::new();
+        let gcpoint = create_gcpoint(&self.scopes, &temps);
+        self.asm
+            .native_call(internal_fct, s.pos, gcpoint, REG_RESULT.into());
+    }
+
     fn emit_stmt_do(&mut self, s: &'ast StmtDoType) {
         let lbl_after = self.asm.create_label();
 
@@ -478,7 +634,17 @@ where
             self.scopes.push_scope();
             self.scopes.add_var(varid, offset);
 
-            let catch_span = self.stmt_with_finally(s, &catch.block, lbl_after);
+            let catch_start = self.asm.pos();
+
+            // exception is always stored on the stack first
+            if self.src.context_slot(varid).is_some() {
+                self.asm
+                    .load_mem(MachineMode::Ptr, REG_RESULT.into(), Mem::Local(offset));
+                self.emit_var_store(varid, REG_RESULT.into(), catch.pos);
+            }
+
+            let (_, catch_end) = self.stmt_with_finally(s, &catch.block, lbl_after);
+            let catch_span = (catch_start, catch_end);
 
             self.scopes.pop_scope();
 
@@ -611,13 +777,14 @@ where
 
     fn specialize_type(&self, ty: BuiltinType) -> BuiltinType {
         match ty {
+            // lambdas share type params with their outermost function
             BuiltinType::ClassTypeParam(cls_id, id) => {
-                debug_assert!(self.fct.parent == FctParent::Class(cls_id));
+                debug_assert!(self.fct.is_lambda() || self.fct.parent == FctParent::Class(cls_id));
                 self.cls_type_params[id.idx()]
             }
 
             BuiltinType::FctTypeParam(fct_id, id) => {
-                debug_assert!(self.fct.id == fct_id);
+                debug_assert!(self.fct.is_lambda() || self.fct.id == fct_id);
                 self.fct_type_params[id.idx()]
             }
 
@@ -631,7 +798,9 @@ where
                 BuiltinType::Class(cls_id, list_id)
             }
 
-            BuiltinType::Lambda(_) => unimplemented!(),
+            BuiltinType::Lambda(_) => {
+                specialize_type(self.vm, ty, self.cls_type_params, self.fct_type_params)
+            }
 
             _ => ty,
         }
@@ -646,6 +815,12 @@ impl<'a, 'ast> CodeGen<'ast> for AstCodeGen<'a, 'ast> {
 
         self.emit_prolog();
         self.store_register_params_on_stack();
+        self.emit_invocation_counter();
+
+        if self.src.has_context {
+            self.emit_context_allocation();
+        }
+
         self.visit_fct(self.ast);
 
         let always_returns = self.src.always_returns;
@@ -678,9 +853,10 @@ impl<'a, 'ast> visit::Visitor<'ast> for AstCodeGen<'a, 'ast> {
             StmtBlock(ref stmt) => self.emit_stmt_block(stmt),
             StmtVar(ref stmt) => self.emit_stmt_var(stmt),
             StmtThrow(ref stmt) => self.emit_stmt_throw(stmt),
-            StmtDefer(_) => unimplemented!(),
+            // defer guards the rest of its block, see emit_stmts
+            StmtDefer(_) => unreachable!(),
             StmtDo(ref stmt) => self.emit_stmt_do(stmt),
-            StmtSpawn(_) => unimplemented!(),
+            StmtSpawn(ref stmt) => self.emit_stmt_spawn(stmt),
         }
     }
 
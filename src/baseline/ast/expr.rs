@@ -1,20 +1,22 @@
 use crate::baseline::asm::BaselineAssembler;
 use crate::baseline::ast::info::JitInfo;
 use crate::baseline::codegen::{
-    self, ensure_native_stub, register_for_mode, AllocationSize, CondCode, ExprStore, Scopes,
-    TempOffsets,
+    self, register_for_mode, AllocationSize, CondCode, ExprStore, Scopes, TempOffsets,
 };
-use crate::baseline::dora_native::{InternalFct, InternalFctDescriptor};
 use crate::baseline::fct::{CatchType, Comment, GcPoint};
+use crate::baseline::ic;
+use crate::cha;
 use crate::class::{ClassDefId, ClassSize, FieldId, TypeParams};
 use crate::cpu::{
     FReg, Mem, Reg, FREG_PARAMS, FREG_RESULT, FREG_TMP1, REG_PARAMS, REG_RESULT, REG_TMP1, REG_TMP2,
 };
-use crate::gc::Address;
 use crate::mem;
 use crate::object::{Header, Str};
 use crate::os::signal::Trap;
-use crate::semck::specialize::{specialize_class_id, specialize_class_ty};
+use crate::semck::specialize::{
+    context_offset, specialize_class_id, specialize_class_ty, specialize_context,
+    specialize_lambda, specialize_type,
+};
 use crate::ty::{BuiltinType, MachineMode};
 use crate::vm::VM;
 use crate::vm::*;
@@ -94,15 +96,15 @@ where
             ExprBin(ref expr) => self.emit_bin(expr, dest),
             ExprCall(ref expr) => self.emit_call(expr, dest),
             ExprTypeParam(_) => unreachable!(),
-            ExprPath(_) => unreachable!(),
+            ExprPath(ref expr) => self.emit_path(expr, dest.reg()),
             ExprDelegation(ref expr) => self.emit_delegation(expr, dest),
             ExprDot(ref expr) => self.emit_dot(expr, dest),
-            ExprSelf(_) => self.emit_self(dest),
+            ExprSelf(ref expr) => self.emit_this(expr, dest),
             ExprSuper(_) => self.emit_self(dest),
             ExprNil(_) => self.emit_nil(dest.reg()),
             ExprConv(ref expr) => self.emit_conv(expr, dest.reg()),
             ExprTry(ref expr) => self.emit_try(expr, dest),
-            ExprLambda(_) => unimplemented!(),
+            ExprLambda(ref expr) => self.emit_lambda(expr, dest.reg()),
         }
     }
 
@@ -153,7 +155,9 @@ where
 
                 let catch_span = {
                     let start = self.asm.pos();
-                    self.asm.emit_bailout_inplace(Trap::UNEXPECTED, e.pos);
+                    let gcpoint = self.create_gcpoint();
+                    self.asm
+                        .emit_bailout_inplace(Trap::UNEXPECTED, e.pos, gcpoint);
                     let end = self.asm.pos();
 
                     (start, end)
@@ -266,7 +270,8 @@ where
                     self.asm.load_false(dest);
                 } else {
                     // bailout
-                    self.asm.emit_bailout_inplace(Trap::CAST, e.pos);
+                    let gcpoint = self.create_gcpoint();
+                    self.asm.emit_bailout_inplace(Trap::CAST, e.pos, gcpoint);
                 }
 
                 // lbl_finished:
@@ -289,7 +294,9 @@ where
                 } else {
                     let lbl_bailout = self.asm.create_label();
                     self.asm.jump_if(CondCode::NotEqual, lbl_bailout);
-                    self.asm.emit_bailout(lbl_bailout, Trap::CAST, e.pos);
+                    let gcpoint = self.create_gcpoint();
+                    self.asm
+                        .emit_bailout(lbl_bailout, Trap::CAST, e.pos, gcpoint);
 
                     self.asm
                         .load_mem(MachineMode::Ptr, dest.into(), Mem::Local(offset));
@@ -365,6 +372,238 @@ where
             .load_mem(var.ty.mode(), dest.into(), Mem::Local(offset));
     }
 
+    fn emit_this(&mut self, e: &'ast ExprSelfType, dest: ExprStore) {
+        // `self` in lambdas is captured from the outermost method
+        if let Some(IdentType::Context(fct_id, var_id)) = self.src.map_idents.get(e.id).cloned() {
+            self.emit_context_var_load(fct_id, var_id, dest);
+        } else {
+            self.emit_self(dest);
+        }
+    }
+
+    fn emit_lambda(&mut self, e: &'ast ExprLambdaType, dest: Reg) {
+        let lambda_id = *self.src.map_lambdas.get(e.id).unwrap();
+        let cls_def_id = specialize_lambda(
+            self.vm,
+            lambda_id,
+            self.cls_type_params,
+            self.fct_type_params,
+        );
+
+        // do NOT add the temporary to the gc point before the
+        // closure is initialized (see `Arg::SelfieNew`)
+        let offset = -(self.jit_info.localsize + self.jit_info.get_store(e.id).offset());
+        self.emit_allocation(e.pos, &[], cls_def_id, offset, dest);
+
+        // closure keeps the context of this function alive
+        if self.src.has_context {
+            let temp = if dest == REG_TMP1 { REG_TMP2 } else { REG_TMP1 };
+            let context = self.jit_info.context_offset.unwrap();
+            self.asm
+                .load_mem(MachineMode::Ptr, temp.into(), Mem::Local(context));
+
+            let write_barrier = self.vm.gc.needs_write_barrier();
+            let card_table_offset = self.vm.gc.card_table_offset();
+
+            let gcpoint = self.create_gcpoint();
+
+            self.asm.store_field(
+                MachineMode::Ptr,
+                dest,
+                Header::size(),
+                temp.into(),
+                e.pos.line as i32,
+                write_barrier,
+                card_table_offset,
+                gcpoint,
+            );
+
+            // write barrier clobbers `dest`
+            self.asm
+                .load_mem(MachineMode::Ptr, dest.into(), Mem::Local(offset));
+        }
+    }
+
+    // allocates the context object on function entry and moves
+    // captured parameters into it
+    pub fn emit_context_allocation(&mut self, pos: Position) {
+        let cls_def_id = specialize_context(
+            self.vm,
+            self.src,
+            self.cls_type_params,
+            self.fct_type_params,
+        );
+
+        let offset = self.jit_info.context_offset.unwrap();
+        self.emit_allocation(pos, &[], cls_def_id, offset, REG_RESULT);
+
+        // context is initialized now and needs to be part of all gc points
+        self.scopes.add_var_offset(offset);
+
+        if self.fct.is_lambda() {
+            // slot 0 stores context of the outer function, which is kept alive by the closure
+            let closure = self.src.var_self().id;
+            let closure_offset = self.jit_info.offset(closure);
+            self.asm.load_mem(
+                MachineMode::Ptr,
+                REG_RESULT.into(),
+                Mem::Local(closure_offset),
+            );
+            self.asm.load_mem(
+                MachineMode::Ptr,
+                REG_RESULT.into(),
+                Mem::Base(REG_RESULT, Header::size()),
+            );
+
+            self.emit_context_store(
+                self.fct.id,
+                context_offset(0),
+                BuiltinType::Ptr,
+                REG_RESULT.into(),
+                pos,
+            );
+        }
+
+        let mut params = Vec::with_capacity(self.ast.params.len() + 1);
+
+        if self.fct.has_self() && !self.fct.is_lambda() {
+            params.push(self.src.var_self().id);
+        }
+
+        for p in &self.ast.params {
+            params.push(*self.src.map_vars.get(p.id).unwrap());
+        }
+
+        for var_id in params {
+            if !self.is_context_var(var_id) {
+                continue;
+            }
+
+            let ty = self.jit_info.ty(var_id);
+            let dest = result_reg(ty.mode());
+            self.asm.var_load(self.jit_info.offset(var_id), ty, dest);
+            self.emit_context_var_store(self.fct.id, var_id, dest, pos);
+        }
+    }
+
+    // variable is stored in context object instead of the stack
+    fn is_context_var(&self, var_id: VarId) -> bool {
+        self.src.context_slot(var_id).is_some()
+    }
+
+    // returns offset and type of a captured variable in the context of `fct_id`
+    fn context_var(&self, fct_id: FctId, var_id: VarId) -> (i32, BuiltinType) {
+        let (slot, ty) = if fct_id == self.fct.id {
+            (self.src.context_slot(var_id), self.src.vars[var_id].ty)
+        } else {
+            let fct = self.vm.fcts.idx(fct_id);
+            let fct = fct.read();
+            let src = fct.src();
+            let src = src.read();
+
+            (src.context_slot(var_id), src.vars[var_id].ty)
+        };
+
+        let slot = slot.expect("variable not captured");
+
+        (context_offset(slot), self.specialize_type(ty))
+    }
+
+    // loads context object of function `fct_id` into `dest`
+    fn emit_load_context(&mut self, fct_id: FctId, dest: Reg) {
+        if fct_id == self.fct.id {
+            let offset = self.jit_info.context_offset.unwrap();
+            self.asm
+                .load_mem(MachineMode::Ptr, dest.into(), Mem::Local(offset));
+            return;
+        }
+
+        // closure stores context of the function it was created in
+        let closure = self.src.var_self().id;
+        let offset = self.jit_info.offset(closure);
+        self.asm
+            .load_mem(MachineMode::Ptr, dest.into(), Mem::Local(offset));
+        self.asm.load_mem(
+            MachineMode::Ptr,
+            dest.into(),
+            Mem::Base(dest, Header::size()),
+        );
+
+        // follow slot 0 of contexts until reaching the owner of the variable
+        let mut outer_id = self.fct.outer_fct_id();
+
+        while outer_id != fct_id {
+            self.asm.load_mem(
+                MachineMode::Ptr,
+                dest.into(),
+                Mem::Base(dest, context_offset(0)),
+            );
+
+            outer_id = self.vm.fcts.idx(outer_id).read().outer_fct_id();
+        }
+    }
+
+    fn emit_context_var_load(&mut self, fct_id: FctId, var_id: VarId, dest: ExprStore) {
+        let (offset, ty) = self.context_var(fct_id, var_id);
+
+        self.asm.emit_comment(Comment::Lit("load var from context"));
+        self.emit_load_context(fct_id, REG_TMP1);
+        self.asm
+            .load_mem(ty.mode(), dest, Mem::Base(REG_TMP1, offset));
+    }
+
+    fn emit_context_var_store(
+        &mut self,
+        fct_id: FctId,
+        var_id: VarId,
+        src: ExprStore,
+        pos: Position,
+    ) {
+        let (offset, ty) = self.context_var(fct_id, var_id);
+
+        self.asm.emit_comment(Comment::Lit("store var in context"));
+        self.emit_context_store(fct_id, offset, ty, src, pos);
+    }
+
+    // stores `src` into context of `fct_id`, `src` must not be REG_TMP1
+    fn emit_context_store(
+        &mut self,
+        fct_id: FctId,
+        offset: i32,
+        ty: BuiltinType,
+        src: ExprStore,
+        pos: Position,
+    ) {
+        self.emit_load_context(fct_id, REG_TMP1);
+
+        let write_barrier = self.vm.gc.needs_write_barrier() && ty.reference_type();
+        let card_table_offset = self.vm.gc.card_table_offset();
+
+        let gcpoint = self.create_gcpoint();
+
+        self.asm.store_field(
+            ty.mode(),
+            REG_TMP1,
+            offset,
+            src,
+            pos.line as i32,
+            write_barrier,
+            card_table_offset,
+            gcpoint,
+        );
+    }
+
+    // stores `src` into variable of this function, no matter
+    // whether it lives on the stack or in the context
+    pub fn emit_var_store(&mut self, var_id: VarId, src: ExprStore, pos: Position) {
+        if self.is_context_var(var_id) {
+            self.emit_context_var_store(self.fct.id, var_id, src, pos);
+        } else {
+            self.asm
+                .var_store(self.jit_info.offset(var_id), self.jit_info.ty(var_id), src);
+        }
+    }
+
     fn emit_nil(&mut self, dest: Reg) {
         self.asm.load_nil(dest);
     }
@@ -399,8 +638,15 @@ where
         let field = &cls.fields[fieldid.idx()];
 
         self.asm.emit_comment(Comment::LoadField(cls_id, fieldid));
-        self.asm
-            .load_field(field.ty.mode(), dest, src, field.offset, pos.line as i32);
+        let gcpoint = self.create_gcpoint();
+        self.asm.load_field(
+            field.ty.mode(),
+            dest,
+            src,
+            field.offset,
+            pos.line as i32,
+            gcpoint,
+        );
     }
 
     fn emit_lit_char(&mut self, lit: &'ast ExprLitCharType, dest: Reg) {
@@ -449,16 +695,37 @@ where
         self.asm.load_constpool(dest, disp + pos);
     }
 
+    fn emit_path(&mut self, e: &'ast ExprPathType, dest: Reg) {
+        let ident = self.src.map_idents.get(e.id).unwrap();
+
+        match ident {
+            &IdentType::EnumValue(_, value) => {
+                self.asm
+                    .load_int_const(MachineMode::Int32, dest, value as i64);
+            }
+
+            _ => unreachable!(),
+        }
+    }
+
     fn emit_ident(&mut self, e: &'ast ExprIdentType, dest: ExprStore) {
         let ident = self.src.map_idents.get(e.id).unwrap();
 
         match ident {
+            &IdentType::Var(varid) if self.is_context_var(varid) => {
+                self.emit_context_var_load(self.fct.id, varid, dest);
+            }
+
             &IdentType::Var(varid) => {
                 self.asm.emit_comment(Comment::LoadVar(varid));
                 self.asm
                     .var_load(self.jit_info.offset(varid), self.jit_info.ty(varid), dest)
             }
 
+            &IdentType::Context(fct_id, varid) => {
+                self.emit_context_var_load(fct_id, varid, dest);
+            }
+
             &IdentType::Global(gid) => {
                 let glob = self.vm.globals.idx(gid);
                 let glob = glob.lock();
@@ -486,6 +753,7 @@ where
                 self.emit_const(const_id, dest);
             }
 
+            &IdentType::Enum(_) | &IdentType::EnumValue(_, _) => unreachable!(),
             &IdentType::Fct(_) | &IdentType::FctType(_, _) => unreachable!(),
             &IdentType::Class(_) | &IdentType::ClassType(_, _) => unreachable!(),
             &IdentType::Method(_, _) | &IdentType::MethodType(_, _, _) => unreachable!(),
@@ -640,10 +908,18 @@ where
             return;
         }
 
-        let ident_type = self.src.map_idents.get(e.lhs.id()).unwrap();
+        let ident_type = self.src.map_idents.get(e.lhs.id()).unwrap().clone();
 
         match ident_type {
-            &IdentType::Var(varid) => {
+            IdentType::Var(varid) if self.is_context_var(varid) => {
+                let ty = self.jit_info.ty(varid);
+                let dest = result_reg(ty.mode());
+                self.emit_expr(&e.rhs, dest);
+
+                self.emit_context_var_store(self.fct.id, varid, dest, e.pos);
+            }
+
+            IdentType::Var(varid) => {
                 let ty = self.jit_info.ty(varid);
                 let dest = result_reg(ty.mode());
                 self.emit_expr(&e.rhs, dest);
@@ -653,7 +929,15 @@ where
                     .var_store(self.jit_info.offset(varid), self.jit_info.ty(varid), dest);
             }
 
-            &IdentType::Global(gid) => {
+            IdentType::Context(fct_id, varid) => {
+                let (_, ty) = self.context_var(fct_id, varid);
+                let dest = result_reg(ty.mode());
+                self.emit_expr(&e.rhs, dest);
+
+                self.emit_context_var_store(fct_id, varid, dest, e.pos);
+            }
+
+            IdentType::Global(gid) => {
                 let glob = self.vm.globals.idx(gid);
                 let (address_value, ty) = {
                     let glob = glob.lock();
@@ -672,7 +956,7 @@ where
                 self.asm.store_mem(ty.mode(), Mem::Base(REG_TMP1, 0), dest);
             }
 
-            &IdentType::Field(ty, fieldid) => {
+            IdentType::Field(ty, fieldid) => {
                 let ty = self.specialize_type(ty);
                 let cls_id = specialize_class_ty(self.vm, ty);
                 let cls = self.vm.class_defs.idx(cls_id);
@@ -713,6 +997,8 @@ where
                 let write_barrier = self.vm.gc.needs_write_barrier() && field.ty.reference_type();
                 let card_table_offset = self.vm.gc.card_table_offset();
 
+                let gcpoint = self.create_gcpoint();
+
                 self.asm.store_field(
                     field.ty.mode(),
                     REG_TMP1,
@@ -721,6 +1007,7 @@ where
                     e.pos.line as i32,
                     write_barrier,
                     card_table_offset,
+                    gcpoint,
                 );
 
                 if verify_refs {
@@ -743,27 +1030,29 @@ where
                 self.free_temp_for_node(temp, temp_offset);
             }
 
-            &IdentType::Struct(_) => {
+            IdentType::Struct(_) => {
                 unimplemented!();
             }
 
-            &IdentType::Const(_) => {
+            IdentType::Const(_) => {
                 unreachable!();
             }
 
-            &IdentType::Fct(_) | &IdentType::FctType(_, _) => unreachable!(),
-            &IdentType::Class(_) | &IdentType::ClassType(_, _) => unreachable!(),
-            &IdentType::Method(_, _) | &IdentType::MethodType(_, _, _) => unreachable!(),
-            &IdentType::StaticMethod(_, _) | &IdentType::StaticMethodType(_, _, _) => {
-                unreachable!()
-            }
+            IdentType::Enum(_) | IdentType::EnumValue(_, _) => unreachable!(),
+            IdentType::Fct(_) | IdentType::FctType(_, _) => unreachable!(),
+            IdentType::Class(_) | IdentType::ClassType(_, _) => unreachable!(),
+            IdentType::Method(_, _) | IdentType::MethodType(_, _, _) => unreachable!(),
+            IdentType::StaticMethod(_, _) | IdentType::StaticMethodType(_, _, _) => unreachable!(),
         }
     }
 
     fn emit_bin(&mut self, e: &'ast ExprBinType, dest: ExprStore) {
         if let Some(intrinsic) = self.intrinsic(e.id) {
             self.emit_intrinsic_bin(&e.lhs, &e.rhs, dest, intrinsic, Some(e.op));
-        } else if e.op == BinOp::Cmp(CmpOp::Is) || e.op == BinOp::Cmp(CmpOp::IsNot) {
+        } else if e.op == BinOp::Cmp(CmpOp::Is)
+            || e.op == BinOp::Cmp(CmpOp::IsNot)
+            || self.ty(e.lhs.id()).is_enum()
+        {
             self.emit_bin_is(e, dest.reg());
         } else if e.op == BinOp::Or {
             self.emit_bin_or(e, dest.reg());
@@ -833,7 +1122,7 @@ where
         self.asm.cmp_reg(dest_mode, REG_RESULT, REG_TMP1);
 
         let op = match e.op {
-            BinOp::Cmp(CmpOp::Is) => CondCode::Equal,
+            BinOp::Cmp(CmpOp::Is) | BinOp::Cmp(CmpOp::Eq) => CondCode::Equal,
             _ => CondCode::NotEqual,
         };
 
@@ -887,47 +1176,11 @@ where
         self.asm.bind_label(lbl_end);
     }
 
-    fn ptr_for_fct_id(
-        &mut self,
-        fid: FctId,
-        cls_type_params: TypeParams,
-        fct_type_params: TypeParams,
-    ) -> Address {
-        if self.fct.id == fid {
-            // we want to recursively invoke the function we are compiling right now
-            ensure_jit_or_stub_ptr(self.src, self.vm, cls_type_params, fct_type_params)
-        } else {
-            let fct = self.vm.fcts.idx(fid);
-            let fct = fct.read();
-
-            match fct.kind {
-                FctKind::Source(_) => {
-                    let src = fct.src();
-                    let mut src = src.write();
-
-                    ensure_jit_or_stub_ptr(&mut src, self.vm, cls_type_params, fct_type_params)
-                }
-
-                FctKind::Native(ptr) => {
-                    let internal_fct = InternalFct {
-                        ptr: ptr,
-                        args: fct.params_with_self(),
-                        return_type: fct.return_type,
-                        throws: fct.ast.throws,
-                        desc: InternalFctDescriptor::NativeThunk(fid),
-                    };
-
-                    ensure_native_stub(self.vm, fid, internal_fct)
-                }
-
-                FctKind::Definition => panic!("prototype for fct call"),
-                FctKind::Builtin(_) => panic!("intrinsic fct call"),
-            }
-        }
-    }
-
     fn emit_call(&mut self, e: &'ast ExprCallType, dest: ExprStore) {
-        if let Some(intrinsic) = self.intrinsic(e.id) {
+        if let Some(csite) = self.jit_info.map_lambda_csites.get(e.id) {
+            let csite = csite.clone();
+            self.emit_lambda_call_site(&csite, e.pos, dest);
+        } else if let Some(intrinsic) = self.intrinsic(e.id) {
             let mut args: Vec<&'ast Expr> = Vec::with_capacity(3);
             let call_type = self.src.map_calls.get(e.id).unwrap();
 
@@ -1185,10 +1438,13 @@ where
             Mem::Local(offset_index),
         );
 
-        self.asm.test_if_nil_bailout(pos, REG_TMP1, Trap::NIL);
+        let gcpoint = self.create_gcpoint();
+        self.asm
+            .test_if_nil_bailout(pos, REG_TMP1, Trap::NIL, gcpoint.clone());
 
         if !self.vm.args.flag_omit_bounds_check {
-            self.asm.check_index_out_of_bounds(pos, REG_TMP1, REG_TMP2);
+            self.asm
+                .check_index_out_of_bounds(pos, REG_TMP1, REG_TMP2, gcpoint);
         }
 
         self.asm.load_mem(mode, res, Mem::Local(offset_value));
@@ -1227,11 +1483,13 @@ where
         self.asm
             .load_mem(MachineMode::Ptr, REG_RESULT.into(), Mem::Local(offset));
 
-        self.asm.test_if_nil_bailout(pos, REG_RESULT, Trap::NIL);
+        let gcpoint = self.create_gcpoint();
+        self.asm
+            .test_if_nil_bailout(pos, REG_RESULT, Trap::NIL, gcpoint.clone());
 
         if !self.vm.args.flag_omit_bounds_check {
             self.asm
-                .check_index_out_of_bounds(pos, REG_RESULT, REG_TMP1);
+                .check_index_out_of_bounds(pos, REG_RESULT, REG_TMP1, gcpoint);
         }
 
         let res = result_reg(mode);
@@ -1273,7 +1531,9 @@ where
 
     fn emit_intrinsic_len(&mut self, pos: Position, e: &'ast Expr, dest: Reg) {
         self.emit_expr(e, REG_RESULT.into());
-        self.asm.test_if_nil_bailout(pos, REG_RESULT, Trap::NIL);
+        let gcpoint = self.create_gcpoint();
+        self.asm
+            .test_if_nil_bailout(pos, REG_RESULT, Trap::NIL, gcpoint);
         self.asm.load_mem(
             MachineMode::Ptr,
             dest.into(),
@@ -1459,7 +1719,7 @@ where
             let lhs_reg = lhs_reg.reg();
             let rhs_reg = rhs_reg.reg();
 
-            self.emit_intrinsic_int(dest.reg(), lhs_reg, rhs_reg, intr, op);
+            self.emit_intrinsic_int(dest.reg(), lhs_reg, rhs_reg, intr, op, rhs.pos());
         }
 
         self.free_temp_for_node(lhs, offset);
@@ -1472,6 +1732,7 @@ where
         rhs: Reg,
         intr: Intrinsic,
         op: Option<BinOp>,
+        pos: Position,
     ) {
         match intr {
             Intrinsic::ByteEq
@@ -1514,8 +1775,22 @@ where
             Intrinsic::IntAdd => self.asm.int_add(MachineMode::Int32, dest, lhs, rhs),
             Intrinsic::IntSub => self.asm.int_sub(MachineMode::Int32, dest, lhs, rhs),
             Intrinsic::IntMul => self.asm.int_mul(MachineMode::Int32, dest, lhs, rhs),
-            Intrinsic::IntDiv => self.asm.int_div(MachineMode::Int32, dest, lhs, rhs),
-            Intrinsic::IntMod => self.asm.int_mod(MachineMode::Int32, dest, lhs, rhs),
+            Intrinsic::IntDiv => self.asm.int_div(
+                MachineMode::Int32,
+                dest,
+                lhs,
+                rhs,
+                pos,
+                self.create_gcpoint(),
+            ),
+            Intrinsic::IntMod => self.asm.int_mod(
+                MachineMode::Int32,
+                dest,
+                lhs,
+                rhs,
+                pos,
+                self.create_gcpoint(),
+            ),
 
             Intrinsic::IntOr => self.asm.int_or(MachineMode::Int32, dest, lhs, rhs),
             Intrinsic::IntAnd => self.asm.int_and(MachineMode::Int32, dest, lhs, rhs),
@@ -1528,8 +1803,22 @@ where
             Intrinsic::LongAdd => self.asm.int_add(MachineMode::Int64, dest, lhs, rhs),
             Intrinsic::LongSub => self.asm.int_sub(MachineMode::Int64, dest, lhs, rhs),
             Intrinsic::LongMul => self.asm.int_mul(MachineMode::Int64, dest, lhs, rhs),
-            Intrinsic::LongDiv => self.asm.int_div(MachineMode::Int64, dest, lhs, rhs),
-            Intrinsic::LongMod => self.asm.int_mod(MachineMode::Int64, dest, lhs, rhs),
+            Intrinsic::LongDiv => self.asm.int_div(
+                MachineMode::Int64,
+                dest,
+                lhs,
+                rhs,
+                pos,
+                self.create_gcpoint(),
+            ),
+            Intrinsic::LongMod => self.asm.int_mod(
+                MachineMode::Int64,
+                dest,
+                lhs,
+                rhs,
+                pos,
+                self.create_gcpoint(),
+            ),
 
             Intrinsic::LongOr => self.asm.int_or(MachineMode::Int64, dest, lhs, rhs),
             Intrinsic::LongAnd => self.asm.int_and(MachineMode::Int64, dest, lhs, rhs),
@@ -1613,13 +1902,156 @@ where
     }
 
     pub fn emit_call_site(&mut self, csite: &CallSite<'ast>, pos: Position, dest: ExprStore) {
-        let mut temps: Vec<(BuiltinType, i32, Option<ClassDefId>)> = Vec::new();
-
         let fid = csite.callee;
         let fct = self.vm.fcts.idx(fid);
         let fct = fct.read();
 
-        for (idx, arg) in csite.args.iter().enumerate() {
+        // check first argument for nil for method calls
+        //
+        // no check necessary for:
+        //   super calls (guaranteed to not be nil) and
+        //   dynamic dispatch (implicit check when loading fctptr from vtable)
+        let devirtualize = !csite.super_call && cha::can_devirtualize(self.vm, &*fct);
+        let check_self = fct.has_self() && !csite.super_call && (!fct.is_virtual() || devirtualize);
+
+        let temps = self.emit_call_args(&csite.args, check_self, pos);
+        self.load_call_args(&csite.args, &temps, pos);
+
+        let return_type = self.specialize_type(csite.return_type);
+        let cls_type_params: TypeParams = csite
+            .cls_type_params
+            .iter()
+            .map(|ty| self.specialize_type(ty))
+            .collect::<Vec<_>>()
+            .into();
+        let fct_type_params: TypeParams = csite
+            .fct_type_params
+            .iter()
+            .map(|ty| self.specialize_type(ty))
+            .collect::<Vec<_>>()
+            .into();
+
+        debug_assert!(cls_type_params
+            .iter()
+            .all(|ty| !ty.contains_type_param(self.vm)));
+        debug_assert!(fct_type_params
+            .iter()
+            .all(|ty| !ty.contains_type_param(self.vm)));
+
+        if csite.super_call {
+            let ptr = codegen::ptr_for_fct_id(
+                self.vm,
+                self.fct,
+                self.src,
+                fid,
+                cls_type_params.clone(),
+                fct_type_params.clone(),
+            );
+            self.asm.emit_comment(Comment::CallSuper(fid));
+            let gcpoint = codegen::create_gcpoint(self.scopes, &self.temps);
+            self.asm.direct_call(
+                fid,
+                ptr.to_ptr(),
+                cls_type_params,
+                fct_type_params,
+                pos,
+                gcpoint,
+                return_type,
+                dest,
+            );
+        } else if devirtualize {
+            let ptr = codegen::ptr_for_fct_id(
+                self.vm,
+                self.fct,
+                self.src,
+                fid,
+                cls_type_params.clone(),
+                fct_type_params.clone(),
+            );
+            self.asm.emit_comment(Comment::CallDirect(fid));
+            let gcpoint = codegen::create_gcpoint(self.scopes, &self.temps);
+            self.asm.direct_call(
+                fid,
+                ptr.to_ptr(),
+                cls_type_params,
+                fct_type_params,
+                pos,
+                gcpoint,
+                return_type,
+                dest,
+            );
+        } else if fct.is_virtual() {
+            let vtable_index = fct.vtable_index.unwrap();
+            self.asm.emit_comment(Comment::CallVirtual(fid));
+            let gcpoint = self.create_gcpoint();
+            let cache = ic::create_inline_cache(self.src, pos, vtable_index);
+            self.asm
+                .inline_cache_call(cache, pos, gcpoint, return_type, dest);
+        } else {
+            let ptr = codegen::ptr_for_fct_id(
+                self.vm,
+                self.fct,
+                self.src,
+                fid,
+                cls_type_params.clone(),
+                fct_type_params.clone(),
+            );
+            self.asm.emit_comment(Comment::CallDirect(fid));
+            let gcpoint = codegen::create_gcpoint(self.scopes, &self.temps);
+            self.asm.direct_call(
+                fid,
+                ptr.to_ptr(),
+                cls_type_params,
+                fct_type_params,
+                pos,
+                gcpoint,
+                return_type,
+                dest,
+            );
+        }
+
+        if csite.args.len() > 0 {
+            if let Arg::SelfieNew(_, _) = csite.args[0] {
+                let (ty, offset, _) = temps[0];
+                self.asm.load_mem(ty.mode(), dest, Mem::Local(offset));
+            }
+        }
+
+        for temp in temps.into_iter() {
+            self.free_temp_with_type(temp.0, temp.1);
+        }
+    }
+
+    fn emit_lambda_call_site(
+        &mut self,
+        csite: &LambdaCallSite<'ast>,
+        pos: Position,
+        dest: ExprStore,
+    ) {
+        let temps = self.emit_call_args(&csite.args, false, pos);
+        self.load_call_args(&csite.args, &temps, pos);
+
+        let return_type = self.specialize_type(csite.return_type);
+
+        self.asm.emit_comment(Comment::Lit("call lambda"));
+        let gcpoint = self.create_gcpoint();
+        self.asm.indirect_call(0, pos, gcpoint, return_type, dest);
+
+        for temp in temps.into_iter() {
+            self.free_temp_with_type(temp.0, temp.1);
+        }
+    }
+
+    // evaluates all arguments and stores them in temporaries
+    fn emit_call_args(
+        &mut self,
+        args: &[Arg<'ast>],
+        check_self: bool,
+        pos: Position,
+    ) -> Vec<(BuiltinType, i32, Option<ClassDefId>)> {
+        let mut temps: Vec<(BuiltinType, i32, Option<ClassDefId>)> = Vec::new();
+
+        for (idx, arg) in args.iter().enumerate() {
             let mode = arg.ty().mode();
             let dest = register_for_mode(mode);
 
@@ -1627,18 +2059,10 @@ where
                 Arg::Expr(ast, ty, _) => {
                     self.emit_expr(ast, dest);
 
-                    // check first argument for nil for method calls
-                    //
-                    // no check necessary for:
-                    //   super calls (guaranteed to not be nil) and
-                    //   dynamic dispatch (implicit check when loading fctptr from vtable)
-                    if idx == 0
-                        && fct.has_self()
-                        && check_for_nil(ty)
-                        && !csite.super_call
-                        && !fct.is_virtual()
-                    {
-                        self.asm.test_if_nil_bailout(pos, dest.reg(), Trap::NIL);
+                    if idx == 0 && check_self && check_for_nil(ty) {
+                        let gcpoint = self.create_gcpoint();
+                        self.asm
+                            .test_if_nil_bailout(pos, dest.reg(), Trap::NIL, gcpoint);
                     }
                 }
 
@@ -1668,12 +2092,22 @@ where
             temps.push((arg.ty(), offset, None));
         }
 
+        temps
+    }
+
+    // moves arguments from temporaries into registers and onto the stack
+    fn load_call_args(
+        &mut self,
+        args: &[Arg<'ast>],
+        temps: &[(BuiltinType, i32, Option<ClassDefId>)],
+        pos: Position,
+    ) {
         let mut arg_offset = -self.jit_info.stacksize();
         let mut idx = 0;
         let mut reg_idx = 0;
         let mut freg_idx = 0;
 
-        for arg in &csite.args {
+        for arg in args {
             let ty = arg.ty();
             let mode = ty.mode();
             let is_float = mode.is_float();
@@ -1682,7 +2116,7 @@ where
             if idx == 0 {
                 if let Some(cls_id) = temps[idx].2 {
                     let reg = REG_PARAMS[reg_idx];
-                    self.emit_allocation(pos, &temps, cls_id, offset, reg);
+                    self.emit_allocation(pos, temps, cls_id, offset, reg);
 
                     // after the allocation `offset` is initialized,
                     // add it to the set of temporaries such that it is part
@@ -1726,74 +2160,6 @@ where
 
             idx += 1;
         }
-
-        let return_type = self.specialize_type(csite.return_type);
-        let cls_type_params: TypeParams = csite
-            .cls_type_params
-            .iter()
-            .map(|ty| self.specialize_type(ty))
-            .collect::<Vec<_>>()
-            .into();
-        let fct_type_params: TypeParams = csite
-            .fct_type_params
-            .iter()
-            .map(|ty| self.specialize_type(ty))
-            .collect::<Vec<_>>()
-            .into();
-
-        debug_assert!(cls_type_params
-            .iter()
-            .all(|ty| !ty.contains_type_param(self.vm)));
-        debug_assert!(fct_type_params
-            .iter()
-            .all(|ty| !ty.contains_type_param(self.vm)));
-
-        if csite.super_call {
-            let ptr = self.ptr_for_fct_id(fid, cls_type_params.clone(), fct_type_params.clone());
-            self.asm.emit_comment(Comment::CallSuper(fid));
-            let gcpoint = codegen::create_gcpoint(self.scopes, &self.temps);
-            self.asm.direct_call(
-                fid,
-                ptr.to_ptr(),
-                cls_type_params,
-                fct_type_params,
-                pos,
-                gcpoint,
-                return_type,
-                dest,
-            );
-        } else if fct.is_virtual() {
-            let vtable_index = fct.vtable_index.unwrap();
-            self.asm.emit_comment(Comment::CallVirtual(fid));
-            let gcpoint = self.create_gcpoint();
-            self.asm
-                .indirect_call(vtable_index, pos, gcpoint, return_type, dest);
-        } else {
-            let ptr = self.ptr_for_fct_id(fid, cls_type_params.clone(), fct_type_params.clone());
-            self.asm.emit_comment(Comment::CallDirect(fid));
-            let gcpoint = codegen::create_gcpoint(self.scopes, &self.temps);
-            self.asm.direct_call(
-                fid,
-                ptr.to_ptr(),
-                cls_type_params,
-                fct_type_params,
-                pos,
-                gcpoint,
-                return_type,
-                dest,
-            );
-        }
-
-        if csite.args.len() > 0 {
-            if let Arg::SelfieNew(_, _) = csite.args[0] {
-                let (ty, offset, _) = temps[0];
-                self.asm.load_mem(ty.mode(), dest, Mem::Local(offset));
-            }
-        }
-
-        for temp in temps.into_iter() {
-            self.free_temp_with_type(temp.0, temp.1);
-        }
     }
 
     fn emit_allocation(
@@ -1951,13 +2317,14 @@ where
 
     fn specialize_type(&self, ty: BuiltinType) -> BuiltinType {
         match ty {
+            // lambdas share type params with their outermost function
             BuiltinType::ClassTypeParam(cls_id, id) => {
-                assert!(self.fct.parent == FctParent::Class(cls_id));
+                assert!(self.fct.is_lambda() || self.fct.parent == FctParent::Class(cls_id));
                 self.cls_type_params[id.idx()]
             }
 
             BuiltinType::FctTypeParam(fct_id, id) => {
-                assert!(self.fct.id == fct_id);
+                assert!(self.fct.is_lambda() || self.fct.id == fct_id);
                 self.fct_type_params[id.idx()]
             }
 
@@ -1971,7 +2338,9 @@ where
                 BuiltinType::Class(cls_id, list_id)
             }
 
-            BuiltinType::Lambda(_) => unimplemented!(),
+            BuiltinType::Lambda(_) => {
+                specialize_type(self.vm, ty, self.cls_type_params, self.fct_type_params)
+            }
 
             _ => ty,
         }
@@ -2010,6 +2379,7 @@ fn check_for_nil(ty: BuiltinType) -> bool {
         BuiltinType::Class(_, _) => true,
         BuiltinType::Struct(_, _) => false,
         BuiltinType::Trait(_) => false,
+        BuiltinType::Enum(_) => false,
         BuiltinType::This => unreachable!(),
         BuiltinType::ClassTypeParam(_, _) => unreachable!(),
         BuiltinType::FctTypeParam(_, _) => unreachable!(),
@@ -2017,23 +2387,6 @@ fn check_for_nil(ty: BuiltinType) -> bool {
     }
 }
 
-fn ensure_jit_or_stub_ptr<'ast>(
-    src: &mut FctSrc,
-    vm: &VM,
-    cls_type_params: TypeParams,
-    fct_type_params: TypeParams,
-) -> Address {
-    let specials = src.specializations.read();
-    let key = (cls_type_params, fct_type_params);
-
-    if let Some(&jit_fct_id) = specials.get(&key) {
-        let jit_fct = vm.jit_fcts.idx(jit_fct_id);
-        return jit_fct.fct_ptr();
-    }
-
-    vm.compiler_thunk()
-}
-
 fn to_cond_code(cmp: CmpOp) -> CondCode {
     match cmp {
         CmpOp::Eq => CondCode::Equal,
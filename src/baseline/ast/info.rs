@@ -11,9 +11,10 @@ use crate::cpu::*;
 use crate::mem;
 use crate::semck::specialize::specialize_type;
 use crate::ty::BuiltinType;
+use crate::ty::LambdaId;
 use crate::vm::{
-    Arg, CallSite, CallType, Fct, FctId, FctKind, FctParent, FctSrc, Intrinsic, NodeMap, Store,
-    TraitId, VarId, VM,
+    Arg, CallSite, CallType, Fct, FctId, FctKind, FctParent, FctSrc, Intrinsic, LambdaCallSite,
+    NodeMap, Store, TraitId, VarId, VM,
 };
 
 pub fn generate<'a, 'ast: 'a>(
@@ -59,9 +60,11 @@ pub struct JitInfo<'ast> {
     pub argsize: i32,                 // size of arguments on stack (need to be on bottom)
     pub leaf: bool,                   // false if fct calls other functions
     pub eh_return_value: Option<i32>, // stack slot for return value storage
+    pub context_offset: Option<i32>,  // stack slot for context object
 
     pub map_stores: NodeMap<Store>,
     pub map_csites: NodeMap<CallSite<'ast>>,
+    pub map_lambda_csites: NodeMap<LambdaCallSite<'ast>>,
     pub map_offsets: NodeMap<i32>,
     pub map_var_offsets: HashMap<VarId, i32>,
     pub map_var_types: HashMap<VarId, BuiltinType>,
@@ -102,9 +105,11 @@ impl<'ast> JitInfo<'ast> {
             argsize: 0,
             leaf: false,
             eh_return_value: None,
+            context_offset: None,
 
             map_stores: NodeMap::new(),
             map_csites: NodeMap::new(),
+            map_lambda_csites: NodeMap::new(),
             map_offsets: NodeMap::new(),
             map_var_offsets: HashMap::new(),
             map_var_types: HashMap::new(),
@@ -180,6 +185,10 @@ impl<'a, 'ast> Visitor<'ast> for InfoGenerator<'a, 'ast> {
                 self.reserve_stmt_do(r#try);
             }
 
+            &StmtDefer(ref defer) => {
+                self.reserve_stmt_defer(defer);
+            }
+
             &StmtFor(ref sfor) => {
                 self.reserve_stmt_for(sfor);
             }
@@ -204,6 +213,9 @@ impl<'a, 'ast> Visitor<'ast> for InfoGenerator<'a, 'ast> {
             ExprBin(ref expr) => self.expr_bin(expr),
             ExprUn(ref expr) => self.expr_un(expr),
             ExprConv(ref expr) => self.expr_conv(expr),
+            ExprLambda(ref expr) => {
+                self.reserve_temp_for_node_with_type(expr.id, BuiltinType::Ptr);
+            }
             ExprTypeParam(_) => unreachable!(),
 
             _ => visit::walk_expr(self, e),
@@ -217,6 +229,11 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
             self.reserve_stack_for_self();
         }
 
+        if self.src.has_context {
+            let offset = self.reserve_stack_for_type(BuiltinType::Ptr);
+            self.jit_info.context_offset = Some(offset);
+        }
+
         self.visit_fct(self.ast);
 
         self.jit_info.localsize = self.localsize;
@@ -227,14 +244,7 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
     }
 
     fn reserve_stmt_do(&mut self, r#try: &'ast StmtDoType) {
-        let ret = self.fct.return_type;
-
-        if !ret.is_unit() {
-            self.eh_return_value = Some(
-                self.eh_return_value
-                    .unwrap_or_else(|| self.reserve_stack_for_type(ret)),
-            );
-        }
+        self.reserve_eh_return_value();
 
         // we also need space for catch block parameters
         for catch in &r#try.catch_blocks {
@@ -248,6 +258,25 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
         }
     }
 
+    fn reserve_stmt_defer(&mut self, defer: &'ast StmtDeferType) {
+        self.reserve_eh_return_value();
+
+        // stack slot for exception while executing the deferred call
+        let offset = self.reserve_stack_for_type(BuiltinType::Ptr);
+        self.jit_info.map_offsets.insert(defer.id, offset);
+    }
+
+    fn reserve_eh_return_value(&mut self) {
+        let ret = self.fct.return_type;
+
+        if !ret.is_unit() {
+            self.eh_return_value = Some(
+                self.eh_return_value
+                    .unwrap_or_else(|| self.reserve_stack_for_type(ret)),
+            );
+        }
+    }
+
     fn reserve_stmt_for(&mut self, stmt: &'ast StmtForType) {
         let for_type_info = self.src.map_fors.get(stmt.id).unwrap();
 
@@ -315,6 +344,9 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
                 cls.ty
             }
 
+            // closure object for lambdas
+            FctParent::Function(_) => BuiltinType::Ptr,
+
             _ => unreachable!(),
         };
 
@@ -322,6 +354,7 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
 
         let id = self.src.var_self().id;
         self.jit_info.map_var_offsets.insert(id, offset);
+        self.jit_info.map_var_types.insert(id, ty);
     }
 
     fn reserve_stack_for_var(&mut self, id: VarId) -> i32 {
@@ -369,6 +402,11 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
     }
 
     fn expr_call(&mut self, expr: &'ast ExprCallType) {
+        if let CallType::Lambda(lambda_id) = **self.src.map_calls.get(expr.id).unwrap() {
+            self.expr_call_lambda(expr, lambda_id);
+            return;
+        }
+
         if let Some(intrinsic) = self.get_intrinsic(expr.id) {
             self.reserve_args_call(expr);
             self.jit_info.map_intrinsics.insert(expr.id, intrinsic);
@@ -433,6 +471,8 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
 
                 fct_id = fid;
             }
+
+            CallType::Lambda(_) => unreachable!(),
         }
 
         let fct = self.vm.fcts.idx(fct_id);
@@ -464,6 +504,32 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
         self.universal_call(expr.id, args, Some(callee_id));
     }
 
+    fn expr_call_lambda(&mut self, expr: &'ast ExprCallType, lambda_id: LambdaId) {
+        // function invokes another function
+        self.leaf = false;
+
+        let lambda = self.vm.lambda_types.lock().get(lambda_id);
+
+        let ty = self.ty(expr.callee.id());
+        let offset = self.reserve_temp_for_type(ty);
+        let mut args = vec![Arg::Expr(&expr.callee, ty, offset)];
+
+        for (arg, &ty) in expr.args.iter().zip(&lambda.params) {
+            let ty = self.specialize_type(ty);
+            let offset = self.reserve_temp_for_type(ty);
+            args.push(Arg::Expr(arg, ty, offset));
+        }
+
+        self.determine_call_stack(&args);
+
+        let csite = LambdaCallSite {
+            args: args,
+            return_type: self.specialize_type(lambda.ret),
+        };
+
+        self.jit_info.map_lambda_csites.insert(expr.id, csite);
+    }
+
     fn reserve_args_call(&mut self, expr: &'ast ExprCallType) {
         for arg in &expr.args {
             self.visit_expr(arg);
@@ -630,6 +696,8 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
                 cls_type_params = ty.type_params(self.vm);
                 fct_type_params = TypeParams::empty();
             }
+
+            CallType::Lambda(_) => unreachable!(),
         }
 
         (cls_type_params, fct_type_params)
@@ -728,7 +796,10 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
         let lhs_ty = self.ty(expr.lhs.id());
         let rhs_ty = self.ty(expr.rhs.id());
 
-        if expr.op == BinOp::Cmp(CmpOp::Is) || expr.op == BinOp::Cmp(CmpOp::IsNot) {
+        if expr.op == BinOp::Cmp(CmpOp::Is)
+            || expr.op == BinOp::Cmp(CmpOp::IsNot)
+            || lhs_ty.is_enum()
+        {
             self.visit_expr(&expr.lhs);
             self.visit_expr(&expr.rhs);
 
@@ -830,6 +901,8 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
             CallType::Ctor(_, _, ref type_params) | CallType::CtorNew(_, _, ref type_params) => {
                 specialize_type(self.vm, ty, type_params, &TypeParams::empty())
             }
+
+            CallType::Lambda(_) => unreachable!(),
         };
 
         self.specialize_type(ty)
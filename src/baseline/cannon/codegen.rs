@@ -1,18 +1,36 @@
 use crate::baseline::asm::BaselineAssembler;
-use crate::baseline::codegen::fct_pattern_match;
-use crate::cpu::{Mem, FREG_RESULT, REG_RESULT};
+use crate::cpu::{
+    FReg, Mem, Reg, FREG_PARAMS, FREG_RESULT, FREG_TMP1, PARAM_OFFSET, REG_PARAMS, REG_RESULT,
+    REG_TMP1, REG_TMP2,
+};
 use dora_parser::ast::*;
+use dora_parser::lexer::position::Position;
 
-use crate::baseline::codegen::{should_emit_debug, CodeGen, Scopes};
-use crate::baseline::fct::{Comment, JitBaselineFct, JitDescriptor};
-use crate::class::TypeParams;
+use crate::baseline::codegen::{
+    ptr_for_fct_id, register_for_mode, should_emit_bytecode, should_emit_debug, AllocationSize,
+    CodeGen, CondCode, ExprStore,
+};
+use crate::baseline::dora_native::{InternalFct, InternalFctDescriptor};
+use crate::baseline::fct::{CatchType, Comment, GcPoint, JitBaselineFct, JitDescriptor};
+use crate::baseline::ic;
+use crate::baseline::tier::HotnessCounters;
+use crate::cha;
+use crate::class::{ClassDef, ClassDefId, ClassSize, FieldId, TypeParams};
+use crate::gc::Address;
 use crate::masm::*;
-use crate::object::Str;
+use crate::mem;
+use crate::object::{Header, Str};
+use crate::os::signal::Trap;
+use crate::stdlib;
+use crate::ty::{BuiltinType, MachineMode};
 use crate::vm::VM;
-use crate::vm::{Fct, FctSrc};
+use crate::vm::{Fct, FctId, FctSrc, GlobalId};
+use crate::vtable::{VTable, DISPLAY_SIZE};
 
 use crate::bytecode::astgen::generate_fct;
-use crate::bytecode::generate::{BytecodeFunction, Register, StrConstPoolIdx};
+use crate::bytecode::generate::{
+    BytecodeFunction, BytecodeIdx, BytecodeType, Register, StrConstPoolIdx,
+};
 use crate::bytecode::opcode::Bytecode;
 
 pub struct CannonCodeGen<'a, 'ast: 'a> {
@@ -20,33 +38,14 @@ pub struct CannonCodeGen<'a, 'ast: 'a> {
     pub fct: &'a Fct<'ast>,
     pub ast: &'ast Function,
     pub asm: BaselineAssembler<'a, 'ast>,
-    pub scopes: Scopes,
     pub src: &'a mut FctSrc,
 
-    pub lbl_break: Option<Label>,
-    pub lbl_continue: Option<Label>,
+    // address of the counters if the function should be optimized when hot
+    pub hotness_counters: Option<Address>,
 
-    // stores all active finally blocks
-    pub active_finallys: Vec<&'ast Stmt>,
-
-    // label to jump instead of emitting epilog for return
-    // needed for return's in finally blocks
-    // return in finally needs to execute to next finally block and not
-    // leave the current function
-    pub lbl_return: Option<Label>,
-
-    // length of active_finallys in last loop
-    // default: 0
-    // break/continue need to emit finally blocks up to the last loop
-    // see tests/finally/break-while.dora
-    pub active_loop: Option<usize>,
-
-    // upper length of active_finallys in emitting finally-blocks for break/continue
-    // default: active_finallys.len()
-    // break/continue needs to execute finally-blocks in loop, return in these blocks
-    // would dump all active_finally-entries from the loop but we need an upper bound.
-    // see emit_finallys_within_loop and tests/finally/continue-return.dora
-    pub active_upper: Option<usize>,
+    // generates an entry for deoptimization at the given instruction instead of
+    // the regular prolog, the registers are passed in a buffer
+    pub deopt_entry: Option<(BytecodeIdx, Vec<Register>)>,
 
     pub cls_type_params: &'a TypeParams,
     pub fct_type_params: &'a TypeParams,
@@ -56,54 +55,194 @@ impl<'a, 'ast> CannonCodeGen<'a, 'ast>
 where
     'ast: 'a,
 {
-    fn emit_prolog(&mut self, bytecode: &BytecodeFunction) {
-        self.asm.prolog(bytecode.stacksize());
+    fn emit_prolog(&mut self, bytecode: &BytecodeFunction, stacksize: i32) {
+        self.asm.prolog(stacksize);
+        self.asm.check_stack_limit(self.fct.pos);
         self.asm.emit_comment(Comment::Lit("prolog end"));
         self.asm.emit_comment(Comment::Newline);
+
+        let params = self.ast.params.len() + if self.fct.has_self() { 1 } else { 0 };
+        let mut reg_idx = 0;
+        let mut freg_idx = 0;
+        let mut param_offset = PARAM_OFFSET;
+
+        for idx in 0..params {
+            let reg = Register(idx);
+            let mode = bytecode.register(reg).mode();
+            let offset = bytecode.offset(reg);
+
+            if mode.is_float() && freg_idx < FREG_PARAMS.len() {
+                let freg = FREG_PARAMS[freg_idx];
+                self.asm.store_mem(mode, Mem::Local(offset), freg.into());
+                freg_idx += 1;
+            } else if !mode.is_float() && reg_idx < REG_PARAMS.len() {
+                let reg = REG_PARAMS[reg_idx];
+                self.asm.store_mem(mode, Mem::Local(offset), reg.into());
+                reg_idx += 1;
+            } else {
+                // params not passed in registers are copied from the caller's frame
+                let tmp = if mode.is_float() {
+                    FREG_TMP1.into()
+                } else {
+                    REG_TMP1.into()
+                };
+
+                self.asm.load_mem(mode, tmp, Mem::Local(param_offset));
+                self.asm.store_mem(mode, Mem::Local(offset), tmp);
+                param_offset += 8;
+            }
+        }
+
+        self.emit_init_references(bytecode, params);
+    }
+
+    // every register of type ptr is part of all gc points,
+    // so they need to be initialized with nil
+    fn emit_init_references(&mut self, bytecode: &BytecodeFunction, skip: usize) {
+        let mut nil_loaded = false;
+
+        for (idx, &ty) in bytecode.registers().iter().enumerate().skip(skip) {
+            if ty != BytecodeType::Ptr {
+                continue;
+            }
+
+            if !nil_loaded {
+                self.asm.load_nil(REG_RESULT);
+                nil_loaded = true;
+            }
+
+            let offset = bytecode.offset(Register(idx));
+            self.asm
+                .store_mem(MachineMode::Ptr, Mem::Local(offset), REG_RESULT.into());
+        }
+    }
+
+    // the buffer with the values of the registers is passed in the first param
+    // register, the first slot of the buffer holds the address of the entry
+    fn emit_deopt_entry(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        stacksize: i32,
+        registers: &[Register],
+        target: Label,
+    ) {
+        self.asm.prolog(stacksize);
+        self.asm.check_stack_limit(self.fct.pos);
+        self.asm.emit_comment(Comment::Lit("deopt entry"));
+        self.asm.emit_comment(Comment::Newline);
+
+        self.asm.copy_reg(MachineMode::Ptr, REG_TMP2, REG_PARAMS[0]);
+        self.emit_init_references(bytecode, 0);
+
+        for (idx, &reg) in registers.iter().enumerate() {
+            let mode = bytecode.register(reg).mode();
+            let tmp = if mode.is_float() {
+                FREG_TMP1.into()
+            } else {
+                REG_TMP1.into()
+            };
+
+            let slot = Mem::Base(REG_TMP2, (idx as i32 + 1) * mem::ptr_width());
+            self.asm.load_mem(mode, tmp, slot);
+            self.asm
+                .store_mem(mode, Mem::Local(bytecode.offset(reg)), tmp);
+        }
+
+        self.asm.jump(target);
     }
 
-    fn emit_epilog(&mut self, bytecode: &BytecodeFunction) {
+    fn emit_epilog(&mut self, stacksize: i32) {
         self.asm.emit_comment(Comment::Newline);
         self.asm.emit_comment(Comment::Lit("epilog"));
 
         let polling_page = self.vm.polling_page.addr();
-        self.asm
-            .epilog_with_polling(bytecode.stacksize(), polling_page);
+        self.asm.epilog_with_polling(stacksize, polling_page);
     }
 
-    fn emit_const_bool(&mut self, bytecode: &BytecodeFunction, dest: Register, bool_const: bool) {
-        let bytecode_type = bytecode.register(dest);
+    fn emit_safepoint(&mut self, bytecode: &BytecodeFunction, pos: Position) {
+        self.asm.emit_comment(Comment::ReadPollingPage);
+        self.asm.check_polling_page(self.vm.polling_page.addr());
+
+        let gcpoint = create_gcpoint(bytecode);
+        self.asm.emit_gcpoint(gcpoint.clone());
+
+        // no on-stack replacement for cannon frames: a hot loop only recompiles
+        // the function, the running activation stays in baseline code
+        if let Some(counters) = self.hotness_counters {
+            self.asm.increment_counter(
+                counters,
+                HotnessCounters::backedges_offset(),
+                self.vm.args.tier_loops(),
+                None,
+                pos,
+                gcpoint,
+            );
+        }
+    }
+
+    fn emit_invocation_counter(&mut self, bytecode: &BytecodeFunction) {
+        if let Some(counters) = self.hotness_counters {
+            let gcpoint = create_gcpoint(bytecode);
+
+            self.asm.increment_counter(
+                counters,
+                HotnessCounters::invocations_offset(),
+                self.vm.args.tier_calls(),
+                None,
+                self.fct.pos,
+                gcpoint,
+            );
+        }
+    }
+
+    fn emit_load_register(&mut self, bytecode: &BytecodeFunction, src: Register, dest: ExprStore) {
+        let mode = bytecode.register(src).mode();
+        let offset = bytecode.offset(src);
+
+        self.asm.load_mem(mode, dest, Mem::Local(offset));
+    }
+
+    fn emit_store_register(&mut self, bytecode: &BytecodeFunction, src: ExprStore, dest: Register) {
+        let mode = bytecode.register(dest).mode();
         let offset = bytecode.offset(dest);
 
+        self.asm.store_mem(mode, Mem::Local(offset), src);
+    }
+
+    fn emit_mov(&mut self, bytecode: &BytecodeFunction, dest: Register, src: Register) {
+        let value = register_for_mode(bytecode.register(src).mode());
+
+        self.emit_load_register(bytecode, src, value);
+        self.emit_store_register(bytecode, value, dest);
+    }
+
+    fn emit_const_nil(&mut self, bytecode: &BytecodeFunction, dest: Register) {
+        self.asm.load_nil(REG_RESULT);
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
+    }
+
+    fn emit_const_bool(&mut self, bytecode: &BytecodeFunction, dest: Register, bool_const: bool) {
         if bool_const {
             self.asm.load_true(REG_RESULT);
         } else {
             self.asm.load_false(REG_RESULT);
         }
-        self.asm
-            .store_mem(bytecode_type.mode(), Mem::Local(offset), REG_RESULT.into());
+
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
     }
 
     fn emit_const_int(&mut self, bytecode: &BytecodeFunction, dest: Register, int_const: i64) {
-        let bytecode_type = bytecode.register(dest);
-        let offset = bytecode.offset(dest);
+        let mode = bytecode.register(dest).mode();
 
-        self.asm
-            .load_int_const(bytecode_type.mode(), REG_RESULT, int_const);
-
-        self.asm
-            .store_mem(bytecode_type.mode(), Mem::Local(offset), REG_RESULT.into());
+        self.asm.load_int_const(mode, REG_RESULT, int_const);
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
     }
 
     fn emit_const_float(&mut self, bytecode: &BytecodeFunction, dest: Register, float_const: f64) {
-        let bytecode_type = bytecode.register(dest);
-        let offset = bytecode.offset(dest);
+        let mode = bytecode.register(dest).mode();
 
-        self.asm
-            .load_float_const(bytecode_type.mode(), FREG_RESULT, float_const);
-
-        self.asm
-            .store_mem(bytecode_type.mode(), Mem::Local(offset), FREG_RESULT.into());
+        self.asm.load_float_const(mode, FREG_RESULT, float_const);
+        self.emit_store_register(bytecode, FREG_RESULT.into(), dest);
     }
 
     fn emit_const_string(
@@ -112,9 +251,6 @@ where
         dest: Register,
         sp: StrConstPoolIdx,
     ) {
-        let bytecode_type = bytecode.register(dest);
-        let offset = bytecode.offset(dest);
-
         let lit_value = bytecode.string(sp);
 
         let handle = Str::from_buffer_in_perm(self.vm, lit_value.as_bytes());
@@ -122,11 +258,825 @@ where
         let pos = self.asm.pos() as i32;
 
         self.asm.emit_comment(Comment::LoadString(handle));
-
         self.asm.load_constpool(REG_RESULT, disp + pos);
 
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
+    }
+
+    fn emit_int_binop<F>(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        dest: Register,
+        lhs: Register,
+        rhs: Register,
+        emit: F,
+    ) where
+        F: FnOnce(&mut BaselineAssembler<'a, 'ast>, MachineMode, Reg, Reg, Reg),
+    {
+        let mode = bytecode.register(dest).mode();
+
+        self.emit_load_register(bytecode, lhs, REG_RESULT.into());
+        self.emit_load_register(bytecode, rhs, REG_TMP1.into());
+        emit(&mut self.asm, mode, REG_RESULT, REG_RESULT, REG_TMP1);
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
+    }
+
+    fn emit_int_unop<F>(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        dest: Register,
+        src: Register,
+        emit: F,
+    ) where
+        F: FnOnce(&mut BaselineAssembler<'a, 'ast>, MachineMode, Reg, Reg),
+    {
+        let mode = bytecode.register(dest).mode();
+
+        self.emit_load_register(bytecode, src, REG_RESULT.into());
+        emit(&mut self.asm, mode, REG_RESULT, REG_RESULT);
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
+    }
+
+    fn emit_float_binop<F>(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        dest: Register,
+        lhs: Register,
+        rhs: Register,
+        emit: F,
+    ) where
+        F: FnOnce(&mut BaselineAssembler<'a, 'ast>, MachineMode, FReg, FReg, FReg),
+    {
+        let mode = bytecode.register(dest).mode();
+
+        self.emit_load_register(bytecode, lhs, FREG_RESULT.into());
+        self.emit_load_register(bytecode, rhs, FREG_TMP1.into());
+        emit(&mut self.asm, mode, FREG_RESULT, FREG_RESULT, FREG_TMP1);
+        self.emit_store_register(bytecode, FREG_RESULT.into(), dest);
+    }
+
+    fn emit_float_unop<F>(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        dest: Register,
+        src: Register,
+        emit: F,
+    ) where
+        F: FnOnce(&mut BaselineAssembler<'a, 'ast>, MachineMode, FReg, FReg),
+    {
+        let mode = bytecode.register(dest).mode();
+
+        self.emit_load_register(bytecode, src, FREG_RESULT.into());
+        emit(&mut self.asm, mode, FREG_RESULT, FREG_RESULT);
+        self.emit_store_register(bytecode, FREG_RESULT.into(), dest);
+    }
+
+    fn emit_test_int(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        dest: Register,
+        lhs: Register,
+        rhs: Register,
+        cond: CondCode,
+    ) {
+        let mode = bytecode.register(lhs).mode();
+
+        self.emit_load_register(bytecode, lhs, REG_RESULT.into());
+        self.emit_load_register(bytecode, rhs, REG_TMP1.into());
+        self.asm.cmp_reg(mode, REG_RESULT, REG_TMP1);
+        self.asm.set(REG_RESULT, cond);
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
+    }
+
+    fn emit_test_float(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        dest: Register,
+        lhs: Register,
+        rhs: Register,
+        cond: CondCode,
+    ) {
+        let mode = bytecode.register(lhs).mode();
+
+        self.emit_load_register(bytecode, lhs, FREG_RESULT.into());
+        self.emit_load_register(bytecode, rhs, FREG_TMP1.into());
+        self.asm
+            .float_cmp(mode, REG_RESULT, FREG_RESULT, FREG_TMP1, cond);
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
+    }
+
+    // truncates or zero-extends the value by loading and storing it with different sizes
+    fn emit_cast(&mut self, bytecode: &BytecodeFunction, dest: Register, src: Register) {
+        self.emit_load_register(bytecode, src, REG_RESULT.into());
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
+    }
+
+    fn emit_extend_int_long(&mut self, bytecode: &BytecodeFunction, dest: Register, src: Register) {
+        self.emit_load_register(bytecode, src, REG_RESULT.into());
+        self.asm.extend_int_long(REG_RESULT, REG_RESULT);
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
+    }
+
+    fn emit_int_to_float(&mut self, bytecode: &BytecodeFunction, dest: Register, src: Register) {
+        let dest_mode = bytecode.register(dest).mode();
+        let src_mode = bytecode.register(src).mode();
+
+        self.emit_load_register(bytecode, src, REG_RESULT.into());
+        self.asm
+            .int_to_float(dest_mode, FREG_RESULT, src_mode, REG_RESULT);
+        self.emit_store_register(bytecode, FREG_RESULT.into(), dest);
+    }
+
+    fn emit_float_to_int(&mut self, bytecode: &BytecodeFunction, dest: Register, src: Register) {
+        let dest_mode = bytecode.register(dest).mode();
+        let src_mode = bytecode.register(src).mode();
+
+        self.emit_load_register(bytecode, src, FREG_RESULT.into());
+        self.asm
+            .float_to_int(dest_mode, REG_RESULT, src_mode, FREG_RESULT);
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
+    }
+
+    fn emit_int_as_float(&mut self, bytecode: &BytecodeFunction, dest: Register, src: Register) {
+        let dest_mode = bytecode.register(dest).mode();
+        let src_mode = bytecode.register(src).mode();
+
+        self.emit_load_register(bytecode, src, REG_RESULT.into());
+        self.asm
+            .int_as_float(dest_mode, FREG_RESULT, src_mode, REG_RESULT);
+        self.emit_store_register(bytecode, FREG_RESULT.into(), dest);
+    }
+
+    fn emit_float_as_int(&mut self, bytecode: &BytecodeFunction, dest: Register, src: Register) {
+        let dest_mode = bytecode.register(dest).mode();
+        let src_mode = bytecode.register(src).mode();
+
+        self.emit_load_register(bytecode, src, FREG_RESULT.into());
+        self.asm
+            .float_as_int(dest_mode, REG_RESULT, src_mode, FREG_RESULT);
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
+    }
+
+    fn emit_load_field(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        dest: Register,
+        obj: Register,
+        cls_id: ClassDefId,
+        field_id: FieldId,
+        pos: Position,
+    ) {
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+        let field = &cls.fields[field_id.idx()];
+
+        let mode = bytecode.register(dest).mode();
+        let value = register_for_mode(mode);
+
+        self.emit_load_register(bytecode, obj, REG_RESULT.into());
+
+        self.asm.emit_comment(Comment::LoadField(cls_id, field_id));
+        let gcpoint = create_gcpoint(bytecode);
+        self.asm.load_field(
+            mode,
+            value,
+            REG_RESULT,
+            field.offset,
+            pos.line as i32,
+            gcpoint,
+        );
+
+        self.emit_store_register(bytecode, value, dest);
+    }
+
+    fn emit_store_field(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        src: Register,
+        obj: Register,
+        cls_id: ClassDefId,
+        field_id: FieldId,
+        pos: Position,
+    ) {
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+        let field = &cls.fields[field_id.idx()];
+
+        let ty = bytecode.register(src);
+        let value = register_for_mode(ty.mode());
+
+        self.emit_load_register(bytecode, obj, REG_TMP1.into());
+        self.emit_load_register(bytecode, src, value);
+
+        self.asm.emit_comment(Comment::StoreField(cls_id, field_id));
+
+        let write_barrier = self.vm.gc.needs_write_barrier() && ty == BytecodeType::Ptr;
+        let card_table_offset = self.vm.gc.card_table_offset();
+
+        let gcpoint = create_gcpoint(bytecode);
+        self.asm.store_field(
+            ty.mode(),
+            REG_TMP1,
+            field.offset,
+            value,
+            pos.line as i32,
+            write_barrier,
+            card_table_offset,
+            gcpoint,
+        );
+    }
+
+    fn emit_load_global(&mut self, bytecode: &BytecodeFunction, dest: Register, gid: GlobalId) {
+        let glob = self.vm.globals.idx(gid);
+        let glob = glob.lock();
+
+        let disp = self.asm.add_addr(glob.address_value.to_ptr());
+        let pos = self.asm.pos() as i32;
+
+        self.asm.emit_comment(Comment::LoadGlobal(gid));
+        self.asm.load_constpool(REG_TMP1, disp + pos);
+
+        let mode = bytecode.register(dest).mode();
+        let value = register_for_mode(mode);
+
+        self.asm.load_mem(mode, value, Mem::Base(REG_TMP1, 0));
+        self.emit_store_register(bytecode, value, dest);
+    }
+
+    fn emit_store_global(&mut self, bytecode: &BytecodeFunction, src: Register, gid: GlobalId) {
+        let glob = self.vm.globals.idx(gid);
+        let glob = glob.lock();
+
+        let mode = bytecode.register(src).mode();
+        let value = register_for_mode(mode);
+        self.emit_load_register(bytecode, src, value);
+
+        let disp = self.asm.add_addr(glob.address_value.to_ptr());
+        let pos = self.asm.pos() as i32;
+
+        self.asm.emit_comment(Comment::StoreGlobal(gid));
+        self.asm.load_constpool(REG_TMP1, disp + pos);
+
+        self.asm.store_mem(mode, Mem::Base(REG_TMP1, 0), value);
+    }
+
+    fn emit_load_array(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        dest: Register,
+        arr: Register,
+        idx: Register,
+        pos: Position,
+    ) {
+        self.emit_load_register(bytecode, arr, REG_RESULT.into());
+        self.emit_load_register(bytecode, idx, REG_TMP1.into());
+
+        let gcpoint = create_gcpoint(bytecode);
+        self.asm
+            .test_if_nil_bailout(pos, REG_RESULT, Trap::NIL, gcpoint.clone());
+
+        if !self.vm.args.flag_omit_bounds_check {
+            self.asm
+                .check_index_out_of_bounds(pos, REG_RESULT, REG_TMP1, gcpoint);
+        }
+
+        let mode = bytecode.register(dest).mode();
+        let value = register_for_mode(mode);
+
+        self.asm.load_array_elem(mode, value, REG_RESULT, REG_TMP1);
+        self.emit_store_register(bytecode, value, dest);
+    }
+
+    fn emit_store_array(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        src: Register,
+        arr: Register,
+        idx: Register,
+        pos: Position,
+    ) {
+        self.emit_load_register(bytecode, arr, REG_TMP1.into());
+        self.emit_load_register(bytecode, idx, REG_TMP2.into());
+
+        let gcpoint = create_gcpoint(bytecode);
+        self.asm
+            .test_if_nil_bailout(pos, REG_TMP1, Trap::NIL, gcpoint.clone());
+
+        if !self.vm.args.flag_omit_bounds_check {
+            self.asm
+                .check_index_out_of_bounds(pos, REG_TMP1, REG_TMP2, gcpoint);
+        }
+
+        let ty = bytecode.register(src);
+        let value = register_for_mode(ty.mode());
+        self.emit_load_register(bytecode, src, value);
+
+        let write_barrier = self.vm.gc.needs_write_barrier() && ty == BytecodeType::Ptr;
+        let card_table_offset = self.vm.gc.card_table_offset();
+
+        self.asm.store_array_elem(
+            ty.mode(),
+            REG_TMP1,
+            REG_TMP2,
+            value,
+            write_barrier,
+            card_table_offset,
+        );
+    }
+
+    fn emit_array_length(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        dest: Register,
+        arr: Register,
+        pos: Position,
+    ) {
+        self.emit_load_register(bytecode, arr, REG_RESULT.into());
+
+        let gcpoint = create_gcpoint(bytecode);
+        self.asm
+            .test_if_nil_bailout(pos, REG_RESULT, Trap::NIL, gcpoint);
+        self.asm.load_mem(
+            MachineMode::Ptr,
+            REG_RESULT.into(),
+            Mem::Base(REG_RESULT, Header::size()),
+        );
+
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
+    }
+
+    fn emit_set_uint8(&mut self, bytecode: &BytecodeFunction, address: Register, value: Register) {
+        self.emit_load_register(bytecode, address, REG_RESULT.into());
+        self.emit_load_register(bytecode, value, REG_TMP1.into());
+
+        self.asm
+            .store_mem(MachineMode::Int8, Mem::Base(REG_RESULT, 0), REG_TMP1.into());
+    }
+
+    fn emit_allocation(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        dest: Register,
+        cls_id: ClassDefId,
+        length: Option<Register>,
+        pos: Position,
+    ) {
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+
+        // allocate storage for object
+        self.asm.emit_comment(Comment::Alloc(cls_id));
+
+        let element_size = match cls.size {
+            ClassSize::Fixed(_) => None,
+            ClassSize::Array(esize) => Some(esize),
+            ClassSize::ObjArray => Some(mem::ptr_width()),
+            ClassSize::Str => Some(1),
+            ClassSize::FreeArray => unreachable!(),
+        };
+
+        let alloc_size = match cls.size {
+            ClassSize::Fixed(size) => {
+                self.asm
+                    .load_int_const(MachineMode::Int32, REG_PARAMS[0], size as i64);
+                AllocationSize::Fixed(size as usize)
+            }
+
+            _ => {
+                let length = length.expect("array allocation without length");
+                self.emit_load_register(bytecode, length, REG_TMP1.into());
+
+                self.asm
+                    .determine_array_size(REG_PARAMS[0], REG_TMP1, element_size.unwrap(), true);
+
+                AllocationSize::Dynamic(REG_PARAMS[0])
+            }
+        };
+
+        let array_ref = match cls.size {
+            ClassSize::ObjArray => true,
+            _ => false,
+        };
+
+        let gcpoint = create_gcpoint(bytecode);
+        self.asm
+            .allocate(REG_RESULT, alloc_size, pos, array_ref, gcpoint);
+
+        // store gc object in register
+        self.emit_store_register(bytecode, REG_RESULT.into(), dest);
+
+        // store classptr in object
+        let cptr = (&**cls.vtable.as_ref().unwrap()) as *const VTable as *const u8;
+        let disp = self.asm.add_addr(cptr);
+        let pos = self.asm.pos() as i32;
+
+        self.asm.emit_comment(Comment::StoreVTable(cls_id));
+        self.asm.load_constpool(REG_TMP1, disp + pos);
+        self.asm
+            .store_mem(MachineMode::Ptr, Mem::Base(REG_RESULT, 0), REG_TMP1.into());
+
+        // clear mark/fwdptr word in header
+        assert!(Header::size() == 2 * mem::ptr_width());
+        self.asm.load_int_const(MachineMode::Ptr, REG_TMP1, 0);
+        self.asm.store_mem(
+            MachineMode::Ptr,
+            Mem::Base(REG_RESULT, mem::ptr_width()),
+            REG_TMP1.into(),
+        );
+
+        match cls.size {
+            ClassSize::Fixed(size) => {
+                self.asm.fill_zero(REG_RESULT, size as usize);
+            }
+
+            _ => {
+                // store length in object
+                let length = length.unwrap();
+                self.emit_load_register(bytecode, length, REG_TMP1.into());
+                self.asm.store_mem(
+                    MachineMode::Ptr,
+                    Mem::Base(REG_RESULT, Header::size()),
+                    REG_TMP1.into(),
+                );
+
+                self.asm.int_add_imm(
+                    MachineMode::Ptr,
+                    REG_RESULT,
+                    REG_RESULT,
+                    (Header::size() + mem::ptr_width()) as i64,
+                );
+
+                self.asm
+                    .determine_array_size(REG_TMP1, REG_TMP1, element_size.unwrap(), false);
+                self.asm
+                    .int_add(MachineMode::Ptr, REG_TMP1, REG_TMP1, REG_RESULT);
+                self.asm.fill_zero_dynamic(REG_RESULT, REG_TMP1);
+            }
+        }
+    }
+
+    fn emit_instance_of(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        dest: Option<Register>,
+        obj: Register,
+        cls_id: ClassDefId,
+        pos: Position,
+    ) {
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+        let vtable: &VTable = cls.vtable.as_ref().unwrap();
+
+        // `is` results in false and `as` succeeds if object is nil
+        self.emit_load_register(bytecode, obj, REG_RESULT.into());
+        let lbl_nil = self.asm.test_if_nil(REG_RESULT);
+
+        // tmp1 = <vtable of object>
+        self.asm
+            .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Base(REG_RESULT, 0));
+
+        let disp = self.asm.add_addr(vtable as *const _ as *mut u8);
+        let asm_pos = self.asm.pos() as i32;
+
+        // tmp2 = <vtable of T>
+        self.asm.load_constpool(REG_TMP2, disp + asm_pos);
+
+        if vtable.subtype_depth >= DISPLAY_SIZE as i32 {
+            // cmp [tmp1 + offset T.vtable.subtype_depth], tmp3
+            self.asm.cmp_mem_imm(
+                MachineMode::Int32,
+                Mem::Base(REG_TMP1, VTable::offset_of_depth()),
+                vtable.subtype_depth,
+            );
+
+            // jnz lbl_false
+            let lbl_false = self.asm.create_label();
+            self.asm.jump_if(CondCode::Less, lbl_false);
+
+            // tmp1 = tmp1.subtype_overflow
+            self.asm.load_mem(
+                MachineMode::Ptr,
+                REG_TMP1.into(),
+                Mem::Base(REG_TMP1, VTable::offset_of_overflow()),
+            );
+
+            let overflow_offset = mem::ptr_width() * (vtable.subtype_depth - DISPLAY_SIZE as i32);
+
+            // cmp [tmp1 + 8*(vtable.subtype_depth - DISPLAY_SIZE) ], tmp2
+            self.asm.cmp_mem(
+                MachineMode::Ptr,
+                Mem::Base(REG_TMP1, overflow_offset),
+                REG_TMP2,
+            );
+
+            if dest.is_some() {
+                // dest = if zero then true else false
+                self.asm.set(REG_RESULT, CondCode::Equal);
+            } else {
+                // jump to lbl_false if cmp did not succeed
+                self.asm.jump_if(CondCode::NonZero, lbl_false);
+            }
+
+            // jmp lbl_finished
+            let lbl_finished = self.asm.create_label();
+            self.asm.jump(lbl_finished);
+
+            // lbl_false:
+            self.asm.bind_label(lbl_false);
+
+            if dest.is_some() {
+                self.asm.load_false(REG_RESULT);
+            } else {
+                let gcpoint = create_gcpoint(bytecode);
+                self.asm.emit_bailout_inplace(Trap::CAST, pos, gcpoint);
+            }
+
+            // lbl_finished:
+            self.asm.bind_label(lbl_finished);
+        } else {
+            let display_entry =
+                VTable::offset_of_display() + vtable.subtype_depth * mem::ptr_width();
+
+            // tmp1 = vtable of object
+            // tmp2 = vtable of T
+            // cmp [tmp1 + offset], tmp2
+            self.asm.cmp_mem(
+                MachineMode::Ptr,
+                Mem::Base(REG_TMP1, display_entry),
+                REG_TMP2,
+            );
+
+            if dest.is_some() {
+                self.asm.set(REG_RESULT, CondCode::Equal);
+            } else {
+                let lbl_bailout = self.asm.create_label();
+                self.asm.jump_if(CondCode::NotEqual, lbl_bailout);
+                let gcpoint = create_gcpoint(bytecode);
+                self.asm.emit_bailout(lbl_bailout, Trap::CAST, pos, gcpoint);
+            }
+        }
+
+        // lbl_nil:
+        self.asm.bind_label(lbl_nil);
+
+        // for nil REG_RESULT is 0, which is boolean false
+        if let Some(dest) = dest {
+            self.emit_store_register(bytecode, REG_RESULT.into(), dest);
+        }
+    }
+
+    fn emit_nil_check(&mut self, bytecode: &BytecodeFunction, obj: Register, pos: Position) {
+        self.emit_load_register(bytecode, obj, REG_RESULT.into());
+
+        let gcpoint = create_gcpoint(bytecode);
         self.asm
-            .store_mem(bytecode_type.mode(), Mem::Local(offset), REG_RESULT.into());
+            .test_if_nil_bailout(pos, REG_RESULT, Trap::NIL, gcpoint);
+    }
+
+    fn emit_throw(&mut self, bytecode: &BytecodeFunction, exception: Register, pos: Position) {
+        self.emit_load_register(bytecode, exception, REG_RESULT.into());
+        self.asm.throw(REG_RESULT, pos);
+    }
+
+    fn emit_trap(&mut self, bytecode: &BytecodeFunction, trap: Trap, pos: Position) {
+        let gcpoint = create_gcpoint(bytecode);
+        self.asm.emit_bailout_inplace(trap, pos, gcpoint);
+    }
+
+    fn emit_spawn(&mut self, bytecode: &BytecodeFunction, closure: Register, pos: Position) {
+        self.emit_load_register(bytecode, closure, REG_PARAMS[0].into());
+
+        let internal_fct = InternalFct {
+            ptr: Address::from_ptr(stdlib::spawn_lambda as *const u8),
+            args: &[BuiltinType::Ptr],
+            return_type: BuiltinType::Unit,
+            throws: false,
+            desc: InternalFctDescriptor::SpawnThunk,
+        };
+
+        let gcpoint = create_gcpoint(bytecode);
+        self.asm
+            .native_call(internal_fct, pos, gcpoint, REG_RESULT.into());
+    }
+
+    fn emit_jump_if(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        opnd: Register,
+        cond: CondCode,
+        lbl: Label,
+    ) {
+        self.emit_load_register(bytecode, opnd, REG_RESULT.into());
+        self.asm.test_and_jump_if(cond, REG_RESULT, lbl);
+    }
+
+    fn emit_return(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        value: Option<Register>,
+        stacksize: i32,
+    ) {
+        if let Some(value) = value {
+            let mode = bytecode.register(value).mode();
+            self.emit_load_register(bytecode, value, register_for_mode(mode));
+        }
+
+        self.emit_epilog(stacksize);
+    }
+
+    // moves arguments from registers into machine registers and onto the stack
+    fn emit_call_args(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        start: Register,
+        num: usize,
+        stacksize: i32,
+    ) {
+        let mut arg_offset = -stacksize;
+        let mut reg_idx = 0;
+        let mut freg_idx = 0;
+        let mut register_args = Vec::new();
+
+        // store arguments on the stack first, FREG_TMP1 is also a parameter register
+        for idx in 0..num {
+            let arg = start.offset(idx);
+            let mode = bytecode.register(arg).mode();
+
+            if mode.is_float() {
+                if freg_idx < FREG_PARAMS.len() {
+                    register_args.push((arg, FREG_PARAMS[freg_idx].into()));
+                    freg_idx += 1;
+                } else {
+                    self.emit_load_register(bytecode, arg, FREG_TMP1.into());
+                    self.asm
+                        .store_mem(mode, Mem::Local(arg_offset), FREG_TMP1.into());
+                    arg_offset += 8;
+                }
+            } else {
+                if reg_idx < REG_PARAMS.len() {
+                    register_args.push((arg, REG_PARAMS[reg_idx].into()));
+                    reg_idx += 1;
+                } else {
+                    self.emit_load_register(bytecode, arg, REG_TMP1.into());
+                    self.asm
+                        .store_mem(mode, Mem::Local(arg_offset), REG_TMP1.into());
+                    arg_offset += 8;
+                }
+            }
+        }
+
+        for (arg, dest) in register_args {
+            self.emit_load_register(bytecode, arg, dest);
+        }
+    }
+
+    fn emit_invoke_direct(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        idx: BytecodeIdx,
+        dest: Option<Register>,
+        fct_id: FctId,
+        start: Register,
+        num: usize,
+        stacksize: i32,
+        pos: Position,
+    ) {
+        self.emit_call_args(bytecode, start, num, stacksize);
+
+        let (cls_type_params, fct_type_params) = bytecode.type_params(idx);
+        let ptr = ptr_for_fct_id(
+            self.vm,
+            self.fct,
+            self.src,
+            fct_id,
+            cls_type_params.clone(),
+            fct_type_params.clone(),
+        );
+
+        self.asm.emit_comment(Comment::CallDirect(fct_id));
+        let gcpoint = create_gcpoint(bytecode);
+        let (ty, value) = call_result(bytecode, dest);
+        self.asm.direct_call(
+            fct_id,
+            ptr.to_ptr(),
+            cls_type_params,
+            fct_type_params,
+            pos,
+            gcpoint,
+            ty,
+            value,
+        );
+
+        if let Some(dest) = dest {
+            self.emit_store_register(bytecode, value, dest);
+        }
+    }
+
+    fn emit_invoke_virtual(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        dest: Option<Register>,
+        fct_id: FctId,
+        start: Register,
+        num: usize,
+        stacksize: i32,
+        pos: Position,
+    ) {
+        self.emit_call_args(bytecode, start, num, stacksize);
+
+        let (vtable_index, devirtualize) = {
+            let fct = self.vm.fcts.idx(fct_id);
+            let fct = fct.read();
+            (
+                fct.vtable_index.unwrap(),
+                cha::can_devirtualize(self.vm, &*fct),
+            )
+        };
+
+        let gcpoint = create_gcpoint(bytecode);
+        let (ty, value) = call_result(bytecode, dest);
+
+        if devirtualize {
+            // the bytecode doesn't check the receiver for nil before virtual calls
+            self.asm
+                .test_if_nil_bailout(pos, REG_PARAMS[0], Trap::NIL, gcpoint.clone());
+
+            let empty = TypeParams::empty();
+            let ptr = ptr_for_fct_id(
+                self.vm,
+                self.fct,
+                self.src,
+                fct_id,
+                empty.clone(),
+                empty.clone(),
+            );
+
+            self.asm.emit_comment(Comment::CallDirect(fct_id));
+            self.asm.direct_call(
+                fct_id,
+                ptr.to_ptr(),
+                empty.clone(),
+                empty,
+                pos,
+                gcpoint,
+                ty,
+                value,
+            );
+        } else {
+            self.asm.emit_comment(Comment::CallVirtual(fct_id));
+            let cache = ic::create_inline_cache(self.src, pos, vtable_index);
+            self.asm.inline_cache_call(cache, pos, gcpoint, ty, value);
+        }
+
+        if let Some(dest) = dest {
+            self.emit_store_register(bytecode, value, dest);
+        }
+    }
+
+    fn emit_invoke_lambda(
+        &mut self,
+        bytecode: &BytecodeFunction,
+        dest: Option<Register>,
+        start: Register,
+        num: usize,
+        stacksize: i32,
+        pos: Position,
+    ) {
+        self.emit_call_args(bytecode, start, num, stacksize);
+
+        self.asm.emit_comment(Comment::Lit("call lambda"));
+        let gcpoint = create_gcpoint(bytecode);
+        let (ty, value) = call_result(bytecode, dest);
+        self.asm.indirect_call(0, pos, gcpoint, ty, value);
+
+        if let Some(dest) = dest {
+            self.emit_store_register(bytecode, value, dest);
+        }
+    }
+
+    fn emit_exception_handlers(&mut self, bytecode: &BytecodeFunction, code_offsets: &[usize]) {
+        for handler in bytecode.exception_handlers() {
+            let catch_type = match handler.catch_type {
+                Some(cls_def_id) => {
+                    let cls_def = self.vm.class_defs.idx(cls_def_id);
+                    let cls_def = cls_def.read();
+
+                    CatchType::Class(&*cls_def as *const ClassDef)
+                }
+
+                None => CatchType::Any,
+            };
+
+            self.asm.emit_exception_handler(
+                (
+                    code_offsets[handler.try_start.0],
+                    code_offsets[handler.try_end.0],
+                ),
+                code_offsets[handler.catch.0],
+                handler.exception.map(|reg| bytecode.offset(reg)),
+                catch_type,
+            );
+        }
     }
 }
 
@@ -148,47 +1098,468 @@ impl<'a, 'ast> CodeGen<'ast> for CannonCodeGen<'a, 'ast> {
             self.asm.debug();
         }
 
-        self.emit_prolog(&bytecode);
-        for btcode in bytecode.code() {
-            match btcode {
-                Bytecode::ConstTrue(dest) => self.emit_const_bool(&bytecode, *dest, true),
-                Bytecode::ConstFalse(dest) => self.emit_const_bool(&bytecode, *dest, false),
+        // arguments passed on the stack are stored at the bottom of the frame
+        let stacksize = mem::align_i32(bytecode.stacksize() + argsize(&bytecode), 16);
+
+        let code = bytecode.code();
+        let labels = (0..code.len() + 1)
+            .map(|_| self.asm.create_label())
+            .collect::<Vec<_>>();
+        let mut code_offsets = Vec::with_capacity(code.len() + 1);
+
+        match self.deopt_entry.take() {
+            Some((bytecode_idx, registers)) => {
+                self.emit_deopt_entry(&bytecode, stacksize, &registers, labels[bytecode_idx.0])
+            }
+
+            None => {
+                self.emit_prolog(&bytecode, stacksize);
+                self.emit_invocation_counter(&bytecode);
+            }
+        }
+
+        for (idx, btcode) in code.iter().enumerate() {
+            self.asm.bind_label(labels[idx]);
+            code_offsets.push(self.asm.pos());
+
+            let bytecode_idx = BytecodeIdx(idx);
+            let pos = bytecode.position(bytecode_idx);
+
+            match *btcode {
+                Bytecode::AddInt(dest, lhs, rhs) | Bytecode::AddLong(dest, lhs, rhs) => self
+                    .emit_int_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.int_add(mode, dest, lhs, rhs)
+                    }),
+                Bytecode::SubInt(dest, lhs, rhs) | Bytecode::SubLong(dest, lhs, rhs) => self
+                    .emit_int_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.int_sub(mode, dest, lhs, rhs)
+                    }),
+                Bytecode::MulInt(dest, lhs, rhs) | Bytecode::MulLong(dest, lhs, rhs) => self
+                    .emit_int_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.int_mul(mode, dest, lhs, rhs)
+                    }),
+                Bytecode::DivInt(dest, lhs, rhs) | Bytecode::DivLong(dest, lhs, rhs) => {
+                    let gcpoint = create_gcpoint(&bytecode);
+                    self.emit_int_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.int_div(mode, dest, lhs, rhs, pos, gcpoint)
+                    })
+                }
+                Bytecode::ModInt(dest, lhs, rhs) | Bytecode::ModLong(dest, lhs, rhs) => {
+                    let gcpoint = create_gcpoint(&bytecode);
+                    self.emit_int_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.int_mod(mode, dest, lhs, rhs, pos, gcpoint)
+                    })
+                }
+                Bytecode::AndInt(dest, lhs, rhs) | Bytecode::AndLong(dest, lhs, rhs) => self
+                    .emit_int_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.int_and(mode, dest, lhs, rhs)
+                    }),
+                Bytecode::OrInt(dest, lhs, rhs) | Bytecode::OrLong(dest, lhs, rhs) => self
+                    .emit_int_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.int_or(mode, dest, lhs, rhs)
+                    }),
+                Bytecode::XorInt(dest, lhs, rhs) | Bytecode::XorLong(dest, lhs, rhs) => self
+                    .emit_int_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.int_xor(mode, dest, lhs, rhs)
+                    }),
+                Bytecode::ShlInt(dest, lhs, rhs) | Bytecode::ShlLong(dest, lhs, rhs) => self
+                    .emit_int_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.int_shl(mode, dest, lhs, rhs)
+                    }),
+                Bytecode::ShrInt(dest, lhs, rhs) | Bytecode::ShrLong(dest, lhs, rhs) => self
+                    .emit_int_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.int_shr(mode, dest, lhs, rhs)
+                    }),
+                Bytecode::SarInt(dest, lhs, rhs) | Bytecode::SarLong(dest, lhs, rhs) => self
+                    .emit_int_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.int_sar(mode, dest, lhs, rhs)
+                    }),
+
+                Bytecode::AddFloat(dest, lhs, rhs) | Bytecode::AddDouble(dest, lhs, rhs) => self
+                    .emit_float_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.float_add(mode, dest, lhs, rhs)
+                    }),
+                Bytecode::SubFloat(dest, lhs, rhs) | Bytecode::SubDouble(dest, lhs, rhs) => self
+                    .emit_float_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.float_sub(mode, dest, lhs, rhs)
+                    }),
+                Bytecode::MulFloat(dest, lhs, rhs) | Bytecode::MulDouble(dest, lhs, rhs) => self
+                    .emit_float_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.float_mul(mode, dest, lhs, rhs)
+                    }),
+                Bytecode::DivFloat(dest, lhs, rhs) | Bytecode::DivDouble(dest, lhs, rhs) => self
+                    .emit_float_binop(&bytecode, dest, lhs, rhs, |asm, mode, dest, lhs, rhs| {
+                        asm.float_div(mode, dest, lhs, rhs)
+                    }),
+
+                Bytecode::NegInt(dest, src) | Bytecode::NegLong(dest, src) => {
+                    self.emit_int_unop(&bytecode, dest, src, |asm, mode, dest, src| {
+                        asm.int_neg(mode, dest, src)
+                    })
+                }
+                Bytecode::NotByte(dest, src)
+                | Bytecode::NotInt(dest, src)
+                | Bytecode::NotLong(dest, src) => {
+                    self.emit_int_unop(&bytecode, dest, src, |asm, mode, dest, src| {
+                        asm.int_not(mode, dest, src)
+                    })
+                }
+                Bytecode::NotBool(dest, src) => {
+                    self.emit_int_unop(&bytecode, dest, src, |asm, _, dest, src| {
+                        asm.bool_not(dest, src)
+                    })
+                }
+                Bytecode::NegFloat(dest, src) | Bytecode::NegDouble(dest, src) => self
+                    .emit_float_unop(&bytecode, dest, src, |asm, mode, dest, src| {
+                        asm.float_neg(mode, dest, src)
+                    }),
+                Bytecode::SqrtFloat(dest, src) | Bytecode::SqrtDouble(dest, src) => self
+                    .emit_float_unop(&bytecode, dest, src, |asm, mode, dest, src| {
+                        asm.float_sqrt(mode, dest, src)
+                    }),
+
+                Bytecode::ExtendByteToInt(dest, src)
+                | Bytecode::ExtendByteToLong(dest, src)
+                | Bytecode::ExtendCharToLong(dest, src)
+                | Bytecode::CastCharToInt(dest, src)
+                | Bytecode::CastIntToChar(dest, src)
+                | Bytecode::CastIntToByte(dest, src)
+                | Bytecode::CastLongToByte(dest, src)
+                | Bytecode::CastLongToChar(dest, src)
+                | Bytecode::CastLongToInt(dest, src) => self.emit_cast(&bytecode, dest, src),
+                Bytecode::ExtendIntToLong(dest, src) => {
+                    self.emit_extend_int_long(&bytecode, dest, src)
+                }
+
+                Bytecode::ConvertIntToFloat(dest, src)
+                | Bytecode::ConvertIntToDouble(dest, src)
+                | Bytecode::ConvertLongToFloat(dest, src)
+                | Bytecode::ConvertLongToDouble(dest, src) => {
+                    self.emit_int_to_float(&bytecode, dest, src)
+                }
+                Bytecode::TruncateFloatToInt(dest, src)
+                | Bytecode::TruncateFloatToLong(dest, src)
+                | Bytecode::TruncateDoubleToInt(dest, src)
+                | Bytecode::TruncateDoubleToLong(dest, src) => {
+                    self.emit_float_to_int(&bytecode, dest, src)
+                }
+                Bytecode::PromoteFloatToDouble(dest, src) => {
+                    self.emit_float_unop(&bytecode, dest, src, |asm, _, dest, src| {
+                        asm.float_to_double(dest, src)
+                    })
+                }
+                Bytecode::DemoteDoubleToFloat(dest, src) => {
+                    self.emit_float_unop(&bytecode, dest, src, |asm, _, dest, src| {
+                        asm.double_to_float(dest, src)
+                    })
+                }
+                Bytecode::ReinterpretFloatAsInt(dest, src)
+                | Bytecode::ReinterpretDoubleAsLong(dest, src) => {
+                    self.emit_float_as_int(&bytecode, dest, src)
+                }
+                Bytecode::ReinterpretIntAsFloat(dest, src)
+                | Bytecode::ReinterpretLongAsDouble(dest, src) => {
+                    self.emit_int_as_float(&bytecode, dest, src)
+                }
+
+                Bytecode::MovBool(dest, src)
+                | Bytecode::MovByte(dest, src)
+                | Bytecode::MovChar(dest, src)
+                | Bytecode::MovInt(dest, src)
+                | Bytecode::MovLong(dest, src)
+                | Bytecode::MovFloat(dest, src)
+                | Bytecode::MovDouble(dest, src)
+                | Bytecode::MovPtr(dest, src) => self.emit_mov(&bytecode, dest, src),
+
+                Bytecode::LoadFieldBool(dest, obj, cls_id, field_id)
+                | Bytecode::LoadFieldByte(dest, obj, cls_id, field_id)
+                | Bytecode::LoadFieldChar(dest, obj, cls_id, field_id)
+                | Bytecode::LoadFieldInt(dest, obj, cls_id, field_id)
+                | Bytecode::LoadFieldLong(dest, obj, cls_id, field_id)
+                | Bytecode::LoadFieldFloat(dest, obj, cls_id, field_id)
+                | Bytecode::LoadFieldDouble(dest, obj, cls_id, field_id)
+                | Bytecode::LoadFieldPtr(dest, obj, cls_id, field_id) => {
+                    self.emit_load_field(&bytecode, dest, obj, cls_id, field_id, pos)
+                }
+
+                Bytecode::StoreFieldBool(src, obj, cls_id, field_id)
+                | Bytecode::StoreFieldByte(src, obj, cls_id, field_id)
+                | Bytecode::StoreFieldChar(src, obj, cls_id, field_id)
+                | Bytecode::StoreFieldInt(src, obj, cls_id, field_id)
+                | Bytecode::StoreFieldLong(src, obj, cls_id, field_id)
+                | Bytecode::StoreFieldFloat(src, obj, cls_id, field_id)
+                | Bytecode::StoreFieldDouble(src, obj, cls_id, field_id)
+                | Bytecode::StoreFieldPtr(src, obj, cls_id, field_id) => {
+                    self.emit_store_field(&bytecode, src, obj, cls_id, field_id, pos)
+                }
+
+                Bytecode::LoadGlobalBool(dest, gid)
+                | Bytecode::LoadGlobalByte(dest, gid)
+                | Bytecode::LoadGlobalChar(dest, gid)
+                | Bytecode::LoadGlobalInt(dest, gid)
+                | Bytecode::LoadGlobalLong(dest, gid)
+                | Bytecode::LoadGlobalFloat(dest, gid)
+                | Bytecode::LoadGlobalDouble(dest, gid)
+                | Bytecode::LoadGlobalPtr(dest, gid) => self.emit_load_global(&bytecode, dest, gid),
+
+                Bytecode::StoreGlobalBool(src, gid)
+                | Bytecode::StoreGlobalByte(src, gid)
+                | Bytecode::StoreGlobalChar(src, gid)
+                | Bytecode::StoreGlobalInt(src, gid)
+                | Bytecode::StoreGlobalLong(src, gid)
+                | Bytecode::StoreGlobalFloat(src, gid)
+                | Bytecode::StoreGlobalDouble(src, gid)
+                | Bytecode::StoreGlobalPtr(src, gid) => self.emit_store_global(&bytecode, src, gid),
+
+                Bytecode::LoadArrayBool(dest, arr, idx)
+                | Bytecode::LoadArrayByte(dest, arr, idx)
+                | Bytecode::LoadArrayChar(dest, arr, idx)
+                | Bytecode::LoadArrayInt(dest, arr, idx)
+                | Bytecode::LoadArrayLong(dest, arr, idx)
+                | Bytecode::LoadArrayFloat(dest, arr, idx)
+                | Bytecode::LoadArrayDouble(dest, arr, idx)
+                | Bytecode::LoadArrayPtr(dest, arr, idx) => {
+                    self.emit_load_array(&bytecode, dest, arr, idx, pos)
+                }
+
+                Bytecode::StoreArrayBool(src, arr, idx)
+                | Bytecode::StoreArrayByte(src, arr, idx)
+                | Bytecode::StoreArrayChar(src, arr, idx)
+                | Bytecode::StoreArrayInt(src, arr, idx)
+                | Bytecode::StoreArrayLong(src, arr, idx)
+                | Bytecode::StoreArrayFloat(src, arr, idx)
+                | Bytecode::StoreArrayDouble(src, arr, idx)
+                | Bytecode::StoreArrayPtr(src, arr, idx) => {
+                    self.emit_store_array(&bytecode, src, arr, idx, pos)
+                }
+
+                Bytecode::ArrayLength(dest, arr) => {
+                    self.emit_array_length(&bytecode, dest, arr, pos)
+                }
+                Bytecode::SetUint8(address, value) => {
+                    self.emit_set_uint8(&bytecode, address, value)
+                }
+
+                Bytecode::ConstNil(dest) => self.emit_const_nil(&bytecode, dest),
+                Bytecode::ConstTrue(dest) => self.emit_const_bool(&bytecode, dest, true),
+                Bytecode::ConstFalse(dest) => self.emit_const_bool(&bytecode, dest, false),
                 Bytecode::ConstZeroByte(dest)
                 | Bytecode::ConstZeroInt(dest)
-                | Bytecode::ConstZeroLong(dest) => self.emit_const_int(&bytecode, *dest, 0),
+                | Bytecode::ConstZeroLong(dest) => self.emit_const_int(&bytecode, dest, 0),
                 Bytecode::ConstByte(dest, value) => {
-                    self.emit_const_int(&bytecode, *dest, *value as i64)
+                    self.emit_const_int(&bytecode, dest, value as i64)
                 }
                 Bytecode::ConstInt(dest, value) => {
-                    self.emit_const_int(&bytecode, *dest, *value as i64)
+                    self.emit_const_int(&bytecode, dest, value as i64)
                 }
                 Bytecode::ConstLong(dest, value) => {
-                    self.emit_const_int(&bytecode, *dest, *value as i64)
+                    self.emit_const_int(&bytecode, dest, value as i64)
                 }
                 Bytecode::ConstChar(dest, value) => {
-                    self.emit_const_int(&bytecode, *dest, *value as i64)
+                    self.emit_const_int(&bytecode, dest, value as i64)
                 }
                 Bytecode::ConstZeroFloat(dest) | Bytecode::ConstZeroDouble(dest) => {
-                    self.emit_const_float(&bytecode, *dest, 0_f64)
+                    self.emit_const_float(&bytecode, dest, 0_f64)
                 }
                 Bytecode::ConstFloat(dest, value) => {
-                    self.emit_const_float(&bytecode, *dest, *value as f64)
+                    self.emit_const_float(&bytecode, dest, value as f64)
+                }
+                Bytecode::ConstDouble(dest, value) => self.emit_const_float(&bytecode, dest, value),
+                Bytecode::ConstString(dest, sp) => self.emit_const_string(&bytecode, dest, sp),
+
+                Bytecode::TestEqPtr(dest, lhs, rhs)
+                | Bytecode::TestEqBool(dest, lhs, rhs)
+                | Bytecode::TestEqByte(dest, lhs, rhs)
+                | Bytecode::TestEqChar(dest, lhs, rhs)
+                | Bytecode::TestEqInt(dest, lhs, rhs)
+                | Bytecode::TestEqLong(dest, lhs, rhs) => {
+                    self.emit_test_int(&bytecode, dest, lhs, rhs, CondCode::Equal)
+                }
+                Bytecode::TestNePtr(dest, lhs, rhs)
+                | Bytecode::TestNeBool(dest, lhs, rhs)
+                | Bytecode::TestNeByte(dest, lhs, rhs)
+                | Bytecode::TestNeChar(dest, lhs, rhs)
+                | Bytecode::TestNeInt(dest, lhs, rhs)
+                | Bytecode::TestNeLong(dest, lhs, rhs) => {
+                    self.emit_test_int(&bytecode, dest, lhs, rhs, CondCode::NotEqual)
+                }
+                Bytecode::TestGtByte(dest, lhs, rhs)
+                | Bytecode::TestGtChar(dest, lhs, rhs)
+                | Bytecode::TestGtInt(dest, lhs, rhs)
+                | Bytecode::TestGtLong(dest, lhs, rhs) => {
+                    self.emit_test_int(&bytecode, dest, lhs, rhs, CondCode::Greater)
+                }
+                Bytecode::TestGeByte(dest, lhs, rhs)
+                | Bytecode::TestGeChar(dest, lhs, rhs)
+                | Bytecode::TestGeInt(dest, lhs, rhs)
+                | Bytecode::TestGeLong(dest, lhs, rhs) => {
+                    self.emit_test_int(&bytecode, dest, lhs, rhs, CondCode::GreaterEq)
                 }
-                Bytecode::ConstDouble(dest, value) => {
-                    self.emit_const_float(&bytecode, *dest, *value)
+                Bytecode::TestLtByte(dest, lhs, rhs)
+                | Bytecode::TestLtChar(dest, lhs, rhs)
+                | Bytecode::TestLtInt(dest, lhs, rhs)
+                | Bytecode::TestLtLong(dest, lhs, rhs) => {
+                    self.emit_test_int(&bytecode, dest, lhs, rhs, CondCode::Less)
+                }
+                Bytecode::TestLeByte(dest, lhs, rhs)
+                | Bytecode::TestLeChar(dest, lhs, rhs)
+                | Bytecode::TestLeInt(dest, lhs, rhs)
+                | Bytecode::TestLeLong(dest, lhs, rhs) => {
+                    self.emit_test_int(&bytecode, dest, lhs, rhs, CondCode::LessEq)
+                }
+
+                Bytecode::TestEqFloat(dest, lhs, rhs) | Bytecode::TestEqDouble(dest, lhs, rhs) => {
+                    self.emit_test_float(&bytecode, dest, lhs, rhs, CondCode::Equal)
                 }
-                Bytecode::ConstString(dest, sp) => {
-                    self.emit_const_string(&bytecode, *dest, *sp);
+                Bytecode::TestNeFloat(dest, lhs, rhs) | Bytecode::TestNeDouble(dest, lhs, rhs) => {
+                    self.emit_test_float(&bytecode, dest, lhs, rhs, CondCode::NotEqual)
                 }
-                Bytecode::RetVoid => {}
-                _ => panic!("bytecode {:?} not implemented", btcode),
+                Bytecode::TestGtFloat(dest, lhs, rhs) | Bytecode::TestGtDouble(dest, lhs, rhs) => {
+                    self.emit_test_float(&bytecode, dest, lhs, rhs, CondCode::Greater)
+                }
+                Bytecode::TestGeFloat(dest, lhs, rhs) | Bytecode::TestGeDouble(dest, lhs, rhs) => {
+                    self.emit_test_float(&bytecode, dest, lhs, rhs, CondCode::GreaterEq)
+                }
+                Bytecode::TestLtFloat(dest, lhs, rhs) | Bytecode::TestLtDouble(dest, lhs, rhs) => {
+                    self.emit_test_float(&bytecode, dest, lhs, rhs, CondCode::Less)
+                }
+                Bytecode::TestLeFloat(dest, lhs, rhs) | Bytecode::TestLeDouble(dest, lhs, rhs) => {
+                    self.emit_test_float(&bytecode, dest, lhs, rhs, CondCode::LessEq)
+                }
+
+                Bytecode::JumpIfFalse(opnd, target) => {
+                    if target.0 <= idx {
+                        self.emit_safepoint(&bytecode, pos);
+                    }
+
+                    self.emit_jump_if(&bytecode, opnd, CondCode::Zero, labels[target.0]);
+                }
+                Bytecode::JumpIfTrue(opnd, target) => {
+                    if target.0 <= idx {
+                        self.emit_safepoint(&bytecode, pos);
+                    }
+
+                    self.emit_jump_if(&bytecode, opnd, CondCode::NonZero, labels[target.0]);
+                }
+                Bytecode::Jump(target) => {
+                    // backward jumps close a loop and need to check for safepoints
+                    if target.0 <= idx {
+                        self.emit_safepoint(&bytecode, pos);
+                    }
+
+                    self.asm.jump(labels[target.0]);
+                }
+
+                Bytecode::InvokeDirectVoid(fct_id, start, num)
+                | Bytecode::InvokeStaticVoid(fct_id, start, num) => self.emit_invoke_direct(
+                    &bytecode,
+                    bytecode_idx,
+                    None,
+                    fct_id,
+                    start,
+                    num,
+                    stacksize,
+                    pos,
+                ),
+                Bytecode::InvokeDirectBool(dest, fct_id, start, num)
+                | Bytecode::InvokeDirectByte(dest, fct_id, start, num)
+                | Bytecode::InvokeDirectChar(dest, fct_id, start, num)
+                | Bytecode::InvokeDirectInt(dest, fct_id, start, num)
+                | Bytecode::InvokeDirectLong(dest, fct_id, start, num)
+                | Bytecode::InvokeDirectFloat(dest, fct_id, start, num)
+                | Bytecode::InvokeDirectDouble(dest, fct_id, start, num)
+                | Bytecode::InvokeDirectPtr(dest, fct_id, start, num)
+                | Bytecode::InvokeStaticBool(dest, fct_id, start, num)
+                | Bytecode::InvokeStaticByte(dest, fct_id, start, num)
+                | Bytecode::InvokeStaticChar(dest, fct_id, start, num)
+                | Bytecode::InvokeStaticInt(dest, fct_id, start, num)
+                | Bytecode::InvokeStaticLong(dest, fct_id, start, num)
+                | Bytecode::InvokeStaticFloat(dest, fct_id, start, num)
+                | Bytecode::InvokeStaticDouble(dest, fct_id, start, num)
+                | Bytecode::InvokeStaticPtr(dest, fct_id, start, num) => self.emit_invoke_direct(
+                    &bytecode,
+                    bytecode_idx,
+                    Some(dest),
+                    fct_id,
+                    start,
+                    num,
+                    stacksize,
+                    pos,
+                ),
+
+                Bytecode::InvokeVirtualVoid(fct_id, start, num) => {
+                    self.emit_invoke_virtual(&bytecode, None, fct_id, start, num, stacksize, pos)
+                }
+                Bytecode::InvokeVirtualBool(dest, fct_id, start, num)
+                | Bytecode::InvokeVirtualByte(dest, fct_id, start, num)
+                | Bytecode::InvokeVirtualChar(dest, fct_id, start, num)
+                | Bytecode::InvokeVirtualInt(dest, fct_id, start, num)
+                | Bytecode::InvokeVirtualLong(dest, fct_id, start, num)
+                | Bytecode::InvokeVirtualFloat(dest, fct_id, start, num)
+                | Bytecode::InvokeVirtualDouble(dest, fct_id, start, num)
+                | Bytecode::InvokeVirtualPtr(dest, fct_id, start, num) => self.emit_invoke_virtual(
+                    &bytecode,
+                    Some(dest),
+                    fct_id,
+                    start,
+                    num,
+                    stacksize,
+                    pos,
+                ),
+
+                Bytecode::InvokeLambdaVoid(start, num) => {
+                    self.emit_invoke_lambda(&bytecode, None, start, num, stacksize, pos)
+                }
+                Bytecode::InvokeLambdaBool(dest, start, num)
+                | Bytecode::InvokeLambdaByte(dest, start, num)
+                | Bytecode::InvokeLambdaChar(dest, start, num)
+                | Bytecode::InvokeLambdaInt(dest, start, num)
+                | Bytecode::InvokeLambdaLong(dest, start, num)
+                | Bytecode::InvokeLambdaFloat(dest, start, num)
+                | Bytecode::InvokeLambdaDouble(dest, start, num)
+                | Bytecode::InvokeLambdaPtr(dest, start, num) => {
+                    self.emit_invoke_lambda(&bytecode, Some(dest), start, num, stacksize, pos)
+                }
+
+                Bytecode::NewObject(dest, cls_id) => {
+                    self.emit_allocation(&bytecode, dest, cls_id, None, pos)
+                }
+                Bytecode::NewArray(dest, cls_id, length) => {
+                    self.emit_allocation(&bytecode, dest, cls_id, Some(length), pos)
+                }
+
+                Bytecode::NilCheck(obj) => self.emit_nil_check(&bytecode, obj, pos),
+                Bytecode::InstanceOf(dest, obj, cls_id) => {
+                    self.emit_instance_of(&bytecode, Some(dest), obj, cls_id, pos)
+                }
+                Bytecode::CheckedCast(obj, cls_id) => {
+                    self.emit_instance_of(&bytecode, None, obj, cls_id, pos)
+                }
+
+                Bytecode::Throw(exception) => self.emit_throw(&bytecode, exception, pos),
+                Bytecode::Trap(trap) => self.emit_trap(&bytecode, trap, pos),
+                Bytecode::Spawn(closure) => self.emit_spawn(&bytecode, closure, pos),
+                Bytecode::Debug => self.asm.debug(),
+
+                Bytecode::RetBool(value)
+                | Bytecode::RetByte(value)
+                | Bytecode::RetChar(value)
+                | Bytecode::RetInt(value)
+                | Bytecode::RetLong(value)
+                | Bytecode::RetFloat(value)
+                | Bytecode::RetDouble(value)
+                | Bytecode::RetPtr(value) => self.emit_return(&bytecode, Some(value), stacksize),
+                Bytecode::RetVoid => self.emit_return(&bytecode, None, stacksize),
             }
         }
 
-        self.emit_epilog(&bytecode);
+        self.asm.bind_label(labels[code.len()]);
+        code_offsets.push(self.asm.pos());
+
+        self.emit_exception_handlers(&bytecode, &code_offsets);
 
         let jit_fct = self.asm.jit(
-            bytecode.stacksize(),
+            stacksize,
             JitDescriptor::DoraFct(self.fct.id),
             self.ast.throws,
         );
@@ -197,10 +1568,97 @@ impl<'a, 'ast> CodeGen<'ast> for CannonCodeGen<'a, 'ast> {
     }
 }
 
-fn should_emit_bytecode(vm: &VM, fct: &Fct) -> bool {
-    if let Some(ref dbg_names) = vm.args.flag_emit_bytecode {
-        fct_pattern_match(vm, fct, dbg_names)
-    } else {
-        false
+// all registers of type ptr are stored in the gc point,
+// the prolog initializes them with nil
+fn create_gcpoint(bytecode: &BytecodeFunction) -> GcPoint {
+    let offsets = bytecode
+        .registers()
+        .iter()
+        .enumerate()
+        .filter(|&(_, &ty)| ty == BytecodeType::Ptr)
+        .map(|(idx, _)| bytecode.offset(Register(idx)))
+        .collect::<Vec<_>>();
+
+    GcPoint::from_offsets(offsets)
+}
+
+// size of the stack area needed for arguments not passed in registers
+fn argsize(bytecode: &BytecodeFunction) -> i32 {
+    let mut argsize = 0;
+
+    for btcode in bytecode.code() {
+        let (start, num) = match *btcode {
+            Bytecode::InvokeDirectVoid(_, start, num)
+            | Bytecode::InvokeDirectBool(_, _, start, num)
+            | Bytecode::InvokeDirectByte(_, _, start, num)
+            | Bytecode::InvokeDirectChar(_, _, start, num)
+            | Bytecode::InvokeDirectInt(_, _, start, num)
+            | Bytecode::InvokeDirectLong(_, _, start, num)
+            | Bytecode::InvokeDirectFloat(_, _, start, num)
+            | Bytecode::InvokeDirectDouble(_, _, start, num)
+            | Bytecode::InvokeDirectPtr(_, _, start, num)
+            | Bytecode::InvokeVirtualVoid(_, start, num)
+            | Bytecode::InvokeVirtualBool(_, _, start, num)
+            | Bytecode::InvokeVirtualByte(_, _, start, num)
+            | Bytecode::InvokeVirtualChar(_, _, start, num)
+            | Bytecode::InvokeVirtualInt(_, _, start, num)
+            | Bytecode::InvokeVirtualLong(_, _, start, num)
+            | Bytecode::InvokeVirtualFloat(_, _, start, num)
+            | Bytecode::InvokeVirtualDouble(_, _, start, num)
+            | Bytecode::InvokeVirtualPtr(_, _, start, num)
+            | Bytecode::InvokeStaticVoid(_, start, num)
+            | Bytecode::InvokeStaticBool(_, _, start, num)
+            | Bytecode::InvokeStaticByte(_, _, start, num)
+            | Bytecode::InvokeStaticChar(_, _, start, num)
+            | Bytecode::InvokeStaticInt(_, _, start, num)
+            | Bytecode::InvokeStaticLong(_, _, start, num)
+            | Bytecode::InvokeStaticFloat(_, _, start, num)
+            | Bytecode::InvokeStaticDouble(_, _, start, num)
+            | Bytecode::InvokeStaticPtr(_, _, start, num)
+            | Bytecode::InvokeLambdaVoid(start, num)
+            | Bytecode::InvokeLambdaBool(_, start, num)
+            | Bytecode::InvokeLambdaByte(_, start, num)
+            | Bytecode::InvokeLambdaChar(_, start, num)
+            | Bytecode::InvokeLambdaInt(_, start, num)
+            | Bytecode::InvokeLambdaLong(_, start, num)
+            | Bytecode::InvokeLambdaFloat(_, start, num)
+            | Bytecode::InvokeLambdaDouble(_, start, num)
+            | Bytecode::InvokeLambdaPtr(_, start, num) => (start, num),
+            _ => continue,
+        };
+
+        let mut reg_args: usize = 0;
+        let mut freg_args: usize = 0;
+
+        for idx in 0..num {
+            if bytecode.register(start.offset(idx)).mode().is_float() {
+                freg_args += 1;
+            } else {
+                reg_args += 1;
+            }
+        }
+
+        let args_on_stack =
+            reg_args.saturating_sub(REG_PARAMS.len()) + freg_args.saturating_sub(FREG_PARAMS.len());
+        argsize = argsize.max(8 * args_on_stack as i32);
     }
+
+    argsize
+}
+
+// machine register and type used for the result of a call
+fn call_result(bytecode: &BytecodeFunction, dest: Option<Register>) -> (BuiltinType, ExprStore) {
+    let ty = match dest.map(|dest| bytecode.register(dest)) {
+        None => return (BuiltinType::Unit, REG_RESULT.into()),
+        Some(BytecodeType::Bool) => BuiltinType::Bool,
+        Some(BytecodeType::Byte) => BuiltinType::Byte,
+        Some(BytecodeType::Char) => BuiltinType::Char,
+        Some(BytecodeType::Int) => BuiltinType::Int,
+        Some(BytecodeType::Long) => BuiltinType::Long,
+        Some(BytecodeType::Float) => BuiltinType::Float,
+        Some(BytecodeType::Double) => BuiltinType::Double,
+        Some(BytecodeType::Ptr) => BuiltinType::Ptr,
+    };
+
+    (ty, register_for_mode(ty.mode()))
 }
@@ -12,9 +12,14 @@ use capstone::{Engine, Error};
 use crate::baseline::asm::BaselineAssembler;
 use crate::baseline::ast::{generate_info, AstCodeGen, JitInfo};
 use crate::baseline::cannon::CannonCodeGen;
-use crate::baseline::dora_native::{self, InternalFct};
-use crate::baseline::fct::{CommentFormat, GcPoint, JitBaselineFct, JitFct};
+use crate::baseline::dora_interp;
+use crate::baseline::dora_native::{self, InternalFct, InternalFctDescriptor};
+use crate::baseline::fct::{CommentFormat, GcPoint, JitBaselineFct, JitFct, JitFctId};
 use crate::baseline::map::CodeDescriptor;
+use crate::baseline::queue;
+use crate::baseline::tier;
+use crate::boots;
+use crate::bytecode::astgen;
 use crate::class::TypeParams;
 use crate::cpu::x64::reg::{FREG_RESULT, REG_RESULT};
 use crate::cpu::{FReg, Reg};
@@ -22,10 +27,11 @@ use crate::driver::cmd::{AsmSyntax, BaselineName};
 use crate::gc::Address;
 use crate::masm::*;
 use crate::mem;
+use crate::opt;
 use crate::os;
 use crate::ty::MachineMode;
 use crate::vm::VM;
-use crate::vm::{Fct, FctId, FctSrc, VarId};
+use crate::vm::{Fct, FctId, FctKind, FctSrc, VarId};
 
 pub fn generate<'ast>(
     vm: &VM<'ast>,
@@ -65,65 +71,55 @@ pub fn generate_fct<'ast>(
         }
     }
 
-    let ast = fct.ast;
+    if should_emit_boots(vm, fct) {
+        boots::emit(vm, fct, src, cls_type_params, fct_type_params);
+    }
 
-    let jit_fct = match vm.args.bc() {
-        BaselineName::Cannon => CannonCodeGen {
-            vm: vm,
-            fct: &fct,
-            ast: ast,
-            asm: BaselineAssembler::new(vm),
-            scopes: Scopes::new(),
-            src: src,
-
-            lbl_break: None,
-            lbl_continue: None,
-
-            active_finallys: Vec::new(),
-            active_upper: None,
-            active_loop: None,
-            lbl_return: None,
-
-            cls_type_params: cls_type_params,
-            fct_type_params: fct_type_params,
-        }
-        .generate(),
-        BaselineName::AstCompiler => {
-            let mut jit_info = JitInfo::new();
-            generate_info(
-                vm,
-                fct,
-                src,
-                &mut jit_info,
-                cls_type_params,
-                fct_type_params,
-            );
-            AstCodeGen {
-                vm: vm,
-                fct: &fct,
-                ast: ast,
-                asm: BaselineAssembler::new(vm),
-                scopes: Scopes::new(),
-                src: src,
-                jit_info: jit_info,
+    let jit_fct = match opt::generate(vm, fct, src, cls_type_params, fct_type_params) {
+        Some(jit_fct) => JitFct::Opt(jit_fct),
+        None => JitFct::Base(generate_baseline(
+            vm,
+            fct,
+            src,
+            cls_type_params,
+            fct_type_params,
+        )),
+    };
 
-                lbl_break: None,
-                lbl_continue: None,
+    install_fct(vm, fct, src, jit_fct, cls_type_params, fct_type_params)
+}
 
-                active_finallys: Vec::new(),
-                active_upper: None,
-                active_loop: None,
-                lbl_return: None,
+// registers the compiled code and makes it the implementation of the given
+// specialization, returns the address of the installed function
+pub fn install_fct<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &FctSrc,
+    jit_fct: JitFct,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+) -> Address {
+    let (jit_fct_id, fct_ptr) = register_fct(vm, fct, src, jit_fct);
 
-                cls_type_params: cls_type_params,
-                fct_type_params: fct_type_params,
-            }
-            .generate()
-        }
-    };
+    {
+        let mut specials = src.specializations.write();
+        let key = (cls_type_params.clone(), fct_type_params.clone());
+        specials.insert(key, jit_fct_id);
+    }
 
+    fct_ptr
+}
+
+// registers the compiled code without using it for calls of the function,
+// OSR entries are only invoked from the baseline code
+pub fn register_fct<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &FctSrc,
+    jit_fct: JitFct,
+) -> (JitFctId, Address) {
     if vm.args.flag_enable_perf {
-        os::perf::register_with_perf(&jit_fct, vm, ast.name);
+        os::perf::register_with_perf(&jit_fct, vm, fct.ast.name);
     }
 
     if should_emit_asm(vm, &*fct) {
@@ -139,6 +135,7 @@ pub fn generate_fct<'ast>(
     let fct_ptr = jit_fct.fct_ptr();
     let ptr_start = jit_fct.ptr_start();
     let ptr_end = jit_fct.ptr_end();
+    let is_opt = jit_fct.to_base().is_none();
 
     debug_assert!(mem::is_aligned(ptr_start.to_usize(), 16));
     debug_assert!(mem::is_aligned(fct_ptr.to_usize(), 16));
@@ -146,24 +143,100 @@ pub fn generate_fct<'ast>(
     let jit_fct_id = {
         let mut jit_fcts = vm.jit_fcts.lock();
         let jit_fct_id = jit_fcts.len().into();
-        jit_fcts.push(Arc::new(JitFct::Base(jit_fct)));
+        jit_fcts.push(Arc::new(jit_fct));
 
         jit_fct_id
     };
 
-    {
-        let mut specials = src.specializations.write();
-        let key = (cls_type_params.clone(), fct_type_params.clone());
-        specials.insert(key, jit_fct_id);
-    }
-
     {
         let mut code_map = vm.code_map.lock();
-        let cdata = CodeDescriptor::DoraFct(jit_fct_id);
+        let cdata = match (vm.args.bc(), is_opt) {
+            (BaselineName::Interpreter, false) => CodeDescriptor::InterpThunk(jit_fct_id),
+            _ => CodeDescriptor::DoraFct(jit_fct_id),
+        };
         code_map.insert(ptr_start, ptr_end, cdata);
     }
 
-    fct_ptr
+    (jit_fct_id, fct_ptr)
+}
+
+fn generate_baseline<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &mut FctSrc,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+) -> JitBaselineFct {
+    let ast = fct.ast;
+
+    match vm.args.bc() {
+        BaselineName::Cannon => {
+            let hotness_counters =
+                tier::create_counters(vm, fct, src, cls_type_params, fct_type_params, None);
+            CannonCodeGen {
+                vm: vm,
+                fct: &fct,
+                ast: ast,
+                asm: BaselineAssembler::new(vm),
+                src: src,
+                hotness_counters: hotness_counters,
+                deopt_entry: None,
+
+                cls_type_params: cls_type_params,
+                fct_type_params: fct_type_params,
+            }
+            .generate()
+        }
+        BaselineName::Interpreter => {
+            dora_interp::generate(vm, fct, src, cls_type_params, fct_type_params)
+        }
+        BaselineName::AstCompiler => {
+            if should_emit_bytecode(vm, fct) {
+                let bytecode = astgen::generate_fct(vm, fct, src, cls_type_params, fct_type_params);
+                bytecode.dump();
+            }
+
+            let mut jit_info = JitInfo::new();
+            generate_info(
+                vm,
+                fct,
+                src,
+                &mut jit_info,
+                cls_type_params,
+                fct_type_params,
+            );
+            let hotness_counters = tier::create_counters(
+                vm,
+                fct,
+                src,
+                cls_type_params,
+                fct_type_params,
+                Some(jit_info.map_var_offsets.clone()),
+            );
+            AstCodeGen {
+                vm: vm,
+                fct: &fct,
+                ast: ast,
+                asm: BaselineAssembler::new(vm),
+                scopes: Scopes::new(),
+                src: src,
+                jit_info: jit_info,
+                hotness_counters: hotness_counters,
+
+                lbl_break: None,
+                lbl_continue: None,
+
+                active_finallys: Vec::new(),
+                active_upper: None,
+                active_loop: None,
+                lbl_return: None,
+
+                cls_type_params: cls_type_params,
+                fct_type_params: fct_type_params,
+            }
+            .generate()
+        }
+    }
 }
 
 #[cfg(target_arch = "x86_64")]
@@ -183,7 +256,7 @@ fn get_engine() -> Result<Engine, Error> {
 pub fn dump_asm<'ast>(
     vm: &VM<'ast>,
     fct: &Fct<'ast>,
-    jit_fct: &JitBaselineFct,
+    jit_fct: &JitFct,
     fct_src: Option<&FctSrc>,
     asm_syntax: AsmSyntax,
 ) {
@@ -414,6 +487,22 @@ pub fn should_emit_debug(vm: &VM, fct: &Fct) -> bool {
     }
 }
 
+pub fn should_emit_bytecode(vm: &VM, fct: &Fct) -> bool {
+    if let Some(ref dbg_names) = vm.args.flag_emit_bytecode {
+        fct_pattern_match(vm, fct, dbg_names)
+    } else {
+        false
+    }
+}
+
+pub fn should_emit_boots(vm: &VM, fct: &Fct) -> bool {
+    if let Some(ref dbg_names) = vm.args.flag_emit_boots {
+        fct_pattern_match(vm, fct, dbg_names)
+    } else {
+        false
+    }
+}
+
 pub fn should_emit_asm(vm: &VM, fct: &Fct) -> bool {
     if let Some(ref dbg_names) = vm.args.flag_emit_asm {
         fct_pattern_match(vm, fct, dbg_names)
@@ -495,6 +584,85 @@ pub enum AllocationSize {
     Dynamic(Reg),
 }
 
+pub fn ptr_for_fct_id(
+    vm: &VM,
+    fct: &Fct,
+    src: &mut FctSrc,
+    fid: FctId,
+    cls_type_params: TypeParams,
+    fct_type_params: TypeParams,
+) -> Address {
+    if fct.id == fid {
+        // we want to recursively invoke the function we are compiling right now
+        ensure_jit_or_stub_ptr(src, vm, cls_type_params, fct_type_params)
+    } else {
+        let fct = vm.fcts.idx(fid);
+        let fct = fct.read();
+
+        match fct.kind {
+            FctKind::Source(_) => {
+                // a compiler thread doesn't wait for a callee compiled by another
+                // thread right now, that thread might wait for this function as well
+                let mut src = if queue::is_compiler_thread() {
+                    match fct.src().try_write() {
+                        Some(src) => src,
+                        None => return vm.compiler_thunk(),
+                    }
+                } else {
+                    fct.src().write()
+                };
+
+                let ptr = ensure_jit_or_stub_ptr(
+                    &mut src,
+                    vm,
+                    cls_type_params.clone(),
+                    fct_type_params.clone(),
+                );
+
+                // compile the callee before its first invocation if possible
+                if ptr == vm.compiler_thunk() {
+                    vm.compile_queue
+                        .compile(fid, &cls_type_params, &fct_type_params);
+                }
+
+                ptr
+            }
+
+            FctKind::Native(ptr) => {
+                let internal_fct = InternalFct {
+                    ptr: ptr,
+                    args: fct.params_with_self(),
+                    return_type: fct.return_type,
+                    throws: fct.ast.throws,
+                    desc: InternalFctDescriptor::NativeThunk(fid),
+                };
+
+                ensure_native_stub(vm, fid, internal_fct)
+            }
+
+            FctKind::Definition => panic!("prototype for fct call"),
+            FctKind::Builtin(_) => panic!("intrinsic fct call"),
+        }
+    }
+}
+
+fn ensure_jit_or_stub_ptr(
+    src: &mut FctSrc,
+    vm: &VM,
+    cls_type_params: TypeParams,
+    fct_type_params: TypeParams,
+) -> Address {
+    let specials = src.specializations.read();
+    let key = (cls_type_params, fct_type_params);
+
+    if let Some(&jit_fct_id) = specials.get(&key) {
+        let jit_fct = vm.jit_fcts.idx(jit_fct_id);
+        return jit_fct.fct_ptr();
+    }
+
+    vm.compiler_thunk()
+}
+
 pub fn ensure_native_stub(vm: &VM, fct_id: FctId, internal_fct: InternalFct) -> Address {
     let mut native_thunks = vm.native_thunks.lock();
     let ptr = internal_fct.ptr;
@@ -509,9 +677,7 @@ pub fn ensure_native_stub(vm: &VM, fct_id: FctId, internal_fct: InternalFct) ->
 
         let jit_fct_id = dora_native::generate(vm, internal_fct, dbg);
         let jit_fct = vm.jit_fcts.idx(jit_fct_id);
-        let jit_fct = jit_fct.to_base().expect("baseline expected");
-
-        let fct_start = jit_fct.fct_start;
+        let fct_start = jit_fct.fct_ptr();
 
         if should_emit_asm(vm, &*fct) {
             dump_asm(
@@ -0,0 +1,113 @@
+use std::cell::RefCell;
+use std::ptr;
+
+use crate::baseline::asm::BaselineAssembler;
+use crate::baseline::cannon::CannonCodeGen;
+use crate::baseline::codegen::{self, CodeGen};
+use crate::baseline::fct::{BailoutInfo, DeoptInfo, JitFct, JitFctId};
+use crate::baseline::map::CodeDescriptor;
+use crate::baseline::tier;
+use crate::gc::Address;
+use crate::vm::{get_vm, Fct, FctSrc, VM};
+
+thread_local! {
+    // values passed from the optimized frame to the baseline code
+    static DEOPT_BUFFER: RefCell<Vec<u64>> = RefCell::new(Vec::new());
+}
+
+// invoked from optimized code when one of its speculative assumptions failed: looks
+// up the frame state at offset `offset` of the optimized code containing `pc` and
+// returns a buffer with the address of baseline code entered at the instruction of
+// the frame state and the values of the bytecode registers read from the frame at
+// `fp`. The optimized code then replaces its frame with the baseline frame.
+pub extern "C" fn deoptimize(fp: *const u8, pc: usize, offset: i32) -> *const u64 {
+    let vm = get_vm();
+
+    let (jit_fct_id, info) = {
+        let data = {
+            let code_map = vm.code_map.lock();
+            code_map.get(pc.into()).expect("pc not found")
+        };
+
+        let jit_fct_id = match data {
+            CodeDescriptor::DoraFct(jit_fct_id) => jit_fct_id,
+            _ => panic!("expected function for code"),
+        };
+
+        let jit_fct = vm.jit_fcts.idx(jit_fct_id);
+
+        match jit_fct.bailout_for_offset(offset) {
+            Some(&BailoutInfo::Deopt(ref info)) => (jit_fct_id, info.clone()),
+            _ => panic!("deoptimization info not found"),
+        }
+    };
+
+    let fct = vm.fcts.idx(info.fct_id);
+    let fct = fct.read();
+
+    if vm.args.flag_opt_verbose {
+        println!(
+            "OPT: deoptimized {} at {}",
+            fct.full_name(vm),
+            info.bytecode_idx.0
+        );
+    }
+
+    let entry = {
+        let src = fct.src();
+        let mut src = src.write();
+        tier::deoptimized(&src, &info.cls_type_params, &info.fct_type_params);
+        deopt_entry(vm, &fct, &mut src, jit_fct_id, offset, &info)
+    };
+
+    // the frame is only read after compilation, a collection might have moved objects
+    DEOPT_BUFFER.with(|buffer| {
+        let mut buffer = buffer.borrow_mut();
+        buffer.clear();
+        buffer.push(entry.to_usize() as u64);
+
+        for &(_, offset) in &info.registers {
+            // slots are copied as a whole, the entry only loads the bytes of the register
+            let value = unsafe { ptr::read_unaligned(fp.offset(offset as isize) as *const u64) };
+            buffer.push(value);
+        }
+
+        buffer.as_ptr()
+    })
+}
+
+// baseline code entered at the instruction of the frame state, the code is
+// generated once for every deoptimization point
+fn deopt_entry<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &mut FctSrc,
+    jit_fct_id: JitFctId,
+    offset: i32,
+    info: &DeoptInfo,
+) -> Address {
+    if let Some(&entry) = src.deopt_entries.get(&(jit_fct_id, offset)) {
+        return entry;
+    }
+
+    let registers = info.registers.iter().map(|&(reg, _)| reg).collect();
+
+    let jit_fct = CannonCodeGen {
+        vm: vm,
+        fct: fct,
+        ast: fct.ast,
+        asm: BaselineAssembler::new(vm),
+        src: src,
+        hotness_counters: None,
+        deopt_entry: Some((info.bytecode_idx, registers)),
+
+        cls_type_params: &info.cls_type_params,
+        fct_type_params: &info.fct_type_params,
+    }
+    .generate();
+
+    let (_, entry) = codegen::register_fct(vm, fct, src, JitFct::Base(jit_fct));
+    src.deopt_entries.insert((jit_fct_id, offset), entry);
+
+    entry
+}
@@ -3,6 +3,7 @@ use std::mem::size_of;
 use crate::baseline;
 use crate::baseline::dora_native::{finish_native_call, start_native_call};
 use crate::baseline::fct::{BailoutInfo, JitBaselineFct, JitDescriptor, JitFct};
+use crate::baseline::ic;
 use crate::baseline::map::CodeDescriptor;
 use crate::class::TypeParams;
 use crate::cpu::{Mem, FREG_PARAMS, REG_FP, REG_PARAMS, REG_RESULT, REG_SP, REG_THREAD, REG_TMP1};
@@ -190,10 +191,8 @@ fn compile_request(ra: usize, receiver: Address) -> Address {
         let jit_fct = vm.jit_fcts.idx(fct_id);
 
         let offset = ra - jit_fct.fct_ptr().to_usize();
-        let jit_fct = jit_fct.to_base().expect("baseline expected");
         jit_fct
-            .bailouts
-            .get(offset as i32)
+            .bailout_for_offset(offset as i32)
             .expect("bailout info not found")
             .clone()
     };
@@ -206,10 +205,14 @@ fn compile_request(ra: usize, receiver: Address) -> Address {
         BailoutInfo::VirtCompile(vtable_index, ref fct_tps) => {
             patch_vtable_call(vm, receiver, vtable_index, fct_tps)
         }
+
+        BailoutInfo::InlineCache(cache) => ic::resolve(vm, cache, receiver),
+
+        BailoutInfo::Deopt(_) => panic!("deoptimization point at call site"),
     }
 }
 
-fn patch_vtable_call(
+pub fn patch_vtable_call(
     vm: &VM,
     receiver: Address,
     vtable_index: u32,
@@ -217,6 +220,17 @@ fn patch_vtable_call(
 ) -> Address {
     let obj = unsafe { &mut *receiver.to_mut_ptr::<Obj>() };
     let vtable = obj.header().vtbl();
+
+    // closure objects store the lambda to call in their class
+    if let Some((fct_id, ref cls_tps, ref fct_tps)) = vtable.class().lambda {
+        let fct_ptr = baseline::generate(vm, fct_id, cls_tps, fct_tps);
+
+        let methodtable = vtable.table_mut();
+        methodtable[vtable_index as usize] = fct_ptr.to_usize();
+
+        return fct_ptr;
+    }
+
     let cls_id = vtable.class().cls_id.expect("no corresponding class");
     let cls = vm.classes.idx(cls_id);
     let cls = cls.read();
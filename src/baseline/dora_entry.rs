@@ -1,6 +1,6 @@
 use crate::baseline::fct::{JitBaselineFct, JitDescriptor, JitFct};
 use crate::baseline::map::CodeDescriptor;
-use crate::cpu::{Mem, REG_PARAMS, REG_SP, REG_THREAD, REG_TMP1};
+use crate::cpu::{Mem, REG_ALLOCATABLE, REG_PARAMS, REG_SP, REG_THREAD, REG_TMP1};
 use crate::gc::Address;
 use crate::masm::MacroAssembler;
 use crate::mem;
@@ -38,10 +38,12 @@ where
     'ast: 'a,
 {
     pub fn generate(mut self) -> JitBaselineFct {
-        let framesize = mem::ptr_width_usize();
+        // thread register followed by the callee-saved registers used by optimized code
+        let framesize = (1 + REG_ALLOCATABLE.len()) * mem::ptr_width_usize();
         let framesize = mem::align_usize(framesize, 16) as i32;
 
         let offset_thread = 0;
+        let offset_saved = mem::ptr_width();
 
         if self.dbg {
             self.masm.debug();
@@ -54,6 +56,12 @@ where
             REG_THREAD.into(),
         );
 
+        for (idx, &reg) in REG_ALLOCATABLE.iter().enumerate() {
+            let offset = offset_saved + idx as i32 * mem::ptr_width();
+            self.masm
+                .store_mem(MachineMode::Ptr, Mem::Base(REG_SP, offset), reg.into());
+        }
+
         self.masm
             .copy_reg(MachineMode::Ptr, REG_THREAD, REG_PARAMS[0]);
         self.masm
@@ -67,6 +75,13 @@ where
             REG_THREAD.into(),
             Mem::Base(REG_SP, offset_thread),
         );
+
+        for (idx, &reg) in REG_ALLOCATABLE.iter().enumerate() {
+            let offset = offset_saved + idx as i32 * mem::ptr_width();
+            self.masm
+                .load_mem(MachineMode::Ptr, reg.into(), Mem::Base(REG_SP, offset));
+        }
+
         self.masm.epilog(framesize);
 
         self.masm
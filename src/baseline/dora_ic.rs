@@ -0,0 +1,27 @@
+use crate::baseline::fct::{JitDescriptor, JitFct};
+use crate::baseline::map::CodeDescriptor;
+use crate::gc::Address;
+use crate::masm::MacroAssembler;
+use crate::vm::VM;
+
+// This code generates the inline cache stub, there is only one instance of it.
+// Virtual call sites in baseline code call it whenever the receiver's class is not
+// in the first entry of the call site's inline cache. The stub dispatches through
+// the other entries of the cache without creating a frame, receivers missing in the
+// cache are passed on to the compiler thunk, which adds them to the cache.
+
+pub fn generate<'a, 'ast: 'a>(vm: &'a VM<'ast>) -> Address {
+    let mut masm = MacroAssembler::new();
+    masm.inline_cache_stub(vm.compiler_thunk());
+
+    let jit_fct = masm.jit(vm, 0, JitDescriptor::InlineCacheStub, false);
+    let addr = jit_fct.fct_ptr();
+    vm.insert_code_map(
+        jit_fct.ptr_start(),
+        jit_fct.ptr_end(),
+        CodeDescriptor::InlineCacheStub,
+    );
+    vm.jit_fcts.push(JitFct::Base(jit_fct));
+
+    addr
+}
@@ -0,0 +1,205 @@
+use std::mem::size_of;
+
+use crate::baseline::codegen::{should_emit_bytecode, should_emit_debug};
+use crate::baseline::dora_native::{finish_native_call, start_native_call};
+use crate::baseline::fct::{JitBaselineFct, JitDescriptor};
+use crate::bytecode::generate::Register;
+use crate::bytecode::interpreter::{self, InterpFct};
+use crate::class::TypeParams;
+use crate::cpu::{
+    Mem, FREG_PARAMS, FREG_RESULT, PARAM_OFFSET, REG_FP, REG_PARAMS, REG_RESULT, REG_SP,
+    REG_THREAD, REG_TMP1, REG_TMP2,
+};
+use crate::exception::DoraToNativeInfo;
+use crate::masm::MacroAssembler;
+use crate::mem;
+use crate::semck::specialize::specialize_type;
+use crate::threads::ThreadLocalData;
+use crate::ty::MachineMode;
+use crate::vm::{Fct, FctSrc, VM};
+
+// generates the stub that runs a function in the interpreter: it stores
+// all arguments in its frame and passes them to `interpreter::run`
+pub fn generate<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &mut FctSrc,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+) -> JitBaselineFct {
+    let interp_fct = InterpFct::new(vm, fct, src, cls_type_params, fct_type_params);
+
+    if should_emit_bytecode(vm, fct) {
+        interp_fct.bytecode().dump();
+    }
+
+    let idx = vm.interp_fcts.push(interp_fct);
+    let interp_fct = vm.interp_fcts.idx_usize(idx);
+
+    let return_type = specialize_type(vm, fct.return_type, cls_type_params, fct_type_params);
+    let float_result = !return_type.is_unit() && return_type.mode().is_float();
+
+    let ngen = DoraInterpGen {
+        vm: vm,
+        fct: fct,
+        masm: MacroAssembler::new(),
+        interp_fct: &*interp_fct,
+        float_result: float_result,
+        dbg: should_emit_debug(vm, fct),
+    };
+
+    ngen.generate()
+}
+
+struct DoraInterpGen<'a, 'ast: 'a> {
+    vm: &'a VM<'ast>,
+    fct: &'a Fct<'ast>,
+    masm: MacroAssembler,
+    interp_fct: &'a InterpFct,
+    float_result: bool,
+    dbg: bool,
+}
+
+impl<'a, 'ast> DoraInterpGen<'a, 'ast>
+where
+    'ast: 'a,
+{
+    fn generate(mut self) -> JitBaselineFct {
+        let params = self.fct.ast.params.len() + if self.fct.has_self() { 1 } else { 0 };
+
+        let framesize = size_of::<DoraToNativeInfo>() as i32 +   // save d2n structure on stack
+            mem::ptr_width() +                                 // save return value on stack
+            mem::ptr_width() +                                 // store thread register
+            params as i32 * mem::ptr_width(); // store arguments on stack
+
+        let framesize = mem::align_i32(framesize, 16);
+
+        let offset_return = 0;
+        let offset_thread = offset_return + mem::ptr_width();
+        let offset_args = offset_thread + mem::ptr_width();
+
+        // `start_native_call` assumes that offset_dtn is on top of current stack frame.
+
+        if self.dbg {
+            self.masm.debug();
+        }
+
+        self.masm.prolog(framesize);
+
+        self.masm.store_mem(
+            MachineMode::Ptr,
+            Mem::Base(REG_SP, offset_thread),
+            REG_THREAD.into(),
+        );
+
+        self.save_params(params, offset_args);
+
+        self.masm.copy_reg(MachineMode::Ptr, REG_PARAMS[0], REG_FP);
+        self.masm.copy_pc(REG_PARAMS[1]);
+        self.masm.raw_call(start_native_call as *const u8);
+
+        self.masm.load_int_const(
+            MachineMode::Ptr,
+            REG_PARAMS[0],
+            self.interp_fct as *const InterpFct as i64,
+        );
+        self.masm.copy_sp(REG_PARAMS[1]);
+        self.masm.int_add_imm(
+            MachineMode::Ptr,
+            REG_PARAMS[1],
+            REG_PARAMS[1],
+            offset_args as i64,
+        );
+        self.masm.raw_call(interpreter::run as *const u8);
+
+        self.masm.store_mem(
+            MachineMode::Ptr,
+            Mem::Base(REG_SP, offset_return),
+            REG_RESULT.into(),
+        );
+
+        self.masm.raw_call(finish_native_call as *const u8);
+
+        self.masm.load_mem(
+            MachineMode::Ptr,
+            REG_THREAD.into(),
+            Mem::Base(REG_SP, offset_thread),
+        );
+
+        // check for pending exception of this thread
+        let offset_exception = ThreadLocalData::exception_object_offset();
+        self.masm.load_mem(
+            MachineMode::Ptr,
+            REG_TMP1.into(),
+            Mem::Base(REG_THREAD, offset_exception),
+        );
+
+        let lbl_exception = self.masm.test_if_not_nil(REG_TMP1);
+
+        if self.float_result {
+            self.masm.load_mem(
+                MachineMode::Float64,
+                FREG_RESULT.into(),
+                Mem::Base(REG_SP, offset_return),
+            );
+        } else {
+            self.masm.load_mem(
+                MachineMode::Ptr,
+                REG_RESULT.into(),
+                Mem::Base(REG_SP, offset_return),
+            );
+        }
+
+        self.masm
+            .epilog_with_polling(framesize, self.vm.polling_page.addr());
+
+        // clear pending exception and throw it
+        self.masm.bind_label(lbl_exception);
+        self.masm.load_nil(REG_TMP2);
+        self.masm.store_mem(
+            MachineMode::Ptr,
+            Mem::Base(REG_THREAD, offset_exception),
+            REG_TMP2.into(),
+        );
+        self.masm.throw(REG_TMP1, self.fct.ast.pos);
+        self.masm.nop();
+
+        self.masm.jit(
+            self.vm,
+            framesize,
+            JitDescriptor::InterpThunk(self.fct.id),
+            self.fct.ast.throws,
+        )
+    }
+
+    // every argument occupies a full slot, the interpreter clears unused bits
+    fn save_params(&mut self, params: usize, offset_args: i32) {
+        let mut reg_idx = 0;
+        let mut freg_idx = 0;
+        let mut param_offset = PARAM_OFFSET;
+
+        for idx in 0..params {
+            let mode = self.interp_fct.bytecode().register(Register(idx)).mode();
+            let offset = offset_args + idx as i32 * mem::ptr_width();
+
+            if mode.is_float() && freg_idx < FREG_PARAMS.len() {
+                let freg = FREG_PARAMS[freg_idx].into();
+                self.masm
+                    .store_mem(MachineMode::Float64, Mem::Base(REG_SP, offset), freg);
+                freg_idx += 1;
+            } else if !mode.is_float() && reg_idx < REG_PARAMS.len() {
+                let reg = REG_PARAMS[reg_idx].into();
+                self.masm
+                    .store_mem(MachineMode::Ptr, Mem::Base(REG_SP, offset), reg);
+                reg_idx += 1;
+            } else {
+                // params not passed in registers are copied from the caller's frame
+                self.masm
+                    .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Local(param_offset));
+                self.masm
+                    .store_mem(MachineMode::Ptr, Mem::Base(REG_SP, offset), REG_TMP1.into());
+                param_offset += mem::ptr_width();
+            }
+        }
+    }
+}
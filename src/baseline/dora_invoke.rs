@@ -0,0 +1,191 @@
+use crate::baseline::codegen::CondCode;
+use crate::baseline::fct::{CatchType, JitBaselineFct, JitDescriptor, JitFct, JitFctId};
+use crate::baseline::map::CodeDescriptor;
+use crate::cpu::{
+    Mem, FREG_PARAMS, REG_ALLOCATABLE, REG_FP, REG_PARAMS, REG_RESULT, REG_THREAD, REG_TMP1,
+    REG_TMP2,
+};
+use crate::gc::Address;
+use crate::masm::MacroAssembler;
+use crate::mem;
+use crate::threads::ThreadLocalData;
+use crate::ty::MachineMode;
+use crate::vm::VM;
+
+// the interpreter calls compiled code and native stubs through this stub:
+//
+//   fn(tld, fct, regs, stack, stack_size) -> value
+//
+// `regs` contains the values of all integer parameter registers followed by
+// the values of all float parameter registers, `stack` the arguments passed
+// on the stack. An exception thrown by the callee is caught here and becomes
+// the pending exception of the thread.
+pub fn generate<'a, 'ast: 'a>(vm: &'a VM<'ast>) -> Address {
+    let ngen = DoraInvokeGen {
+        vm: vm,
+        masm: MacroAssembler::new(),
+        dbg: vm.args.flag_emit_debug_entry,
+    };
+
+    let jit_fct = ngen.generate();
+    let ptr = jit_fct.fct_ptr();
+    let ptr_start = jit_fct.ptr_start();
+    let ptr_end = jit_fct.ptr_end();
+
+    let jit_fct_id: JitFctId = vm.jit_fcts.push(JitFct::Base(jit_fct)).into();
+    vm.insert_code_map(ptr_start, ptr_end, CodeDescriptor::DoraInvoke(jit_fct_id));
+
+    ptr
+}
+
+struct DoraInvokeGen<'a, 'ast: 'a> {
+    vm: &'a VM<'ast>,
+    masm: MacroAssembler,
+    dbg: bool,
+}
+
+impl<'a, 'ast> DoraInvokeGen<'a, 'ast>
+where
+    'ast: 'a,
+{
+    pub fn generate(mut self) -> JitBaselineFct {
+        // thread register, exception object and the callee-saved registers used
+        // by optimized code
+        let framesize = (2 + REG_ALLOCATABLE.len() as i32) * mem::ptr_width();
+        let framesize = mem::align_i32(framesize, 16);
+
+        let offset_thread = -mem::ptr_width();
+        let offset_exception = -2 * mem::ptr_width();
+
+        let reg_fct = REG_RESULT;
+        let reg_regs = REG_TMP2;
+        let reg_stack = REG_PARAMS[3];
+        let reg_stack_size = REG_PARAMS[4];
+        let reg_dest = REG_PARAMS[5];
+
+        if self.dbg {
+            self.masm.debug();
+        }
+
+        self.masm.prolog(framesize);
+        self.masm.store_mem(
+            MachineMode::Ptr,
+            Mem::Local(offset_thread),
+            REG_THREAD.into(),
+        );
+
+        for (idx, &reg) in REG_ALLOCATABLE.iter().enumerate() {
+            self.masm
+                .store_mem(MachineMode::Ptr, Mem::Local(offset_saved(idx)), reg.into());
+        }
+
+        self.masm
+            .copy_reg(MachineMode::Ptr, REG_THREAD, REG_PARAMS[0]);
+        self.masm.copy_reg(MachineMode::Ptr, reg_fct, REG_PARAMS[1]);
+        self.masm
+            .copy_reg(MachineMode::Ptr, reg_regs, REG_PARAMS[2]);
+
+        // reserve space for arguments passed on the stack
+        self.masm.copy_sp(REG_TMP1);
+        self.masm
+            .int_sub(MachineMode::Int64, REG_TMP1, REG_TMP1, reg_stack_size);
+        self.masm.set_sp(REG_TMP1);
+        self.masm.copy_sp(reg_dest);
+
+        // copy arguments to the reserved area
+        let lbl_copy = self.masm.create_label();
+        let lbl_copied = self.masm.create_label();
+
+        self.masm.bind_label(lbl_copy);
+        self.masm.cmp_reg_imm(MachineMode::Ptr, reg_stack_size, 0);
+        self.masm.jump_if(CondCode::Equal, lbl_copied);
+        self.masm
+            .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Base(reg_stack, 0));
+        self.masm
+            .store_mem(MachineMode::Ptr, Mem::Base(reg_dest, 0), REG_TMP1.into());
+
+        for &reg in &[reg_stack, reg_dest] {
+            self.masm
+                .int_add_imm(MachineMode::Ptr, reg, reg, mem::ptr_width() as i64);
+        }
+
+        self.masm.int_add_imm(
+            MachineMode::Ptr,
+            reg_stack_size,
+            reg_stack_size,
+            -mem::ptr_width() as i64,
+        );
+        self.masm.jump(lbl_copy);
+        self.masm.bind_label(lbl_copied);
+
+        // load parameter registers
+        for (idx, &freg) in FREG_PARAMS.iter().enumerate() {
+            let offset = (REG_PARAMS.len() + idx) as i32 * mem::ptr_width();
+            self.masm.load_mem(
+                MachineMode::Float64,
+                freg.into(),
+                Mem::Base(reg_regs, offset),
+            );
+        }
+
+        for (idx, &reg) in REG_PARAMS.iter().enumerate() {
+            let offset = idx as i32 * mem::ptr_width();
+            self.masm
+                .load_mem(MachineMode::Ptr, reg.into(), Mem::Base(reg_regs, offset));
+        }
+
+        let try_start = self.masm.pos();
+        self.masm.call_reg(reg_fct);
+        let try_end = self.masm.pos();
+
+        self.emit_return(framesize, offset_thread);
+
+        // exception thrown by callee: make it the pending exception
+        let catch = self.masm.pos();
+        self.masm.load_mem(
+            MachineMode::Ptr,
+            REG_TMP1.into(),
+            Mem::Local(offset_exception),
+        );
+        self.masm.store_mem(
+            MachineMode::Ptr,
+            Mem::Base(REG_THREAD, ThreadLocalData::exception_object_offset()),
+            REG_TMP1.into(),
+        );
+        self.emit_return(framesize, offset_thread);
+
+        self.masm.emit_exception_handler(
+            (try_start, try_end),
+            catch,
+            Some(offset_exception),
+            CatchType::Any,
+        );
+
+        self.masm
+            .jit(self.vm, framesize, JitDescriptor::DoraInvoke, false)
+    }
+
+    fn emit_return(&mut self, framesize: i32, offset_thread: i32) {
+        self.masm.load_mem(
+            MachineMode::Ptr,
+            REG_THREAD.into(),
+            Mem::Local(offset_thread),
+        );
+
+        for (idx, &reg) in REG_ALLOCATABLE.iter().enumerate() {
+            self.masm
+                .load_mem(MachineMode::Ptr, reg.into(), Mem::Local(offset_saved(idx)));
+        }
+
+        // drop arguments passed on the stack
+        self.masm.copy_reg(MachineMode::Ptr, REG_TMP1, REG_FP);
+        self.masm
+            .int_add_imm(MachineMode::Ptr, REG_TMP1, REG_TMP1, -framesize as i64);
+        self.masm.set_sp(REG_TMP1);
+        self.masm.epilog(framesize);
+    }
+}
+
+fn offset_saved(idx: usize) -> i32 {
+    -(3 + idx as i32) * mem::ptr_width()
+}
@@ -1,19 +1,22 @@
 use std::collections::hash_map::HashMap;
 use std::mem::size_of;
+use std::ptr;
 
 use dora_parser::lexer::position::Position;
 
 use crate::baseline::fct::{JitBaselineFct, JitDescriptor, JitFct, JitFctId};
 use crate::baseline::map::CodeDescriptor;
-use crate::cpu::{Mem, FREG_PARAMS, REG_FP, REG_PARAMS, REG_RESULT, REG_SP, REG_THREAD};
+use crate::cpu::{
+    Mem, FREG_PARAMS, REG_FP, REG_PARAMS, REG_RESULT, REG_SP, REG_THREAD, REG_TMP1, REG_TMP2,
+};
 use crate::exception::DoraToNativeInfo;
 use crate::gc::Address;
 use crate::masm::MacroAssembler;
 use crate::mem;
-use crate::threads::THREAD;
+use crate::threads::{ThreadLocalData, THREAD};
 use crate::ty::{BuiltinType, MachineMode};
+use crate::vm::FctId;
 use crate::vm::VM;
-use crate::vm::{exception_get_and_clear, FctId};
 
 pub struct NativeThunks {
     map: HashMap<Address, JitFctId>,
@@ -41,6 +44,10 @@ pub enum InternalFctDescriptor {
     AllocThunk,
     VerifyThunk,
     TrapThunk,
+    SpawnThunk,
+    TierUpThunk,
+    OsrThunk,
+    DeoptThunk,
 }
 
 pub struct InternalFct<'a> {
@@ -71,6 +78,10 @@ pub fn generate<'a, 'ast: 'a>(vm: &'a VM<'ast>, fct: InternalFct, dbg: bool) ->
         InternalFctDescriptor::TrapThunk => CodeDescriptor::TrapThunk,
         InternalFctDescriptor::VerifyThunk => CodeDescriptor::VerifyThunk,
         InternalFctDescriptor::AllocThunk => CodeDescriptor::AllocThunk,
+        InternalFctDescriptor::SpawnThunk => CodeDescriptor::SpawnThunk,
+        InternalFctDescriptor::TierUpThunk => CodeDescriptor::TierUpThunk,
+        InternalFctDescriptor::OsrThunk => CodeDescriptor::OsrThunk,
+        InternalFctDescriptor::DeoptThunk => CodeDescriptor::DeoptThunk,
     };
 
     vm.insert_code_map(jit_start, jit_end, code_desc);
@@ -139,7 +150,21 @@ where
 
         self.masm.raw_call(finish_native_call as *const u8);
 
-        let lbl_exception = self.masm.test_if_not_nil(REG_RESULT);
+        self.masm.load_mem(
+            MachineMode::Ptr,
+            REG_THREAD.into(),
+            Mem::Base(REG_SP, offset_thread),
+        );
+
+        // check for pending exception of this thread
+        let offset_exception = ThreadLocalData::exception_object_offset();
+        self.masm.load_mem(
+            MachineMode::Ptr,
+            REG_TMP1.into(),
+            Mem::Base(REG_THREAD, offset_exception),
+        );
+
+        let lbl_exception = self.masm.test_if_not_nil(REG_TMP1);
 
         if save_return {
             self.masm.load_mem(
@@ -149,17 +174,18 @@ where
             );
         }
 
-        self.masm.load_mem(
-            MachineMode::Ptr,
-            REG_THREAD.into(),
-            Mem::Base(REG_SP, offset_thread),
-        );
-
         self.masm
             .epilog_with_polling(framesize, self.vm.polling_page.addr());
 
+        // clear pending exception and throw it
         self.masm.bind_label(lbl_exception);
-        self.masm.throw(REG_RESULT, Position::new(1, 1));
+        self.masm.load_nil(REG_TMP2);
+        self.masm.store_mem(
+            MachineMode::Ptr,
+            Mem::Base(REG_THREAD, offset_exception),
+            REG_TMP2.into(),
+        );
+        self.masm.throw(REG_TMP1, Position::new(1, 1));
         self.masm.nop();
 
         let desc = match self.fct.desc {
@@ -167,6 +193,10 @@ where
             InternalFctDescriptor::AllocThunk => JitDescriptor::AllocThunk,
             InternalFctDescriptor::VerifyThunk => JitDescriptor::VerifyThunk,
             InternalFctDescriptor::TrapThunk => JitDescriptor::TrapThunk,
+            InternalFctDescriptor::SpawnThunk => JitDescriptor::SpawnThunk,
+            InternalFctDescriptor::TierUpThunk => JitDescriptor::TierUpThunk,
+            InternalFctDescriptor::OsrThunk => JitDescriptor::OsrThunk,
+            InternalFctDescriptor::DeoptThunk => JitDescriptor::DeoptThunk,
         };
 
         self.masm.jit(self.vm, framesize, desc, self.fct.throws)
@@ -235,6 +265,7 @@ pub fn start_native_call(fp: *const u8, pc: usize) {
 
         dtn.fp = fp as usize;
         dtn.pc = pc;
+        dtn.interp_frame = ptr::null();
 
         THREAD.with(|thread| {
             thread.borrow().push_dtn(dtn);
@@ -243,11 +274,9 @@ pub fn start_native_call(fp: *const u8, pc: usize) {
     }
 }
 
-pub fn finish_native_call() -> *const u8 {
+pub fn finish_native_call() {
     THREAD.with(|thread| {
         thread.borrow().handles.pop_border();
         thread.borrow().pop_dtn();
     });
-
-    exception_get_and_clear()
 }
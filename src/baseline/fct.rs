@@ -3,6 +3,7 @@ use std::fmt;
 use std::ptr;
 use std::sync::Arc;
 
+use crate::bytecode::generate::{BytecodeIdx, Register};
 use crate::class::{ClassDef, ClassDefId, FieldId, TypeParams};
 use crate::cpu::flush_icache;
 use crate::dseg::DSeg;
@@ -13,7 +14,7 @@ use crate::utils::GrowableVec;
 use crate::vm::VM;
 use crate::vm::{FctId, FctSrc, GlobalId, VarId};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct JitFctId(usize);
 
 impl JitFctId {
@@ -58,24 +59,123 @@ impl JitFct {
         }
     }
 
+    pub fn fct_end(&self) -> Address {
+        match self {
+            &JitFct::Base(ref base) => base.fct_end(),
+            &JitFct::Opt(ref opt) => opt.fct_end(),
+        }
+    }
+
+    pub fn fct_len(&self) -> usize {
+        match self {
+            &JitFct::Base(ref base) => base.fct_len(),
+            &JitFct::Opt(ref opt) => opt.fct_len(),
+        }
+    }
+
+    pub fn get_comment(&self, pos: i32) -> Option<&[Comment]> {
+        match self {
+            &JitFct::Base(ref base) => base.get_comment(pos),
+            &JitFct::Opt(ref opt) => opt.get_comment(pos),
+        }
+    }
+
     pub fn to_base(&self) -> Option<&JitBaselineFct> {
         match self {
             &JitFct::Base(ref base) => Some(base),
             _ => None,
         }
     }
+
+    pub fn ptr_start(&self) -> Address {
+        match self {
+            &JitFct::Base(ref base) => base.ptr_start(),
+            &JitFct::Opt(ref opt) => opt.ptr_start(),
+        }
+    }
+
+    pub fn ptr_end(&self) -> Address {
+        match self {
+            &JitFct::Base(ref base) => base.ptr_end(),
+            &JitFct::Opt(ref opt) => opt.ptr_end(),
+        }
+    }
+
+    pub fn framesize(&self) -> i32 {
+        match self {
+            &JitFct::Base(ref base) => base.framesize,
+            &JitFct::Opt(ref opt) => opt.framesize,
+        }
+    }
+
+    pub fn throws(&self) -> bool {
+        match self {
+            &JitFct::Base(ref base) => base.throws,
+            &JitFct::Opt(ref opt) => opt.throws,
+        }
+    }
+
+    pub fn exception_handlers(&self) -> &[ExHandler] {
+        match self {
+            &JitFct::Base(ref base) => &base.exception_handlers,
+            &JitFct::Opt(ref opt) => &opt.exception_handlers,
+        }
+    }
+
+    pub fn lineno_for_offset(&self, offset: i32) -> i32 {
+        match self {
+            &JitFct::Base(ref base) => base.lineno_for_offset(offset),
+            &JitFct::Opt(ref opt) => opt.lineno_for_offset(offset),
+        }
+    }
+
+    // functions inlined at the program point, only optimized code inlines calls
+    pub fn inlined_frames_for_offset(&self, offset: i32) -> &[(FctId, i32)] {
+        match self {
+            &JitFct::Base(_) => &[],
+            &JitFct::Opt(ref opt) => opt.inlined_frames_for_offset(offset),
+        }
+    }
+
+    pub fn gcpoint_for_offset(&self, offset: i32) -> Option<&GcPoint> {
+        match self {
+            &JitFct::Base(ref base) => base.gcpoint_for_offset(offset),
+            &JitFct::Opt(ref opt) => opt.gcpoint_for_offset(offset),
+        }
+    }
+
+    pub fn nil_check_for_offset(&self, offset: i32) -> bool {
+        match self {
+            &JitFct::Base(ref base) => base.nil_check_for_offset(offset),
+            &JitFct::Opt(ref opt) => opt.nil_check_for_offset(offset),
+        }
+    }
+
+    pub fn bailout_for_offset(&self, offset: i32) -> Option<&BailoutInfo> {
+        match self {
+            &JitFct::Base(ref base) => base.bailouts.get(offset),
+            &JitFct::Opt(ref opt) => opt.bailouts.get(offset),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum JitDescriptor {
     DoraFct(FctId),
     CompilerThunk,
+    InlineCacheStub,
     ThrowThunk,
     TrapThunk,
     AllocThunk,
     VerifyThunk,
+    SpawnThunk,
+    TierUpThunk,
+    OsrThunk,
+    DeoptThunk,
     NativeThunk(FctId),
+    InterpThunk(FctId),
     DoraEntry,
+    DoraInvoke,
 }
 
 pub struct JitBaselineFct {
@@ -115,32 +215,13 @@ impl JitBaselineFct {
         throws: bool,
         mut exception_handlers: Vec<ExHandler>,
     ) -> JitBaselineFct {
-        let size = dseg.size() as usize + buffer.len();
-        let ptr = vm.gc.alloc_code(size);
-
-        if ptr.is_null() {
-            panic!("out of memory: not enough executable memory left!");
-        }
-
-        dseg.finish(ptr.to_ptr());
-
-        let fct_start = ptr.offset(dseg.size() as usize);
-
-        unsafe {
-            ptr::copy_nonoverlapping(buffer.as_ptr(), fct_start.to_mut_ptr(), buffer.len());
-        }
-
-        flush_icache(ptr.to_ptr(), size);
-
-        for handler in &mut exception_handlers {
-            handler.try_start = fct_start.offset(handler.try_start).to_usize();
-            handler.try_end = fct_start.offset(handler.try_end).to_usize();
-            handler.catch = fct_start.offset(handler.catch).to_usize();
-        }
+        let (code_start, code_end) = install_code(vm, dseg, buffer);
+        let fct_start = code_start.offset(dseg.size() as usize);
+        relocate_exception_handlers(&mut exception_handlers, fct_start);
 
         JitBaselineFct {
-            code_start: ptr,
-            code_end: ptr.offset(size as usize),
+            code_start: code_start,
+            code_end: code_end,
             bailouts: bailouts,
             nil_checks: nil_checks,
             gcpoints: gcpoints,
@@ -178,6 +259,7 @@ impl JitBaselineFct {
     pub fn fct_id(&self) -> FctId {
         match self.desc {
             JitDescriptor::NativeThunk(fct_id) => fct_id,
+            JitDescriptor::InterpThunk(fct_id) => fct_id,
             JitDescriptor::DoraFct(fct_id) => fct_id,
             _ => panic!("no fctid found"),
         }
@@ -200,6 +282,38 @@ impl JitBaselineFct {
     }
 }
 
+// copies the data segment followed by the machine code into executable memory,
+// returns start and end of the allocated code
+pub fn install_code(vm: &VM, dseg: &DSeg, buffer: &[u8]) -> (Address, Address) {
+    let size = dseg.size() as usize + buffer.len();
+    let ptr = vm.gc.alloc_code(size);
+
+    if ptr.is_null() {
+        panic!("out of memory: not enough executable memory left!");
+    }
+
+    dseg.finish(ptr.to_ptr());
+
+    let fct_start = ptr.offset(dseg.size() as usize);
+
+    unsafe {
+        ptr::copy_nonoverlapping(buffer.as_ptr(), fct_start.to_mut_ptr(), buffer.len());
+    }
+
+    flush_icache(ptr.to_ptr(), size);
+
+    (ptr, ptr.offset(size))
+}
+
+// handlers are emitted with offsets relative to the function start
+pub fn relocate_exception_handlers(exception_handlers: &mut [ExHandler], fct_start: Address) {
+    for handler in exception_handlers {
+        handler.try_start = fct_start.offset(handler.try_start).to_usize();
+        handler.try_end = fct_start.offset(handler.try_end).to_usize();
+        handler.catch = fct_start.offset(handler.catch).to_usize();
+    }
+}
+
 impl fmt::Debug for JitBaselineFct {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -233,7 +347,7 @@ impl GcPoints {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct GcPoint {
     pub offsets: Vec<i32>,
 }
@@ -449,6 +563,32 @@ impl LineNumberTable {
     }
 }
 
+// functions inlined at program points of optimized code: the innermost function comes
+// first together with the line of its call site in the enclosing function
+#[derive(Debug)]
+pub struct InlineTable {
+    map: HashMap<i32, Vec<(FctId, i32)>>,
+}
+
+impl InlineTable {
+    pub fn new() -> InlineTable {
+        InlineTable {
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, offset: i32, frames: Vec<(FctId, i32)>) {
+        assert!(self.map.insert(offset, frames).is_none());
+    }
+
+    pub fn get(&self, offset: i32) -> &[(FctId, i32)] {
+        match self.map.get(&offset) {
+            Some(frames) => frames,
+            None => &[],
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ExHandler {
     pub try_start: usize,
@@ -489,4 +629,18 @@ impl Bailouts {
 pub enum BailoutInfo {
     Compile(FctId, i32, TypeParams, TypeParams),
     VirtCompile(u32, TypeParams),
+    InlineCache(Address),
+    Deopt(DeoptInfo),
+}
+
+// state of a deoptimization point in optimized code: execution continues in
+// baseline code at the bytecode instruction `bytecode_idx`, the values of the
+// bytecode registers are stored at the given offsets of the optimized frame
+#[derive(Clone, Debug)]
+pub struct DeoptInfo {
+    pub fct_id: FctId,
+    pub cls_type_params: TypeParams,
+    pub fct_type_params: TypeParams,
+    pub bytecode_idx: BytecodeIdx,
+    pub registers: Vec<(Register, i32)>,
 }
@@ -0,0 +1,266 @@
+use std::mem::size_of;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use dora_parser::lexer::position::Position;
+use parking_lot::Mutex;
+
+use crate::baseline::dora_compile::patch_vtable_call;
+use crate::class::{ClassDefId, TypeParams};
+use crate::gc::Address;
+use crate::mem;
+use crate::object::Obj;
+use crate::vm::{FctSrc, VM};
+use crate::vtable::VTable;
+
+// number of receiver classes a call site dispatches without loading from the vtable
+pub const POLYMORPHIC_ENTRIES: usize = 4;
+
+// inline cache of a virtual call site in baseline code. The call site compares the
+// vtable of the receiver with the first entry of the current state and calls its
+// target directly, other receivers are handled by the inline cache stub which checks
+// the remaining entries. Receivers not found in the cache are added by `resolve`
+// when the stub falls back to the compiler thunk. As soon as all entries are taken
+// the call site is megamorphic and the stub loads the method from the vtable.
+//
+// States are never modified once published, updates install a new state instead.
+// Machine code might still read older states, so they live as long as the cache.
+#[repr(C)]
+pub struct InlineCache {
+    state: AtomicPtr<CacheState>,
+
+    pos: Position,
+    vtable_index: u32,
+    states: Mutex<Vec<Box<CacheState>>>,
+}
+
+impl InlineCache {
+    pub fn new(pos: Position, vtable_index: u32) -> InlineCache {
+        let state = Box::new(CacheState::empty());
+        let ptr = &*state as *const CacheState as *mut CacheState;
+
+        InlineCache {
+            state: AtomicPtr::new(ptr),
+            pos: pos,
+            vtable_index: vtable_index,
+            states: Mutex::new(vec![state]),
+        }
+    }
+
+    pub fn offset_of_state() -> i32 {
+        offset_of!(InlineCache, state) as i32
+    }
+
+    pub fn feedback(&self) -> Feedback {
+        let state = unsafe { &*self.state.load(Ordering::Acquire) };
+
+        if state.megamorphic_offset != 0 {
+            return Feedback::Megamorphic;
+        }
+
+        let classes = state
+            .entries
+            .iter()
+            .take_while(|entry| entry.vtable != 0)
+            .map(|entry| {
+                let vtable = unsafe { &*(entry.vtable as *const VTable) };
+                vtable.class().id
+            })
+            .collect();
+
+        Feedback::from_classes(classes)
+    }
+
+    fn update(&self, vtable: &VTable, target: Address) {
+        let mut states = self.states.lock();
+        let current = unsafe { &*self.state.load(Ordering::Acquire) };
+        let vtable = vtable as *const VTable as usize;
+
+        // another thread might have added the receiver in the meantime
+        if current.megamorphic_offset != 0
+            || current.entries.iter().any(|entry| entry.vtable == vtable)
+        {
+            return;
+        }
+
+        let mut state = Box::new(current.clone());
+
+        match state.entries.iter_mut().find(|entry| entry.vtable == 0) {
+            Some(entry) => {
+                entry.vtable = vtable;
+                entry.target = target.to_usize();
+            }
+
+            None => {
+                let offset =
+                    VTable::offset_of_method_table() + self.vtable_index as i32 * mem::ptr_width();
+                state.megamorphic_offset = offset as usize;
+            }
+        }
+
+        let ptr = &*state as *const CacheState as *mut CacheState;
+        self.state.store(ptr, Ordering::Release);
+        states.push(state);
+    }
+}
+
+#[repr(C)]
+#[derive(Clone)]
+pub struct CacheState {
+    entries: [CacheEntry; POLYMORPHIC_ENTRIES],
+
+    // offset of the method in the vtable once the call site is megamorphic, 0 before
+    megamorphic_offset: usize,
+}
+
+impl CacheState {
+    fn empty() -> CacheState {
+        CacheState {
+            entries: [CacheEntry {
+                vtable: 0,
+                target: 0,
+            }; POLYMORPHIC_ENTRIES],
+            megamorphic_offset: 0,
+        }
+    }
+
+    pub fn offset_of_vtable(idx: usize) -> i32 {
+        (offset_of!(CacheState, entries)
+            + idx * size_of::<CacheEntry>()
+            + offset_of!(CacheEntry, vtable)) as i32
+    }
+
+    pub fn offset_of_target(idx: usize) -> i32 {
+        (offset_of!(CacheState, entries)
+            + idx * size_of::<CacheEntry>()
+            + offset_of!(CacheEntry, target)) as i32
+    }
+
+    pub fn offset_of_megamorphic_offset() -> i32 {
+        offset_of!(CacheState, megamorphic_offset) as i32
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct CacheEntry {
+    vtable: usize,
+    target: usize,
+}
+
+// receiver classes seen at a call site
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Feedback {
+    Uninitialized,
+    Monomorphic(ClassDefId),
+    Polymorphic(Vec<ClassDefId>),
+    Megamorphic,
+}
+
+impl Feedback {
+    fn from_classes(classes: Vec<ClassDefId>) -> Feedback {
+        match classes.len() {
+            0 => Feedback::Uninitialized,
+            1 => Feedback::Monomorphic(classes[0]),
+            len if len <= POLYMORPHIC_ENTRIES => Feedback::Polymorphic(classes),
+            _ => Feedback::Megamorphic,
+        }
+    }
+}
+
+// allocates the cache for a virtual call site at `pos`, returns its address
+pub fn create_inline_cache(src: &mut FctSrc, pos: Position, vtable_index: u32) -> Address {
+    let cache = Box::new(InlineCache::new(pos, vtable_index));
+    let addr = Address::from_ptr(&*cache as *const InlineCache);
+    src.inline_caches.push(cache);
+
+    addr
+}
+
+// receiver classes of the virtual call at `pos` over all specializations of the function
+pub fn feedback(src: &FctSrc, pos: Position) -> Feedback {
+    let mut classes = Vec::new();
+
+    for cache in src.inline_caches.iter().filter(|cache| cache.pos == pos) {
+        let cls_ids = match cache.feedback() {
+            Feedback::Uninitialized => Vec::new(),
+            Feedback::Monomorphic(cls_id) => vec![cls_id],
+            Feedback::Polymorphic(cls_ids) => cls_ids,
+            Feedback::Megamorphic => return Feedback::Megamorphic,
+        };
+
+        for cls_id in cls_ids {
+            if !classes.contains(&cls_id) {
+                classes.push(cls_id);
+            }
+        }
+    }
+
+    Feedback::from_classes(classes)
+}
+
+// invoked through the compiler thunk for receivers not found in the cache at
+// `cache`: returns the method for the receiver and adds its class to the cache
+pub fn resolve(vm: &VM, cache: Address, receiver: Address) -> Address {
+    let cache = unsafe { &*cache.to_ptr::<InlineCache>() };
+    let obj = unsafe { &*receiver.to_ptr::<Obj>() };
+    let vtable = obj.header().vtbl();
+
+    let target = patch_vtable_call(vm, receiver, cache.vtable_index, &TypeParams::empty());
+    cache.update(vtable, target);
+
+    target
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    fn vtable<'a>(vm: &VM, name: &'static str) -> &'a VTable {
+        let cls_def_id = vm.cls_def_by_name(name);
+        let cls_def = vm.class_defs.idx(cls_def_id);
+        let cls_def = cls_def.read();
+        let vtable: *const VTable = &**cls_def.vtable.as_ref().unwrap();
+
+        unsafe { &*vtable }
+    }
+
+    #[test]
+    fn test_feedback_transitions() {
+        test::parse(
+            "@open class A { @open fun foo() {} }
+            class B: A { @override fun foo() {} }
+            class C: A { @override fun foo() {} }
+            class D: A { @override fun foo() {} }
+            class E: A { @override fun foo() {} }",
+            |vm| {
+                let cache = InlineCache::new(Position::new(1, 1), 0);
+                let a = vm.cls_def_by_name("A");
+                let b = vm.cls_def_by_name("B");
+                let c = vm.cls_def_by_name("C");
+                let d = vm.cls_def_by_name("D");
+                assert_eq!(cache.feedback(), Feedback::Uninitialized);
+
+                cache.update(vtable(vm, "A"), Address::null());
+                assert_eq!(cache.feedback(), Feedback::Monomorphic(a));
+
+                // receivers already in the cache don't change it
+                cache.update(vtable(vm, "A"), Address::null());
+                assert_eq!(cache.feedback(), Feedback::Monomorphic(a));
+
+                cache.update(vtable(vm, "B"), Address::null());
+                assert_eq!(cache.feedback(), Feedback::Polymorphic(vec![a, b]));
+
+                cache.update(vtable(vm, "C"), Address::null());
+                cache.update(vtable(vm, "D"), Address::null());
+                assert_eq!(cache.feedback(), Feedback::Polymorphic(vec![a, b, c, d]));
+
+                cache.update(vtable(vm, "E"), Address::null());
+                assert_eq!(cache.feedback(), Feedback::Megamorphic);
+
+                cache.update(vtable(vm, "A"), Address::null());
+                assert_eq!(cache.feedback(), Feedback::Megamorphic);
+            },
+        );
+    }
+}
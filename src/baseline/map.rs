@@ -31,10 +31,15 @@ impl CodeMap {
                     println!("dora {}", fct.full_name(vm));
                 }
                 &CodeDescriptor::CompilerThunk => println!("compiler_thunk"),
+                &CodeDescriptor::InlineCacheStub => println!("inline_cache_stub"),
                 &CodeDescriptor::ThrowThunk => println!("throw_thunk"),
                 &CodeDescriptor::TrapThunk => println!("trap_thunk"),
                 &CodeDescriptor::AllocThunk => println!("alloc_thunk"),
                 &CodeDescriptor::VerifyThunk => println!("verify_thunk"),
+                &CodeDescriptor::SpawnThunk => println!("spawn_thunk"),
+                &CodeDescriptor::TierUpThunk => println!("tier_up_thunk"),
+                &CodeDescriptor::OsrThunk => println!("osr_thunk"),
+                &CodeDescriptor::DeoptThunk => println!("deopt_thunk"),
                 &CodeDescriptor::NativeThunk(jit_fct_id) => {
                     let jit_fct = vm.jit_fcts.idx(jit_fct_id);
                     let fct = vm.fcts.idx(jit_fct.fct_id());
@@ -42,7 +47,15 @@ impl CodeMap {
 
                     println!("native {}", fct.full_name(vm));
                 }
+                &CodeDescriptor::InterpThunk(jit_fct_id) => {
+                    let jit_fct = vm.jit_fcts.idx(jit_fct_id);
+                    let fct = vm.fcts.idx(jit_fct.fct_id());
+                    let fct = fct.read();
+
+                    println!("interp {}", fct.full_name(vm));
+                }
                 &CodeDescriptor::DoraEntry => println!("dora_entry"),
+                &CodeDescriptor::DoraInvoke(_) => println!("dora_invoke"),
             }
         }
 
@@ -65,12 +78,19 @@ impl CodeMap {
 pub enum CodeDescriptor {
     DoraFct(JitFctId),
     CompilerThunk,
+    InlineCacheStub,
     ThrowThunk,
     TrapThunk,
     AllocThunk,
     VerifyThunk,
+    SpawnThunk,
+    TierUpThunk,
+    OsrThunk,
+    DeoptThunk,
     NativeThunk(JitFctId),
+    InterpThunk(JitFctId),
     DoraEntry,
+    DoraInvoke(JitFctId),
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -0,0 +1,181 @@
+use parking_lot::{Condvar, Mutex};
+use std::cell::Cell;
+use std::collections::{HashSet, VecDeque};
+use std::mem;
+use std::thread::{self, JoinHandle};
+
+use crate::baseline;
+use crate::baseline::tier::{self, HotnessCounters};
+use crate::class::TypeParams;
+use crate::gc::Address;
+use crate::vm::{get_vm, FctId, VM};
+
+// compile requests serviced by background threads with `--compile-threads`: functions
+// called by compiled code are compiled ahead of their first invocation and hot functions
+// are recompiled while their baseline code keeps running. Workers publish code in the
+// specializations of the function under its lock, just like the compiler thunk. Call
+// sites are only patched by the compiler thunk, the next invocation finds the code or
+// waits for the worker that holds the lock of the function.
+//
+// Workers are no Dora threads and don't stop at safepoints. Compilation only allocates
+// in the code and perm space, which are neither collected nor moved, and never reads
+// objects from the heap, so collections can run while workers compile.
+pub struct CompileQueue {
+    state: Mutex<QueueState>,
+    cond: Condvar,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+}
+
+struct QueueState {
+    running: bool,
+    requests: VecDeque<CompileRequest>,
+
+    // specializations requested for baseline compilation so far
+    requested: HashSet<(FctId, TypeParams, TypeParams)>,
+}
+
+thread_local! {
+    static COMPILER_THREAD: Cell<bool> = Cell::new(false);
+}
+
+// true on the worker threads of the compile queue
+pub fn is_compiler_thread() -> bool {
+    COMPILER_THREAD.with(|compiler_thread| compiler_thread.get())
+}
+
+enum CompileRequest {
+    Baseline(FctId, TypeParams, TypeParams),
+
+    // address of the `HotnessCounters` of the baseline code
+    TierUp(Address),
+}
+
+impl CompileQueue {
+    pub fn new() -> CompileQueue {
+        CompileQueue {
+            state: Mutex::new(QueueState {
+                running: false,
+                requests: VecDeque::new(),
+                requested: HashSet::new(),
+            }),
+            cond: Condvar::new(),
+            workers: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn start(&self, threads: usize) {
+        if threads == 0 {
+            return;
+        }
+
+        self.state.lock().running = true;
+
+        let mut workers = self.workers.lock();
+
+        for _ in 0..threads {
+            let vm = get_vm();
+
+            let worker = thread::Builder::new()
+                .name("compiler".into())
+                .spawn(move || vm.compile_queue.run_worker(vm))
+                .expect("could not spawn compiler thread");
+
+            workers.push(worker);
+        }
+    }
+
+    // pending requests are dropped, requests in progress are finished
+    pub fn stop(&self) {
+        {
+            let mut state = self.state.lock();
+            state.running = false;
+            state.requests.clear();
+        }
+
+        self.cond.notify_all();
+
+        let workers = mem::replace(&mut *self.workers.lock(), Vec::new());
+
+        for worker in workers {
+            worker.join().expect("compiler thread panicked");
+        }
+    }
+
+    // requests baseline code for a function that is expected to be called,
+    // ignored without running workers
+    pub fn compile(
+        &self,
+        fct_id: FctId,
+        cls_type_params: &TypeParams,
+        fct_type_params: &TypeParams,
+    ) {
+        let mut state = self.state.lock();
+
+        if !state.running {
+            return;
+        }
+
+        let key = (fct_id, cls_type_params.clone(), fct_type_params.clone());
+
+        if !state.requested.insert(key) {
+            return;
+        }
+
+        state.requests.push_back(CompileRequest::Baseline(
+            fct_id,
+            cls_type_params.clone(),
+            fct_type_params.clone(),
+        ));
+        self.cond.notify_one();
+    }
+
+    // requests the recompilation of hot baseline code, returns false
+    // without running workers
+    pub fn tier_up(&self, counters: &HotnessCounters) -> bool {
+        let mut state = self.state.lock();
+
+        if !state.running {
+            return false;
+        }
+
+        let counters = Address::from_ptr(counters as *const HotnessCounters);
+        state.requests.push_back(CompileRequest::TierUp(counters));
+        self.cond.notify_one();
+
+        true
+    }
+
+    fn run_worker(&self, vm: &VM) {
+        COMPILER_THREAD.with(|compiler_thread| compiler_thread.set(true));
+
+        while let Some(request) = self.next_request() {
+            match request {
+                CompileRequest::Baseline(fct_id, cls_type_params, fct_type_params) => {
+                    baseline::generate(vm, fct_id, &cls_type_params, &fct_type_params);
+                }
+
+                CompileRequest::TierUp(counters) => {
+                    let counters = unsafe { &*counters.to_ptr::<HotnessCounters>() };
+                    tier::recompile(vm, counters);
+                }
+            }
+        }
+    }
+
+    // blocks until a request is available, returns `None` once the queue is stopped
+    fn next_request(&self) -> Option<CompileRequest> {
+        let mut state = self.state.lock();
+
+        loop {
+            if !state.running {
+                return None;
+            }
+
+            if let Some(request) = state.requests.pop_front() {
+                return Some(request);
+            }
+
+            self.cond.wait(&mut state);
+        }
+    }
+}
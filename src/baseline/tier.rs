@@ -0,0 +1,238 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+
+use dora_parser::ast::NodeId;
+use parking_lot::Mutex;
+
+use crate::baseline::codegen;
+use crate::baseline::fct::JitFct;
+use crate::class::TypeParams;
+use crate::cpu;
+use crate::gc::Address;
+use crate::opt;
+use crate::vm::{get_vm, Fct, FctId, FctSrc, VarId, VM};
+
+// counters of a baseline-compiled function specialization, baseline code increments
+// them on every invocation and loop back-edge and invokes `tier_up` as soon as one of
+// them reaches its threshold. Only the counters are accessed from machine code.
+#[repr(C)]
+#[derive(Debug)]
+pub struct HotnessCounters {
+    invocations: AtomicI64,
+    backedges: AtomicI64,
+
+    fct_id: FctId,
+    cls_type_params: TypeParams,
+    fct_type_params: TypeParams,
+
+    // frame offsets of the variables in the baseline code, `None` if
+    // the baseline code doesn't support on-stack replacement
+    var_offsets: Option<HashMap<VarId, i32>>,
+
+    // set as soon as the function was recompiled (or rejected) by the optimizing compiler
+    done: AtomicBool,
+
+    // entry of the baseline code and the instructions overwritten by the jump
+    // to the optimized code
+    patched: Mutex<Option<(Address, u64)>>,
+}
+
+impl HotnessCounters {
+    pub fn new(
+        fct_id: FctId,
+        cls_type_params: &TypeParams,
+        fct_type_params: &TypeParams,
+        var_offsets: Option<HashMap<VarId, i32>>,
+    ) -> HotnessCounters {
+        HotnessCounters {
+            invocations: AtomicI64::new(0),
+            backedges: AtomicI64::new(0),
+            fct_id: fct_id,
+            cls_type_params: cls_type_params.clone(),
+            fct_type_params: fct_type_params.clone(),
+            var_offsets: var_offsets,
+            done: AtomicBool::new(false),
+            patched: Mutex::new(None),
+        }
+    }
+
+    pub fn invocations_offset() -> i32 {
+        offset_of!(HotnessCounters, invocations) as i32
+    }
+
+    pub fn backedges_offset() -> i32 {
+        offset_of!(HotnessCounters, backedges) as i32
+    }
+}
+
+// allocates the counters for baseline code of the given specialization, returns
+// `None` if the function should not be recompiled when hot
+pub fn create_counters<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &mut FctSrc,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+    var_offsets: Option<HashMap<VarId, i32>>,
+) -> Option<Address> {
+    // functions with @optimize were already rejected by the optimizing compiler
+    if !vm.args.flag_enable_tiering || fct.ast.has_optimize {
+        return None;
+    }
+
+    let counters = Box::new(HotnessCounters::new(
+        fct.id,
+        cls_type_params,
+        fct_type_params,
+        var_offsets,
+    ));
+    let addr = Address::from_ptr(&*counters as *const HotnessCounters);
+    src.hotness_counters.push(counters);
+
+    Some(addr)
+}
+
+// invoked from baseline code when one of the counters crossed its threshold:
+// recompiles the function, in the background if compiler threads are running.
+pub extern "C" fn tier_up(counters: *const HotnessCounters) {
+    let vm = get_vm();
+    let counters = unsafe { &*counters };
+
+    // both counters might cross their thresholds, only recompile once
+    if counters.done.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    if !vm.compile_queue.tier_up(counters) {
+        recompile(vm, counters);
+    }
+}
+
+// compiles the function with the optimizing compiler and redirects the entry of
+// the baseline code to the optimized code. Running activations finish in
+// baseline code.
+pub fn recompile(vm: &VM, counters: &HotnessCounters) {
+    let fct = vm.fcts.idx(counters.fct_id);
+    let fct = fct.read();
+    let src = fct.src();
+    let mut src = src.write();
+
+    let cls_type_params = counters.cls_type_params.clone();
+    let fct_type_params = counters.fct_type_params.clone();
+
+    let baseline_ptr = {
+        let specials = src.specializations.read();
+        let key = (cls_type_params.clone(), fct_type_params.clone());
+        let jit_fct_id = *specials.get(&key).expect("baseline code missing");
+        vm.jit_fcts.idx(jit_fct_id).fct_ptr()
+    };
+
+    let jit_fct = match opt::compile(vm, &fct, &mut src, &cls_type_params, &fct_type_params) {
+        Some(jit_fct) => jit_fct,
+        None => return,
+    };
+
+    let opt_ptr = codegen::install_fct(
+        vm,
+        &fct,
+        &src,
+        JitFct::Opt(jit_fct),
+        &cls_type_params,
+        &fct_type_params,
+    );
+
+    // callers still invoke the baseline code through patched call sites and vtables
+    let code = cpu::patch_jump(baseline_ptr, opt_ptr);
+    *counters.patched.lock() = Some((baseline_ptr, code));
+}
+
+// invoked when a speculative assumption of the optimized code of the specialization
+// failed: calls enter the baseline code again and the counters start over, the
+// function is recompiled as soon as it is hot again.
+pub fn deoptimized(src: &FctSrc, cls_type_params: &TypeParams, fct_type_params: &TypeParams) {
+    let counters = src.hotness_counters.iter().find(|counters| {
+        counters.cls_type_params == *cls_type_params && counters.fct_type_params == *fct_type_params
+    });
+
+    let counters = match counters {
+        Some(counters) => counters,
+        None => return,
+    };
+
+    if let Some((baseline_ptr, code)) = counters.patched.lock().take() {
+        cpu::unpatch_jump(baseline_ptr, code);
+    }
+
+    counters.invocations.store(0, Ordering::SeqCst);
+    counters.backedges.store(0, Ordering::SeqCst);
+    counters.done.store(false, Ordering::SeqCst);
+}
+
+thread_local! {
+    // values passed from the baseline frame to the OSR entry
+    static OSR_BUFFER: RefCell<Vec<u64>> = RefCell::new(Vec::new());
+}
+
+// invoked from baseline code when the back-edge counter crossed its threshold in the
+// loop statement `loop_id`: recompiles the function like `tier_up` and compiles an
+// additional entry at the loop. Returns a buffer with the address of that entry and the
+// variables of the baseline frame at `fp`, the baseline code then replaces its frame
+// with the optimized code. Returns null if the loop can't be optimized.
+pub extern "C" fn osr(
+    counters: *const HotnessCounters,
+    loop_id: usize,
+    fp: *const u8,
+) -> *const u64 {
+    tier_up(counters);
+
+    let vm = get_vm();
+    let counters = unsafe { &*counters };
+
+    let var_offsets = match counters.var_offsets {
+        Some(ref var_offsets) => var_offsets,
+        None => return ptr::null(),
+    };
+
+    let fct = vm.fcts.idx(counters.fct_id);
+    let fct = fct.read();
+    let src = fct.src();
+    let mut src = src.write();
+
+    let (jit_fct, vars) = match opt::compile_osr(
+        vm,
+        &fct,
+        &mut src,
+        &counters.cls_type_params,
+        &counters.fct_type_params,
+        NodeId(loop_id),
+    ) {
+        Some(result) => result,
+        None => return ptr::null(),
+    };
+
+    // the entry is only reachable from this frame, calls still use the code from `tier_up`
+    let (_, entry) = codegen::register_fct(vm, &fct, &src, JitFct::Opt(jit_fct));
+
+    // the frame is only read after compilation, a collection might have moved objects
+    OSR_BUFFER.with(|buffer| {
+        let mut buffer = buffer.borrow_mut();
+        buffer.clear();
+        buffer.push(entry.to_usize() as u64);
+
+        for var_id in vars {
+            // slots are copied as a whole, the entry only loads the bytes of the variable
+            let value = match var_offsets.get(&var_id) {
+                Some(&offset) => unsafe {
+                    ptr::read_unaligned(fp.offset(offset as isize) as *const u64)
+                },
+                None => 0,
+            };
+
+            buffer.push(value);
+        }
+
+        buffer.as_ptr()
+    })
+}
@@ -1,5 +1,191 @@
+use dora_parser::ast::NodeId;
+
+use crate::bytecode::astgen;
+use crate::bytecode::generate::Register;
+use crate::class::TypeParams;
+use crate::opt::fct::JitOptFct;
+use crate::vm::{Fct, FctParent, FctSrc, VarId, VM};
+
+use self::function::Type;
+
 mod cfg;
+mod codegen;
 mod dfg;
+mod dom;
 mod function;
+mod inline;
 mod inst;
+mod llvm;
+mod parser;
+pub mod pass;
+mod printer;
+mod regalloc;
+mod ssagen;
 mod utils;
+mod verifier;
+
+// prints the boots IR of the function after running the passes of `--opt-passes`
+pub fn emit<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &mut FctSrc,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+) {
+    let bytecode = astgen::generate_fct(vm, fct, src, cls_type_params, fct_type_params);
+    let name = vm.interner.str(fct.name);
+
+    let mut boots_fct = match ssagen::generate(vm, fct, &bytecode) {
+        Ok(boots_fct) => boots_fct,
+        Err(msg) => {
+            println!("function {}: {}", name, msg);
+            return;
+        }
+    };
+
+    inline::run(vm, fct, &mut boots_fct);
+
+    let pm = vm.args.opt_passes().expect("invalid passes");
+
+    if let Err(msg) = pm.run(&mut boots_fct) {
+        panic!("function {}: {}", name, msg);
+    }
+
+    print!("{}", boots_fct);
+}
+
+// prints LLVM IR for all non-generic functions of the program, functions not
+// supported by boots are listed in comments
+pub fn emit_llvm<'ast>(vm: &VM<'ast>) {
+    let program_fcts = llvm::program_fcts(vm);
+    let mut module = llvm::Module::new(vm);
+    let empty = TypeParams::empty();
+
+    for fct in vm.fcts.iter() {
+        let fct = fct.read();
+
+        if !fct.is_src()
+            || fct.is_abstract
+            || !fct.type_params.is_empty()
+            || !program_fcts.contains(&fct.ast.id)
+        {
+            continue;
+        }
+
+        let generic_class = match fct.parent {
+            FctParent::Class(cls_id) => vm.classes.idx(cls_id).read().is_generic(),
+            FctParent::Impl(impl_id) => match vm.impls[impl_id].read().class_id {
+                Some(cls_id) => vm.classes.idx(cls_id).read().is_generic(),
+                None => true,
+            },
+            FctParent::Trait(_) => true,
+            FctParent::Function(_) | FctParent::None => false,
+        };
+
+        if generic_class {
+            continue;
+        }
+
+        let src = fct.src();
+        let mut src = src.write();
+        let bytecode = astgen::generate_fct(vm, &fct, &mut src, &empty, &empty);
+
+        let ir = match ssagen::generate(vm, &fct, &bytecode) {
+            Ok(ir) => ir,
+            Err(msg) => {
+                module.add_comment(&format!("{}: {}", fct.full_name(vm), msg));
+                continue;
+            }
+        };
+
+        let param_types: Vec<Type> = (0..fct.params_with_self().len())
+            .map(|idx| Type::from(bytecode.register(Register(idx))))
+            .collect();
+
+        module.add_function(&fct, &param_types, &ir);
+    }
+
+    print!("{}", module);
+}
+
+// compiles the function through the boots IR into machine code, returns an error
+// if the function uses features not supported by boots
+pub fn compile<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &mut FctSrc,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+) -> Result<JitOptFct, String> {
+    let bytecode = astgen::generate_fct(vm, fct, src, cls_type_params, fct_type_params);
+    let mut ir = ssagen::generate_with_feedback(vm, fct, src, &bytecode)?;
+    inline::run(vm, fct, &mut ir);
+
+    // passes may remove unused params from the IR
+    let param_types: Vec<Type> = (0..fct.params_with_self().len())
+        .map(|idx| Type::from(bytecode.register(Register(idx))))
+        .collect();
+
+    let pm = vm.args.opt_passes()?;
+    pm.run(&mut ir)?;
+
+    codegen::generate(
+        vm,
+        fct,
+        src,
+        cls_type_params,
+        fct_type_params,
+        &ir,
+        &param_types,
+        false,
+    )
+}
+
+// compiles the function with an entry at the loop statement `loop_id` for on-stack
+// replacement, returns the variables in the order the OSR entry expects their values
+pub fn compile_osr<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &mut FctSrc,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+    loop_id: NodeId,
+) -> Result<(JitOptFct, Vec<VarId>), String> {
+    let bytecode = astgen::generate_fct(vm, fct, src, cls_type_params, fct_type_params);
+    let start = bytecode
+        .loop_start(loop_id)
+        .ok_or_else(|| "no entry for loop".to_string())?;
+
+    let mut vars: Vec<(VarId, Register)> = bytecode
+        .var_registers()
+        .iter()
+        .map(|(&var_id, &reg)| (var_id, reg))
+        .collect();
+    vars.sort_by_key(|&(_, reg)| reg.0);
+
+    let regs: Vec<Register> = vars.iter().map(|&(_, reg)| reg).collect();
+    let mut ir = ssagen::generate_osr(vm, fct, src, &bytecode, start, &regs)?;
+    inline::run(vm, fct, &mut ir);
+
+    let param_types: Vec<Type> = regs
+        .iter()
+        .map(|&reg| Type::from(bytecode.register(reg)))
+        .collect();
+
+    let pm = vm.args.opt_passes()?;
+    pm.run(&mut ir)?;
+
+    let jit_fct = codegen::generate(
+        vm,
+        fct,
+        src,
+        cls_type_params,
+        fct_type_params,
+        &ir,
+        &param_types,
+        true,
+    )?;
+    let vars = vars.into_iter().map(|(var_id, _)| var_id).collect();
+
+    Ok((jit_fct, vars))
+}
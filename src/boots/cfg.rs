@@ -3,9 +3,11 @@ use std::collections::hash_map::HashMap;
 use crate::boots::function::Block;
 use crate::boots::inst::Inst;
 
+// order of blocks and instructions and the edges between blocks
 pub struct ControlFlowGraph {
     entry_block: Option<Block>,
     exit_block: Option<Block>,
+    layout: Vec<Block>,
     blocks: HashMap<Block, BlockNode>,
     insts: HashMap<Inst, InstNode>,
 }
@@ -15,10 +17,191 @@ impl ControlFlowGraph {
         ControlFlowGraph {
             entry_block: None,
             exit_block: None,
+            layout: Vec::new(),
             blocks: HashMap::new(),
             insts: HashMap::new(),
         }
     }
+
+    pub fn add_block(&mut self, block: Block) {
+        assert!(!self.blocks.contains_key(&block));
+
+        if self.entry_block.is_none() {
+            self.entry_block = Some(block);
+        }
+
+        self.layout.push(block);
+        self.blocks.insert(block, BlockNode::new());
+    }
+
+    // removes an empty block without edges
+    pub fn remove_block(&mut self, block: Block) {
+        {
+            let node = &self.blocks[&block];
+            assert!(node.first_inst.is_none());
+            assert!(node.predecessors.is_empty() && node.successors.is_empty());
+        }
+
+        self.blocks.remove(&block);
+        self.layout.retain(|&b| b != block);
+
+        if self.entry_block == Some(block) {
+            self.entry_block = self.layout.first().cloned();
+        }
+    }
+
+    pub fn entry_block(&self) -> Option<Block> {
+        self.entry_block
+    }
+
+    pub fn set_entry_block(&mut self, block: Block) {
+        assert!(self.blocks.contains_key(&block));
+        self.entry_block = Some(block);
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.layout
+    }
+
+    pub fn contains_block(&self, block: Block) -> bool {
+        self.blocks.contains_key(&block)
+    }
+
+    pub fn predecessors(&self, block: Block) -> &[Block] {
+        &self.blocks[&block].predecessors
+    }
+
+    pub fn successors(&self, block: Block) -> &[Block] {
+        &self.blocks[&block].successors
+    }
+
+    pub fn add_edge(&mut self, from: Block, to: Block) {
+        self.blocks.get_mut(&from).unwrap().successors.push(to);
+        self.blocks.get_mut(&to).unwrap().predecessors.push(from);
+    }
+
+    pub fn remove_edge(&mut self, from: Block, to: Block) {
+        remove_first(&mut self.blocks.get_mut(&from).unwrap().successors, to);
+        remove_first(&mut self.blocks.get_mut(&to).unwrap().predecessors, from);
+    }
+
+    pub fn first_inst(&self, block: Block) -> Option<Inst> {
+        self.blocks[&block].first_inst
+    }
+
+    pub fn last_inst(&self, block: Block) -> Option<Inst> {
+        self.blocks[&block].last_inst
+    }
+
+    pub fn next_inst(&self, inst: Inst) -> Option<Inst> {
+        self.insts[&inst].next_inst
+    }
+
+    pub fn prev_inst(&self, inst: Inst) -> Option<Inst> {
+        self.insts[&inst].prev_inst
+    }
+
+    pub fn inst_block(&self, inst: Inst) -> Option<Block> {
+        self.insts.get(&inst).and_then(|node| node.block)
+    }
+
+    pub fn insts(&self, block: Block) -> InstIter {
+        InstIter {
+            cfg: self,
+            next: self.first_inst(block),
+        }
+    }
+
+    pub fn append_inst(&mut self, block: Block, inst: Inst) {
+        let last_inst = self.blocks[&block].last_inst;
+
+        self.insts.insert(
+            inst,
+            InstNode {
+                prev_inst: last_inst,
+                next_inst: None,
+                block: Some(block),
+            },
+        );
+
+        match last_inst {
+            Some(last_inst) => self.insts.get_mut(&last_inst).unwrap().next_inst = Some(inst),
+            None => self.blocks.get_mut(&block).unwrap().first_inst = Some(inst),
+        }
+
+        self.blocks.get_mut(&block).unwrap().last_inst = Some(inst);
+    }
+
+    pub fn prepend_inst(&mut self, block: Block, inst: Inst) {
+        match self.first_inst(block) {
+            Some(first_inst) => self.insert_inst_before(first_inst, inst),
+            None => self.append_inst(block, inst),
+        }
+    }
+
+    pub fn insert_inst_before(&mut self, before: Inst, inst: Inst) {
+        let (prev_inst, block) = {
+            let node = &self.insts[&before];
+            (
+                node.prev_inst,
+                node.block.expect("instruction not in layout"),
+            )
+        };
+
+        self.insts.insert(
+            inst,
+            InstNode {
+                prev_inst: prev_inst,
+                next_inst: Some(before),
+                block: Some(block),
+            },
+        );
+
+        self.insts.get_mut(&before).unwrap().prev_inst = Some(inst);
+
+        match prev_inst {
+            Some(prev_inst) => self.insts.get_mut(&prev_inst).unwrap().next_inst = Some(inst),
+            None => self.blocks.get_mut(&block).unwrap().first_inst = Some(inst),
+        }
+    }
+
+    pub fn remove_inst(&mut self, inst: Inst) {
+        let node = self.insts.remove(&inst).expect("instruction not in layout");
+        let block = node.block.expect("instruction not in layout");
+
+        match node.prev_inst {
+            Some(prev_inst) => self.insts.get_mut(&prev_inst).unwrap().next_inst = node.next_inst,
+            None => self.blocks.get_mut(&block).unwrap().first_inst = node.next_inst,
+        }
+
+        match node.next_inst {
+            Some(next_inst) => self.insts.get_mut(&next_inst).unwrap().prev_inst = node.prev_inst,
+            None => self.blocks.get_mut(&block).unwrap().last_inst = node.prev_inst,
+        }
+    }
+}
+
+fn remove_first(blocks: &mut Vec<Block>, block: Block) {
+    let idx = blocks
+        .iter()
+        .position(|&b| b == block)
+        .expect("edge not found");
+    blocks.remove(idx);
+}
+
+pub struct InstIter<'a> {
+    cfg: &'a ControlFlowGraph,
+    next: Option<Inst>,
+}
+
+impl<'a> Iterator for InstIter<'a> {
+    type Item = Inst;
+
+    fn next(&mut self) -> Option<Inst> {
+        let inst = self.next?;
+        self.next = self.cfg.next_inst(inst);
+        Some(inst)
+    }
 }
 
 struct BlockNode {
@@ -44,13 +227,3 @@ struct InstNode {
     next_inst: Option<Inst>,
     block: Option<Block>,
 }
-
-impl InstNode {
-    fn new() -> InstNode {
-        InstNode {
-            prev_inst: None,
-            next_inst: None,
-            block: None,
-        }
-    }
-}
@@ -0,0 +1,1402 @@
+use std::collections::HashMap;
+
+use dora_parser::lexer::position::Position;
+
+use crate::baseline::asm::BaselineAssembler;
+use crate::baseline::codegen::{
+    ptr_for_fct_id, register_for_mode, should_emit_debug, AllocationSize, CondCode, ExprStore,
+};
+use crate::baseline::dora_native::{InternalFct, InternalFctDescriptor};
+use crate::baseline::fct::{Comment, DeoptInfo, GcPoint};
+use crate::boots::function::{Block, Function, Type, Value};
+use crate::boots::inst::{BinOp, CallKind, CmpOp, ConvOp, Inst, InstData, UnOp};
+use crate::boots::regalloc::{self, Allocation, Location, Move};
+use crate::bytecode::generate::{BytecodeIdx, Register};
+use crate::class::{ClassDefId, ClassSize, FieldId, TypeParams};
+use crate::cpu::{
+    Mem, FREG_ALLOCATABLE, FREG_PARAMS, FREG_RESULT, FREG_TMP1, PARAM_OFFSET, REG_ALLOCATABLE,
+    REG_PARAMS, REG_RESULT, REG_TMP1, REG_TMP2,
+};
+use crate::gc::Address;
+use crate::masm::Label;
+use crate::mem;
+use crate::object::{Header, Str};
+use crate::opt::fct::JitOptFct;
+use crate::os::signal::Trap;
+use crate::stdlib;
+use crate::ty::{BuiltinType, MachineMode};
+use crate::vm::{Fct, FctSrc, GlobalId, VM};
+use crate::vtable::{VTable, DISPLAY_SIZE};
+
+// translates the boots IR into machine code, operands are loaded into the same
+// temporary registers as in the baseline compiler and results are stored into
+// the location picked by the register allocator
+pub fn generate<'a, 'ast: 'a>(
+    vm: &'a VM<'ast>,
+    fct: &'a Fct<'ast>,
+    src: &'a mut FctSrc,
+    cls_type_params: &'a TypeParams,
+    fct_type_params: &'a TypeParams,
+    ir: &'a Function,
+    param_types: &[Type],
+    osr: bool,
+) -> Result<JitOptFct, String> {
+    let alloc = regalloc::allocate(ir, &REG_ALLOCATABLE, &FREG_ALLOCATABLE);
+
+    // values in registers are spilled below the stack slots of the register
+    // allocator at deoptimization points, arguments passed on the stack are
+    // stored at the bottom of the frame
+    let framesize = mem::align_i32(
+        alloc.stack_size() + deopt_size(ir) + argsize(ir, &alloc),
+        16,
+    );
+
+    let mut codegen = CodeGen {
+        vm: vm,
+        fct: fct,
+        src: src,
+        cls_type_params: cls_type_params,
+        fct_type_params: fct_type_params,
+        ir: ir,
+        alloc: alloc,
+        asm: BaselineAssembler::new(vm),
+        labels: HashMap::new(),
+        framesize: framesize,
+        osr: osr,
+    };
+
+    codegen.generate(param_types)?;
+
+    Ok(codegen.asm.jit_opt(framesize, fct.id, fct.ast.throws))
+}
+
+struct CodeGen<'a, 'ast: 'a> {
+    vm: &'a VM<'ast>,
+    fct: &'a Fct<'ast>,
+    src: &'a mut FctSrc,
+    cls_type_params: &'a TypeParams,
+    fct_type_params: &'a TypeParams,
+    ir: &'a Function,
+    alloc: Allocation,
+    asm: BaselineAssembler<'a, 'ast>,
+    labels: HashMap<Block, Label>,
+    framesize: i32,
+
+    // params are passed in a buffer instead of the calling convention,
+    // the entry is used for on-stack replacement
+    osr: bool,
+}
+
+impl<'a, 'ast> CodeGen<'a, 'ast>
+where
+    'ast: 'a,
+{
+    fn generate(&mut self, param_types: &[Type]) -> Result<(), String> {
+        if should_emit_debug(self.vm, self.fct) {
+            self.asm.debug();
+        }
+
+        for &block in self.alloc.blocks() {
+            let label = self.asm.create_label();
+            self.labels.insert(block, label);
+        }
+
+        self.emit_prolog(param_types);
+
+        let blocks = self.alloc.blocks().to_vec();
+
+        for (idx, &block) in blocks.iter().enumerate() {
+            self.asm.bind_label(self.labels[&block]);
+            let next = blocks.get(idx + 1).cloned();
+
+            for inst in self.ir.insts(block) {
+                self.emit_inst(block, inst, next)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn emit_prolog(&mut self, param_types: &[Type]) {
+        self.asm.prolog(self.framesize);
+        self.asm.check_stack_limit(self.fct.pos);
+        self.asm.emit_comment(Comment::Lit("prolog end"));
+        self.asm.emit_comment(Comment::Newline);
+
+        let mut params = HashMap::new();
+
+        if let Some(entry) = self.ir.entry_block() {
+            for inst in self.ir.insts(entry) {
+                if let InstData::Param { idx, .. } = *self.ir.inst(inst) {
+                    params.insert(idx as usize, self.ir.inst_result(inst).unwrap());
+                }
+            }
+        }
+
+        if self.osr {
+            self.emit_osr_params(param_types, &params);
+            return;
+        }
+
+        let mut moves = Vec::new();
+        let mut reg_idx = 0;
+        let mut freg_idx = 0;
+        let mut param_offset = PARAM_OFFSET;
+
+        for (idx, &ty) in param_types.iter().enumerate() {
+            let src = if ty.is_float() && freg_idx < FREG_PARAMS.len() {
+                freg_idx += 1;
+                Location::FReg(FREG_PARAMS[freg_idx - 1])
+            } else if !ty.is_float() && reg_idx < REG_PARAMS.len() {
+                reg_idx += 1;
+                Location::Reg(REG_PARAMS[reg_idx - 1])
+            } else {
+                // params not passed in registers are read from the caller's frame
+                param_offset += 8;
+                Location::Stack(param_offset - 8)
+            };
+
+            if let Some(&value) = params.get(&idx) {
+                moves.push(Move {
+                    src: src,
+                    dest: self.alloc.location(value),
+                    ty: ty,
+                });
+            }
+        }
+
+        self.emit_moves(&moves);
+    }
+
+    // the buffer with the values of the baseline frame is passed in the first param
+    // register, the first slot of the buffer holds the address of the entry
+    fn emit_osr_params(&mut self, param_types: &[Type], params: &HashMap<usize, Value>) {
+        self.asm.copy_reg(MachineMode::Ptr, REG_TMP2, REG_PARAMS[0]);
+
+        for (idx, &ty) in param_types.iter().enumerate() {
+            let value = match params.get(&idx) {
+                Some(&value) => value,
+                None => continue,
+            };
+
+            let mode = machine_mode(ty);
+            let slot = Mem::Base(REG_TMP2, (idx as i32 + 1) * mem::ptr_width());
+
+            match self.alloc.location(value) {
+                Location::Stack(dest) => {
+                    let tmp = if mode.is_float() {
+                        FREG_RESULT.into()
+                    } else {
+                        REG_TMP1.into()
+                    };
+
+                    self.asm.load_mem(mode, tmp, slot);
+                    self.asm.store_mem(mode, Mem::Local(dest), tmp);
+                }
+
+                dest => self.asm.load_mem(mode, location_store(dest), slot),
+            }
+        }
+    }
+
+    fn emit_inst(&mut self, block: Block, inst: Inst, next: Option<Block>) -> Result<(), String> {
+        let pos = self.ir.dfg.position(inst).unwrap_or(self.fct.pos);
+        let result = self.ir.inst_result(inst);
+
+        // stack traces list the inlined functions of program points
+        let inlined_frames = self
+            .ir
+            .dfg
+            .inlined(inst)
+            .iter()
+            .map(|&(fct_id, pos)| (fct_id, pos.line as i32))
+            .collect();
+        self.asm.set_inlined_frames(inlined_frames);
+
+        match *self.ir.inst(inst) {
+            InstData::Binary { op, ty, lhs, rhs } => {
+                self.emit_binary(inst, op, ty, lhs, rhs, result.unwrap(), pos)?
+            }
+            InstData::Unary { op, ty, opnd } => self.emit_unary(op, ty, opnd, result.unwrap())?,
+            InstData::Convert { op, from, to, opnd } => {
+                self.emit_convert(op, from, to, opnd, result.unwrap())?
+            }
+            InstData::Cmp { ty, op, lhs, rhs } => self.emit_cmp(ty, op, lhs, rhs, result.unwrap()),
+
+            InstData::Goto { target } => {
+                if self.alloc.is_back_edge(block, target) {
+                    self.emit_safepoint(inst);
+                }
+
+                self.emit_edge(block, target, next);
+            }
+            InstData::If {
+                opnd,
+                then_block,
+                else_block,
+            } => self.emit_if(block, inst, opnd, then_block, else_block, next),
+            InstData::Ret { opnd } => self.emit_return(opnd),
+
+            InstData::TrueConst
+            | InstData::FalseConst
+            | InstData::NilConst
+            | InstData::Int8Const(_)
+            | InstData::CharConst(_)
+            | InstData::Int32Const(_)
+            | InstData::Int64Const(_)
+            | InstData::Float32Const(_)
+            | InstData::Float64Const(_) => self.emit_const(inst, result.unwrap()),
+            InstData::StringConst(ref value) => self.emit_const_string(value, result.unwrap()),
+
+            // params are moved into their locations in the prolog,
+            // phis by the predecessors
+            InstData::Param { .. } | InstData::Phi { .. } => {}
+
+            InstData::LoadField {
+                ty,
+                obj,
+                cls,
+                field,
+            } => self.emit_load_field(inst, ty, obj, cls, field, result.unwrap(), pos),
+            InstData::StoreField {
+                ty,
+                obj,
+                cls,
+                field,
+                value,
+            } => self.emit_store_field(inst, ty, obj, cls, field, value, pos),
+            InstData::LoadGlobal { ty, global } => {
+                self.emit_load_global(ty, global, result.unwrap())
+            }
+            InstData::StoreGlobal { ty, global, value } => {
+                self.emit_store_global(ty, global, value)
+            }
+            InstData::LoadArray { ty, array, index } => {
+                self.emit_load_array(ty, array, index, result.unwrap())
+            }
+            InstData::StoreArray {
+                ty,
+                array,
+                index,
+                value,
+            } => self.emit_store_array(ty, array, index, value),
+            InstData::ArrayLength { array } => self.emit_array_length(array, result.unwrap()),
+            InstData::SetUint8 { address, value } => self.emit_set_uint8(address, value),
+
+            InstData::NilCheck { opnd } => {
+                self.load(opnd, REG_RESULT.into());
+                let gcpoint = self.gcpoint(inst);
+                self.asm
+                    .test_if_nil_bailout(pos, REG_RESULT, Trap::NIL, gcpoint);
+            }
+            InstData::BoundsCheck { index, length } => {
+                self.emit_bounds_check(inst, index, length, pos)
+            }
+            InstData::InstanceOf { opnd, cls } => {
+                self.emit_instance_of(inst, opnd, cls, result, pos)
+            }
+            InstData::CheckedCast { opnd, cls } => {
+                self.emit_instance_of(inst, opnd, cls, None, pos)
+            }
+            InstData::HasClass { opnd, cls } => self.emit_has_class(opnd, cls, result.unwrap()),
+
+            InstData::Call {
+                ref kind,
+                ty,
+                ref args,
+            } => self.emit_call(inst, kind, ty, args, result, pos),
+            InstData::NewObject { cls } => {
+                self.emit_allocation(inst, cls, None, result.unwrap(), pos)
+            }
+            InstData::NewArray { cls, length } => {
+                self.emit_allocation(inst, cls, Some(length), result.unwrap(), pos)
+            }
+
+            InstData::Throw { opnd } => {
+                self.load(opnd, REG_RESULT.into());
+                self.asm.throw(REG_RESULT, pos);
+            }
+            InstData::Trap { trap } => {
+                let gcpoint = self.gcpoint(inst);
+                self.asm.emit_bailout_inplace(trap, pos, gcpoint);
+            }
+            InstData::Deoptimize { pc, ref state } => self.emit_deoptimize(pc, state, pos),
+            InstData::Spawn { opnd } => self.emit_spawn(inst, opnd, pos),
+            InstData::Debug => self.asm.debug(),
+            InstData::Deleted => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    fn emit_binary(
+        &mut self,
+        inst: Inst,
+        op: BinOp,
+        ty: Type,
+        lhs: Value,
+        rhs: Value,
+        dest: Value,
+        pos: Position,
+    ) -> Result<(), String> {
+        let mode = machine_mode(ty);
+
+        if ty.is_float() {
+            self.load(lhs, FREG_RESULT.into());
+            self.load(rhs, FREG_TMP1.into());
+
+            match op {
+                BinOp::Add => self
+                    .asm
+                    .float_add(mode, FREG_RESULT, FREG_RESULT, FREG_TMP1),
+                BinOp::Sub => self
+                    .asm
+                    .float_sub(mode, FREG_RESULT, FREG_RESULT, FREG_TMP1),
+                BinOp::Mul => self
+                    .asm
+                    .float_mul(mode, FREG_RESULT, FREG_RESULT, FREG_TMP1),
+                BinOp::Div => self
+                    .asm
+                    .float_div(mode, FREG_RESULT, FREG_RESULT, FREG_TMP1),
+                _ => return Err(format!("unsupported operation {} {}", op.name(), ty.name())),
+            }
+
+            self.store(dest, FREG_RESULT.into());
+            return Ok(());
+        }
+
+        match ty {
+            Type::Int | Type::Long => {}
+            _ => return Err(format!("unsupported operation {} {}", op.name(), ty.name())),
+        }
+
+        self.load(lhs, REG_RESULT.into());
+        self.load(rhs, REG_TMP1.into());
+
+        match op {
+            BinOp::Add => self.asm.int_add(mode, REG_RESULT, REG_RESULT, REG_TMP1),
+            BinOp::Sub => self.asm.int_sub(mode, REG_RESULT, REG_RESULT, REG_TMP1),
+            BinOp::Mul => self.asm.int_mul(mode, REG_RESULT, REG_RESULT, REG_TMP1),
+            BinOp::Div => {
+                let gcpoint = self.gcpoint(inst);
+                self.asm
+                    .int_div(mode, REG_RESULT, REG_RESULT, REG_TMP1, pos, gcpoint)
+            }
+            BinOp::Mod => {
+                let gcpoint = self.gcpoint(inst);
+                self.asm
+                    .int_mod(mode, REG_RESULT, REG_RESULT, REG_TMP1, pos, gcpoint)
+            }
+            BinOp::And => self.asm.int_and(mode, REG_RESULT, REG_RESULT, REG_TMP1),
+            BinOp::Or => self.asm.int_or(mode, REG_RESULT, REG_RESULT, REG_TMP1),
+            BinOp::Xor => self.asm.int_xor(mode, REG_RESULT, REG_RESULT, REG_TMP1),
+            BinOp::Shl => self.asm.int_shl(mode, REG_RESULT, REG_RESULT, REG_TMP1),
+            BinOp::Shr => self.asm.int_shr(mode, REG_RESULT, REG_RESULT, REG_TMP1),
+            BinOp::Sar => self.asm.int_sar(mode, REG_RESULT, REG_RESULT, REG_TMP1),
+        }
+
+        self.store(dest, REG_RESULT.into());
+        Ok(())
+    }
+
+    fn emit_unary(&mut self, op: UnOp, ty: Type, opnd: Value, dest: Value) -> Result<(), String> {
+        let mode = machine_mode(ty);
+
+        if ty.is_float() {
+            self.load(opnd, FREG_RESULT.into());
+
+            match op {
+                UnOp::Neg => self.asm.float_neg(mode, FREG_RESULT, FREG_RESULT),
+                UnOp::Sqrt => self.asm.float_sqrt(mode, FREG_RESULT, FREG_RESULT),
+                UnOp::Not => return Err(format!("unsupported operation not {}", ty.name())),
+            }
+
+            self.store(dest, FREG_RESULT.into());
+            return Ok(());
+        }
+
+        self.load(opnd, REG_RESULT.into());
+
+        match (op, ty) {
+            (UnOp::Neg, Type::Int) | (UnOp::Neg, Type::Long) => {
+                self.asm.int_neg(mode, REG_RESULT, REG_RESULT)
+            }
+            (UnOp::Not, Type::Bool) => self.asm.bool_not(REG_RESULT, REG_RESULT),
+            (UnOp::Not, Type::Byte) | (UnOp::Not, Type::Int) | (UnOp::Not, Type::Long) => {
+                self.asm.int_not(mode, REG_RESULT, REG_RESULT)
+            }
+            _ => return Err(format!("unsupported operation {} {}", op.name(), ty.name())),
+        }
+
+        self.store(dest, REG_RESULT.into());
+        Ok(())
+    }
+
+    fn emit_convert(
+        &mut self,
+        op: ConvOp,
+        from: Type,
+        to: Type,
+        opnd: Value,
+        dest: Value,
+    ) -> Result<(), String> {
+        let from_mode = machine_mode(from);
+        let to_mode = machine_mode(to);
+
+        match op {
+            ConvOp::ZeroExtend | ConvOp::Truncate | ConvOp::Reinterpret
+                if !from.is_float() && !to.is_float() =>
+            {
+                // 32-bit operations clear the upper half, bytes are extended explicitly
+                self.load(opnd, REG_RESULT.into());
+
+                if from == Type::Byte || to == Type::Byte {
+                    let mode = if to == Type::Long {
+                        MachineMode::Int64
+                    } else {
+                        MachineMode::Int32
+                    };
+                    self.asm.extend_byte(mode, REG_RESULT, REG_RESULT);
+                } else if to_mode == MachineMode::Int32 || from_mode == MachineMode::Int32 {
+                    self.asm
+                        .copy_reg(MachineMode::Int32, REG_RESULT, REG_RESULT);
+                }
+
+                self.store(dest, REG_RESULT.into());
+            }
+
+            ConvOp::SignExtend if from == Type::Int && to == Type::Long => {
+                self.load(opnd, REG_RESULT.into());
+                self.asm.extend_int_long(REG_RESULT, REG_RESULT);
+                self.store(dest, REG_RESULT.into());
+            }
+
+            ConvOp::IntToFloat => {
+                self.load(opnd, REG_RESULT.into());
+                self.asm
+                    .int_to_float(to_mode, FREG_RESULT, from_mode, REG_RESULT);
+                self.store(dest, FREG_RESULT.into());
+            }
+
+            ConvOp::FloatToInt => {
+                self.load(opnd, FREG_RESULT.into());
+                self.asm
+                    .float_to_int(to_mode, REG_RESULT, from_mode, FREG_RESULT);
+                self.store(dest, REG_RESULT.into());
+            }
+
+            ConvOp::FloatExtend => {
+                self.load(opnd, FREG_RESULT.into());
+                self.asm.float_to_double(FREG_RESULT, FREG_RESULT);
+                self.store(dest, FREG_RESULT.into());
+            }
+
+            ConvOp::FloatTruncate => {
+                self.load(opnd, FREG_RESULT.into());
+                self.asm.double_to_float(FREG_RESULT, FREG_RESULT);
+                self.store(dest, FREG_RESULT.into());
+            }
+
+            ConvOp::Reinterpret if from.is_float() && !to.is_float() => {
+                self.load(opnd, FREG_RESULT.into());
+                self.asm
+                    .float_as_int(to_mode, REG_RESULT, from_mode, FREG_RESULT);
+                self.store(dest, REG_RESULT.into());
+            }
+
+            ConvOp::Reinterpret if !from.is_float() && to.is_float() => {
+                self.load(opnd, REG_RESULT.into());
+                self.asm
+                    .int_as_float(to_mode, FREG_RESULT, from_mode, REG_RESULT);
+                self.store(dest, FREG_RESULT.into());
+            }
+
+            _ => {
+                return Err(format!(
+                    "unsupported conversion {} {} -> {}",
+                    op.name(),
+                    from.name(),
+                    to.name()
+                ))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn emit_cmp(&mut self, ty: Type, op: CmpOp, lhs: Value, rhs: Value, dest: Value) {
+        let mode = machine_mode(ty);
+        let cond = match op {
+            CmpOp::Lt => CondCode::Less,
+            CmpOp::Le => CondCode::LessEq,
+            CmpOp::Eq => CondCode::Equal,
+            CmpOp::Ne => CondCode::NotEqual,
+            CmpOp::Gt => CondCode::Greater,
+            CmpOp::Ge => CondCode::GreaterEq,
+        };
+
+        if ty.is_float() {
+            self.load(lhs, FREG_RESULT.into());
+            self.load(rhs, FREG_TMP1.into());
+            self.asm
+                .float_cmp(mode, REG_RESULT, FREG_RESULT, FREG_TMP1, cond);
+        } else {
+            self.load(lhs, REG_RESULT.into());
+            self.load(rhs, REG_TMP1.into());
+            self.asm.cmp_reg(mode, REG_RESULT, REG_TMP1);
+            self.asm.set(REG_RESULT, cond);
+        }
+
+        self.store(dest, REG_RESULT.into());
+    }
+
+    fn emit_if(
+        &mut self,
+        block: Block,
+        inst: Inst,
+        opnd: Value,
+        then_block: Block,
+        else_block: Block,
+        next: Option<Block>,
+    ) {
+        if self.alloc.is_back_edge(block, then_block) || self.alloc.is_back_edge(block, else_block)
+        {
+            self.emit_safepoint(inst);
+        }
+
+        self.load(opnd, REG_RESULT.into());
+
+        let then_moves = self.edge_moves(block, then_block);
+        let else_moves = self.edge_moves(block, else_block);
+
+        if then_moves.is_empty() && else_moves.is_empty() && next == Some(else_block) {
+            self.asm
+                .test_and_jump_if(CondCode::NonZero, REG_RESULT, self.labels[&then_block]);
+            return;
+        }
+
+        // edges with phi moves get their own code after the branch
+        let lbl_else = if else_moves.is_empty() {
+            self.labels[&else_block]
+        } else {
+            self.asm.create_label()
+        };
+
+        self.asm
+            .test_and_jump_if(CondCode::Zero, REG_RESULT, lbl_else);
+
+        self.emit_moves(&then_moves);
+
+        if else_moves.is_empty() {
+            if next != Some(then_block) {
+                self.asm.jump(self.labels[&then_block]);
+            }
+        } else {
+            self.asm.jump(self.labels[&then_block]);
+            self.asm.bind_label(lbl_else);
+            self.emit_moves(&else_moves);
+
+            if next != Some(else_block) {
+                self.asm.jump(self.labels[&else_block]);
+            }
+        }
+    }
+
+    // moves the phi inputs into the locations of the phis and jumps to the target
+    fn emit_edge(&mut self, block: Block, target: Block, next: Option<Block>) {
+        let moves = self.edge_moves(block, target);
+        self.emit_moves(&moves);
+
+        if next != Some(target) {
+            self.asm.jump(self.labels[&target]);
+        }
+    }
+
+    fn edge_moves(&self, block: Block, target: Block) -> Vec<Move> {
+        let mut moves = Vec::new();
+
+        for inst in self.ir.insts(target) {
+            match *self.ir.inst(inst) {
+                InstData::Phi { ty, ref inputs } => {
+                    let result = self.ir.inst_result(inst).unwrap();
+
+                    for &(pred, input) in inputs {
+                        if pred == block {
+                            moves.push(Move {
+                                src: self.alloc.location(input),
+                                dest: self.alloc.location(result),
+                                ty: ty,
+                            });
+                        }
+                    }
+                }
+
+                _ => break,
+            }
+        }
+
+        moves
+    }
+
+    fn emit_moves(&mut self, moves: &[Move]) {
+        let moves =
+            regalloc::sequentialize(moves, Location::Reg(REG_TMP2), Location::FReg(FREG_TMP1));
+
+        for mov in moves {
+            let mode = machine_mode(mov.ty);
+
+            match (mov.src, mov.dest) {
+                (Location::Stack(src), Location::Stack(dest)) => {
+                    let tmp = if mode.is_float() {
+                        FREG_RESULT.into()
+                    } else {
+                        REG_TMP1.into()
+                    };
+
+                    self.asm.load_mem(mode, tmp, Mem::Local(src));
+                    self.asm.store_mem(mode, Mem::Local(dest), tmp);
+                }
+
+                (Location::Stack(src), dest) => {
+                    self.asm
+                        .load_mem(mode, location_store(dest), Mem::Local(src))
+                }
+
+                (src, Location::Stack(dest)) => {
+                    self.asm
+                        .store_mem(mode, Mem::Local(dest), location_store(src))
+                }
+
+                (src, dest) => self
+                    .asm
+                    .copy(mode, location_store(dest), location_store(src)),
+            }
+        }
+    }
+
+    // values in registers are stored into the stack, the runtime then reads all
+    // values from the frame and replaces it with a baseline frame
+    fn emit_deoptimize(&mut self, pc: u32, state: &[(u32, Value)], pos: Position) {
+        let mut moves = Vec::new();
+        let mut registers = Vec::new();
+        let mut ptr_offsets = Vec::new();
+        let mut offset = -self.alloc.stack_size();
+
+        for &(reg, value) in state {
+            let ty = self.ir.value_type(value);
+
+            let value_offset = match self.alloc.location(value) {
+                Location::Stack(value_offset) => value_offset,
+                location => {
+                    offset -= mem::ptr_width();
+                    moves.push(Move {
+                        src: location,
+                        dest: Location::Stack(offset),
+                        ty: ty,
+                    });
+                    offset
+                }
+            };
+
+            if ty == Type::Ptr {
+                ptr_offsets.push(value_offset);
+            }
+
+            registers.push((Register(reg as usize), value_offset));
+        }
+
+        self.emit_moves(&moves);
+
+        let info = DeoptInfo {
+            fct_id: self.fct.id,
+            cls_type_params: self.cls_type_params.clone(),
+            fct_type_params: self.fct_type_params.clone(),
+            bytecode_idx: BytecodeIdx(pc as usize),
+            registers: registers,
+        };
+
+        let gcpoint = GcPoint::from_offsets(ptr_offsets);
+        self.asm.deoptimize(info, self.framesize, pos, gcpoint);
+    }
+
+    fn emit_safepoint(&mut self, inst: Inst) {
+        self.asm.emit_comment(Comment::ReadPollingPage);
+        self.asm.check_polling_page(self.vm.polling_page.addr());
+
+        let gcpoint = self.gcpoint(inst);
+        self.asm.emit_gcpoint(gcpoint);
+    }
+
+    fn emit_return(&mut self, opnd: Option<Value>) {
+        if let Some(opnd) = opnd {
+            let mode = machine_mode(self.ir.value_type(opnd));
+            self.load(opnd, register_for_mode(mode));
+        }
+
+        self.asm.emit_comment(Comment::Newline);
+        self.asm.emit_comment(Comment::Lit("epilog"));
+
+        let polling_page = self.vm.polling_page.addr();
+        self.asm.epilog_with_polling(self.framesize, polling_page);
+    }
+
+    fn emit_const(&mut self, inst: Inst, dest: Value) {
+        let value = self.result_store(dest);
+
+        match *self.ir.inst(inst) {
+            InstData::TrueConst => self.asm.load_true(value.reg()),
+            InstData::FalseConst => self.asm.load_false(value.reg()),
+            InstData::NilConst => self.asm.load_nil(value.reg()),
+            InstData::Int8Const(int_const) => {
+                self.asm
+                    .load_int_const(MachineMode::Int8, value.reg(), int_const as i64)
+            }
+            InstData::CharConst(char_const) => {
+                self.asm
+                    .load_int_const(MachineMode::Int32, value.reg(), char_const as i64)
+            }
+            InstData::Int32Const(int_const) => {
+                self.asm
+                    .load_int_const(MachineMode::Int32, value.reg(), int_const as i64)
+            }
+            InstData::Int64Const(int_const) => {
+                self.asm
+                    .load_int_const(MachineMode::Int64, value.reg(), int_const)
+            }
+            InstData::Float32Const(float_const) => {
+                self.asm
+                    .load_float_const(MachineMode::Float32, value.freg(), float_const as f64)
+            }
+            InstData::Float64Const(float_const) => {
+                self.asm
+                    .load_float_const(MachineMode::Float64, value.freg(), float_const)
+            }
+            _ => unreachable!(),
+        }
+
+        self.store(dest, value);
+    }
+
+    fn emit_const_string(&mut self, lit_value: &str, dest: Value) {
+        let handle = Str::from_buffer_in_perm(self.vm, lit_value.as_bytes());
+        let disp = self.asm.add_addr(handle.raw() as *const u8);
+        let pos = self.asm.pos() as i32;
+
+        self.asm.emit_comment(Comment::LoadString(handle));
+        self.asm.load_constpool(REG_RESULT, disp + pos);
+
+        self.store(dest, REG_RESULT.into());
+    }
+
+    fn emit_load_field(
+        &mut self,
+        inst: Inst,
+        ty: Type,
+        obj: Value,
+        cls_id: ClassDefId,
+        field_id: FieldId,
+        dest: Value,
+        pos: Position,
+    ) {
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+        let field = &cls.fields[field_id.idx()];
+
+        let mode = machine_mode(ty);
+        let value = register_for_mode(mode);
+
+        self.load(obj, REG_RESULT.into());
+
+        self.asm.emit_comment(Comment::LoadField(cls_id, field_id));
+        let gcpoint = self.gcpoint(inst);
+        self.asm.load_field(
+            mode,
+            value,
+            REG_RESULT,
+            field.offset,
+            pos.line as i32,
+            gcpoint,
+        );
+
+        self.store(dest, value);
+    }
+
+    fn emit_store_field(
+        &mut self,
+        inst: Inst,
+        ty: Type,
+        obj: Value,
+        cls_id: ClassDefId,
+        field_id: FieldId,
+        src: Value,
+        pos: Position,
+    ) {
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+        let field = &cls.fields[field_id.idx()];
+
+        let mode = machine_mode(ty);
+        let value = register_for_mode(mode);
+
+        self.load(obj, REG_TMP1.into());
+        self.load(src, value);
+
+        self.asm.emit_comment(Comment::StoreField(cls_id, field_id));
+
+        let write_barrier = self.vm.gc.needs_write_barrier() && ty == Type::Ptr;
+        let card_table_offset = self.vm.gc.card_table_offset();
+
+        let gcpoint = self.gcpoint(inst);
+        self.asm.store_field(
+            mode,
+            REG_TMP1,
+            field.offset,
+            value,
+            pos.line as i32,
+            write_barrier,
+            card_table_offset,
+            gcpoint,
+        );
+    }
+
+    fn emit_load_global(&mut self, ty: Type, gid: GlobalId, dest: Value) {
+        let glob = self.vm.globals.idx(gid);
+        let glob = glob.lock();
+
+        let disp = self.asm.add_addr(glob.address_value.to_ptr());
+        let pos = self.asm.pos() as i32;
+
+        self.asm.emit_comment(Comment::LoadGlobal(gid));
+        self.asm.load_constpool(REG_TMP1, disp + pos);
+
+        let mode = machine_mode(ty);
+        let value = register_for_mode(mode);
+
+        self.asm.load_mem(mode, value, Mem::Base(REG_TMP1, 0));
+        self.store(dest, value);
+    }
+
+    fn emit_store_global(&mut self, ty: Type, gid: GlobalId, src: Value) {
+        let glob = self.vm.globals.idx(gid);
+        let glob = glob.lock();
+
+        let mode = machine_mode(ty);
+        let value = register_for_mode(mode);
+        self.load(src, value);
+
+        let disp = self.asm.add_addr(glob.address_value.to_ptr());
+        let pos = self.asm.pos() as i32;
+
+        self.asm.emit_comment(Comment::StoreGlobal(gid));
+        self.asm.load_constpool(REG_TMP1, disp + pos);
+
+        self.asm.store_mem(mode, Mem::Base(REG_TMP1, 0), value);
+    }
+
+    // nil and bounds checks are separate instructions
+    fn emit_load_array(&mut self, ty: Type, array: Value, index: Value, dest: Value) {
+        self.load(array, REG_RESULT.into());
+        self.load(index, REG_TMP1.into());
+
+        let mode = machine_mode(ty);
+        let value = register_for_mode(mode);
+
+        self.asm.load_array_elem(mode, value, REG_RESULT, REG_TMP1);
+        self.store(dest, value);
+    }
+
+    fn emit_store_array(&mut self, ty: Type, array: Value, index: Value, src: Value) {
+        self.load(array, REG_TMP1.into());
+        self.load(index, REG_TMP2.into());
+
+        let mode = machine_mode(ty);
+        let value = register_for_mode(mode);
+        self.load(src, value);
+
+        let write_barrier = self.vm.gc.needs_write_barrier() && ty == Type::Ptr;
+        let card_table_offset = self.vm.gc.card_table_offset();
+
+        self.asm.store_array_elem(
+            mode,
+            REG_TMP1,
+            REG_TMP2,
+            value,
+            write_barrier,
+            card_table_offset,
+        );
+    }
+
+    fn emit_array_length(&mut self, array: Value, dest: Value) {
+        self.load(array, REG_RESULT.into());
+        self.asm.load_mem(
+            MachineMode::Int32,
+            REG_RESULT.into(),
+            Mem::Base(REG_RESULT, Header::size()),
+        );
+        self.store(dest, REG_RESULT.into());
+    }
+
+    fn emit_set_uint8(&mut self, address: Value, value: Value) {
+        self.load(address, REG_RESULT.into());
+        self.load(value, REG_TMP1.into());
+
+        self.asm
+            .store_mem(MachineMode::Int8, Mem::Base(REG_RESULT, 0), REG_TMP1.into());
+    }
+
+    fn emit_bounds_check(&mut self, inst: Inst, index: Value, length: Value, pos: Position) {
+        self.load(index, REG_RESULT.into());
+        self.load(length, REG_TMP1.into());
+        self.asm.cmp_reg(MachineMode::Int32, REG_RESULT, REG_TMP1);
+
+        let lbl = self.asm.create_label();
+        self.asm.jump_if(CondCode::UnsignedGreaterEq, lbl);
+        let gcpoint = self.gcpoint(inst);
+        self.asm
+            .emit_bailout(lbl, Trap::INDEX_OUT_OF_BOUNDS, pos, gcpoint);
+    }
+
+    fn emit_allocation(
+        &mut self,
+        inst: Inst,
+        cls_id: ClassDefId,
+        length: Option<Value>,
+        dest: Value,
+        pos: Position,
+    ) {
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+
+        // allocate storage for object
+        self.asm.emit_comment(Comment::Alloc(cls_id));
+
+        let element_size = match cls.size {
+            ClassSize::Fixed(_) => None,
+            ClassSize::Array(esize) => Some(esize),
+            ClassSize::ObjArray => Some(mem::ptr_width()),
+            ClassSize::Str => Some(1),
+            ClassSize::FreeArray => unreachable!(),
+        };
+
+        let alloc_size = match cls.size {
+            ClassSize::Fixed(size) => {
+                self.asm
+                    .load_int_const(MachineMode::Int32, REG_PARAMS[0], size as i64);
+                AllocationSize::Fixed(size as usize)
+            }
+
+            _ => {
+                let length = length.expect("array allocation without length");
+                self.load(length, REG_TMP1.into());
+
+                self.asm
+                    .determine_array_size(REG_PARAMS[0], REG_TMP1, element_size.unwrap(), true);
+
+                AllocationSize::Dynamic(REG_PARAMS[0])
+            }
+        };
+
+        let array_ref = match cls.size {
+            ClassSize::ObjArray => true,
+            _ => false,
+        };
+
+        let gcpoint = self.gcpoint(inst);
+        self.asm
+            .allocate(REG_RESULT, alloc_size, pos, array_ref, gcpoint);
+
+        // store gc object in its location
+        self.store(dest, REG_RESULT.into());
+
+        // store classptr in object
+        let cptr = (&**cls.vtable.as_ref().unwrap()) as *const VTable as *const u8;
+        let disp = self.asm.add_addr(cptr);
+        let pos = self.asm.pos() as i32;
+
+        self.asm.emit_comment(Comment::StoreVTable(cls_id));
+        self.asm.load_constpool(REG_TMP1, disp + pos);
+        self.asm
+            .store_mem(MachineMode::Ptr, Mem::Base(REG_RESULT, 0), REG_TMP1.into());
+
+        // clear mark/fwdptr word in header
+        assert!(Header::size() == 2 * mem::ptr_width());
+        self.asm.load_int_const(MachineMode::Ptr, REG_TMP1, 0);
+        self.asm.store_mem(
+            MachineMode::Ptr,
+            Mem::Base(REG_RESULT, mem::ptr_width()),
+            REG_TMP1.into(),
+        );
+
+        match cls.size {
+            ClassSize::Fixed(size) => {
+                self.asm.fill_zero(REG_RESULT, size as usize);
+            }
+
+            _ => {
+                // store length in object
+                self.load(length.unwrap(), REG_TMP1.into());
+                self.asm.store_mem(
+                    MachineMode::Ptr,
+                    Mem::Base(REG_RESULT, Header::size()),
+                    REG_TMP1.into(),
+                );
+
+                self.asm.int_add_imm(
+                    MachineMode::Ptr,
+                    REG_RESULT,
+                    REG_RESULT,
+                    (Header::size() + mem::ptr_width()) as i64,
+                );
+
+                self.asm
+                    .determine_array_size(REG_TMP1, REG_TMP1, element_size.unwrap(), false);
+                self.asm
+                    .int_add(MachineMode::Ptr, REG_TMP1, REG_TMP1, REG_RESULT);
+                self.asm.fill_zero_dynamic(REG_RESULT, REG_TMP1);
+            }
+        }
+    }
+
+    fn emit_instance_of(
+        &mut self,
+        inst: Inst,
+        opnd: Value,
+        cls_id: ClassDefId,
+        dest: Option<Value>,
+        pos: Position,
+    ) {
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+        let vtable: &VTable = cls.vtable.as_ref().unwrap();
+
+        // `is` results in false and `as` succeeds if object is nil
+        self.load(opnd, REG_RESULT.into());
+        let lbl_nil = self.asm.test_if_nil(REG_RESULT);
+
+        // tmp1 = <vtable of object>
+        self.asm
+            .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Base(REG_RESULT, 0));
+
+        let disp = self.asm.add_addr(vtable as *const _ as *mut u8);
+        let asm_pos = self.asm.pos() as i32;
+
+        // tmp2 = <vtable of T>
+        self.asm.load_constpool(REG_TMP2, disp + asm_pos);
+
+        if vtable.subtype_depth >= DISPLAY_SIZE as i32 {
+            self.asm.cmp_mem_imm(
+                MachineMode::Int32,
+                Mem::Base(REG_TMP1, VTable::offset_of_depth()),
+                vtable.subtype_depth,
+            );
+
+            let lbl_false = self.asm.create_label();
+            self.asm.jump_if(CondCode::Less, lbl_false);
+
+            self.asm.load_mem(
+                MachineMode::Ptr,
+                REG_TMP1.into(),
+                Mem::Base(REG_TMP1, VTable::offset_of_overflow()),
+            );
+
+            let overflow_offset = mem::ptr_width() * (vtable.subtype_depth - DISPLAY_SIZE as i32);
+
+            self.asm.cmp_mem(
+                MachineMode::Ptr,
+                Mem::Base(REG_TMP1, overflow_offset),
+                REG_TMP2,
+            );
+
+            if dest.is_some() {
+                self.asm.set(REG_RESULT, CondCode::Equal);
+            } else {
+                self.asm.jump_if(CondCode::NonZero, lbl_false);
+            }
+
+            let lbl_finished = self.asm.create_label();
+            self.asm.jump(lbl_finished);
+
+            self.asm.bind_label(lbl_false);
+
+            if dest.is_some() {
+                self.asm.load_false(REG_RESULT);
+            } else {
+                let gcpoint = self.gcpoint(inst);
+                self.asm.emit_bailout_inplace(Trap::CAST, pos, gcpoint);
+            }
+
+            self.asm.bind_label(lbl_finished);
+        } else {
+            let display_entry =
+                VTable::offset_of_display() + vtable.subtype_depth * mem::ptr_width();
+
+            self.asm.cmp_mem(
+                MachineMode::Ptr,
+                Mem::Base(REG_TMP1, display_entry),
+                REG_TMP2,
+            );
+
+            if dest.is_some() {
+                self.asm.set(REG_RESULT, CondCode::Equal);
+            } else {
+                let lbl_bailout = self.asm.create_label();
+                self.asm.jump_if(CondCode::NotEqual, lbl_bailout);
+                let gcpoint = self.gcpoint(inst);
+                self.asm.emit_bailout(lbl_bailout, Trap::CAST, pos, gcpoint);
+            }
+        }
+
+        self.asm.bind_label(lbl_nil);
+
+        // for nil REG_RESULT is 0, which is boolean false
+        if let Some(dest) = dest {
+            self.store(dest, REG_RESULT.into());
+        }
+    }
+
+    fn emit_has_class(&mut self, opnd: Value, cls_id: ClassDefId, dest: Value) {
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+        let vtable: &VTable = cls.vtable.as_ref().unwrap();
+
+        // tmp1 = <vtable of object>
+        self.load(opnd, REG_RESULT.into());
+        self.asm
+            .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Base(REG_RESULT, 0));
+
+        let disp = self.asm.add_addr(vtable as *const _ as *mut u8);
+        let asm_pos = self.asm.pos() as i32;
+
+        // tmp2 = <vtable of T>
+        self.asm.load_constpool(REG_TMP2, disp + asm_pos);
+        self.asm.cmp_reg(MachineMode::Ptr, REG_TMP1, REG_TMP2);
+        self.asm.set(REG_RESULT, CondCode::Equal);
+
+        self.store(dest, REG_RESULT.into());
+    }
+
+    fn emit_call(
+        &mut self,
+        inst: Inst,
+        kind: &CallKind,
+        ty: Option<Type>,
+        args: &[Value],
+        dest: Option<Value>,
+        pos: Position,
+    ) {
+        self.emit_call_args(args);
+
+        let gcpoint = self.gcpoint(inst);
+        let (return_type, value) = call_result(ty);
+
+        match *kind {
+            CallKind::Direct(fct_id, ref cls_type_params, ref fct_type_params)
+            | CallKind::Static(fct_id, ref cls_type_params, ref fct_type_params) => {
+                let ptr = ptr_for_fct_id(
+                    self.vm,
+                    self.fct,
+                    self.src,
+                    fct_id,
+                    cls_type_params.clone(),
+                    fct_type_params.clone(),
+                );
+
+                self.asm.emit_comment(Comment::CallDirect(fct_id));
+                self.asm.direct_call(
+                    fct_id,
+                    ptr.to_ptr(),
+                    cls_type_params.clone(),
+                    fct_type_params.clone(),
+                    pos,
+                    gcpoint,
+                    return_type,
+                    value,
+                );
+            }
+
+            CallKind::Virtual(fct_id) => {
+                let vtable_index = {
+                    let fct = self.vm.fcts.idx(fct_id);
+                    let fct = fct.read();
+                    fct.vtable_index.unwrap()
+                };
+
+                self.asm.emit_comment(Comment::CallVirtual(fct_id));
+                self.asm
+                    .indirect_call(vtable_index, pos, gcpoint, return_type, value);
+            }
+
+            CallKind::Lambda => {
+                self.asm.emit_comment(Comment::Lit("call lambda"));
+                self.asm.indirect_call(0, pos, gcpoint, return_type, value);
+            }
+        }
+
+        if let Some(dest) = dest {
+            self.store(dest, value);
+        }
+    }
+
+    // values never live in parameter registers, so arguments can be loaded in any order
+    fn emit_call_args(&mut self, args: &[Value]) {
+        let mut arg_offset = -self.framesize;
+        let mut reg_idx = 0;
+        let mut freg_idx = 0;
+
+        for &arg in args {
+            let mode = machine_mode(self.ir.value_type(arg));
+
+            if mode.is_float() {
+                if freg_idx < FREG_PARAMS.len() {
+                    self.load(arg, FREG_PARAMS[freg_idx].into());
+                    freg_idx += 1;
+                } else {
+                    self.load(arg, FREG_RESULT.into());
+                    self.asm
+                        .store_mem(mode, Mem::Local(arg_offset), FREG_RESULT.into());
+                    arg_offset += 8;
+                }
+            } else {
+                if reg_idx < REG_PARAMS.len() {
+                    self.load(arg, REG_PARAMS[reg_idx].into());
+                    reg_idx += 1;
+                } else {
+                    self.load(arg, REG_TMP1.into());
+                    self.asm
+                        .store_mem(mode, Mem::Local(arg_offset), REG_TMP1.into());
+                    arg_offset += 8;
+                }
+            }
+        }
+    }
+
+    fn emit_spawn(&mut self, inst: Inst, opnd: Value, pos: Position) {
+        self.load(opnd, REG_PARAMS[0].into());
+
+        let internal_fct = InternalFct {
+            ptr: Address::from_ptr(stdlib::spawn_lambda as *const u8),
+            args: &[BuiltinType::Ptr],
+            return_type: BuiltinType::Unit,
+            throws: false,
+            desc: InternalFctDescriptor::SpawnThunk,
+        };
+
+        let gcpoint = self.gcpoint(inst);
+        self.asm
+            .native_call(internal_fct, pos, gcpoint, REG_RESULT.into());
+    }
+
+    fn gcpoint(&self, inst: Inst) -> GcPoint {
+        GcPoint::from_offsets(self.alloc.gcpoint_offsets(inst))
+    }
+
+    fn load(&mut self, value: Value, dest: ExprStore) {
+        let mode = machine_mode(self.ir.value_type(value));
+
+        match self.alloc.location(value) {
+            Location::Stack(offset) => self.asm.load_mem(mode, dest, Mem::Local(offset)),
+            location => self.asm.copy(mode, dest, location_store(location)),
+        }
+    }
+
+    fn store(&mut self, value: Value, src: ExprStore) {
+        let mode = machine_mode(self.ir.value_type(value));
+
+        match self.alloc.location(value) {
+            Location::Stack(offset) => self.asm.store_mem(mode, Mem::Local(offset), src),
+            location => {
+                let dest = location_store(location);
+
+                if dest != src {
+                    self.asm.copy(mode, dest, src);
+                }
+            }
+        }
+    }
+
+    // register the result can be computed in directly, float constants are
+    // loaded pc-relative which only works for the lower float registers
+    fn result_store(&self, value: Value) -> ExprStore {
+        match self.alloc.location(value) {
+            Location::Reg(reg) => reg.into(),
+            _ => register_for_mode(machine_mode(self.ir.value_type(value))),
+        }
+    }
+}
+
+// stack area for values in registers at deoptimization points
+fn deopt_size(ir: &Function) -> i32 {
+    let mut size = 0;
+
+    for &block in ir.blocks() {
+        for inst in ir.insts(block) {
+            if let InstData::Deoptimize { ref state, .. } = *ir.inst(inst) {
+                size = size.max(state.len() as i32 * mem::ptr_width());
+            }
+        }
+    }
+
+    size
+}
+
+fn location_store(location: Location) -> ExprStore {
+    match location {
+        Location::Reg(reg) => reg.into(),
+        Location::FReg(freg) => freg.into(),
+        Location::Stack(_) => unreachable!(),
+    }
+}
+
+fn machine_mode(ty: Type) -> MachineMode {
+    match ty {
+        Type::Bool | Type::Byte => MachineMode::Int8,
+        Type::Char | Type::Int => MachineMode::Int32,
+        Type::Long => MachineMode::Int64,
+        Type::Float => MachineMode::Float32,
+        Type::Double => MachineMode::Float64,
+        Type::Ptr => MachineMode::Ptr,
+    }
+}
+
+// machine register and type used for the result of a call
+fn call_result(ty: Option<Type>) -> (BuiltinType, ExprStore) {
+    let ty = match ty {
+        None => return (BuiltinType::Unit, REG_RESULT.into()),
+        Some(Type::Bool) => BuiltinType::Bool,
+        Some(Type::Byte) => BuiltinType::Byte,
+        Some(Type::Char) => BuiltinType::Char,
+        Some(Type::Int) => BuiltinType::Int,
+        Some(Type::Long) => BuiltinType::Long,
+        Some(Type::Float) => BuiltinType::Float,
+        Some(Type::Double) => BuiltinType::Double,
+        Some(Type::Ptr) => BuiltinType::Ptr,
+    };
+
+    (ty, register_for_mode(ty.mode()))
+}
+
+// size of the stack area needed for arguments not passed in registers
+fn argsize(ir: &Function, alloc: &Allocation) -> i32 {
+    let mut argsize = 0;
+
+    for &block in alloc.blocks() {
+        for inst in ir.insts(block) {
+            let args = match *ir.inst(inst) {
+                InstData::Call { ref args, .. } => args,
+                _ => continue,
+            };
+
+            let freg_args = args
+                .iter()
+                .filter(|&&arg| ir.value_type(arg).is_float())
+                .count();
+            let reg_args = args.len() - freg_args;
+
+            let args_on_stack = reg_args.saturating_sub(REG_PARAMS.len())
+                + freg_args.saturating_sub(FREG_PARAMS.len());
+            argsize = argsize.max(8 * args_on_stack as i32);
+        }
+    }
+
+    argsize
+}
@@ -1,19 +1,29 @@
-use crate::boots::function::{Block, BlockData, Value, ValueData};
+use std::collections::hash_map::HashMap;
+
+use crate::boots::function::{Block, BlockData, Type, Value, ValueData};
 use crate::boots::inst::{Inst, InstData};
 use crate::boots::utils::VecMap;
+use crate::vm::FctId;
+use dora_parser::lexer::position::Position;
 
 pub struct DataFlowGraph {
     insts: VecMap<Inst, InstData>,
+    results: VecMap<Inst, Option<Value>>,
     blocks: VecMap<Block, BlockData>,
     values: VecMap<Value, ValueData>,
+    positions: HashMap<Inst, Position>,
+    inlined: HashMap<Inst, Vec<(FctId, Position)>>,
 }
 
 impl DataFlowGraph {
     pub fn new() -> DataFlowGraph {
         DataFlowGraph {
             insts: VecMap::new(),
+            results: VecMap::new(),
             blocks: VecMap::new(),
             values: VecMap::new(),
+            positions: HashMap::new(),
+            inlined: HashMap::new(),
         }
     }
 
@@ -21,11 +31,68 @@ impl DataFlowGraph {
         self.blocks.push(BlockData::new())
     }
 
+    // creates the instruction and its result value
     pub fn make_inst(&mut self, inst_data: InstData) -> Inst {
-        self.insts.push(inst_data)
+        let ty = inst_data.result_type();
+        let inst = self.insts.push(inst_data);
+
+        let result = ty.map(|ty| self.make_value(ValueData::Inst { ty: ty, inst: inst }));
+        self.results.push(result);
+
+        inst
     }
 
     pub fn make_value(&mut self, value_data: ValueData) -> Value {
         self.values.push(value_data)
     }
+
+    pub fn inst(&self, inst: Inst) -> &InstData {
+        &self.insts[inst]
+    }
+
+    pub fn inst_mut(&mut self, inst: Inst) -> &mut InstData {
+        &mut self.insts[inst]
+    }
+
+    pub fn num_insts(&self) -> usize {
+        self.insts.len()
+    }
+
+    pub fn inst_result(&self, inst: Inst) -> Option<Value> {
+        self.results[inst]
+    }
+
+    pub fn value_type(&self, value: Value) -> Type {
+        match self.values[value] {
+            ValueData::Inst { ty, .. } => ty,
+        }
+    }
+
+    // the instruction defining `value`
+    pub fn value_inst(&self, value: Value) -> Inst {
+        match self.values[value] {
+            ValueData::Inst { inst, .. } => inst,
+        }
+    }
+
+    pub fn set_position(&mut self, inst: Inst, pos: Position) {
+        self.positions.insert(inst, pos);
+    }
+
+    pub fn position(&self, inst: Inst) -> Option<Position> {
+        self.positions.get(&inst).cloned()
+    }
+
+    // the functions an inlined instruction was copied from, the innermost function
+    // comes first together with the position of its call site
+    pub fn set_inlined(&mut self, inst: Inst, frames: Vec<(FctId, Position)>) {
+        self.inlined.insert(inst, frames);
+    }
+
+    pub fn inlined(&self, inst: Inst) -> &[(FctId, Position)] {
+        match self.inlined.get(&inst) {
+            Some(frames) => frames,
+            None => &[],
+        }
+    }
 }
@@ -0,0 +1,244 @@
+use std::collections::hash_map::HashMap;
+use std::collections::HashSet;
+
+use crate::boots::function::{Block, Function};
+
+// dominator tree of all blocks reachable from the entry block, computed with
+// the iterative algorithm by Cooper, Harvey and Kennedy
+pub struct DominatorTree {
+    rpo: Vec<Block>,
+    rpo_number: HashMap<Block, usize>,
+    idom: HashMap<Block, Block>,
+    children: HashMap<Block, Vec<Block>>,
+}
+
+impl DominatorTree {
+    pub fn compute(fct: &Function) -> DominatorTree {
+        let rpo = reverse_postorder(fct);
+        let rpo_number: HashMap<Block, usize> =
+            rpo.iter().enumerate().map(|(idx, &b)| (b, idx)).collect();
+
+        // idoms by reverse postorder number, the entry block is its own idom
+        let mut idoms: Vec<Option<usize>> = vec![None; rpo.len()];
+
+        if !rpo.is_empty() {
+            idoms[0] = Some(0);
+        }
+
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for (idx, &block) in rpo.iter().enumerate().skip(1) {
+                let mut new_idom: Option<usize> = None;
+
+                for pred in fct.cfg.predecessors(block) {
+                    let pred = match rpo_number.get(pred) {
+                        Some(&pred) => pred,
+                        None => continue,
+                    };
+
+                    if idoms[pred].is_none() {
+                        continue;
+                    }
+
+                    new_idom = Some(match new_idom {
+                        Some(new_idom) => intersect(&idoms, pred, new_idom),
+                        None => pred,
+                    });
+                }
+
+                if new_idom.is_some() && idoms[idx] != new_idom {
+                    idoms[idx] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut idom = HashMap::new();
+        let mut children: HashMap<Block, Vec<Block>> = HashMap::new();
+
+        for (idx, &block) in rpo.iter().enumerate().skip(1) {
+            let dominator = rpo[idoms[idx].expect("block without idom")];
+            idom.insert(block, dominator);
+            children.entry(dominator).or_default().push(block);
+        }
+
+        DominatorTree {
+            rpo: rpo,
+            rpo_number: rpo_number,
+            idom: idom,
+            children: children,
+        }
+    }
+
+    // immediate dominator, None for the entry block and unreachable blocks
+    pub fn idom(&self, block: Block) -> Option<Block> {
+        self.idom.get(&block).cloned()
+    }
+
+    pub fn dominates(&self, dominator: Block, block: Block) -> bool {
+        if !self.is_reachable(block) {
+            return false;
+        }
+
+        let mut current = block;
+
+        loop {
+            if current == dominator {
+                return true;
+            }
+
+            match self.idom(current) {
+                Some(idom) => current = idom,
+                None => return false,
+            }
+        }
+    }
+
+    pub fn strictly_dominates(&self, dominator: Block, block: Block) -> bool {
+        dominator != block && self.dominates(dominator, block)
+    }
+
+    pub fn children(&self, block: Block) -> &[Block] {
+        self.children
+            .get(&block)
+            .map(|children| &children[..])
+            .unwrap_or(&[])
+    }
+
+    pub fn is_reachable(&self, block: Block) -> bool {
+        self.rpo_number.contains_key(&block)
+    }
+
+    pub fn reverse_postorder(&self) -> &[Block] {
+        &self.rpo
+    }
+
+    pub fn rpo_number(&self, block: Block) -> Option<usize> {
+        self.rpo_number.get(&block).cloned()
+    }
+}
+
+fn intersect(idoms: &[Option<usize>], mut b1: usize, mut b2: usize) -> usize {
+    while b1 != b2 {
+        while b1 > b2 {
+            b1 = idoms[b1].unwrap();
+        }
+
+        while b2 > b1 {
+            b2 = idoms[b2].unwrap();
+        }
+    }
+
+    b1
+}
+
+pub fn reverse_postorder(fct: &Function) -> Vec<Block> {
+    let entry = match fct.entry_block() {
+        Some(entry) => entry,
+        None => return Vec::new(),
+    };
+
+    let mut postorder = Vec::new();
+    let mut visited = HashSet::new();
+    let mut worklist = vec![(entry, 0)];
+    visited.insert(entry);
+
+    while let Some(&mut (block, ref mut next_succ)) = worklist.last_mut() {
+        let successors = fct.cfg.successors(block);
+
+        if *next_succ < successors.len() {
+            let succ = successors[*next_succ];
+            *next_succ += 1;
+
+            if visited.insert(succ) {
+                worklist.push((succ, 0));
+            }
+        } else {
+            postorder.push(block);
+            worklist.pop();
+        }
+    }
+
+    postorder.reverse();
+    postorder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DominatorTree;
+    use crate::boots::function::{Block, Function};
+    use crate::boots::inst::InstData;
+
+    fn goto(fct: &mut Function, from: Block, to: Block) {
+        fct.append_inst(from, InstData::Goto { target: to });
+    }
+
+    fn branch(fct: &mut Function, from: Block, then_block: Block, else_block: Block) {
+        let cond = fct.append_inst(from, InstData::TrueConst);
+        let cond = fct.inst_result(cond).unwrap();
+        fct.append_inst(
+            from,
+            InstData::If {
+                opnd: cond,
+                then_block: then_block,
+                else_block: else_block,
+            },
+        );
+    }
+
+    #[test]
+    fn dom_diamond() {
+        let mut fct = Function::new();
+        let b0 = fct.make_block();
+        let b1 = fct.make_block();
+        let b2 = fct.make_block();
+        let b3 = fct.make_block();
+
+        branch(&mut fct, b0, b1, b2);
+        goto(&mut fct, b1, b3);
+        goto(&mut fct, b2, b3);
+
+        let dom = DominatorTree::compute(&fct);
+        assert_eq!(None, dom.idom(b0));
+        assert_eq!(Some(b0), dom.idom(b1));
+        assert_eq!(Some(b0), dom.idom(b2));
+        assert_eq!(Some(b0), dom.idom(b3));
+        assert!(dom.dominates(b0, b3));
+        assert!(!dom.dominates(b1, b3));
+        assert_eq!(
+            &[b1, b2, b3],
+            {
+                let mut children = dom.children(b0).to_vec();
+                children.sort();
+                children
+            }
+            .as_slice()
+        );
+    }
+
+    #[test]
+    fn dom_loop() {
+        let mut fct = Function::new();
+        let b0 = fct.make_block();
+        let b1 = fct.make_block();
+        let b2 = fct.make_block();
+        let b3 = fct.make_block();
+        let b4 = fct.make_block();
+
+        goto(&mut fct, b0, b1);
+        branch(&mut fct, b1, b2, b3);
+        goto(&mut fct, b2, b1);
+        fct.append_inst(b3, InstData::Ret { opnd: None });
+
+        let dom = DominatorTree::compute(&fct);
+        assert_eq!(Some(b1), dom.idom(b2));
+        assert_eq!(Some(b1), dom.idom(b3));
+        assert!(dom.strictly_dominates(b1, b2));
+        assert!(!dom.dominates(b2, b1));
+        assert!(!dom.is_reachable(b4));
+        assert_eq!(&[b0, b1], &dom.reverse_postorder()[..2]);
+    }
+}
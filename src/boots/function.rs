@@ -1,27 +1,176 @@
-use crate::boots::cfg::ControlFlowGraph;
+use crate::boots::cfg::{ControlFlowGraph, InstIter};
 use crate::boots::dfg::DataFlowGraph;
-use crate::boots::inst::Inst;
+use crate::boots::inst::{Inst, InstData};
 use crate::boots::utils::VecKey;
+use crate::bytecode::generate::BytecodeType;
 
-struct Function {
-    dfg: DataFlowGraph,
-    cfg: ControlFlowGraph,
+pub struct Function {
+    pub name: String,
+    pub dfg: DataFlowGraph,
+    pub cfg: ControlFlowGraph,
 }
 
 impl Function {
-    fn new() -> Function {
+    pub fn new() -> Function {
         Function {
+            name: String::new(),
             dfg: DataFlowGraph::new(),
             cfg: ControlFlowGraph::new(),
         }
     }
 
-    fn make_block(&mut self) -> Block {
-        self.dfg.make_block()
+    // the first block created becomes the entry block
+    pub fn make_block(&mut self) -> Block {
+        let block = self.dfg.make_block();
+        self.cfg.add_block(block);
+        block
+    }
+
+    pub fn entry_block(&self) -> Option<Block> {
+        self.cfg.entry_block()
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        self.cfg.blocks()
+    }
+
+    pub fn insts(&self, block: Block) -> InstIter {
+        self.cfg.insts(block)
+    }
+
+    pub fn inst(&self, inst: Inst) -> &InstData {
+        self.dfg.inst(inst)
+    }
+
+    pub fn inst_result(&self, inst: Inst) -> Option<Value> {
+        self.dfg.inst_result(inst)
+    }
+
+    pub fn value_type(&self, value: Value) -> Type {
+        self.dfg.value_type(value)
+    }
+
+    // appends the instruction to `block`, terminators also add the
+    // edges to their successors
+    pub fn append_inst(&mut self, block: Block, inst_data: InstData) -> Inst {
+        let inst = self.dfg.make_inst(inst_data);
+        self.cfg.append_inst(block, inst);
+
+        for succ in self.dfg.inst(inst).successors() {
+            self.cfg.add_edge(block, succ);
+        }
+
+        inst
+    }
+
+    // inserts a non-terminator at the start of `block`
+    pub fn prepend_inst(&mut self, block: Block, inst_data: InstData) -> Inst {
+        assert!(!inst_data.is_terminator());
+        let inst = self.dfg.make_inst(inst_data);
+        self.cfg.prepend_inst(block, inst);
+        inst
+    }
+
+    // removes the instruction from its block, terminators also remove
+    // the edges to their successors
+    pub fn remove_inst(&mut self, inst: Inst) {
+        let block = self
+            .cfg
+            .inst_block(inst)
+            .expect("instruction not in layout");
+
+        for succ in self.dfg.inst(inst).successors() {
+            self.cfg.remove_edge(block, succ);
+        }
+
+        self.cfg.remove_inst(inst);
+    }
+
+    // inserts a non-terminator before `before` in the same block
+    pub fn insert_inst_before(&mut self, before: Inst, inst_data: InstData) -> Inst {
+        assert!(!inst_data.is_terminator());
+        let inst = self.dfg.make_inst(inst_data);
+        self.cfg.insert_inst_before(before, inst);
+        inst
+    }
+
+    // replaces the instruction in place, the result value stays the same
+    // and edges are updated when a terminator changes its successors
+    pub fn replace_inst(&mut self, inst: Inst, inst_data: InstData) {
+        assert_eq!(self.dfg.inst(inst).result_type(), inst_data.result_type());
+        let block = self
+            .cfg
+            .inst_block(inst)
+            .expect("instruction not in layout");
+
+        for succ in self.dfg.inst(inst).successors() {
+            self.cfg.remove_edge(block, succ);
+        }
+
+        for succ in inst_data.successors() {
+            self.cfg.add_edge(block, succ);
+        }
+
+        *self.dfg.inst_mut(inst) = inst_data;
+    }
+
+    // moves the instruction to the end of `block`
+    pub fn move_inst(&mut self, inst: Inst, block: Block) {
+        let successors = self.dfg.inst(inst).successors();
+        self.remove_inst(inst);
+        self.cfg.append_inst(block, inst);
+
+        for succ in successors {
+            self.cfg.add_edge(block, succ);
+        }
+    }
+
+    // moves a non-terminator before `before`, which may be in another block
+    pub fn move_inst_before(&mut self, inst: Inst, before: Inst) {
+        assert!(!self.dfg.inst(inst).is_terminator());
+        self.cfg.remove_inst(inst);
+        self.cfg.insert_inst_before(before, inst);
+    }
+
+    // removes an empty block without edges
+    pub fn remove_block(&mut self, block: Block) {
+        self.cfg.remove_block(block);
+    }
+
+    // removes the phi inputs for the edge from `pred` to `block`
+    pub fn remove_phi_inputs(&mut self, block: Block, pred: Block) {
+        let phis: Vec<Inst> = self
+            .insts(block)
+            .take_while(|&inst| self.inst(inst).is_phi())
+            .collect();
+
+        for phi in phis {
+            if let InstData::Phi { ref mut inputs, .. } = *self.dfg.inst_mut(phi) {
+                if let Some(idx) = inputs.iter().position(|&(block, _)| block == pred) {
+                    inputs.remove(idx);
+                }
+            }
+        }
+    }
+
+    // the last instruction of `block` if it ends the block
+    pub fn terminator(&self, block: Block) -> Option<Inst> {
+        self.cfg
+            .last_inst(block)
+            .filter(|&inst| self.dfg.inst(inst).is_terminator())
+    }
+
+    // replaces every use of `old` in instructions of the layout by `new`
+    pub fn replace_uses(&mut self, old: Value, new: Value) {
+        for &block in self.cfg.blocks() {
+            for inst in self.cfg.insts(block) {
+                self.dfg.inst_mut(inst).replace_operand(old, new);
+            }
+        }
     }
 }
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Block(u32);
 
 impl VecKey for Block {
@@ -34,17 +183,15 @@ impl VecKey for Block {
     }
 }
 
-pub struct BlockData {
-    params: Vec<Type>,
-}
+pub struct BlockData;
 
 impl BlockData {
     pub fn new() -> BlockData {
-        BlockData { params: Vec::new() }
+        BlockData
     }
 }
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Value(u32);
 
 impl VecKey for Value {
@@ -59,9 +206,9 @@ impl VecKey for Value {
 
 pub enum ValueData {
     Inst { ty: Type, inst: Inst },
-    Arg { ty: Type, block: Block, arg: u32 },
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Type {
     Bool,
     Byte,
@@ -73,13 +220,101 @@ pub enum Type {
     Ptr,
 }
 
+impl Type {
+    pub fn is_float(&self) -> bool {
+        match *self {
+            Type::Float | Type::Double => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_ptr(&self) -> bool {
+        *self == Type::Ptr
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Type::Bool => "bool",
+            Type::Byte => "byte",
+            Type::Char => "char",
+            Type::Int => "int",
+            Type::Long => "long",
+            Type::Float => "float",
+            Type::Double => "double",
+            Type::Ptr => "ptr",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Type> {
+        match name {
+            "bool" => Some(Type::Bool),
+            "byte" => Some(Type::Byte),
+            "char" => Some(Type::Char),
+            "int" => Some(Type::Int),
+            "long" => Some(Type::Long),
+            "float" => Some(Type::Float),
+            "double" => Some(Type::Double),
+            "ptr" => Some(Type::Ptr),
+            _ => None,
+        }
+    }
+}
+
+impl From<BytecodeType> for Type {
+    fn from(ty: BytecodeType) -> Type {
+        match ty {
+            BytecodeType::Bool => Type::Bool,
+            BytecodeType::Byte => Type::Byte,
+            BytecodeType::Char => Type::Char,
+            BytecodeType::Int => Type::Int,
+            BytecodeType::Long => Type::Long,
+            BytecodeType::Float => Type::Float,
+            BytecodeType::Double => Type::Double,
+            BytecodeType::Ptr => Type::Ptr,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Function;
+    use super::{Function, Type};
+    use crate::boots::inst::{BinOp, InstData};
 
     #[test]
     fn simple_fn() {
         let mut fct = Function::new();
         let _b = fct.make_block();
     }
+
+    #[test]
+    fn append_and_remove() {
+        let mut fct = Function::new();
+        let b0 = fct.make_block();
+        let b1 = fct.make_block();
+        assert_eq!(Some(b0), fct.entry_block());
+
+        let c1 = fct.append_inst(b0, InstData::Int32Const(1));
+        let c1 = fct.inst_result(c1).unwrap();
+        let add = fct.append_inst(
+            b0,
+            InstData::Binary {
+                op: BinOp::Add,
+                ty: Type::Int,
+                lhs: c1,
+                rhs: c1,
+            },
+        );
+        assert_eq!(Type::Int, fct.value_type(fct.inst_result(add).unwrap()));
+
+        let goto = fct.append_inst(b0, InstData::Goto { target: b1 });
+        assert_eq!(Some(goto), fct.terminator(b0));
+        assert_eq!(&[b1], fct.cfg.successors(b0));
+        assert_eq!(&[b0], fct.cfg.predecessors(b1));
+        assert_eq!(3, fct.insts(b0).count());
+
+        fct.remove_inst(goto);
+        assert_eq!(None, fct.terminator(b0));
+        assert!(fct.cfg.predecessors(b1).is_empty());
+        assert_eq!(2, fct.insts(b0).count());
+    }
 }
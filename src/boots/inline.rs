@@ -0,0 +1,539 @@
+use std::collections::hash_map::HashMap;
+use std::collections::HashSet;
+
+use crate::boots::function::{Block, Function, Value};
+use crate::boots::inst::{CallKind, Inst, InstData};
+use crate::boots::ssagen;
+use crate::bytecode::astgen;
+use crate::class::TypeParams;
+use crate::vm::{Fct, FctId, VM};
+
+// calls in inlined code are inlined up to this nesting depth
+const MAX_DEPTH: usize = 4;
+
+// maximum number of bytecode instructions inlined into one function
+const MAX_INLINED_SIZE: usize = 1000;
+
+// replaces calls of small functions by the body of the callee: direct and static
+// calls are inlined when the bytecode of the callee is at most `--opt-inline-size`
+// instructions long. Virtual calls of methods without overrides are direct calls
+// behind a class check in the IR, they are inlined like every other direct call.
+pub fn run(vm: &VM, fct: &Fct, ir: &mut Function) {
+    let max_size = vm.args.opt_inline_size();
+
+    if max_size == 0 {
+        return;
+    }
+
+    let mut visited = HashSet::new();
+    let mut inlined_size = 0;
+
+    loop {
+        let calls = calls(ir, &visited);
+
+        if calls.is_empty() {
+            break;
+        }
+
+        for call in calls {
+            visited.insert(call);
+
+            // recursive calls are never inlined
+            let depth = ir.dfg.inlined(call).len();
+            let callers: Vec<FctId> = ir.dfg.inlined(call).iter().map(|&(id, _)| id).collect();
+
+            if depth >= MAX_DEPTH {
+                continue;
+            }
+
+            let kind = match *ir.inst(call) {
+                InstData::Call { ref kind, .. } => kind.clone(),
+                _ => unreachable!(),
+            };
+
+            let (callee_id, cls_type_params, fct_type_params) = match kind {
+                CallKind::Direct(fct_id, cls_type_params, fct_type_params)
+                | CallKind::Static(fct_id, cls_type_params, fct_type_params) => {
+                    (fct_id, cls_type_params, fct_type_params)
+                }
+
+                CallKind::Virtual(_) | CallKind::Lambda => continue,
+            };
+
+            if callee_id == fct.id || callers.contains(&callee_id) {
+                continue;
+            }
+
+            let (callee, size) =
+                match callee_ir(vm, callee_id, &cls_type_params, &fct_type_params, max_size) {
+                    Some(result) => result,
+                    None => continue,
+                };
+
+            if inlined_size + size > MAX_INLINED_SIZE {
+                continue;
+            }
+
+            inlined_size += size;
+
+            inline_call(ir, call, &callee, callee_id);
+        }
+    }
+}
+
+// calls in the layout that were not looked at yet
+fn calls(ir: &Function, visited: &HashSet<Inst>) -> Vec<Inst> {
+    let mut calls = Vec::new();
+
+    for &block in ir.blocks() {
+        for inst in ir.insts(block) {
+            if let InstData::Call { .. } = *ir.inst(inst) {
+                if !visited.contains(&inst) {
+                    calls.push(inst);
+                }
+            }
+        }
+    }
+
+    calls
+}
+
+// the IR of the callee and its bytecode size, `None` if the callee is too large
+// or cannot be inlined
+fn callee_ir(
+    vm: &VM,
+    callee_id: FctId,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+    max_size: usize,
+) -> Option<(Function, usize)> {
+    let callee = vm.fcts.idx(callee_id);
+    let callee = callee.read();
+
+    if !callee.is_src() || callee.is_abstract {
+        return None;
+    }
+
+    // the callee might be compiled by another thread right now
+    let mut src = callee.src().try_write()?;
+    let bytecode = astgen::generate_fct(vm, &*callee, &mut src, cls_type_params, fct_type_params);
+    let size = bytecode.code().len();
+
+    if size > max_size {
+        return None;
+    }
+
+    let ir = ssagen::generate(vm, &*callee, &bytecode).ok()?;
+
+    // functions that never return are error paths, frame states
+    // of deoptimization points only describe the outermost function
+    let mut returns = false;
+
+    for &block in ir.blocks() {
+        for inst in ir.insts(block) {
+            match *ir.inst(inst) {
+                InstData::Ret { .. } => returns = true,
+                InstData::Deoptimize { .. } => return None,
+                _ => {}
+            }
+        }
+    }
+
+    if returns {
+        Some((ir, size))
+    } else {
+        None
+    }
+}
+
+// replaces the call by the body of the callee: the block is split after the call,
+// params are replaced by the arguments and returns jump to the new block where
+// a phi merges the returned values
+fn inline_call(fct: &mut Function, call: Inst, callee: &Function, callee_id: FctId) {
+    let (args, result) = call_args(fct, call);
+    let block = fct.cfg.inst_block(call).unwrap();
+    let cont = split_after(fct, call);
+    let (entry, returns) = copy_body(fct, call, callee, callee_id, &args, cont);
+
+    fct.remove_inst(call);
+    *fct.dfg.inst_mut(call) = InstData::Deleted;
+
+    let goto = fct.append_inst(block, InstData::Goto { target: entry });
+    copy_origin(fct, call, goto);
+
+    merge_returns(fct, call, cont, result, returns);
+}
+
+fn call_args(fct: &Function, call: Inst) -> (Vec<Value>, Option<Value>) {
+    match *fct.inst(call) {
+        InstData::Call { ref args, .. } => (args.clone(), fct.inst_result(call)),
+        _ => unreachable!(),
+    }
+}
+
+// moves the instructions after `inst` into a new block, phis of the successors
+// get their inputs from the new block
+fn split_after(fct: &mut Function, inst: Inst) -> Block {
+    let block = fct.cfg.inst_block(inst).unwrap();
+    let cont = fct.make_block();
+    let mut next = fct.cfg.next_inst(inst);
+
+    while let Some(inst) = next {
+        next = fct.cfg.next_inst(inst);
+        fct.move_inst(inst, cont);
+    }
+
+    let mut successors = fct.cfg.successors(cont).to_vec();
+    successors.dedup();
+
+    for succ in successors {
+        let phis: Vec<Inst> = fct
+            .insts(succ)
+            .take_while(|&inst| fct.inst(inst).is_phi())
+            .collect();
+
+        for phi in phis {
+            if let InstData::Phi { ref mut inputs, .. } = *fct.dfg.inst_mut(phi) {
+                for input in inputs.iter_mut() {
+                    if input.0 == block {
+                        input.0 = cont;
+                    }
+                }
+            }
+        }
+    }
+
+    cont
+}
+
+// copies the blocks of the callee into the function, returns the copy of the
+// entry block and the returned values with the blocks returning them
+fn copy_body(
+    fct: &mut Function,
+    call: Inst,
+    callee: &Function,
+    callee_id: FctId,
+    args: &[Value],
+    cont: Block,
+) -> (Block, Vec<(Block, Option<Value>)>) {
+    let mut frames = Vec::new();
+
+    if let Some(pos) = fct.dfg.position(call) {
+        frames.push((callee_id, pos));
+    }
+
+    frames.extend_from_slice(fct.dfg.inlined(call));
+
+    let blocks: HashMap<Block, Block> = callee
+        .blocks()
+        .iter()
+        .map(|&block| (block, fct.make_block()))
+        .collect();
+
+    let mut values: HashMap<Value, Value> = HashMap::new();
+    let mut copies = Vec::new();
+    let mut returns = Vec::new();
+
+    for &callee_block in callee.blocks() {
+        let block = blocks[&callee_block];
+
+        for callee_inst in callee.insts(callee_block) {
+            let inst_data = match *callee.inst(callee_inst) {
+                InstData::Param { idx, .. } => {
+                    let param = callee.inst_result(callee_inst).unwrap();
+                    values.insert(param, args[idx as usize]);
+                    continue;
+                }
+
+                InstData::Ret { opnd } => {
+                    returns.push((block, opnd));
+                    InstData::Goto { target: cont }
+                }
+
+                InstData::Goto { target } => InstData::Goto {
+                    target: blocks[&target],
+                },
+
+                InstData::If {
+                    opnd,
+                    then_block,
+                    else_block,
+                } => InstData::If {
+                    opnd: opnd,
+                    then_block: blocks[&then_block],
+                    else_block: blocks[&else_block],
+                },
+
+                InstData::Phi { ty, ref inputs } => InstData::Phi {
+                    ty: ty,
+                    inputs: inputs
+                        .iter()
+                        .map(|&(pred, value)| (blocks[&pred], value))
+                        .collect(),
+                },
+
+                ref inst_data => inst_data.clone(),
+            };
+
+            let inst = fct.append_inst(block, inst_data);
+
+            if let Some(pos) = callee.dfg.position(callee_inst) {
+                fct.dfg.set_position(inst, pos);
+            }
+
+            let mut inst_frames = callee.dfg.inlined(callee_inst).to_vec();
+            inst_frames.extend_from_slice(&frames);
+
+            if !inst_frames.is_empty() {
+                fct.dfg.set_inlined(inst, inst_frames);
+            }
+
+            if let Some(value) = callee.inst_result(callee_inst) {
+                values.insert(value, fct.inst_result(inst).unwrap());
+            }
+
+            copies.push(inst);
+        }
+    }
+
+    // operands may be defined in blocks copied later
+    for inst in copies {
+        for opnd in fct.dfg.inst_mut(inst).operands_mut() {
+            let value = values[&*opnd];
+            *opnd = value;
+        }
+    }
+
+    let returns = returns
+        .into_iter()
+        .map(|(block, value)| (block, value.map(|value| values[&value])))
+        .collect();
+
+    (blocks[&callee.entry_block().unwrap()], returns)
+}
+
+// uses of the call result are replaced by the returned value
+fn merge_returns(
+    fct: &mut Function,
+    call: Inst,
+    cont: Block,
+    result: Option<Value>,
+    returns: Vec<(Block, Option<Value>)>,
+) {
+    let result = match result {
+        Some(result) => result,
+        None => return,
+    };
+
+    let inputs: Vec<(Block, Value)> = returns
+        .into_iter()
+        .map(|(block, value)| (block, value.unwrap()))
+        .collect();
+
+    if inputs.len() == 1 {
+        fct.replace_uses(result, inputs[0].1);
+        return;
+    }
+
+    let ty = fct.value_type(result);
+    let phi = fct.prepend_inst(
+        cont,
+        InstData::Phi {
+            ty: ty,
+            inputs: Vec::new(),
+        },
+    );
+    copy_origin(fct, call, phi);
+
+    // the result of a fallback call is an input of the phi
+    let value = fct.inst_result(phi).unwrap();
+    fct.replace_uses(result, value);
+
+    match *fct.dfg.inst_mut(phi) {
+        InstData::Phi {
+            inputs: ref mut phi_inputs,
+            ..
+        } => *phi_inputs = inputs,
+        _ => unreachable!(),
+    }
+}
+
+// instructions created for a call get the position and the inlined functions of the call
+fn copy_origin(fct: &mut Function, call: Inst, inst: Inst) {
+    if let Some(pos) = fct.dfg.position(call) {
+        fct.dfg.set_position(inst, pos);
+    }
+
+    let frames = fct.dfg.inlined(call).to_vec();
+
+    if !frames.is_empty() {
+        fct.dfg.set_inlined(inst, frames);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::inline_call;
+    use crate::boots::function::Function;
+    use crate::boots::inst::{Inst, InstData};
+    use crate::boots::parser::parse;
+    use crate::boots::verifier::verify;
+    use crate::vm::FctId;
+
+    fn parse_verified(text: &str) -> Function {
+        let fct = parse(text).unwrap_or_else(|msg| panic!("{}", msg));
+        verify(&fct).unwrap_or_else(|msg| panic!("input: {}", msg));
+        fct
+    }
+
+    fn find_call(fct: &Function) -> Inst {
+        for &block in fct.blocks() {
+            for inst in fct.insts(block) {
+                if let InstData::Call { .. } = *fct.inst(inst) {
+                    return inst;
+                }
+            }
+        }
+
+        panic!("no call found")
+    }
+
+    // inlines the first call of `caller` and compares the printed result with `after`
+    fn check(caller: &str, callee: &str, after: &str) {
+        let mut fct = parse_verified(caller);
+        let callee = parse_verified(callee);
+        let call = find_call(&fct);
+
+        inline_call(&mut fct, call, &callee, FctId(3));
+
+        verify(&fct).unwrap_or_else(|msg| panic!("output: {}", msg));
+        assert_eq!(after, fct.to_string());
+    }
+
+    #[test]
+    fn inline_merges_returns() {
+        check(
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = const int 1
+    v2 = call int static fct 3 (v0, v1)
+    v3 = add int v2, v0
+    ret v3
+}",
+            "function g {
+b0:
+    v0 = param int 0
+    v1 = param int 1
+    v2 = cmp lt int v0, v1
+    if v2, b1, b2
+b1:
+    ret v0
+b2:
+    ret v1
+}",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = const int 1
+    goto b2
+b1:
+    v2 = phi int [b3: v0, b4: v1]
+    v3 = add int v2, v0
+    ret v3
+b2:
+    v4 = cmp lt int v0, v1
+    if v4, b3, b4
+b3:
+    goto b1
+b4:
+    goto b1
+}
+",
+        );
+    }
+
+    #[test]
+    fn inline_in_loop() {
+        check(
+            "function f {
+b0:
+    v0 = param int 0
+    goto b1
+b1:
+    v1 = phi int [b0: v0, b1: v2]
+    v2 = call int static fct 3 (v1)
+    v3 = cmp lt int v2, v0
+    if v3, b1, b2
+b2:
+    ret v2
+}",
+            "function g {
+b0:
+    v0 = param int 0
+    v1 = const int 1
+    v2 = add int v0, v1
+    ret v2
+}",
+            "function f {
+b0:
+    v0 = param int 0
+    goto b1
+b1:
+    v1 = phi int [b0: v0, b3: v4]
+    goto b4
+b2:
+    ret v4
+b3:
+    v2 = cmp lt int v4, v0
+    if v2, b1, b2
+b4:
+    v3 = const int 1
+    v4 = add int v1, v3
+    goto b3
+}
+",
+        );
+    }
+
+    #[test]
+    fn inline_guarded_call() {
+        check(
+            "function f {
+b0:
+    v0 = param ptr 0
+    nil_check v0
+    v1 = has_class v0, class 2
+    if v1, b1, b2
+b1:
+    v2 = call int direct fct 3 (v0)
+    ret v2
+b2:
+    deoptimize 1 [0: v0]
+}",
+            "function g {
+b0:
+    v0 = param ptr 0
+    v1 = load_field int v0, class 2, field 0
+    ret v1
+}",
+            "function f {
+b0:
+    v0 = param ptr 0
+    nil_check v0
+    v1 = has_class v0, class 2
+    if v1, b1, b2
+b1:
+    goto b4
+b2:
+    deoptimize 1 [0: v0]
+b3:
+    ret v2
+b4:
+    v2 = load_field int v0, class 2, field 0
+    goto b3
+}
+",
+        );
+    }
+}
@@ -1,7 +1,10 @@
 use crate::boots::function::{Block, Type, Value};
 use crate::boots::utils::VecKey;
+use crate::class::{ClassDefId, FieldId, TypeParams};
+use crate::os::signal::Trap;
+use crate::vm::{FctId, GlobalId};
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Inst(u32);
 
 impl VecKey for Inst {
@@ -14,17 +17,28 @@ impl VecKey for Inst {
     }
 }
 
+// loads and stores of fields and array elements do not check their object,
+// `NilCheck` and `BoundsCheck` are separate instructions
+#[derive(Clone, Debug)]
 pub enum InstData {
-    Add {
+    Binary {
+        op: BinOp,
         ty: Type,
         lhs: Value,
         rhs: Value,
     },
 
-    Sub {
+    Unary {
+        op: UnOp,
         ty: Type,
-        lhs: Value,
-        rhs: Value,
+        opnd: Value,
+    },
+
+    Convert {
+        op: ConvOp,
+        from: Type,
+        to: Type,
+        opnd: Value,
     },
 
     Goto {
@@ -52,19 +66,522 @@ pub enum InstData {
     FalseConst,
     NilConst,
     Int8Const(u8),
+    CharConst(char),
     Int32Const(i32),
     Int64Const(i64),
     Float32Const(f32),
     Float64Const(f64),
+    StringConst(String),
 
     Param {
         ty: Type,
         idx: u32,
     },
 
+    // one input for every predecessor of the block
+    Phi {
+        ty: Type,
+        inputs: Vec<(Block, Value)>,
+    },
+
+    LoadField {
+        ty: Type,
+        obj: Value,
+        cls: ClassDefId,
+        field: FieldId,
+    },
+
+    StoreField {
+        ty: Type,
+        obj: Value,
+        cls: ClassDefId,
+        field: FieldId,
+        value: Value,
+    },
+
+    LoadGlobal {
+        ty: Type,
+        global: GlobalId,
+    },
+
+    StoreGlobal {
+        ty: Type,
+        global: GlobalId,
+        value: Value,
+    },
+
+    LoadArray {
+        ty: Type,
+        array: Value,
+        index: Value,
+    },
+
+    StoreArray {
+        ty: Type,
+        array: Value,
+        index: Value,
+        value: Value,
+    },
+
+    ArrayLength {
+        array: Value,
+    },
+
+    SetUint8 {
+        address: Value,
+        value: Value,
+    },
+
+    NilCheck {
+        opnd: Value,
+    },
+
+    BoundsCheck {
+        index: Value,
+        length: Value,
+    },
+
+    InstanceOf {
+        opnd: Value,
+        cls: ClassDefId,
+    },
+
+    CheckedCast {
+        opnd: Value,
+        cls: ClassDefId,
+    },
+
+    // exact class test, the object must not be nil
+    HasClass {
+        opnd: Value,
+        cls: ClassDefId,
+    },
+
+    Call {
+        kind: CallKind,
+        ty: Option<Type>,
+        args: Vec<Value>,
+    },
+
+    NewObject {
+        cls: ClassDefId,
+    },
+
+    NewArray {
+        cls: ClassDefId,
+        length: Value,
+    },
+
+    Throw {
+        opnd: Value,
+    },
+
+    Trap {
+        trap: Trap,
+    },
+
+    // leaves the optimized code and continues in baseline code at the bytecode
+    // instruction `pc`, `state` holds the values of the bytecode registers
+    Deoptimize {
+        pc: u32,
+        state: Vec<(u32, Value)>,
+    },
+
+    Spawn {
+        opnd: Value,
+    },
+
+    Debug,
+
     Deleted,
 }
 
+impl InstData {
+    pub fn result_type(&self) -> Option<Type> {
+        match *self {
+            InstData::Binary { ty, .. } | InstData::Unary { ty, .. } => Some(ty),
+            InstData::Convert { to, .. } => Some(to),
+            InstData::Cmp { .. } => Some(Type::Bool),
+
+            InstData::TrueConst | InstData::FalseConst => Some(Type::Bool),
+            InstData::NilConst | InstData::StringConst(_) => Some(Type::Ptr),
+            InstData::Int8Const(_) => Some(Type::Byte),
+            InstData::CharConst(_) => Some(Type::Char),
+            InstData::Int32Const(_) => Some(Type::Int),
+            InstData::Int64Const(_) => Some(Type::Long),
+            InstData::Float32Const(_) => Some(Type::Float),
+            InstData::Float64Const(_) => Some(Type::Double),
+
+            InstData::Param { ty, .. } | InstData::Phi { ty, .. } => Some(ty),
+
+            InstData::LoadField { ty, .. }
+            | InstData::LoadGlobal { ty, .. }
+            | InstData::LoadArray { ty, .. } => Some(ty),
+
+            InstData::ArrayLength { .. } => Some(Type::Int),
+            InstData::InstanceOf { .. } | InstData::HasClass { .. } => Some(Type::Bool),
+            InstData::Call { ty, .. } => ty,
+            InstData::NewObject { .. } | InstData::NewArray { .. } => Some(Type::Ptr),
+
+            _ => None,
+        }
+    }
+
+    pub fn is_terminator(&self) -> bool {
+        match *self {
+            InstData::Goto { .. }
+            | InstData::If { .. }
+            | InstData::Ret { .. }
+            | InstData::Throw { .. }
+            | InstData::Trap { .. }
+            | InstData::Deoptimize { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_phi(&self) -> bool {
+        match *self {
+            InstData::Phi { .. } => true,
+            _ => false,
+        }
+    }
+
+    // instructions that need to stay even if their result is unused:
+    // terminators, stores, calls and everything that may throw
+    pub fn has_side_effects(&self) -> bool {
+        match *self {
+            InstData::Binary {
+                op: BinOp::Div, ty, ..
+            }
+            | InstData::Binary {
+                op: BinOp::Mod, ty, ..
+            } => !ty.is_float(),
+
+            InstData::Goto { .. }
+            | InstData::If { .. }
+            | InstData::Ret { .. }
+            | InstData::Throw { .. }
+            | InstData::Trap { .. }
+            | InstData::Deoptimize { .. }
+            | InstData::StoreField { .. }
+            | InstData::StoreGlobal { .. }
+            | InstData::StoreArray { .. }
+            | InstData::SetUint8 { .. }
+            | InstData::NilCheck { .. }
+            | InstData::BoundsCheck { .. }
+            | InstData::CheckedCast { .. }
+            | InstData::Call { .. }
+            | InstData::NewArray { .. }
+            | InstData::Spawn { .. }
+            | InstData::Debug => true,
+
+            _ => false,
+        }
+    }
+
+    // instructions that may change the heap or globals
+    pub fn writes_memory(&self) -> bool {
+        match *self {
+            InstData::StoreField { .. }
+            | InstData::StoreGlobal { .. }
+            | InstData::StoreArray { .. }
+            | InstData::SetUint8 { .. }
+            | InstData::Call { .. }
+            | InstData::Spawn { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn successors(&self) -> Vec<Block> {
+        match *self {
+            InstData::Goto { target } => vec![target],
+            InstData::If {
+                then_block,
+                else_block,
+                ..
+            } => vec![then_block, else_block],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn operands(&self) -> Vec<Value> {
+        let mut operands = Vec::new();
+
+        match *self {
+            InstData::Binary { lhs, rhs, .. } | InstData::Cmp { lhs, rhs, .. } => {
+                operands.push(lhs);
+                operands.push(rhs);
+            }
+
+            InstData::Unary { opnd, .. }
+            | InstData::Convert { opnd, .. }
+            | InstData::If { opnd, .. }
+            | InstData::NilCheck { opnd }
+            | InstData::InstanceOf { opnd, .. }
+            | InstData::CheckedCast { opnd, .. }
+            | InstData::HasClass { opnd, .. }
+            | InstData::Throw { opnd }
+            | InstData::Spawn { opnd } => operands.push(opnd),
+
+            InstData::Ret { opnd } => operands.extend(opnd),
+
+            InstData::Phi { ref inputs, .. } => {
+                operands.extend(inputs.iter().map(|&(_, value)| value));
+            }
+
+            InstData::Deoptimize { ref state, .. } => {
+                operands.extend(state.iter().map(|&(_, value)| value));
+            }
+
+            InstData::LoadField { obj, .. } => operands.push(obj),
+            InstData::StoreField { obj, value, .. } => {
+                operands.push(obj);
+                operands.push(value);
+            }
+
+            InstData::StoreGlobal { value, .. } => operands.push(value),
+
+            InstData::LoadArray { array, index, .. } => {
+                operands.push(array);
+                operands.push(index);
+            }
+
+            InstData::StoreArray {
+                array,
+                index,
+                value,
+                ..
+            } => {
+                operands.push(array);
+                operands.push(index);
+                operands.push(value);
+            }
+
+            InstData::ArrayLength { array } => operands.push(array),
+            InstData::SetUint8 { address, value } => {
+                operands.push(address);
+                operands.push(value);
+            }
+
+            InstData::BoundsCheck { index, length } => {
+                operands.push(index);
+                operands.push(length);
+            }
+
+            InstData::Call { ref args, .. } => operands.extend(args.iter().cloned()),
+            InstData::NewArray { length, .. } => operands.push(length),
+
+            _ => {}
+        }
+
+        operands
+    }
+
+    pub fn replace_operand(&mut self, old: Value, new: Value) {
+        for opnd in self.operands_mut() {
+            if *opnd == old {
+                *opnd = new;
+            }
+        }
+    }
+
+    // operands in the same order as `operands`
+    pub fn operands_mut(&mut self) -> Vec<&mut Value> {
+        match *self {
+            InstData::Binary {
+                ref mut lhs,
+                ref mut rhs,
+                ..
+            }
+            | InstData::Cmp {
+                ref mut lhs,
+                ref mut rhs,
+                ..
+            } => vec![lhs, rhs],
+
+            InstData::Unary { ref mut opnd, .. }
+            | InstData::Convert { ref mut opnd, .. }
+            | InstData::If { ref mut opnd, .. }
+            | InstData::NilCheck { ref mut opnd }
+            | InstData::InstanceOf { ref mut opnd, .. }
+            | InstData::CheckedCast { ref mut opnd, .. }
+            | InstData::HasClass { ref mut opnd, .. }
+            | InstData::Throw { ref mut opnd }
+            | InstData::Spawn { ref mut opnd } => vec![opnd],
+
+            InstData::Ret { ref mut opnd } => opnd.iter_mut().collect(),
+
+            InstData::Phi { ref mut inputs, .. } => inputs
+                .iter_mut()
+                .map(|&mut (_, ref mut value)| value)
+                .collect(),
+
+            InstData::Deoptimize { ref mut state, .. } => state
+                .iter_mut()
+                .map(|&mut (_, ref mut value)| value)
+                .collect(),
+
+            InstData::LoadField { ref mut obj, .. } => vec![obj],
+            InstData::StoreField {
+                ref mut obj,
+                ref mut value,
+                ..
+            } => vec![obj, value],
+
+            InstData::StoreGlobal { ref mut value, .. } => vec![value],
+
+            InstData::LoadArray {
+                ref mut array,
+                ref mut index,
+                ..
+            } => vec![array, index],
+
+            InstData::StoreArray {
+                ref mut array,
+                ref mut index,
+                ref mut value,
+                ..
+            } => vec![array, index, value],
+
+            InstData::ArrayLength { ref mut array } => vec![array],
+            InstData::SetUint8 {
+                ref mut address,
+                ref mut value,
+            } => vec![address, value],
+
+            InstData::BoundsCheck {
+                ref mut index,
+                ref mut length,
+            } => vec![index, length],
+
+            InstData::Call { ref mut args, .. } => args.iter_mut().collect(),
+            InstData::NewArray { ref mut length, .. } => vec![length],
+
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Sar,
+}
+
+impl BinOp {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            BinOp::Add => "add",
+            BinOp::Sub => "sub",
+            BinOp::Mul => "mul",
+            BinOp::Div => "div",
+            BinOp::Mod => "mod",
+            BinOp::And => "and",
+            BinOp::Or => "or",
+            BinOp::Xor => "xor",
+            BinOp::Shl => "shl",
+            BinOp::Shr => "shr",
+            BinOp::Sar => "sar",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<BinOp> {
+        match name {
+            "add" => Some(BinOp::Add),
+            "sub" => Some(BinOp::Sub),
+            "mul" => Some(BinOp::Mul),
+            "div" => Some(BinOp::Div),
+            "mod" => Some(BinOp::Mod),
+            "and" => Some(BinOp::And),
+            "or" => Some(BinOp::Or),
+            "xor" => Some(BinOp::Xor),
+            "shl" => Some(BinOp::Shl),
+            "shr" => Some(BinOp::Shr),
+            "sar" => Some(BinOp::Sar),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum UnOp {
+    Neg,
+    Not,
+    Sqrt,
+}
+
+impl UnOp {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            UnOp::Neg => "neg",
+            UnOp::Not => "not",
+            UnOp::Sqrt => "sqrt",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<UnOp> {
+        match name {
+            "neg" => Some(UnOp::Neg),
+            "not" => Some(UnOp::Not),
+            "sqrt" => Some(UnOp::Sqrt),
+            _ => None,
+        }
+    }
+}
+
+// conversions between types: extensions treat Byte and Char as unsigned,
+// `Reinterpret` keeps the bits
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConvOp {
+    ZeroExtend,
+    SignExtend,
+    Truncate,
+    IntToFloat,
+    FloatToInt,
+    FloatExtend,
+    FloatTruncate,
+    Reinterpret,
+}
+
+impl ConvOp {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ConvOp::ZeroExtend => "zext",
+            ConvOp::SignExtend => "sext",
+            ConvOp::Truncate => "trunc",
+            ConvOp::IntToFloat => "itof",
+            ConvOp::FloatToInt => "ftoi",
+            ConvOp::FloatExtend => "fext",
+            ConvOp::FloatTruncate => "ftrunc",
+            ConvOp::Reinterpret => "bitcast",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ConvOp> {
+        match name {
+            "zext" => Some(ConvOp::ZeroExtend),
+            "sext" => Some(ConvOp::SignExtend),
+            "trunc" => Some(ConvOp::Truncate),
+            "itof" => Some(ConvOp::IntToFloat),
+            "ftoi" => Some(ConvOp::FloatToInt),
+            "fext" => Some(ConvOp::FloatExtend),
+            "ftrunc" => Some(ConvOp::FloatTruncate),
+            "bitcast" => Some(ConvOp::Reinterpret),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum CmpOp {
     Lt,
     Le,
@@ -73,3 +590,38 @@ pub enum CmpOp {
     Gt,
     Ge,
 }
+
+impl CmpOp {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            CmpOp::Lt => "lt",
+            CmpOp::Le => "le",
+            CmpOp::Eq => "eq",
+            CmpOp::Ne => "ne",
+            CmpOp::Gt => "gt",
+            CmpOp::Ge => "ge",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<CmpOp> {
+        match name {
+            "lt" => Some(CmpOp::Lt),
+            "le" => Some(CmpOp::Le),
+            "eq" => Some(CmpOp::Eq),
+            "ne" => Some(CmpOp::Ne),
+            "gt" => Some(CmpOp::Gt),
+            "ge" => Some(CmpOp::Ge),
+            _ => None,
+        }
+    }
+}
+
+// the receiver of direct and virtual calls is the first argument,
+// for lambdas the first argument is the closure
+#[derive(Clone, Debug, PartialEq)]
+pub enum CallKind {
+    Direct(FctId, TypeParams, TypeParams),
+    Virtual(FctId),
+    Static(FctId, TypeParams, TypeParams),
+    Lambda,
+}
@@ -0,0 +1,1131 @@
+use std::collections::hash_map::HashMap;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use dora_parser::ast::visit::Visitor;
+use dora_parser::ast::{self, NodeId};
+
+use crate::boots::function::{Block, Function, Type, Value};
+use crate::boots::inst::{BinOp, CallKind, CmpOp, ConvOp, Inst, InstData, UnOp};
+use crate::boots::printer::{trap_name, Names};
+use crate::boots::utils::VecKey;
+use crate::class::{ClassDefId, TypeParams};
+use crate::mem;
+use crate::object::{offset_of_array_data, offset_of_array_length};
+use crate::os::signal::Trap;
+use crate::vm::{Fct, FctId, GlobalId, VM};
+use crate::vtable::VTable;
+
+// functions of the runtime called from the generated code
+const RUNTIME: &[(&str, &str)] = &[
+    ("@dora.trap", "declare void @dora.trap(i32) noreturn"),
+    (
+        "@dora.throw",
+        "declare void @dora.throw(ptr addrspace(1)) noreturn",
+    ),
+    (
+        "@dora.deoptimize",
+        "declare void @dora.deoptimize(i32, ...) noreturn",
+    ),
+    (
+        "@dora.alloc_object",
+        "declare ptr addrspace(1) @dora.alloc_object(ptr)",
+    ),
+    (
+        "@dora.alloc_array",
+        "declare ptr addrspace(1) @dora.alloc_array(ptr, i32)",
+    ),
+    (
+        "@dora.string",
+        "declare ptr addrspace(1) @dora.string(ptr, i64)",
+    ),
+    (
+        "@dora.write_barrier",
+        "declare void @dora.write_barrier(ptr addrspace(1))",
+    ),
+    (
+        "@dora.instance_of",
+        "declare i1 @dora.instance_of(ptr addrspace(1), ptr)",
+    ),
+    (
+        "@dora.checked_cast",
+        "declare void @dora.checked_cast(ptr addrspace(1), ptr)",
+    ),
+    ("@dora.spawn", "declare void @dora.spawn(ptr addrspace(1))"),
+    ("@llvm.sqrt.f32", "declare float @llvm.sqrt.f32(float)"),
+    ("@llvm.sqrt.f64", "declare double @llvm.sqrt.f64(double)"),
+    ("@llvm.debugtrap", "declare void @llvm.debugtrap()"),
+];
+
+// functions declared outside of the standard library
+pub fn program_fcts(vm: &VM) -> HashSet<NodeId> {
+    let mut collector = FctCollector {
+        ids: HashSet::new(),
+    };
+
+    for file in &vm.ast.files {
+        if !file.path.starts_with("stdlib") {
+            collector.visit_file(file);
+        }
+    }
+
+    collector.ids
+}
+
+struct FctCollector {
+    ids: HashSet<NodeId>,
+}
+
+impl<'v> Visitor<'v> for FctCollector {
+    fn visit_fct(&mut self, f: &'v ast::Function) {
+        self.ids.insert(f.id);
+    }
+
+    fn visit_method(&mut self, f: &'v ast::Function) {
+        self.ids.insert(f.id);
+    }
+
+    fn visit_ctor(&mut self, f: &'v ast::Function) {
+        self.ids.insert(f.id);
+    }
+}
+
+// textual LLVM IR of boots functions: GC pointers are in address space 1, the
+// runtime is called for allocations, write barriers, traps and type checks.
+// Functions and globals outside of the module are declared at the end.
+pub struct Module<'a, 'ast: 'a> {
+    vm: &'a VM<'ast>,
+    functions: Vec<String>,
+    defined: HashSet<String>,
+
+    // declarations and globals by symbol
+    declarations: BTreeMap<String, String>,
+    globals: BTreeMap<String, String>,
+    strings: Vec<Vec<u8>>,
+}
+
+impl<'a, 'ast> Module<'a, 'ast> {
+    pub fn new(vm: &'a VM<'ast>) -> Module<'a, 'ast> {
+        Module {
+            vm: vm,
+            functions: Vec::new(),
+            defined: HashSet::new(),
+            declarations: BTreeMap::new(),
+            globals: BTreeMap::new(),
+            strings: Vec::new(),
+        }
+    }
+
+    // `param_types` holds the types of all params, the IR only has params that are used
+    pub fn add_function(&mut self, fct: &Fct<'ast>, param_types: &[Type], ir: &Function) {
+        let symbol = symbol(&fct.full_name(self.vm));
+        let text = FunctionEmitter::new(self, ir).emit(&symbol, param_types);
+
+        self.defined.insert(symbol);
+        self.functions.push(text);
+    }
+
+    // functions that could not be translated are listed in comments
+    pub fn add_comment(&mut self, text: &str) {
+        self.functions.push(format!("; {}\n", text));
+    }
+
+    fn runtime(&mut self, symbol: &'static str) -> &'static str {
+        let &(_, declaration) = RUNTIME
+            .iter()
+            .find(|&&(name, _)| name == symbol)
+            .expect("unknown runtime function");
+        self.declarations.insert(symbol.into(), declaration.into());
+        symbol
+    }
+
+    fn fct_symbol(
+        &mut self,
+        fct_id: FctId,
+        cls_type_params: &TypeParams,
+        fct_type_params: &TypeParams,
+        return_type: &str,
+        arg_types: &[&str],
+    ) -> String {
+        let name = {
+            let fct = self.vm.fcts.idx(fct_id);
+            let fct = fct.read();
+            fct.full_name(self.vm)
+        };
+
+        // specializations of generic functions get the type params as suffix
+        let type_params: Vec<String> = cls_type_params
+            .iter()
+            .chain(fct_type_params.iter())
+            .map(|ty| ty.name(self.vm))
+            .collect();
+
+        let symbol = if type_params.is_empty() {
+            symbol(&name)
+        } else {
+            symbol(&format!("{} {{{}}}", name, type_params.join(", ")))
+        };
+
+        let declaration = format!(
+            "declare {} {}({})",
+            return_type,
+            symbol,
+            arg_types.join(", ")
+        );
+        self.declarations.insert(symbol.clone(), declaration);
+
+        symbol
+    }
+
+    fn vtable(&mut self, cls_id: ClassDefId) -> String {
+        let name = {
+            let cls = self.vm.class_defs.idx(cls_id);
+            let cls = cls.read();
+            cls.name(self.vm)
+        };
+
+        let symbol = symbol(&format!("vtable.{}", name));
+        self.globals
+            .insert(symbol.clone(), format!("{} = external constant i8", symbol));
+
+        symbol
+    }
+
+    fn global(&mut self, global_id: GlobalId, ty: Type) -> String {
+        let name = {
+            let glob = self.vm.globals.idx(global_id);
+            let glob = glob.lock();
+            self.vm.interner.str(glob.name).to_string()
+        };
+
+        let symbol = symbol(&name);
+        self.globals.insert(
+            symbol.clone(),
+            format!("{} = external global {}", symbol, memory_type(ty)),
+        );
+
+        symbol
+    }
+
+    fn string(&mut self, value: &str) -> String {
+        self.strings.push(value.as_bytes().to_vec());
+        format!("@.str.{}", self.strings.len() - 1)
+    }
+}
+
+impl<'a, 'ast> fmt::Display for Module<'a, 'ast> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "; ModuleID = 'dora'")?;
+        writeln!(f, "source_filename = \"dora\"")?;
+
+        // pointers into the heap are moved by the GC and cannot be converted to integers
+        writeln!(
+            f,
+            "target datalayout = \"e-m:e-i64:64-f80:128-n8:16:32:64-S128-ni:1\""
+        )?;
+
+        if !self.strings.is_empty() || !self.globals.is_empty() {
+            writeln!(f)?;
+        }
+
+        for (idx, value) in self.strings.iter().enumerate() {
+            writeln!(
+                f,
+                "@.str.{} = private unnamed_addr constant [{} x i8] c\"{}\"",
+                idx,
+                value.len(),
+                escape(value)
+            )?;
+        }
+
+        for global in self.globals.values() {
+            writeln!(f, "{}", global)?;
+        }
+
+        for function in &self.functions {
+            writeln!(f)?;
+            write!(f, "{}", function)?;
+        }
+
+        let declarations: Vec<&String> = self
+            .declarations
+            .iter()
+            .filter(|&(symbol, _)| !self.defined.contains(symbol))
+            .map(|(_, declaration)| declaration)
+            .collect();
+
+        if !declarations.is_empty() {
+            writeln!(f)?;
+        }
+
+        for declaration in declarations {
+            writeln!(f, "{}", declaration)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct FunctionEmitter<'m, 'a: 'm, 'ast: 'a> {
+    module: &'m mut Module<'a, 'ast>,
+    ir: &'m Function,
+    names: Names,
+    out: String,
+    next_tmp: usize,
+
+    // checks continue in a new label, the label of the code emitted right now
+    // is the block with the number of checks before
+    current: (Block, usize),
+
+    // label at the end of every block
+    exits: HashMap<Block, String>,
+
+    traps: Vec<Trap>,
+}
+
+impl<'m, 'a, 'ast> FunctionEmitter<'m, 'a, 'ast> {
+    fn new(module: &'m mut Module<'a, 'ast>, ir: &'m Function) -> FunctionEmitter<'m, 'a, 'ast> {
+        let names = Names::new(ir);
+        let mut exits = HashMap::new();
+
+        for &block in ir.blocks() {
+            let checks = ir
+                .insts(block)
+                .filter(|&inst| needs_label(ir.inst(inst)))
+                .count();
+            exits.insert(block, label(&names, block, checks));
+        }
+
+        FunctionEmitter {
+            module: module,
+            ir: ir,
+            names: names,
+            out: String::new(),
+            next_tmp: 0,
+            current: (Block::new(0), 0),
+            exits: exits,
+            traps: Vec::new(),
+        }
+    }
+
+    fn emit(mut self, symbol: &str, param_types: &[Type]) -> String {
+        let entry = self.ir.entry_block().expect("function without blocks");
+        let mut params: Vec<String> = (0..param_types.len())
+            .map(|idx| format!("%a{}", idx))
+            .collect();
+
+        for inst in self.ir.insts(entry) {
+            if let InstData::Param { idx, .. } = *self.ir.inst(inst) {
+                params[idx as usize] = self.value(self.ir.inst_result(inst).unwrap());
+            }
+        }
+
+        let params: Vec<String> = param_types
+            .iter()
+            .zip(params)
+            .map(|(&ty, name)| format!("{} {}", llvm_type(ty), name))
+            .collect();
+
+        self.out.push_str(&format!(
+            "define {} {}({}) {{\n",
+            return_type(self.ir),
+            symbol,
+            params.join(", ")
+        ));
+
+        // the entry block of LLVM functions has no predecessors
+        if !self.ir.cfg.predecessors(entry).is_empty() {
+            self.out.push_str("entry:\n");
+            self.line(format!("br label %{}", self.names.block(entry)));
+        }
+
+        for &block in self.ir.blocks() {
+            self.current = (block, 0);
+            self.out
+                .push_str(&format!("{}:\n", self.names.block(block)));
+
+            for inst in self.ir.insts(block) {
+                self.emit_inst(inst);
+            }
+        }
+
+        let trap_fct = self.module.runtime("@dora.trap");
+
+        for trap in self.traps.clone() {
+            self.out.push_str(&format!("trap.{}:\n", trap_name(trap)));
+            self.line(format!("call void {}(i32 {})", trap_fct, trap.int()));
+            self.line("unreachable".into());
+        }
+
+        self.out.push_str("}\n");
+        self.out
+    }
+
+    fn emit_inst(&mut self, inst: Inst) {
+        let ir = self.ir;
+        let result = ir.inst_result(inst).map(|value| self.value(value));
+
+        match *ir.inst(inst) {
+            InstData::Binary { op, ty, lhs, rhs } => {
+                let lty = llvm_type(ty);
+                let lhs = self.value(lhs);
+                let mut rhs = self.value(rhs);
+
+                match op {
+                    BinOp::Div | BinOp::Mod if !ty.is_float() => {
+                        let zero = self.tmp();
+                        self.line(format!("{} = icmp eq {} {}, 0", zero, lty, rhs));
+                        self.check(&zero, Trap::DIV0);
+                    }
+
+                    // the shift amount is masked like on the machine
+                    BinOp::Shl | BinOp::Shr | BinOp::Sar => {
+                        let amount = self.tmp();
+                        self.line(format!(
+                            "{} = and {} {}, {}",
+                            amount,
+                            lty,
+                            rhs,
+                            bit_width(ty) - 1
+                        ));
+                        rhs = amount;
+                    }
+
+                    _ => {}
+                }
+
+                self.line(format!(
+                    "{} = {} {} {}, {}",
+                    result.unwrap(),
+                    binop_name(op, ty),
+                    lty,
+                    lhs,
+                    rhs
+                ));
+            }
+
+            InstData::Unary { op, ty, opnd } => {
+                let lty = llvm_type(ty);
+                let opnd = self.value(opnd);
+                let result = result.unwrap();
+
+                let text = match op {
+                    UnOp::Neg if ty.is_float() => format!("fneg {} {}", lty, opnd),
+                    UnOp::Neg => format!("sub {} 0, {}", lty, opnd),
+                    UnOp::Not if ty == Type::Bool => format!("xor i1 {}, true", opnd),
+                    UnOp::Not => format!("xor {} {}, -1", lty, opnd),
+                    UnOp::Sqrt => {
+                        let sqrt = if ty == Type::Float {
+                            "@llvm.sqrt.f32"
+                        } else {
+                            "@llvm.sqrt.f64"
+                        };
+                        let sqrt = self.module.runtime(sqrt);
+                        format!("call {} {}({} {})", lty, sqrt, lty, opnd)
+                    }
+                };
+
+                self.line(format!("{} = {}", result, text));
+            }
+
+            InstData::Convert { op, from, to, opnd } => {
+                let name = match op {
+                    ConvOp::ZeroExtend => "zext",
+                    ConvOp::SignExtend => "sext",
+                    ConvOp::Truncate => "trunc",
+                    ConvOp::IntToFloat => "sitofp",
+                    ConvOp::FloatToInt => "fptosi",
+                    ConvOp::FloatExtend => "fpext",
+                    ConvOp::FloatTruncate => "fptrunc",
+                    ConvOp::Reinterpret => "bitcast",
+                };
+
+                self.line(format!(
+                    "{} = {} {} {} to {}",
+                    result.unwrap(),
+                    name,
+                    llvm_type(from),
+                    self.value(opnd),
+                    llvm_type(to)
+                ));
+            }
+
+            InstData::Cmp { ty, op, lhs, rhs } => {
+                let text = if ty.is_float() {
+                    format!("fcmp {}", fcmp_name(op))
+                } else {
+                    format!("icmp {}", icmp_name(op))
+                };
+
+                self.line(format!(
+                    "{} = {} {} {}, {}",
+                    result.unwrap(),
+                    text,
+                    llvm_type(ty),
+                    self.value(lhs),
+                    self.value(rhs)
+                ));
+            }
+
+            InstData::Goto { target } => {
+                self.line(format!("br label %{}", self.names.block(target)));
+            }
+
+            InstData::If {
+                opnd,
+                then_block,
+                else_block,
+            } => {
+                self.line(format!(
+                    "br i1 {}, label %{}, label %{}",
+                    self.value(opnd),
+                    self.names.block(then_block),
+                    self.names.block(else_block)
+                ));
+            }
+
+            InstData::Ret { opnd: Some(opnd) } => {
+                self.line(format!("ret {}", self.typed(opnd)));
+            }
+
+            InstData::Ret { opnd: None } => self.line("ret void".into()),
+
+            // constants are used as operands, params are named in the signature
+            InstData::TrueConst
+            | InstData::FalseConst
+            | InstData::NilConst
+            | InstData::Int8Const(_)
+            | InstData::CharConst(_)
+            | InstData::Int32Const(_)
+            | InstData::Int64Const(_)
+            | InstData::Float32Const(_)
+            | InstData::Float64Const(_)
+            | InstData::Param { .. } => {}
+
+            InstData::StringConst(ref value) => {
+                let string = self.module.string(value);
+                let fct = self.module.runtime("@dora.string");
+                self.line(format!(
+                    "{} = call ptr addrspace(1) {}(ptr {}, i64 {})",
+                    result.unwrap(),
+                    fct,
+                    string,
+                    value.len()
+                ));
+            }
+
+            InstData::Phi { ty, ref inputs } => {
+                let inputs: Vec<String> = inputs
+                    .iter()
+                    .map(|&(pred, value)| {
+                        format!("[ {}, %{} ]", self.value(value), self.exits[&pred])
+                    })
+                    .collect();
+
+                self.line(format!(
+                    "{} = phi {} {}",
+                    result.unwrap(),
+                    llvm_type(ty),
+                    inputs.join(", ")
+                ));
+            }
+
+            InstData::LoadField {
+                ty,
+                obj,
+                cls,
+                field,
+            } => {
+                let offset = self.field_offset(cls, field.idx());
+                let address = self.offset_address(obj, offset);
+                self.load(&result.unwrap(), ty, &address);
+            }
+
+            InstData::StoreField {
+                ty,
+                obj,
+                cls,
+                field,
+                value,
+            } => {
+                let offset = self.field_offset(cls, field.idx());
+                let address = self.offset_address(obj, offset);
+                self.store(ty, value, &address);
+                self.write_barrier(ty, obj);
+            }
+
+            InstData::LoadGlobal { ty, global } => {
+                let global = self.module.global(global, ty);
+                self.load(&result.unwrap(), ty, &format!("ptr {}", global));
+            }
+
+            InstData::StoreGlobal { ty, global, value } => {
+                let global = self.module.global(global, ty);
+                self.store(ty, value, &format!("ptr {}", global));
+            }
+
+            InstData::LoadArray { ty, array, index } => {
+                let address = self.element_address(ty, array, index);
+                self.load(&result.unwrap(), ty, &address);
+            }
+
+            InstData::StoreArray {
+                ty,
+                array,
+                index,
+                value,
+            } => {
+                let address = self.element_address(ty, array, index);
+                self.store(ty, value, &address);
+                self.write_barrier(ty, array);
+            }
+
+            InstData::ArrayLength { array } => {
+                let address = self.offset_address(array, offset_of_array_length());
+                self.load(&result.unwrap(), Type::Int, &address);
+            }
+
+            InstData::SetUint8 { address, value } => {
+                let ptr = self.tmp();
+                self.line(format!(
+                    "{} = inttoptr i64 {} to ptr",
+                    ptr,
+                    self.value(address)
+                ));
+                self.line(format!("store i8 {}, ptr {}", self.value(value), ptr));
+            }
+
+            InstData::NilCheck { opnd } => self.nil_check(opnd),
+
+            InstData::BoundsCheck { index, length } => {
+                let cond = self.tmp();
+                self.line(format!(
+                    "{} = icmp uge i32 {}, {}",
+                    cond,
+                    self.value(index),
+                    self.value(length)
+                ));
+                self.check(&cond, Trap::INDEX_OUT_OF_BOUNDS);
+            }
+
+            InstData::InstanceOf { opnd, cls } => {
+                let vtable = self.module.vtable(cls);
+                let fct = self.module.runtime("@dora.instance_of");
+                self.line(format!(
+                    "{} = call i1 {}({}, ptr {})",
+                    result.unwrap(),
+                    fct,
+                    self.typed(opnd),
+                    vtable
+                ));
+            }
+
+            InstData::CheckedCast { opnd, cls } => {
+                let vtable = self.module.vtable(cls);
+                let fct = self.module.runtime("@dora.checked_cast");
+                self.line(format!(
+                    "call void {}({}, ptr {})",
+                    fct,
+                    self.typed(opnd),
+                    vtable
+                ));
+            }
+
+            InstData::HasClass { opnd, cls } => {
+                let vtable = self.module.vtable(cls);
+                let object_vtable = self.tmp();
+                self.line(format!(
+                    "{} = load ptr, {}",
+                    object_vtable,
+                    self.typed(opnd)
+                ));
+                self.line(format!(
+                    "{} = icmp eq ptr {}, {}",
+                    result.unwrap(),
+                    object_vtable,
+                    vtable
+                ));
+            }
+
+            InstData::Call {
+                ref kind,
+                ty,
+                ref args,
+            } => {
+                let return_type = ty.map(llvm_type).unwrap_or("void");
+                let arg_types: Vec<&str> = args
+                    .iter()
+                    .map(|&arg| llvm_type(ir.value_type(arg)))
+                    .collect();
+
+                let callee = match *kind {
+                    CallKind::Direct(fct_id, ref cls_type_params, ref fct_type_params)
+                    | CallKind::Static(fct_id, ref cls_type_params, ref fct_type_params) => {
+                        self.module.fct_symbol(
+                            fct_id,
+                            cls_type_params,
+                            fct_type_params,
+                            return_type,
+                            &arg_types,
+                        )
+                    }
+
+                    CallKind::Virtual(fct_id) => {
+                        let vtable_index = {
+                            let fct = self.module.vm.fcts.idx(fct_id);
+                            let fct = fct.read();
+                            fct.vtable_index.unwrap()
+                        };
+
+                        self.method(args[0], vtable_index)
+                    }
+
+                    // the closure holds the lambda in the first entry of its vtable
+                    CallKind::Lambda => self.method(args[0], 0),
+                };
+
+                let args: Vec<String> = args.iter().map(|&arg| self.typed(arg)).collect();
+                let call = format!("call {} {}({})", return_type, callee, args.join(", "));
+
+                match result {
+                    Some(result) => self.line(format!("{} = {}", result, call)),
+                    None => self.line(call),
+                }
+            }
+
+            InstData::NewObject { cls } => {
+                let vtable = self.module.vtable(cls);
+                let fct = self.module.runtime("@dora.alloc_object");
+                self.line(format!(
+                    "{} = call ptr addrspace(1) {}(ptr {})",
+                    result.unwrap(),
+                    fct,
+                    vtable
+                ));
+            }
+
+            InstData::NewArray { cls, length } => {
+                let vtable = self.module.vtable(cls);
+                let fct = self.module.runtime("@dora.alloc_array");
+                self.line(format!(
+                    "{} = call ptr addrspace(1) {}(ptr {}, i32 {})",
+                    result.unwrap(),
+                    fct,
+                    vtable,
+                    self.value(length)
+                ));
+            }
+
+            InstData::Throw { opnd } => {
+                let fct = self.module.runtime("@dora.throw");
+                self.line(format!("call void {}({})", fct, self.typed(opnd)));
+                self.line("unreachable".into());
+            }
+
+            InstData::Trap { trap } => {
+                self.use_trap(trap);
+                self.line(format!("br label %trap.{}", trap_name(trap)));
+            }
+
+            InstData::Deoptimize { pc, ref state } => {
+                let fct = self.module.runtime("@dora.deoptimize");
+                let mut args = vec![format!("i32 {}", pc)];
+                args.extend(state.iter().map(|&(_, value)| self.typed(value)));
+
+                self.line(format!("call void (i32, ...) {}({})", fct, args.join(", ")));
+                self.line("unreachable".into());
+            }
+
+            InstData::Spawn { opnd } => {
+                let fct = self.module.runtime("@dora.spawn");
+                self.line(format!("call void {}({})", fct, self.typed(opnd)));
+            }
+
+            InstData::Debug => {
+                let fct = self.module.runtime("@llvm.debugtrap");
+                self.line(format!("call void {}()", fct));
+            }
+
+            InstData::Deleted => unreachable!(),
+        }
+    }
+
+    fn line(&mut self, text: String) {
+        self.out.push_str("  ");
+        self.out.push_str(&text);
+        self.out.push('\n');
+    }
+
+    fn tmp(&mut self) -> String {
+        let tmp = format!("%t{}", self.next_tmp);
+        self.next_tmp += 1;
+        tmp
+    }
+
+    // constants are emitted as literals
+    fn value(&self, value: Value) -> String {
+        let inst = self.ir.dfg.value_inst(value);
+
+        match *self.ir.inst(inst) {
+            InstData::TrueConst => "true".into(),
+            InstData::FalseConst => "false".into(),
+            InstData::NilConst => "null".into(),
+            InstData::Int8Const(value) => format!("{}", value as i8),
+            InstData::CharConst(value) => format!("{}", value as u32),
+            InstData::Int32Const(value) => format!("{}", value),
+            InstData::Int64Const(value) => format!("{}", value),
+            InstData::Float32Const(value) => format!("0x{:016X}", (value as f64).to_bits()),
+            InstData::Float64Const(value) => format!("0x{:016X}", value.to_bits()),
+            _ => format!("%{}", self.names.value(value)),
+        }
+    }
+
+    fn typed(&self, value: Value) -> String {
+        format!(
+            "{} {}",
+            llvm_type(self.ir.value_type(value)),
+            self.value(value)
+        )
+    }
+
+    // branches to the trap if `cond` is true, the code continues in a new label
+    fn check(&mut self, cond: &str, trap: Trap) {
+        self.use_trap(trap);
+
+        let (block, checks) = self.current;
+        let next = label(&self.names, block, checks + 1);
+        self.current = (block, checks + 1);
+
+        self.line(format!(
+            "br i1 {}, label %trap.{}, label %{}",
+            cond,
+            trap_name(trap),
+            next
+        ));
+        self.out.push_str(&format!("{}:\n", next));
+    }
+
+    fn use_trap(&mut self, trap: Trap) {
+        if !self.traps.contains(&trap) {
+            self.traps.push(trap);
+        }
+    }
+
+    fn nil_check(&mut self, opnd: Value) {
+        let cond = self.tmp();
+        self.line(format!("{} = icmp eq {}, null", cond, self.typed(opnd)));
+        self.check(&cond, Trap::NIL);
+    }
+
+    // loads the function at `index` of the vtable of `receiver`
+    fn method(&mut self, receiver: Value, index: u32) -> String {
+        self.nil_check(receiver);
+
+        let vtable = self.tmp();
+        self.line(format!("{} = load ptr, {}", vtable, self.typed(receiver)));
+
+        let entry = self.tmp();
+        let offset = VTable::offset_of_method_table() + index as i32 * mem::ptr_width();
+        self.line(format!(
+            "{} = getelementptr i8, ptr {}, i64 {}",
+            entry, vtable, offset
+        ));
+
+        let method = self.tmp();
+        self.line(format!("{} = load ptr, ptr {}", method, entry));
+
+        method
+    }
+
+    fn field_offset(&self, cls_id: ClassDefId, field: usize) -> i32 {
+        let cls = self.module.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+        cls.fields[field].offset
+    }
+
+    fn offset_address(&mut self, obj: Value, offset: i32) -> String {
+        let address = self.tmp();
+        self.line(format!(
+            "{} = getelementptr i8, {}, i64 {}",
+            address,
+            self.typed(obj),
+            offset
+        ));
+        format!("ptr addrspace(1) {}", address)
+    }
+
+    fn element_address(&mut self, ty: Type, array: Value, index: Value) -> String {
+        let data = self.tmp();
+        self.line(format!(
+            "{} = getelementptr i8, {}, i64 {}",
+            data,
+            self.typed(array),
+            offset_of_array_data()
+        ));
+
+        let address = self.tmp();
+        self.line(format!(
+            "{} = getelementptr {}, ptr addrspace(1) {}, i32 {}",
+            address,
+            memory_type(ty),
+            data,
+            self.value(index)
+        ));
+        format!("ptr addrspace(1) {}", address)
+    }
+
+    // bools are stored as bytes
+    fn load(&mut self, dest: &str, ty: Type, address: &str) {
+        if ty == Type::Bool {
+            let byte = self.tmp();
+            self.line(format!("{} = load i8, {}", byte, address));
+            self.line(format!("{} = trunc i8 {} to i1", dest, byte));
+        } else {
+            self.line(format!("{} = load {}, {}", dest, llvm_type(ty), address));
+        }
+    }
+
+    fn store(&mut self, ty: Type, value: Value, address: &str) {
+        let value = if ty == Type::Bool {
+            let byte = self.tmp();
+            self.line(format!("{} = zext i1 {} to i8", byte, self.value(value)));
+            format!("i8 {}", byte)
+        } else {
+            self.typed(value)
+        };
+
+        self.line(format!("store {}, {}", value, address));
+    }
+
+    fn write_barrier(&mut self, ty: Type, obj: Value) {
+        if ty == Type::Ptr && self.module.vm.gc.needs_write_barrier() {
+            let fct = self.module.runtime("@dora.write_barrier");
+            self.line(format!("call void {}({})", fct, self.typed(obj)));
+        }
+    }
+}
+
+// instructions that branch to a trap and continue in a new label
+fn needs_label(inst_data: &InstData) -> bool {
+    match *inst_data {
+        InstData::NilCheck { .. } | InstData::BoundsCheck { .. } => true,
+        InstData::Binary {
+            op: BinOp::Div, ty, ..
+        }
+        | InstData::Binary {
+            op: BinOp::Mod, ty, ..
+        } => !ty.is_float(),
+        InstData::Call {
+            kind: CallKind::Virtual(_),
+            ..
+        }
+        | InstData::Call {
+            kind: CallKind::Lambda,
+            ..
+        } => true,
+        _ => false,
+    }
+}
+
+fn label(names: &Names, block: Block, checks: usize) -> String {
+    if checks == 0 {
+        names.block(block)
+    } else {
+        format!("{}.{}", names.block(block), checks)
+    }
+}
+
+// the type of the returned values, functions without returns are void
+fn return_type(ir: &Function) -> &'static str {
+    for &block in ir.blocks() {
+        for inst in ir.insts(block) {
+            if let InstData::Ret { opnd: Some(opnd) } = *ir.inst(inst) {
+                return llvm_type(ir.value_type(opnd));
+            }
+        }
+    }
+
+    "void"
+}
+
+fn llvm_type(ty: Type) -> &'static str {
+    match ty {
+        Type::Bool => "i1",
+        Type::Byte => "i8",
+        Type::Char | Type::Int => "i32",
+        Type::Long => "i64",
+        Type::Float => "float",
+        Type::Double => "double",
+        Type::Ptr => "ptr addrspace(1)",
+    }
+}
+
+fn memory_type(ty: Type) -> &'static str {
+    match ty {
+        Type::Bool => "i8",
+        _ => llvm_type(ty),
+    }
+}
+
+fn bit_width(ty: Type) -> u32 {
+    match ty {
+        Type::Bool => 1,
+        Type::Byte => 8,
+        Type::Char | Type::Int | Type::Float => 32,
+        Type::Long | Type::Double | Type::Ptr => 64,
+    }
+}
+
+fn binop_name(op: BinOp, ty: Type) -> &'static str {
+    let float = ty.is_float();
+
+    match op {
+        BinOp::Add if float => "fadd",
+        BinOp::Add => "add",
+        BinOp::Sub if float => "fsub",
+        BinOp::Sub => "sub",
+        BinOp::Mul if float => "fmul",
+        BinOp::Mul => "mul",
+        BinOp::Div if float => "fdiv",
+        BinOp::Div => "sdiv",
+        BinOp::Mod if float => "frem",
+        BinOp::Mod => "srem",
+        BinOp::And => "and",
+        BinOp::Or => "or",
+        BinOp::Xor => "xor",
+        BinOp::Shl => "shl",
+        BinOp::Shr => "lshr",
+        BinOp::Sar => "ashr",
+    }
+}
+
+// comparisons are signed like in the machine code
+fn icmp_name(op: CmpOp) -> &'static str {
+    match op {
+        CmpOp::Lt => "slt",
+        CmpOp::Le => "sle",
+        CmpOp::Eq => "eq",
+        CmpOp::Ne => "ne",
+        CmpOp::Gt => "sgt",
+        CmpOp::Ge => "sge",
+    }
+}
+
+fn fcmp_name(op: CmpOp) -> &'static str {
+    match op {
+        CmpOp::Lt => "olt",
+        CmpOp::Le => "ole",
+        CmpOp::Eq => "oeq",
+        CmpOp::Ne => "une",
+        CmpOp::Gt => "ogt",
+        CmpOp::Ge => "oge",
+    }
+}
+
+// names of Dora functions contain spaces and parentheses and are always quoted
+fn symbol(name: &str) -> String {
+    format!("@\"{}\"", escape(name.as_bytes()))
+}
+
+fn escape(bytes: &[u8]) -> String {
+    let mut result = String::new();
+
+    for &byte in bytes {
+        if byte >= 0x20 && byte < 0x7f && byte != b'"' && byte != b'\\' {
+            result.push(byte as char);
+        } else {
+            result.push_str(&format!("\\{:02X}", byte));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Module;
+    use crate::boots::function::Type;
+    use crate::boots::ssagen;
+    use crate::bytecode::astgen;
+    use crate::bytecode::generate::Register;
+    use crate::class::TypeParams;
+    use crate::test;
+
+    // the module with the function `f` of `code`
+    fn llvm(code: &'static str) -> String {
+        test::parse(code, |vm| {
+            let fct_id = vm.fct_by_name("f").expect("no function `f`.");
+            let tp = TypeParams::empty();
+            let bytecode = astgen::generate(vm, fct_id, &tp, &tp);
+            let fct = vm.fcts.idx(fct_id);
+            let fct = fct.read();
+
+            let ir = ssagen::generate(vm, &fct, &bytecode).unwrap();
+            let param_types: Vec<Type> = (0..fct.params_with_self().len())
+                .map(|idx| Type::from(bytecode.register(Register(idx))))
+                .collect();
+
+            let mut module = Module::new(vm);
+            module.add_function(&fct, &param_types, &ir);
+            module.to_string()
+        })
+    }
+
+    #[test]
+    fn llvm_arithmetic() {
+        let text = llvm("fun f(a: Int, b: Int) -> Int { return a * b + a; }");
+        assert!(text.contains("define i32 @\"f(Int, Int) -> Int\"(i32 %v0, i32 %v1) {"));
+        assert!(text.contains(" = mul i32 %v0, %v1\n"));
+        assert!(text.contains("  ret i32 %v"));
+    }
+
+    #[test]
+    fn llvm_division_traps_on_zero() {
+        let text = llvm("fun f(a: Long, b: Long) -> Long { return a / b; }");
+        assert!(text.contains("%t0 = icmp eq i64 %v1, 0\n"));
+        assert!(text.contains("br i1 %t0, label %trap.div0, label %b0.1\n"));
+        assert!(text.contains(" = sdiv i64 %v0, %v1\n"));
+        assert!(text.contains("trap.div0:\n  call void @dora.trap(i32 1)\n  unreachable\n"));
+        assert!(text.contains("declare void @dora.trap(i32) noreturn\n"));
+    }
+
+    #[test]
+    fn llvm_loop_uses_phis() {
+        let text = llvm(
+            "fun f(n: Int) -> Int {
+                var i = 0;
+                var sum = 0;
+                while i < n { sum = sum + i; i = i + 1; }
+                return sum;
+            }",
+        );
+        assert!(text.contains(" = phi i32 [ 0, %b"));
+        assert!(text.contains(" = icmp slt i32 "));
+        assert!(text.contains("br i1 %v"));
+    }
+
+    #[test]
+    fn llvm_array_access() {
+        let text = llvm("fun f(a: Array[Bool], i: Int) -> Bool { return a(i); }");
+        assert!(text.contains("= icmp eq ptr addrspace(1) %v0, null\n"));
+        assert!(text.contains("= icmp uge i32 %v1, "));
+        assert!(text.contains(", label %trap.index_out_of_bounds, label %"));
+        assert!(text.contains("= load i8, ptr addrspace(1) %t"));
+        assert!(text.contains("= trunc i8 %t"));
+        assert!(text.contains("ret i1 %v"));
+    }
+
+    #[test]
+    fn llvm_fields_and_calls() {
+        let text = llvm(
+            "class Foo(let a: Int) { fun get() -> Int = self.a; }
+            fun g(x: Int) -> Int = x;
+            fun f(foo: Foo) -> Int { return g(foo.a) + foo.get(); }",
+        );
+        assert!(text.contains("= getelementptr i8, ptr addrspace(1) %v0, i64 "));
+        assert!(text.contains("= call i32 @\"g(Int) -> Int\"(i32 %v"));
+        assert!(text.contains("= call i32 @\"Foo.get() -> Int\"(ptr addrspace(1) %v0)"));
+        assert!(text.contains("declare i32 @\"g(Int) -> Int\"(i32)\n"));
+        assert!(text.contains("declare i32 @\"Foo.get() -> Int\"(ptr addrspace(1))\n"));
+    }
+}
@@ -0,0 +1,1034 @@
+use std::collections::hash_map::HashMap;
+use std::str::FromStr;
+
+use crate::boots::function::{Block, Function, Type, Value};
+use crate::boots::inst::{BinOp, CallKind, CmpOp, ConvOp, Inst, InstData, UnOp};
+use crate::boots::printer::trap_from_name;
+use crate::boots::utils::VecKey;
+use crate::class::{ClassDefId, ClassId, FieldId, TypeParams};
+use crate::ty::{BuiltinType, TypeListId};
+use crate::vm::{EnumId, FctId, GlobalId, StructId, TraitId};
+
+// reads a function in the format written by the printer, operands may refer
+// to values defined later in the text. The result is not verified.
+pub fn parse(text: &str) -> Result<Function, String> {
+    let tokens = lex(text)?;
+
+    let mut parser = Parser {
+        tokens: tokens,
+        pos: 0,
+        fct: Function::new(),
+        blocks: HashMap::new(),
+        values: HashMap::new(),
+        operands: Vec::new(),
+        uses: Vec::new(),
+    };
+
+    parser.parse_function()?;
+    Ok(parser.fct)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Punct(char),
+    Arrow,
+}
+
+struct TokenInfo {
+    token: Token,
+    line: usize,
+    line_start: bool,
+}
+
+fn lex(text: &str) -> Result<Vec<TokenInfo>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut line_start = true;
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        let ch = chars[idx];
+
+        if ch == '\n' {
+            line += 1;
+            line_start = true;
+            idx += 1;
+            continue;
+        }
+
+        if ch.is_whitespace() {
+            idx += 1;
+            continue;
+        }
+
+        // comments extend to the end of the line
+        if ch == ';' {
+            while idx < chars.len() && chars[idx] != '\n' {
+                idx += 1;
+            }
+            continue;
+        }
+
+        let token = if ch == '-' && chars.get(idx + 1) == Some(&'>') {
+            idx += 2;
+            Token::Arrow
+        } else if ch.is_alphabetic() || ch == '_' {
+            let start = idx;
+            while idx < chars.len() && (chars[idx].is_alphanumeric() || chars[idx] == '_') {
+                idx += 1;
+            }
+            Token::Ident(chars[start..idx].iter().collect())
+        } else if ch.is_ascii_digit() || ch == '-' {
+            let start = idx;
+            idx += 1;
+            while idx < chars.len()
+                && (chars[idx].is_alphanumeric()
+                    || chars[idx] == '.'
+                    || (chars[idx] == '-' && (chars[idx - 1] == 'e' || chars[idx - 1] == 'E')))
+            {
+                idx += 1;
+            }
+            Token::Number(chars[start..idx].iter().collect())
+        } else if ch == '"' {
+            let (value, end) = lex_string(&chars, idx + 1).map_err(|msg| at(line, &msg))?;
+            idx = end;
+            Token::Str(value)
+        } else if "=,:()[]{}".contains(ch) {
+            idx += 1;
+            Token::Punct(ch)
+        } else {
+            return Err(at(line, &format!("unexpected character `{}`", ch)));
+        };
+
+        tokens.push(TokenInfo {
+            token: token,
+            line: line,
+            line_start: line_start,
+        });
+        line_start = false;
+    }
+
+    Ok(tokens)
+}
+
+// string literal with the escapes of Rust's debug format, returns the
+// value and the index after the closing quote
+fn lex_string(chars: &[char], mut idx: usize) -> Result<(String, usize), String> {
+    let mut value = String::new();
+
+    loop {
+        match chars.get(idx) {
+            None | Some('\n') => return Err("unterminated string".into()),
+            Some('"') => return Ok((value, idx + 1)),
+
+            Some('\\') => {
+                let escaped = match chars.get(idx + 1) {
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('0') => '\0',
+                    Some('\\') => '\\',
+                    Some('"') => '"',
+                    Some('\'') => '\'',
+                    Some('u') if chars.get(idx + 2) == Some(&'{') => {
+                        let start = idx + 3;
+                        let end = start
+                            + chars[start..]
+                                .iter()
+                                .position(|&ch| ch == '}')
+                                .ok_or("invalid unicode escape")?;
+                        let code: String = chars[start..end].iter().collect();
+                        let ch = u32::from_str_radix(&code, 16)
+                            .ok()
+                            .and_then(std::char::from_u32)
+                            .ok_or("invalid unicode escape")?;
+                        value.push(ch);
+                        idx = end + 1;
+                        continue;
+                    }
+                    _ => return Err("invalid escape".into()),
+                };
+
+                value.push(escaped);
+                idx += 2;
+            }
+
+            Some(&ch) => {
+                value.push(ch);
+                idx += 1;
+            }
+        }
+    }
+}
+
+fn at(line: usize, msg: &str) -> String {
+    format!("line {}: {}", line, msg)
+}
+
+struct Parser {
+    tokens: Vec<TokenInfo>,
+    pos: usize,
+    fct: Function,
+    blocks: HashMap<String, Block>,
+    values: HashMap<String, Value>,
+
+    // operand names of the instruction currently parsed
+    operands: Vec<String>,
+
+    // operand names of all instructions, resolved at the end of the function
+    uses: Vec<(Inst, Vec<String>, usize)>,
+}
+
+impl Parser {
+    fn parse_function(&mut self) -> Result<(), String> {
+        self.expect_keyword("function")?;
+        self.fct.name = match self.next() {
+            Some(Token::Ident(name)) | Some(Token::Str(name)) => name,
+            _ => return Err(self.error("function name")),
+        };
+        self.expect_punct('{')?;
+        self.declare_blocks()?;
+
+        let mut block: Option<Block> = None;
+
+        while !self.is_punct('}') {
+            if self.is_label() {
+                let name = self.ident()?;
+                self.expect_punct(':')?;
+                block = Some(self.blocks[&name]);
+                continue;
+            }
+
+            let line = self.line();
+            let block = block.ok_or_else(|| at(line, "instruction outside of block"))?;
+            self.parse_inst(block)?;
+        }
+
+        self.expect_punct('}')?;
+
+        if self.pos < self.tokens.len() {
+            return Err(at(self.line(), "unexpected text after function"));
+        }
+
+        self.resolve_uses()
+    }
+
+    // blocks are created in order of their labels, so jumps may refer to later blocks
+    fn declare_blocks(&mut self) -> Result<(), String> {
+        for idx in self.pos..self.tokens.len() {
+            let info = &self.tokens[idx];
+
+            if let Token::Ident(ref name) = info.token {
+                let next = self.tokens.get(idx + 1).map(|info| &info.token);
+
+                if info.line_start && next == Some(&Token::Punct(':')) {
+                    if self.blocks.contains_key(name) {
+                        return Err(at(info.line, &format!("block {} defined twice", name)));
+                    }
+
+                    let block = self.fct.make_block();
+                    self.blocks.insert(name.clone(), block);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_label(&self) -> bool {
+        match self.tokens.get(self.pos) {
+            Some(info) => {
+                info.line_start
+                    && self.tokens.get(self.pos + 1).map(|info| &info.token)
+                        == Some(&Token::Punct(':'))
+            }
+            None => false,
+        }
+    }
+
+    fn parse_inst(&mut self, block: Block) -> Result<(), String> {
+        let line = self.line();
+
+        let result =
+            if self.tokens.get(self.pos + 1).map(|info| &info.token) == Some(&Token::Punct('=')) {
+                let name = self.ident()?;
+                self.expect_punct('=')?;
+                Some(name)
+            } else {
+                None
+            };
+
+        let opcode = self.ident()?;
+        self.operands.clear();
+        let inst_data = self.parse_inst_data(&opcode)?;
+
+        if inst_data.result_type().is_some() != result.is_some() {
+            return Err(at(
+                line,
+                &format!(
+                    "result of `{}` needs to be named exactly if it exists",
+                    opcode
+                ),
+            ));
+        }
+
+        let inst = self.fct.append_inst(block, inst_data);
+
+        if let Some(name) = result {
+            if self.values.contains_key(&name) {
+                return Err(at(line, &format!("value {} defined twice", name)));
+            }
+
+            let value = self.fct.inst_result(inst).unwrap();
+            self.values.insert(name, value);
+        }
+
+        let operands = self.operands.drain(..).collect();
+        self.uses.push((inst, operands, line));
+
+        Ok(())
+    }
+
+    fn parse_inst_data(&mut self, opcode: &str) -> Result<InstData, String> {
+        if let Some(op) = BinOp::from_name(opcode) {
+            let ty = self.ty()?;
+            let lhs = self.value()?;
+            self.expect_punct(',')?;
+            let rhs = self.value()?;
+
+            return Ok(InstData::Binary {
+                op: op,
+                ty: ty,
+                lhs: lhs,
+                rhs: rhs,
+            });
+        }
+
+        if let Some(op) = UnOp::from_name(opcode) {
+            let ty = self.ty()?;
+            let opnd = self.value()?;
+
+            return Ok(InstData::Unary {
+                op: op,
+                ty: ty,
+                opnd: opnd,
+            });
+        }
+
+        if let Some(op) = ConvOp::from_name(opcode) {
+            let from = self.ty()?;
+            self.expect(Token::Arrow)?;
+            let to = self.ty()?;
+            let opnd = self.value()?;
+
+            return Ok(InstData::Convert {
+                op: op,
+                from: from,
+                to: to,
+                opnd: opnd,
+            });
+        }
+
+        let inst_data = match opcode {
+            "cmp" => {
+                let name = self.ident()?;
+                let op = CmpOp::from_name(&name).ok_or_else(|| self.error("comparison"))?;
+                let ty = self.ty()?;
+                let lhs = self.value()?;
+                self.expect_punct(',')?;
+                let rhs = self.value()?;
+
+                InstData::Cmp {
+                    ty: ty,
+                    op: op,
+                    lhs: lhs,
+                    rhs: rhs,
+                }
+            }
+
+            "goto" => InstData::Goto {
+                target: self.block()?,
+            },
+
+            "if" => {
+                let opnd = self.value()?;
+                self.expect_punct(',')?;
+                let then_block = self.block()?;
+                self.expect_punct(',')?;
+                let else_block = self.block()?;
+
+                InstData::If {
+                    opnd: opnd,
+                    then_block: then_block,
+                    else_block: else_block,
+                }
+            }
+
+            "ret" => {
+                let opnd = if self.is_value() {
+                    Some(self.value()?)
+                } else {
+                    None
+                };
+
+                InstData::Ret { opnd: opnd }
+            }
+
+            "const" => self.parse_const()?,
+
+            "param" => {
+                let ty = self.ty()?;
+                let idx = self.number()?;
+
+                InstData::Param { ty: ty, idx: idx }
+            }
+
+            "phi" => {
+                let ty = self.ty()?;
+                let mut inputs = Vec::new();
+                self.expect_punct('[')?;
+
+                while !self.is_punct(']') {
+                    if !inputs.is_empty() {
+                        self.expect_punct(',')?;
+                    }
+
+                    let block = self.block()?;
+                    self.expect_punct(':')?;
+                    let value = self.value()?;
+                    inputs.push((block, value));
+                }
+
+                self.expect_punct(']')?;
+
+                InstData::Phi {
+                    ty: ty,
+                    inputs: inputs,
+                }
+            }
+
+            "load_field" => {
+                let ty = self.ty()?;
+                let obj = self.value()?;
+                let (cls, field) = self.field()?;
+
+                InstData::LoadField {
+                    ty: ty,
+                    obj: obj,
+                    cls: cls,
+                    field: field,
+                }
+            }
+
+            "store_field" => {
+                let ty = self.ty()?;
+                let obj = self.value()?;
+                let (cls, field) = self.field()?;
+                self.expect_punct(',')?;
+                let value = self.value()?;
+
+                InstData::StoreField {
+                    ty: ty,
+                    obj: obj,
+                    cls: cls,
+                    field: field,
+                    value: value,
+                }
+            }
+
+            "load_global" => {
+                let ty = self.ty()?;
+                let global = self.global()?;
+
+                InstData::LoadGlobal {
+                    ty: ty,
+                    global: global,
+                }
+            }
+
+            "store_global" => {
+                let ty = self.ty()?;
+                let global = self.global()?;
+                self.expect_punct(',')?;
+                let value = self.value()?;
+
+                InstData::StoreGlobal {
+                    ty: ty,
+                    global: global,
+                    value: value,
+                }
+            }
+
+            "load_array" => {
+                let ty = self.ty()?;
+                let array = self.value()?;
+                self.expect_punct(',')?;
+                let index = self.value()?;
+
+                InstData::LoadArray {
+                    ty: ty,
+                    array: array,
+                    index: index,
+                }
+            }
+
+            "store_array" => {
+                let ty = self.ty()?;
+                let array = self.value()?;
+                self.expect_punct(',')?;
+                let index = self.value()?;
+                self.expect_punct(',')?;
+                let value = self.value()?;
+
+                InstData::StoreArray {
+                    ty: ty,
+                    array: array,
+                    index: index,
+                    value: value,
+                }
+            }
+
+            "array_length" => InstData::ArrayLength {
+                array: self.value()?,
+            },
+
+            "set_uint8" => {
+                let address = self.value()?;
+                self.expect_punct(',')?;
+                let value = self.value()?;
+
+                InstData::SetUint8 {
+                    address: address,
+                    value: value,
+                }
+            }
+
+            "nil_check" => InstData::NilCheck {
+                opnd: self.value()?,
+            },
+
+            "bounds_check" => {
+                let index = self.value()?;
+                self.expect_punct(',')?;
+                let length = self.value()?;
+
+                InstData::BoundsCheck {
+                    index: index,
+                    length: length,
+                }
+            }
+
+            "instance_of" => {
+                let opnd = self.value()?;
+                self.expect_punct(',')?;
+                let cls = self.class()?;
+
+                InstData::InstanceOf {
+                    opnd: opnd,
+                    cls: cls,
+                }
+            }
+
+            "checked_cast" => {
+                let opnd = self.value()?;
+                self.expect_punct(',')?;
+                let cls = self.class()?;
+
+                InstData::CheckedCast {
+                    opnd: opnd,
+                    cls: cls,
+                }
+            }
+
+            "has_class" => {
+                let opnd = self.value()?;
+                self.expect_punct(',')?;
+                let cls = self.class()?;
+
+                InstData::HasClass {
+                    opnd: opnd,
+                    cls: cls,
+                }
+            }
+
+            "call" => self.parse_call()?,
+
+            "new_object" => InstData::NewObject { cls: self.class()? },
+
+            "new_array" => {
+                let cls = self.class()?;
+                self.expect_punct(',')?;
+                let length = self.value()?;
+
+                InstData::NewArray {
+                    cls: cls,
+                    length: length,
+                }
+            }
+
+            "throw" => InstData::Throw {
+                opnd: self.value()?,
+            },
+
+            "trap" => {
+                let name = self.ident()?;
+                let trap = trap_from_name(&name).ok_or_else(|| self.error("trap"))?;
+
+                InstData::Trap { trap: trap }
+            }
+
+            "deoptimize" => {
+                let pc = self.number()?;
+                let mut state = Vec::new();
+                self.expect_punct('[')?;
+
+                while !self.is_punct(']') {
+                    if !state.is_empty() {
+                        self.expect_punct(',')?;
+                    }
+
+                    let reg = self.number()?;
+                    self.expect_punct(':')?;
+                    state.push((reg, self.value()?));
+                }
+
+                self.expect_punct(']')?;
+
+                InstData::Deoptimize {
+                    pc: pc,
+                    state: state,
+                }
+            }
+
+            "spawn" => InstData::Spawn {
+                opnd: self.value()?,
+            },
+
+            "debug" => InstData::Debug,
+
+            _ => {
+                let msg = format!("unknown instruction `{}`", opcode);
+                return Err(at(self.prev_line(), &msg));
+            }
+        };
+
+        Ok(inst_data)
+    }
+
+    fn parse_const(&mut self) -> Result<InstData, String> {
+        if let Some(&Token::Str(_)) = self.peek() {
+            return Err(self.error("type"));
+        }
+
+        let name = self.ident()?;
+
+        let inst_data = match name.as_str() {
+            "true" => InstData::TrueConst,
+            "false" => InstData::FalseConst,
+            "nil" => InstData::NilConst,
+            "byte" => InstData::Int8Const(self.number()?),
+            "char" => {
+                let code: u32 = self.number()?;
+                let value = std::char::from_u32(code).ok_or_else(|| self.error("char"))?;
+                InstData::CharConst(value)
+            }
+            "int" => InstData::Int32Const(self.number()?),
+            "long" => InstData::Int64Const(self.number()?),
+            "float" => InstData::Float32Const(self.float()?),
+            "double" => InstData::Float64Const(self.float()?),
+            "string" => match self.next() {
+                Some(Token::Str(value)) => InstData::StringConst(value),
+                _ => return Err(self.error("string")),
+            },
+            _ => return Err(self.error("constant")),
+        };
+
+        Ok(inst_data)
+    }
+
+    fn parse_call(&mut self) -> Result<InstData, String> {
+        let ty = match self.ident()?.as_str() {
+            "void" => None,
+            name => Some(Type::from_name(name).ok_or_else(|| self.error("type"))?),
+        };
+
+        let kind = match self.ident()?.as_str() {
+            "direct" => {
+                let fct_id = self.fct_id()?;
+                let (cls_type_params, fct_type_params) = self.type_params()?;
+                CallKind::Direct(fct_id, cls_type_params, fct_type_params)
+            }
+
+            "virtual" => CallKind::Virtual(self.fct_id()?),
+
+            "static" => {
+                let fct_id = self.fct_id()?;
+                let (cls_type_params, fct_type_params) = self.type_params()?;
+                CallKind::Static(fct_id, cls_type_params, fct_type_params)
+            }
+
+            "lambda" => CallKind::Lambda,
+            _ => return Err(self.error("call kind")),
+        };
+
+        let mut args = Vec::new();
+        self.expect_punct('(')?;
+
+        while !self.is_punct(')') {
+            if !args.is_empty() {
+                self.expect_punct(',')?;
+            }
+
+            args.push(self.value()?);
+        }
+
+        self.expect_punct(')')?;
+
+        Ok(InstData::Call {
+            kind: kind,
+            ty: ty,
+            args: args,
+        })
+    }
+
+    fn type_params(&mut self) -> Result<(TypeParams, TypeParams), String> {
+        if !self.is_punct('[') {
+            return Ok((TypeParams::empty(), TypeParams::empty()));
+        }
+
+        let cls_type_params = self.type_param_list()?;
+        let fct_type_params = self.type_param_list()?;
+
+        Ok((cls_type_params, fct_type_params))
+    }
+
+    fn type_param_list(&mut self) -> Result<TypeParams, String> {
+        let mut types = Vec::new();
+        self.expect_punct('[')?;
+
+        while !self.is_punct(']') {
+            if !types.is_empty() {
+                self.expect_punct(',')?;
+            }
+
+            types.push(self.type_param()?);
+        }
+
+        self.expect_punct(']')?;
+        Ok(TypeParams::with(types))
+    }
+
+    fn type_param(&mut self) -> Result<BuiltinType, String> {
+        let name = self.ident()?;
+
+        let ty = match name.as_str() {
+            "Unit" => BuiltinType::Unit,
+            "Bool" => BuiltinType::Bool,
+            "Byte" => BuiltinType::Byte,
+            "Char" => BuiltinType::Char,
+            "Int" => BuiltinType::Int,
+            "Long" => BuiltinType::Long,
+            "Float" => BuiltinType::Float,
+            "Double" => BuiltinType::Double,
+            "Ptr" => BuiltinType::Ptr,
+
+            "Class" | "Struct" => {
+                self.expect_punct('(')?;
+                let id: usize = self.number()?;
+                self.expect_punct(',')?;
+                let list_id: usize = self.number()?;
+                self.expect_punct(')')?;
+
+                if name == "Class" {
+                    BuiltinType::Class(ClassId::from(id), TypeListId::from(list_id))
+                } else {
+                    BuiltinType::Struct(StructId::from(id as u32), TypeListId::from(list_id))
+                }
+            }
+
+            "Trait" | "Enum" | "Lambda" => {
+                self.expect_punct('(')?;
+                let id: usize = self.number()?;
+                self.expect_punct(')')?;
+
+                match name.as_str() {
+                    "Trait" => BuiltinType::Trait(TraitId::from(id as u32)),
+                    "Enum" => BuiltinType::Enum(EnumId::from(id as u32)),
+                    _ => BuiltinType::Lambda(id.into()),
+                }
+            }
+
+            _ => return Err(self.error("type param")),
+        };
+
+        Ok(ty)
+    }
+
+    // all values are defined now, replace the placeholders by the real operands
+    fn resolve_uses(&mut self) -> Result<(), String> {
+        for (inst, names, line) in self.uses.drain(..) {
+            let mut operands = Vec::new();
+
+            for name in names {
+                match self.values.get(&name) {
+                    Some(&value) => operands.push(value),
+                    None => return Err(at(line, &format!("undefined value {}", name))),
+                }
+            }
+
+            let inst_data = self.fct.dfg.inst_mut(inst);
+
+            for (opnd, value) in inst_data.operands_mut().into_iter().zip(operands) {
+                *opnd = value;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        if !self.is_value() {
+            return Err(self.error("value"));
+        }
+
+        let name = self.ident()?;
+        self.operands.push(name);
+
+        Ok(Value::new(0))
+    }
+
+    fn is_value(&self) -> bool {
+        match self.peek() {
+            Some(&Token::Ident(ref name)) => {
+                name.starts_with('v') && name[1..].chars().all(|ch| ch.is_ascii_digit())
+            }
+            _ => false,
+        }
+    }
+
+    fn block(&mut self) -> Result<Block, String> {
+        let line = self.line();
+        let name = self.ident()?;
+
+        match self.blocks.get(&name) {
+            Some(&block) => Ok(block),
+            None => Err(at(line, &format!("undefined block {}", name))),
+        }
+    }
+
+    fn ty(&mut self) -> Result<Type, String> {
+        let name = self.ident()?;
+        Type::from_name(&name).ok_or_else(|| self.error("type"))
+    }
+
+    fn class(&mut self) -> Result<ClassDefId, String> {
+        self.expect_keyword("class")?;
+        let idx: usize = self.number()?;
+        Ok(idx.into())
+    }
+
+    fn field(&mut self) -> Result<(ClassDefId, FieldId), String> {
+        self.expect_punct(',')?;
+        let cls = self.class()?;
+        self.expect_punct(',')?;
+        self.expect_keyword("field")?;
+        let idx: usize = self.number()?;
+
+        Ok((cls, idx.into()))
+    }
+
+    fn global(&mut self) -> Result<GlobalId, String> {
+        self.expect_keyword("global")?;
+        let idx: u32 = self.number()?;
+        Ok(idx.into())
+    }
+
+    fn fct_id(&mut self) -> Result<FctId, String> {
+        self.expect_keyword("fct")?;
+        let idx: usize = self.number()?;
+        Ok(idx.into())
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, String> {
+        match self.next() {
+            Some(Token::Number(ref value)) => value.parse().map_err(|_| self.error("number")),
+            _ => Err(self.error("number")),
+        }
+    }
+
+    fn float<T: FromStr>(&mut self) -> Result<T, String> {
+        match self.next() {
+            Some(Token::Number(ref value)) | Some(Token::Ident(ref value)) => value
+                .parse()
+                .map_err(|_| self.error("floating point number")),
+            _ => Err(self.error("floating point number")),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(name),
+            _ => Err(self.error("identifier")),
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Ident(ref name)) if name == keyword => Ok(()),
+            _ => Err(self.error(&format!("`{}`", keyword))),
+        }
+    }
+
+    fn expect_punct(&mut self, punct: char) -> Result<(), String> {
+        self.expect(Token::Punct(punct))
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        if self.peek() == Some(&token) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            let expected = match token {
+                Token::Punct(punct) => format!("`{}`", punct),
+                Token::Arrow => "`->`".into(),
+                _ => format!("{:?}", token),
+            };
+
+            Err(self.error(&expected))
+        }
+    }
+
+    fn is_punct(&self, punct: char) -> bool {
+        self.peek() == Some(&Token::Punct(punct))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|info| &info.token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn line(&self) -> usize {
+        match self.tokens.get(self.pos) {
+            Some(info) => info.line,
+            None => self.tokens.last().map(|info| info.line).unwrap_or(1),
+        }
+    }
+
+    // line of the token before the current position
+    fn prev_line(&self) -> usize {
+        let idx = self.pos.saturating_sub(1);
+        let info = self.tokens.get(idx).or_else(|| self.tokens.last());
+        info.map(|info| info.line).unwrap_or(1)
+    }
+
+    fn error(&self, expected: &str) -> String {
+        at(self.prev_line(), &format!("{} expected", expected))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::boots::inst::InstData;
+
+    fn roundtrip(text: &str) {
+        let fct = parse(text).unwrap_or_else(|msg| panic!("{}", msg));
+        assert_eq!(text, fct.to_string());
+    }
+
+    #[test]
+    fn parse_roundtrip() {
+        roundtrip(
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = const int -3
+    v2 = add int v0, v1
+    v3 = cmp lt int v2, v0
+    if v3, b1, b2
+b1:
+    v4 = sext int -> long v2
+    v5 = const string \"a\\\"b\\n\"
+    nil_check v5
+    v6 = call long static fct 12 (v4)
+    call void direct fct 7 [Class(31, 0), Int] [] (v5, v6)
+    goto b2
+b2:
+    v7 = phi int [b0: v0, b1: v2]
+    ret v7
+}
+",
+        );
+
+        roundtrip(
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = param int 1
+    v2 = instance_of v0, class 4
+    v3 = has_class v0, class 5
+    if v2, b1, b2
+b1:
+    ret v1
+b2:
+    deoptimize 7 [0: v0, 1: v1, 3: v2]
+}
+",
+        );
+    }
+
+    #[test]
+    fn parse_forward_reference() {
+        let fct = parse(
+            "function loop {
+b0:
+    v0 = const int 0
+    goto b1
+b1:
+    v1 = phi int [b0: v0, b1: v2]
+    v2 = add int v1, v1
+    goto b1
+}",
+        )
+        .unwrap();
+
+        let b1 = fct.blocks()[1];
+        let phi = fct.insts(b1).next().unwrap();
+
+        match fct.inst(phi) {
+            InstData::Phi { inputs, .. } => {
+                assert_eq!(fct.cfg.predecessors(b1), &[fct.blocks()[0], b1]);
+                assert_eq!(
+                    inputs[1].1,
+                    fct.inst_result(fct.insts(b1).nth(1).unwrap()).unwrap()
+                );
+            }
+            _ => panic!("phi expected"),
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Err("line 3: undefined value v7".into()),
+            parse("function f {\nb0:\n    ret v7\n}").map(|_| ())
+        );
+        assert_eq!(
+            Err("line 3: unknown instruction `frobnicate`".into()),
+            parse("function f {\nb0:\n    frobnicate\n}").map(|_| ())
+        );
+        assert_eq!(
+            Err("line 3: undefined block b9".into()),
+            parse("function f {\nb0:\n    goto b9\n}").map(|_| ())
+        );
+    }
+}
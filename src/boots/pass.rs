@@ -0,0 +1,262 @@
+use crate::boots::function::Function;
+use crate::boots::verifier;
+
+pub mod bce;
+pub mod dce;
+pub mod escape;
+pub mod gvn;
+pub mod licm;
+pub mod sccp;
+pub mod simplify_cfg;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pass {
+    Escape,
+    Sccp,
+    Gvn,
+    Licm,
+    Bce,
+    Dce,
+    SimplifyCfg,
+}
+
+pub const PASSES: &[Pass] = &[
+    Pass::Escape,
+    Pass::Sccp,
+    Pass::Gvn,
+    Pass::Licm,
+    Pass::Bce,
+    Pass::Dce,
+    Pass::SimplifyCfg,
+];
+
+impl Pass {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Pass::Escape => "escape",
+            Pass::Sccp => "sccp",
+            Pass::Gvn => "gvn",
+            Pass::Licm => "licm",
+            Pass::Bce => "bce",
+            Pass::Dce => "dce",
+            Pass::SimplifyCfg => "simplify-cfg",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Pass> {
+        PASSES.iter().find(|pass| pass.name() == name).cloned()
+    }
+
+    pub fn run(&self, fct: &mut Function) {
+        match *self {
+            Pass::Escape => escape::run(fct),
+            Pass::Sccp => sccp::run(fct),
+            Pass::Gvn => gvn::run(fct),
+            Pass::Licm => licm::run(fct),
+            Pass::Bce => bce::run(fct),
+            Pass::Dce => dce::run(fct),
+            Pass::SimplifyCfg => simplify_cfg::run(fct),
+        }
+    }
+}
+
+// runs passes in order, with `verify` the function is verified after every pass
+pub struct PassManager {
+    passes: Vec<Pass>,
+    verify: bool,
+}
+
+impl PassManager {
+    pub fn new() -> PassManager {
+        PassManager {
+            passes: Vec::new(),
+            verify: cfg!(debug_assertions),
+        }
+    }
+
+    // objects that don't escape are replaced first, their fields are values
+    // for the other passes. Constants are folded next, so GVN and DCE see the
+    // simplified branches and CFG simplification can merge what remains. GVN removes
+    // checks in loops that repeat the checks of the header before they
+    // are hoisted, the hoisted array lengths are then used by BCE.
+    pub fn default_pipeline() -> PassManager {
+        let mut pm = PassManager::new();
+
+        for &pass in PASSES {
+            pm.add(pass);
+        }
+
+        pm
+    }
+
+    // comma-separated list of pass names, e.g. `sccp,dce`
+    pub fn from_names(names: &str) -> Result<PassManager, String> {
+        let mut pm = PassManager::new();
+
+        for name in names.split(',').map(|name| name.trim()) {
+            if name.is_empty() {
+                continue;
+            }
+
+            match Pass::from_name(name) {
+                Some(pass) => pm.add(pass),
+                None => {
+                    let names: Vec<&str> = PASSES.iter().map(|pass| pass.name()).collect();
+                    return Err(format!(
+                        "unknown pass `{}`, available passes: {}",
+                        name,
+                        names.join(", ")
+                    ));
+                }
+            }
+        }
+
+        Ok(pm)
+    }
+
+    pub fn add(&mut self, pass: Pass) {
+        self.passes.push(pass);
+    }
+
+    pub fn passes(&self) -> &[Pass] {
+        &self.passes
+    }
+
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    pub fn run(&self, fct: &mut Function) -> Result<(), String> {
+        for pass in &self.passes {
+            pass.run(fct);
+
+            if self.verify {
+                verifier::verify(fct).map_err(|msg| format!("after {}: {}", pass.name(), msg))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pass, PassManager};
+    use crate::boots::parser::parse;
+    use crate::boots::verifier::verify;
+
+    // runs the passes on `before` and compares the printed result with `after`
+    pub fn check(passes: &str, before: &str, after: &str) {
+        let mut fct = parse(before).unwrap_or_else(|msg| panic!("{}", msg));
+        verify(&fct).unwrap_or_else(|msg| panic!("input: {}", msg));
+
+        let pm = PassManager::from_names(passes).unwrap();
+        pm.run(&mut fct).unwrap_or_else(|msg| panic!("{}", msg));
+
+        assert_eq!(after, fct.to_string());
+    }
+
+    #[test]
+    fn pass_names() {
+        let pm = PassManager::from_names("sccp, dce,simplify-cfg").unwrap();
+        assert_eq!(&[Pass::Sccp, Pass::Dce, Pass::SimplifyCfg], pm.passes());
+
+        assert_eq!(
+            Err(
+                "unknown pass `inline`, available passes: escape, sccp, gvn, licm, bce, dce, simplify-cfg"
+                    .into()
+            ),
+            PassManager::from_names("gvn,inline").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn default_pipeline() {
+        check(
+            "sccp,gvn,dce,simplify-cfg",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = const int 2
+    v2 = const int 3
+    v3 = mul int v1, v2
+    v4 = const int 6
+    v5 = cmp eq int v3, v4
+    if v5, b1, b2
+b1:
+    v6 = add int v0, v3
+    v7 = add int v0, v3
+    v8 = mul int v6, v7
+    goto b3
+b2:
+    v9 = const int 0
+    goto b3
+b3:
+    v10 = phi int [b1: v8, b2: v9]
+    ret v10
+}",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = const int 6
+    v2 = add int v0, v1
+    v3 = mul int v2, v2
+    ret v3
+}
+",
+        );
+    }
+
+    #[test]
+    fn loop_checks_are_hoisted_or_removed() {
+        check(
+            "gvn,licm,bce,dce",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = const int 0
+    goto b1
+b1:
+    v2 = phi int [b0: v1, b2: v10]
+    v3 = phi int [b0: v1, b2: v8]
+    nil_check v0
+    v4 = array_length v0
+    v5 = cmp lt int v2, v4
+    if v5, b2, b3
+b2:
+    nil_check v0
+    v6 = array_length v0
+    bounds_check v2, v6
+    v7 = load_array int v0, v2
+    v8 = add int v3, v7
+    v9 = const int 1
+    v10 = add int v2, v9
+    goto b1
+b3:
+    ret v3
+}",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = const int 0
+    nil_check v0
+    v2 = array_length v0
+    v3 = const int 1
+    goto b1
+b1:
+    v4 = phi int [b0: v1, b2: v9]
+    v5 = phi int [b0: v1, b2: v8]
+    v6 = cmp lt int v4, v2
+    if v6, b2, b3
+b2:
+    v7 = load_array int v0, v4
+    v8 = add int v5, v7
+    v9 = add int v4, v3
+    goto b1
+b3:
+    ret v5
+}
+",
+        );
+    }
+}
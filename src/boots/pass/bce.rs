@@ -0,0 +1,510 @@
+use crate::boots::dom::DominatorTree;
+use crate::boots::function::{Block, Function, Type, Value};
+use crate::boots::inst::{BinOp, CmpOp, Inst, InstData};
+
+// bounds on values are only derived through this many definitions and facts
+const MAX_DEPTH: usize = 6;
+
+// bounds check elimination: a check is removed when its index is known to be
+// non-negative and less than the length. Facts about values come from the
+// conditions of dominating branches and from dominating bounds checks, phis
+// counting up from a non-negative start are non-negative.
+pub fn run(fct: &mut Function) {
+    if fct.entry_block().is_none() {
+        return;
+    }
+
+    let dom = DominatorTree::compute(fct);
+    let mut redundant = Vec::new();
+
+    {
+        let ranges = Ranges {
+            fct: fct,
+            dom: &dom,
+        };
+
+        for &block in dom.reverse_postorder() {
+            for inst in fct.insts(block) {
+                if let InstData::BoundsCheck { index, length } = *fct.inst(inst) {
+                    let facts = ranges.facts(block, Some(inst));
+
+                    if ranges
+                        .lower_bound(&facts, index, 0)
+                        .map_or(false, |lower| lower >= 0)
+                        && ranges.is_less(&facts, index, length, 0)
+                    {
+                        redundant.push(inst);
+                    }
+                }
+            }
+        }
+    }
+
+    for inst in redundant {
+        fct.remove_inst(inst);
+    }
+}
+
+// relations between int values that hold at some position
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Fact {
+    Less(Value, Value),
+    LessEq(Value, Value),
+    NonNegative(Value),
+}
+
+struct Ranges<'a> {
+    fct: &'a Function,
+    dom: &'a DominatorTree,
+}
+
+impl<'a> Ranges<'a> {
+    // facts before `inst` in `block`, without an instruction at the end of the block
+    fn facts(&self, block: Block, inst: Option<Inst>) -> Vec<Fact> {
+        let mut facts = Vec::new();
+
+        for other in self.fct.insts(block) {
+            if Some(other) == inst {
+                break;
+            }
+
+            self.check_facts(other, &mut facts);
+        }
+
+        let mut current = block;
+
+        loop {
+            self.edge_facts(current, &mut facts);
+
+            match self.dom.idom(current) {
+                Some(idom) => {
+                    for other in self.fct.insts(idom) {
+                        self.check_facts(other, &mut facts);
+                    }
+
+                    current = idom;
+                }
+
+                None => break,
+            }
+        }
+
+        facts
+    }
+
+    fn check_facts(&self, inst: Inst, facts: &mut Vec<Fact>) {
+        if let InstData::BoundsCheck { index, length } = *self.fct.inst(inst) {
+            facts.push(Fact::NonNegative(index));
+            facts.push(Fact::Less(index, length));
+        }
+    }
+
+    // the block is only entered through the branch of its single predecessor
+    fn edge_facts(&self, block: Block, facts: &mut Vec<Fact>) {
+        let preds = self.fct.cfg.predecessors(block);
+
+        if preds.len() != 1 {
+            return;
+        }
+
+        let term = match self.fct.terminator(preds[0]) {
+            Some(term) => term,
+            None => return,
+        };
+
+        let (opnd, taken) = match *self.fct.inst(term) {
+            InstData::If {
+                opnd,
+                then_block,
+                else_block,
+            } if then_block != else_block => (opnd, block == then_block),
+            _ => return,
+        };
+
+        let (op, lhs, rhs) = match *self.def(opnd) {
+            InstData::Cmp {
+                ty: Type::Int,
+                op,
+                lhs,
+                rhs,
+            } => (op, lhs, rhs),
+            _ => return,
+        };
+
+        let op = if taken { op } else { negate(op) };
+
+        match op {
+            CmpOp::Lt => facts.push(Fact::Less(lhs, rhs)),
+            CmpOp::Le => facts.push(Fact::LessEq(lhs, rhs)),
+            CmpOp::Gt => facts.push(Fact::Less(rhs, lhs)),
+            CmpOp::Ge => facts.push(Fact::LessEq(rhs, lhs)),
+            CmpOp::Eq => {
+                facts.push(Fact::LessEq(lhs, rhs));
+                facts.push(Fact::LessEq(rhs, lhs));
+            }
+            CmpOp::Ne => {}
+        }
+    }
+
+    fn def(&self, value: Value) -> &InstData {
+        self.fct.inst(self.fct.dfg.value_inst(value))
+    }
+
+    fn int_const(&self, value: Value) -> Option<i64> {
+        match *self.def(value) {
+            InstData::Int32Const(value) => Some(value as i64),
+            _ => None,
+        }
+    }
+
+    // the operand and the constant of `value + c` or `value - c`
+    fn offset(&self, value: Value) -> Option<(Value, i64)> {
+        match *self.def(value) {
+            InstData::Binary {
+                op: BinOp::Add,
+                ty: Type::Int,
+                lhs,
+                rhs,
+            } => match (self.int_const(lhs), self.int_const(rhs)) {
+                (_, Some(c)) => Some((lhs, c)),
+                (Some(c), _) => Some((rhs, c)),
+                _ => None,
+            },
+
+            InstData::Binary {
+                op: BinOp::Sub,
+                ty: Type::Int,
+                lhs,
+                rhs,
+            } => self.int_const(rhs).map(|c| (lhs, -c)),
+
+            _ => None,
+        }
+    }
+
+    // smallest value, None if unknown
+    fn lower_bound(&self, facts: &[Fact], value: Value, depth: usize) -> Option<i64> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        let mut lower = match *self.def(value) {
+            InstData::Int32Const(value) => Some(value as i64),
+
+            // lengths of new arrays are checked at the allocation
+            InstData::ArrayLength { array } => match *self.def(array) {
+                InstData::NewArray { length, .. } => {
+                    max(Some(0), self.lower_bound(&[], length, depth + 1))
+                }
+                _ => Some(0),
+            },
+
+            InstData::Phi { .. } => self.induction_lower_bound(value, depth),
+
+            InstData::Binary { .. } => match self.offset(value) {
+                Some((opnd, c)) if c <= 0 => self
+                    .lower_bound(facts, opnd, depth + 1)
+                    .map(|lower| lower + c)
+                    .filter(|&lower| lower >= i32::min_value() as i64),
+
+                Some((opnd, 1)) if self.has_upper_bound(facts, opnd) => self
+                    .lower_bound(facts, opnd, depth + 1)
+                    .map(|lower| lower + 1),
+
+                _ => None,
+            },
+
+            _ => None,
+        };
+
+        for &fact in facts {
+            let bound = match fact {
+                Fact::Less(lhs, rhs) if rhs == value => self
+                    .lower_bound(facts, lhs, depth + 1)
+                    .map(|lower| lower + 1),
+                Fact::LessEq(lhs, rhs) if rhs == value => self.lower_bound(facts, lhs, depth + 1),
+                Fact::NonNegative(opnd) if opnd == value => Some(0),
+                _ => None,
+            };
+
+            lower = max(lower, bound);
+        }
+
+        lower
+    }
+
+    // some int is greater than `value`, so `value + 1` does not overflow
+    fn has_upper_bound(&self, facts: &[Fact], value: Value) -> bool {
+        facts.iter().any(|&fact| match fact {
+            Fact::Less(lhs, _) => lhs == value,
+            _ => false,
+        })
+    }
+
+    // a phi in a loop header that is only incremented by one on back edges is
+    // at least as large as its inputs from outside of the loop
+    fn induction_lower_bound(&self, phi: Value, depth: usize) -> Option<i64> {
+        let phi_inst = self.fct.dfg.value_inst(phi);
+        let header = self.fct.cfg.inst_block(phi_inst)?;
+
+        let inputs = match *self.fct.inst(phi_inst) {
+            InstData::Phi {
+                ty: Type::Int,
+                ref inputs,
+            } => inputs,
+            _ => return None,
+        };
+
+        let mut lower: Option<i64> = None;
+
+        for &(pred, input) in inputs {
+            if input == phi {
+                continue;
+            }
+
+            if self.dom.dominates(header, pred) {
+                if !self.is_increment(input, phi) {
+                    return None;
+                }
+            } else {
+                let bound = self.lower_bound(&self.facts(pred, None), input, depth + 1)?;
+                lower = Some(lower.map_or(bound, |lower| lower.min(bound)));
+            }
+        }
+
+        lower
+    }
+
+    // `phi + 0` or `phi + 1` where the addition cannot overflow
+    fn is_increment(&self, value: Value, phi: Value) -> bool {
+        match self.offset(value) {
+            Some((opnd, 0)) => opnd == phi,
+            Some((opnd, 1)) if opnd == phi => {
+                let inst = self.fct.dfg.value_inst(value);
+                let block = self.fct.cfg.inst_block(inst).unwrap();
+                self.has_upper_bound(&self.facts(block, Some(inst)), phi)
+            }
+            _ => false,
+        }
+    }
+
+    // `lhs < rhs`
+    fn is_less(&self, facts: &[Fact], lhs: Value, rhs: Value, depth: usize) -> bool {
+        if depth > MAX_DEPTH {
+            return false;
+        }
+
+        for &fact in facts {
+            match fact {
+                Fact::Less(a, b) if a == lhs && b == rhs => return true,
+                Fact::Less(a, b) if a == lhs && self.is_less_eq(facts, b, rhs, depth + 1) => {
+                    return true
+                }
+                Fact::LessEq(a, b) if a == lhs && self.is_less(facts, b, rhs, depth + 1) => {
+                    return true
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(value) = self.int_const(lhs) {
+            return self
+                .lower_bound(facts, rhs, depth + 1)
+                .map_or(false, |lower| value < lower);
+        }
+
+        // `opnd - c` is less than anything greater than or equal to opnd unless
+        // the subtraction overflows
+        match self.offset(lhs) {
+            Some((opnd, 0)) => self.is_less(facts, opnd, rhs, depth + 1),
+
+            Some((opnd, c)) if c < 0 => {
+                self.lower_bound(facts, opnd, depth + 1)
+                    .map_or(false, |lower| lower + c >= i32::min_value() as i64)
+                    && self.is_less_eq(facts, opnd, rhs, depth + 1)
+            }
+
+            _ => false,
+        }
+    }
+
+    // `lhs <= rhs`
+    fn is_less_eq(&self, facts: &[Fact], lhs: Value, rhs: Value, depth: usize) -> bool {
+        lhs == rhs
+            || facts
+                .iter()
+                .any(|&fact| fact == Fact::LessEq(lhs, rhs) || fact == Fact::Less(lhs, rhs))
+            || self.is_less(facts, lhs, rhs, depth)
+    }
+}
+
+fn negate(op: CmpOp) -> CmpOp {
+    match op {
+        CmpOp::Lt => CmpOp::Ge,
+        CmpOp::Le => CmpOp::Gt,
+        CmpOp::Eq => CmpOp::Ne,
+        CmpOp::Ne => CmpOp::Eq,
+        CmpOp::Gt => CmpOp::Le,
+        CmpOp::Ge => CmpOp::Lt,
+    }
+}
+
+fn max(lhs: Option<i64>, rhs: Option<i64>) -> Option<i64> {
+    match (lhs, rhs) {
+        (Some(lhs), Some(rhs)) => Some(lhs.max(rhs)),
+        (lhs, None) => lhs,
+        (None, rhs) => rhs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::boots::pass::tests::check;
+
+    #[test]
+    fn bce_removes_checks_of_induction_variables() {
+        check(
+            "bce",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = const int 0
+    v2 = const int 1
+    nil_check v0
+    v3 = array_length v0
+    goto b1
+b1:
+    v4 = phi int [b0: v1, b2: v5]
+    v6 = cmp lt int v4, v3
+    if v6, b2, b3
+b2:
+    bounds_check v4, v3
+    store_array int v0, v4, v1
+    v5 = add int v4, v2
+    goto b1
+b3:
+    ret
+}",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = const int 0
+    v2 = const int 1
+    nil_check v0
+    v3 = array_length v0
+    goto b1
+b1:
+    v4 = phi int [b0: v1, b2: v6]
+    v5 = cmp lt int v4, v3
+    if v5, b2, b3
+b2:
+    store_array int v0, v4, v1
+    v6 = add int v4, v2
+    goto b1
+b3:
+    ret
+}
+",
+        );
+    }
+
+    #[test]
+    fn bce_uses_dominating_checks() {
+        check(
+            "bce",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = param int 1
+    v2 = const int 0
+    v3 = const int 1
+    nil_check v0
+    v4 = array_length v0
+    bounds_check v1, v4
+    v5 = sub int v1, v3
+    bounds_check v5, v4
+    bounds_check v2, v4
+    v6 = load_array int v0, v5
+    v7 = load_array int v0, v2
+    v8 = add int v6, v7
+    ret v8
+}",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = param int 1
+    v2 = const int 0
+    v3 = const int 1
+    nil_check v0
+    v4 = array_length v0
+    bounds_check v1, v4
+    v5 = sub int v1, v3
+    bounds_check v5, v4
+    v6 = load_array int v0, v5
+    v7 = load_array int v0, v2
+    v8 = add int v6, v7
+    ret v8
+}
+",
+        );
+    }
+
+    #[test]
+    fn bce_keeps_checks_that_may_fail() {
+        check(
+            "bce",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = const int 0
+    v2 = const int 1
+    nil_check v0
+    v3 = array_length v0
+    goto b1
+b1:
+    v4 = phi int [b0: v1, b2: v5]
+    v6 = cmp le int v4, v3
+    if v6, b2, b3
+b2:
+    bounds_check v4, v3
+    store_array int v0, v4, v1
+    v5 = add int v4, v2
+    goto b1
+b3:
+    v7 = const int 10
+    v8 = new_array class 3, v7
+    v9 = array_length v8
+    v10 = const int 9
+    bounds_check v10, v9
+    bounds_check v7, v9
+    ret
+}",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = const int 0
+    v2 = const int 1
+    nil_check v0
+    v3 = array_length v0
+    goto b1
+b1:
+    v4 = phi int [b0: v1, b2: v6]
+    v5 = cmp le int v4, v3
+    if v5, b2, b3
+b2:
+    bounds_check v4, v3
+    store_array int v0, v4, v1
+    v6 = add int v4, v2
+    goto b1
+b3:
+    v7 = const int 10
+    v8 = new_array class 3, v7
+    v9 = array_length v8
+    v10 = const int 9
+    bounds_check v7, v9
+    ret
+}
+",
+        );
+    }
+}
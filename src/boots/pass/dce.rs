@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+
+use crate::boots::function::Function;
+use crate::boots::inst::Inst;
+
+// removes instructions whose results are never used: instructions with side
+// effects are live, every instruction defining an operand of a live
+// instruction is live as well. Cycles of dead phis are removed too.
+pub fn run(fct: &mut Function) {
+    let mut live = HashSet::new();
+    let mut worklist = Vec::new();
+
+    for &block in fct.blocks() {
+        for inst in fct.insts(block) {
+            if fct.inst(inst).has_side_effects() {
+                live.insert(inst);
+                worklist.push(inst);
+            }
+        }
+    }
+
+    while let Some(inst) = worklist.pop() {
+        for value in fct.inst(inst).operands() {
+            let def = fct.dfg.value_inst(value);
+
+            if live.insert(def) {
+                worklist.push(def);
+            }
+        }
+    }
+
+    let blocks = fct.blocks().to_vec();
+
+    for block in blocks {
+        let dead: Vec<Inst> = fct
+            .insts(block)
+            .filter(|inst| !live.contains(inst))
+            .collect();
+
+        for inst in dead {
+            fct.remove_inst(inst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::boots::pass::tests::check;
+
+    #[test]
+    fn dce_removes_unused() {
+        check(
+            "dce",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = param int 1
+    v2 = const int 0
+    v3 = div int v1, v2
+    v4 = add int v1, v1
+    v5 = load_field int v0, class 1, field 0
+    v6 = new_object class 2
+    store_field int v0, class 1, field 1, v1
+    ret
+}",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = param int 1
+    v2 = const int 0
+    v3 = div int v1, v2
+    store_field int v0, class 1, field 1, v1
+    ret
+}
+",
+        );
+    }
+
+    #[test]
+    fn dce_removes_dead_phi_cycle() {
+        check(
+            "dce",
+            "function f {
+b0:
+    v0 = param int 0
+    goto b1
+b1:
+    v1 = phi int [b0: v0, b1: v2]
+    v2 = add int v1, v0
+    goto b1
+}",
+            "function f {
+b0:
+    goto b1
+b1:
+    goto b1
+}
+",
+        );
+    }
+
+    #[test]
+    fn dce_keeps_deoptimization_state() {
+        check(
+            "dce",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = add int v0, v0
+    v2 = mul int v0, v0
+    deoptimize 3 [0: v0, 1: v1]
+}",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = add int v0, v0
+    deoptimize 3 [0: v0, 1: v1]
+}
+",
+        );
+    }
+}
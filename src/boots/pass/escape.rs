@@ -0,0 +1,466 @@
+use std::collections::hash_map::HashMap;
+
+use crate::boots::function::{Block, Function, Type, Value};
+use crate::boots::inst::{Inst, InstData};
+use crate::class::ClassDefId;
+
+// escape analysis and scalar replacement: objects allocated in the function that
+// are only used by accesses of their own fields, nil checks and class tests don't
+// escape. Their fields become SSA values: loads are replaced by the last value
+// stored, phis merge the stores of different paths and fields never stored are
+// zero. The allocation and the stores are removed, together with their write
+// barriers. Values of replaced fields are ordinary values afterwards, the GC points
+// record pointers among them like any other value and no pointer to the removed
+// object remains. Objects stored into fields of replaced objects are replaced in
+// the next round. Objects in deoptimization states escape, baseline code would
+// need the object.
+pub fn run(fct: &mut Function) {
+    loop {
+        let allocs = non_escaping_allocs(fct);
+
+        if allocs.is_empty() {
+            break;
+        }
+
+        for (alloc, cls) in allocs {
+            ScalarReplacement::new(fct, alloc, cls).run();
+        }
+    }
+}
+
+fn non_escaping_allocs(fct: &Function) -> Vec<(Inst, ClassDefId)> {
+    let mut allocs = Vec::new();
+    let mut users: HashMap<Value, Vec<Inst>> = HashMap::new();
+
+    for &block in fct.blocks() {
+        for inst in fct.insts(block) {
+            if let InstData::NewObject { cls } = *fct.inst(inst) {
+                allocs.push((inst, cls));
+            }
+
+            for value in fct.inst(inst).operands() {
+                users.entry(value).or_insert_with(Vec::new).push(inst);
+            }
+        }
+    }
+
+    allocs.retain(|&(alloc, cls)| {
+        let obj = fct.inst_result(alloc).unwrap();
+        let users = match users.get(&obj) {
+            Some(users) => users,
+            None => return true,
+        };
+
+        // accesses through other classes might use different field ids for a field
+        users.iter().all(|&user| match *fct.inst(user) {
+            InstData::LoadField {
+                obj: opnd,
+                cls: field_cls,
+                ..
+            } => opnd == obj && field_cls == cls,
+
+            InstData::StoreField {
+                obj: opnd,
+                cls: field_cls,
+                value,
+                ..
+            } => opnd == obj && field_cls == cls && value != obj,
+
+            InstData::NilCheck { .. } | InstData::HasClass { .. } => true,
+
+            _ => false,
+        })
+    });
+
+    allocs
+}
+
+struct ScalarReplacement<'a> {
+    fct: &'a mut Function,
+    alloc: Inst,
+    obj: Value,
+    cls: ClassDefId,
+
+    // types of the fields by field index
+    types: HashMap<usize, Type>,
+    zeros: HashMap<usize, Value>,
+
+    // field values at the end of blocks with stores and at block entries
+    end_defs: HashMap<(Block, usize), Value>,
+    entry_defs: HashMap<(Block, usize), Value>,
+
+    // results of removed loads with their replacement
+    replaced: HashMap<Value, Value>,
+    phis: Vec<Inst>,
+}
+
+impl<'a> ScalarReplacement<'a> {
+    fn new(fct: &'a mut Function, alloc: Inst, cls: ClassDefId) -> ScalarReplacement<'a> {
+        let obj = fct.inst_result(alloc).unwrap();
+
+        ScalarReplacement {
+            fct: fct,
+            alloc: alloc,
+            obj: obj,
+            cls: cls,
+            types: HashMap::new(),
+            zeros: HashMap::new(),
+            end_defs: HashMap::new(),
+            entry_defs: HashMap::new(),
+            replaced: HashMap::new(),
+            phis: Vec::new(),
+        }
+    }
+
+    fn run(mut self) {
+        let blocks = self.fct.blocks().to_vec();
+
+        for &block in &blocks {
+            for inst in self.fct.insts(block) {
+                match *self.fct.inst(inst) {
+                    InstData::LoadField { ty, obj, field, .. } if obj == self.obj => {
+                        self.types.insert(field.idx(), ty);
+                    }
+
+                    InstData::StoreField {
+                        ty,
+                        obj,
+                        field,
+                        value,
+                        ..
+                    } if obj == self.obj => {
+                        self.types.insert(field.idx(), ty);
+                        self.end_defs.insert((block, field.idx()), value);
+                    }
+
+                    _ => {}
+                }
+            }
+        }
+
+        let mut removed = Vec::new();
+
+        for &block in &blocks {
+            let insts: Vec<Inst> = self.fct.insts(block).collect();
+            let mut defs = HashMap::new();
+            let mut allocated = false;
+
+            for inst in insts {
+                match self.fct.inst(inst).clone() {
+                    InstData::NewObject { .. } if inst == self.alloc => {
+                        allocated = true;
+                        removed.push(inst);
+                    }
+
+                    InstData::LoadField { obj, field, .. } if obj == self.obj => {
+                        let value = match defs.get(&field.idx()) {
+                            Some(&value) => value,
+                            None if allocated => self.zero(field.idx()),
+                            None => self.read_entry(block, field.idx()),
+                        };
+
+                        let result = self.fct.inst_result(inst).unwrap();
+                        self.replaced.insert(result, value);
+                        removed.push(inst);
+                    }
+
+                    InstData::StoreField {
+                        obj, field, value, ..
+                    } if obj == self.obj => {
+                        defs.insert(field.idx(), value);
+                        removed.push(inst);
+                    }
+
+                    InstData::NilCheck { opnd } if opnd == self.obj => {
+                        removed.push(inst);
+                    }
+
+                    InstData::HasClass { opnd, cls } if opnd == self.obj => {
+                        let result = if cls == self.cls {
+                            InstData::TrueConst
+                        } else {
+                            InstData::FalseConst
+                        };
+
+                        self.fct.replace_inst(inst, result);
+                    }
+
+                    _ => {}
+                }
+            }
+        }
+
+        for inst in removed {
+            self.fct.remove_inst(inst);
+        }
+
+        self.replace_values();
+        self.remove_trivial_phis();
+    }
+
+    fn zero(&mut self, field: usize) -> Value {
+        if let Some(&zero) = self.zeros.get(&field) {
+            return zero;
+        }
+
+        let inst_data = match self.types[&field] {
+            Type::Bool => InstData::FalseConst,
+            Type::Byte => InstData::Int8Const(0),
+            Type::Char => InstData::CharConst('\0'),
+            Type::Int => InstData::Int32Const(0),
+            Type::Long => InstData::Int64Const(0),
+            Type::Float => InstData::Float32Const(0.0),
+            Type::Double => InstData::Float64Const(0.0),
+            Type::Ptr => InstData::NilConst,
+        };
+
+        let inst = self.fct.insert_inst_before(self.alloc, inst_data);
+        let zero = self.fct.inst_result(inst).unwrap();
+        self.zeros.insert(field, zero);
+
+        zero
+    }
+
+    // fields not stored in the block of the allocation are zero at its end,
+    // the allocation dominates all loads so its entry is never read
+    fn read_end(&mut self, block: Block, field: usize) -> Value {
+        match self.end_defs.get(&(block, field)) {
+            Some(&value) => value,
+            None if Some(block) == self.fct.cfg.inst_block(self.alloc) => self.zero(field),
+            None => self.read_entry(block, field),
+        }
+    }
+
+    // blocks without predecessors are unreachable, any value works there
+    fn read_entry(&mut self, block: Block, field: usize) -> Value {
+        if let Some(&value) = self.entry_defs.get(&(block, field)) {
+            return value;
+        }
+
+        let preds = self.fct.cfg.predecessors(block).to_vec();
+
+        let value = match preds.len() {
+            0 => self.zero(field),
+            1 => self.read_end(preds[0], field),
+            _ => {
+                // the phi is registered before its inputs to terminate for loops
+                let ty = self.types[&field];
+                let phi = self.fct.prepend_inst(
+                    block,
+                    InstData::Phi {
+                        ty: ty,
+                        inputs: Vec::new(),
+                    },
+                );
+                let value = self.fct.inst_result(phi).unwrap();
+                self.entry_defs.insert((block, field), value);
+                self.phis.push(phi);
+
+                let inputs = preds
+                    .into_iter()
+                    .map(|pred| (pred, self.read_end(pred, field)))
+                    .collect();
+
+                if let InstData::Phi {
+                    inputs: ref mut phi_inputs,
+                    ..
+                } = *self.fct.dfg.inst_mut(phi)
+                {
+                    *phi_inputs = inputs;
+                }
+
+                value
+            }
+        };
+
+        self.entry_defs.insert((block, field), value);
+        value
+    }
+
+    // a removed load might have been stored into another field, the values
+    // are replaced until a value that was not replaced is found
+    fn resolve(&self, mut value: Value) -> Value {
+        while let Some(&replacement) = self.replaced.get(&value) {
+            value = replacement;
+        }
+
+        value
+    }
+
+    fn replace_values(&mut self) {
+        let blocks = self.fct.blocks().to_vec();
+
+        for block in blocks {
+            let insts: Vec<Inst> = self.fct.insts(block).collect();
+
+            for inst in insts {
+                let operands: Vec<Value> = self.fct.inst(inst).operands();
+                let resolved: Vec<Value> = operands.iter().map(|&op| self.resolve(op)).collect();
+
+                for (opnd, value) in self
+                    .fct
+                    .dfg
+                    .inst_mut(inst)
+                    .operands_mut()
+                    .into_iter()
+                    .zip(resolved)
+                {
+                    *opnd = value;
+                }
+            }
+        }
+    }
+
+    // phis with a single input value besides themselves are replaced by that value
+    fn remove_trivial_phis(&mut self) {
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for idx in 0..self.phis.len() {
+                let phi = self.phis[idx];
+
+                if self.fct.cfg.inst_block(phi).is_none() {
+                    continue;
+                }
+
+                let result = self.fct.inst_result(phi).unwrap();
+                let mut inputs = self.fct.inst(phi).operands();
+                inputs.retain(|&input| input != result);
+                inputs.sort();
+                inputs.dedup();
+
+                if inputs.len() == 1 {
+                    self.fct.remove_inst(phi);
+                    self.fct.replace_uses(result, inputs[0]);
+                    changed = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::boots::pass::tests::check;
+
+    #[test]
+    fn escape_replaces_fields() {
+        check(
+            "escape",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = new_object class 1
+    nil_check v1
+    store_field int v1, class 1, field 0, v0
+    v2 = load_field int v1, class 1, field 0
+    v3 = load_field int v1, class 1, field 1
+    v4 = add int v2, v3
+    ret v4
+}",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = const int 0
+    v2 = add int v0, v1
+    ret v2
+}
+",
+        );
+    }
+
+    #[test]
+    fn escape_merges_stores_in_loops() {
+        check(
+            "escape",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = new_object class 1
+    v2 = const int 0
+    store_field int v1, class 1, field 0, v2
+    goto b1
+b1:
+    v3 = load_field int v1, class 1, field 0
+    v4 = cmp lt int v3, v0
+    if v4, b2, b3
+b2:
+    v5 = const int 1
+    v6 = add int v3, v5
+    store_field int v1, class 1, field 0, v6
+    goto b1
+b3:
+    ret v3
+}",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = const int 0
+    goto b1
+b1:
+    v2 = phi int [b0: v1, b2: v5]
+    v3 = cmp lt int v2, v0
+    if v3, b2, b3
+b2:
+    v4 = const int 1
+    v5 = add int v2, v4
+    goto b1
+b3:
+    ret v2
+}
+",
+        );
+    }
+
+    #[test]
+    fn escape_replaces_nested_objects() {
+        check(
+            "escape",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = new_object class 1
+    store_field int v1, class 1, field 0, v0
+    v2 = new_object class 2
+    store_field ptr v2, class 2, field 0, v1
+    v3 = has_class v2, class 2
+    v4 = load_field ptr v2, class 2, field 0
+    v5 = load_field int v4, class 1, field 0
+    ret v5
+}",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = const true
+    ret v0
+}
+",
+        );
+    }
+
+    #[test]
+    fn escape_keeps_escaping_objects() {
+        check(
+            "escape",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = new_object class 1
+    store_field ptr v0, class 2, field 0, v1
+    v2 = new_object class 1
+    deoptimize 3 [0: v2]
+}",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = new_object class 1
+    store_field ptr v0, class 2, field 0, v1
+    v2 = new_object class 1
+    deoptimize 3 [0: v2]
+}
+",
+        );
+    }
+}
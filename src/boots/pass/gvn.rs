@@ -0,0 +1,435 @@
+use std::collections::hash_map::HashMap;
+
+use crate::boots::dom::DominatorTree;
+use crate::boots::function::{Block, Function, Type, Value};
+use crate::boots::inst::{BinOp, CmpOp, ConvOp, Inst, InstData, UnOp};
+use crate::vm::GlobalId;
+
+// global value numbering over the dominator tree: an instruction is
+// replaced by an equal instruction in a dominating position. Loads are
+// numbered together with the memory state they read, which changes at
+// stores and calls. Redundant nil, bounds and cast checks are removed.
+pub fn run(fct: &mut Function) {
+    let entry_block = match fct.entry_block() {
+        Some(block) => block,
+        None => return,
+    };
+
+    let dom = DominatorTree::compute(fct);
+
+    let mut gvn = Gvn {
+        fct: fct,
+        table: HashMap::new(),
+        replacements: HashMap::new(),
+        exit_memory: HashMap::new(),
+        next_memory: 0,
+    };
+
+    gvn.visit(&dom, entry_block);
+    gvn.replace_operands();
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Const(Type, u64),
+    Nil,
+    Binary(BinOp, Type, Value, Value),
+    Unary(UnOp, Type, Value),
+    Convert(ConvOp, Type, Type, Value),
+    Cmp(CmpOp, Type, Value, Value),
+    ArrayLength(Value),
+    InstanceOf(Value, usize),
+    NilCheck(Value),
+    BoundsCheck(Value, Value),
+    CheckedCast(Value, usize),
+    HasClass(Value, usize),
+    LoadField(Type, Value, usize, usize, u32),
+    LoadArray(Type, Value, Value, u32),
+    LoadGlobal(Type, GlobalId, u32),
+}
+
+struct Gvn<'a> {
+    fct: &'a mut Function,
+
+    // instructions available in the current position of the dominator tree
+    table: HashMap<Key, Inst>,
+
+    // values replaced by an equal value
+    replacements: HashMap<Value, Value>,
+
+    // memory state at the end of every visited block
+    exit_memory: HashMap<Block, u32>,
+    next_memory: u32,
+}
+
+enum Visit {
+    Enter(Block),
+    Exit(Vec<Key>),
+}
+
+impl<'a> Gvn<'a> {
+    fn visit(&mut self, dom: &DominatorTree, entry_block: Block) {
+        let mut stack = vec![Visit::Enter(entry_block)];
+
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Enter(block) => {
+                    let keys = self.visit_block(dom, block);
+                    stack.push(Visit::Exit(keys));
+
+                    for &child in dom.children(block).iter().rev() {
+                        stack.push(Visit::Enter(child));
+                    }
+                }
+
+                Visit::Exit(keys) => {
+                    for key in keys {
+                        self.table.remove(&key);
+                    }
+                }
+            }
+        }
+    }
+
+    // returns the keys added for this block
+    fn visit_block(&mut self, dom: &DominatorTree, block: Block) -> Vec<Key> {
+        let mut keys = Vec::new();
+
+        // the memory state is only known when the block directly follows
+        // its immediate dominator
+        let predecessors = self.fct.cfg.predecessors(block);
+        let mut memory = if predecessors.len() == 1 && dom.idom(block) == Some(predecessors[0]) {
+            self.exit_memory[&predecessors[0]]
+        } else {
+            self.new_memory()
+        };
+
+        let insts: Vec<Inst> = self.fct.insts(block).collect();
+
+        for inst in insts {
+            self.replace_inst_operands(inst);
+
+            if self.remove_trivial_phi(inst) {
+                continue;
+            }
+
+            if self.fct.inst(inst).writes_memory() {
+                memory = self.new_memory();
+            }
+
+            let key = match key(self.fct.inst(inst), memory) {
+                Some(key) => key,
+                None => continue,
+            };
+
+            if let Some(&existing) = self.table.get(&key) {
+                if let Some(result) = self.fct.inst_result(inst) {
+                    let value = self.fct.inst_result(existing).unwrap();
+                    self.replacements.insert(result, value);
+                }
+
+                self.fct.remove_inst(inst);
+            } else {
+                self.table.insert(key.clone(), inst);
+                keys.push(key);
+            }
+        }
+
+        self.exit_memory.insert(block, memory);
+        keys
+    }
+
+    // phis with only one distinct input besides themselves
+    fn remove_trivial_phi(&mut self, inst: Inst) -> bool {
+        let result = match self.fct.inst(inst) {
+            &InstData::Phi { .. } => self.fct.inst_result(inst).unwrap(),
+            _ => return false,
+        };
+
+        let mut same = None;
+
+        for value in self.fct.inst(inst).operands() {
+            if value == result || Some(value) == same {
+                continue;
+            }
+
+            if same.is_some() {
+                return false;
+            }
+
+            same = Some(value);
+        }
+
+        match same {
+            Some(same) => {
+                self.replacements.insert(result, same);
+                self.fct.remove_inst(inst);
+                true
+            }
+
+            None => false,
+        }
+    }
+
+    fn new_memory(&mut self) -> u32 {
+        self.next_memory += 1;
+        self.next_memory
+    }
+
+    fn resolve(&self, mut value: Value) -> Value {
+        while let Some(&replacement) = self.replacements.get(&value) {
+            value = replacement;
+        }
+
+        value
+    }
+
+    fn replace_inst_operands(&mut self, inst: Inst) {
+        let operands = self.fct.inst(inst).operands();
+        let resolved: Vec<Value> = operands.iter().map(|&value| self.resolve(value)).collect();
+
+        if operands != resolved {
+            let inst_data = self.fct.dfg.inst_mut(inst);
+
+            for (opnd, value) in inst_data.operands_mut().into_iter().zip(resolved) {
+                *opnd = value;
+            }
+        }
+    }
+
+    // phi inputs on back edges are only replaced after all blocks were visited
+    fn replace_operands(&mut self) {
+        let blocks = self.fct.blocks().to_vec();
+
+        for block in blocks {
+            let insts: Vec<Inst> = self.fct.insts(block).collect();
+
+            for inst in insts {
+                self.replace_inst_operands(inst);
+            }
+        }
+    }
+}
+
+fn key(inst_data: &InstData, memory: u32) -> Option<Key> {
+    let key = match *inst_data {
+        InstData::TrueConst => Key::Const(Type::Bool, 1),
+        InstData::FalseConst => Key::Const(Type::Bool, 0),
+        InstData::NilConst => Key::Nil,
+        InstData::Int8Const(value) => Key::Const(Type::Byte, value as u64),
+        InstData::CharConst(value) => Key::Const(Type::Char, value as u64),
+        InstData::Int32Const(value) => Key::Const(Type::Int, value as u32 as u64),
+        InstData::Int64Const(value) => Key::Const(Type::Long, value as u64),
+        InstData::Float32Const(value) => Key::Const(Type::Float, value.to_bits() as u64),
+        InstData::Float64Const(value) => Key::Const(Type::Double, value.to_bits()),
+
+        InstData::Binary { op, ty, lhs, rhs } => {
+            let (lhs, rhs) = if is_commutative(op) && rhs < lhs {
+                (rhs, lhs)
+            } else {
+                (lhs, rhs)
+            };
+
+            Key::Binary(op, ty, lhs, rhs)
+        }
+
+        InstData::Unary { op, ty, opnd } => Key::Unary(op, ty, opnd),
+        InstData::Convert { op, from, to, opnd } => Key::Convert(op, from, to, opnd),
+
+        InstData::Cmp { op, ty, lhs, rhs } => {
+            let (lhs, rhs) = if (op == CmpOp::Eq || op == CmpOp::Ne) && rhs < lhs {
+                (rhs, lhs)
+            } else {
+                (lhs, rhs)
+            };
+
+            Key::Cmp(op, ty, lhs, rhs)
+        }
+
+        InstData::ArrayLength { array } => Key::ArrayLength(array),
+        InstData::InstanceOf { opnd, cls } => Key::InstanceOf(opnd, cls.to_usize()),
+        InstData::NilCheck { opnd } => Key::NilCheck(opnd),
+        InstData::BoundsCheck { index, length } => Key::BoundsCheck(index, length),
+        InstData::CheckedCast { opnd, cls } => Key::CheckedCast(opnd, cls.to_usize()),
+        InstData::HasClass { opnd, cls } => Key::HasClass(opnd, cls.to_usize()),
+
+        InstData::LoadField {
+            ty,
+            obj,
+            cls,
+            field,
+        } => Key::LoadField(ty, obj, cls.to_usize(), field.idx(), memory),
+
+        InstData::LoadArray { ty, array, index } => Key::LoadArray(ty, array, index, memory),
+        InstData::LoadGlobal { ty, global } => Key::LoadGlobal(ty, global, memory),
+
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+fn is_commutative(op: BinOp) -> bool {
+    match op {
+        BinOp::Add | BinOp::Mul | BinOp::And | BinOp::Or | BinOp::Xor => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::boots::pass::tests::check;
+
+    #[test]
+    fn gvn_removes_redundant_arithmetic() {
+        check(
+            "gvn",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = param int 1
+    v2 = add int v0, v1
+    v3 = add int v1, v0
+    v4 = sub int v0, v1
+    v5 = sub int v1, v0
+    v6 = mul int v3, v4
+    v7 = mul int v2, v4
+    v8 = add int v6, v7
+    v9 = add int v8, v5
+    ret v9
+}",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = param int 1
+    v2 = add int v0, v1
+    v3 = sub int v0, v1
+    v4 = sub int v1, v0
+    v5 = mul int v2, v3
+    v6 = add int v5, v5
+    v7 = add int v6, v4
+    ret v7
+}
+",
+        );
+    }
+
+    #[test]
+    fn gvn_only_uses_dominating_values() {
+        check(
+            "gvn",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = param bool 1
+    if v1, b1, b2
+b1:
+    v2 = neg int v0
+    goto b3
+b2:
+    v3 = neg int v0
+    goto b3
+b3:
+    v4 = phi int [b1: v2, b2: v3]
+    v5 = neg int v0
+    v6 = add int v4, v5
+    ret v6
+}",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = param bool 1
+    if v1, b1, b2
+b1:
+    v2 = neg int v0
+    goto b3
+b2:
+    v3 = neg int v0
+    goto b3
+b3:
+    v4 = phi int [b1: v2, b2: v3]
+    v5 = neg int v0
+    v6 = add int v4, v5
+    ret v6
+}
+",
+        );
+    }
+
+    #[test]
+    fn gvn_loads_and_checks() {
+        check(
+            "gvn",
+            "function f {
+b0:
+    v0 = param ptr 0
+    nil_check v0
+    v1 = load_field int v0, class 1, field 0
+    nil_check v0
+    v2 = load_field int v0, class 1, field 0
+    v3 = add int v1, v2
+    store_field int v0, class 1, field 0, v3
+    nil_check v0
+    v4 = load_field int v0, class 1, field 0
+    v5 = array_length v0
+    v6 = array_length v0
+    bounds_check v4, v5
+    bounds_check v4, v6
+    ret v4
+}",
+            "function f {
+b0:
+    v0 = param ptr 0
+    nil_check v0
+    v1 = load_field int v0, class 1, field 0
+    v2 = add int v1, v1
+    store_field int v0, class 1, field 0, v2
+    v3 = load_field int v0, class 1, field 0
+    v4 = array_length v0
+    bounds_check v3, v4
+    ret v3
+}
+",
+        );
+    }
+
+    #[test]
+    fn gvn_loop_loads() {
+        // the store in the loop body invalidates the load before the loop
+        check(
+            "gvn",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = param bool 1
+    v2 = load_field int v0, class 1, field 0
+    goto b1
+b1:
+    v3 = load_field int v0, class 1, field 0
+    if v1, b2, b3
+b2:
+    v4 = load_field int v0, class 1, field 0
+    store_field int v0, class 1, field 0, v4
+    goto b1
+b3:
+    ret v3
+}",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = param bool 1
+    v2 = load_field int v0, class 1, field 0
+    goto b1
+b1:
+    v3 = load_field int v0, class 1, field 0
+    if v1, b2, b3
+b2:
+    store_field int v0, class 1, field 0, v3
+    goto b1
+b3:
+    ret v3
+}
+",
+        );
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::hash_map::HashMap;
+use std::collections::HashSet;
+
+use crate::boots::dom::DominatorTree;
+use crate::boots::function::{Block, Function, Value};
+use crate::boots::inst::{Inst, InstData};
+
+// loop-invariant code motion: instructions whose operands are defined outside
+// of a loop are moved into the preheader of the loop. Loads need an object
+// that was checked for nil before the loop, field loads also a loop without
+// stores and calls. Checks at the start of the loop header are executed
+// whenever the loop is entered and are hoisted as well.
+pub fn run(fct: &mut Function) {
+    if fct.entry_block().is_none() {
+        return;
+    }
+
+    let mut preheaders = HashMap::new();
+
+    {
+        let dom = DominatorTree::compute(fct);
+
+        for (header, _) in loops(fct, &dom) {
+            if let Some(preheader) = insert_preheader(fct, &dom, header) {
+                preheaders.insert(header, preheader);
+            }
+        }
+    }
+
+    let dom = DominatorTree::compute(fct);
+
+    // inner loops come first, their preheaders are part of the outer loop
+    for (header, body) in loops(fct, &dom) {
+        if let Some(&preheader) = preheaders.get(&header) {
+            hoist(fct, &dom, header, preheader, &body);
+        }
+    }
+}
+
+// natural loops by header, sorted by size
+fn loops(fct: &Function, dom: &DominatorTree) -> Vec<(Block, HashSet<Block>)> {
+    let mut loops = Vec::new();
+
+    for &header in dom.reverse_postorder() {
+        let mut worklist: Vec<Block> = fct
+            .cfg
+            .predecessors(header)
+            .iter()
+            .cloned()
+            .filter(|&pred| dom.dominates(header, pred))
+            .collect();
+
+        if worklist.is_empty() {
+            continue;
+        }
+
+        let mut body = HashSet::new();
+        body.insert(header);
+
+        while let Some(block) = worklist.pop() {
+            if body.insert(block) {
+                worklist.extend(
+                    fct.cfg
+                        .predecessors(block)
+                        .iter()
+                        .filter(|&&pred| dom.is_reachable(pred)),
+                );
+            }
+        }
+
+        loops.push((header, body));
+    }
+
+    loops.sort_by_key(|&(_, ref body)| body.len());
+    loops
+}
+
+// the block that jumps to the header from outside of the loop, an edge from
+// a branch gets a new block. Loops with more than one entry are not changed.
+fn insert_preheader(fct: &mut Function, dom: &DominatorTree, header: Block) -> Option<Block> {
+    let outside: Vec<Block> = fct
+        .cfg
+        .predecessors(header)
+        .iter()
+        .cloned()
+        .filter(|&pred| !dom.dominates(header, pred))
+        .collect();
+
+    if outside.len() != 1 {
+        return None;
+    }
+
+    let pred = outside[0];
+    let term = fct.terminator(pred)?;
+
+    let branch = match *fct.inst(term) {
+        InstData::Goto { .. } => return Some(pred),
+
+        InstData::If {
+            opnd,
+            then_block,
+            else_block,
+        } if then_block != else_block => {
+            let replace = |block| if block == header { None } else { Some(block) };
+            (opnd, replace(then_block), replace(else_block))
+        }
+
+        _ => return None,
+    };
+
+    let preheader = fct.make_block();
+    let (opnd, then_block, else_block) = branch;
+
+    fct.replace_inst(
+        term,
+        InstData::If {
+            opnd: opnd,
+            then_block: then_block.unwrap_or(preheader),
+            else_block: else_block.unwrap_or(preheader),
+        },
+    );
+    fct.append_inst(preheader, InstData::Goto { target: header });
+
+    let phis: Vec<Inst> = fct
+        .insts(header)
+        .take_while(|&inst| fct.inst(inst).is_phi())
+        .collect();
+
+    for phi in phis {
+        if let InstData::Phi { ref mut inputs, .. } = *fct.dfg.inst_mut(phi) {
+            for input in inputs.iter_mut() {
+                if input.0 == pred {
+                    input.0 = preheader;
+                }
+            }
+        }
+    }
+
+    Some(preheader)
+}
+
+fn hoist(
+    fct: &mut Function,
+    dom: &DominatorTree,
+    header: Block,
+    preheader: Block,
+    body: &HashSet<Block>,
+) {
+    let writes_memory = body
+        .iter()
+        .any(|&block| fct.insts(block).any(|inst| fct.inst(inst).writes_memory()));
+    let term = fct.terminator(preheader).unwrap();
+
+    for &block in dom.reverse_postorder() {
+        if !body.contains(&block) {
+            continue;
+        }
+
+        // no side effect happened in the header so far
+        let mut prefix = block == header;
+        let insts: Vec<Inst> = fct.insts(block).collect();
+
+        for inst in insts {
+            let invariant = fct.inst(inst).operands().into_iter().all(|value| {
+                let def = fct.cfg.inst_block(fct.dfg.value_inst(value)).unwrap();
+                !body.contains(&def)
+            });
+
+            if invariant && can_hoist(fct, dom, inst, preheader, prefix, writes_memory) {
+                fct.move_inst_before(inst, term);
+                continue;
+            }
+
+            if fct.inst(inst).has_side_effects() {
+                prefix = false;
+            }
+        }
+    }
+}
+
+fn can_hoist(
+    fct: &Function,
+    dom: &DominatorTree,
+    inst: Inst,
+    preheader: Block,
+    prefix: bool,
+    writes_memory: bool,
+) -> bool {
+    let inst_data = fct.inst(inst);
+
+    match *inst_data {
+        InstData::Binary { .. }
+        | InstData::Unary { .. }
+        | InstData::Convert { .. }
+        | InstData::Cmp { .. } => !inst_data.has_side_effects(),
+
+        InstData::TrueConst
+        | InstData::FalseConst
+        | InstData::NilConst
+        | InstData::Int8Const(_)
+        | InstData::CharConst(_)
+        | InstData::Int32Const(_)
+        | InstData::Int64Const(_)
+        | InstData::Float32Const(_)
+        | InstData::Float64Const(_) => true,
+
+        InstData::NilCheck { .. } | InstData::BoundsCheck { .. } => prefix,
+
+        InstData::ArrayLength { array: obj } | InstData::HasClass { opnd: obj, .. } => {
+            is_non_nil(fct, dom, obj, preheader)
+        }
+
+        InstData::LoadField { obj, .. } => !writes_memory && is_non_nil(fct, dom, obj, preheader),
+
+        _ => false,
+    }
+}
+
+// allocations and values checked in a block dominating the end of the preheader
+fn is_non_nil(fct: &Function, dom: &DominatorTree, value: Value, preheader: Block) -> bool {
+    match *fct.inst(fct.dfg.value_inst(value)) {
+        InstData::NewObject { .. } | InstData::NewArray { .. } => return true,
+        _ => {}
+    }
+
+    let mut block = Some(preheader);
+
+    while let Some(current) = block {
+        let checked = fct.insts(current).any(|inst| match *fct.inst(inst) {
+            InstData::NilCheck { opnd } => opnd == value,
+            _ => false,
+        });
+
+        if checked {
+            return true;
+        }
+
+        block = dom.idom(current);
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::boots::pass::tests::check;
+
+    #[test]
+    fn licm_hoists_array_length() {
+        check(
+            "licm",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = const int 0
+    goto b1
+b1:
+    v2 = phi int [b0: v1, b2: v8]
+    v3 = phi int [b0: v1, b2: v7]
+    nil_check v0
+    v4 = array_length v0
+    v5 = cmp lt int v2, v4
+    if v5, b2, b3
+b2:
+    v6 = load_array int v0, v2
+    v7 = add int v3, v6
+    v9 = const int 1
+    v8 = add int v2, v9
+    goto b1
+b3:
+    ret v3
+}",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = const int 0
+    nil_check v0
+    v2 = array_length v0
+    v3 = const int 1
+    goto b1
+b1:
+    v4 = phi int [b0: v1, b2: v9]
+    v5 = phi int [b0: v1, b2: v8]
+    v6 = cmp lt int v4, v2
+    if v6, b2, b3
+b2:
+    v7 = load_array int v0, v4
+    v8 = add int v5, v7
+    v9 = add int v4, v3
+    goto b1
+b3:
+    ret v5
+}
+",
+        );
+    }
+
+    #[test]
+    fn licm_keeps_checks_after_side_effects() {
+        check(
+            "licm",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = param ptr 1
+    v2 = const int 0
+    v3 = param bool 2
+    if v3, b1, b3
+b1:
+    v4 = phi int [b0: v2, b2: v8]
+    store_field int v1, class 2, field 0, v4
+    nil_check v0
+    v5 = load_field int v0, class 2, field 0
+    v6 = cmp lt int v4, v5
+    if v6, b2, b3
+b2:
+    v7 = const int 1
+    v8 = add int v4, v7
+    goto b1
+b3:
+    ret
+}",
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = param ptr 1
+    v2 = const int 0
+    v3 = param bool 2
+    if v3, b4, b3
+b1:
+    v4 = phi int [b4: v2, b2: v7]
+    store_field int v1, class 2, field 0, v4
+    nil_check v0
+    v5 = load_field int v0, class 2, field 0
+    v6 = cmp lt int v4, v5
+    if v6, b2, b3
+b2:
+    v7 = add int v4, v8
+    goto b1
+b3:
+    ret
+b4:
+    v8 = const int 1
+    goto b1
+}
+",
+        );
+    }
+}
@@ -0,0 +1,688 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::HashMap;
+use std::collections::HashSet;
+
+use crate::boots::function::{Block, Function, Type, Value};
+use crate::boots::inst::{BinOp, CmpOp, ConvOp, Inst, InstData, UnOp};
+
+// sparse conditional constant propagation (Wegman, Zadeck): values are
+// only evaluated along edges that can be executed, afterwards constant
+// values are materialized, constant branches become gotos and blocks that
+// are never executed are removed
+pub fn run(fct: &mut Function) {
+    let entry_block = match fct.entry_block() {
+        Some(block) => block,
+        None => return,
+    };
+
+    let mut sccp = Sccp {
+        fct: fct,
+        values: HashMap::new(),
+        executable_edges: HashSet::new(),
+        executable_blocks: HashSet::new(),
+        block_worklist: vec![entry_block],
+        value_worklist: Vec::new(),
+        users: HashMap::new(),
+    };
+
+    sccp.executable_blocks.insert(entry_block);
+    sccp.compute_users();
+    sccp.solve();
+    sccp.rewrite();
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Const {
+    Bool(bool),
+    Byte(u8),
+    Char(char),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+}
+
+// floats are compared by bits, so 0.0 and -0.0 are different constants
+impl PartialEq for Const {
+    fn eq(&self, other: &Const) -> bool {
+        match (*self, *other) {
+            (Const::Float(lhs), Const::Float(rhs)) => lhs.to_bits() == rhs.to_bits(),
+            (Const::Double(lhs), Const::Double(rhs)) => lhs.to_bits() == rhs.to_bits(),
+            (Const::Bool(lhs), Const::Bool(rhs)) => lhs == rhs,
+            (Const::Byte(lhs), Const::Byte(rhs)) => lhs == rhs,
+            (Const::Char(lhs), Const::Char(rhs)) => lhs == rhs,
+            (Const::Int(lhs), Const::Int(rhs)) => lhs == rhs,
+            (Const::Long(lhs), Const::Long(rhs)) => lhs == rhs,
+            _ => false,
+        }
+    }
+}
+
+impl Const {
+    pub fn from_inst(inst_data: &InstData) -> Option<Const> {
+        match *inst_data {
+            InstData::TrueConst => Some(Const::Bool(true)),
+            InstData::FalseConst => Some(Const::Bool(false)),
+            InstData::Int8Const(value) => Some(Const::Byte(value)),
+            InstData::CharConst(value) => Some(Const::Char(value)),
+            InstData::Int32Const(value) => Some(Const::Int(value)),
+            InstData::Int64Const(value) => Some(Const::Long(value)),
+            InstData::Float32Const(value) => Some(Const::Float(value)),
+            InstData::Float64Const(value) => Some(Const::Double(value)),
+            _ => None,
+        }
+    }
+
+    pub fn to_inst(self) -> InstData {
+        match self {
+            Const::Bool(true) => InstData::TrueConst,
+            Const::Bool(false) => InstData::FalseConst,
+            Const::Byte(value) => InstData::Int8Const(value),
+            Const::Char(value) => InstData::CharConst(value),
+            Const::Int(value) => InstData::Int32Const(value),
+            Const::Long(value) => InstData::Int64Const(value),
+            Const::Float(value) => InstData::Float32Const(value),
+            Const::Double(value) => InstData::Float64Const(value),
+        }
+    }
+
+    // integer value, Byte and Char are unsigned
+    fn to_i64(self) -> Option<i64> {
+        match self {
+            Const::Byte(value) => Some(value as i64),
+            Const::Char(value) => Some(value as i64),
+            Const::Int(value) => Some(value as i64),
+            Const::Long(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn to_f64(self) -> Option<f64> {
+        match self {
+            Const::Float(value) => Some(value as f64),
+            Const::Double(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    // truncates the integer to `ty`
+    fn from_i64(ty: Type, value: i64) -> Option<Const> {
+        match ty {
+            Type::Byte => Some(Const::Byte(value as u8)),
+            Type::Char => std::char::from_u32(value as u32).map(Const::Char),
+            Type::Int => Some(Const::Int(value as i32)),
+            Type::Long => Some(Const::Long(value)),
+            _ => None,
+        }
+    }
+
+    fn from_f64(ty: Type, value: f64) -> Option<Const> {
+        match ty {
+            Type::Float => Some(Const::Float(value as f32)),
+            Type::Double => Some(Const::Double(value)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Lattice {
+    // not yet known, optimistically assumed to be constant
+    Top,
+    Const(Const),
+    Bottom,
+}
+
+impl Lattice {
+    fn meet(self, other: Lattice) -> Lattice {
+        match (self, other) {
+            (Lattice::Top, value) | (value, Lattice::Top) => value,
+            (Lattice::Const(lhs), Lattice::Const(rhs)) if lhs == rhs => Lattice::Const(lhs),
+            _ => Lattice::Bottom,
+        }
+    }
+}
+
+struct Sccp<'a> {
+    fct: &'a mut Function,
+    values: HashMap<Value, Lattice>,
+    executable_edges: HashSet<(Block, Block)>,
+    executable_blocks: HashSet<Block>,
+    block_worklist: Vec<Block>,
+    value_worklist: Vec<Value>,
+    users: HashMap<Value, Vec<Inst>>,
+}
+
+impl<'a> Sccp<'a> {
+    fn compute_users(&mut self) {
+        for &block in self.fct.blocks() {
+            for inst in self.fct.insts(block) {
+                for value in self.fct.inst(inst).operands() {
+                    self.users.entry(value).or_insert_with(Vec::new).push(inst);
+                }
+            }
+        }
+    }
+
+    fn solve(&mut self) {
+        loop {
+            if let Some(block) = self.block_worklist.pop() {
+                let insts: Vec<Inst> = self.fct.insts(block).collect();
+
+                for inst in insts {
+                    self.visit_inst(inst);
+                }
+            } else if let Some(value) = self.value_worklist.pop() {
+                let users = self.users.get(&value).cloned().unwrap_or_default();
+
+                for inst in users {
+                    let block = self.fct.cfg.inst_block(inst).unwrap();
+
+                    if self.executable_blocks.contains(&block) {
+                        self.visit_inst(inst);
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn visit_inst(&mut self, inst: Inst) {
+        let block = self.fct.cfg.inst_block(inst).unwrap();
+        let inst_data = self.fct.inst(inst).clone();
+
+        match inst_data {
+            InstData::Goto { target } => self.mark_edge(block, target),
+
+            InstData::If {
+                opnd,
+                then_block,
+                else_block,
+            } => match self.value(opnd) {
+                Lattice::Top => {}
+                Lattice::Const(Const::Bool(true)) => self.mark_edge(block, then_block),
+                Lattice::Const(Const::Bool(false)) => self.mark_edge(block, else_block),
+                _ => {
+                    self.mark_edge(block, then_block);
+                    self.mark_edge(block, else_block);
+                }
+            },
+
+            _ => {
+                if let Some(result) = self.fct.inst_result(inst) {
+                    let value = self.evaluate(block, &inst_data);
+                    self.update(result, value);
+                }
+            }
+        }
+    }
+
+    fn mark_edge(&mut self, from: Block, to: Block) {
+        if !self.executable_edges.insert((from, to)) {
+            return;
+        }
+
+        if self.executable_blocks.insert(to) {
+            self.block_worklist.push(to);
+        } else {
+            // a new input for the phis of an already visited block
+            let phis: Vec<Inst> = self
+                .fct
+                .insts(to)
+                .take_while(|&inst| self.fct.inst(inst).is_phi())
+                .collect();
+
+            for phi in phis {
+                self.visit_inst(phi);
+            }
+        }
+    }
+
+    fn update(&mut self, value: Value, lattice: Lattice) {
+        let old = self.value(value);
+        let new = old.meet(lattice);
+
+        if old != new {
+            self.values.insert(value, new);
+            self.value_worklist.push(value);
+        }
+    }
+
+    fn value(&self, value: Value) -> Lattice {
+        self.values.get(&value).cloned().unwrap_or(Lattice::Top)
+    }
+
+    fn evaluate(&self, block: Block, inst_data: &InstData) -> Lattice {
+        if let Some(value) = Const::from_inst(inst_data) {
+            return Lattice::Const(value);
+        }
+
+        match *inst_data {
+            InstData::Phi { ref inputs, .. } => {
+                let mut result = Lattice::Top;
+
+                for &(pred, value) in inputs {
+                    if self.executable_edges.contains(&(pred, block)) {
+                        result = result.meet(self.value(value));
+                    }
+                }
+
+                result
+            }
+
+            InstData::Binary { op, ty, lhs, rhs } => {
+                self.fold2(lhs, rhs, |lhs, rhs| fold_binary(op, ty, lhs, rhs))
+            }
+
+            InstData::Cmp { op, lhs, rhs, .. } => {
+                self.fold2(lhs, rhs, |lhs, rhs| fold_cmp(op, lhs, rhs))
+            }
+
+            InstData::Unary { op, ty, opnd } => match self.value(opnd) {
+                Lattice::Const(value) => to_lattice(fold_unary(op, ty, value)),
+                value => value,
+            },
+
+            InstData::Convert { op, to, opnd, .. } => match self.value(opnd) {
+                Lattice::Const(value) => to_lattice(fold_convert(op, to, value)),
+                value => value,
+            },
+
+            _ => Lattice::Bottom,
+        }
+    }
+
+    fn fold2<F>(&self, lhs: Value, rhs: Value, fold: F) -> Lattice
+    where
+        F: FnOnce(Const, Const) -> Option<Const>,
+    {
+        match (self.value(lhs), self.value(rhs)) {
+            (Lattice::Const(lhs), Lattice::Const(rhs)) => to_lattice(fold(lhs, rhs)),
+            (Lattice::Bottom, _) | (_, Lattice::Bottom) => Lattice::Bottom,
+            _ => Lattice::Top,
+        }
+    }
+
+    fn rewrite(&mut self) {
+        let blocks: Vec<Block> = self.fct.blocks().to_vec();
+
+        for &block in &blocks {
+            if !self.executable_blocks.contains(&block) {
+                continue;
+            }
+
+            let insts: Vec<Inst> = self.fct.insts(block).collect();
+            let first_non_phi = insts
+                .iter()
+                .cloned()
+                .find(|&inst| !self.fct.inst(inst).is_phi());
+
+            for inst in insts {
+                self.rewrite_inst(block, inst, first_non_phi);
+            }
+        }
+
+        // edges from executable blocks to blocks that are never executed
+        // are gone now, only edges among removed blocks are left
+        for &block in &blocks {
+            if self.executable_blocks.contains(&block) {
+                continue;
+            }
+
+            for succ in self.fct.cfg.successors(block).to_vec() {
+                if self.executable_blocks.contains(&succ) {
+                    self.fct.remove_phi_inputs(succ, block);
+                }
+            }
+
+            let insts: Vec<Inst> = self.fct.insts(block).collect();
+
+            for inst in insts {
+                self.fct.remove_inst(inst);
+            }
+        }
+
+        for &block in &blocks {
+            if !self.executable_blocks.contains(&block) {
+                self.fct.remove_block(block);
+            }
+        }
+    }
+
+    fn rewrite_inst(&mut self, block: Block, inst: Inst, first_non_phi: Option<Inst>) {
+        let inst_data = self.fct.inst(inst).clone();
+
+        if let InstData::If {
+            opnd,
+            then_block,
+            else_block,
+        } = inst_data
+        {
+            let (target, other) = match self.value(opnd) {
+                Lattice::Const(Const::Bool(true)) => (then_block, else_block),
+                Lattice::Const(Const::Bool(false)) => (else_block, then_block),
+                _ => return,
+            };
+
+            if target != other {
+                self.fct.remove_phi_inputs(other, block);
+            }
+
+            self.fct
+                .replace_inst(inst, InstData::Goto { target: target });
+            return;
+        }
+
+        let result = match self.fct.inst_result(inst) {
+            Some(result) => result,
+            None => return,
+        };
+
+        let value = match self.value(result) {
+            Lattice::Const(value) => value,
+            _ => return,
+        };
+
+        if Const::from_inst(&inst_data).is_some() {
+            return;
+        }
+
+        if inst_data.is_phi() {
+            // phis need to stay at the start of the block
+            let before = first_non_phi.expect("block without terminator");
+            let constant = self.fct.insert_inst_before(before, value.to_inst());
+            let constant = self.fct.inst_result(constant).unwrap();
+            self.fct.replace_uses(result, constant);
+            self.fct.remove_inst(inst);
+        } else {
+            self.fct.replace_inst(inst, value.to_inst());
+        }
+    }
+}
+
+fn to_lattice(value: Option<Const>) -> Lattice {
+    match value {
+        Some(value) => Lattice::Const(value),
+        None => Lattice::Bottom,
+    }
+}
+
+// returns None for operations that trap or are not folded
+pub fn fold_binary(op: BinOp, ty: Type, lhs: Const, rhs: Const) -> Option<Const> {
+    if ty.is_float() {
+        let lhs = lhs.to_f64()?;
+        let rhs = rhs.to_f64()?;
+
+        // operations on floats are done in their own precision
+        let value = if ty == Type::Float {
+            let (lhs, rhs) = (lhs as f32, rhs as f32);
+
+            (match op {
+                BinOp::Add => lhs + rhs,
+                BinOp::Sub => lhs - rhs,
+                BinOp::Mul => lhs * rhs,
+                BinOp::Div => lhs / rhs,
+                _ => return None,
+            }) as f64
+        } else {
+            match op {
+                BinOp::Add => lhs + rhs,
+                BinOp::Sub => lhs - rhs,
+                BinOp::Mul => lhs * rhs,
+                BinOp::Div => lhs / rhs,
+                _ => return None,
+            }
+        };
+
+        return Const::from_f64(ty, value);
+    }
+
+    if ty == Type::Bool {
+        let (lhs, rhs) = match (lhs, rhs) {
+            (Const::Bool(lhs), Const::Bool(rhs)) => (lhs, rhs),
+            _ => return None,
+        };
+
+        let value = match op {
+            BinOp::And => lhs & rhs,
+            BinOp::Or => lhs | rhs,
+            BinOp::Xor => lhs ^ rhs,
+            _ => return None,
+        };
+
+        return Some(Const::Bool(value));
+    }
+
+    let lhs = lhs.to_i64()?;
+    let rhs = rhs.to_i64()?;
+
+    let value = match ty {
+        Type::Int => {
+            let (lhs, rhs) = (lhs as i32, rhs as i32);
+
+            (match op {
+                BinOp::Add => lhs.wrapping_add(rhs),
+                BinOp::Sub => lhs.wrapping_sub(rhs),
+                BinOp::Mul => lhs.wrapping_mul(rhs),
+                BinOp::Div => lhs.checked_div(rhs)?,
+                BinOp::Mod => lhs.checked_rem(rhs)?,
+                BinOp::And => lhs & rhs,
+                BinOp::Or => lhs | rhs,
+                BinOp::Xor => lhs ^ rhs,
+                BinOp::Shl => lhs.wrapping_shl(rhs as u32),
+                BinOp::Shr => (lhs as u32).wrapping_shr(rhs as u32) as i32,
+                BinOp::Sar => lhs.wrapping_shr(rhs as u32),
+            }) as i64
+        }
+
+        Type::Long => match op {
+            BinOp::Add => lhs.wrapping_add(rhs),
+            BinOp::Sub => lhs.wrapping_sub(rhs),
+            BinOp::Mul => lhs.wrapping_mul(rhs),
+            BinOp::Div => lhs.checked_div(rhs)?,
+            BinOp::Mod => lhs.checked_rem(rhs)?,
+            BinOp::And => lhs & rhs,
+            BinOp::Or => lhs | rhs,
+            BinOp::Xor => lhs ^ rhs,
+            BinOp::Shl => lhs.wrapping_shl(rhs as u32),
+            BinOp::Shr => (lhs as u64).wrapping_shr(rhs as u32) as i64,
+            BinOp::Sar => lhs.wrapping_shr(rhs as u32),
+        },
+
+        _ => return None,
+    };
+
+    Const::from_i64(ty, value)
+}
+
+pub fn fold_cmp(op: CmpOp, lhs: Const, rhs: Const) -> Option<Const> {
+    let ordering = match (lhs, rhs) {
+        (Const::Bool(lhs), Const::Bool(rhs)) => lhs.partial_cmp(&rhs),
+        (Const::Float(lhs), Const::Float(rhs)) => lhs.partial_cmp(&rhs),
+        (Const::Double(lhs), Const::Double(rhs)) => lhs.partial_cmp(&rhs),
+        (lhs, rhs) => lhs.to_i64()?.partial_cmp(&rhs.to_i64()?),
+    };
+
+    // comparisons with NaN are only true for `ne`
+    let value = match ordering {
+        Some(ordering) => match op {
+            CmpOp::Lt => ordering == Ordering::Less,
+            CmpOp::Le => ordering != Ordering::Greater,
+            CmpOp::Eq => ordering == Ordering::Equal,
+            CmpOp::Ne => ordering != Ordering::Equal,
+            CmpOp::Gt => ordering == Ordering::Greater,
+            CmpOp::Ge => ordering != Ordering::Less,
+        },
+        None => op == CmpOp::Ne,
+    };
+
+    Some(Const::Bool(value))
+}
+
+pub fn fold_unary(op: UnOp, ty: Type, value: Const) -> Option<Const> {
+    match (op, value) {
+        (UnOp::Not, Const::Bool(value)) => Some(Const::Bool(!value)),
+        (UnOp::Neg, Const::Float(value)) => Some(Const::Float(-value)),
+        (UnOp::Neg, Const::Double(value)) => Some(Const::Double(-value)),
+        (UnOp::Sqrt, Const::Float(value)) => Some(Const::Float(value.sqrt())),
+        (UnOp::Sqrt, Const::Double(value)) => Some(Const::Double(value.sqrt())),
+        (UnOp::Neg, value) => Const::from_i64(ty, value.to_i64()?.wrapping_neg()),
+        (UnOp::Not, value) => Const::from_i64(ty, !value.to_i64()?),
+        _ => None,
+    }
+}
+
+pub fn fold_convert(op: ConvOp, to: Type, value: Const) -> Option<Const> {
+    match op {
+        ConvOp::ZeroExtend | ConvOp::Truncate => Const::from_i64(to, value.to_i64()?),
+        ConvOp::SignExtend => match value {
+            Const::Int(value) => Const::from_i64(to, value as i64),
+            _ => None,
+        },
+        ConvOp::IntToFloat => {
+            let value = value.to_i64()?;
+
+            match to {
+                Type::Float => Some(Const::Float(value as f32)),
+                Type::Double => Some(Const::Double(value as f64)),
+                _ => None,
+            }
+        }
+        ConvOp::FloatToInt => {
+            // the machine instructions do not saturate like `as`
+            let value = value.to_f64()?;
+            let (min, max) = match to {
+                Type::Int => (i32::min_value() as f64, i32::max_value() as f64),
+                Type::Long => (i64::min_value() as f64, i64::max_value() as f64),
+                _ => return None,
+            };
+
+            if value.is_nan() || value < min || value >= max {
+                None
+            } else {
+                Const::from_i64(to, value as i64)
+            }
+        }
+        ConvOp::FloatExtend => Const::from_f64(to, value.to_f64()?),
+        ConvOp::FloatTruncate => Const::from_f64(to, value.to_f64()?),
+        ConvOp::Reinterpret => match (value, to) {
+            (Const::Float(value), Type::Int) => Some(Const::Int(value.to_bits() as i32)),
+            (Const::Double(value), Type::Long) => Some(Const::Long(value.to_bits() as i64)),
+            (Const::Int(value), Type::Float) => Some(Const::Float(f32::from_bits(value as u32))),
+            (Const::Long(value), Type::Double) => Some(Const::Double(f64::from_bits(value as u64))),
+            (value, _) => Const::from_i64(to, value.to_i64()?),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::boots::pass::tests::check;
+
+    #[test]
+    fn sccp_folds_arithmetic() {
+        check(
+            "sccp",
+            "function f {
+b0:
+    v0 = const int 7
+    v1 = const int 5
+    v2 = sub int v0, v1
+    v3 = const int 0
+    v4 = div int v0, v3
+    v5 = sext int -> long v2
+    ret v5
+}",
+            "function f {
+b0:
+    v0 = const int 7
+    v1 = const int 5
+    v2 = const int 2
+    v3 = const int 0
+    v4 = div int v0, v3
+    v5 = const long 2
+    ret v5
+}
+",
+        );
+    }
+
+    #[test]
+    fn sccp_removes_dead_branch() {
+        check(
+            "sccp",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = const false
+    if v1, b1, b2
+b1:
+    v2 = const int 1
+    goto b3
+b2:
+    goto b3
+b3:
+    v3 = phi int [b1: v2, b2: v0]
+    ret v3
+}",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = const false
+    goto b1
+b1:
+    goto b2
+b2:
+    v2 = phi int [b1: v0]
+    ret v2
+}
+",
+        );
+    }
+
+    #[test]
+    fn sccp_loop_phi() {
+        // x stays 1 in every iteration, only the counter varies
+        check(
+            "sccp",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = const int 1
+    v2 = const int 0
+    goto b1
+b1:
+    v3 = phi int [b0: v1, b2: v6]
+    v4 = phi int [b0: v2, b2: v7]
+    v5 = cmp lt int v4, v0
+    if v5, b2, b3
+b2:
+    v6 = mul int v3, v3
+    v7 = add int v4, v1
+    goto b1
+b3:
+    ret v3
+}",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = const int 1
+    v2 = const int 0
+    goto b1
+b1:
+    v3 = phi int [b0: v2, b2: v7]
+    v4 = const int 1
+    v5 = cmp lt int v3, v0
+    if v5, b2, b3
+b2:
+    v6 = const int 1
+    v7 = add int v3, v1
+    goto b1
+b3:
+    ret v4
+}
+",
+        );
+    }
+}
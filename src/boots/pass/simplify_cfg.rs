@@ -0,0 +1,300 @@
+use std::collections::HashSet;
+
+use crate::boots::function::{Block, Function};
+use crate::boots::inst::{Inst, InstData};
+
+// removes unreachable blocks, forwards jumps over blocks that only contain
+// a goto and merges blocks into their single predecessor
+pub fn run(fct: &mut Function) {
+    if fct.entry_block().is_none() {
+        return;
+    }
+
+    remove_unreachable_blocks(fct);
+
+    loop {
+        let mut changed = false;
+
+        for block in fct.blocks().to_vec() {
+            if !fct.cfg.contains_block(block) {
+                continue;
+            }
+
+            if forward_empty_block(fct, block) || merge_into_predecessor(fct, block) {
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+}
+
+fn remove_unreachable_blocks(fct: &mut Function) {
+    let mut reachable = HashSet::new();
+    let mut worklist = vec![fct.entry_block().unwrap()];
+
+    while let Some(block) = worklist.pop() {
+        if reachable.insert(block) {
+            worklist.extend(fct.cfg.successors(block).iter().cloned());
+        }
+    }
+
+    let unreachable: Vec<Block> = fct
+        .blocks()
+        .iter()
+        .cloned()
+        .filter(|block| !reachable.contains(block))
+        .collect();
+
+    for &block in &unreachable {
+        for succ in fct.cfg.successors(block).to_vec() {
+            if reachable.contains(&succ) {
+                fct.remove_phi_inputs(succ, block);
+            }
+        }
+
+        let insts: Vec<Inst> = fct.insts(block).collect();
+
+        for inst in insts {
+            fct.remove_inst(inst);
+        }
+    }
+
+    for block in unreachable {
+        fct.remove_block(block);
+    }
+}
+
+// redirects the predecessors of a block that only jumps to its successor
+fn forward_empty_block(fct: &mut Function, block: Block) -> bool {
+    if Some(block) == fct.entry_block() {
+        return false;
+    }
+
+    let target = match fct.cfg.first_inst(block).map(|inst| fct.inst(inst)) {
+        Some(&InstData::Goto { target }) if target != block => target,
+        _ => return false,
+    };
+
+    let target_has_phis = fct
+        .cfg
+        .first_inst(target)
+        .map(|inst| fct.inst(inst).is_phi())
+        .unwrap_or(false);
+
+    let mut changed = false;
+
+    for pred in fct.cfg.predecessors(block).to_vec() {
+        // phis cannot distinguish two edges from the same block
+        if target_has_phis && fct.cfg.predecessors(target).contains(&pred) {
+            continue;
+        }
+
+        let terminator = fct.terminator(pred).unwrap();
+        let mut inst_data = fct.inst(terminator).clone();
+
+        match inst_data {
+            InstData::Goto {
+                target: ref mut goto_target,
+            } => *goto_target = target,
+            InstData::If {
+                ref mut then_block,
+                ref mut else_block,
+                ..
+            } => {
+                // only one edge is redirected at a time
+                if *then_block == block {
+                    *then_block = target;
+                } else {
+                    *else_block = target;
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        fct.replace_inst(terminator, inst_data);
+        add_phi_inputs(fct, target, block, pred);
+        changed = true;
+    }
+
+    if fct.cfg.predecessors(block).is_empty() {
+        let goto = fct.terminator(block).unwrap();
+        fct.remove_phi_inputs(target, block);
+        fct.remove_inst(goto);
+        fct.remove_block(block);
+        changed = true;
+    }
+
+    changed
+}
+
+// the new edge from `pred` gets the same values as the edge from `block`
+fn add_phi_inputs(fct: &mut Function, target: Block, block: Block, pred: Block) {
+    let phis: Vec<Inst> = fct
+        .insts(target)
+        .take_while(|&inst| fct.inst(inst).is_phi())
+        .collect();
+
+    for phi in phis {
+        if let InstData::Phi { ref mut inputs, .. } = *fct.dfg.inst_mut(phi) {
+            let value = inputs
+                .iter()
+                .find(|&&(input_block, _)| input_block == block)
+                .map(|&(_, value)| value)
+                .expect("phi input missing");
+            inputs.push((pred, value));
+        }
+    }
+}
+
+// appends the block to its predecessor if the predecessor jumps only there
+fn merge_into_predecessor(fct: &mut Function, block: Block) -> bool {
+    if Some(block) == fct.entry_block() {
+        return false;
+    }
+
+    let pred = match *fct.cfg.predecessors(block) {
+        [pred] if pred != block => pred,
+        _ => return false,
+    };
+
+    let goto = fct.terminator(pred).unwrap();
+
+    match *fct.inst(goto) {
+        InstData::Goto { .. } => {}
+        _ => return false,
+    }
+
+    fct.remove_inst(goto);
+
+    let insts: Vec<Inst> = fct.insts(block).collect();
+
+    for inst in insts {
+        if fct.inst(inst).is_phi() {
+            // phis have a single input
+            let value = fct.inst(inst).operands()[0];
+            let result = fct.inst_result(inst).unwrap();
+            fct.replace_uses(result, value);
+            fct.remove_inst(inst);
+        } else {
+            fct.move_inst(inst, pred);
+        }
+    }
+
+    // phis in the successors now get their values from `pred`
+    for succ in fct.cfg.successors(pred).to_vec() {
+        rename_phi_inputs(fct, succ, block, pred);
+    }
+
+    fct.remove_block(block);
+    true
+}
+
+fn rename_phi_inputs(fct: &mut Function, block: Block, from: Block, to: Block) {
+    let phis: Vec<Inst> = fct
+        .insts(block)
+        .take_while(|&inst| fct.inst(inst).is_phi())
+        .collect();
+
+    for phi in phis {
+        if let InstData::Phi { ref mut inputs, .. } = *fct.dfg.inst_mut(phi) {
+            for input in inputs.iter_mut() {
+                if input.0 == from {
+                    input.0 = to;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::boots::pass::tests::check;
+
+    #[test]
+    fn simplify_merges_blocks() {
+        check(
+            "simplify-cfg",
+            "function f {
+b0:
+    v0 = param int 0
+    goto b1
+b1:
+    v1 = phi int [b0: v0]
+    v2 = add int v1, v1
+    goto b2
+b2:
+    ret v2
+}",
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = add int v0, v0
+    ret v1
+}
+",
+        );
+    }
+
+    #[test]
+    fn simplify_forwards_empty_blocks() {
+        check(
+            "simplify-cfg",
+            "function f {
+b0:
+    v0 = param bool 0
+    v1 = const int 1
+    v2 = const int 2
+    if v0, b1, b2
+b1:
+    goto b3
+b2:
+    goto b4
+b4:
+    goto b3
+b3:
+    v3 = phi int [b1: v1, b4: v2]
+    ret v3
+}",
+            "function f {
+b0:
+    v0 = param bool 0
+    v1 = const int 1
+    v2 = const int 2
+    if v0, b2, b1
+b1:
+    goto b2
+b2:
+    v3 = phi int [b1: v2, b0: v1]
+    ret v3
+}
+",
+        );
+    }
+
+    #[test]
+    fn simplify_removes_unreachable() {
+        check(
+            "simplify-cfg",
+            "function f {
+b0:
+    v0 = const int 1
+    goto b2
+b1:
+    v1 = const int 2
+    goto b2
+b2:
+    v2 = phi int [b0: v0, b1: v1]
+    ret v2
+}",
+            "function f {
+b0:
+    v0 = const int 1
+    ret v0
+}
+",
+        );
+    }
+}
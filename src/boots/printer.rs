@@ -0,0 +1,335 @@
+use std::collections::hash_map::HashMap;
+use std::fmt;
+
+use crate::boots::function::{Block, Function, Value};
+use crate::boots::inst::{CallKind, Inst, InstData};
+use crate::boots::utils::VecKey;
+use crate::class::TypeParams;
+use crate::os::signal::Trap;
+use crate::ty::BuiltinType;
+
+// blocks and values are numbered in layout order, so the text of a function
+// does not depend on the order in which instructions were created:
+//
+//   function f {
+//   b0:
+//       v0 = param int 0
+//       v1 = const int 1
+//       v2 = add int v0, v1
+//       ret v2
+//   }
+pub struct Names {
+    blocks: HashMap<Block, usize>,
+    values: HashMap<Value, usize>,
+}
+
+impl Names {
+    pub fn new(fct: &Function) -> Names {
+        let mut blocks = HashMap::new();
+        let mut values = HashMap::new();
+
+        for &block in fct.blocks() {
+            let idx = blocks.len();
+            blocks.insert(block, idx);
+
+            for inst in fct.insts(block) {
+                if let Some(value) = fct.inst_result(inst) {
+                    let idx = values.len();
+                    values.insert(value, idx);
+                }
+            }
+        }
+
+        Names {
+            blocks: blocks,
+            values: values,
+        }
+    }
+
+    // values and blocks outside of the layout keep their internal number
+    pub fn value(&self, value: Value) -> String {
+        match self.values.get(&value) {
+            Some(idx) => format!("v{}", idx),
+            None => format!("v?{}", value.index()),
+        }
+    }
+
+    pub fn block(&self, block: Block) -> String {
+        match self.blocks.get(&block) {
+            Some(idx) => format!("b{}", idx),
+            None => format!("b?{}", block.index()),
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = Names::new(self);
+
+        // names of lambdas and threads are no identifiers
+        if is_ident(&self.name) {
+            writeln!(f, "function {} {{", self.name)?;
+        } else {
+            writeln!(f, "function {:?} {{", self.name)?;
+        }
+
+        for &block in self.blocks() {
+            writeln!(f, "{}:", names.block(block))?;
+
+            for inst in self.insts(block) {
+                writeln!(f, "    {}", inst_to_string(self, &names, inst))?;
+            }
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+fn is_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(ch) if ch.is_alphabetic() || ch == '_' => {
+            chars.all(|ch| ch.is_alphanumeric() || ch == '_')
+        }
+        _ => false,
+    }
+}
+
+pub fn inst_to_string(fct: &Function, names: &Names, inst: Inst) -> String {
+    let inst_data = fct.inst(inst);
+    let v = |value: Value| names.value(value);
+    let b = |block: Block| names.block(block);
+
+    let text = match *inst_data {
+        InstData::Binary { op, ty, lhs, rhs } => {
+            format!("{} {} {}, {}", op.name(), ty.name(), v(lhs), v(rhs))
+        }
+
+        InstData::Unary { op, ty, opnd } => format!("{} {} {}", op.name(), ty.name(), v(opnd)),
+
+        InstData::Convert { op, from, to, opnd } => {
+            format!("{} {} -> {} {}", op.name(), from.name(), to.name(), v(opnd))
+        }
+
+        InstData::Cmp { ty, op, lhs, rhs } => {
+            format!("cmp {} {} {}, {}", op.name(), ty.name(), v(lhs), v(rhs))
+        }
+
+        InstData::Goto { target } => format!("goto {}", b(target)),
+
+        InstData::If {
+            opnd,
+            then_block,
+            else_block,
+        } => format!("if {}, {}, {}", v(opnd), b(then_block), b(else_block)),
+
+        InstData::Ret { opnd: Some(opnd) } => format!("ret {}", v(opnd)),
+        InstData::Ret { opnd: None } => "ret".into(),
+
+        InstData::TrueConst => "const true".into(),
+        InstData::FalseConst => "const false".into(),
+        InstData::NilConst => "const nil".into(),
+        InstData::Int8Const(value) => format!("const byte {}", value),
+        InstData::CharConst(value) => format!("const char {}", value as u32),
+        InstData::Int32Const(value) => format!("const int {}", value),
+        InstData::Int64Const(value) => format!("const long {}", value),
+        InstData::Float32Const(value) => format!("const float {}", value),
+        InstData::Float64Const(value) => format!("const double {}", value),
+        InstData::StringConst(ref value) => format!("const string {:?}", value),
+
+        InstData::Param { ty, idx } => format!("param {} {}", ty.name(), idx),
+
+        InstData::Phi { ty, ref inputs } => {
+            let inputs: Vec<String> = inputs
+                .iter()
+                .map(|&(block, value)| format!("{}: {}", b(block), v(value)))
+                .collect();
+            format!("phi {} [{}]", ty.name(), inputs.join(", "))
+        }
+
+        InstData::LoadField {
+            ty,
+            obj,
+            cls,
+            field,
+        } => format!(
+            "load_field {} {}, class {}, field {}",
+            ty.name(),
+            v(obj),
+            cls.to_usize(),
+            field.idx()
+        ),
+
+        InstData::StoreField {
+            ty,
+            obj,
+            cls,
+            field,
+            value,
+        } => format!(
+            "store_field {} {}, class {}, field {}, {}",
+            ty.name(),
+            v(obj),
+            cls.to_usize(),
+            field.idx(),
+            v(value)
+        ),
+
+        InstData::LoadGlobal { ty, global } => {
+            format!("load_global {} global {}", ty.name(), global.idx())
+        }
+
+        InstData::StoreGlobal { ty, global, value } => format!(
+            "store_global {} global {}, {}",
+            ty.name(),
+            global.idx(),
+            v(value)
+        ),
+
+        InstData::LoadArray { ty, array, index } => {
+            format!("load_array {} {}, {}", ty.name(), v(array), v(index))
+        }
+
+        InstData::StoreArray {
+            ty,
+            array,
+            index,
+            value,
+        } => format!(
+            "store_array {} {}, {}, {}",
+            ty.name(),
+            v(array),
+            v(index),
+            v(value)
+        ),
+
+        InstData::ArrayLength { array } => format!("array_length {}", v(array)),
+        InstData::SetUint8 { address, value } => format!("set_uint8 {}, {}", v(address), v(value)),
+        InstData::NilCheck { opnd } => format!("nil_check {}", v(opnd)),
+        InstData::BoundsCheck { index, length } => {
+            format!("bounds_check {}, {}", v(index), v(length))
+        }
+        InstData::InstanceOf { opnd, cls } => {
+            format!("instance_of {}, class {}", v(opnd), cls.to_usize())
+        }
+        InstData::CheckedCast { opnd, cls } => {
+            format!("checked_cast {}, class {}", v(opnd), cls.to_usize())
+        }
+        InstData::HasClass { opnd, cls } => {
+            format!("has_class {}, class {}", v(opnd), cls.to_usize())
+        }
+
+        InstData::Call {
+            ref kind,
+            ty,
+            ref args,
+        } => {
+            let ty = ty.map(|ty| ty.name()).unwrap_or("void");
+            let args: Vec<String> = args.iter().map(|&arg| v(arg)).collect();
+            format!("call {} {} ({})", ty, call_kind(kind), args.join(", "))
+        }
+
+        InstData::NewObject { cls } => format!("new_object class {}", cls.to_usize()),
+        InstData::NewArray { cls, length } => {
+            format!("new_array class {}, {}", cls.to_usize(), v(length))
+        }
+
+        InstData::Throw { opnd } => format!("throw {}", v(opnd)),
+        InstData::Trap { trap } => format!("trap {}", trap_name(trap)),
+        InstData::Deoptimize { pc, ref state } => {
+            let state: Vec<String> = state
+                .iter()
+                .map(|&(reg, value)| format!("{}: {}", reg, v(value)))
+                .collect();
+            format!("deoptimize {} [{}]", pc, state.join(", "))
+        }
+        InstData::Spawn { opnd } => format!("spawn {}", v(opnd)),
+        InstData::Debug => "debug".into(),
+        InstData::Deleted => "deleted".into(),
+    };
+
+    match fct.inst_result(inst) {
+        Some(value) => format!("{} = {}", v(value), text),
+        None => text,
+    }
+}
+
+fn call_kind(kind: &CallKind) -> String {
+    match *kind {
+        CallKind::Direct(fct_id, ref cls_type_params, ref fct_type_params) => format!(
+            "direct fct {}{}",
+            fct_id.0,
+            type_params(cls_type_params, fct_type_params)
+        ),
+        CallKind::Virtual(fct_id) => format!("virtual fct {}", fct_id.0),
+        CallKind::Static(fct_id, ref cls_type_params, ref fct_type_params) => format!(
+            "static fct {}{}",
+            fct_id.0,
+            type_params(cls_type_params, fct_type_params)
+        ),
+        CallKind::Lambda => "lambda".into(),
+    }
+}
+
+// type params are only printed for generic calls
+fn type_params(cls_type_params: &TypeParams, fct_type_params: &TypeParams) -> String {
+    if cls_type_params.len() == 0 && fct_type_params.len() == 0 {
+        return String::new();
+    }
+
+    let list = |type_params: &TypeParams| -> String {
+        let types: Vec<String> = type_params.iter().map(|ty| type_param_name(&ty)).collect();
+        types.join(", ")
+    };
+
+    format!(" [{}] [{}]", list(cls_type_params), list(fct_type_params))
+}
+
+// class, struct and other ids only have a meaning within the vm
+fn type_param_name(ty: &BuiltinType) -> String {
+    match *ty {
+        BuiltinType::Unit => "Unit".into(),
+        BuiltinType::Bool => "Bool".into(),
+        BuiltinType::Byte => "Byte".into(),
+        BuiltinType::Char => "Char".into(),
+        BuiltinType::Int => "Int".into(),
+        BuiltinType::Long => "Long".into(),
+        BuiltinType::Float => "Float".into(),
+        BuiltinType::Double => "Double".into(),
+        BuiltinType::Ptr => "Ptr".into(),
+        BuiltinType::Class(cls_id, list_id) => {
+            format!("Class({}, {})", usize::from(cls_id), list_id.idx())
+        }
+        BuiltinType::Struct(struct_id, list_id) => {
+            format!("Struct({}, {})", struct_id.idx(), list_id.idx())
+        }
+        BuiltinType::Trait(trait_id) => format!("Trait({})", trait_id.idx()),
+        BuiltinType::Enum(enum_id) => format!("Enum({})", enum_id.idx()),
+        BuiltinType::Lambda(lambda_id) => format!("Lambda({})", lambda_id.idx()),
+        _ => panic!("unexpected type param {:?}", ty),
+    }
+}
+
+const TRAPS: &[(Trap, &str)] = &[
+    (Trap::DIV0, "div0"),
+    (Trap::ASSERT, "assert"),
+    (Trap::INDEX_OUT_OF_BOUNDS, "index_out_of_bounds"),
+    (Trap::NIL, "nil"),
+    (Trap::THROW, "throw"),
+    (Trap::CAST, "cast"),
+    (Trap::UNEXPECTED, "unexpected"),
+    (Trap::OOM, "oom"),
+    (Trap::STACK_OVERFLOW, "stack_overflow"),
+];
+
+pub fn trap_name(trap: Trap) -> &'static str {
+    TRAPS.iter().find(|&&(t, _)| t == trap).unwrap().1
+}
+
+pub fn trap_from_name(name: &str) -> Option<Trap> {
+    TRAPS
+        .iter()
+        .find(|&&(_, n)| n == name)
+        .map(|&(trap, _)| trap)
+}
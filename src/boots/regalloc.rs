@@ -0,0 +1,657 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::boots::dom::reverse_postorder;
+use crate::boots::function::{Block, Function, Type, Value};
+use crate::boots::inst::{Inst, InstData};
+use crate::cpu::{FReg, Reg};
+use crate::mem;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Location {
+    Reg(Reg),
+    FReg(FReg),
+
+    // offset relative to the frame pointer
+    Stack(i32),
+}
+
+// result of the linear scan register allocation, every value keeps
+// its location for its whole lifetime
+pub struct Allocation {
+    blocks: Vec<Block>,
+    block_idx: HashMap<Block, usize>,
+    intervals: HashMap<Value, Interval>,
+    locations: HashMap<Value, Location>,
+    live_ptrs: HashMap<Inst, Vec<Value>>,
+    stack_slots: usize,
+}
+
+impl Allocation {
+    // reachable blocks in layout order
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn location(&self, value: Value) -> Location {
+        self.locations[&value]
+    }
+
+    pub fn is_back_edge(&self, from: Block, to: Block) -> bool {
+        self.block_idx[&to] <= self.block_idx[&from]
+    }
+
+    pub fn stack_size(&self) -> i32 {
+        self.stack_slots as i32 * mem::ptr_width()
+    }
+
+    // stack slots of pointers live across the instruction,
+    // pointers are never kept in registers across calls and safepoints
+    pub fn gcpoint_offsets(&self, inst: Inst) -> Vec<i32> {
+        match self.live_ptrs.get(&inst) {
+            Some(values) => values
+                .iter()
+                .filter_map(|value| match self.locations[value] {
+                    Location::Stack(offset) => Some(offset),
+                    _ => None,
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+// a value occupies its location from `start` to `end` inclusive
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Interval {
+    start: u32,
+    end: u32,
+}
+
+impl Interval {
+    fn intersects(&self, other: &Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+}
+
+// linear scan allocation (Poletto and Sarkar) with a single interval per value,
+// values that live across calls (or pointers across safepoints) are spilled
+pub fn allocate(fct: &Function, regs: &[Reg], fregs: &[FReg]) -> Allocation {
+    let blocks = reverse_postorder(fct);
+    let block_idx: HashMap<Block, usize> = blocks
+        .iter()
+        .enumerate()
+        .map(|(idx, &block)| (block, idx))
+        .collect();
+
+    // every block gets a position before its first and after its last instruction,
+    // phis are defined at the start and phi moves happen at the end of a block
+    let mut block_from = HashMap::new();
+    let mut block_to = HashMap::new();
+    let mut inst_pos = HashMap::new();
+    let mut pos = 0;
+
+    for &block in &blocks {
+        block_from.insert(block, pos);
+        pos += 1;
+
+        for inst in fct.insts(block) {
+            inst_pos.insert(inst, pos);
+            pos += 1;
+        }
+
+        block_to.insert(block, pos);
+        pos += 1;
+    }
+
+    let (live_in, live_out) = liveness(fct, &blocks);
+
+    let mut intervals = HashMap::new();
+    let mut call_points = Vec::new();
+    let mut safepoints = Vec::new();
+
+    for &block in &blocks {
+        let from = block_from[&block];
+        let to = block_to[&block];
+
+        for &value in &live_in[&block] {
+            extend(&mut intervals, value, from);
+        }
+
+        for &value in &live_out[&block] {
+            extend(&mut intervals, value, to);
+        }
+
+        for inst in fct.insts(block) {
+            let pos = inst_pos[&inst];
+            let inst_data = fct.inst(inst);
+
+            match *inst_data {
+                InstData::Phi { ref inputs, .. } => {
+                    let result = fct.inst_result(inst).unwrap();
+                    extend(&mut intervals, result, from);
+
+                    for &(pred, input) in inputs {
+                        if let Some(&pred_to) = block_to.get(&pred) {
+                            extend(&mut intervals, input, pred_to);
+                            extend(&mut intervals, result, pred_to);
+                        }
+                    }
+                }
+
+                InstData::Param { .. } => {
+                    let result = fct.inst_result(inst).unwrap();
+                    extend(&mut intervals, result, 0);
+                }
+
+                _ => {
+                    if let Some(result) = fct.inst_result(inst) {
+                        extend(&mut intervals, result, pos);
+                    }
+
+                    for value in inst_data.operands() {
+                        extend(&mut intervals, value, pos);
+                    }
+                }
+            }
+
+            if is_call(inst_data) {
+                call_points.push(pos);
+            }
+
+            if inst_data.is_terminator()
+                && inst_data
+                    .successors()
+                    .iter()
+                    .any(|succ| block_idx[succ] <= block_idx[&block])
+            {
+                safepoints.push(pos);
+            }
+        }
+    }
+
+    let live_ptrs = live_ptrs(fct, &blocks, &live_out);
+
+    let mut sorted: Vec<(Value, Interval)> = intervals
+        .iter()
+        .map(|(&value, &interval)| (value, interval))
+        .collect();
+    sorted.sort_by_key(|&(value, interval)| (interval.start, value));
+
+    let mut locations = HashMap::new();
+    let mut slots: Vec<Vec<Interval>> = Vec::new();
+    let mut active: Vec<(Value, Interval)> = Vec::new();
+    let mut free_regs: Vec<Reg> = regs.iter().rev().cloned().collect();
+    let mut free_fregs: Vec<FReg> = fregs.iter().rev().cloned().collect();
+
+    for (value, interval) in sorted {
+        active.retain(|&(active_value, active_interval)| {
+            if active_interval.end >= interval.start {
+                return true;
+            }
+
+            match locations[&active_value] {
+                Location::Reg(reg) => free_regs.push(reg),
+                Location::FReg(freg) => free_fregs.push(freg),
+                Location::Stack(_) => unreachable!(),
+            }
+
+            false
+        });
+
+        let ty = fct.value_type(value);
+
+        if crosses(&call_points, interval) || (ty.is_ptr() && crosses(&safepoints, interval)) {
+            let offset = assign_slot(&mut slots, interval);
+            locations.insert(value, Location::Stack(offset));
+            continue;
+        }
+
+        let free = if ty.is_float() {
+            free_fregs.pop().map(Location::FReg)
+        } else {
+            free_regs.pop().map(Location::Reg)
+        };
+
+        if let Some(location) = free {
+            locations.insert(value, location);
+            active.push((value, interval));
+            continue;
+        }
+
+        // out of registers: spill whichever interval ends last
+        let candidate = active
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(active_value, _))| {
+                fct.value_type(active_value).is_float() == ty.is_float()
+            })
+            .max_by_key(|&(_, &(_, active_interval))| active_interval.end)
+            .map(|(idx, _)| idx);
+
+        match candidate {
+            Some(idx) if active[idx].1.end > interval.end => {
+                let (spilled, spilled_interval) = active.swap_remove(idx);
+                let location = locations[&spilled];
+                let offset = assign_slot(&mut slots, spilled_interval);
+                locations.insert(spilled, Location::Stack(offset));
+                locations.insert(value, location);
+                active.push((value, interval));
+            }
+
+            _ => {
+                let offset = assign_slot(&mut slots, interval);
+                locations.insert(value, Location::Stack(offset));
+            }
+        }
+    }
+
+    Allocation {
+        blocks: blocks,
+        block_idx: block_idx,
+        intervals: intervals,
+        locations: locations,
+        live_ptrs: live_ptrs,
+        stack_slots: slots.len(),
+    }
+}
+
+fn liveness(
+    fct: &Function,
+    blocks: &[Block],
+) -> (
+    HashMap<Block, HashSet<Value>>,
+    HashMap<Block, HashSet<Value>>,
+) {
+    let mut live_in: HashMap<Block, HashSet<Value>> = blocks
+        .iter()
+        .map(|&block| (block, HashSet::new()))
+        .collect();
+    let mut live_out = live_in.clone();
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for &block in blocks.iter().rev() {
+            let mut live = HashSet::new();
+
+            for &succ in fct.cfg.successors(block) {
+                live.extend(live_in[&succ].iter().cloned());
+                live.extend(phi_inputs(fct, succ, block));
+            }
+
+            live_out.insert(block, live.clone());
+
+            let insts: Vec<Inst> = fct.insts(block).collect();
+
+            for &inst in insts.iter().rev() {
+                if let Some(result) = fct.inst_result(inst) {
+                    live.remove(&result);
+                }
+
+                if !fct.inst(inst).is_phi() {
+                    live.extend(fct.inst(inst).operands());
+                }
+            }
+
+            if live != live_in[&block] {
+                live_in.insert(block, live);
+                changed = true;
+            }
+        }
+    }
+
+    (live_in, live_out)
+}
+
+// pointers live across each instruction
+fn live_ptrs(
+    fct: &Function,
+    blocks: &[Block],
+    live_out: &HashMap<Block, HashSet<Value>>,
+) -> HashMap<Inst, Vec<Value>> {
+    let mut result = HashMap::new();
+
+    for &block in blocks {
+        let mut live = live_out[&block].clone();
+        let insts: Vec<Inst> = fct.insts(block).collect();
+
+        for &inst in insts.iter().rev() {
+            if fct.inst(inst).is_phi() {
+                break;
+            }
+
+            // the result is not yet stored when the instruction reaches its gc point
+            if let Some(value) = fct.inst_result(inst) {
+                live.remove(&value);
+            }
+
+            let mut ptrs: Vec<Value> = live
+                .iter()
+                .filter(|&&value| fct.value_type(value).is_ptr())
+                .cloned()
+                .collect();
+            ptrs.sort();
+            result.insert(inst, ptrs);
+
+            live.extend(fct.inst(inst).operands());
+        }
+    }
+
+    result
+}
+
+fn phi_inputs(fct: &Function, block: Block, pred: Block) -> Vec<Value> {
+    let mut inputs = Vec::new();
+
+    for inst in fct.insts(block) {
+        match *fct.inst(inst) {
+            InstData::Phi {
+                inputs: ref phi_inputs,
+                ..
+            } => inputs.extend(
+                phi_inputs
+                    .iter()
+                    .filter(|&&(input_block, _)| input_block == pred)
+                    .map(|&(_, value)| value),
+            ),
+            _ => break,
+        }
+    }
+
+    inputs
+}
+
+fn extend(intervals: &mut HashMap<Value, Interval>, value: Value, pos: u32) {
+    let interval = intervals.entry(value).or_insert(Interval {
+        start: pos,
+        end: pos,
+    });
+
+    interval.start = interval.start.min(pos);
+    interval.end = interval.end.max(pos);
+}
+
+// instructions that clobber all registers
+fn is_call(inst: &InstData) -> bool {
+    match *inst {
+        InstData::Call { .. }
+        | InstData::NewObject { .. }
+        | InstData::NewArray { .. }
+        | InstData::Spawn { .. } => true,
+        _ => false,
+    }
+}
+
+// whether one of the sorted positions lies strictly inside the interval
+fn crosses(positions: &[u32], interval: Interval) -> bool {
+    let idx = match positions.binary_search(&(interval.start + 1)) {
+        Ok(idx) | Err(idx) => idx,
+    };
+
+    idx < positions.len() && positions[idx] < interval.end
+}
+
+fn assign_slot(slots: &mut Vec<Vec<Interval>>, interval: Interval) -> i32 {
+    let idx = match slots
+        .iter()
+        .position(|occupants| occupants.iter().all(|other| !other.intersects(&interval)))
+    {
+        Some(idx) => idx,
+        None => {
+            slots.push(Vec::new());
+            slots.len() - 1
+        }
+    };
+
+    slots[idx].push(interval);
+    -(idx as i32 + 1) * mem::ptr_width()
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub src: Location,
+    pub dest: Location,
+    pub ty: Type,
+}
+
+// orders parallel moves such that no location is overwritten before it was read,
+// cycles are broken by saving one value in the scratch location of its class
+pub fn sequentialize(moves: &[Move], scratch: Location, fscratch: Location) -> Vec<Move> {
+    let mut pending: Vec<Move> = moves
+        .iter()
+        .filter(|mov| mov.src != mov.dest)
+        .cloned()
+        .collect();
+    let mut result = Vec::new();
+
+    while !pending.is_empty() {
+        let ready = pending
+            .iter()
+            .position(|mov| pending.iter().all(|other| other.src != mov.dest));
+
+        if let Some(idx) = ready {
+            result.push(pending.remove(idx));
+            continue;
+        }
+
+        // every destination is still read by another move: this is a cycle
+        let dest = pending[0].dest;
+        let ty = pending.iter().find(|mov| mov.src == dest).unwrap().ty;
+        let tmp = if ty.is_float() { fscratch } else { scratch };
+
+        result.push(Move {
+            src: dest,
+            dest: tmp,
+            ty: ty,
+        });
+
+        for mov in &mut pending {
+            if mov.src == dest {
+                mov.src = tmp;
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{allocate, sequentialize, Allocation, Location, Move};
+    use crate::boots::function::{Function, Type, Value};
+    use crate::boots::parser::parse;
+    use crate::cpu::{FReg, Reg};
+
+    fn regs() -> Vec<Reg> {
+        vec![Reg(1), Reg(2), Reg(3)]
+    }
+
+    fn fregs() -> Vec<FReg> {
+        vec![FReg(1), FReg(2)]
+    }
+
+    // values are numbered in order of definition like in the printer
+    fn value(fct: &Function, idx: usize) -> Value {
+        fct.blocks()
+            .iter()
+            .flat_map(|&block| fct.insts(block))
+            .filter_map(|inst| fct.inst_result(inst))
+            .nth(idx)
+            .unwrap()
+    }
+
+    fn assert_no_conflicts(alloc: &Allocation) {
+        for (&v1, i1) in &alloc.intervals {
+            for (&v2, i2) in &alloc.intervals {
+                if v1 != v2 && i1.intersects(i2) {
+                    assert_ne!(
+                        alloc.location(v1),
+                        alloc.location(v2),
+                        "{:?} and {:?} share a location",
+                        v1,
+                        v2
+                    );
+                }
+            }
+        }
+    }
+
+    fn is_stack(location: Location) -> bool {
+        match location {
+            Location::Stack(_) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn alloc_straight_line() {
+        let fct = parse(
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = param int 1
+    v2 = add int v0, v1
+    v3 = param double 2
+    v4 = add double v3, v3
+    ret v2
+}",
+        )
+        .unwrap();
+        let alloc = allocate(&fct, &regs(), &fregs());
+
+        assert_no_conflicts(&alloc);
+        assert_eq!(Location::Reg(Reg(1)), alloc.location(value(&fct, 0)));
+        assert_eq!(Location::Reg(Reg(2)), alloc.location(value(&fct, 1)));
+        assert_eq!(Location::Reg(Reg(3)), alloc.location(value(&fct, 2)));
+        assert_eq!(Location::FReg(FReg(1)), alloc.location(value(&fct, 3)));
+        assert_eq!(Location::FReg(FReg(2)), alloc.location(value(&fct, 4)));
+        assert_eq!(0, alloc.stack_size());
+    }
+
+    #[test]
+    fn alloc_spills_furthest_end() {
+        let fct = parse(
+            "function f {
+b0:
+    v0 = param int 0
+    v1 = const int 1
+    v2 = const int 2
+    v3 = const int 3
+    v4 = add int v1, v2
+    v5 = add int v4, v3
+    v6 = add int v5, v0
+    ret v6
+}",
+        )
+        .unwrap();
+        let alloc = allocate(&fct, &regs()[0..2], &fregs());
+
+        assert_no_conflicts(&alloc);
+        assert!(is_stack(alloc.location(value(&fct, 0))));
+        assert!(is_stack(alloc.location(value(&fct, 3))));
+        assert!(is_stack(alloc.location(value(&fct, 4))));
+        assert!(!is_stack(alloc.location(value(&fct, 5))));
+        assert_eq!(24, alloc.stack_size());
+    }
+
+    #[test]
+    fn alloc_spills_across_calls() {
+        let fct = parse(
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = param int 1
+    v2 = call int direct fct 7 (v0)
+    v3 = add int v1, v2
+    ret v3
+}",
+        )
+        .unwrap();
+        let alloc = allocate(&fct, &regs(), &fregs());
+
+        assert_no_conflicts(&alloc);
+        assert!(!is_stack(alloc.location(value(&fct, 0))));
+        assert!(is_stack(alloc.location(value(&fct, 1))));
+        assert!(!is_stack(alloc.location(value(&fct, 2))));
+    }
+
+    #[test]
+    fn alloc_loop() {
+        let fct = parse(
+            "function f {
+b0:
+    v0 = param ptr 0
+    v1 = param int 1
+    v2 = const int 0
+    goto b1
+b1:
+    v3 = phi int [b0: v2, b2: v6]
+    v4 = cmp lt int v3, v1
+    if v4, b2, b3
+b2:
+    v5 = const int 1
+    v6 = add int v3, v5
+    goto b1
+b3:
+    ret v0
+}",
+        )
+        .unwrap();
+        let alloc = allocate(&fct, &regs(), &fregs());
+
+        assert_no_conflicts(&alloc);
+
+        // pointers live across the back edge are kept in memory
+        let v0 = alloc.location(value(&fct, 0));
+        assert!(is_stack(v0));
+        assert!(!is_stack(alloc.location(value(&fct, 3))));
+
+        let b1 = fct.blocks()[1];
+        let b2 = fct.blocks()[2];
+        assert!(alloc.is_back_edge(b2, b1));
+        assert!(!alloc.is_back_edge(b1, b2));
+
+        let goto = fct.terminator(b2).unwrap();
+        match v0 {
+            Location::Stack(offset) => assert_eq!(vec![offset], alloc.gcpoint_offsets(goto)),
+            _ => unreachable!(),
+        }
+    }
+
+    fn mov(src: Location, dest: Location) -> Move {
+        Move {
+            src: src,
+            dest: dest,
+            ty: Type::Int,
+        }
+    }
+
+    #[test]
+    fn sequentialize_chain() {
+        let r1 = Location::Reg(Reg(1));
+        let r2 = Location::Reg(Reg(2));
+        let r3 = Location::Reg(Reg(3));
+        let tmp = Location::Reg(Reg(9));
+        let ftmp = Location::FReg(FReg(9));
+
+        assert_eq!(
+            vec![mov(r2, r3), mov(r1, r2)],
+            sequentialize(&[mov(r1, r2), mov(r2, r3), mov(r1, r1)], tmp, ftmp)
+        );
+    }
+
+    #[test]
+    fn sequentialize_cycle() {
+        let r1 = Location::Reg(Reg(1));
+        let r2 = Location::Reg(Reg(2));
+        let s1 = Location::Stack(-8);
+        let tmp = Location::Reg(Reg(9));
+        let ftmp = Location::FReg(FReg(9));
+
+        assert_eq!(
+            vec![mov(r2, tmp), mov(r1, r2), mov(s1, r1), mov(tmp, s1)],
+            sequentialize(&[mov(r1, r2), mov(r2, s1), mov(s1, r1)], tmp, ftmp)
+        );
+    }
+}
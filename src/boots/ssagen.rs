@@ -0,0 +1,1522 @@
+use std::collections::hash_map::HashMap;
+use std::collections::HashSet;
+
+use crate::baseline::ic::{self, Feedback};
+use crate::boots::function::{Block, Function, Type, Value};
+use crate::boots::inst::{BinOp, CallKind, CmpOp, ConvOp, Inst, InstData, UnOp};
+use crate::bytecode::generate::{BytecodeFunction, BytecodeIdx, Register};
+use crate::bytecode::opcode::Bytecode;
+use crate::cha;
+use crate::class::{ClassDefId, TypeParams};
+use crate::semck::specialize::specialize_class_id;
+use crate::vm::{Fct, FctId, FctParent, FctSrc, VM};
+
+// translates the bytecode of a function into SSA form, uses the algorithm
+// from "Simple and Efficient Construction of Static Single Assignment Form"
+// by Braun et al.: every register is a variable, phis are created on demand
+// and trivial phis are removed right away
+pub fn generate(vm: &VM, fct: &Fct, bytecode: &BytecodeFunction) -> Result<Function, String> {
+    let params: Vec<Register> = (0..fct.params_with_self().len()).map(Register).collect();
+    generate_from(vm, fct, None, bytecode, 0, &params, false)
+}
+
+// like `generate`, but also guards virtual calls of overridden methods with
+// the receiver class recorded by the inline cache of the baseline code
+pub fn generate_with_feedback(
+    vm: &VM,
+    fct: &Fct,
+    src: &FctSrc,
+    bytecode: &BytecodeFunction,
+) -> Result<Function, String> {
+    let params: Vec<Register> = (0..fct.params_with_self().len()).map(Register).collect();
+    generate_from(vm, fct, Some(src), bytecode, 0, &params, false)
+}
+
+// translates the bytecode into a function that starts at the loop at `start`,
+// param `idx` of the function holds the value of `regs[idx]`. Used as entry for
+// on-stack replacement, fails if other registers are live at the loop.
+pub fn generate_osr(
+    vm: &VM,
+    fct: &Fct,
+    src: &FctSrc,
+    bytecode: &BytecodeFunction,
+    start: BytecodeIdx,
+    regs: &[Register],
+) -> Result<Function, String> {
+    generate_from(vm, fct, Some(src), bytecode, start.0, regs, true)
+}
+
+fn generate_from(
+    vm: &VM,
+    fct: &Fct,
+    src: Option<&FctSrc>,
+    bytecode: &BytecodeFunction,
+    start: usize,
+    params: &[Register],
+    osr: bool,
+) -> Result<Function, String> {
+    if !bytecode.exception_handlers().is_empty() {
+        return Err("exception handlers are not supported".into());
+    }
+
+    let mut ir = Function::new();
+    ir.name = vm.interner.str(fct.name).to_string();
+
+    let ssagen = SsaGen {
+        vm: vm,
+        src: src,
+        bytecode: bytecode,
+        params: params,
+        start: start,
+        osr: osr,
+        missing: None,
+        fct: ir,
+        blocks: HashMap::new(),
+        unfilled_preds: HashMap::new(),
+        sealed: HashSet::new(),
+        current_defs: vec![HashMap::new(); bytecode.registers().len()],
+        incomplete_phis: HashMap::new(),
+        block: None,
+        pc: 0,
+        uses: vec![Vec::new(); bytecode.code().len()],
+        defs: vec![Vec::new(); bytecode.code().len()],
+        deopts: Vec::new(),
+    };
+
+    ssagen.generate()
+}
+
+struct SsaGen<'a, 'ast: 'a> {
+    vm: &'a VM<'ast>,
+    bytecode: &'a BytecodeFunction,
+
+    // source of the inline caches for receiver class feedback
+    src: Option<&'a FctSrc>,
+
+    params: &'a [Register],
+    fct: Function,
+
+    // bytecode index of the first instruction
+    start: usize,
+
+    // the function is an OSR entry, registers not passed
+    // as params are not available in the entry block
+    osr: bool,
+    missing: Option<Register>,
+
+    // block starting at the bytecode index
+    blocks: HashMap<usize, Block>,
+
+    // a block is sealed once all of its predecessors are filled
+    unfilled_preds: HashMap<Block, usize>,
+    sealed: HashSet<Block>,
+
+    current_defs: Vec<HashMap<Block, Value>>,
+    incomplete_phis: HashMap<Block, Vec<(Register, Inst)>>,
+
+    block: Option<Block>,
+    pc: usize,
+
+    // registers read and written by the instruction at a bytecode index
+    uses: Vec<Vec<Register>>,
+    defs: Vec<Vec<Register>>,
+
+    // class checks of calls: the block of the check, the block leaving
+    // the optimized code and the bytecode index of the call
+    deopts: Vec<(Block, Block, usize)>,
+}
+
+impl<'a, 'ast> SsaGen<'a, 'ast>
+where
+    'ast: 'a,
+{
+    fn generate(mut self) -> Result<Function, String> {
+        let code = self.bytecode.code();
+        let starts = block_starts(code);
+        let successors: HashMap<usize, Vec<usize>> = starts
+            .iter()
+            .enumerate()
+            .map(|(idx, &start)| {
+                let end = starts.get(idx + 1).cloned().unwrap_or(code.len());
+                (start, block_successors(code, end))
+            })
+            .collect();
+
+        if !successors.contains_key(&self.start) {
+            return Err("entry does not start a basic block".into());
+        }
+
+        let reachable = reachable_starts(&successors, self.start);
+
+        for &start in &reachable {
+            for &succ in &successors[&start] {
+                if succ >= code.len() {
+                    return Err("control flow falls off the end of the function".into());
+                }
+            }
+        }
+
+        // the entry block defines the parameters, it is never the target of a jump
+        let entry = self.fct.make_block();
+        self.sealed.insert(entry);
+        self.block = Some(entry);
+
+        let params = self.params;
+
+        for (idx, &reg) in params.iter().enumerate() {
+            let param = self.ins_value(InstData::Param {
+                ty: self.ty(reg),
+                idx: idx as u32,
+            });
+            self.write_variable(reg, entry, param);
+        }
+
+        for &start in &reachable {
+            let block = self.fct.make_block();
+            self.blocks.insert(start, block);
+            self.unfilled_preds.insert(block, 0);
+        }
+
+        *self
+            .unfilled_preds
+            .get_mut(&self.blocks[&self.start])
+            .unwrap() += 1;
+
+        for &start in &reachable {
+            for succ in &successors[&start] {
+                *self.unfilled_preds.get_mut(&self.blocks[succ]).unwrap() += 1;
+            }
+        }
+
+        let first_block = self.blocks[&self.start];
+        self.ins(InstData::Goto {
+            target: first_block,
+        });
+        self.fill(entry);
+
+        for (idx, &start) in starts.iter().enumerate() {
+            if !self.blocks.contains_key(&start) {
+                continue;
+            }
+
+            let end = starts.get(idx + 1).cloned().unwrap_or(code.len());
+            self.block = Some(self.blocks[&start]);
+
+            for pc in start..end {
+                self.pc = pc;
+                self.translate(&code[pc]);
+            }
+
+            // class checks of calls split the block
+            let block = self.block.unwrap();
+
+            if self.fct.terminator(block).is_none() {
+                let target = self.blocks[&end];
+                self.ins(InstData::Goto { target: target });
+            }
+
+            self.fill(block);
+        }
+
+        if !self.deopts.is_empty() {
+            self.emit_deopts(&starts, &successors);
+        }
+
+        assert!(self.incomplete_phis.is_empty());
+        assert_eq!(self.sealed.len(), self.fct.blocks().len());
+
+        if let Some(reg) = self.missing {
+            return Err(format!("register {} is live at the OSR entry", reg));
+        }
+
+        Ok(self.fct)
+    }
+
+    // all variables of `block` are known, seals successors without unfilled predecessors
+    fn fill(&mut self, block: Block) {
+        for succ in self.fct.cfg.successors(block).to_vec() {
+            let unfilled = self.unfilled_preds.get_mut(&succ).unwrap();
+            *unfilled -= 1;
+
+            if *unfilled == 0 {
+                self.seal(succ);
+            }
+        }
+    }
+
+    fn seal(&mut self, block: Block) {
+        while let Some(phis) = self.incomplete_phis.remove(&block) {
+            for (reg, phi) in phis {
+                self.add_phi_operands(reg, phi);
+            }
+        }
+
+        self.sealed.insert(block);
+    }
+
+    fn write_variable(&mut self, reg: Register, block: Block, value: Value) {
+        self.current_defs[reg.0].insert(block, value);
+    }
+
+    fn read_variable(&mut self, reg: Register, block: Block) -> Value {
+        match self.current_defs[reg.0].get(&block) {
+            Some(&value) => value,
+            None => self.read_variable_recursive(reg, block),
+        }
+    }
+
+    fn read_variable_recursive(&mut self, reg: Register, block: Block) -> Value {
+        let ty = self.ty(reg);
+
+        let value = if !self.sealed.contains(&block) {
+            let phi = self.phi(block, ty);
+            self.incomplete_phis
+                .entry(block)
+                .or_default()
+                .push((reg, phi));
+            self.fct.inst_result(phi).unwrap()
+        } else {
+            let preds = self.fct.cfg.predecessors(block).to_vec();
+
+            match preds.len() {
+                0 => {
+                    if self.osr {
+                        self.missing = Some(reg);
+                    }
+
+                    self.undefined(ty)
+                }
+                1 => self.read_variable(reg, preds[0]),
+                _ => {
+                    // the phi breaks cycles of the recursion
+                    let phi = self.phi(block, ty);
+                    let value = self.fct.inst_result(phi).unwrap();
+                    self.write_variable(reg, block, value);
+                    self.add_phi_operands(reg, phi)
+                }
+            }
+        };
+
+        self.write_variable(reg, block, value);
+        value
+    }
+
+    fn phi(&mut self, block: Block, ty: Type) -> Inst {
+        self.fct.prepend_inst(
+            block,
+            InstData::Phi {
+                ty: ty,
+                inputs: Vec::new(),
+            },
+        )
+    }
+
+    fn add_phi_operands(&mut self, reg: Register, phi: Inst) -> Value {
+        let block = self.fct.cfg.inst_block(phi).unwrap();
+
+        for pred in self.fct.cfg.predecessors(block).to_vec() {
+            let value = self.read_variable(reg, pred);
+
+            match self.fct.dfg.inst_mut(phi) {
+                InstData::Phi { inputs, .. } => inputs.push((pred, value)),
+                _ => unreachable!(),
+            }
+        }
+
+        self.try_remove_trivial_phi(phi)
+    }
+
+    // a phi that only references itself and one other value is replaced by that value
+    fn try_remove_trivial_phi(&mut self, phi: Inst) -> Value {
+        let phi_value = self.fct.inst_result(phi).unwrap();
+        let mut same: Option<Value> = None;
+
+        for opnd in self.fct.inst(phi).operands() {
+            if Some(opnd) == same || opnd == phi_value {
+                continue;
+            }
+
+            if same.is_some() {
+                return phi_value;
+            }
+
+            same = Some(opnd);
+        }
+
+        let same = match same {
+            Some(same) => same,
+            None => {
+                let ty = self.fct.value_type(phi_value);
+                self.undefined(ty)
+            }
+        };
+
+        let users = self.phi_users(phi_value, phi);
+
+        self.fct.remove_inst(phi);
+        *self.fct.dfg.inst_mut(phi) = InstData::Deleted;
+        self.fct.replace_uses(phi_value, same);
+
+        for defs in &mut self.current_defs {
+            for value in defs.values_mut() {
+                if *value == phi_value {
+                    *value = same;
+                }
+            }
+        }
+
+        for user in users {
+            if self.fct.cfg.inst_block(user).is_some() {
+                self.try_remove_trivial_phi(user);
+            }
+        }
+
+        same
+    }
+
+    fn phi_users(&self, value: Value, phi: Inst) -> Vec<Inst> {
+        let mut users = Vec::new();
+
+        for &block in self.fct.blocks() {
+            for inst in self.fct.insts(block) {
+                let inst_data = self.fct.inst(inst);
+
+                if inst != phi && inst_data.is_phi() && inst_data.operands().contains(&value) {
+                    users.push(inst);
+                }
+            }
+        }
+
+        users
+    }
+
+    // value of a register read before any assignment
+    fn undefined(&mut self, ty: Type) -> Value {
+        let inst_data = match ty {
+            Type::Bool => InstData::FalseConst,
+            Type::Byte => InstData::Int8Const(0),
+            Type::Char => InstData::CharConst('\0'),
+            Type::Int => InstData::Int32Const(0),
+            Type::Long => InstData::Int64Const(0),
+            Type::Float => InstData::Float32Const(0.0),
+            Type::Double => InstData::Float64Const(0.0),
+            Type::Ptr => InstData::NilConst,
+        };
+
+        let entry = self.fct.entry_block().unwrap();
+        let inst = self.fct.prepend_inst(entry, inst_data);
+        self.fct.inst_result(inst).unwrap()
+    }
+
+    fn ty(&self, reg: Register) -> Type {
+        self.bytecode.register(reg).into()
+    }
+
+    fn ins(&mut self, inst_data: InstData) -> Inst {
+        let inst = self.fct.append_inst(self.block.unwrap(), inst_data);
+        let pos = self.bytecode.position(BytecodeIdx(self.pc));
+        self.fct.dfg.set_position(inst, pos);
+        inst
+    }
+
+    fn ins_value(&mut self, inst_data: InstData) -> Value {
+        let inst = self.ins(inst_data);
+        self.fct.inst_result(inst).unwrap()
+    }
+
+    fn def(&mut self, dest: Register, inst_data: InstData) {
+        let value = self.ins_value(inst_data);
+        self.set(dest, value);
+    }
+
+    fn set(&mut self, dest: Register, value: Value) {
+        self.defs[self.pc].push(dest);
+        let block = self.block.unwrap();
+        self.write_variable(dest, block, value);
+    }
+
+    fn get(&mut self, reg: Register) -> Value {
+        self.uses[self.pc].push(reg);
+        let block = self.block.unwrap();
+        self.read_variable(reg, block)
+    }
+
+    fn target(&self, idx: BytecodeIdx) -> Block {
+        self.blocks[&idx.0]
+    }
+
+    fn translate(&mut self, bytecode: &Bytecode) {
+        match *bytecode {
+            Bytecode::AddInt(dest, lhs, rhs)
+            | Bytecode::AddLong(dest, lhs, rhs)
+            | Bytecode::AddFloat(dest, lhs, rhs)
+            | Bytecode::AddDouble(dest, lhs, rhs) => self.binary(BinOp::Add, dest, lhs, rhs),
+
+            Bytecode::SubInt(dest, lhs, rhs)
+            | Bytecode::SubLong(dest, lhs, rhs)
+            | Bytecode::SubFloat(dest, lhs, rhs)
+            | Bytecode::SubDouble(dest, lhs, rhs) => self.binary(BinOp::Sub, dest, lhs, rhs),
+
+            Bytecode::MulInt(dest, lhs, rhs)
+            | Bytecode::MulLong(dest, lhs, rhs)
+            | Bytecode::MulFloat(dest, lhs, rhs)
+            | Bytecode::MulDouble(dest, lhs, rhs) => self.binary(BinOp::Mul, dest, lhs, rhs),
+
+            Bytecode::DivInt(dest, lhs, rhs)
+            | Bytecode::DivLong(dest, lhs, rhs)
+            | Bytecode::DivFloat(dest, lhs, rhs)
+            | Bytecode::DivDouble(dest, lhs, rhs) => self.binary(BinOp::Div, dest, lhs, rhs),
+
+            Bytecode::ModInt(dest, lhs, rhs) | Bytecode::ModLong(dest, lhs, rhs) => {
+                self.binary(BinOp::Mod, dest, lhs, rhs)
+            }
+
+            Bytecode::AndInt(dest, lhs, rhs) | Bytecode::AndLong(dest, lhs, rhs) => {
+                self.binary(BinOp::And, dest, lhs, rhs)
+            }
+
+            Bytecode::OrInt(dest, lhs, rhs) | Bytecode::OrLong(dest, lhs, rhs) => {
+                self.binary(BinOp::Or, dest, lhs, rhs)
+            }
+
+            Bytecode::XorInt(dest, lhs, rhs) | Bytecode::XorLong(dest, lhs, rhs) => {
+                self.binary(BinOp::Xor, dest, lhs, rhs)
+            }
+
+            Bytecode::ShlInt(dest, lhs, rhs) | Bytecode::ShlLong(dest, lhs, rhs) => {
+                self.binary(BinOp::Shl, dest, lhs, rhs)
+            }
+
+            Bytecode::ShrInt(dest, lhs, rhs) | Bytecode::ShrLong(dest, lhs, rhs) => {
+                self.binary(BinOp::Shr, dest, lhs, rhs)
+            }
+
+            Bytecode::SarInt(dest, lhs, rhs) | Bytecode::SarLong(dest, lhs, rhs) => {
+                self.binary(BinOp::Sar, dest, lhs, rhs)
+            }
+
+            Bytecode::NegInt(dest, src)
+            | Bytecode::NegLong(dest, src)
+            | Bytecode::NegFloat(dest, src)
+            | Bytecode::NegDouble(dest, src) => self.unary(UnOp::Neg, dest, src),
+
+            Bytecode::NotBool(dest, src)
+            | Bytecode::NotByte(dest, src)
+            | Bytecode::NotInt(dest, src)
+            | Bytecode::NotLong(dest, src) => self.unary(UnOp::Not, dest, src),
+
+            Bytecode::SqrtFloat(dest, src) | Bytecode::SqrtDouble(dest, src) => {
+                self.unary(UnOp::Sqrt, dest, src)
+            }
+
+            Bytecode::ExtendByteToInt(dest, src)
+            | Bytecode::ExtendByteToLong(dest, src)
+            | Bytecode::ExtendCharToLong(dest, src) => self.convert(ConvOp::ZeroExtend, dest, src),
+            Bytecode::ExtendIntToLong(dest, src) => self.convert(ConvOp::SignExtend, dest, src),
+
+            Bytecode::CastCharToInt(dest, src) | Bytecode::CastIntToChar(dest, src) => {
+                self.convert(ConvOp::Reinterpret, dest, src)
+            }
+
+            Bytecode::CastIntToByte(dest, src)
+            | Bytecode::CastLongToByte(dest, src)
+            | Bytecode::CastLongToChar(dest, src)
+            | Bytecode::CastLongToInt(dest, src) => self.convert(ConvOp::Truncate, dest, src),
+
+            Bytecode::ConvertIntToFloat(dest, src)
+            | Bytecode::ConvertIntToDouble(dest, src)
+            | Bytecode::ConvertLongToFloat(dest, src)
+            | Bytecode::ConvertLongToDouble(dest, src) => {
+                self.convert(ConvOp::IntToFloat, dest, src)
+            }
+
+            Bytecode::TruncateFloatToInt(dest, src)
+            | Bytecode::TruncateFloatToLong(dest, src)
+            | Bytecode::TruncateDoubleToInt(dest, src)
+            | Bytecode::TruncateDoubleToLong(dest, src) => {
+                self.convert(ConvOp::FloatToInt, dest, src)
+            }
+
+            Bytecode::PromoteFloatToDouble(dest, src) => {
+                self.convert(ConvOp::FloatExtend, dest, src)
+            }
+            Bytecode::DemoteDoubleToFloat(dest, src) => {
+                self.convert(ConvOp::FloatTruncate, dest, src)
+            }
+
+            Bytecode::ReinterpretFloatAsInt(dest, src)
+            | Bytecode::ReinterpretIntAsFloat(dest, src)
+            | Bytecode::ReinterpretDoubleAsLong(dest, src)
+            | Bytecode::ReinterpretLongAsDouble(dest, src) => {
+                self.convert(ConvOp::Reinterpret, dest, src)
+            }
+
+            Bytecode::MovBool(dest, src)
+            | Bytecode::MovByte(dest, src)
+            | Bytecode::MovChar(dest, src)
+            | Bytecode::MovInt(dest, src)
+            | Bytecode::MovLong(dest, src)
+            | Bytecode::MovFloat(dest, src)
+            | Bytecode::MovDouble(dest, src)
+            | Bytecode::MovPtr(dest, src) => {
+                let value = self.get(src);
+                self.set(dest, value);
+            }
+
+            Bytecode::LoadFieldBool(dest, obj, cls, field)
+            | Bytecode::LoadFieldByte(dest, obj, cls, field)
+            | Bytecode::LoadFieldChar(dest, obj, cls, field)
+            | Bytecode::LoadFieldInt(dest, obj, cls, field)
+            | Bytecode::LoadFieldLong(dest, obj, cls, field)
+            | Bytecode::LoadFieldFloat(dest, obj, cls, field)
+            | Bytecode::LoadFieldDouble(dest, obj, cls, field)
+            | Bytecode::LoadFieldPtr(dest, obj, cls, field) => {
+                let obj = self.nil_check(obj);
+                let ty = self.ty(dest);
+                self.def(
+                    dest,
+                    InstData::LoadField {
+                        ty: ty,
+                        obj: obj,
+                        cls: cls,
+                        field: field,
+                    },
+                );
+            }
+
+            Bytecode::StoreFieldBool(src, obj, cls, field)
+            | Bytecode::StoreFieldByte(src, obj, cls, field)
+            | Bytecode::StoreFieldChar(src, obj, cls, field)
+            | Bytecode::StoreFieldInt(src, obj, cls, field)
+            | Bytecode::StoreFieldLong(src, obj, cls, field)
+            | Bytecode::StoreFieldFloat(src, obj, cls, field)
+            | Bytecode::StoreFieldDouble(src, obj, cls, field)
+            | Bytecode::StoreFieldPtr(src, obj, cls, field) => {
+                let obj = self.nil_check(obj);
+                let value = self.get(src);
+                self.ins(InstData::StoreField {
+                    ty: self.ty(src),
+                    obj: obj,
+                    cls: cls,
+                    field: field,
+                    value: value,
+                });
+            }
+
+            Bytecode::LoadGlobalBool(dest, global)
+            | Bytecode::LoadGlobalByte(dest, global)
+            | Bytecode::LoadGlobalChar(dest, global)
+            | Bytecode::LoadGlobalInt(dest, global)
+            | Bytecode::LoadGlobalLong(dest, global)
+            | Bytecode::LoadGlobalFloat(dest, global)
+            | Bytecode::LoadGlobalDouble(dest, global)
+            | Bytecode::LoadGlobalPtr(dest, global) => {
+                let ty = self.ty(dest);
+                self.def(
+                    dest,
+                    InstData::LoadGlobal {
+                        ty: ty,
+                        global: global,
+                    },
+                );
+            }
+
+            Bytecode::StoreGlobalBool(src, global)
+            | Bytecode::StoreGlobalByte(src, global)
+            | Bytecode::StoreGlobalChar(src, global)
+            | Bytecode::StoreGlobalInt(src, global)
+            | Bytecode::StoreGlobalLong(src, global)
+            | Bytecode::StoreGlobalFloat(src, global)
+            | Bytecode::StoreGlobalDouble(src, global)
+            | Bytecode::StoreGlobalPtr(src, global) => {
+                let value = self.get(src);
+                self.ins(InstData::StoreGlobal {
+                    ty: self.ty(src),
+                    global: global,
+                    value: value,
+                });
+            }
+
+            Bytecode::LoadArrayBool(dest, arr, idx)
+            | Bytecode::LoadArrayByte(dest, arr, idx)
+            | Bytecode::LoadArrayChar(dest, arr, idx)
+            | Bytecode::LoadArrayInt(dest, arr, idx)
+            | Bytecode::LoadArrayLong(dest, arr, idx)
+            | Bytecode::LoadArrayFloat(dest, arr, idx)
+            | Bytecode::LoadArrayDouble(dest, arr, idx)
+            | Bytecode::LoadArrayPtr(dest, arr, idx) => {
+                let (array, index) = self.array_element(arr, idx);
+                let ty = self.ty(dest);
+                self.def(
+                    dest,
+                    InstData::LoadArray {
+                        ty: ty,
+                        array: array,
+                        index: index,
+                    },
+                );
+            }
+
+            Bytecode::StoreArrayBool(src, arr, idx)
+            | Bytecode::StoreArrayByte(src, arr, idx)
+            | Bytecode::StoreArrayChar(src, arr, idx)
+            | Bytecode::StoreArrayInt(src, arr, idx)
+            | Bytecode::StoreArrayLong(src, arr, idx)
+            | Bytecode::StoreArrayFloat(src, arr, idx)
+            | Bytecode::StoreArrayDouble(src, arr, idx)
+            | Bytecode::StoreArrayPtr(src, arr, idx) => {
+                let (array, index) = self.array_element(arr, idx);
+                let value = self.get(src);
+                self.ins(InstData::StoreArray {
+                    ty: self.ty(src),
+                    array: array,
+                    index: index,
+                    value: value,
+                });
+            }
+
+            Bytecode::ArrayLength(dest, arr) => {
+                let array = self.nil_check(arr);
+                self.def(dest, InstData::ArrayLength { array: array });
+            }
+
+            Bytecode::SetUint8(address, value) => {
+                let address = self.get(address);
+                let value = self.get(value);
+                self.ins(InstData::SetUint8 {
+                    address: address,
+                    value: value,
+                });
+            }
+
+            Bytecode::ConstNil(dest) => self.def(dest, InstData::NilConst),
+            Bytecode::ConstTrue(dest) => self.def(dest, InstData::TrueConst),
+            Bytecode::ConstFalse(dest) => self.def(dest, InstData::FalseConst),
+            Bytecode::ConstZeroByte(dest) => self.def(dest, InstData::Int8Const(0)),
+            Bytecode::ConstZeroInt(dest) => self.def(dest, InstData::Int32Const(0)),
+            Bytecode::ConstZeroLong(dest) => self.def(dest, InstData::Int64Const(0)),
+            Bytecode::ConstZeroFloat(dest) => self.def(dest, InstData::Float32Const(0.0)),
+            Bytecode::ConstZeroDouble(dest) => self.def(dest, InstData::Float64Const(0.0)),
+            Bytecode::ConstChar(dest, value) => self.def(dest, InstData::CharConst(value)),
+            Bytecode::ConstByte(dest, value) => self.def(dest, InstData::Int8Const(value)),
+            Bytecode::ConstInt(dest, value) => self.def(dest, InstData::Int32Const(value as i32)),
+            Bytecode::ConstLong(dest, value) => self.def(dest, InstData::Int64Const(value as i64)),
+            Bytecode::ConstFloat(dest, value) => self.def(dest, InstData::Float32Const(value)),
+            Bytecode::ConstDouble(dest, value) => self.def(dest, InstData::Float64Const(value)),
+            Bytecode::ConstString(dest, idx) => {
+                let value = self.bytecode.string(idx).clone();
+                self.def(dest, InstData::StringConst(value))
+            }
+
+            Bytecode::TestEqPtr(dest, lhs, rhs)
+            | Bytecode::TestEqBool(dest, lhs, rhs)
+            | Bytecode::TestEqByte(dest, lhs, rhs)
+            | Bytecode::TestEqChar(dest, lhs, rhs)
+            | Bytecode::TestEqInt(dest, lhs, rhs)
+            | Bytecode::TestEqLong(dest, lhs, rhs)
+            | Bytecode::TestEqFloat(dest, lhs, rhs)
+            | Bytecode::TestEqDouble(dest, lhs, rhs) => self.cmp(CmpOp::Eq, dest, lhs, rhs),
+
+            Bytecode::TestNePtr(dest, lhs, rhs)
+            | Bytecode::TestNeBool(dest, lhs, rhs)
+            | Bytecode::TestNeByte(dest, lhs, rhs)
+            | Bytecode::TestNeChar(dest, lhs, rhs)
+            | Bytecode::TestNeInt(dest, lhs, rhs)
+            | Bytecode::TestNeLong(dest, lhs, rhs)
+            | Bytecode::TestNeFloat(dest, lhs, rhs)
+            | Bytecode::TestNeDouble(dest, lhs, rhs) => self.cmp(CmpOp::Ne, dest, lhs, rhs),
+
+            Bytecode::TestGtByte(dest, lhs, rhs)
+            | Bytecode::TestGtChar(dest, lhs, rhs)
+            | Bytecode::TestGtInt(dest, lhs, rhs)
+            | Bytecode::TestGtLong(dest, lhs, rhs)
+            | Bytecode::TestGtFloat(dest, lhs, rhs)
+            | Bytecode::TestGtDouble(dest, lhs, rhs) => self.cmp(CmpOp::Gt, dest, lhs, rhs),
+
+            Bytecode::TestGeByte(dest, lhs, rhs)
+            | Bytecode::TestGeChar(dest, lhs, rhs)
+            | Bytecode::TestGeInt(dest, lhs, rhs)
+            | Bytecode::TestGeLong(dest, lhs, rhs)
+            | Bytecode::TestGeFloat(dest, lhs, rhs)
+            | Bytecode::TestGeDouble(dest, lhs, rhs) => self.cmp(CmpOp::Ge, dest, lhs, rhs),
+
+            Bytecode::TestLtByte(dest, lhs, rhs)
+            | Bytecode::TestLtChar(dest, lhs, rhs)
+            | Bytecode::TestLtInt(dest, lhs, rhs)
+            | Bytecode::TestLtLong(dest, lhs, rhs)
+            | Bytecode::TestLtFloat(dest, lhs, rhs)
+            | Bytecode::TestLtDouble(dest, lhs, rhs) => self.cmp(CmpOp::Lt, dest, lhs, rhs),
+
+            Bytecode::TestLeByte(dest, lhs, rhs)
+            | Bytecode::TestLeChar(dest, lhs, rhs)
+            | Bytecode::TestLeInt(dest, lhs, rhs)
+            | Bytecode::TestLeLong(dest, lhs, rhs)
+            | Bytecode::TestLeFloat(dest, lhs, rhs)
+            | Bytecode::TestLeDouble(dest, lhs, rhs) => self.cmp(CmpOp::Le, dest, lhs, rhs),
+
+            Bytecode::JumpIfFalse(opnd, target) => {
+                let fallthrough = self.blocks[&(self.pc + 1)];
+                let target = self.target(target);
+                self.branch(opnd, fallthrough, target);
+            }
+
+            Bytecode::JumpIfTrue(opnd, target) => {
+                let fallthrough = self.blocks[&(self.pc + 1)];
+                let target = self.target(target);
+                self.branch(opnd, target, fallthrough);
+            }
+
+            Bytecode::Jump(target) => {
+                let target = self.target(target);
+                self.ins(InstData::Goto { target: target });
+            }
+
+            Bytecode::InvokeDirectVoid(fct_id, start, num) => {
+                let kind = self.direct_call(fct_id);
+                self.call(None, kind, start, num);
+            }
+
+            Bytecode::InvokeDirectBool(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectByte(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectChar(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectInt(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectLong(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectFloat(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectDouble(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectPtr(dest, fct_id, start, num) => {
+                let kind = self.direct_call(fct_id);
+                self.call(Some(dest), kind, start, num);
+            }
+
+            Bytecode::InvokeVirtualVoid(fct_id, start, num) => {
+                self.call(None, CallKind::Virtual(fct_id), start, num);
+            }
+
+            Bytecode::InvokeVirtualBool(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualByte(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualChar(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualInt(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualLong(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualFloat(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualDouble(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualPtr(dest, fct_id, start, num) => {
+                self.call(Some(dest), CallKind::Virtual(fct_id), start, num);
+            }
+
+            Bytecode::InvokeStaticVoid(fct_id, start, num) => {
+                let kind = self.static_call(fct_id);
+                self.call(None, kind, start, num);
+            }
+
+            Bytecode::InvokeStaticBool(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticByte(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticChar(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticInt(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticLong(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticFloat(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticDouble(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticPtr(dest, fct_id, start, num) => {
+                let kind = self.static_call(fct_id);
+                self.call(Some(dest), kind, start, num);
+            }
+
+            Bytecode::InvokeLambdaVoid(start, num) => {
+                self.call(None, CallKind::Lambda, start, num);
+            }
+
+            Bytecode::InvokeLambdaBool(dest, start, num)
+            | Bytecode::InvokeLambdaByte(dest, start, num)
+            | Bytecode::InvokeLambdaChar(dest, start, num)
+            | Bytecode::InvokeLambdaInt(dest, start, num)
+            | Bytecode::InvokeLambdaLong(dest, start, num)
+            | Bytecode::InvokeLambdaFloat(dest, start, num)
+            | Bytecode::InvokeLambdaDouble(dest, start, num)
+            | Bytecode::InvokeLambdaPtr(dest, start, num) => {
+                self.call(Some(dest), CallKind::Lambda, start, num);
+            }
+
+            Bytecode::NewObject(dest, cls) => self.def(dest, InstData::NewObject { cls: cls }),
+
+            Bytecode::NewArray(dest, cls, length) => {
+                let length = self.get(length);
+                self.def(
+                    dest,
+                    InstData::NewArray {
+                        cls: cls,
+                        length: length,
+                    },
+                );
+            }
+
+            Bytecode::NilCheck(obj) => {
+                self.nil_check(obj);
+            }
+
+            Bytecode::InstanceOf(dest, src, cls) => {
+                let opnd = self.get(src);
+                self.def(
+                    dest,
+                    InstData::InstanceOf {
+                        opnd: opnd,
+                        cls: cls,
+                    },
+                );
+            }
+
+            Bytecode::CheckedCast(src, cls) => {
+                let opnd = self.get(src);
+                self.ins(InstData::CheckedCast {
+                    opnd: opnd,
+                    cls: cls,
+                });
+            }
+
+            Bytecode::Throw(src) => {
+                let opnd = self.get(src);
+                self.ins(InstData::Throw { opnd: opnd });
+            }
+
+            Bytecode::Trap(trap) => {
+                self.ins(InstData::Trap { trap: trap });
+            }
+
+            Bytecode::Spawn(src) => {
+                let opnd = self.get(src);
+                self.ins(InstData::Spawn { opnd: opnd });
+            }
+
+            Bytecode::Debug => {
+                self.ins(InstData::Debug);
+            }
+
+            Bytecode::RetBool(src)
+            | Bytecode::RetByte(src)
+            | Bytecode::RetChar(src)
+            | Bytecode::RetInt(src)
+            | Bytecode::RetLong(src)
+            | Bytecode::RetFloat(src)
+            | Bytecode::RetDouble(src)
+            | Bytecode::RetPtr(src) => {
+                let opnd = self.get(src);
+                self.ins(InstData::Ret { opnd: Some(opnd) });
+            }
+
+            Bytecode::RetVoid => {
+                self.ins(InstData::Ret { opnd: None });
+            }
+        }
+    }
+
+    fn binary(&mut self, op: BinOp, dest: Register, lhs: Register, rhs: Register) {
+        let lhs = self.get(lhs);
+        let rhs = self.get(rhs);
+        let ty = self.ty(dest);
+
+        self.def(
+            dest,
+            InstData::Binary {
+                op: op,
+                ty: ty,
+                lhs: lhs,
+                rhs: rhs,
+            },
+        );
+    }
+
+    fn unary(&mut self, op: UnOp, dest: Register, src: Register) {
+        let opnd = self.get(src);
+        let ty = self.ty(dest);
+
+        self.def(
+            dest,
+            InstData::Unary {
+                op: op,
+                ty: ty,
+                opnd: opnd,
+            },
+        );
+    }
+
+    fn convert(&mut self, op: ConvOp, dest: Register, src: Register) {
+        let opnd = self.get(src);
+        let from = self.ty(src);
+        let to = self.ty(dest);
+
+        self.def(
+            dest,
+            InstData::Convert {
+                op: op,
+                from: from,
+                to: to,
+                opnd: opnd,
+            },
+        );
+    }
+
+    fn cmp(&mut self, op: CmpOp, dest: Register, lhs: Register, rhs: Register) {
+        let ty = self.ty(lhs);
+        let lhs = self.get(lhs);
+        let rhs = self.get(rhs);
+
+        self.def(
+            dest,
+            InstData::Cmp {
+                ty: ty,
+                op: op,
+                lhs: lhs,
+                rhs: rhs,
+            },
+        );
+    }
+
+    fn branch(&mut self, opnd: Register, then_block: Block, else_block: Block) {
+        if then_block == else_block {
+            self.ins(InstData::Goto { target: then_block });
+            return;
+        }
+
+        let opnd = self.get(opnd);
+        self.ins(InstData::If {
+            opnd: opnd,
+            then_block: then_block,
+            else_block: else_block,
+        });
+    }
+
+    fn nil_check(&mut self, obj: Register) -> Value {
+        let opnd = self.get(obj);
+        self.ins(InstData::NilCheck { opnd: opnd });
+        opnd
+    }
+
+    fn array_element(&mut self, arr: Register, idx: Register) -> (Value, Value) {
+        let array = self.nil_check(arr);
+        let index = self.get(idx);
+
+        if !self.vm.args.flag_omit_bounds_check {
+            let length = self.ins_value(InstData::ArrayLength { array: array });
+            self.ins(InstData::BoundsCheck {
+                index: index,
+                length: length,
+            });
+        }
+
+        (array, index)
+    }
+
+    fn direct_call(&self, fct_id: FctId) -> CallKind {
+        let (cls_type_params, fct_type_params) = self.bytecode.type_params(BytecodeIdx(self.pc));
+        CallKind::Direct(fct_id, cls_type_params, fct_type_params)
+    }
+
+    fn static_call(&self, fct_id: FctId) -> CallKind {
+        let (cls_type_params, fct_type_params) = self.bytecode.type_params(BytecodeIdx(self.pc));
+        CallKind::Static(fct_id, cls_type_params, fct_type_params)
+    }
+
+    fn call(&mut self, dest: Option<Register>, kind: CallKind, start: Register, num: usize) {
+        let kind = match kind {
+            CallKind::Virtual(fct_id) => match self.receiver_class(fct_id) {
+                Some((cls_id, target_id)) => {
+                    self.guard_class(start, cls_id);
+                    CallKind::Direct(target_id, TypeParams::empty(), TypeParams::empty())
+                }
+
+                None => CallKind::Virtual(fct_id),
+            },
+
+            kind => kind,
+        };
+
+        let args = (0..num).map(|idx| self.get(start.offset(idx))).collect();
+        let inst_data = InstData::Call {
+            kind: kind,
+            ty: dest.map(|dest| self.ty(dest)),
+            args: args,
+        };
+
+        match dest {
+            Some(dest) => self.def(dest, inst_data),
+            None => {
+                self.ins(inst_data);
+            }
+        }
+    }
+
+    // the class the receiver of a virtual call of `fct_id` is expected to have and the
+    // method called for it: without overrides every receiver calls `fct_id`, otherwise
+    // the inline cache of the call site has to have seen a single receiver class
+    fn receiver_class(&self, fct_id: FctId) -> Option<(ClassDefId, FctId)> {
+        if let Some(cls_id) = monomorphic_class(self.vm, fct_id) {
+            return Some((cls_id, fct_id));
+        }
+
+        let src = self.src?;
+        let pos = self.bytecode.position(BytecodeIdx(self.pc));
+
+        let cls_def_id = match ic::feedback(src, pos) {
+            Feedback::Monomorphic(cls_def_id) => cls_def_id,
+            _ => return None,
+        };
+
+        let vtable_index = self.vm.fcts.idx(fct_id).read().vtable_index?;
+        let cls_id = self.vm.class_defs.idx(cls_def_id).read().cls_id?;
+
+        let cls = self.vm.classes.idx(cls_id);
+        let cls = cls.read();
+
+        if cls.is_generic() {
+            return None;
+        }
+
+        let target_id = cls.virtual_fcts[vtable_index as usize];
+
+        if !self.vm.fcts.idx(target_id).read().type_params.is_empty() {
+            return None;
+        }
+
+        Some((cls_def_id, target_id))
+    }
+
+    // the method is called directly for objects of class `cls_id`, objects of other
+    // classes leave the optimized code and run the call in baseline code
+    fn guard_class(&mut self, receiver: Register, cls_id: ClassDefId) {
+        let receiver = self.nil_check(receiver);
+        let has_class = self.ins_value(InstData::HasClass {
+            opnd: receiver,
+            cls: cls_id,
+        });
+
+        let block = self.block.unwrap();
+        let call_block = self.fct.make_block();
+        let deopt_block = self.fct.make_block();
+
+        self.ins(InstData::If {
+            opnd: has_class,
+            then_block: call_block,
+            else_block: deopt_block,
+        });
+
+        self.seal(call_block);
+        self.seal(deopt_block);
+        self.deopts.push((block, deopt_block, self.pc));
+        self.block = Some(call_block);
+    }
+
+    // the frame state of a class check contains the registers live at the call,
+    // their values are the values at the end of the block of the check
+    fn emit_deopts(&mut self, starts: &[usize], successors: &HashMap<usize, Vec<usize>>) {
+        let live_in = self.liveness(starts, successors);
+
+        for (block, deopt_block, pc) in self.deopts.clone() {
+            let idx = match starts.binary_search(&pc) {
+                Ok(idx) => idx,
+                Err(idx) => idx - 1,
+            };
+            let end = starts.get(idx + 1).cloned().unwrap_or(self.uses.len());
+            let mut live = live_out(&live_in, &successors[&starts[idx]]);
+
+            for pc in (pc..end).rev() {
+                self.transfer(pc, &mut live);
+            }
+
+            let mut regs: Vec<usize> = live.into_iter().collect();
+            regs.sort();
+
+            let state = regs
+                .into_iter()
+                .map(|reg| (reg as u32, self.read_variable(Register(reg), block)))
+                .collect();
+
+            let inst = self.fct.append_inst(
+                deopt_block,
+                InstData::Deoptimize {
+                    pc: pc as u32,
+                    state: state,
+                },
+            );
+            let pos = self.bytecode.position(BytecodeIdx(pc));
+            self.fct.dfg.set_position(inst, pos);
+        }
+    }
+
+    // registers live at the start of the translated blocks, a register is live
+    // if it is read before it is written on some path
+    fn liveness(
+        &self,
+        starts: &[usize],
+        successors: &HashMap<usize, Vec<usize>>,
+    ) -> HashMap<usize, HashSet<usize>> {
+        let mut live_in: HashMap<usize, HashSet<usize>> = HashMap::new();
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for (idx, &start) in starts.iter().enumerate().rev() {
+                if !self.blocks.contains_key(&start) {
+                    continue;
+                }
+
+                let end = starts.get(idx + 1).cloned().unwrap_or(self.uses.len());
+                let mut live = live_out(&live_in, &successors[&start]);
+
+                for pc in (start..end).rev() {
+                    self.transfer(pc, &mut live);
+                }
+
+                if live_in.get(&start) != Some(&live) {
+                    live_in.insert(start, live);
+                    changed = true;
+                }
+            }
+        }
+
+        live_in
+    }
+
+    fn transfer(&self, pc: usize, live: &mut HashSet<usize>) {
+        for reg in &self.defs[pc] {
+            live.remove(&reg.0);
+        }
+
+        for reg in &self.uses[pc] {
+            live.insert(reg.0);
+        }
+    }
+}
+
+fn live_out(live_in: &HashMap<usize, HashSet<usize>>, successors: &[usize]) -> HashSet<usize> {
+    successors
+        .iter()
+        .filter_map(|succ| live_in.get(succ))
+        .flat_map(|live| live.iter().cloned())
+        .collect()
+}
+
+// virtual calls of a method that is not overridden by any subclass always invoke
+// the method for objects of its class, returns the class if it can be checked
+fn monomorphic_class(vm: &VM, fct_id: FctId) -> Option<ClassDefId> {
+    let cls_id = {
+        let fct = vm.fcts.idx(fct_id);
+        let fct = fct.read();
+
+        if !fct.type_params.is_empty() {
+            return None;
+        }
+
+        match fct.parent {
+            FctParent::Class(cls_id) => cls_id,
+            _ => return None,
+        }
+    };
+
+    {
+        let cls = vm.classes.idx(cls_id);
+        let cls = cls.read();
+
+        if cls.is_generic() || cls.is_abstract {
+            return None;
+        }
+    }
+
+    if cha::has_overrides(vm, fct_id) {
+        return None;
+    }
+
+    Some(specialize_class_id(vm, cls_id))
+}
+
+// indices of the first instruction of every block
+fn block_starts(code: &[Bytecode]) -> Vec<usize> {
+    let mut starts = vec![0];
+
+    for (pc, bytecode) in code.iter().enumerate() {
+        match *bytecode {
+            Bytecode::Jump(target)
+            | Bytecode::JumpIfFalse(_, target)
+            | Bytecode::JumpIfTrue(_, target) => {
+                starts.push(target.0);
+                starts.push(pc + 1);
+            }
+
+            _ if ends_block(bytecode) => starts.push(pc + 1),
+            _ => {}
+        }
+    }
+
+    starts.retain(|&start| start < code.len());
+    starts.sort();
+    starts.dedup();
+    starts
+}
+
+fn ends_block(bytecode: &Bytecode) -> bool {
+    match *bytecode {
+        Bytecode::Jump(_)
+        | Bytecode::JumpIfFalse(_, _)
+        | Bytecode::JumpIfTrue(_, _)
+        | Bytecode::Throw(_)
+        | Bytecode::Trap(_)
+        | Bytecode::RetBool(_)
+        | Bytecode::RetByte(_)
+        | Bytecode::RetChar(_)
+        | Bytecode::RetInt(_)
+        | Bytecode::RetLong(_)
+        | Bytecode::RetFloat(_)
+        | Bytecode::RetDouble(_)
+        | Bytecode::RetPtr(_)
+        | Bytecode::RetVoid => true,
+        _ => false,
+    }
+}
+
+// successors of the block ending before `end`
+fn block_successors(code: &[Bytecode], end: usize) -> Vec<usize> {
+    let mut successors = match code[end - 1] {
+        Bytecode::Jump(target) => vec![target.0],
+        Bytecode::JumpIfFalse(_, target) | Bytecode::JumpIfTrue(_, target) => vec![end, target.0],
+        ref bytecode if ends_block(bytecode) => Vec::new(),
+        _ => vec![end],
+    };
+
+    successors.dedup();
+    successors
+}
+
+fn reachable_starts(successors: &HashMap<usize, Vec<usize>>, start: usize) -> Vec<usize> {
+    let mut reachable = HashSet::new();
+    let mut worklist = vec![start];
+
+    while let Some(start) = worklist.pop() {
+        if !reachable.insert(start) {
+            continue;
+        }
+
+        if let Some(succs) = successors.get(&start) {
+            worklist.extend(succs.iter().cloned());
+        }
+    }
+
+    let mut reachable: Vec<usize> = reachable.into_iter().collect();
+    reachable.sort();
+    reachable
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::boots::function::{Function, Type};
+    use crate::boots::inst::{BinOp, CallKind, InstData};
+    use crate::boots::ssagen;
+    use crate::boots::verifier;
+    use crate::bytecode::astgen;
+    use crate::class::TypeParams;
+    use crate::test;
+
+    fn ssa<F>(code: &'static str, testfct: F)
+    where
+        F: FnOnce(Result<Function, String>),
+    {
+        test::parse(code, |vm| {
+            let fct_id = vm.fct_by_name("f").expect("no function `f`.");
+            let tp = TypeParams::empty();
+            let bytecode = astgen::generate(vm, fct_id, &tp, &tp);
+            let fct = vm.fcts.idx(fct_id);
+            let fct = fct.read();
+
+            testfct(ssagen::generate(vm, &fct, &bytecode));
+        })
+    }
+
+    fn phis(fct: &Function) -> Vec<&InstData> {
+        fct.blocks()
+            .iter()
+            .flat_map(|&block| fct.insts(block))
+            .map(|inst| fct.inst(inst))
+            .filter(|inst| inst.is_phi())
+            .collect()
+    }
+
+    #[test]
+    fn ssa_straight_line() {
+        ssa(
+            "fun f(a: Int, b: Int) -> Int { let c = a + b; return c; }",
+            |fct| {
+                let fct = fct.unwrap();
+                assert_eq!(2, fct.blocks().len());
+                assert!(phis(&fct).is_empty());
+
+                let body = fct.blocks()[1];
+                let insts: Vec<_> = fct.insts(body).map(|inst| fct.inst(inst)).collect();
+
+                match insts[0] {
+                    InstData::Binary {
+                        op: BinOp::Add,
+                        ty: Type::Int,
+                        ..
+                    } => {}
+                    _ => panic!("add expected"),
+                }
+
+                match insts[1] {
+                    InstData::Ret { opnd: Some(_) } => {}
+                    _ => panic!("ret expected"),
+                }
+
+                assert_eq!(Ok(()), verifier::verify(&fct));
+            },
+        );
+    }
+
+    #[test]
+    fn ssa_if_merges_with_phi() {
+        ssa(
+            "fun f(a: Bool) -> Int { var x = 1; if a { x = 2; } return x; }",
+            |fct| {
+                let fct = fct.unwrap();
+                let phis = phis(&fct);
+                assert_eq!(1, phis.len());
+
+                match phis[0] {
+                    InstData::Phi { ty, inputs } => {
+                        assert_eq!(Type::Int, *ty);
+                        assert_eq!(2, inputs.len());
+                    }
+                    _ => unreachable!(),
+                }
+
+                assert_eq!(Ok(()), verifier::verify(&fct));
+            },
+        );
+    }
+
+    #[test]
+    fn ssa_loop() {
+        ssa(
+            "fun f(n: Int) -> Int {
+                var i = 0;
+                var sum = 0;
+                while i < n { sum = sum + i; i = i + 1; }
+                return sum;
+            }",
+            |fct| {
+                let fct = fct.unwrap();
+                assert_eq!(2, phis(&fct).len());
+                assert_eq!(Ok(()), verifier::verify(&fct));
+            },
+        );
+    }
+
+    #[test]
+    fn ssa_loop_invariant_without_phi() {
+        ssa(
+            "fun f(n: Int) -> Int {
+                var i = 0;
+                let k = n * 2;
+                while i < n { i = i + k; }
+                return k;
+            }",
+            |fct| {
+                let fct = fct.unwrap();
+                assert_eq!(1, phis(&fct).len());
+                assert_eq!(Ok(()), verifier::verify(&fct));
+            },
+        );
+    }
+
+    #[test]
+    fn ssa_array_access() {
+        ssa(
+            "fun f(a: Array[Int], i: Int) -> Int { return a(i); }",
+            |fct| {
+                let fct = fct.unwrap();
+                let body = fct.blocks()[1];
+                let has_bounds_check = fct.insts(body).any(|inst| match fct.inst(inst) {
+                    InstData::BoundsCheck { .. } => true,
+                    _ => false,
+                });
+                assert!(has_bounds_check);
+                assert_eq!(Ok(()), verifier::verify(&fct));
+            },
+        );
+    }
+
+    #[test]
+    fn ssa_guards_calls_of_methods_without_overrides() {
+        ssa(
+            "fun f(foo: Foo, x: Int) -> Int { let y = x + 1; let v = foo.value(); return v + y; }
+            @open class Foo { @open fun value() -> Int = 1; }",
+            |fct| {
+                let fct = fct.unwrap();
+                let insts: Vec<_> = fct
+                    .blocks()
+                    .iter()
+                    .flat_map(|&block| fct.insts(block))
+                    .map(|inst| fct.inst(inst))
+                    .collect();
+
+                assert!(insts.iter().any(|inst| match inst {
+                    InstData::HasClass { .. } => true,
+                    _ => false,
+                }));
+
+                assert!(insts.iter().any(|inst| match inst {
+                    InstData::Call {
+                        kind: CallKind::Direct(..),
+                        ..
+                    } => true,
+                    _ => false,
+                }));
+
+                let state = insts
+                    .iter()
+                    .filter_map(|inst| match inst {
+                        InstData::Deoptimize { state, .. } => Some(state),
+                        _ => None,
+                    })
+                    .next()
+                    .expect("deoptimize expected");
+
+                // `x` is dead after computing `y`
+                assert!(!state.is_empty());
+                assert!(state.iter().all(|&(reg, _)| reg != 1));
+                assert_eq!(Ok(()), verifier::verify(&fct));
+            },
+        );
+    }
+
+    #[test]
+    fn ssa_keeps_virtual_calls_of_overridden_methods() {
+        ssa(
+            "fun f(foo: Foo) -> Int { return foo.value(); }
+            @open class Foo { @open fun value() -> Int = 1; }
+            class Bar: Foo { @override fun value() -> Int = 2; }",
+            |fct| {
+                let fct = fct.unwrap();
+                let virtual_call = fct.blocks().iter().any(|&block| {
+                    fct.insts(block).any(|inst| match fct.inst(inst) {
+                        InstData::Call {
+                            kind: CallKind::Virtual(_),
+                            ..
+                        } => true,
+                        _ => false,
+                    })
+                });
+
+                assert!(virtual_call);
+                assert_eq!(Ok(()), verifier::verify(&fct));
+            },
+        );
+    }
+
+    #[test]
+    fn ssa_rejects_exception_handlers() {
+        ssa(
+            "fun g() throws {}
+            fun f() { do { try g(); } catch e: Exception {} }",
+            |fct| {
+                assert!(fct.is_err());
+            },
+        );
+    }
+}
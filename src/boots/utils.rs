@@ -25,6 +25,14 @@ where
         self.data.push(value);
         K::new(idx)
     }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = K> {
+        (0..self.data.len()).map(K::new)
+    }
 }
 
 impl<K, V> Index<K> for VecMap<K, V>
@@ -0,0 +1,458 @@
+use std::collections::hash_map::HashMap;
+
+use crate::boots::dom::DominatorTree;
+use crate::boots::function::{Block, Function, Type, Value};
+use crate::boots::inst::{BinOp, CallKind, Inst, InstData, UnOp};
+use crate::boots::printer::{inst_to_string, Names};
+
+// checks structure of the function: terminators, cfg edges, phi arity,
+// dominance of definitions over uses and operand types
+pub fn verify(fct: &Function) -> Result<(), String> {
+    let mut verifier = Verifier {
+        fct: fct,
+        names: Names::new(fct),
+        dom: DominatorTree::compute(fct),
+        positions: HashMap::new(),
+        return_type: None,
+    };
+
+    verifier.verify()
+}
+
+struct Verifier<'a> {
+    fct: &'a Function,
+    names: Names,
+    dom: DominatorTree,
+
+    // index of instruction in its block
+    positions: HashMap<Inst, usize>,
+
+    // type of the first return instruction
+    return_type: Option<Option<Type>>,
+}
+
+impl<'a> Verifier<'a> {
+    fn verify(&mut self) -> Result<(), String> {
+        if self.fct.entry_block().is_none() {
+            return Err("function without entry block".into());
+        }
+
+        for &block in self.fct.blocks() {
+            self.verify_block(block)?;
+        }
+
+        for &block in self.fct.blocks() {
+            for inst in self.fct.insts(block) {
+                self.verify_inst(block, inst)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn verify_block(&mut self, block: Block) -> Result<(), String> {
+        let name = self.names.block(block);
+        let mut phis_allowed = true;
+        let mut terminator = None;
+
+        for (idx, inst) in self.fct.insts(block).enumerate() {
+            let inst_data = self.fct.inst(inst);
+            self.positions.insert(inst, idx);
+
+            if let Some(terminator) = terminator {
+                return Err(self.inst_error(terminator, "terminator in the middle of block"));
+            }
+
+            match *inst_data {
+                InstData::Deleted => return Err(self.inst_error(inst, "deleted instruction")),
+                InstData::Phi { .. } if !phis_allowed => {
+                    return Err(self.inst_error(inst, "phi after other instructions"));
+                }
+                InstData::Phi { .. } => {}
+                _ => phis_allowed = false,
+            }
+
+            if inst_data.is_terminator() {
+                terminator = Some(inst);
+            }
+        }
+
+        let terminator = match terminator {
+            Some(terminator) => terminator,
+            None => return Err(format!("{}: block without terminator", name)),
+        };
+
+        let mut successors = self.fct.inst(terminator).successors();
+        let mut edges = self.fct.cfg.successors(block).to_vec();
+        successors.sort();
+        edges.sort();
+
+        if successors != edges {
+            return Err(format!("{}: edges do not match terminator", name));
+        }
+
+        Ok(())
+    }
+
+    fn verify_inst(&mut self, block: Block, inst: Inst) -> Result<(), String> {
+        let inst_data = self.fct.inst(inst);
+
+        if let InstData::Phi { ref inputs, .. } = *inst_data {
+            let mut blocks: Vec<Block> = inputs.iter().map(|&(block, _)| block).collect();
+            let mut predecessors = self.fct.cfg.predecessors(block).to_vec();
+            blocks.sort();
+            predecessors.sort();
+
+            if blocks != predecessors {
+                return Err(self.inst_error(inst, "phi inputs do not match predecessors"));
+            }
+
+            for &(pred, value) in inputs {
+                self.verify_dominance(inst, value, pred, None)?;
+            }
+        } else {
+            let position = self.positions[&inst];
+
+            for value in inst_data.operands() {
+                self.verify_dominance(inst, value, block, Some(position))?;
+            }
+        }
+
+        self.verify_types(inst, inst_data)
+    }
+
+    // the definition of value needs to dominate the use in block, for phis the
+    // use is at the end of the predecessor
+    fn verify_dominance(
+        &self,
+        inst: Inst,
+        value: Value,
+        block: Block,
+        position: Option<usize>,
+    ) -> Result<(), String> {
+        let def = self.fct.dfg.value_inst(value);
+
+        let def_block = match self.fct.cfg.inst_block(def) {
+            Some(def_block) => def_block,
+            None => {
+                let msg = format!(
+                    "operand {} not defined in function",
+                    self.names.value(value)
+                );
+                return Err(self.inst_error(inst, &msg));
+            }
+        };
+
+        if !self.dom.is_reachable(block) {
+            return Ok(());
+        }
+
+        let dominates = if def_block == block {
+            match position {
+                Some(position) => self.positions[&def] < position,
+                None => true,
+            }
+        } else {
+            self.dom.dominates(def_block, block)
+        };
+
+        if dominates {
+            Ok(())
+        } else {
+            let msg = format!(
+                "definition of {} does not dominate use",
+                self.names.value(value)
+            );
+            Err(self.inst_error(inst, &msg))
+        }
+    }
+
+    fn verify_types(&mut self, inst: Inst, inst_data: &InstData) -> Result<(), String> {
+        match *inst_data {
+            InstData::Binary { op, ty, lhs, rhs } => {
+                let valid = match op {
+                    BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod => {
+                        ty != Type::Bool && ty != Type::Ptr
+                    }
+                    BinOp::And | BinOp::Or | BinOp::Xor => !ty.is_float() && ty != Type::Ptr,
+                    BinOp::Shl | BinOp::Shr | BinOp::Sar => ty == Type::Int || ty == Type::Long,
+                };
+
+                if !valid {
+                    return Err(self.inst_error(inst, "invalid type for operation"));
+                }
+
+                self.expect_type(inst, lhs, ty)?;
+                self.expect_type(inst, rhs, ty)
+            }
+
+            InstData::Unary { op, ty, opnd } => {
+                let valid = match op {
+                    UnOp::Neg => ty != Type::Bool && ty != Type::Ptr,
+                    UnOp::Not => !ty.is_float() && ty != Type::Ptr,
+                    UnOp::Sqrt => ty.is_float(),
+                };
+
+                if !valid {
+                    return Err(self.inst_error(inst, "invalid type for operation"));
+                }
+
+                self.expect_type(inst, opnd, ty)
+            }
+
+            InstData::Convert { from, opnd, .. } => self.expect_type(inst, opnd, from),
+
+            InstData::Cmp { ty, lhs, rhs, .. } => {
+                self.expect_type(inst, lhs, ty)?;
+                self.expect_type(inst, rhs, ty)
+            }
+
+            InstData::If { opnd, .. } => self.expect_type(inst, opnd, Type::Bool),
+
+            InstData::Ret { opnd } => {
+                let ty = opnd.map(|opnd| self.fct.value_type(opnd));
+
+                match self.return_type {
+                    Some(return_type) if return_type != ty => {
+                        Err(self.inst_error(inst, "return type differs from other returns"))
+                    }
+                    _ => {
+                        self.return_type = Some(ty);
+                        Ok(())
+                    }
+                }
+            }
+
+            InstData::Phi { ty, ref inputs } => {
+                for &(_, value) in inputs {
+                    self.expect_type(inst, value, ty)?;
+                }
+
+                Ok(())
+            }
+
+            InstData::LoadField { obj, .. } => self.expect_type(inst, obj, Type::Ptr),
+
+            InstData::StoreField { ty, obj, value, .. } => {
+                self.expect_type(inst, obj, Type::Ptr)?;
+                self.expect_type(inst, value, ty)
+            }
+
+            InstData::StoreGlobal { ty, value, .. } => self.expect_type(inst, value, ty),
+
+            InstData::LoadArray { array, index, .. } => {
+                self.expect_type(inst, array, Type::Ptr)?;
+                self.expect_type(inst, index, Type::Int)
+            }
+
+            InstData::StoreArray {
+                ty,
+                array,
+                index,
+                value,
+            } => {
+                self.expect_type(inst, array, Type::Ptr)?;
+                self.expect_type(inst, index, Type::Int)?;
+                self.expect_type(inst, value, ty)
+            }
+
+            InstData::ArrayLength { array } => self.expect_type(inst, array, Type::Ptr),
+
+            InstData::SetUint8 { address, value } => {
+                self.expect_type(inst, address, Type::Long)?;
+                self.expect_type(inst, value, Type::Byte)
+            }
+
+            InstData::NilCheck { opnd }
+            | InstData::InstanceOf { opnd, .. }
+            | InstData::CheckedCast { opnd, .. }
+            | InstData::HasClass { opnd, .. }
+            | InstData::Throw { opnd }
+            | InstData::Spawn { opnd } => self.expect_type(inst, opnd, Type::Ptr),
+
+            InstData::BoundsCheck { index, length } => {
+                self.expect_type(inst, index, Type::Int)?;
+                self.expect_type(inst, length, Type::Int)
+            }
+
+            InstData::Call {
+                kind: CallKind::Virtual(_),
+                ref args,
+                ..
+            } => match args.first() {
+                Some(&receiver) => self.expect_type(inst, receiver, Type::Ptr),
+                None => Err(self.inst_error(inst, "virtual call without receiver")),
+            },
+
+            InstData::NewArray { length, .. } => self.expect_type(inst, length, Type::Int),
+
+            _ => Ok(()),
+        }
+    }
+
+    fn expect_type(&self, inst: Inst, value: Value, expected: Type) -> Result<(), String> {
+        let ty = self.fct.value_type(value);
+
+        if ty == expected {
+            Ok(())
+        } else {
+            let msg = format!(
+                "operand {} has type {} but {} expected",
+                self.names.value(value),
+                ty.name(),
+                expected.name()
+            );
+            Err(self.inst_error(inst, &msg))
+        }
+    }
+
+    fn inst_error(&self, inst: Inst, msg: &str) -> String {
+        let block = self.fct.cfg.inst_block(inst).unwrap();
+
+        format!(
+            "{}: `{}`: {}",
+            self.names.block(block),
+            inst_to_string(self.fct, &self.names, inst),
+            msg
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify;
+    use crate::boots::parser::parse;
+
+    fn verify_text(text: &str) -> Result<(), String> {
+        let fct = parse(text).unwrap_or_else(|msg| panic!("{}", msg));
+        verify(&fct)
+    }
+
+    #[test]
+    fn verify_valid() {
+        assert_eq!(
+            Ok(()),
+            verify_text(
+                "function f {
+b0:
+    v0 = param int 0
+    v1 = const int 0
+    goto b1
+b1:
+    v2 = phi int [b0: v1, b2: v4]
+    v3 = cmp lt int v2, v0
+    if v3, b2, b3
+b2:
+    v4 = add int v2, v0
+    goto b1
+b3:
+    ret v2
+}"
+            )
+        );
+    }
+
+    #[test]
+    fn verify_missing_terminator() {
+        assert_eq!(
+            Err("b0: block without terminator".into()),
+            verify_text("function f {\nb0:\n    v0 = const int 1\n}")
+        );
+    }
+
+    #[test]
+    fn verify_terminator_in_middle() {
+        assert_eq!(
+            Err("b0: `ret`: terminator in the middle of block".into()),
+            verify_text("function f {\nb0:\n    ret\n    ret\n}")
+        );
+    }
+
+    #[test]
+    fn verify_dominance() {
+        assert_eq!(
+            Err("b0: `v0 = add int v1, v1`: definition of v1 does not dominate use".into()),
+            verify_text(
+                "function f {
+b0:
+    v0 = add int v1, v1
+    v1 = const int 1
+    ret v0
+}"
+            )
+        );
+
+        assert_eq!(
+            Err("b3: `ret v1`: definition of v1 does not dominate use".into()),
+            verify_text(
+                "function f {
+b0:
+    v0 = const true
+    if v0, b1, b2
+b1:
+    v1 = const int 1
+    goto b3
+b2:
+    goto b3
+b3:
+    ret v1
+}"
+            )
+        );
+    }
+
+    #[test]
+    fn verify_phi_arity() {
+        assert_eq!(
+            Err("b1: `v1 = phi int [b0: v0]`: phi inputs do not match predecessors".into()),
+            verify_text(
+                "function f {
+b0:
+    v0 = const int 1
+    goto b1
+b1:
+    v1 = phi int [b0: v0]
+    goto b1
+}"
+            )
+        );
+    }
+
+    #[test]
+    fn verify_types() {
+        assert_eq!(
+            Err("b0: `v2 = add int v0, v1`: operand v1 has type long but int expected".into()),
+            verify_text(
+                "function f {
+b0:
+    v0 = const int 1
+    v1 = const long 1
+    v2 = add int v0, v1
+    ret v2
+}"
+            )
+        );
+
+        assert_eq!(
+            Err("b0: `if v0, b1, b1`: operand v0 has type int but bool expected".into()),
+            verify_text(
+                "function f {\nb0:\n    v0 = const int 1\n    if v0, b1, b1\nb1:\n    ret\n}"
+            )
+        );
+
+        assert_eq!(
+            Err("b2: `ret`: return type differs from other returns".into()),
+            verify_text(
+                "function f {
+b0:
+    v0 = const true
+    if v0, b1, b2
+b1:
+    ret v0
+b2:
+    ret
+}"
+            )
+        );
+    }
+}
@@ -1,3 +1,4 @@
 pub mod astgen;
 pub mod generate;
+pub mod interpreter;
 pub mod opcode;
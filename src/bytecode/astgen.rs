@@ -6,23 +6,31 @@ use dora_parser::ast::*;
 use dora_parser::lexer::token::{FloatSuffix, IntSuffix};
 
 use crate::bytecode::generate::{
-    BytecodeFunction, BytecodeGenerator, BytecodeType, Label, Register,
+    BytecodeFunction, BytecodeGenerator, BytecodeIdx, BytecodeType, Label, Register,
 };
-use crate::class::TypeParams;
-use crate::semck::specialize::{specialize_class_id_params, specialize_class_ty, specialize_type};
-use crate::ty::BuiltinType;
-use crate::vm::{CallType, Fct, FctId, FctKind, FctSrc, IdentType, Intrinsic, VarId, VM};
+use crate::class::{ClassDefId, ClassSize, FieldId, TypeParams};
+use crate::os::signal::Trap;
+use crate::semck::always_returns;
+use crate::semck::specialize::{
+    specialize_class_id, specialize_class_ty, specialize_context, specialize_lambda,
+    specialize_type,
+};
+use crate::ty::{BuiltinType, LambdaId};
+use crate::vm::{CallType, ConstId, Fct, FctId, FctKind, FctSrc, IdentType, Intrinsic, VarId, VM};
 
 pub struct LoopLabels {
     cond: Label,
     end: Label,
+    // number of active finally blocks when entering the loop
+    finallys: usize,
 }
 
 impl LoopLabels {
-    fn new(cond: Label, end: Label) -> LoopLabels {
+    fn new(cond: Label, end: Label, finallys: usize) -> LoopLabels {
         LoopLabels {
             cond: cond,
             end: end,
+            finallys: finallys,
         }
     }
 }
@@ -60,6 +68,15 @@ pub fn generate_fct<'ast>(
         gen: BytecodeGenerator::new(),
         loops: Vec::new(),
         var_registers: HashMap::new(),
+
+        active_finallys: Vec::new(),
+        active_upper: None,
+        lbl_return: None,
+        eh_return_value: None,
+
+        context: None,
+        closure_class: None,
+        context_classes: HashMap::new(),
     };
     ast_bytecode_generator.generate()
 }
@@ -76,13 +93,26 @@ pub struct AstBytecodeGen<'a, 'ast: 'a> {
     gen: BytecodeGenerator,
     loops: Vec<LoopLabels>,
     var_registers: HashMap<VarId, Register>,
+
+    active_finallys: Vec<&'ast Stmt>,
+    active_upper: Option<usize>,
+    lbl_return: Option<Label>,
+    eh_return_value: Option<Register>,
+
+    // context object of this function and its class
+    context: Option<(Register, ClassDefId)>,
+    closure_class: Option<ClassDefId>,
+    context_classes: HashMap<FctId, ClassDefId>,
 }
 
 impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
     pub fn generate(mut self) -> BytecodeFunction {
+        self.gen.set_position(self.ast.pos);
+
         if self.fct.has_self() {
             let var_id = self.src.var_self().id;
-            let reg = self.gen.add_register(BytecodeType::Ptr);
+            let ty: BytecodeType = self.specialize_type(self.src.var_self().ty).into();
+            let reg = self.gen.add_register(ty);
             self.var_registers.insert(var_id, reg);
         }
 
@@ -94,7 +124,13 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
             self.var_registers.insert(var_id, reg);
         }
 
-        if let Some(ref block) = self.ast.block {
+        if self.src.has_context {
+            self.emit_context_allocation();
+        }
+
+        let ast = self.ast;
+
+        if let Some(ref block) = ast.block {
             self.visit_stmt(block);
         }
 
@@ -102,11 +138,13 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
             self.gen.emit_ret_void();
         }
 
-        self.gen.generate()
+        self.gen.generate(self.var_registers)
     }
 
-    // TODO - implement other statements
-    fn visit_stmt(&mut self, stmt: &Stmt) {
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        let saved_pos = self.gen.position().unwrap();
+        self.gen.set_position(stmt.pos());
+
         match *stmt {
             StmtBlock(ref block) => self.visit_block(block),
             StmtReturn(ref ret) => self.visit_stmt_return(ret),
@@ -117,50 +155,124 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
             StmtVar(ref stmt) => self.visit_stmt_var(stmt),
             StmtWhile(ref stmt) => self.visit_stmt_while(stmt),
             StmtLoop(ref stmt) => self.visit_stmt_loop(stmt),
-            // StmtThrow(ref stmt) => {},
-            // StmtDefer(ref stmt) => {},
-            // StmtDo(ref stmt) => {},
-            // StmtSpawn(ref stmt) => {},
-            // StmtFor(ref stmt) => {},
-            _ => unimplemented!(),
+            StmtThrow(ref stmt) => self.visit_stmt_throw(stmt),
+            StmtDefer(_) => unreachable!("defer is handled by visit_stmts"),
+            StmtDo(ref stmt) => self.visit_stmt_do(stmt),
+            StmtSpawn(ref stmt) => self.visit_stmt_spawn(stmt),
+            StmtFor(ref stmt) => self.visit_stmt_for(stmt),
         }
+
+        self.gen.set_position(saved_pos);
     }
 
-    fn visit_stmt_var(&mut self, stmt: &StmtVarType) {
+    fn visit_stmt_var(&mut self, stmt: &'ast StmtVarType) {
         let var_id = *self.src.map_vars.get(stmt.id).unwrap();
         let ty: BytecodeType = self.specialize_type(self.src.vars[var_id].ty).into();
-        let var_reg = self.gen.add_register(ty);
-
-        self.var_registers.insert(var_id, var_reg);
 
-        if let Some(ref expr) = stmt.expr {
-            self.visit_expr(expr, DataDest::Reg(var_reg));
+        if let Some(var_reg) = self.declare_var(var_id) {
+            if let Some(ref expr) = stmt.expr {
+                self.visit_expr(expr, DataDest::Reg(var_reg));
+            } else if ty == BytecodeType::Ptr {
+                self.gen.emit_const_nil(var_reg);
+            }
+        } else if let Some(ref expr) = stmt.expr {
+            let value = self.visit_expr(expr, DataDest::Alloc);
+            self.emit_var_store(var_id, value);
         }
     }
 
-    fn visit_stmt_while(&mut self, stmt: &StmtWhileType) {
+    fn visit_stmt_while(&mut self, stmt: &'ast StmtWhileType) {
+        self.gen.add_loop_start(stmt.id);
         let cond_lbl = self.gen.define_label();
         let end_lbl = self.gen.create_label();
         let cond_reg = self.visit_expr(&stmt.cond, DataDest::Alloc);
         self.gen.emit_jump_if_false(cond_reg, end_lbl);
-        self.loops.push(LoopLabels::new(cond_lbl, end_lbl));
-        self.visit_stmt(&stmt.block);
-        self.loops.pop();
+        self.visit_loop_body(&stmt.block, cond_lbl, end_lbl);
         self.gen.emit_jump(cond_lbl);
         self.gen.bind_label(end_lbl);
     }
 
-    fn visit_stmt_loop(&mut self, stmt: &StmtLoopType) {
+    fn visit_stmt_loop(&mut self, stmt: &'ast StmtLoopType) {
+        self.gen.add_loop_start(stmt.id);
         let start_lbl = self.gen.define_label();
         let end_lbl = self.gen.create_label();
-        self.loops.push(LoopLabels::new(start_lbl, end_lbl));
-        self.visit_stmt(&stmt.block);
-        self.loops.pop();
+        self.visit_loop_body(&stmt.block, start_lbl, end_lbl);
         self.gen.emit_jump(start_lbl);
         self.gen.bind_label(end_lbl);
     }
 
-    fn visit_stmt_if(&mut self, stmt: &StmtIfType) {
+    fn visit_stmt_for(&mut self, stmt: &'ast StmtForType) {
+        let for_type_info = self.src.map_fors.get(stmt.id).unwrap().clone();
+        let object_type = self.ty(stmt.expr.id());
+
+        // iterator = obj.makeIterator()
+        let iterator = self.gen.add_register(BytecodeType::Ptr);
+        let call_type = CallType::Method(
+            object_type,
+            for_type_info.make_iterator,
+            TypeParams::empty(),
+        );
+        self.emit_call_site(
+            &call_type,
+            for_type_info.make_iterator,
+            &[CallArg::Expr(&stmt.expr)],
+            DataDest::Reg(iterator),
+        );
+
+        let var_id = *self.src.map_vars.get(stmt.id).unwrap();
+        let var_reg = self.declare_var(var_id);
+
+        let cond_lbl = self.gen.define_label();
+        let end_lbl = self.gen.create_label();
+
+        // while iterator.hasNext()
+        let call_type = CallType::Method(
+            for_type_info.iterator_type,
+            for_type_info.has_next,
+            TypeParams::empty(),
+        );
+        let cond_reg = self.emit_call_site(
+            &call_type,
+            for_type_info.has_next,
+            &[CallArg::Reg(iterator)],
+            DataDest::Alloc,
+        );
+        self.gen.emit_jump_if_false(cond_reg, end_lbl);
+
+        // var = iterator.next()
+        let call_type = CallType::Method(
+            for_type_info.iterator_type,
+            for_type_info.next,
+            TypeParams::empty(),
+        );
+        let dest = match var_reg {
+            Some(var_reg) => DataDest::Reg(var_reg),
+            None => DataDest::Alloc,
+        };
+        let value = self.emit_call_site(
+            &call_type,
+            for_type_info.next,
+            &[CallArg::Reg(iterator)],
+            dest,
+        );
+
+        if var_reg.is_none() {
+            self.emit_var_store(var_id, value);
+        }
+
+        self.visit_loop_body(&stmt.block, cond_lbl, end_lbl);
+        self.gen.emit_jump(cond_lbl);
+        self.gen.bind_label(end_lbl);
+    }
+
+    fn visit_loop_body(&mut self, block: &'ast Stmt, cond: Label, end: Label) {
+        let finallys = self.active_finallys.len();
+        self.loops.push(LoopLabels::new(cond, end, finallys));
+        self.visit_stmt(block);
+        self.loops.pop();
+    }
+
+    fn visit_stmt_if(&mut self, stmt: &'ast StmtIfType) {
         if let Some(ref else_block) = stmt.else_block {
             let else_lbl = self.gen.create_label();
             let end_lbl = self.gen.create_label();
@@ -183,74 +295,308 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
         }
     }
 
-    fn visit_stmt_expr(&mut self, stmt: &StmtExprType) {
+    fn visit_stmt_expr(&mut self, stmt: &'ast StmtExprType) {
         self.visit_expr(&stmt.expr, DataDest::Effect);
     }
 
-    fn visit_block(&mut self, block: &StmtBlockType) {
-        for stmt in &block.stmts {
+    fn visit_block(&mut self, block: &'ast StmtBlockType) {
+        self.visit_stmts(&block.stmts);
+    }
+
+    fn visit_stmts(&mut self, stmts: &'ast [Box<Stmt>]) {
+        for (idx, stmt) in stmts.iter().enumerate() {
+            if let StmtDefer(ref defer) = **stmt {
+                // all remaining statements of the block are guarded by the defer
+                self.visit_stmt_defer(defer, stmt, &stmts[idx + 1..]);
+                return;
+            }
+
             self.visit_stmt(stmt);
         }
     }
 
-    fn visit_stmt_return(&mut self, ret: &StmtReturnType) {
-        if let Some(ref expr) = ret.expr {
-            let return_type: BytecodeType = self.specialize_type(self.fct.return_type).into();
-            let result_reg = self.visit_expr(expr, DataDest::Alloc);
+    fn visit_stmt_defer(
+        &mut self,
+        defer: &'ast StmtDeferType,
+        stmt: &'ast Stmt,
+        rest: &'ast [Box<Stmt>],
+    ) {
+        let end_lbl = self.gen.create_label();
 
-            match return_type {
-                BytecodeType::Bool => self.gen.emit_ret_bool(result_reg),
-                BytecodeType::Byte => self.gen.emit_ret_byte(result_reg),
-                BytecodeType::Char => self.gen.emit_ret_char(result_reg),
-                BytecodeType::Int => self.gen.emit_ret_int(result_reg),
-                BytecodeType::Long => self.gen.emit_ret_long(result_reg),
-                BytecodeType::Float => self.gen.emit_ret_float(result_reg),
-                BytecodeType::Double => self.gen.emit_ret_double(result_reg),
-                BytecodeType::Ptr => self.gen.emit_ret_ptr(result_reg),
+        self.active_finallys.push(stmt);
+        let try_start = self.gen.pc();
+        self.visit_stmts(rest);
+        let try_end = self.gen.pc();
+        self.active_finallys.pop();
+
+        if !rest.iter().any(|stmt| always_returns(stmt)) {
+            self.visit_expr(&defer.expr, DataDest::Effect);
+            self.gen.emit_jump(end_lbl);
+        }
+
+        // run deferred expression and rethrow exception
+        let exception = self.gen.add_register(BytecodeType::Ptr);
+        let catch = self.gen.pc();
+        self.visit_expr(&defer.expr, DataDest::Effect);
+        self.gen.emit_throw(exception);
+
+        self.gen.bind_label(end_lbl);
+
+        if try_start != try_end {
+            self.gen
+                .add_exception_handler(try_start, try_end, catch, Some(exception), None);
+        }
+    }
+
+    fn visit_stmt_return(&mut self, ret: &'ast StmtReturnType) {
+        let len = self.active_upper.unwrap_or(self.active_finallys.len());
+        let return_type: BytecodeType = if self.fct.return_type.is_unit() {
+            BytecodeType::Ptr
+        } else {
+            self.specialize_type(self.fct.return_type).into()
+        };
+
+        let result_reg = if let Some(ref expr) = ret.expr {
+            if len > 0 || self.lbl_return.is_some() {
+                // finally blocks must not clobber the return value
+                let reg = self.eh_return_value(return_type);
+                self.visit_expr(expr, DataDest::Reg(reg));
+                Some(reg)
+            } else {
+                Some(self.visit_expr(expr, DataDest::Alloc))
             }
+        } else {
+            None
+        };
+
+        // return inside finally block jumps to the next outer finally block
+        if let Some(lbl_return) = self.lbl_return {
+            self.gen.emit_jump(lbl_return);
+            return;
+        }
+
+        for idx in (0..len).rev() {
+            let lbl_return = self.gen.create_label();
+            self.lbl_return = Some(lbl_return);
+
+            let finally = self.active_finallys[idx];
+            self.visit_finally(finally);
+
+            self.gen.bind_label(lbl_return);
+        }
+
+        self.lbl_return = None;
+
+        if let Some(result_reg) = result_reg {
+            self.emit_ret(return_type, result_reg);
         } else {
             self.gen.emit_ret_void();
         }
     }
 
-    fn visit_stmt_break(&mut self, _stmt: &StmtBreakType) {
-        let end = self.loops.pop().unwrap().end;
+    fn eh_return_value(&mut self, ty: BytecodeType) -> Register {
+        if let Some(reg) = self.eh_return_value {
+            return reg;
+        }
+
+        let reg = self.gen.add_register(ty);
+        self.eh_return_value = Some(reg);
+        reg
+    }
+
+    fn visit_stmt_break(&mut self, _stmt: &'ast StmtBreakType) {
+        self.emit_finallys_within_loop();
+        let end = self.loops.last().unwrap().end;
         self.gen.emit_jump(end);
     }
 
-    fn visit_stmt_continue(&mut self, _stmt: &StmtContinueType) {
+    fn visit_stmt_continue(&mut self, _stmt: &'ast StmtContinueType) {
+        self.emit_finallys_within_loop();
         let cond = self.loops.last().unwrap().cond;
         self.gen.emit_jump(cond);
     }
 
-    // TODO - implement other expressions
-    fn visit_expr(&mut self, expr: &Expr, dest: DataDest) -> Register {
-        match *expr {
+    // runs all finally blocks between the innermost loop and the current statement
+    fn emit_finallys_within_loop(&mut self) {
+        let start = self.loops.last().unwrap().finallys;
+        let end = self.active_finallys.len();
+        let saved_upper = self.active_upper;
+
+        for idx in (start..end).rev() {
+            self.active_upper = Some(idx);
+            let finally = self.active_finallys[idx];
+            self.visit_finally(finally);
+        }
+
+        self.active_upper = saved_upper;
+    }
+
+    fn visit_finally(&mut self, finally: &'ast Stmt) {
+        if let StmtDefer(ref defer) = *finally {
+            self.visit_expr(&defer.expr, DataDest::Effect);
+        } else {
+            self.visit_stmt(finally);
+        }
+    }
+
+    fn visit_stmt_throw(&mut self, stmt: &'ast StmtThrowType) {
+        let exception = self.visit_expr(&stmt.expr, DataDest::Alloc);
+        self.gen.emit_nil_check(exception);
+        self.gen.emit_throw(exception);
+    }
+
+    fn visit_stmt_spawn(&mut self, stmt: &'ast StmtSpawnType) {
+        let closure = self.visit_expr(&stmt.expr, DataDest::Alloc);
+        self.gen.emit_spawn(closure);
+    }
+
+    fn visit_stmt_do(&mut self, stmt: &'ast StmtDoType) {
+        let end_lbl = self.gen.create_label();
+
+        let do_span = self.visit_stmt_with_finally(stmt, &stmt.do_block, end_lbl);
+        let catch_spans = self.visit_do_catch_blocks(stmt, do_span, end_lbl);
+        let finally = self.visit_do_finally_block(stmt);
+
+        self.gen.bind_label(end_lbl);
+
+        if let Some((finally_start, exception)) = finally {
+            let (try_start, try_end) = do_span;
+            self.gen.add_exception_handler(
+                try_start,
+                try_end,
+                finally_start,
+                Some(exception),
+                None,
+            );
+
+            for (catch_start, catch_end) in catch_spans {
+                self.gen.add_exception_handler(
+                    catch_start,
+                    catch_end,
+                    finally_start,
+                    Some(exception),
+                    None,
+                );
+            }
+        }
+    }
+
+    fn visit_do_catch_blocks(
+        &mut self,
+        stmt: &'ast StmtDoType,
+        try_span: (BytecodeIdx, BytecodeIdx),
+        end_lbl: Label,
+    ) -> Vec<(BytecodeIdx, BytecodeIdx)> {
+        let mut catch_spans = Vec::new();
+
+        for catch in &stmt.catch_blocks {
+            let var_id = *self.src.map_vars.get(catch.id).unwrap();
+            let var_reg = self.declare_var(var_id);
+            let exception = var_reg.unwrap_or_else(|| self.gen.add_register(BytecodeType::Ptr));
+
+            let catch_start = self.gen.pc();
+
+            // exception is always stored in a register first
+            if var_reg.is_none() {
+                self.emit_var_store(var_id, exception);
+            }
+
+            let (_, catch_end) = self.visit_stmt_with_finally(stmt, &catch.block, end_lbl);
+
+            let ty = self.ty(catch.data_type.id());
+            let cls_def_id = specialize_class_ty(self.vm, ty);
+
+            let (try_start, try_end) = try_span;
+            self.gen.add_exception_handler(
+                try_start,
+                try_end,
+                catch_start,
+                Some(exception),
+                Some(cls_def_id),
+            );
+
+            catch_spans.push((catch_start, catch_end));
+        }
+
+        catch_spans
+    }
+
+    fn visit_stmt_with_finally(
+        &mut self,
+        stmt: &'ast StmtDoType,
+        block: &'ast Stmt,
+        end_lbl: Label,
+    ) -> (BytecodeIdx, BytecodeIdx) {
+        if let Some(ref finally_block) = stmt.finally_block {
+            self.active_finallys.push(&finally_block.block);
+        }
+
+        let start = self.gen.pc();
+        self.visit_stmt(block);
+        let end = self.gen.pc();
+
+        if stmt.finally_block.is_some() {
+            self.active_finallys.pop();
+        }
+
+        if !always_returns(block) {
+            if let Some(ref finally_block) = stmt.finally_block {
+                self.visit_stmt(&finally_block.block);
+            }
+
+            self.gen.emit_jump(end_lbl);
+        }
+
+        (start, end)
+    }
+
+    fn visit_do_finally_block(
+        &mut self,
+        stmt: &'ast StmtDoType,
+    ) -> Option<(BytecodeIdx, Register)> {
+        let finally_block = stmt.finally_block.as_ref()?;
+
+        let exception = self.gen.add_register(BytecodeType::Ptr);
+        let finally_start = self.gen.pc();
+
+        self.visit_stmt(&finally_block.block);
+        self.gen.emit_throw(exception);
+
+        Some((finally_start, exception))
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr, dest: DataDest) -> Register {
+        let saved_pos = self.gen.position().unwrap();
+        self.gen.set_position(expr.pos());
+
+        let result = match *expr {
             ExprUn(ref un) => self.visit_expr_un(un, dest),
             ExprBin(ref bin) => self.visit_expr_bin(bin, dest),
             ExprDot(ref field) => self.visit_expr_dot(field, dest),
-            // ExprArray(ref array) => {},
             ExprLitChar(ref lit) => self.visit_expr_lit_char(lit, dest),
             ExprLitInt(ref lit) => self.visit_expr_lit_int(lit, dest),
             ExprLitFloat(ref lit) => self.visit_expr_lit_float(lit, dest),
             ExprLitStr(ref lit) => self.visit_expr_lit_string(lit, dest),
-            // ExprLitStruct(ref lit) => {},
             ExprLitBool(ref lit) => self.visit_expr_lit_bool(lit, dest),
             ExprIdent(ref ident) => self.visit_expr_ident(ident, dest),
             ExprAssign(ref assign) => self.visit_expr_assign(assign, dest),
             ExprCall(ref call) => self.visit_expr_call(call, dest),
-            // ExprDelegation(ref call) => {},
+            ExprDelegation(ref call) => self.visit_expr_delegation(call, dest),
             ExprSelf(ref selfie) => self.visit_expr_self(selfie, dest),
-            // ExprSuper(ref expr) => {},
+            ExprSuper(ref expr) => self.visit_expr_super(expr, dest),
             ExprNil(ref nil) => self.visit_expr_nil(nil, dest),
-            // ExprConv(ref expr) => {},
-            // ExprTry(ref expr) => {},
-            // ExprLambda(ref expr) => {},
-            _ => unimplemented!(),
-        }
+            ExprConv(ref expr) => self.visit_expr_conv(expr, dest),
+            ExprTry(ref expr) => self.visit_expr_try(expr, dest),
+            ExprLambda(ref expr) => self.visit_expr_lambda(expr, dest),
+            ExprPath(ref path) => self.visit_expr_path(path, dest),
+            ExprTypeParam(_) => unreachable!(),
+        };
+
+        self.gen.set_position(saved_pos);
+        result
     }
 
-    fn visit_expr_dot(&mut self, e: &ExprDotType, dest: DataDest) -> Register {
+    fn visit_expr_dot(&mut self, e: &'ast ExprDotType, dest: DataDest) -> Register {
         let (class, field_id) = {
             let ident_type = self.src.map_idents.get(e.id).unwrap();
 
@@ -260,65 +606,161 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
             }
         };
 
-        let class = self.specialize_type(class);
-        let cls_id = specialize_class_ty(self.vm, class);
-        let cls = self.vm.class_defs.idx(cls_id);
-        let cls = cls.read();
-        let field = &cls.fields[field_id.idx()];
-        let ty: BytecodeType = field.ty.into();
-
+        let (cls_id, ty) = self.field_info(class, field_id);
         let dest = self.ensure_register(dest, ty);
         let obj = self.visit_expr(&e.object, DataDest::Alloc);
 
-        match ty {
-            BytecodeType::Byte => self.gen.emit_load_field_byte(dest, obj, cls_id, field_id),
-            BytecodeType::Bool => self.gen.emit_load_field_bool(dest, obj, cls_id, field_id),
-            BytecodeType::Char => self.gen.emit_load_field_char(dest, obj, cls_id, field_id),
-            BytecodeType::Int => self.gen.emit_load_field_int(dest, obj, cls_id, field_id),
-            BytecodeType::Long => self.gen.emit_load_field_long(dest, obj, cls_id, field_id),
-            BytecodeType::Float => self.gen.emit_load_field_float(dest, obj, cls_id, field_id),
-            BytecodeType::Double => self.gen.emit_load_field_double(dest, obj, cls_id, field_id),
-            BytecodeType::Ptr => self.gen.emit_load_field_ptr(dest, obj, cls_id, field_id),
-        }
+        self.emit_load_field(ty, dest, obj, cls_id, field_id);
 
         dest
     }
 
-    fn visit_expr_call(&mut self, expr: &ExprCallType, dest: DataDest) -> Register {
-        if let Some(_intrinsic) = self.get_intrinsic(expr.id) {
-            unimplemented!()
+    fn visit_expr_call(&mut self, expr: &'ast ExprCallType, dest: DataDest) -> Register {
+        let call_type = self.src.map_calls.get(expr.id).unwrap().clone();
+
+        if let CallType::Lambda(lambda_id) = *call_type {
+            return self.visit_expr_call_lambda(expr, lambda_id, dest);
         }
 
-        let call_type = self.src.map_calls.get(expr.id).unwrap().clone();
-        let fct_id = call_type.fct_id();
+        let mut args = expr.args.iter().map(|arg| &**arg).collect::<Vec<_>>();
 
-        let fct = self.vm.fcts.idx(fct_id);
-        let fct = fct.read();
+        match *call_type {
+            CallType::Method(_, _, _) => args.insert(0, expr.object().unwrap()),
+            CallType::Expr(_, _) => args.insert(0, &expr.callee),
+            _ => {}
+        }
+
+        if let Some(intrinsic) = self.get_intrinsic(expr.id) {
+            return self.emit_call_intrinsic(expr.id, &args, intrinsic, dest);
+        }
+
+        let callee_id = self.callee_id(&call_type);
+
+        if let FctKind::Builtin(intrinsic) = self.vm.fcts.idx(callee_id).read().kind {
+            return self.emit_call_intrinsic(expr.id, &args, intrinsic, dest);
+        }
+
+        let mut args = args.into_iter().map(CallArg::Expr).collect::<Vec<_>>();
+
+        match *call_type {
+            CallType::Ctor(_, _, _) => args.insert(0, CallArg::Selfie),
+            CallType::CtorNew(_, _, _) => {
+                let ty = self.ty(expr.id);
+                let cls_id = specialize_class_ty(self.vm, ty);
+                args.insert(0, CallArg::SelfieNew(cls_id));
+            }
+            _ => {}
+        }
+
+        self.emit_call_site(&call_type, callee_id, &args, dest)
+    }
+
+    fn visit_expr_call_lambda(
+        &mut self,
+        expr: &'ast ExprCallType,
+        lambda_id: LambdaId,
+        dest: DataDest,
+    ) -> Register {
+        let lambda = self.vm.lambda_types.lock().get(lambda_id);
 
-        let callee_id = if fct.kind.is_definition() {
-            unimplemented!()
+        let mut arg_types = vec![BytecodeType::Ptr];
+        for &param in &lambda.params {
+            arg_types.push(self.specialize_type(param).into());
+        }
+
+        let return_type = self.specialize_type(lambda.ret);
+        let return_reg = if dest.is_effect() || return_type.is_unit() {
+            None
         } else {
-            fct_id
+            Some(self.ensure_register(dest, return_type.into()))
         };
 
-        let callee = self.vm.fcts.idx(callee_id);
-        let callee = callee.read();
+        let num_args = arg_types.len();
+        let start_reg = self.gen.add_register_chain(&arg_types);
+
+        self.visit_expr(&expr.callee, DataDest::Reg(start_reg));
+
+        for (idx, arg) in expr.args.iter().enumerate() {
+            self.visit_expr(arg, DataDest::Reg(start_reg.offset(idx + 1)));
+        }
+
+        if let Some(return_reg) = return_reg {
+            let return_type: BytecodeType = return_type.into();
+
+            match return_type {
+                BytecodeType::Bool => self
+                    .gen
+                    .emit_invoke_lambda_bool(return_reg, start_reg, num_args),
+                BytecodeType::Byte => self
+                    .gen
+                    .emit_invoke_lambda_byte(return_reg, start_reg, num_args),
+                BytecodeType::Char => self
+                    .gen
+                    .emit_invoke_lambda_char(return_reg, start_reg, num_args),
+                BytecodeType::Int => self
+                    .gen
+                    .emit_invoke_lambda_int(return_reg, start_reg, num_args),
+                BytecodeType::Long => self
+                    .gen
+                    .emit_invoke_lambda_long(return_reg, start_reg, num_args),
+                BytecodeType::Float => self
+                    .gen
+                    .emit_invoke_lambda_float(return_reg, start_reg, num_args),
+                BytecodeType::Double => self
+                    .gen
+                    .emit_invoke_lambda_double(return_reg, start_reg, num_args),
+                BytecodeType::Ptr => self
+                    .gen
+                    .emit_invoke_lambda_ptr(return_reg, start_reg, num_args),
+            }
 
-        if let FctKind::Builtin(_intrinsic) = callee.kind {
-            unimplemented!()
+            return_reg
+        } else {
+            self.gen.emit_invoke_lambda_void(start_reg, num_args);
+            Register::invalid()
         }
+    }
+
+    fn visit_expr_delegation(
+        &mut self,
+        expr: &'ast ExprDelegationType,
+        dest: DataDest,
+    ) -> Register {
+        let call_type = self.src.map_calls.get(expr.id).unwrap().clone();
+        let callee_id = call_type.fct_id();
+
+        let mut args = expr
+            .args
+            .iter()
+            .map(|arg| CallArg::Expr(arg))
+            .collect::<Vec<_>>();
+        args.insert(0, CallArg::Selfie);
+
+        self.emit_call_site(&call_type, callee_id, &args, dest)
+    }
+
+    fn emit_call_site(
+        &mut self,
+        call_type: &CallType,
+        callee_id: FctId,
+        args: &[CallArg<'ast>],
+        dest: DataDest,
+    ) -> Register {
+        let callee = self.vm.fcts.idx(callee_id);
+        let callee = callee.read();
 
-        let return_type = if dest.is_effect() {
+        let return_type = if dest.is_effect() || call_type.is_ctor() || call_type.is_ctor_new() {
             BuiltinType::Unit
         } else {
-            self.specialize_type_for_call(&call_type, callee.return_type)
+            self.specialize_type_for_call(call_type, callee.return_type)
         };
         let arg_types = callee
             .params_with_self()
             .iter()
-            .map(|&arg| self.specialize_type_for_call(&call_type, arg).into())
+            .map(|&arg| self.specialize_type_for_call(call_type, arg).into())
             .collect::<Vec<BytecodeType>>();
         let num_args = arg_types.len();
+        debug_assert_eq!(num_args, args.len());
 
         let return_reg = if return_type.is_unit() {
             Register::invalid()
@@ -332,120 +774,800 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
             Register::zero()
         };
 
-        let arg_start_reg = if let CallType::CtorNew(cls_id, _, tp) = &*call_type {
-            let cls_id = specialize_class_id_params(self.vm, *cls_id, tp);
-            self.gen.emit_new_object(start_reg, cls_id);
-            start_reg.offset(1)
-        } else if callee.has_self() {
-            let self_id = self.src.var_self().id;
-            let self_reg = self.var_reg(self_id);
-
-            self.gen.emit_mov_ptr(start_reg, self_reg);
-            start_reg.offset(1)
-        } else {
-            start_reg
+        let super_call = match args.first() {
+            Some(CallArg::Expr(object)) => object.is_super(),
+            _ => false,
         };
 
-        for (idx, arg) in expr.args.iter().enumerate() {
-            let arg_reg = arg_start_reg.offset(idx);
-            self.visit_expr(arg, DataDest::Reg(arg_reg));
-        }
+        // check first argument for nil for method calls
+        //
+        // no check necessary for:
+        //   super calls (guaranteed to not be nil) and
+        //   dynamic dispatch (implicit check when loading fctptr from vtable)
+        let check_self = callee.has_self() && !super_call && !callee.is_virtual();
 
-        match *call_type {
-            CallType::Ctor(_, _, _) | CallType::CtorNew(_, _, _) => {
-                self.gen
-                    .emit_invoke_direct_void(callee_id, start_reg, num_args);
-            }
+        // size of arrays and strings depends on the length argument
+        let mut new_array = None;
 
-            CallType::Method(_, _, _) => unimplemented!(),
-            CallType::Expr(_, _) => unimplemented!(),
+        for (idx, arg) in args.iter().enumerate() {
+            let arg_reg = start_reg.offset(idx);
 
-            CallType::Fct(_, _, _) => {
-                if return_type.is_unit() {
-                    self.gen
-                        .emit_invoke_static_void(callee_id, start_reg, num_args);
-                } else {
-                    let return_type: BytecodeType = return_type.into();
-
-                    match return_type.into() {
-                        BytecodeType::Bool => self
-                            .gen
-                            .emit_invoke_static_bool(return_reg, callee_id, start_reg, num_args),
-                        BytecodeType::Byte => self
-                            .gen
-                            .emit_invoke_static_byte(return_reg, callee_id, start_reg, num_args),
-                        BytecodeType::Char => self
-                            .gen
-                            .emit_invoke_static_char(return_reg, callee_id, start_reg, num_args),
-                        BytecodeType::Int => self
-                            .gen
-                            .emit_invoke_static_int(return_reg, callee_id, start_reg, num_args),
-                        BytecodeType::Long => self
-                            .gen
-                            .emit_invoke_static_long(return_reg, callee_id, start_reg, num_args),
-                        BytecodeType::Float => self
-                            .gen
-                            .emit_invoke_static_float(return_reg, callee_id, start_reg, num_args),
-                        BytecodeType::Double => self
-                            .gen
-                            .emit_invoke_static_double(return_reg, callee_id, start_reg, num_args),
-                        BytecodeType::Ptr => self
-                            .gen
-                            .emit_invoke_static_ptr(return_reg, callee_id, start_reg, num_args),
+            match *arg {
+                CallArg::Expr(expr) => {
+                    self.visit_expr(expr, DataDest::Reg(arg_reg));
+
+                    if idx == 0 && check_self && arg_types[0] == BytecodeType::Ptr {
+                        self.gen.emit_nil_check(arg_reg);
                     }
                 }
-            }
-        }
 
-        if call_type.is_ctor_new() {
-            start_reg
-        } else {
-            return_reg
-        }
-    }
+                CallArg::Reg(reg) => self.emit_mov(arg_types[idx], arg_reg, reg),
 
-    fn visit_expr_nil(&mut self, _nil: &ExprNilType, dest: DataDest) -> Register {
-        if dest.is_effect() {
-            return Register::invalid();
-        }
+                CallArg::Selfie => {
+                    let self_reg = self.var_reg(self.src.var_self().id);
+                    self.gen.emit_mov_ptr(arg_reg, self_reg);
+                }
 
-        let dest = self.ensure_register(dest, BytecodeType::Ptr);
+                CallArg::SelfieNew(cls_id) => {
+                    let cls = self.vm.class_defs.idx(cls_id);
+                    let cls = cls.read();
 
-        self.gen.emit_const_nil(dest);
+                    match cls.size {
+                        ClassSize::Fixed(_) => self.gen.emit_new_object(arg_reg, cls_id),
+                        _ => new_array = Some(cls_id),
+                    }
+                }
+            }
+        }
 
-        dest
-    }
+        if let Some(cls_id) = new_array {
+            let length = if num_args > 1 {
+                start_reg.offset(1)
+            } else {
+                let length = self.gen.add_register(BytecodeType::Int);
+                self.gen.emit_const_zero_int(length);
+                length
+            };
 
-    fn visit_expr_self(&mut self, _selfie: &ExprSelfType, dest: DataDest) -> Register {
-        if dest.is_effect() {
-            return Register::invalid();
+            self.gen.emit_new_array(start_reg, cls_id, length);
         }
 
-        let var_id = self.src.var_self().id;
-        let var_reg = self.var_reg(var_id);
+        let (cls_type_params, fct_type_params) = self.determine_call_type_params(call_type);
+        self.gen.set_type_params(cls_type_params, fct_type_params);
 
-        if dest.is_alloc() {
-            return var_reg;
-        }
+        let kind = if call_type.is_ctor() || call_type.is_ctor_new() || super_call {
+            InvokeKind::Direct
+        } else if callee.is_virtual() {
+            InvokeKind::Virtual
+        } else if callee.has_self() {
+            InvokeKind::Direct
+        } else {
+            InvokeKind::Static
+        };
 
-        let dest = dest.reg();
-        let ty: BytecodeType = self.src.var_self().ty.into();
+        let return_type = if return_type.is_unit() {
+            None
+        } else {
+            Some(return_type.into())
+        };
 
-        match ty {
-            BytecodeType::Bool => self.gen.emit_mov_bool(dest, var_reg),
-            BytecodeType::Byte => self.gen.emit_mov_byte(dest, var_reg),
-            BytecodeType::Char => self.gen.emit_mov_char(dest, var_reg),
-            BytecodeType::Double => self.gen.emit_mov_double(dest, var_reg),
-            BytecodeType::Float => self.gen.emit_mov_float(dest, var_reg),
-            BytecodeType::Int => self.gen.emit_mov_int(dest, var_reg),
-            BytecodeType::Long => self.gen.emit_mov_long(dest, var_reg),
-            BytecodeType::Ptr => self.gen.emit_mov_ptr(dest, var_reg),
-        }
-        dest
-    }
+        self.emit_invoke(
+            kind,
+            return_type,
+            return_reg,
+            callee_id,
+            start_reg,
+            num_args,
+        );
 
-    fn visit_expr_lit_char(&mut self, lit: &ExprLitCharType, dest: DataDest) -> Register {
-        if dest.is_effect() {
+        if call_type.is_ctor_new() {
+            // the new object is the result of the constructor call
+            match dest {
+                DataDest::Effect => Register::invalid(),
+                DataDest::Alloc => start_reg,
+                DataDest::Reg(reg) => {
+                    self.gen.emit_mov_ptr(reg, start_reg);
+                    reg
+                }
+            }
+        } else {
+            return_reg
+        }
+    }
+
+    fn emit_invoke(
+        &mut self,
+        kind: InvokeKind,
+        return_type: Option<BytecodeType>,
+        dest: Register,
+        fct_id: FctId,
+        start: Register,
+        num: usize,
+    ) {
+        let gen = &mut self.gen;
+
+        match (kind, return_type) {
+            (InvokeKind::Direct, None) => gen.emit_invoke_direct_void(fct_id, start, num),
+            (InvokeKind::Direct, Some(BytecodeType::Bool)) => {
+                gen.emit_invoke_direct_bool(dest, fct_id, start, num)
+            }
+            (InvokeKind::Direct, Some(BytecodeType::Byte)) => {
+                gen.emit_invoke_direct_byte(dest, fct_id, start, num)
+            }
+            (InvokeKind::Direct, Some(BytecodeType::Char)) => {
+                gen.emit_invoke_direct_char(dest, fct_id, start, num)
+            }
+            (InvokeKind::Direct, Some(BytecodeType::Int)) => {
+                gen.emit_invoke_direct_int(dest, fct_id, start, num)
+            }
+            (InvokeKind::Direct, Some(BytecodeType::Long)) => {
+                gen.emit_invoke_direct_long(dest, fct_id, start, num)
+            }
+            (InvokeKind::Direct, Some(BytecodeType::Float)) => {
+                gen.emit_invoke_direct_float(dest, fct_id, start, num)
+            }
+            (InvokeKind::Direct, Some(BytecodeType::Double)) => {
+                gen.emit_invoke_direct_double(dest, fct_id, start, num)
+            }
+            (InvokeKind::Direct, Some(BytecodeType::Ptr)) => {
+                gen.emit_invoke_direct_ptr(dest, fct_id, start, num)
+            }
+
+            (InvokeKind::Virtual, None) => gen.emit_invoke_virtual_void(fct_id, start, num),
+            (InvokeKind::Virtual, Some(BytecodeType::Bool)) => {
+                gen.emit_invoke_virtual_bool(dest, fct_id, start, num)
+            }
+            (InvokeKind::Virtual, Some(BytecodeType::Byte)) => {
+                gen.emit_invoke_virtual_byte(dest, fct_id, start, num)
+            }
+            (InvokeKind::Virtual, Some(BytecodeType::Char)) => {
+                gen.emit_invoke_virtual_char(dest, fct_id, start, num)
+            }
+            (InvokeKind::Virtual, Some(BytecodeType::Int)) => {
+                gen.emit_invoke_virtual_int(dest, fct_id, start, num)
+            }
+            (InvokeKind::Virtual, Some(BytecodeType::Long)) => {
+                gen.emit_invoke_virtual_long(dest, fct_id, start, num)
+            }
+            (InvokeKind::Virtual, Some(BytecodeType::Float)) => {
+                gen.emit_invoke_virtual_float(dest, fct_id, start, num)
+            }
+            (InvokeKind::Virtual, Some(BytecodeType::Double)) => {
+                gen.emit_invoke_virtual_double(dest, fct_id, start, num)
+            }
+            (InvokeKind::Virtual, Some(BytecodeType::Ptr)) => {
+                gen.emit_invoke_virtual_ptr(dest, fct_id, start, num)
+            }
+
+            (InvokeKind::Static, None) => gen.emit_invoke_static_void(fct_id, start, num),
+            (InvokeKind::Static, Some(BytecodeType::Bool)) => {
+                gen.emit_invoke_static_bool(dest, fct_id, start, num)
+            }
+            (InvokeKind::Static, Some(BytecodeType::Byte)) => {
+                gen.emit_invoke_static_byte(dest, fct_id, start, num)
+            }
+            (InvokeKind::Static, Some(BytecodeType::Char)) => {
+                gen.emit_invoke_static_char(dest, fct_id, start, num)
+            }
+            (InvokeKind::Static, Some(BytecodeType::Int)) => {
+                gen.emit_invoke_static_int(dest, fct_id, start, num)
+            }
+            (InvokeKind::Static, Some(BytecodeType::Long)) => {
+                gen.emit_invoke_static_long(dest, fct_id, start, num)
+            }
+            (InvokeKind::Static, Some(BytecodeType::Float)) => {
+                gen.emit_invoke_static_float(dest, fct_id, start, num)
+            }
+            (InvokeKind::Static, Some(BytecodeType::Double)) => {
+                gen.emit_invoke_static_double(dest, fct_id, start, num)
+            }
+            (InvokeKind::Static, Some(BytecodeType::Ptr)) => {
+                gen.emit_invoke_static_ptr(dest, fct_id, start, num)
+            }
+        }
+    }
+
+    // trait methods called on type params are resolved to the implementation
+    // for the specialized object type
+    fn callee_id(&self, call_type: &CallType) -> FctId {
+        let fct_id = call_type.fct_id();
+        let fct = self.vm.fcts.idx(fct_id);
+        let fct = fct.read();
+
+        if !fct.kind.is_definition() {
+            return fct_id;
+        }
+
+        let object_type = match *call_type {
+            CallType::Method(ty, _, _) => self.specialize_type(ty),
+            _ => unreachable!(),
+        };
+
+        let cls_id = object_type.cls_id(self.vm).unwrap();
+        let cls = self.vm.classes.idx(cls_id);
+        let cls = cls.read();
+
+        let impl_id = cls
+            .find_impl_for_trait(self.vm, fct.trait_id())
+            .expect("no impl found for generic trait call");
+        let ximpl = self.vm.impls[impl_id].read();
+
+        ximpl
+            .find_implements(self.vm, fct_id)
+            .expect("no impl found for generic trait call")
+    }
+
+    fn determine_call_type_params(&self, call_type: &CallType) -> (TypeParams, TypeParams) {
+        match *call_type {
+            CallType::Ctor(_, _, ref type_params) | CallType::CtorNew(_, _, ref type_params) => (
+                self.specialize_type_params(type_params),
+                TypeParams::empty(),
+            ),
+
+            CallType::Method(ty, _, ref type_params) => {
+                let ty = self.specialize_type(ty);
+
+                (
+                    ty.type_params(self.vm),
+                    self.specialize_type_params(type_params),
+                )
+            }
+
+            CallType::Fct(_, ref cls_type_params, ref fct_type_params) => (
+                self.specialize_type_params(cls_type_params),
+                self.specialize_type_params(fct_type_params),
+            ),
+
+            CallType::Expr(ty, _) => {
+                let ty = self.specialize_type(ty);
+                (ty.type_params(self.vm), TypeParams::empty())
+            }
+
+            CallType::Lambda(_) => unreachable!(),
+        }
+    }
+
+    fn emit_call_intrinsic(
+        &mut self,
+        id: NodeId,
+        args: &[&'ast Expr],
+        intrinsic: Intrinsic,
+        dest: DataDest,
+    ) -> Register {
+        match intrinsic {
+            Intrinsic::GenericArrayLen | Intrinsic::StrLen => {
+                let dest = self.ensure_register(dest, BytecodeType::Int);
+                let array = self.visit_expr(args[0], DataDest::Alloc);
+                self.gen.emit_array_length(dest, array);
+
+                dest
+            }
+
+            Intrinsic::GenericArrayGet | Intrinsic::StrGet => {
+                let ty = if intrinsic == Intrinsic::StrGet {
+                    BytecodeType::Byte
+                } else {
+                    self.element_type(args[0])
+                };
+
+                let dest = self.ensure_register(dest, ty);
+                let array = self.visit_expr(args[0], DataDest::Alloc);
+                let index = self.visit_expr(args[1], DataDest::Alloc);
+                self.emit_load_array(ty, dest, array, index);
+
+                dest
+            }
+
+            Intrinsic::GenericArraySet | Intrinsic::StrSet => {
+                let ty = if intrinsic == Intrinsic::StrSet {
+                    BytecodeType::Byte
+                } else {
+                    self.element_type(args[0])
+                };
+
+                self.emit_array_set(ty, args[0], args[1], args[2]);
+
+                Register::invalid()
+            }
+
+            Intrinsic::Assert => {
+                self.emit_intrinsic_assert(args[0]);
+                Register::invalid()
+            }
+
+            Intrinsic::Debug => {
+                self.gen.emit_debug();
+                Register::invalid()
+            }
+
+            Intrinsic::SetUint8 => {
+                let address = self.visit_expr(args[0], DataDest::Alloc);
+                let value = self.visit_expr(args[1], DataDest::Alloc);
+                self.gen.emit_set_uint8(address, value);
+
+                Register::invalid()
+            }
+
+            Intrinsic::DefaultValue => self.emit_intrinsic_default_value(id, dest),
+
+            Intrinsic::BoolToInt
+            | Intrinsic::ByteToInt
+            | Intrinsic::BoolToLong
+            | Intrinsic::ByteToLong
+            | Intrinsic::CharToInt
+            | Intrinsic::CharToLong
+            | Intrinsic::IntToByte
+            | Intrinsic::IntToChar
+            | Intrinsic::IntToLong
+            | Intrinsic::IntToFloat
+            | Intrinsic::IntToDouble
+            | Intrinsic::IntAsFloat
+            | Intrinsic::LongToByte
+            | Intrinsic::LongToChar
+            | Intrinsic::LongToInt
+            | Intrinsic::LongToFloat
+            | Intrinsic::LongToDouble
+            | Intrinsic::LongAsDouble
+            | Intrinsic::FloatToInt
+            | Intrinsic::FloatToLong
+            | Intrinsic::FloatToDouble
+            | Intrinsic::FloatAsInt
+            | Intrinsic::DoubleToInt
+            | Intrinsic::DoubleToLong
+            | Intrinsic::DoubleToFloat
+            | Intrinsic::DoubleAsLong => self.emit_intrinsic_conversion(args[0], intrinsic, dest),
+
+            Intrinsic::FloatIsNan | Intrinsic::DoubleIsNan => {
+                let dest = self.ensure_register(dest, BytecodeType::Bool);
+                let src = self.visit_expr(args[0], DataDest::Alloc);
+
+                // NaN is the only value not equal to itself
+                if intrinsic == Intrinsic::FloatIsNan {
+                    self.gen.emit_test_ne_float(dest, src, src);
+                } else {
+                    self.gen.emit_test_ne_double(dest, src, src);
+                }
+
+                dest
+            }
+
+            Intrinsic::FloatSqrt | Intrinsic::DoubleSqrt => {
+                let ty = if intrinsic == Intrinsic::FloatSqrt {
+                    BytecodeType::Float
+                } else {
+                    BytecodeType::Double
+                };
+
+                let dest = self.ensure_register(dest, ty);
+                let src = self.visit_expr(args[0], DataDest::Alloc);
+
+                if intrinsic == Intrinsic::FloatSqrt {
+                    self.gen.emit_sqrt_float(dest, src);
+                } else {
+                    self.gen.emit_sqrt_double(dest, src);
+                }
+
+                dest
+            }
+
+            Intrinsic::BoolNot
+            | Intrinsic::ByteNot
+            | Intrinsic::IntNot
+            | Intrinsic::IntNeg
+            | Intrinsic::IntPlus
+            | Intrinsic::LongNot
+            | Intrinsic::LongNeg
+            | Intrinsic::LongPlus
+            | Intrinsic::FloatNeg
+            | Intrinsic::FloatPlus
+            | Intrinsic::DoubleNeg
+            | Intrinsic::DoublePlus => self.emit_intrinsic_un(args[0], intrinsic, dest),
+
+            Intrinsic::Shl
+            | Intrinsic::BoolEq
+            | Intrinsic::ByteEq
+            | Intrinsic::ByteCmp
+            | Intrinsic::CharEq
+            | Intrinsic::CharCmp
+            | Intrinsic::IntEq
+            | Intrinsic::IntCmp
+            | Intrinsic::IntAdd
+            | Intrinsic::IntSub
+            | Intrinsic::IntMul
+            | Intrinsic::IntDiv
+            | Intrinsic::IntMod
+            | Intrinsic::IntOr
+            | Intrinsic::IntAnd
+            | Intrinsic::IntXor
+            | Intrinsic::IntShl
+            | Intrinsic::IntSar
+            | Intrinsic::IntShr
+            | Intrinsic::LongEq
+            | Intrinsic::LongCmp
+            | Intrinsic::LongAdd
+            | Intrinsic::LongSub
+            | Intrinsic::LongMul
+            | Intrinsic::LongDiv
+            | Intrinsic::LongMod
+            | Intrinsic::LongOr
+            | Intrinsic::LongAnd
+            | Intrinsic::LongXor
+            | Intrinsic::LongShl
+            | Intrinsic::LongSar
+            | Intrinsic::LongShr
+            | Intrinsic::FloatEq
+            | Intrinsic::FloatCmp
+            | Intrinsic::FloatAdd
+            | Intrinsic::FloatSub
+            | Intrinsic::FloatMul
+            | Intrinsic::FloatDiv
+            | Intrinsic::DoubleEq
+            | Intrinsic::DoubleCmp
+            | Intrinsic::DoubleAdd
+            | Intrinsic::DoubleSub
+            | Intrinsic::DoubleMul
+            | Intrinsic::DoubleDiv => {
+                self.emit_intrinsic_bin(args[0], args[1], intrinsic, None, dest)
+            }
+
+            _ => panic!("unknown intrinsic {:?}", intrinsic),
+        }
+    }
+
+    fn element_type(&self, array: &Expr) -> BytecodeType {
+        let ty = self.ty(array.id());
+        ty.type_params(self.vm)[0].into()
+    }
+
+    fn emit_array_set(
+        &mut self,
+        ty: BytecodeType,
+        array: &'ast Expr,
+        index: &'ast Expr,
+        value: &'ast Expr,
+    ) {
+        let array = self.visit_expr(array, DataDest::Alloc);
+        let index = self.visit_expr(index, DataDest::Alloc);
+        let value = self.visit_expr(value, DataDest::Alloc);
+
+        match ty {
+            BytecodeType::Bool => self.gen.emit_store_array_bool(value, array, index),
+            BytecodeType::Byte => self.gen.emit_store_array_byte(value, array, index),
+            BytecodeType::Char => self.gen.emit_store_array_char(value, array, index),
+            BytecodeType::Int => self.gen.emit_store_array_int(value, array, index),
+            BytecodeType::Long => self.gen.emit_store_array_long(value, array, index),
+            BytecodeType::Float => self.gen.emit_store_array_float(value, array, index),
+            BytecodeType::Double => self.gen.emit_store_array_double(value, array, index),
+            BytecodeType::Ptr => self.gen.emit_store_array_ptr(value, array, index),
+        }
+    }
+
+    fn emit_intrinsic_assert(&mut self, cond: &'ast Expr) {
+        let end_lbl = self.gen.create_label();
+        let cond = self.visit_expr(cond, DataDest::Alloc);
+        self.gen.emit_jump_if_true(cond, end_lbl);
+
+        // throw Error("assert failed")
+        let cls_id = self.vm.vips.error_class;
+        let ctor_id = {
+            let cls = self.vm.classes.idx(cls_id);
+            let cls = cls.read();
+            cls.constructor.unwrap()
+        };
+        let cls_def_id = specialize_class_id(self.vm, cls_id);
+
+        let error = self
+            .gen
+            .add_register_chain(&[BytecodeType::Ptr, BytecodeType::Ptr]);
+        self.gen.emit_new_object(error, cls_def_id);
+        let msg = self.gen.add_string_const_pool("assert failed".into());
+        self.gen.emit_const_string(error.offset(1), msg);
+        self.gen.emit_invoke_direct_void(ctor_id, error, 2);
+        self.gen.emit_throw(error);
+
+        self.gen.bind_label(end_lbl);
+    }
+
+    fn emit_intrinsic_default_value(&mut self, id: NodeId, dest: DataDest) -> Register {
+        if dest.is_effect() {
+            return Register::invalid();
+        }
+
+        let ty = self.ty(id);
+        let dest = self.ensure_register(dest, ty.into());
+
+        match ty {
+            BuiltinType::Bool => self.gen.emit_const_false(dest),
+            BuiltinType::Byte => self.gen.emit_const_zero_byte(dest),
+            BuiltinType::Char => self.gen.emit_const_char(dest, '\0'),
+            BuiltinType::Int | BuiltinType::Enum(_) => self.gen.emit_const_zero_int(dest),
+            BuiltinType::Long => self.gen.emit_const_zero_long(dest),
+            BuiltinType::Float => self.gen.emit_const_zero_float(dest),
+            BuiltinType::Double => self.gen.emit_const_zero_double(dest),
+            _ => self.gen.emit_const_nil(dest),
+        }
+
+        dest
+    }
+
+    fn emit_intrinsic_conversion(
+        &mut self,
+        src: &'ast Expr,
+        intrinsic: Intrinsic,
+        dest: DataDest,
+    ) -> Register {
+        let (ty, emit): (BytecodeType, fn(&mut BytecodeGenerator, Register, Register)) =
+            match intrinsic {
+                Intrinsic::BoolToInt | Intrinsic::ByteToInt => (
+                    BytecodeType::Int,
+                    BytecodeGenerator::emit_extend_byte_to_int,
+                ),
+                Intrinsic::BoolToLong | Intrinsic::ByteToLong => (
+                    BytecodeType::Long,
+                    BytecodeGenerator::emit_extend_byte_to_long,
+                ),
+                Intrinsic::CharToInt => {
+                    (BytecodeType::Int, BytecodeGenerator::emit_cast_char_to_int)
+                }
+                Intrinsic::CharToLong => (
+                    BytecodeType::Long,
+                    BytecodeGenerator::emit_extend_char_to_long,
+                ),
+                Intrinsic::IntToByte => {
+                    (BytecodeType::Byte, BytecodeGenerator::emit_cast_int_to_byte)
+                }
+                Intrinsic::IntToChar => {
+                    (BytecodeType::Char, BytecodeGenerator::emit_cast_int_to_char)
+                }
+                Intrinsic::IntToLong => (
+                    BytecodeType::Long,
+                    BytecodeGenerator::emit_extend_int_to_long,
+                ),
+                Intrinsic::IntToFloat => (
+                    BytecodeType::Float,
+                    BytecodeGenerator::emit_convert_int_to_float,
+                ),
+                Intrinsic::IntToDouble => (
+                    BytecodeType::Double,
+                    BytecodeGenerator::emit_convert_int_to_double,
+                ),
+                Intrinsic::IntAsFloat => (
+                    BytecodeType::Float,
+                    BytecodeGenerator::emit_reinterpret_int_as_float,
+                ),
+                Intrinsic::LongToByte => (
+                    BytecodeType::Byte,
+                    BytecodeGenerator::emit_cast_long_to_byte,
+                ),
+                Intrinsic::LongToChar => (
+                    BytecodeType::Char,
+                    BytecodeGenerator::emit_cast_long_to_char,
+                ),
+                Intrinsic::LongToInt => {
+                    (BytecodeType::Int, BytecodeGenerator::emit_cast_long_to_int)
+                }
+                Intrinsic::LongToFloat => (
+                    BytecodeType::Float,
+                    BytecodeGenerator::emit_convert_long_to_float,
+                ),
+                Intrinsic::LongToDouble => (
+                    BytecodeType::Double,
+                    BytecodeGenerator::emit_convert_long_to_double,
+                ),
+                Intrinsic::LongAsDouble => (
+                    BytecodeType::Double,
+                    BytecodeGenerator::emit_reinterpret_long_as_double,
+                ),
+                Intrinsic::FloatToInt => (
+                    BytecodeType::Int,
+                    BytecodeGenerator::emit_truncate_float_to_int,
+                ),
+                Intrinsic::FloatToLong => (
+                    BytecodeType::Long,
+                    BytecodeGenerator::emit_truncate_float_to_long,
+                ),
+                Intrinsic::FloatToDouble => (
+                    BytecodeType::Double,
+                    BytecodeGenerator::emit_promote_float_to_double,
+                ),
+                Intrinsic::FloatAsInt => (
+                    BytecodeType::Int,
+                    BytecodeGenerator::emit_reinterpret_float_as_int,
+                ),
+                Intrinsic::DoubleToInt => (
+                    BytecodeType::Int,
+                    BytecodeGenerator::emit_truncate_double_to_int,
+                ),
+                Intrinsic::DoubleToLong => (
+                    BytecodeType::Long,
+                    BytecodeGenerator::emit_truncate_double_to_long,
+                ),
+                Intrinsic::DoubleToFloat => (
+                    BytecodeType::Float,
+                    BytecodeGenerator::emit_demote_double_to_float,
+                ),
+                Intrinsic::DoubleAsLong => (
+                    BytecodeType::Long,
+                    BytecodeGenerator::emit_reinterpret_double_as_long,
+                ),
+                _ => unreachable!(),
+            };
+
+        if dest.is_effect() {
+            self.visit_expr(src, dest);
+            return Register::invalid();
+        }
+
+        let dest = self.ensure_register(dest, ty);
+        let src = self.visit_expr(src, DataDest::Alloc);
+        emit(&mut self.gen, dest, src);
+
+        dest
+    }
+
+    fn visit_expr_nil(&mut self, _nil: &'ast ExprNilType, dest: DataDest) -> Register {
+        if dest.is_effect() {
+            return Register::invalid();
+        }
+
+        let dest = self.ensure_register(dest, BytecodeType::Ptr);
+
+        self.gen.emit_const_nil(dest);
+
+        dest
+    }
+
+    fn visit_expr_self(&mut self, selfie: &'ast ExprSelfType, dest: DataDest) -> Register {
+        if let Some(&IdentType::Context(fct_id, var_id)) = self.src.map_idents.get(selfie.id) {
+            return self.emit_context_var_load(fct_id, var_id, dest);
+        }
+
+        self.emit_self(dest)
+    }
+
+    fn visit_expr_super(&mut self, _expr: &'ast ExprSuperType, dest: DataDest) -> Register {
+        self.emit_self(dest)
+    }
+
+    fn emit_self(&mut self, dest: DataDest) -> Register {
+        if dest.is_effect() {
+            return Register::invalid();
+        }
+
+        let var_id = self.src.var_self().id;
+        let var_reg = self.var_reg(var_id);
+
+        if dest.is_alloc() {
+            return var_reg;
+        }
+
+        let dest = dest.reg();
+        let ty: BytecodeType = self.src.var_self().ty.into();
+
+        self.emit_mov(ty, dest, var_reg);
+
+        dest
+    }
+
+    fn visit_expr_conv(&mut self, e: &'ast ExprConvType, dest: DataDest) -> Register {
+        let conv = *self.src.map_convs.get(e.id).unwrap();
+        let object = self.visit_expr(&e.object, DataDest::Alloc);
+
+        if e.is {
+            if dest.is_effect() {
+                return Register::invalid();
+            }
+
+            let dest = self.ensure_register(dest, BytecodeType::Bool);
+
+            if conv.valid {
+                // check succeeds for every object which is not nil
+                let nil = self.gen.add_register(BytecodeType::Ptr);
+                self.gen.emit_const_nil(nil);
+                self.gen.emit_test_ne_ptr(dest, object, nil);
+            } else {
+                let cls_def_id = specialize_class_id(self.vm, conv.cls_id);
+                self.gen.emit_instance_of(dest, object, cls_def_id);
+            }
+
+            dest
+        } else {
+            if !conv.valid {
+                // nil passes every cast
+                let cls_def_id = specialize_class_id(self.vm, conv.cls_id);
+                self.gen.emit_checked_cast(object, cls_def_id);
+            }
+
+            match dest {
+                DataDest::Effect => Register::invalid(),
+                DataDest::Alloc => object,
+                DataDest::Reg(dest) => {
+                    self.gen.emit_mov_ptr(dest, object);
+                    dest
+                }
+            }
+        }
+    }
+
+    fn visit_expr_try(&mut self, e: &'ast ExprTryType, dest: DataDest) -> Register {
+        let alt_expr = match e.mode {
+            TryMode::Normal => return self.visit_expr(&e.expr, dest),
+            TryMode::Else(ref alt_expr) => Some(alt_expr),
+            TryMode::Force => None,
+            TryMode::Opt => panic!("unsupported"),
+        };
+
+        let ty = self.ty(e.expr.id());
+        let dest = if dest.is_effect() || ty.is_unit() {
+            DataDest::Effect
+        } else {
+            DataDest::Reg(self.ensure_register(dest, ty.into()))
+        };
+
+        let end_lbl = self.gen.create_label();
+
+        let try_start = self.gen.pc();
+        self.visit_expr(&e.expr, dest);
+        let try_end = self.gen.pc();
+        self.gen.emit_jump(end_lbl);
+
+        let catch = self.gen.pc();
+
+        if let Some(alt_expr) = alt_expr {
+            self.visit_expr(alt_expr, dest);
+        } else {
+            self.gen.emit_trap(Trap::UNEXPECTED);
+        }
+
+        self.gen.bind_label(end_lbl);
+        self.gen
+            .add_exception_handler(try_start, try_end, catch, None, None);
+
+        match dest {
+            DataDest::Reg(dest) => dest,
+            _ => Register::invalid(),
+        }
+    }
+
+    fn visit_expr_lambda(&mut self, e: &'ast ExprLambdaType, dest: DataDest) -> Register {
+        let lambda_id = *self.src.map_lambdas.get(e.id).unwrap();
+        let cls_def_id = specialize_lambda(
+            self.vm,
+            lambda_id,
+            self.cls_type_params,
+            self.fct_type_params,
+        );
+
+        let dest = self.ensure_register(dest, BytecodeType::Ptr);
+        self.gen.emit_new_object(dest, cls_def_id);
+
+        // closure keeps the context of this function alive
+        if let Some((context, _)) = self.context {
+            self.gen
+                .emit_store_field_ptr(context, dest, cls_def_id, FieldId::from(0));
+        }
+
+        dest
+    }
+
+    fn visit_expr_path(&mut self, e: &'ast ExprPathType, dest: DataDest) -> Register {
+        let value = match *self.src.map_idents.get(e.id).unwrap() {
+            IdentType::EnumValue(_, value) => value,
+            _ => unreachable!(),
+        };
+
+        if dest.is_effect() {
+            return Register::invalid();
+        }
+
+        let dest = self.ensure_register(dest, BytecodeType::Int);
+        self.gen.emit_const_int(dest, value as u32);
+
+        dest
+    }
+
+    fn visit_expr_lit_char(&mut self, lit: &'ast ExprLitCharType, dest: DataDest) -> Register {
+        if dest.is_effect() {
             return Register::invalid();
         }
 
@@ -456,7 +1578,7 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
         dest
     }
 
-    fn visit_expr_lit_int(&mut self, lit: &ExprLitIntType, dest: DataDest) -> Register {
+    fn visit_expr_lit_int(&mut self, lit: &'ast ExprLitIntType, dest: DataDest) -> Register {
         if dest.is_effect() {
             return Register::invalid();
         }
@@ -488,7 +1610,7 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
         dest
     }
 
-    fn visit_expr_lit_float(&mut self, lit: &ExprLitFloatType, dest: DataDest) -> Register {
+    fn visit_expr_lit_float(&mut self, lit: &'ast ExprLitFloatType, dest: DataDest) -> Register {
         if dest.is_effect() {
             return Register::invalid();
         }
@@ -517,7 +1639,7 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
         dest
     }
 
-    fn visit_expr_lit_string(&mut self, lit: &ExprLitStrType, dest: DataDest) -> Register {
+    fn visit_expr_lit_string(&mut self, lit: &'ast ExprLitStrType, dest: DataDest) -> Register {
         if dest.is_effect() {
             return Register::invalid();
         }
@@ -531,7 +1653,7 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
         dest
     }
 
-    fn visit_expr_lit_bool(&mut self, lit: &ExprLitBoolType, dest: DataDest) -> Register {
+    fn visit_expr_lit_bool(&mut self, lit: &'ast ExprLitBoolType, dest: DataDest) -> Register {
         if dest.is_effect() {
             return Register::invalid();
         }
@@ -547,65 +1669,106 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
         dest
     }
 
-    fn visit_expr_un(&mut self, expr: &ExprUnType, dest: DataDest) -> Register {
+    fn visit_expr_un(&mut self, expr: &'ast ExprUnType, dest: DataDest) -> Register {
         if let Some(intrinsic) = self.get_intrinsic(expr.id) {
-            if dest.is_effect() {
-                self.visit_expr(&expr.opnd, dest);
-                return Register::invalid();
-            }
-
-            match intrinsic {
-                Intrinsic::IntPlus
-                | Intrinsic::LongPlus
-                | Intrinsic::FloatPlus
-                | Intrinsic::DoublePlus => self.visit_expr(&expr.opnd, dest),
-
-                Intrinsic::IntNeg => {
-                    let dest = self.ensure_register(dest, BytecodeType::Int);
-                    let src = self.visit_expr(&expr.opnd, DataDest::Alloc);
-                    self.gen.emit_neg_int(dest, src);
-
-                    dest
-                }
+            self.emit_intrinsic_un(&expr.opnd, intrinsic, dest)
+        } else {
+            let call_type = self.src.map_calls.get(expr.id).unwrap().clone();
+            let callee_id = self.callee_id(&call_type);
+            let args = [CallArg::Expr(&expr.opnd)];
 
-                Intrinsic::LongNeg => {
-                    let dest = self.ensure_register(dest, BytecodeType::Long);
-                    let src = self.visit_expr(&expr.opnd, DataDest::Alloc);
-                    self.gen.emit_neg_long(dest, src);
+            self.emit_call_site(&call_type, callee_id, &args, dest)
+        }
+    }
 
-                    dest
-                }
+    fn emit_intrinsic_un(
+        &mut self,
+        opnd: &'ast Expr,
+        intrinsic: Intrinsic,
+        dest: DataDest,
+    ) -> Register {
+        if dest.is_effect() {
+            self.visit_expr(opnd, dest);
+            return Register::invalid();
+        }
 
-                Intrinsic::BoolNot => {
-                    let dest = self.ensure_register(dest, BytecodeType::Bool);
-                    let src = self.visit_expr(&expr.opnd, DataDest::Alloc);
-                    self.gen.emit_not_bool(dest, src);
+        let ty = match intrinsic {
+            Intrinsic::IntPlus
+            | Intrinsic::LongPlus
+            | Intrinsic::FloatPlus
+            | Intrinsic::DoublePlus => return self.visit_expr(opnd, dest),
+
+            Intrinsic::BoolNot => BytecodeType::Bool,
+            Intrinsic::ByteNot => BytecodeType::Byte,
+            Intrinsic::IntNeg | Intrinsic::IntNot => BytecodeType::Int,
+            Intrinsic::LongNeg | Intrinsic::LongNot => BytecodeType::Long,
+            Intrinsic::FloatNeg => BytecodeType::Float,
+            Intrinsic::DoubleNeg => BytecodeType::Double,
+            _ => unreachable!(),
+        };
 
-                    dest
-                }
+        let dest = self.ensure_register(dest, ty);
+        let src = self.visit_expr(opnd, DataDest::Alloc);
 
-                _ => unimplemented!(),
-            }
-        } else {
-            unimplemented!()
+        match intrinsic {
+            Intrinsic::BoolNot => self.gen.emit_not_bool(dest, src),
+            Intrinsic::ByteNot => self.gen.emit_not_byte(dest, src),
+            Intrinsic::IntNeg => self.gen.emit_neg_int(dest, src),
+            Intrinsic::IntNot => self.gen.emit_not_int(dest, src),
+            Intrinsic::LongNeg => self.gen.emit_neg_long(dest, src),
+            Intrinsic::LongNot => self.gen.emit_not_long(dest, src),
+            Intrinsic::FloatNeg => self.gen.emit_neg_float(dest, src),
+            Intrinsic::DoubleNeg => self.gen.emit_neg_double(dest, src),
+            _ => unreachable!(),
         }
+
+        dest
     }
 
-    fn visit_expr_bin(&mut self, e: &ExprBinType, dest: DataDest) -> Register {
+    fn visit_expr_bin(&mut self, e: &'ast ExprBinType, dest: DataDest) -> Register {
         if e.op == BinOp::Cmp(CmpOp::Is) || e.op == BinOp::Cmp(CmpOp::IsNot) {
             self.emit_bin_is(e, dest)
         } else if e.op == BinOp::Or {
             self.emit_bin_or(e, dest)
         } else if e.op == BinOp::And {
             self.emit_bin_and(e, dest)
+        } else if self.ty(e.lhs.id()).is_enum() {
+            // enums are compared by value
+            self.emit_bin_is(e, dest)
         } else if let Some(intrinsic) = self.get_intrinsic(e.id) {
-            self.emit_intrinsic_bin(&e.lhs, &e.rhs, intrinsic, e.op, dest)
+            self.emit_intrinsic_bin(&e.lhs, &e.rhs, intrinsic, Some(e.op), dest)
+        } else {
+            self.emit_bin_call(e, dest)
+        }
+    }
+
+    fn emit_bin_call(&mut self, e: &'ast ExprBinType, dest: DataDest) -> Register {
+        let call_type = self.src.map_calls.get(e.id).unwrap().clone();
+        let callee_id = self.callee_id(&call_type);
+        let args = [CallArg::Expr(&e.lhs), CallArg::Expr(&e.rhs)];
+
+        let op = match e.op {
+            BinOp::Cmp(op) if op != CmpOp::Eq && !dest.is_effect() => op,
+            _ => return self.emit_call_site(&call_type, callee_id, &args, dest),
+        };
+
+        let dest = self.ensure_register(dest, BytecodeType::Bool);
+        let result = self.emit_call_site(&call_type, callee_id, &args, DataDest::Alloc);
+
+        if op == CmpOp::Ne {
+            // a != b is !a.equals(b)
+            self.gen.emit_not_bool(dest, result);
         } else {
-            unimplemented!();
+            // a < b is a.compareTo(b) < 0
+            let zero = self.gen.add_register(BytecodeType::Int);
+            self.gen.emit_const_zero_int(zero);
+            self.emit_test(BytecodeType::Int, op, dest, result, zero);
         }
+
+        dest
     }
 
-    fn emit_bin_is(&mut self, e: &ExprBinType, dest: DataDest) -> Register {
+    fn emit_bin_is(&mut self, e: &'ast ExprBinType, dest: DataDest) -> Register {
         if dest.is_effect() {
             self.visit_expr(&e.lhs, dest);
             self.visit_expr(&e.rhs, dest);
@@ -617,16 +1780,43 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
         let lhs_reg = self.visit_expr(&e.lhs, DataDest::Alloc);
         let rhs_reg = self.visit_expr(&e.rhs, DataDest::Alloc);
 
-        if e.op == BinOp::Cmp(CmpOp::Is) {
-            self.gen.emit_test_eq_ptr(dest, lhs_reg, rhs_reg);
-        } else {
-            self.gen.emit_test_ne_ptr(dest, lhs_reg, rhs_reg);
-        }
+        let ty = match self.ty(e.lhs.id()) {
+            BuiltinType::Nil => BytecodeType::Ptr,
+            ty => ty.into(),
+        };
+
+        // floating point values are identical if their bits are equal
+        let (ty, lhs_reg, rhs_reg) = match ty {
+            BytecodeType::Float => {
+                let lhs_bits = self.gen.add_register(BytecodeType::Int);
+                let rhs_bits = self.gen.add_register(BytecodeType::Int);
+                self.gen.emit_reinterpret_float_as_int(lhs_bits, lhs_reg);
+                self.gen.emit_reinterpret_float_as_int(rhs_bits, rhs_reg);
+                (BytecodeType::Int, lhs_bits, rhs_bits)
+            }
+
+            BytecodeType::Double => {
+                let lhs_bits = self.gen.add_register(BytecodeType::Long);
+                let rhs_bits = self.gen.add_register(BytecodeType::Long);
+                self.gen.emit_reinterpret_double_as_long(lhs_bits, lhs_reg);
+                self.gen.emit_reinterpret_double_as_long(rhs_bits, rhs_reg);
+                (BytecodeType::Long, lhs_bits, rhs_bits)
+            }
+
+            _ => (ty, lhs_reg, rhs_reg),
+        };
+
+        let op = match e.op {
+            BinOp::Cmp(CmpOp::Is) | BinOp::Cmp(CmpOp::Eq) => CmpOp::Eq,
+            _ => CmpOp::Ne,
+        };
+
+        self.emit_test(ty, op, dest, lhs_reg, rhs_reg);
 
         dest
     }
 
-    fn emit_bin_or(&mut self, e: &ExprBinType, dest: DataDest) -> Register {
+    fn emit_bin_or(&mut self, e: &'ast ExprBinType, dest: DataDest) -> Register {
         if dest.is_effect() {
             let end_lbl = self.gen.create_label();
             let dest = self.gen.add_register(BytecodeType::Bool);
@@ -650,7 +1840,7 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
         }
     }
 
-    fn emit_bin_and(&mut self, e: &ExprBinType, dest: DataDest) -> Register {
+    fn emit_bin_and(&mut self, e: &'ast ExprBinType, dest: DataDest) -> Register {
         if dest.is_effect() {
             let end_lbl = self.gen.create_label();
             let dest = self.gen.add_register(BytecodeType::Bool);
@@ -676,26 +1866,37 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
 
     fn emit_intrinsic_bin(
         &mut self,
-        lhs: &Expr,
-        rhs: &Expr,
+        lhs: &'ast Expr,
+        rhs: &'ast Expr,
         intrinsic: Intrinsic,
-        op: BinOp,
+        op: Option<BinOp>,
         dest: DataDest,
     ) -> Register {
+        let operand_type: BytecodeType = self.ty(lhs.id()).into();
+
         let result_type = match intrinsic {
-            Intrinsic::IntAdd
-            | Intrinsic::IntSub
-            | Intrinsic::IntMul
-            | Intrinsic::IntDiv
-            | Intrinsic::IntMod
-            | Intrinsic::IntOr
-            | Intrinsic::IntAnd
-            | Intrinsic::IntXor
-            | Intrinsic::IntShl
-            | Intrinsic::IntShr
-            | Intrinsic::IntSar => BytecodeType::Int,
-            Intrinsic::IntEq | Intrinsic::IntCmp => BytecodeType::Bool,
-            _ => unimplemented!(),
+            Intrinsic::BoolEq
+            | Intrinsic::ByteEq
+            | Intrinsic::CharEq
+            | Intrinsic::IntEq
+            | Intrinsic::LongEq
+            | Intrinsic::FloatEq
+            | Intrinsic::DoubleEq => BytecodeType::Bool,
+
+            Intrinsic::ByteCmp
+            | Intrinsic::CharCmp
+            | Intrinsic::IntCmp
+            | Intrinsic::LongCmp
+            | Intrinsic::FloatCmp
+            | Intrinsic::DoubleCmp => {
+                if op.is_some() {
+                    BytecodeType::Bool
+                } else {
+                    BytecodeType::Int
+                }
+            }
+
+            _ => operand_type,
         };
 
         if dest.is_effect() {
@@ -718,62 +1919,259 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
             Intrinsic::IntOr => self.gen.emit_or_int(dest, lhs_reg, rhs_reg),
             Intrinsic::IntAnd => self.gen.emit_and_int(dest, lhs_reg, rhs_reg),
             Intrinsic::IntXor => self.gen.emit_xor_int(dest, lhs_reg, rhs_reg),
-            Intrinsic::IntShl => self.gen.emit_shl_int(dest, lhs_reg, rhs_reg),
+            Intrinsic::IntShl | Intrinsic::Shl => self.gen.emit_shl_int(dest, lhs_reg, rhs_reg),
             Intrinsic::IntShr => self.gen.emit_shr_int(dest, lhs_reg, rhs_reg),
             Intrinsic::IntSar => self.gen.emit_sar_int(dest, lhs_reg, rhs_reg),
-            Intrinsic::IntEq => match op {
-                BinOp::Cmp(CmpOp::Eq) => self.gen.emit_test_eq_int(dest, lhs_reg, rhs_reg),
-                BinOp::Cmp(CmpOp::Ne) => self.gen.emit_test_ne_int(dest, lhs_reg, rhs_reg),
-                _ => unreachable!(),
-            },
-            Intrinsic::IntCmp => match op {
-                BinOp::Cmp(CmpOp::Lt) => self.gen.emit_test_lt_int(dest, lhs_reg, rhs_reg),
-                BinOp::Cmp(CmpOp::Le) => self.gen.emit_test_le_int(dest, lhs_reg, rhs_reg),
-                BinOp::Cmp(CmpOp::Gt) => self.gen.emit_test_gt_int(dest, lhs_reg, rhs_reg),
-                BinOp::Cmp(CmpOp::Ge) => self.gen.emit_test_ge_int(dest, lhs_reg, rhs_reg),
-                _ => unreachable!(),
+
+            Intrinsic::LongAdd => self.gen.emit_add_long(dest, lhs_reg, rhs_reg),
+            Intrinsic::LongSub => self.gen.emit_sub_long(dest, lhs_reg, rhs_reg),
+            Intrinsic::LongMul => self.gen.emit_mul_long(dest, lhs_reg, rhs_reg),
+            Intrinsic::LongDiv => self.gen.emit_div_long(dest, lhs_reg, rhs_reg),
+            Intrinsic::LongMod => self.gen.emit_mod_long(dest, lhs_reg, rhs_reg),
+            Intrinsic::LongOr => self.gen.emit_or_long(dest, lhs_reg, rhs_reg),
+            Intrinsic::LongAnd => self.gen.emit_and_long(dest, lhs_reg, rhs_reg),
+            Intrinsic::LongXor => self.gen.emit_xor_long(dest, lhs_reg, rhs_reg),
+            Intrinsic::LongShl => self.gen.emit_shl_long(dest, lhs_reg, rhs_reg),
+            Intrinsic::LongShr => self.gen.emit_shr_long(dest, lhs_reg, rhs_reg),
+            Intrinsic::LongSar => self.gen.emit_sar_long(dest, lhs_reg, rhs_reg),
+
+            Intrinsic::FloatAdd => self.gen.emit_add_float(dest, lhs_reg, rhs_reg),
+            Intrinsic::FloatSub => self.gen.emit_sub_float(dest, lhs_reg, rhs_reg),
+            Intrinsic::FloatMul => self.gen.emit_mul_float(dest, lhs_reg, rhs_reg),
+            Intrinsic::FloatDiv => self.gen.emit_div_float(dest, lhs_reg, rhs_reg),
+
+            Intrinsic::DoubleAdd => self.gen.emit_add_double(dest, lhs_reg, rhs_reg),
+            Intrinsic::DoubleSub => self.gen.emit_sub_double(dest, lhs_reg, rhs_reg),
+            Intrinsic::DoubleMul => self.gen.emit_mul_double(dest, lhs_reg, rhs_reg),
+            Intrinsic::DoubleDiv => self.gen.emit_div_double(dest, lhs_reg, rhs_reg),
+
+            Intrinsic::BoolEq
+            | Intrinsic::ByteEq
+            | Intrinsic::CharEq
+            | Intrinsic::IntEq
+            | Intrinsic::LongEq
+            | Intrinsic::FloatEq
+            | Intrinsic::DoubleEq => {
+                let op = match op {
+                    Some(BinOp::Cmp(op)) => op,
+                    _ => CmpOp::Eq,
+                };
+
+                self.emit_test(operand_type, op, dest, lhs_reg, rhs_reg);
+            }
+
+            Intrinsic::ByteCmp
+            | Intrinsic::CharCmp
+            | Intrinsic::IntCmp
+            | Intrinsic::LongCmp
+            | Intrinsic::FloatCmp
+            | Intrinsic::DoubleCmp => match op {
+                Some(BinOp::Cmp(op)) => self.emit_test(operand_type, op, dest, lhs_reg, rhs_reg),
+                _ => self.emit_compare_to(operand_type, dest, lhs_reg, rhs_reg),
             },
-            _ => unimplemented!(),
+
+            _ => unreachable!(),
         }
 
         dest
     }
 
-    fn visit_expr_assign(&mut self, e: &ExprAssignType, dest: DataDest) -> Register {
+    // compareTo() returns the difference of both operands
+    fn emit_compare_to(&mut self, ty: BytecodeType, dest: Register, lhs: Register, rhs: Register) {
+        match ty {
+            BytecodeType::Int => self.gen.emit_sub_int(dest, lhs, rhs),
+
+            BytecodeType::Long => {
+                let result = self.gen.add_register(BytecodeType::Long);
+                self.gen.emit_sub_long(result, lhs, rhs);
+                self.gen.emit_cast_long_to_int(dest, result);
+            }
+
+            BytecodeType::Byte | BytecodeType::Char => {
+                let lhs_int = self.gen.add_register(BytecodeType::Int);
+                let rhs_int = self.gen.add_register(BytecodeType::Int);
+
+                if ty == BytecodeType::Byte {
+                    self.gen.emit_extend_byte_to_int(lhs_int, lhs);
+                    self.gen.emit_extend_byte_to_int(rhs_int, rhs);
+                } else {
+                    self.gen.emit_cast_char_to_int(lhs_int, lhs);
+                    self.gen.emit_cast_char_to_int(rhs_int, rhs);
+                }
+
+                self.gen.emit_sub_int(dest, lhs_int, rhs_int);
+            }
+
+            _ => unimplemented!(),
+        }
+    }
+
+    fn emit_test(
+        &mut self,
+        ty: BytecodeType,
+        op: CmpOp,
+        dest: Register,
+        lhs: Register,
+        rhs: Register,
+    ) {
+        let gen = &mut self.gen;
+
+        match (ty, op) {
+            (BytecodeType::Bool, CmpOp::Eq) => gen.emit_test_eq_bool(dest, lhs, rhs),
+            (BytecodeType::Bool, CmpOp::Ne) => gen.emit_test_ne_bool(dest, lhs, rhs),
+
+            (BytecodeType::Byte, CmpOp::Eq) => gen.emit_test_eq_byte(dest, lhs, rhs),
+            (BytecodeType::Byte, CmpOp::Ne) => gen.emit_test_ne_byte(dest, lhs, rhs),
+            (BytecodeType::Byte, CmpOp::Gt) => gen.emit_test_gt_byte(dest, lhs, rhs),
+            (BytecodeType::Byte, CmpOp::Ge) => gen.emit_test_ge_byte(dest, lhs, rhs),
+            (BytecodeType::Byte, CmpOp::Lt) => gen.emit_test_lt_byte(dest, lhs, rhs),
+            (BytecodeType::Byte, CmpOp::Le) => gen.emit_test_le_byte(dest, lhs, rhs),
+
+            (BytecodeType::Char, CmpOp::Eq) => gen.emit_test_eq_char(dest, lhs, rhs),
+            (BytecodeType::Char, CmpOp::Ne) => gen.emit_test_ne_char(dest, lhs, rhs),
+            (BytecodeType::Char, CmpOp::Gt) => gen.emit_test_gt_char(dest, lhs, rhs),
+            (BytecodeType::Char, CmpOp::Ge) => gen.emit_test_ge_char(dest, lhs, rhs),
+            (BytecodeType::Char, CmpOp::Lt) => gen.emit_test_lt_char(dest, lhs, rhs),
+            (BytecodeType::Char, CmpOp::Le) => gen.emit_test_le_char(dest, lhs, rhs),
+
+            (BytecodeType::Int, CmpOp::Eq) => gen.emit_test_eq_int(dest, lhs, rhs),
+            (BytecodeType::Int, CmpOp::Ne) => gen.emit_test_ne_int(dest, lhs, rhs),
+            (BytecodeType::Int, CmpOp::Gt) => gen.emit_test_gt_int(dest, lhs, rhs),
+            (BytecodeType::Int, CmpOp::Ge) => gen.emit_test_ge_int(dest, lhs, rhs),
+            (BytecodeType::Int, CmpOp::Lt) => gen.emit_test_lt_int(dest, lhs, rhs),
+            (BytecodeType::Int, CmpOp::Le) => gen.emit_test_le_int(dest, lhs, rhs),
+
+            (BytecodeType::Long, CmpOp::Eq) => gen.emit_test_eq_long(dest, lhs, rhs),
+            (BytecodeType::Long, CmpOp::Ne) => gen.emit_test_ne_long(dest, lhs, rhs),
+            (BytecodeType::Long, CmpOp::Gt) => gen.emit_test_gt_long(dest, lhs, rhs),
+            (BytecodeType::Long, CmpOp::Ge) => gen.emit_test_ge_long(dest, lhs, rhs),
+            (BytecodeType::Long, CmpOp::Lt) => gen.emit_test_lt_long(dest, lhs, rhs),
+            (BytecodeType::Long, CmpOp::Le) => gen.emit_test_le_long(dest, lhs, rhs),
+
+            (BytecodeType::Float, CmpOp::Eq) => gen.emit_test_eq_float(dest, lhs, rhs),
+            (BytecodeType::Float, CmpOp::Ne) => gen.emit_test_ne_float(dest, lhs, rhs),
+            (BytecodeType::Float, CmpOp::Gt) => gen.emit_test_gt_float(dest, lhs, rhs),
+            (BytecodeType::Float, CmpOp::Ge) => gen.emit_test_ge_float(dest, lhs, rhs),
+            (BytecodeType::Float, CmpOp::Lt) => gen.emit_test_lt_float(dest, lhs, rhs),
+            (BytecodeType::Float, CmpOp::Le) => gen.emit_test_le_float(dest, lhs, rhs),
+
+            (BytecodeType::Double, CmpOp::Eq) => gen.emit_test_eq_double(dest, lhs, rhs),
+            (BytecodeType::Double, CmpOp::Ne) => gen.emit_test_ne_double(dest, lhs, rhs),
+            (BytecodeType::Double, CmpOp::Gt) => gen.emit_test_gt_double(dest, lhs, rhs),
+            (BytecodeType::Double, CmpOp::Ge) => gen.emit_test_ge_double(dest, lhs, rhs),
+            (BytecodeType::Double, CmpOp::Lt) => gen.emit_test_lt_double(dest, lhs, rhs),
+            (BytecodeType::Double, CmpOp::Le) => gen.emit_test_le_double(dest, lhs, rhs),
+
+            (BytecodeType::Ptr, CmpOp::Eq) => gen.emit_test_eq_ptr(dest, lhs, rhs),
+            (BytecodeType::Ptr, CmpOp::Ne) => gen.emit_test_ne_ptr(dest, lhs, rhs),
+
+            _ => unreachable!(),
+        }
+    }
+
+    fn visit_expr_assign(&mut self, e: &'ast ExprAssignType, dest: DataDest) -> Register {
         assert!(dest.is_effect());
 
-        if e.lhs.is_ident() {
-            let ident_type = self.src.map_idents.get(e.lhs.id()).unwrap();
-            match ident_type {
-                &IdentType::Var(var_id) => {
+        if e.lhs.is_call() {
+            self.visit_expr_assign_call(e);
+            return Register::invalid();
+        }
+
+        let ident_type = self.src.map_idents.get(e.lhs.id()).unwrap().clone();
+
+        match ident_type {
+            IdentType::Var(var_id) => {
+                if self.is_context_var(var_id) {
+                    let value = self.visit_expr(&e.rhs, DataDest::Alloc);
+                    self.emit_var_store(var_id, value);
+                } else {
                     let var_reg = self.var_reg(var_id);
                     self.visit_expr(&e.rhs, DataDest::Reg(var_reg));
                 }
+            }
+
+            IdentType::Context(fct_id, var_id) => {
+                let value = self.visit_expr(&e.rhs, DataDest::Alloc);
+                self.emit_context_var_store(fct_id, var_id, value);
+            }
 
-                &IdentType::Global(_) => unimplemented!(),
-                &IdentType::Field(_, _) => unimplemented!(),
+            IdentType::Global(gid) => {
+                let ty: BytecodeType = self.vm.globals.idx(gid).lock().ty.into();
+                let value = self.visit_expr(&e.rhs, DataDest::Alloc);
 
-                &IdentType::Struct(_) => unimplemented!(),
-                &IdentType::Const(_) => unreachable!(),
-                &IdentType::Fct(_) | &IdentType::FctType(_, _) => unreachable!(),
-                &IdentType::Class(_) | &IdentType::ClassType(_, _) => unimplemented!(),
-                &IdentType::Method(_, _) | &IdentType::MethodType(_, _, _) => unimplemented!(),
-                &IdentType::StaticMethod(_, _) | &IdentType::StaticMethodType(_, _, _) => {
-                    unreachable!()
+                match ty {
+                    BytecodeType::Bool => self.gen.emit_store_global_bool(value, gid),
+                    BytecodeType::Byte => self.gen.emit_store_global_byte(value, gid),
+                    BytecodeType::Char => self.gen.emit_store_global_char(value, gid),
+                    BytecodeType::Int => self.gen.emit_store_global_int(value, gid),
+                    BytecodeType::Long => self.gen.emit_store_global_long(value, gid),
+                    BytecodeType::Float => self.gen.emit_store_global_float(value, gid),
+                    BytecodeType::Double => self.gen.emit_store_global_double(value, gid),
+                    BytecodeType::Ptr => self.gen.emit_store_global_ptr(value, gid),
                 }
             }
-        } else {
-            unimplemented!();
+
+            IdentType::Field(class, field_id) => {
+                let obj = if let Some(dot) = e.lhs.to_dot() {
+                    self.visit_expr(&dot.object, DataDest::Alloc)
+                } else {
+                    self.var_reg(self.src.var_self().id)
+                };
+
+                let (cls_id, ty) = self.field_info(class, field_id);
+                let value = self.visit_expr(&e.rhs, DataDest::Alloc);
+
+                self.emit_store_field(ty, value, obj, cls_id, field_id);
+            }
+
+            IdentType::Struct(_) => unimplemented!(),
+            IdentType::Const(_) => unreachable!(),
+            IdentType::Enum(_) | IdentType::EnumValue(_, _) => unreachable!(),
+            IdentType::Fct(_) | IdentType::FctType(_, _) => unreachable!(),
+            IdentType::Class(_) | IdentType::ClassType(_, _) => unreachable!(),
+            IdentType::Method(_, _) | IdentType::MethodType(_, _, _) => unreachable!(),
+            IdentType::StaticMethod(_, _) | IdentType::StaticMethodType(_, _, _) => unreachable!(),
         }
 
         Register::invalid()
     }
 
-    fn visit_expr_ident(&mut self, ident: &ExprIdentType, dest: DataDest) -> Register {
-        let ident_type = self.src.map_idents.get(ident.id).unwrap();
+    // a(i) = v is a call to a.set(i, v)
+    fn visit_expr_assign_call(&mut self, e: &'ast ExprAssignType) {
+        let call_expr = e.lhs.to_call().unwrap();
+        let object = &call_expr.callee;
+        let index = &call_expr.args[0];
+
+        if let Some(intrinsic) = self.get_intrinsic(e.id) {
+            let ty = match intrinsic {
+                Intrinsic::GenericArraySet => self.element_type(object),
+                Intrinsic::StrSet => BytecodeType::Byte,
+                _ => panic!("unexpected intrinsic {:?}", intrinsic),
+            };
+
+            self.emit_array_set(ty, object, index, &e.rhs);
+        } else {
+            let call_type = self.src.map_calls.get(e.id).unwrap().clone();
+            let callee_id = self.callee_id(&call_type);
+            let args = [
+                CallArg::Expr(object),
+                CallArg::Expr(index),
+                CallArg::Expr(&e.rhs),
+            ];
+
+            self.emit_call_site(&call_type, callee_id, &args, DataDest::Effect);
+        }
+    }
+
+    fn visit_expr_ident(&mut self, ident: &'ast ExprIdentType, dest: DataDest) -> Register {
+        let ident_type = self.src.map_idents.get(ident.id).unwrap().clone();
 
         match ident_type {
-            &IdentType::Var(var_id) => {
+            IdentType::Var(var_id) => {
+                if self.is_context_var(var_id) {
+                    return self.emit_context_var_load(self.fct.id, var_id, dest);
+                }
+
                 if dest.is_effect() {
                     return Register::invalid();
                 }
@@ -788,22 +2186,15 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
                 let dest = dest.reg();
 
                 if dest != var_reg {
-                    match ty {
-                        BytecodeType::Bool => self.gen.emit_mov_bool(dest, var_reg),
-                        BytecodeType::Byte => self.gen.emit_mov_byte(dest, var_reg),
-                        BytecodeType::Char => self.gen.emit_mov_char(dest, var_reg),
-                        BytecodeType::Int => self.gen.emit_mov_int(dest, var_reg),
-                        BytecodeType::Long => self.gen.emit_mov_long(dest, var_reg),
-                        BytecodeType::Float => self.gen.emit_mov_float(dest, var_reg),
-                        BytecodeType::Double => self.gen.emit_mov_double(dest, var_reg),
-                        BytecodeType::Ptr => self.gen.emit_mov_ptr(dest, var_reg),
-                    }
+                    self.emit_mov(ty, dest, var_reg);
                 }
 
                 dest
             }
 
-            &IdentType::Global(gid) => {
+            IdentType::Context(fct_id, var_id) => self.emit_context_var_load(fct_id, var_id, dest),
+
+            IdentType::Global(gid) => {
                 if dest.is_effect() {
                     return Register::invalid();
                 }
@@ -828,17 +2219,331 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
                 dest
             }
 
-            &IdentType::Field(_, _) => unimplemented!(),
-            &IdentType::Struct(_) => unimplemented!(),
-            &IdentType::Const(_) => unimplemented!(),
+            IdentType::Field(class, field_id) => {
+                let (cls_id, ty) = self.field_info(class, field_id);
+                let dest = self.ensure_register(dest, ty);
+                let obj = self.var_reg(self.src.var_self().id);
+
+                self.emit_load_field(ty, dest, obj, cls_id, field_id);
+
+                dest
+            }
+
+            IdentType::Struct(_) => unimplemented!(),
+            IdentType::Const(const_id) => self.emit_const(const_id, dest),
+
+            IdentType::Enum(_) | IdentType::EnumValue(_, _) => unreachable!(),
+            IdentType::Fct(_) | IdentType::FctType(_, _) => unreachable!(),
+            IdentType::Class(_) | IdentType::ClassType(_, _) => unreachable!(),
+            IdentType::Method(_, _) | IdentType::MethodType(_, _, _) => unreachable!(),
+            IdentType::StaticMethod(_, _) | IdentType::StaticMethodType(_, _, _) => unreachable!(),
+        }
+    }
+
+    fn emit_const(&mut self, const_id: ConstId, dest: DataDest) -> Register {
+        if dest.is_effect() {
+            return Register::invalid();
+        }
+
+        let xconst = self.vm.consts.idx(const_id);
+        let xconst = xconst.lock();
+        let ty = xconst.ty;
+
+        let dest = self.ensure_register(dest, ty.into());
+
+        match ty {
+            BuiltinType::Bool => {
+                if xconst.value.to_bool() {
+                    self.gen.emit_const_true(dest);
+                } else {
+                    self.gen.emit_const_false(dest);
+                }
+            }
+
+            BuiltinType::Char => self.gen.emit_const_char(dest, xconst.value.to_char()),
+            BuiltinType::Byte => self.gen.emit_const_byte(dest, xconst.value.to_int() as u8),
+            BuiltinType::Int => self.gen.emit_const_int(dest, xconst.value.to_int() as u32),
+            BuiltinType::Long => self.gen.emit_const_long(dest, xconst.value.to_int() as u64),
+            BuiltinType::Float => self
+                .gen
+                .emit_const_float(dest, xconst.value.to_float() as f32),
+            BuiltinType::Double => self.gen.emit_const_double(dest, xconst.value.to_float()),
+            _ => unimplemented!(),
+        }
+
+        dest
+    }
+
+    fn field_info(&self, class: BuiltinType, field_id: FieldId) -> (ClassDefId, BytecodeType) {
+        let class = self.specialize_type(class);
+        let cls_id = specialize_class_ty(self.vm, class);
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+        let field = &cls.fields[field_id.idx()];
+
+        (cls_id, field.ty.into())
+    }
+
+    fn emit_load_field(
+        &mut self,
+        ty: BytecodeType,
+        dest: Register,
+        obj: Register,
+        cls_id: ClassDefId,
+        field_id: FieldId,
+    ) {
+        match ty {
+            BytecodeType::Byte => self.gen.emit_load_field_byte(dest, obj, cls_id, field_id),
+            BytecodeType::Bool => self.gen.emit_load_field_bool(dest, obj, cls_id, field_id),
+            BytecodeType::Char => self.gen.emit_load_field_char(dest, obj, cls_id, field_id),
+            BytecodeType::Int => self.gen.emit_load_field_int(dest, obj, cls_id, field_id),
+            BytecodeType::Long => self.gen.emit_load_field_long(dest, obj, cls_id, field_id),
+            BytecodeType::Float => self.gen.emit_load_field_float(dest, obj, cls_id, field_id),
+            BytecodeType::Double => self.gen.emit_load_field_double(dest, obj, cls_id, field_id),
+            BytecodeType::Ptr => self.gen.emit_load_field_ptr(dest, obj, cls_id, field_id),
+        }
+    }
+
+    fn emit_store_field(
+        &mut self,
+        ty: BytecodeType,
+        src: Register,
+        obj: Register,
+        cls_id: ClassDefId,
+        field_id: FieldId,
+    ) {
+        match ty {
+            BytecodeType::Byte => self.gen.emit_store_field_byte(src, obj, cls_id, field_id),
+            BytecodeType::Bool => self.gen.emit_store_field_bool(src, obj, cls_id, field_id),
+            BytecodeType::Char => self.gen.emit_store_field_char(src, obj, cls_id, field_id),
+            BytecodeType::Int => self.gen.emit_store_field_int(src, obj, cls_id, field_id),
+            BytecodeType::Long => self.gen.emit_store_field_long(src, obj, cls_id, field_id),
+            BytecodeType::Float => self.gen.emit_store_field_float(src, obj, cls_id, field_id),
+            BytecodeType::Double => self.gen.emit_store_field_double(src, obj, cls_id, field_id),
+            BytecodeType::Ptr => self.gen.emit_store_field_ptr(src, obj, cls_id, field_id),
+        }
+    }
+
+    fn emit_load_array(
+        &mut self,
+        ty: BytecodeType,
+        dest: Register,
+        array: Register,
+        index: Register,
+    ) {
+        match ty {
+            BytecodeType::Bool => self.gen.emit_load_array_bool(dest, array, index),
+            BytecodeType::Byte => self.gen.emit_load_array_byte(dest, array, index),
+            BytecodeType::Char => self.gen.emit_load_array_char(dest, array, index),
+            BytecodeType::Int => self.gen.emit_load_array_int(dest, array, index),
+            BytecodeType::Long => self.gen.emit_load_array_long(dest, array, index),
+            BytecodeType::Float => self.gen.emit_load_array_float(dest, array, index),
+            BytecodeType::Double => self.gen.emit_load_array_double(dest, array, index),
+            BytecodeType::Ptr => self.gen.emit_load_array_ptr(dest, array, index),
+        }
+    }
+
+    fn emit_mov(&mut self, ty: BytecodeType, dest: Register, src: Register) {
+        match ty {
+            BytecodeType::Bool => self.gen.emit_mov_bool(dest, src),
+            BytecodeType::Byte => self.gen.emit_mov_byte(dest, src),
+            BytecodeType::Char => self.gen.emit_mov_char(dest, src),
+            BytecodeType::Int => self.gen.emit_mov_int(dest, src),
+            BytecodeType::Long => self.gen.emit_mov_long(dest, src),
+            BytecodeType::Float => self.gen.emit_mov_float(dest, src),
+            BytecodeType::Double => self.gen.emit_mov_double(dest, src),
+            BytecodeType::Ptr => self.gen.emit_mov_ptr(dest, src),
+        }
+    }
+
+    fn emit_ret(&mut self, ty: BytecodeType, src: Register) {
+        match ty {
+            BytecodeType::Bool => self.gen.emit_ret_bool(src),
+            BytecodeType::Byte => self.gen.emit_ret_byte(src),
+            BytecodeType::Char => self.gen.emit_ret_char(src),
+            BytecodeType::Int => self.gen.emit_ret_int(src),
+            BytecodeType::Long => self.gen.emit_ret_long(src),
+            BytecodeType::Float => self.gen.emit_ret_float(src),
+            BytecodeType::Double => self.gen.emit_ret_double(src),
+            BytecodeType::Ptr => self.gen.emit_ret_ptr(src),
+        }
+    }
+
+    // allocates the context object on function entry and moves
+    // captured parameters into it
+    fn emit_context_allocation(&mut self) {
+        let cls_id = specialize_context(
+            self.vm,
+            self.src,
+            self.cls_type_params,
+            self.fct_type_params,
+        );
+
+        let context = self.gen.add_register(BytecodeType::Ptr);
+        self.gen.emit_new_object(context, cls_id);
+        self.context = Some((context, cls_id));
+
+        if self.fct.is_lambda() {
+            // slot 0 stores context of the outer function, which is kept alive by the closure
+            let outer = self.load_outer_context();
+            self.gen
+                .emit_store_field_ptr(outer, context, cls_id, FieldId::from(0));
+        }
+
+        let mut params = Vec::with_capacity(self.ast.params.len() + 1);
+
+        if self.fct.has_self() && !self.fct.is_lambda() {
+            params.push(self.src.var_self().id);
+        }
+
+        for param in &self.ast.params {
+            params.push(*self.src.map_vars.get(param.id).unwrap());
+        }
+
+        for var_id in params {
+            if self.is_context_var(var_id) {
+                let var_reg = self.var_reg(var_id);
+                self.emit_var_store(var_id, var_reg);
+            }
+        }
+    }
 
-            &IdentType::Fct(_) | &IdentType::FctType(_, _) => unreachable!(),
-            &IdentType::Class(_) | &IdentType::ClassType(_, _) => unreachable!(),
-            &IdentType::Method(_, _) | &IdentType::MethodType(_, _, _) => unreachable!(),
-            &IdentType::StaticMethod(_, _) | &IdentType::StaticMethodType(_, _, _) => {
-                unreachable!()
+    // loads the context stored in the closure of this lambda
+    fn load_outer_context(&mut self) -> Register {
+        let closure_class = match self.closure_class {
+            Some(cls_id) => cls_id,
+            None => {
+                let cls_id = specialize_lambda(
+                    self.vm,
+                    self.fct.id,
+                    self.cls_type_params,
+                    self.fct_type_params,
+                );
+                self.closure_class = Some(cls_id);
+                cls_id
             }
+        };
+
+        let closure = self.var_reg(self.src.var_self().id);
+        let outer = self.gen.add_register(BytecodeType::Ptr);
+        self.gen
+            .emit_load_field_ptr(outer, closure, closure_class, FieldId::from(0));
+
+        outer
+    }
+
+    // loads context object of function `fct_id` into a register
+    fn load_context(&mut self, fct_id: FctId) -> Register {
+        if fct_id == self.fct.id {
+            return self.context.expect("function without context").0;
+        }
+
+        let mut context = self.load_outer_context();
+
+        // follow slot 0 of contexts until reaching the owner of the variable
+        let mut outer_id = self.fct.outer_fct_id();
+
+        while outer_id != fct_id {
+            let cls_id = self.context_class(outer_id);
+            let outer = self.gen.add_register(BytecodeType::Ptr);
+            self.gen
+                .emit_load_field_ptr(outer, context, cls_id, FieldId::from(0));
+            context = outer;
+
+            outer_id = self.vm.fcts.idx(outer_id).read().outer_fct_id();
+        }
+
+        context
+    }
+
+    fn context_class(&mut self, fct_id: FctId) -> ClassDefId {
+        if fct_id == self.fct.id {
+            return self.context.expect("function without context").1;
+        }
+
+        if let Some(&cls_id) = self.context_classes.get(&fct_id) {
+            return cls_id;
+        }
+
+        let cls_id = {
+            let fct = self.vm.fcts.idx(fct_id);
+            let fct = fct.read();
+            let src = fct.src();
+            let src = src.read();
+
+            specialize_context(self.vm, &src, self.cls_type_params, self.fct_type_params)
+        };
+
+        self.context_classes.insert(fct_id, cls_id);
+        cls_id
+    }
+
+    fn context_var(&mut self, fct_id: FctId, var_id: VarId) -> (ClassDefId, FieldId, BytecodeType) {
+        let (slot, ty) = if fct_id == self.fct.id {
+            (self.src.context_slot(var_id), self.src.vars[var_id].ty)
+        } else {
+            let fct = self.vm.fcts.idx(fct_id);
+            let fct = fct.read();
+            let src = fct.src();
+            let src = src.read();
+
+            (src.context_slot(var_id), src.vars[var_id].ty)
+        };
+
+        let slot = slot.expect("variable not captured");
+        let cls_id = self.context_class(fct_id);
+
+        (cls_id, FieldId::from(slot), self.specialize_type(ty).into())
+    }
+
+    fn emit_context_var_load(&mut self, fct_id: FctId, var_id: VarId, dest: DataDest) -> Register {
+        if dest.is_effect() {
+            return Register::invalid();
+        }
+
+        let (cls_id, field_id, ty) = self.context_var(fct_id, var_id);
+        let context = self.load_context(fct_id);
+        let dest = self.ensure_register(dest, ty);
+
+        self.emit_load_field(ty, dest, context, cls_id, field_id);
+
+        dest
+    }
+
+    fn emit_context_var_store(&mut self, fct_id: FctId, var_id: VarId, src: Register) {
+        let (cls_id, field_id, ty) = self.context_var(fct_id, var_id);
+        let context = self.load_context(fct_id);
+
+        self.emit_store_field(ty, src, context, cls_id, field_id);
+    }
+
+    // stores `src` into a variable of this function
+    fn emit_var_store(&mut self, var_id: VarId, src: Register) {
+        if self.is_context_var(var_id) {
+            self.emit_context_var_store(self.fct.id, var_id, src);
+        } else {
+            let ty: BytecodeType = self.specialize_type(self.src.vars[var_id].ty).into();
+            let var_reg = self.var_reg(var_id);
+            self.emit_mov(ty, var_reg, src);
+        }
+    }
+
+    fn is_context_var(&self, var_id: VarId) -> bool {
+        self.src.context_slot(var_id).is_some()
+    }
+
+    // allocates the register of a variable, variables captured
+    // by lambdas live in the context object instead
+    fn declare_var(&mut self, var_id: VarId) -> Option<Register> {
+        if self.is_context_var(var_id) {
+            return None;
         }
+
+        let ty: BytecodeType = self.specialize_type(self.src.vars[var_id].ty).into();
+        let var_reg = self.gen.add_register(ty);
+        self.var_registers.insert(var_id, var_reg);
+
+        Some(var_reg)
     }
 
     fn var_reg(&self, var_id: VarId) -> Register {
@@ -874,10 +2579,12 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
                 specialize_type(self.vm, ty, type_params, &TypeParams::empty())
             }
 
-            CallType::Expr(ty, _) => {
-                let type_params = ty.type_params(self.vm);
+            CallType::Expr(object_ty, _) => {
+                let type_params = object_ty.type_params(self.vm);
                 specialize_type(self.vm, ty, &type_params, &TypeParams::empty())
             }
+
+            CallType::Lambda(_) => unreachable!(),
         };
 
         self.specialize_type(ty)
@@ -887,6 +2594,19 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
         specialize_type(self.vm, ty, self.cls_type_params, self.fct_type_params)
     }
 
+    fn specialize_type_params(&self, type_params: &TypeParams) -> TypeParams {
+        let type_params = type_params
+            .iter()
+            .map(|ty| self.specialize_type(ty))
+            .collect::<Vec<_>>();
+
+        type_params.into()
+    }
+
+    fn ty(&self, id: NodeId) -> BuiltinType {
+        self.specialize_type(self.src.ty(id))
+    }
+
     fn get_intrinsic(&self, id: NodeId) -> Option<Intrinsic> {
         let fid = self.src.map_calls.get(id).unwrap().fct_id();
 
@@ -905,6 +2625,21 @@ impl<'a, 'ast> AstBytecodeGen<'a, 'ast> {
     }
 }
 
+#[derive(Copy, Clone)]
+enum CallArg<'ast> {
+    Expr(&'ast Expr),
+    Reg(Register),
+    Selfie,
+    SelfieNew(ClassDefId),
+}
+
+#[derive(Copy, Clone)]
+enum InvokeKind {
+    Direct,
+    Virtual,
+    Static,
+}
+
 #[derive(Copy, Clone)]
 enum DataDest {
     // Do not store result. Only interested in side-effects of
@@ -948,8 +2683,10 @@ mod tests {
     use crate::bytecode::astgen;
     use crate::bytecode::generate::{BytecodeFunction, BytecodeIdx, Register, StrConstPoolIdx};
     use crate::bytecode::opcode::Bytecode::*;
-    use crate::class::TypeParams;
+    use crate::class::{FieldId, TypeParams};
+    use crate::os::signal::Trap;
     use crate::test;
+    use crate::ty::BuiltinType;
     use crate::vm::VM;
 
     fn code(code: &'static str) -> BytecodeFunction {
@@ -1923,6 +3660,496 @@ mod tests {
         assert_eq!(expected, fct.code());
     }
 
+    #[test]
+    fn gen_method_call() {
+        gen(
+            "class Foo { fun g() -> Int { return 1; } }
+            fun f(a: Foo) -> Int { return a.g(); }",
+            |vm, fct| {
+                let g = vm.cls_method_by_name("Foo", "g", false).unwrap();
+                let expected = vec![
+                    MovPtr(r(2), r(0)),
+                    NilCheck(r(2)),
+                    InvokeDirectInt(r(1), g, r(2), 1),
+                    RetInt(r(1)),
+                ];
+                assert_eq!(expected, fct.code());
+            },
+        );
+    }
+
+    #[test]
+    fn gen_virtual_method_call() {
+        gen(
+            "@open class Foo { @open fun g() {} }
+            fun f(a: Foo) { a.g(); }",
+            |vm, fct| {
+                let g = vm.cls_method_by_name("Foo", "g", false).unwrap();
+                let expected = vec![MovPtr(r(1), r(0)), InvokeVirtualVoid(g, r(1), 1), RetVoid];
+                assert_eq!(expected, fct.code());
+            },
+        );
+    }
+
+    #[test]
+    fn gen_super_call() {
+        test::parse(
+            "@open class A { @open fun g() {} }
+            class B: A { @override fun g() {} fun f() { super.g(); } }",
+            |vm| {
+                let fct_id = vm.cls_method_by_name("B", "f", false).unwrap();
+                let tp = TypeParams::empty();
+                let fct = astgen::generate(vm, fct_id, &tp, &tp);
+                let g = vm.cls_method_by_name("A", "g", false).unwrap();
+                let expected = vec![MovPtr(r(1), r(0)), InvokeDirectVoid(g, r(1), 1), RetVoid];
+                assert_eq!(expected, fct.code());
+            },
+        );
+    }
+
+    #[test]
+    fn gen_lambda_call() {
+        let fct = code("fun f(a: (Int) -> Int) -> Int { return a(1); }");
+        let expected = vec![
+            MovPtr(r(2), r(0)),
+            ConstInt(r(3), 1),
+            InvokeLambdaInt(r(1), r(2), 2),
+            RetInt(r(1)),
+        ];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_store_field() {
+        gen(
+            "class Foo(var bar: Int) fun f(a: Foo, b: Int) { a.bar = b; }",
+            |vm, fct| {
+                let (cls, field) = vm.field_by_name("Foo", "bar");
+                let expected = vec![StoreFieldInt(r(1), r(0), cls, field), RetVoid];
+                assert_eq!(expected, fct.code());
+            },
+        );
+    }
+
+    #[test]
+    fn gen_implicit_field_access() {
+        test::parse(
+            "class Foo(var bar: Int) {
+                fun f(b: Int) -> Int { self.bar = b; return self.bar; }
+            }",
+            |vm| {
+                let fct_id = vm.cls_method_by_name("Foo", "f", false).unwrap();
+                let tp = TypeParams::empty();
+                let fct = astgen::generate(vm, fct_id, &tp, &tp);
+                let (cls, field) = vm.field_by_name("Foo", "bar");
+                let expected = vec![
+                    StoreFieldInt(r(1), r(0), cls, field),
+                    LoadFieldInt(r(2), r(0), cls, field),
+                    RetInt(r(2)),
+                ];
+                assert_eq!(expected, fct.code());
+            },
+        );
+    }
+
+    #[test]
+    fn gen_store_global() {
+        gen("var a: Int; fun f(b: Int) { a = b; }", |vm, fct| {
+            let gid = vm.global_by_name("a");
+            let expected = vec![StoreGlobalInt(r(0), gid), RetVoid];
+            assert_eq!(expected, fct.code());
+        });
+    }
+
+    #[test]
+    fn gen_const() {
+        let fct = code("const A: Int = 1; fun f() -> Int { return A; }");
+        let expected = vec![ConstInt(r(0), 1), RetInt(r(0))];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_stmt_for() {
+        gen("fun f() { for i in range(0, 3) {} }", |vm, fct| {
+            let range = vm.fct_by_name("range").unwrap();
+            let make_iterator = vm
+                .cls_method_by_name("IntRange", "makeIterator", false)
+                .unwrap();
+            let has_next = vm
+                .cls_method_by_name("IntRangeIter", "hasNext", false)
+                .unwrap();
+            let next = vm
+                .cls_method_by_name("IntRangeIter", "next", false)
+                .unwrap();
+            let expected = vec![
+                ConstZeroInt(r(2)),
+                ConstInt(r(3), 3),
+                InvokeStaticPtr(r(1), range, r(2), 2),
+                NilCheck(r(1)),
+                InvokeDirectPtr(r(0), make_iterator, r(1), 1),
+                MovPtr(r(6), r(0)),
+                InvokeDirectBool(r(5), has_next, r(6), 1),
+                JumpIfFalse(r(5), bc(11)),
+                MovPtr(r(7), r(0)),
+                InvokeDirectInt(r(4), next, r(7), 1),
+                Jump(bc(5)),
+                RetVoid,
+            ];
+            assert_eq!(expected, fct.code());
+        });
+    }
+
+    #[test]
+    fn gen_stmt_throw() {
+        let fct = code("fun f(a: Exception) { throw a; }");
+        let expected = vec![NilCheck(r(0)), Throw(r(0)), RetVoid];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_expr_try_else() {
+        gen(
+            "fun g() throws -> Int { return 1; }
+            fun f() -> Int { return try g() else 0; }",
+            |vm, fct| {
+                let g = vm.fct_by_name("g").unwrap();
+                let expected = vec![
+                    InvokeStaticInt(r(0), g, r(0), 0),
+                    Jump(bc(3)),
+                    ConstZeroInt(r(0)),
+                    RetInt(r(0)),
+                ];
+                assert_eq!(expected, fct.code());
+                let handlers = fct.exception_handlers();
+                assert_eq!(1, handlers.len());
+                assert_eq!(bc(0), handlers[0].try_start);
+                assert_eq!(bc(1), handlers[0].try_end);
+                assert_eq!(bc(2), handlers[0].catch);
+                assert_eq!(None, handlers[0].catch_type);
+            },
+        );
+    }
+
+    #[test]
+    fn gen_expr_try_force() {
+        gen(
+            "fun g() throws -> Int { return 1; }
+            fun f() -> Int { return try! g(); }",
+            |vm, fct| {
+                let g = vm.fct_by_name("g").unwrap();
+                let expected = vec![
+                    InvokeStaticInt(r(0), g, r(0), 0),
+                    Jump(bc(3)),
+                    Trap(Trap::UNEXPECTED),
+                    RetInt(r(0)),
+                ];
+                assert_eq!(expected, fct.code());
+                assert_eq!(1, fct.exception_handlers().len());
+            },
+        );
+    }
+
+    #[test]
+    fn gen_stmt_do_catch() {
+        gen(
+            "fun g() throws {}
+            fun f() { do { try g(); } catch e: Exception {} }",
+            |vm, fct| {
+                let g = vm.fct_by_name("g").unwrap();
+                let expected = vec![
+                    InvokeStaticVoid(g, r(0), 0),
+                    Jump(bc(3)),
+                    Jump(bc(3)),
+                    RetVoid,
+                ];
+                assert_eq!(expected, fct.code());
+                let handlers = fct.exception_handlers();
+                assert_eq!(1, handlers.len());
+                assert_eq!(bc(0), handlers[0].try_start);
+                assert_eq!(bc(1), handlers[0].try_end);
+                assert_eq!(bc(2), handlers[0].catch);
+                assert_eq!(Some(r(0)), handlers[0].exception);
+                assert_eq!(
+                    Some(vm.cls_def_by_name("Exception")),
+                    handlers[0].catch_type
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn gen_stmt_do_finally() {
+        gen(
+            "fun g() throws {}
+            fun f() throws { do { try g(); } finally { try g(); } }",
+            |vm, fct| {
+                let g = vm.fct_by_name("g").unwrap();
+                let expected = vec![
+                    InvokeStaticVoid(g, r(0), 0),
+                    InvokeStaticVoid(g, r(0), 0),
+                    Jump(bc(5)),
+                    InvokeStaticVoid(g, r(0), 0),
+                    Throw(r(0)),
+                    RetVoid,
+                ];
+                assert_eq!(expected, fct.code());
+                let handlers = fct.exception_handlers();
+                assert_eq!(1, handlers.len());
+                assert_eq!(bc(3), handlers[0].catch);
+                assert_eq!(None, handlers[0].catch_type);
+            },
+        );
+    }
+
+    #[test]
+    fn gen_stmt_return_in_do_finally() {
+        gen(
+            "fun g() throws {}
+            fun f() throws -> Int { do { return 1; } finally { try g(); } }",
+            |vm, fct| {
+                let g = vm.fct_by_name("g").unwrap();
+                let expected = vec![
+                    ConstInt(r(0), 1),
+                    InvokeStaticVoid(g, r(0), 0),
+                    RetInt(r(0)),
+                    InvokeStaticVoid(g, r(0), 0),
+                    Throw(r(1)),
+                ];
+                assert_eq!(expected, fct.code());
+            },
+        );
+    }
+
+    #[test]
+    fn gen_stmt_defer() {
+        gen(
+            "fun g() {}
+            fun f() { defer g(); g(); }",
+            |vm, fct| {
+                let g = vm.fct_by_name("g").unwrap();
+                let expected = vec![
+                    InvokeStaticVoid(g, r(0), 0),
+                    InvokeStaticVoid(g, r(0), 0),
+                    Jump(bc(5)),
+                    InvokeStaticVoid(g, r(0), 0),
+                    Throw(r(0)),
+                    RetVoid,
+                ];
+                assert_eq!(expected, fct.code());
+                assert_eq!(1, fct.exception_handlers().len());
+            },
+        );
+    }
+
+    #[test]
+    fn gen_lambda_with_context() {
+        let fct = code("fun f() { var a = 1; let b = || { a = 2; }; }");
+        let (ctx_cls, lambda_cls) = match (&fct.code()[0], &fct.code()[3]) {
+            (&NewObject(_, ctx_cls), &NewObject(_, lambda_cls)) => (ctx_cls, lambda_cls),
+            _ => panic!("context and lambda allocation expected"),
+        };
+        let expected = vec![
+            NewObject(r(0), ctx_cls),
+            ConstInt(r(1), 1),
+            StoreFieldInt(r(1), r(0), ctx_cls, FieldId::from(1)),
+            NewObject(r(2), lambda_cls),
+            StoreFieldPtr(r(0), r(2), lambda_cls, FieldId::from(0)),
+            RetVoid,
+        ];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_array_length() {
+        let fct = code("fun f(a: Array[Int]) -> Int { return a.length(); }");
+        let expected = vec![ArrayLength(r(1), r(0)), RetInt(r(1))];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_array_get() {
+        let fct = code("fun f(a: Array[Int]) -> Int { return a(0); }");
+        let expected = vec![
+            ConstZeroInt(r(2)),
+            LoadArrayInt(r(1), r(0), r(2)),
+            RetInt(r(1)),
+        ];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_array_set() {
+        let fct = code("fun f(a: Array[Int], b: Int) { a(0) = b; }");
+        let expected = vec![ConstZeroInt(r(2)), StoreArrayInt(r(1), r(0), r(2)), RetVoid];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_string_get_byte() {
+        let fct = code("fun f(a: String) -> Byte { return a.getByte(0); }");
+        let expected = vec![
+            ConstZeroInt(r(2)),
+            LoadArrayByte(r(1), r(0), r(2)),
+            RetByte(r(1)),
+        ];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_expr_is() {
+        gen(
+            "@open class A class B: A
+            fun f(a: A) -> Bool { return a is B; }",
+            |vm, fct| {
+                let cls = vm.cls_def_by_name("B");
+                let expected = vec![InstanceOf(r(1), r(0), cls), RetBool(r(1))];
+                assert_eq!(expected, fct.code());
+            },
+        );
+    }
+
+    #[test]
+    fn gen_expr_as() {
+        gen(
+            "@open class A class B: A
+            fun f(a: A) -> B { return a as B; }",
+            |vm, fct| {
+                let cls = vm.cls_def_by_name("B");
+                let expected = vec![CheckedCast(r(0), cls), RetPtr(r(0))];
+                assert_eq!(expected, fct.code());
+            },
+        );
+    }
+
+    #[test]
+    fn gen_enum_compare() {
+        let fct = code("enum Foo { A, B } fun f(a: Foo) -> Bool { return a == Foo::B; }");
+        let expected = vec![
+            ConstInt(r(2), 1),
+            TestEqInt(r(1), r(0), r(2)),
+            RetBool(r(1)),
+        ];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_assert() {
+        gen("fun f(a: Bool) { assert(a); }", |vm, fct| {
+            let cls = vm.cls_def_by_name("Error");
+            let ctor = vm.ctor_by_name("Error");
+            let expected = vec![
+                JumpIfTrue(r(0), bc(5)),
+                NewObject(r(1), cls),
+                ConstString(r(2), sp(0)),
+                InvokeDirectVoid(ctor, r(1), 2),
+                Throw(r(1)),
+                RetVoid,
+            ];
+            assert_eq!(expected, fct.code());
+        });
+    }
+
+    #[test]
+    fn gen_debug() {
+        let fct = code("fun f() { debug(); }");
+        let expected = vec![Debug, RetVoid];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_intrinsic_conversion() {
+        let fct = code("fun f(a: Int) -> Long { return a.toLong(); }");
+        let expected = vec![ExtendIntToLong(r(1), r(0)), RetLong(r(1))];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_intrinsic_is_nan() {
+        let fct = code("fun f(a: Double) -> Bool { return a.isNan(); }");
+        let expected = vec![TestNeDouble(r(1), r(0), r(0)), RetBool(r(1))];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_add_long() {
+        let fct = code("fun f(a: Long, b: Long) -> Long { return a + b; }");
+        let expected = vec![AddLong(r(2), r(0), r(1)), RetLong(r(2))];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_test_lt_float() {
+        let fct = code("fun f(a: Float, b: Float) -> Bool { return a < b; }");
+        let expected = vec![TestLtFloat(r(2), r(0), r(1)), RetBool(r(2))];
+        assert_eq!(expected, fct.code());
+    }
+
+    #[test]
+    fn gen_operator_method() {
+        gen(
+            "class Foo { fun plus(b: Foo) -> Foo { return self; } }
+            fun f(a: Foo, b: Foo) -> Foo { return a + b; }",
+            |vm, fct| {
+                let plus = vm.cls_method_by_name("Foo", "plus", false).unwrap();
+                let expected = vec![
+                    MovPtr(r(3), r(0)),
+                    NilCheck(r(3)),
+                    MovPtr(r(4), r(1)),
+                    InvokeDirectPtr(r(2), plus, r(3), 2),
+                    RetPtr(r(2)),
+                ];
+                assert_eq!(expected, fct.code());
+            },
+        );
+    }
+
+    #[test]
+    fn gen_new_object_into_var() {
+        gen("fun f() { let a = Object(); }", |vm, fct| {
+            let cls_id = vm.cls_def_by_name("Object");
+            let ctor_id = vm.ctor_by_name("Object");
+            let expected = vec![
+                NewObject(r(1), cls_id),
+                InvokeDirectVoid(ctor_id, r(1), 1),
+                MovPtr(r(0), r(1)),
+                RetVoid,
+            ];
+            assert_eq!(expected, fct.code());
+        });
+    }
+
+    #[test]
+    fn gen_new_array() {
+        gen(
+            "fun f() -> Array[Int] { return Array[Int](3); }",
+            |_, fct| {
+                let code = fct.code();
+                assert_eq!(ConstInt(r(1), 3), code[0]);
+                match code[1] {
+                    NewArray(dest, _, len) => {
+                        assert_eq!(r(0), dest);
+                        assert_eq!(r(1), len);
+                    }
+                    _ => panic!("NewArray expected"),
+                }
+                assert_eq!(RetPtr(r(0)), code[3]);
+            },
+        );
+    }
+
+    #[test]
+    fn gen_generic_call_type_params() {
+        gen(
+            "fun g[T](a: T) {}
+            fun f() { g[Int](1); }",
+            |_, fct| {
+                let (cls_tps, fct_tps) = fct.type_params(bc(1));
+                assert_eq!(TypeParams::empty(), cls_tps);
+                assert_eq!(TypeParams::with(vec![BuiltinType::Int]), fct_tps);
+            },
+        );
+    }
+
     fn r(val: usize) -> Register {
         Register(val)
     }
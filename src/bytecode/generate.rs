@@ -6,9 +6,12 @@ use std::mem;
 use crate::mem as cratemem;
 
 use crate::bytecode::opcode::Bytecode;
-use crate::class::{ClassDefId, FieldId};
+use crate::class::{ClassDefId, FieldId, TypeParams};
+use crate::os::signal::Trap;
 use crate::ty::{BuiltinType, MachineMode};
-use crate::vm::{FctId, GlobalId};
+use crate::vm::{FctId, GlobalId, VarId};
+use dora_parser::ast::NodeId;
+use dora_parser::lexer::position::Position;
 
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Register(pub usize);
@@ -40,7 +43,7 @@ impl fmt::Display for Register {
 #[derive(Copy, Clone, PartialEq, Debug, Eq, Hash)]
 pub struct Label(pub usize);
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct BytecodeIdx(pub usize);
 
 impl BytecodeIdx {
@@ -59,7 +62,7 @@ impl fmt::Display for BytecodeIdx {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum BytecodeType {
     Bool,
     Byte,
@@ -109,7 +112,9 @@ impl From<BuiltinType> for BytecodeType {
             BuiltinType::Long => BytecodeType::Long,
             BuiltinType::Float => BytecodeType::Float,
             BuiltinType::Double => BytecodeType::Double,
-            BuiltinType::Class(_, _) => BytecodeType::Ptr,
+            BuiltinType::Enum(_) => BytecodeType::Int,
+            BuiltinType::Nil | BuiltinType::Ptr => BytecodeType::Ptr,
+            BuiltinType::Class(_, _) | BuiltinType::Lambda(_) => BytecodeType::Ptr,
             _ => panic!("BuiltinType cannot converted to BytecodeType"),
         }
     }
@@ -124,12 +129,26 @@ impl fmt::Display for StrConstPoolIdx {
     }
 }
 
+// exceptions thrown in `try_start..try_end` continue at `catch`, when
+// `catch_type` is None the handler catches every exception
+pub struct ExceptionHandler {
+    pub try_start: BytecodeIdx,
+    pub try_end: BytecodeIdx,
+    pub catch: BytecodeIdx,
+    pub exception: Option<Register>,
+    pub catch_type: Option<ClassDefId>,
+}
+
 pub struct BytecodeGenerator {
     code: Vec<Bytecode>,
     labels: Vec<Option<BytecodeIdx>>,
     unresolved_jumps: Vec<(BytecodeIdx, Label)>,
     registers: Vec<BytecodeType>,
     string_pool_map: HashMap<String, StrConstPoolIdx>,
+    exception_handlers: Vec<ExceptionHandler>,
+    type_params: HashMap<BytecodeIdx, (TypeParams, TypeParams)>,
+    positions: Vec<(BytecodeIdx, Position)>,
+    loop_starts: HashMap<NodeId, BytecodeIdx>,
 }
 
 impl BytecodeGenerator {
@@ -140,6 +159,10 @@ impl BytecodeGenerator {
             unresolved_jumps: Vec::new(),
             registers: Vec::new(),
             string_pool_map: HashMap::new(),
+            exception_handlers: Vec::new(),
+            type_params: HashMap::new(),
+            positions: Vec::new(),
+            loop_starts: HashMap::new(),
         }
     }
 
@@ -180,10 +203,63 @@ impl BytecodeGenerator {
         self.labels[lbl.0]
     }
 
-    fn pc(&self) -> BytecodeIdx {
+    pub fn pc(&self) -> BytecodeIdx {
         BytecodeIdx(self.code.len())
     }
 
+    // the next instruction starts the loop statement `id`
+    pub fn add_loop_start(&mut self, id: NodeId) {
+        let pc = self.pc();
+        self.loop_starts.insert(id, pc);
+    }
+
+    pub fn add_exception_handler(
+        &mut self,
+        try_start: BytecodeIdx,
+        try_end: BytecodeIdx,
+        catch: BytecodeIdx,
+        exception: Option<Register>,
+        catch_type: Option<ClassDefId>,
+    ) {
+        self.exception_handlers.push(ExceptionHandler {
+            try_start: try_start,
+            try_end: try_end,
+            catch: catch,
+            exception: exception,
+            catch_type: catch_type,
+        });
+    }
+
+    // type params of the callee for the invoke emitted next
+    pub fn set_type_params(&mut self, cls_type_params: TypeParams, fct_type_params: TypeParams) {
+        if cls_type_params.len() > 0 || fct_type_params.len() > 0 {
+            self.type_params
+                .insert(self.pc(), (cls_type_params, fct_type_params));
+        }
+    }
+
+    // source position of all instructions emitted from now on
+    pub fn set_position(&mut self, pos: Position) {
+        let pc = self.pc();
+
+        if let Some(&mut (last_pc, ref mut last_pos)) = self.positions.last_mut() {
+            if last_pc == pc {
+                *last_pos = pos;
+                return;
+            }
+
+            if *last_pos == pos {
+                return;
+            }
+        }
+
+        self.positions.push((pc, pos));
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        self.positions.last().map(|&(_, pos)| pos)
+    }
+
     pub fn emit_add_int(&mut self, dest: Register, lhs: Register, rhs: Register) {
         self.code.push(Bytecode::AddInt(dest, lhs, rhs));
     }
@@ -852,7 +928,585 @@ impl BytecodeGenerator {
         self.code.push(Bytecode::NewObject(dest, cls_id));
     }
 
-    pub fn generate(mut self) -> BytecodeFunction {
+    pub fn emit_sub_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::SubLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_sub_float(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::SubFloat(dest, lhs, rhs));
+    }
+
+    pub fn emit_sub_double(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::SubDouble(dest, lhs, rhs));
+    }
+
+    pub fn emit_neg_float(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::NegFloat(dest, src));
+    }
+
+    pub fn emit_neg_double(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::NegDouble(dest, src));
+    }
+
+    pub fn emit_mul_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::MulLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_mul_float(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::MulFloat(dest, lhs, rhs));
+    }
+
+    pub fn emit_mul_double(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::MulDouble(dest, lhs, rhs));
+    }
+
+    pub fn emit_div_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::DivLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_div_float(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::DivFloat(dest, lhs, rhs));
+    }
+
+    pub fn emit_div_double(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::DivDouble(dest, lhs, rhs));
+    }
+
+    pub fn emit_mod_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::ModLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_and_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::AndLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_or_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::OrLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_xor_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::XorLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_not_byte(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::NotByte(dest, src));
+    }
+
+    pub fn emit_not_int(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::NotInt(dest, src));
+    }
+
+    pub fn emit_not_long(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::NotLong(dest, src));
+    }
+
+    pub fn emit_shl_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::ShlLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_shr_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::ShrLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_sar_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::SarLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_sqrt_float(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::SqrtFloat(dest, src));
+    }
+
+    pub fn emit_sqrt_double(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::SqrtDouble(dest, src));
+    }
+
+    pub fn emit_extend_byte_to_int(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::ExtendByteToInt(dest, src));
+    }
+
+    pub fn emit_extend_byte_to_long(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::ExtendByteToLong(dest, src));
+    }
+
+    pub fn emit_extend_char_to_long(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::ExtendCharToLong(dest, src));
+    }
+
+    pub fn emit_extend_int_to_long(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::ExtendIntToLong(dest, src));
+    }
+
+    pub fn emit_cast_char_to_int(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::CastCharToInt(dest, src));
+    }
+
+    pub fn emit_cast_int_to_char(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::CastIntToChar(dest, src));
+    }
+
+    pub fn emit_cast_int_to_byte(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::CastIntToByte(dest, src));
+    }
+
+    pub fn emit_cast_long_to_byte(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::CastLongToByte(dest, src));
+    }
+
+    pub fn emit_cast_long_to_char(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::CastLongToChar(dest, src));
+    }
+
+    pub fn emit_cast_long_to_int(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::CastLongToInt(dest, src));
+    }
+
+    pub fn emit_convert_int_to_float(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::ConvertIntToFloat(dest, src));
+    }
+
+    pub fn emit_convert_int_to_double(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::ConvertIntToDouble(dest, src));
+    }
+
+    pub fn emit_convert_long_to_float(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::ConvertLongToFloat(dest, src));
+    }
+
+    pub fn emit_convert_long_to_double(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::ConvertLongToDouble(dest, src));
+    }
+
+    pub fn emit_truncate_float_to_int(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::TruncateFloatToInt(dest, src));
+    }
+
+    pub fn emit_truncate_float_to_long(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::TruncateFloatToLong(dest, src));
+    }
+
+    pub fn emit_truncate_double_to_int(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::TruncateDoubleToInt(dest, src));
+    }
+
+    pub fn emit_truncate_double_to_long(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::TruncateDoubleToLong(dest, src));
+    }
+
+    pub fn emit_promote_float_to_double(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::PromoteFloatToDouble(dest, src));
+    }
+
+    pub fn emit_demote_double_to_float(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::DemoteDoubleToFloat(dest, src));
+    }
+
+    pub fn emit_reinterpret_float_as_int(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::ReinterpretFloatAsInt(dest, src));
+    }
+
+    pub fn emit_reinterpret_int_as_float(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::ReinterpretIntAsFloat(dest, src));
+    }
+
+    pub fn emit_reinterpret_double_as_long(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::ReinterpretDoubleAsLong(dest, src));
+    }
+
+    pub fn emit_reinterpret_long_as_double(&mut self, dest: Register, src: Register) {
+        self.code.push(Bytecode::ReinterpretLongAsDouble(dest, src));
+    }
+
+    pub fn emit_store_field_bool(
+        &mut self,
+        src: Register,
+        obj: Register,
+        cls: ClassDefId,
+        field: FieldId,
+    ) {
+        self.code
+            .push(Bytecode::StoreFieldBool(src, obj, cls, field));
+    }
+
+    pub fn emit_store_field_byte(
+        &mut self,
+        src: Register,
+        obj: Register,
+        cls: ClassDefId,
+        field: FieldId,
+    ) {
+        self.code
+            .push(Bytecode::StoreFieldByte(src, obj, cls, field));
+    }
+
+    pub fn emit_store_field_char(
+        &mut self,
+        src: Register,
+        obj: Register,
+        cls: ClassDefId,
+        field: FieldId,
+    ) {
+        self.code
+            .push(Bytecode::StoreFieldChar(src, obj, cls, field));
+    }
+
+    pub fn emit_store_field_int(
+        &mut self,
+        src: Register,
+        obj: Register,
+        cls: ClassDefId,
+        field: FieldId,
+    ) {
+        self.code
+            .push(Bytecode::StoreFieldInt(src, obj, cls, field));
+    }
+
+    pub fn emit_store_field_long(
+        &mut self,
+        src: Register,
+        obj: Register,
+        cls: ClassDefId,
+        field: FieldId,
+    ) {
+        self.code
+            .push(Bytecode::StoreFieldLong(src, obj, cls, field));
+    }
+
+    pub fn emit_store_field_float(
+        &mut self,
+        src: Register,
+        obj: Register,
+        cls: ClassDefId,
+        field: FieldId,
+    ) {
+        self.code
+            .push(Bytecode::StoreFieldFloat(src, obj, cls, field));
+    }
+
+    pub fn emit_store_field_double(
+        &mut self,
+        src: Register,
+        obj: Register,
+        cls: ClassDefId,
+        field: FieldId,
+    ) {
+        self.code
+            .push(Bytecode::StoreFieldDouble(src, obj, cls, field));
+    }
+
+    pub fn emit_store_field_ptr(
+        &mut self,
+        src: Register,
+        obj: Register,
+        cls: ClassDefId,
+        field: FieldId,
+    ) {
+        self.code
+            .push(Bytecode::StoreFieldPtr(src, obj, cls, field));
+    }
+
+    pub fn emit_store_global_bool(&mut self, src: Register, gid: GlobalId) {
+        self.code.push(Bytecode::StoreGlobalBool(src, gid));
+    }
+
+    pub fn emit_store_global_byte(&mut self, src: Register, gid: GlobalId) {
+        self.code.push(Bytecode::StoreGlobalByte(src, gid));
+    }
+
+    pub fn emit_store_global_char(&mut self, src: Register, gid: GlobalId) {
+        self.code.push(Bytecode::StoreGlobalChar(src, gid));
+    }
+
+    pub fn emit_store_global_int(&mut self, src: Register, gid: GlobalId) {
+        self.code.push(Bytecode::StoreGlobalInt(src, gid));
+    }
+
+    pub fn emit_store_global_long(&mut self, src: Register, gid: GlobalId) {
+        self.code.push(Bytecode::StoreGlobalLong(src, gid));
+    }
+
+    pub fn emit_store_global_float(&mut self, src: Register, gid: GlobalId) {
+        self.code.push(Bytecode::StoreGlobalFloat(src, gid));
+    }
+
+    pub fn emit_store_global_double(&mut self, src: Register, gid: GlobalId) {
+        self.code.push(Bytecode::StoreGlobalDouble(src, gid));
+    }
+
+    pub fn emit_store_global_ptr(&mut self, src: Register, gid: GlobalId) {
+        self.code.push(Bytecode::StoreGlobalPtr(src, gid));
+    }
+
+    pub fn emit_load_array_bool(&mut self, dest: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::LoadArrayBool(dest, arr, idx));
+    }
+
+    pub fn emit_load_array_byte(&mut self, dest: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::LoadArrayByte(dest, arr, idx));
+    }
+
+    pub fn emit_load_array_char(&mut self, dest: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::LoadArrayChar(dest, arr, idx));
+    }
+
+    pub fn emit_load_array_int(&mut self, dest: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::LoadArrayInt(dest, arr, idx));
+    }
+
+    pub fn emit_load_array_long(&mut self, dest: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::LoadArrayLong(dest, arr, idx));
+    }
+
+    pub fn emit_load_array_float(&mut self, dest: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::LoadArrayFloat(dest, arr, idx));
+    }
+
+    pub fn emit_load_array_double(&mut self, dest: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::LoadArrayDouble(dest, arr, idx));
+    }
+
+    pub fn emit_load_array_ptr(&mut self, dest: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::LoadArrayPtr(dest, arr, idx));
+    }
+
+    pub fn emit_store_array_bool(&mut self, src: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::StoreArrayBool(src, arr, idx));
+    }
+
+    pub fn emit_store_array_byte(&mut self, src: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::StoreArrayByte(src, arr, idx));
+    }
+
+    pub fn emit_store_array_char(&mut self, src: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::StoreArrayChar(src, arr, idx));
+    }
+
+    pub fn emit_store_array_int(&mut self, src: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::StoreArrayInt(src, arr, idx));
+    }
+
+    pub fn emit_store_array_long(&mut self, src: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::StoreArrayLong(src, arr, idx));
+    }
+
+    pub fn emit_store_array_float(&mut self, src: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::StoreArrayFloat(src, arr, idx));
+    }
+
+    pub fn emit_store_array_double(&mut self, src: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::StoreArrayDouble(src, arr, idx));
+    }
+
+    pub fn emit_store_array_ptr(&mut self, src: Register, arr: Register, idx: Register) {
+        self.code.push(Bytecode::StoreArrayPtr(src, arr, idx));
+    }
+
+    pub fn emit_array_length(&mut self, dest: Register, arr: Register) {
+        self.code.push(Bytecode::ArrayLength(dest, arr));
+    }
+
+    pub fn emit_set_uint8(&mut self, address: Register, value: Register) {
+        self.code.push(Bytecode::SetUint8(address, value));
+    }
+
+    pub fn emit_test_eq_bool(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestEqBool(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_ne_bool(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestNeBool(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_eq_byte(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestEqByte(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_ne_byte(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestNeByte(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_gt_byte(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestGtByte(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_ge_byte(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestGeByte(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_lt_byte(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestLtByte(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_le_byte(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestLeByte(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_eq_char(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestEqChar(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_ne_char(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestNeChar(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_gt_char(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestGtChar(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_ge_char(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestGeChar(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_lt_char(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestLtChar(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_le_char(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestLeChar(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_eq_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestEqLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_ne_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestNeLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_gt_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestGtLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_ge_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestGeLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_lt_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestLtLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_le_long(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestLeLong(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_eq_float(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestEqFloat(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_ne_float(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestNeFloat(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_gt_float(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestGtFloat(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_ge_float(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestGeFloat(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_lt_float(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestLtFloat(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_le_float(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestLeFloat(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_eq_double(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestEqDouble(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_ne_double(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestNeDouble(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_gt_double(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestGtDouble(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_ge_double(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestGeDouble(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_lt_double(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestLtDouble(dest, lhs, rhs));
+    }
+
+    pub fn emit_test_le_double(&mut self, dest: Register, lhs: Register, rhs: Register) {
+        self.code.push(Bytecode::TestLeDouble(dest, lhs, rhs));
+    }
+
+    pub fn emit_new_array(&mut self, dest: Register, cls: ClassDefId, length: Register) {
+        self.code.push(Bytecode::NewArray(dest, cls, length));
+    }
+
+    pub fn emit_nil_check(&mut self, obj: Register) {
+        self.code.push(Bytecode::NilCheck(obj));
+    }
+
+    pub fn emit_instance_of(&mut self, dest: Register, obj: Register, cls: ClassDefId) {
+        self.code.push(Bytecode::InstanceOf(dest, obj, cls));
+    }
+
+    pub fn emit_checked_cast(&mut self, obj: Register, cls: ClassDefId) {
+        self.code.push(Bytecode::CheckedCast(obj, cls));
+    }
+
+    pub fn emit_throw(&mut self, exception: Register) {
+        self.code.push(Bytecode::Throw(exception));
+    }
+
+    pub fn emit_trap(&mut self, trap: Trap) {
+        self.code.push(Bytecode::Trap(trap));
+    }
+
+    pub fn emit_spawn(&mut self, lambda: Register) {
+        self.code.push(Bytecode::Spawn(lambda));
+    }
+
+    pub fn emit_debug(&mut self) {
+        self.code.push(Bytecode::Debug);
+    }
+
+    pub fn emit_invoke_lambda_void(&mut self, start: Register, num: usize) {
+        self.code.push(Bytecode::InvokeLambdaVoid(start, num));
+    }
+
+    pub fn emit_invoke_lambda_bool(&mut self, dest: Register, start: Register, num: usize) {
+        self.code.push(Bytecode::InvokeLambdaBool(dest, start, num));
+    }
+
+    pub fn emit_invoke_lambda_byte(&mut self, dest: Register, start: Register, num: usize) {
+        self.code.push(Bytecode::InvokeLambdaByte(dest, start, num));
+    }
+
+    pub fn emit_invoke_lambda_char(&mut self, dest: Register, start: Register, num: usize) {
+        self.code.push(Bytecode::InvokeLambdaChar(dest, start, num));
+    }
+
+    pub fn emit_invoke_lambda_int(&mut self, dest: Register, start: Register, num: usize) {
+        self.code.push(Bytecode::InvokeLambdaInt(dest, start, num));
+    }
+
+    pub fn emit_invoke_lambda_long(&mut self, dest: Register, start: Register, num: usize) {
+        self.code.push(Bytecode::InvokeLambdaLong(dest, start, num));
+    }
+
+    pub fn emit_invoke_lambda_float(&mut self, dest: Register, start: Register, num: usize) {
+        self.code
+            .push(Bytecode::InvokeLambdaFloat(dest, start, num));
+    }
+
+    pub fn emit_invoke_lambda_double(&mut self, dest: Register, start: Register, num: usize) {
+        self.code
+            .push(Bytecode::InvokeLambdaDouble(dest, start, num));
+    }
+
+    pub fn emit_invoke_lambda_ptr(&mut self, dest: Register, start: Register, num: usize) {
+        self.code.push(Bytecode::InvokeLambdaPtr(dest, start, num));
+    }
+
+    pub fn generate(mut self, var_registers: HashMap<VarId, Register>) -> BytecodeFunction {
         self.resolve_forward_jumps();
 
         BytecodeFunction {
@@ -860,6 +1514,11 @@ impl BytecodeGenerator {
             offset: generate_offset(&self.registers),
             registers: self.registers,
             string_pool: generate_string_pool(self.string_pool_map),
+            exception_handlers: self.exception_handlers,
+            type_params: self.type_params,
+            positions: self.positions,
+            loop_starts: self.loop_starts,
+            var_registers: var_registers,
         }
     }
 
@@ -917,6 +1576,11 @@ pub struct BytecodeFunction {
     registers: Vec<BytecodeType>,
     string_pool: Vec<String>,
     offset: Vec<i32>,
+    exception_handlers: Vec<ExceptionHandler>,
+    type_params: HashMap<BytecodeIdx, (TypeParams, TypeParams)>,
+    positions: Vec<(BytecodeIdx, Position)>,
+    loop_starts: HashMap<NodeId, BytecodeIdx>,
+    var_registers: HashMap<VarId, Register>,
 }
 
 impl BytecodeFunction {
@@ -936,6 +1600,39 @@ impl BytecodeFunction {
         &self.registers
     }
 
+    pub fn exception_handlers(&self) -> &[ExceptionHandler] {
+        &self.exception_handlers
+    }
+
+    // first instruction of the `while` or `loop` statement `id`
+    pub fn loop_start(&self, id: NodeId) -> Option<BytecodeIdx> {
+        self.loop_starts.get(&id).cloned()
+    }
+
+    pub fn var_registers(&self) -> &HashMap<VarId, Register> {
+        &self.var_registers
+    }
+
+    // type params of the function invoked at `idx`, empty for non-generic calls
+    pub fn type_params(&self, idx: BytecodeIdx) -> (TypeParams, TypeParams) {
+        match self.type_params.get(&idx) {
+            Some(&(ref cls_type_params, ref fct_type_params)) => {
+                (cls_type_params.clone(), fct_type_params.clone())
+            }
+            None => (TypeParams::empty(), TypeParams::empty()),
+        }
+    }
+
+    // source position of the instruction at `idx`
+    pub fn position(&self, idx: BytecodeIdx) -> Position {
+        let entry = match self.positions.binary_search_by_key(&idx.0, |&(pc, _)| pc.0) {
+            Ok(entry) => entry,
+            Err(entry) => entry.checked_sub(1).expect("no position for instruction"),
+        };
+
+        self.positions[entry].1
+    }
+
     pub fn register(&self, register: Register) -> BytecodeType {
         *self.registers.get(register.0).expect("register not found")
     }
@@ -1005,7 +1702,7 @@ impl BytecodeFunction {
                 Bytecode::JumpIfTrue(opnd, target) => {
                     println!("{}: if {} goto {}", btidx, opnd, target)
                 }
-                Bytecode::Jump(dest) => println!("{}: Jump bc#{}", btidx, dest),
+                Bytecode::Jump(dest) => println!("{}: Jump {}", btidx, dest),
                 Bytecode::ModInt(dest, lhs, rhs) => {
                     println!("{}: {} <-int {} % {}", btidx, dest, lhs, rhs)
                 }
@@ -1076,13 +1773,13 @@ impl BytecodeFunction {
                     println!("{}: {} <- {} >.int {}", btidx, dest, lhs, rhs)
                 }
                 Bytecode::TestGeInt(dest, lhs, rhs) => {
-                    println!("{}: {} <- {} <=.int {}", btidx, dest, lhs, rhs)
+                    println!("{}: {} <- {} >=.int {}", btidx, dest, lhs, rhs)
                 }
                 Bytecode::TestLtInt(dest, lhs, rhs) => {
-                    println!("{}: {} <- {} >.int {}", btidx, dest, lhs, rhs)
+                    println!("{}: {} <- {} <.int {}", btidx, dest, lhs, rhs)
                 }
                 Bytecode::TestLeInt(dest, lhs, rhs) => {
-                    println!("{}: {} <- {} >=.int {}", btidx, dest, lhs, rhs)
+                    println!("{}: {} <- {} <=.int {}", btidx, dest, lhs, rhs)
                 }
                 Bytecode::LoadGlobalBool(dest, gid) => {
                     println!("{}: {} <-bool global {:?}", btidx, dest, gid)
@@ -1272,8 +1969,396 @@ impl BytecodeFunction {
                 Bytecode::NewObject(dest, cls_id) => {
                     println!("{}: {} <- new {:?}", btidx, dest, cls_id);
                 }
+                Bytecode::SubLong(dest, lhs, rhs) => {
+                    println!("{}: {} <-long {} - {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::SubFloat(dest, lhs, rhs) => {
+                    println!("{}: {} <-float {} - {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::SubDouble(dest, lhs, rhs) => {
+                    println!("{}: {} <-double {} - {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::NegFloat(dest, src) => println!("{}: {} <-float -{}", btidx, dest, src),
+                Bytecode::NegDouble(dest, src) => println!("{}: {} <-double -{}", btidx, dest, src),
+                Bytecode::MulLong(dest, lhs, rhs) => {
+                    println!("{}: {} <-long {} * {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::MulFloat(dest, lhs, rhs) => {
+                    println!("{}: {} <-float {} * {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::MulDouble(dest, lhs, rhs) => {
+                    println!("{}: {} <-double {} * {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::DivLong(dest, lhs, rhs) => {
+                    println!("{}: {} <-long {} / {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::DivFloat(dest, lhs, rhs) => {
+                    println!("{}: {} <-float {} / {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::DivDouble(dest, lhs, rhs) => {
+                    println!("{}: {} <-double {} / {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::ModLong(dest, lhs, rhs) => {
+                    println!("{}: {} <-long {} % {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::AndLong(dest, lhs, rhs) => {
+                    println!("{}: {} <-long {} & {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::OrLong(dest, lhs, rhs) => {
+                    println!("{}: {} <-long {} | {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::XorLong(dest, lhs, rhs) => {
+                    println!("{}: {} <-long {} ^ {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::NotByte(dest, src) => println!("{}: {} <-byte !{}", btidx, dest, src),
+                Bytecode::NotInt(dest, src) => println!("{}: {} <-int !{}", btidx, dest, src),
+                Bytecode::NotLong(dest, src) => println!("{}: {} <-long !{}", btidx, dest, src),
+                Bytecode::ShlLong(dest, lhs, rhs) => {
+                    println!("{}: {} <-long {} << {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::ShrLong(dest, lhs, rhs) => {
+                    println!("{}: {} <-long {} >>> {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::SarLong(dest, lhs, rhs) => {
+                    println!("{}: {} <-long {} >> {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::SqrtFloat(dest, src) => {
+                    println!("{}: {} <-float sqrt {}", btidx, dest, src)
+                }
+                Bytecode::SqrtDouble(dest, src) => {
+                    println!("{}: {} <-double sqrt {}", btidx, dest, src)
+                }
+                Bytecode::ExtendByteToInt(dest, src) => {
+                    println!("{}: {} <- extend byte to int {}", btidx, dest, src)
+                }
+                Bytecode::ExtendByteToLong(dest, src) => {
+                    println!("{}: {} <- extend byte to long {}", btidx, dest, src)
+                }
+                Bytecode::ExtendCharToLong(dest, src) => {
+                    println!("{}: {} <- extend char to long {}", btidx, dest, src)
+                }
+                Bytecode::ExtendIntToLong(dest, src) => {
+                    println!("{}: {} <- extend int to long {}", btidx, dest, src)
+                }
+                Bytecode::CastCharToInt(dest, src) => {
+                    println!("{}: {} <- cast char to int {}", btidx, dest, src)
+                }
+                Bytecode::CastIntToChar(dest, src) => {
+                    println!("{}: {} <- cast int to char {}", btidx, dest, src)
+                }
+                Bytecode::CastIntToByte(dest, src) => {
+                    println!("{}: {} <- cast int to byte {}", btidx, dest, src)
+                }
+                Bytecode::CastLongToByte(dest, src) => {
+                    println!("{}: {} <- cast long to byte {}", btidx, dest, src)
+                }
+                Bytecode::CastLongToChar(dest, src) => {
+                    println!("{}: {} <- cast long to char {}", btidx, dest, src)
+                }
+                Bytecode::CastLongToInt(dest, src) => {
+                    println!("{}: {} <- cast long to int {}", btidx, dest, src)
+                }
+                Bytecode::ConvertIntToFloat(dest, src) => {
+                    println!("{}: {} <- convert int to float {}", btidx, dest, src)
+                }
+                Bytecode::ConvertIntToDouble(dest, src) => {
+                    println!("{}: {} <- convert int to double {}", btidx, dest, src)
+                }
+                Bytecode::ConvertLongToFloat(dest, src) => {
+                    println!("{}: {} <- convert long to float {}", btidx, dest, src)
+                }
+                Bytecode::ConvertLongToDouble(dest, src) => {
+                    println!("{}: {} <- convert long to double {}", btidx, dest, src)
+                }
+                Bytecode::TruncateFloatToInt(dest, src) => {
+                    println!("{}: {} <- truncate float to int {}", btidx, dest, src)
+                }
+                Bytecode::TruncateFloatToLong(dest, src) => {
+                    println!("{}: {} <- truncate float to long {}", btidx, dest, src)
+                }
+                Bytecode::TruncateDoubleToInt(dest, src) => {
+                    println!("{}: {} <- truncate double to int {}", btidx, dest, src)
+                }
+                Bytecode::TruncateDoubleToLong(dest, src) => {
+                    println!("{}: {} <- truncate double to long {}", btidx, dest, src)
+                }
+                Bytecode::PromoteFloatToDouble(dest, src) => {
+                    println!("{}: {} <- promote float to double {}", btidx, dest, src)
+                }
+                Bytecode::DemoteDoubleToFloat(dest, src) => {
+                    println!("{}: {} <- demote double to float {}", btidx, dest, src)
+                }
+                Bytecode::ReinterpretFloatAsInt(dest, src) => {
+                    println!("{}: {} <- reinterpret float as int {}", btidx, dest, src)
+                }
+                Bytecode::ReinterpretIntAsFloat(dest, src) => {
+                    println!("{}: {} <- reinterpret int as float {}", btidx, dest, src)
+                }
+                Bytecode::ReinterpretDoubleAsLong(dest, src) => {
+                    println!("{}: {} <- reinterpret double as long {}", btidx, dest, src)
+                }
+                Bytecode::ReinterpretLongAsDouble(dest, src) => {
+                    println!("{}: {} <- reinterpret long as double {}", btidx, dest, src)
+                }
+                Bytecode::StoreFieldBool(src, obj, cls, field) => {
+                    println!("{}: {} {:?}.{:?} <-bool {}", btidx, obj, cls, field, src)
+                }
+                Bytecode::StoreFieldByte(src, obj, cls, field) => {
+                    println!("{}: {} {:?}.{:?} <-byte {}", btidx, obj, cls, field, src)
+                }
+                Bytecode::StoreFieldChar(src, obj, cls, field) => {
+                    println!("{}: {} {:?}.{:?} <-char {}", btidx, obj, cls, field, src)
+                }
+                Bytecode::StoreFieldInt(src, obj, cls, field) => {
+                    println!("{}: {} {:?}.{:?} <-int {}", btidx, obj, cls, field, src)
+                }
+                Bytecode::StoreFieldLong(src, obj, cls, field) => {
+                    println!("{}: {} {:?}.{:?} <-long {}", btidx, obj, cls, field, src)
+                }
+                Bytecode::StoreFieldFloat(src, obj, cls, field) => {
+                    println!("{}: {} {:?}.{:?} <-float {}", btidx, obj, cls, field, src)
+                }
+                Bytecode::StoreFieldDouble(src, obj, cls, field) => {
+                    println!("{}: {} {:?}.{:?} <-double {}", btidx, obj, cls, field, src)
+                }
+                Bytecode::StoreFieldPtr(src, obj, cls, field) => {
+                    println!("{}: {} {:?}.{:?} <-ptr {}", btidx, obj, cls, field, src)
+                }
+                Bytecode::StoreGlobalBool(src, gid) => {
+                    println!("{}: global {:?} <-bool {}", btidx, gid, src)
+                }
+                Bytecode::StoreGlobalByte(src, gid) => {
+                    println!("{}: global {:?} <-byte {}", btidx, gid, src)
+                }
+                Bytecode::StoreGlobalChar(src, gid) => {
+                    println!("{}: global {:?} <-char {}", btidx, gid, src)
+                }
+                Bytecode::StoreGlobalInt(src, gid) => {
+                    println!("{}: global {:?} <-int {}", btidx, gid, src)
+                }
+                Bytecode::StoreGlobalLong(src, gid) => {
+                    println!("{}: global {:?} <-long {}", btidx, gid, src)
+                }
+                Bytecode::StoreGlobalFloat(src, gid) => {
+                    println!("{}: global {:?} <-float {}", btidx, gid, src)
+                }
+                Bytecode::StoreGlobalDouble(src, gid) => {
+                    println!("{}: global {:?} <-double {}", btidx, gid, src)
+                }
+                Bytecode::StoreGlobalPtr(src, gid) => {
+                    println!("{}: global {:?} <-ptr {}", btidx, gid, src)
+                }
+                Bytecode::LoadArrayBool(dest, arr, idx) => {
+                    println!("{}: {} <-bool {}[{}]", btidx, dest, arr, idx)
+                }
+                Bytecode::LoadArrayByte(dest, arr, idx) => {
+                    println!("{}: {} <-byte {}[{}]", btidx, dest, arr, idx)
+                }
+                Bytecode::LoadArrayChar(dest, arr, idx) => {
+                    println!("{}: {} <-char {}[{}]", btidx, dest, arr, idx)
+                }
+                Bytecode::LoadArrayInt(dest, arr, idx) => {
+                    println!("{}: {} <-int {}[{}]", btidx, dest, arr, idx)
+                }
+                Bytecode::LoadArrayLong(dest, arr, idx) => {
+                    println!("{}: {} <-long {}[{}]", btidx, dest, arr, idx)
+                }
+                Bytecode::LoadArrayFloat(dest, arr, idx) => {
+                    println!("{}: {} <-float {}[{}]", btidx, dest, arr, idx)
+                }
+                Bytecode::LoadArrayDouble(dest, arr, idx) => {
+                    println!("{}: {} <-double {}[{}]", btidx, dest, arr, idx)
+                }
+                Bytecode::LoadArrayPtr(dest, arr, idx) => {
+                    println!("{}: {} <-ptr {}[{}]", btidx, dest, arr, idx)
+                }
+                Bytecode::StoreArrayBool(src, arr, idx) => {
+                    println!("{}: {}[{}] <-bool {}", btidx, arr, idx, src)
+                }
+                Bytecode::StoreArrayByte(src, arr, idx) => {
+                    println!("{}: {}[{}] <-byte {}", btidx, arr, idx, src)
+                }
+                Bytecode::StoreArrayChar(src, arr, idx) => {
+                    println!("{}: {}[{}] <-char {}", btidx, arr, idx, src)
+                }
+                Bytecode::StoreArrayInt(src, arr, idx) => {
+                    println!("{}: {}[{}] <-int {}", btidx, arr, idx, src)
+                }
+                Bytecode::StoreArrayLong(src, arr, idx) => {
+                    println!("{}: {}[{}] <-long {}", btidx, arr, idx, src)
+                }
+                Bytecode::StoreArrayFloat(src, arr, idx) => {
+                    println!("{}: {}[{}] <-float {}", btidx, arr, idx, src)
+                }
+                Bytecode::StoreArrayDouble(src, arr, idx) => {
+                    println!("{}: {}[{}] <-double {}", btidx, arr, idx, src)
+                }
+                Bytecode::StoreArrayPtr(src, arr, idx) => {
+                    println!("{}: {}[{}] <-ptr {}", btidx, arr, idx, src)
+                }
+                Bytecode::ArrayLength(dest, arr) => {
+                    println!("{}: {} <-int length {}", btidx, dest, arr)
+                }
+                Bytecode::SetUint8(address, value) => {
+                    println!("{}: [{}] <-byte {}", btidx, address, value)
+                }
+                Bytecode::TestEqBool(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} =.bool {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestNeBool(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} !=.bool {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestEqByte(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} =.byte {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestNeByte(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} !=.byte {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestGtByte(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} >.byte {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestGeByte(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} >=.byte {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestLtByte(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} <.byte {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestLeByte(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} <=.byte {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestEqChar(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} =.char {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestNeChar(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} !=.char {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestGtChar(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} >.char {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestGeChar(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} >=.char {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestLtChar(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} <.char {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestLeChar(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} <=.char {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestEqLong(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} =.long {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestNeLong(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} !=.long {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestGtLong(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} >.long {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestGeLong(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} >=.long {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestLtLong(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} <.long {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestLeLong(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} <=.long {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestEqFloat(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} =.float {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestNeFloat(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} !=.float {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestGtFloat(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} >.float {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestGeFloat(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} >=.float {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestLtFloat(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} <.float {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestLeFloat(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} <=.float {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestEqDouble(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} =.double {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestNeDouble(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} !=.double {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestGtDouble(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} >.double {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestGeDouble(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} >=.double {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestLtDouble(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} <.double {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::TestLeDouble(dest, lhs, rhs) => {
+                    println!("{}: {} <- {} <=.double {}", btidx, dest, lhs, rhs)
+                }
+                Bytecode::NewArray(dest, cls, length) => {
+                    println!("{}: {} <- new array {:?} {}", btidx, dest, cls, length)
+                }
+                Bytecode::NilCheck(obj) => println!("{}: nil check {}", btidx, obj),
+                Bytecode::InstanceOf(dest, obj, cls) => {
+                    println!("{}: {} <- {} is {:?}", btidx, dest, obj, cls)
+                }
+                Bytecode::CheckedCast(obj, cls) => {
+                    println!("{}: check {} as {:?}", btidx, obj, cls)
+                }
+                Bytecode::Throw(exception) => println!("{}: throw {}", btidx, exception),
+                Bytecode::Trap(trap) => println!("{}: trap {:?}", btidx, trap),
+                Bytecode::Spawn(lambda) => println!("{}: spawn {}", btidx, lambda),
+                Bytecode::Debug => println!("{}: debug", btidx),
+                Bytecode::InvokeLambdaVoid(start, num) => {
+                    println!("{}: invoke lambda {} {}", btidx, start, num)
+                }
+                Bytecode::InvokeLambdaBool(dest, start, num) => {
+                    println!("{}: {} <-bool invoke lambda {} {}", btidx, dest, start, num)
+                }
+                Bytecode::InvokeLambdaByte(dest, start, num) => {
+                    println!("{}: {} <-byte invoke lambda {} {}", btidx, dest, start, num)
+                }
+                Bytecode::InvokeLambdaChar(dest, start, num) => {
+                    println!("{}: {} <-char invoke lambda {} {}", btidx, dest, start, num)
+                }
+                Bytecode::InvokeLambdaInt(dest, start, num) => {
+                    println!("{}: {} <-int invoke lambda {} {}", btidx, dest, start, num)
+                }
+                Bytecode::InvokeLambdaLong(dest, start, num) => {
+                    println!("{}: {} <-long invoke lambda {} {}", btidx, dest, start, num)
+                }
+                Bytecode::InvokeLambdaFloat(dest, start, num) => println!(
+                    "{}: {} <-float invoke lambda {} {}",
+                    btidx, dest, start, num
+                ),
+                Bytecode::InvokeLambdaDouble(dest, start, num) => println!(
+                    "{}: {} <-double invoke lambda {} {}",
+                    btidx, dest, start, num
+                ),
+                Bytecode::InvokeLambdaPtr(dest, start, num) => {
+                    println!("{}: {} <-ptr invoke lambda {} {}", btidx, dest, start, num)
+                }
             }
             btidx = btidx + 1;
         }
+
+        for handler in &self.exception_handlers {
+            print!(
+                "handler {}-{} => {}",
+                handler.try_start, handler.try_end, handler.catch
+            );
+
+            if let Some(exception) = handler.exception {
+                print!(" {} <-", exception);
+            }
+
+            match handler.catch_type {
+                Some(cls_id) => println!(" catch {:?}", cls_id),
+                None => println!(" catch all"),
+            }
+        }
     }
 }
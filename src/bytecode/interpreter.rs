@@ -0,0 +1,1415 @@
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::baseline;
+use crate::baseline::codegen::ensure_native_stub;
+use crate::baseline::dora_compile::patch_vtable_call;
+use crate::baseline::dora_native::{InternalFct, InternalFctDescriptor};
+use crate::baseline::fct::CatchType;
+use crate::bytecode::astgen;
+use crate::bytecode::generate::{
+    BytecodeFunction, BytecodeIdx, BytecodeType, ExceptionHandler, Register, StrConstPoolIdx,
+};
+use crate::bytecode::opcode::Bytecode;
+use crate::class::{ClassDef, ClassDefId, ClassSize, FieldId, TypeParams};
+use crate::cpu::{FREG_PARAMS, REG_PARAMS};
+use crate::exception::{self, catches, DoraToNativeInfo};
+use crate::gc::swiper::CARD_SIZE_BITS;
+use crate::gc::Address;
+use crate::object::{offset_of_array_data, offset_of_array_length, Header, Obj, Ref, Str};
+use crate::os::signal::Trap;
+use crate::stdlib;
+use crate::threads::THREAD;
+use crate::ty::MachineMode;
+use crate::vm::{exception_get_and_clear, exception_set, get_vm, has_exception};
+use crate::vm::{Fct, FctId, FctKind, FctSrc, GlobalId, VM};
+use crate::vtable::{VTable, DISPLAY_SIZE};
+
+// a function specialized for its type params, executed by the interpreter
+pub struct InterpFct {
+    pub fct_id: FctId,
+    bytecode: BytecodeFunction,
+    strings: Vec<Address>,
+    call_targets: Vec<AtomicUsize>,
+}
+
+impl InterpFct {
+    pub fn new<'ast>(
+        vm: &VM<'ast>,
+        fct: &Fct<'ast>,
+        src: &mut FctSrc,
+        cls_type_params: &TypeParams,
+        fct_type_params: &TypeParams,
+    ) -> InterpFct {
+        let bytecode = astgen::generate_fct(vm, fct, src, cls_type_params, fct_type_params);
+
+        let strings = bytecode
+            .string_pool()
+            .iter()
+            .map(|value| Str::from_buffer_in_perm(vm, value.as_bytes()).address())
+            .collect();
+
+        let call_targets = bytecode
+            .code()
+            .iter()
+            .map(|_| AtomicUsize::new(0))
+            .collect();
+
+        InterpFct {
+            fct_id: fct.id,
+            bytecode: bytecode,
+            strings: strings,
+            call_targets: call_targets,
+        }
+    }
+
+    pub fn bytecode(&self) -> &BytecodeFunction {
+        &self.bytecode
+    }
+
+    fn string(&self, sp: StrConstPoolIdx) -> Address {
+        self.strings[sp.0]
+    }
+}
+
+// registers of a function activation, the GC finds the frame
+// through the DoraToNativeInfo of the interpreter stub
+pub struct InterpFrame {
+    fct: *const InterpFct,
+    registers: Vec<u64>,
+    pc: usize,
+}
+
+impl InterpFrame {
+    fn fct(&self) -> &InterpFct {
+        unsafe { &*self.fct }
+    }
+
+    pub fn lineno(&self) -> i32 {
+        let pos = self.fct().bytecode.position(BytecodeIdx(self.pc));
+        pos.line as i32
+    }
+
+    // innermost handler at the current position that catches `exception`
+    pub fn handler(&self, vm: &VM, exception: Ref<Obj>) -> Option<&ExceptionHandler> {
+        let clsptr = exception.header().vtbl().classptr();
+        let pc = self.pc;
+
+        self.fct()
+            .bytecode
+            .exception_handlers()
+            .iter()
+            .filter(|handler| handler.try_start.0 <= pc && pc < handler.try_end.0)
+            .find(|handler| {
+                let catch_type = match handler.catch_type {
+                    Some(cls_def_id) => {
+                        let cls_def = vm.class_defs.idx(cls_def_id);
+                        let cls_def = cls_def.read();
+
+                        CatchType::Class(&*cls_def as *const ClassDef)
+                    }
+
+                    None => CatchType::Any,
+                };
+
+                catches(vm, catch_type, clsptr)
+            })
+    }
+
+    // addresses of all registers of type ptr
+    pub fn roots(&self) -> Vec<Address> {
+        self.fct()
+            .bytecode
+            .registers()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &ty)| ty == BytecodeType::Ptr)
+            .map(|(idx, _)| Address::from_ptr(&self.registers[idx] as *const u64))
+            .collect()
+    }
+}
+
+// entry point of the interpreter, called by the interpreter stub with
+// a pointer to the arguments of the function
+pub extern "C" fn run(fct: *const InterpFct, args: *const u64) -> u64 {
+    let vm = get_vm();
+    let fct = unsafe { &*fct };
+    let bytecode = &fct.bytecode;
+
+    let (dtn, tld, stack_limit) = THREAD.with(|thread| {
+        let thread = thread.borrow();
+        let tld = Address::from_ptr(&thread.tld as *const _);
+
+        (
+            thread.dtn() as *mut DoraToNativeInfo,
+            tld,
+            thread.tld.stack_limit(),
+        )
+    });
+
+    let mut registers = vec![0; bytecode.registers().len()];
+    let params = fct_params(vm, fct.fct_id);
+
+    for idx in 0..params {
+        let value = unsafe { *args.offset(idx as isize) };
+        registers[idx] = truncate(bytecode.register(Register(idx)), value);
+    }
+
+    // the interpreter uses the native stack for every invocation
+    let stack_top = &registers as *const _ as usize;
+
+    if stack_top < stack_limit.to_usize() {
+        stdlib::trap(Trap::STACK_OVERFLOW.int());
+        return 0;
+    }
+
+    let mut frame = InterpFrame {
+        fct: fct,
+        registers: registers,
+        pc: 0,
+    };
+
+    unsafe {
+        (*dtn).interp_frame = &frame;
+    }
+
+    let result = Interpreter {
+        vm: vm,
+        fct: fct,
+        frame: &mut frame,
+        dtn: dtn,
+        tld: tld,
+    }
+    .run();
+
+    unsafe {
+        (*dtn).interp_frame = ptr::null();
+    }
+
+    result
+}
+
+fn fct_params(vm: &VM, fct_id: FctId) -> usize {
+    let fct = vm.fcts.idx(fct_id);
+    let fct = fct.read();
+
+    fct.ast.params.len() + if fct.has_self() { 1 } else { 0 }
+}
+
+// address of the compiled code or stub for a call to `fct_id`
+fn fct_ptr(
+    vm: &VM,
+    fct_id: FctId,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+) -> Address {
+    let fct = vm.fcts.idx(fct_id);
+    let fct = fct.read();
+
+    match fct.kind {
+        FctKind::Source(_) => {
+            drop(fct);
+            baseline::generate(vm, fct_id, cls_type_params, fct_type_params)
+        }
+
+        FctKind::Native(ptr) => {
+            let internal_fct = InternalFct {
+                ptr: ptr,
+                args: fct.params_with_self(),
+                return_type: fct.return_type,
+                throws: fct.ast.throws,
+                desc: InternalFctDescriptor::NativeThunk(fct_id),
+            };
+
+            ensure_native_stub(vm, fct_id, internal_fct)
+        }
+
+        FctKind::Definition => panic!("prototype for fct call"),
+        FctKind::Builtin(_) => panic!("intrinsic fct call"),
+    }
+}
+
+// clears the bits a value of type `ty` does not use
+fn truncate(ty: BytecodeType, value: u64) -> u64 {
+    match ty.mode() {
+        MachineMode::Int8 => value as u8 as u64,
+        MachineMode::Int32 | MachineMode::Float32 => value as u32 as u64,
+        MachineMode::Int64 | MachineMode::Float64 | MachineMode::Ptr => value,
+    }
+}
+
+unsafe fn load(addr: Address, mode: MachineMode) -> u64 {
+    match mode {
+        MachineMode::Int8 => *addr.to_ptr::<u8>() as u64,
+        MachineMode::Int32 | MachineMode::Float32 => *addr.to_ptr::<u32>() as u64,
+        MachineMode::Int64 | MachineMode::Float64 | MachineMode::Ptr => *addr.to_ptr::<u64>(),
+    }
+}
+
+unsafe fn store(addr: Address, mode: MachineMode, value: u64) {
+    match mode {
+        MachineMode::Int8 => *addr.to_mut_ptr::<u8>() = value as u8,
+        MachineMode::Int32 | MachineMode::Float32 => *addr.to_mut_ptr::<u32>() = value as u32,
+        MachineMode::Int64 | MachineMode::Float64 | MachineMode::Ptr => {
+            *addr.to_mut_ptr::<u64>() = value
+        }
+    }
+}
+
+// float to int conversions behave like cvttss2si/cvttsd2si:
+// NaN and values out of range result in the minimum value
+fn truncate_to_int(value: f64) -> i32 {
+    if value >= -2147483648.0 && value < 2147483648.0 {
+        value as i32
+    } else {
+        i32::min_value()
+    }
+}
+
+fn truncate_to_long(value: f64) -> i64 {
+    if value >= -9223372036854775808.0 && value < 9223372036854775808.0 {
+        value as i64
+    } else {
+        i64::min_value()
+    }
+}
+
+enum Step {
+    Next,
+    Jump(BytecodeIdx),
+    Return(u64),
+    Trap(Trap),
+    Exception,
+}
+
+struct Interpreter<'a, 'ast: 'a> {
+    vm: &'a VM<'ast>,
+    fct: &'a InterpFct,
+    frame: &'a mut InterpFrame,
+    dtn: *const DoraToNativeInfo,
+    tld: Address,
+}
+
+impl<'a, 'ast> Interpreter<'a, 'ast>
+where
+    'ast: 'a,
+{
+    fn run(&mut self) -> u64 {
+        loop {
+            let step = self.step();
+
+            match step {
+                Step::Next => self.frame.pc += 1,
+                Step::Jump(target) => self.frame.pc = target.0,
+                Step::Return(value) => return value,
+
+                Step::Trap(trap) => {
+                    stdlib::trap(trap.int());
+
+                    if !self.unwind() {
+                        return 0;
+                    }
+                }
+
+                Step::Exception => {
+                    if !self.unwind() {
+                        return 0;
+                    }
+                }
+            }
+        }
+    }
+
+    // continues at the innermost handler for the pending exception, returns false
+    // if the exception is not handled in this function
+    fn unwind(&mut self) -> bool {
+        let exception = Address::from_ptr(exception_get_and_clear());
+        let obj: Ref<Obj> = exception.into();
+
+        if let Some(handler) = self.frame.handler(self.vm, obj) {
+            let catch = handler.catch;
+
+            if let Some(reg) = handler.exception {
+                self.set(reg, exception.to_usize() as u64);
+            }
+
+            self.frame.pc = catch.0;
+            return true;
+        }
+
+        // abort while this frame is still part of the stack trace
+        let dtn = unsafe { &*self.dtn };
+        exception::ensure_handler(self.vm, obj, dtn.pc, dtn.fp);
+
+        exception_set(exception.to_ptr());
+        false
+    }
+
+    fn get(&self, reg: Register) -> u64 {
+        self.frame.registers[reg.0]
+    }
+
+    fn set(&mut self, reg: Register, value: u64) {
+        self.frame.registers[reg.0] = value;
+    }
+
+    fn ty(&self, reg: Register) -> BytecodeType {
+        self.fct.bytecode.register(reg)
+    }
+
+    fn bool(&self, reg: Register) -> bool {
+        self.get(reg) != 0
+    }
+
+    fn int(&self, reg: Register) -> i32 {
+        self.get(reg) as u32 as i32
+    }
+
+    fn long(&self, reg: Register) -> i64 {
+        self.get(reg) as i64
+    }
+
+    fn float(&self, reg: Register) -> f32 {
+        f32::from_bits(self.get(reg) as u32)
+    }
+
+    fn double(&self, reg: Register) -> f64 {
+        f64::from_bits(self.get(reg))
+    }
+
+    fn ptr(&self, reg: Register) -> Address {
+        Address::from(self.get(reg) as usize)
+    }
+
+    fn set_bool(&mut self, reg: Register, value: bool) {
+        self.set(reg, value as u64);
+    }
+
+    fn set_int(&mut self, reg: Register, value: i32) {
+        self.set(reg, value as u32 as u64);
+    }
+
+    fn set_long(&mut self, reg: Register, value: i64) {
+        self.set(reg, value as u64);
+    }
+
+    fn set_float(&mut self, reg: Register, value: f32) {
+        self.set(reg, value.to_bits() as u64);
+    }
+
+    fn set_double(&mut self, reg: Register, value: f64) {
+        self.set(reg, value.to_bits());
+    }
+
+    fn int_binop<F>(&mut self, dest: Register, lhs: Register, rhs: Register, op: F) -> Step
+    where
+        F: FnOnce(i32, i32) -> i32,
+    {
+        let value = op(self.int(lhs), self.int(rhs));
+        self.set_int(dest, value);
+        Step::Next
+    }
+
+    fn long_binop<F>(&mut self, dest: Register, lhs: Register, rhs: Register, op: F) -> Step
+    where
+        F: FnOnce(i64, i64) -> i64,
+    {
+        let value = op(self.long(lhs), self.long(rhs));
+        self.set_long(dest, value);
+        Step::Next
+    }
+
+    fn float_binop<F>(&mut self, dest: Register, lhs: Register, rhs: Register, op: F) -> Step
+    where
+        F: FnOnce(f32, f32) -> f32,
+    {
+        let value = op(self.float(lhs), self.float(rhs));
+        self.set_float(dest, value);
+        Step::Next
+    }
+
+    fn double_binop<F>(&mut self, dest: Register, lhs: Register, rhs: Register, op: F) -> Step
+    where
+        F: FnOnce(f64, f64) -> f64,
+    {
+        let value = op(self.double(lhs), self.double(rhs));
+        self.set_double(dest, value);
+        Step::Next
+    }
+
+    fn int_div<F>(&mut self, dest: Register, lhs: Register, rhs: Register, op: F) -> Step
+    where
+        F: FnOnce(i32, i32) -> i32,
+    {
+        if self.int(rhs) == 0 {
+            return Step::Trap(Trap::DIV0);
+        }
+
+        self.int_binop(dest, lhs, rhs, op)
+    }
+
+    fn long_div<F>(&mut self, dest: Register, lhs: Register, rhs: Register, op: F) -> Step
+    where
+        F: FnOnce(i64, i64) -> i64,
+    {
+        if self.long(rhs) == 0 {
+            return Step::Trap(Trap::DIV0);
+        }
+
+        self.long_binop(dest, lhs, rhs, op)
+    }
+
+    fn test<T, F>(&mut self, dest: Register, lhs: T, rhs: T, op: F) -> Step
+    where
+        F: FnOnce(T, T) -> bool,
+    {
+        let value = op(lhs, rhs);
+        self.set_bool(dest, value);
+        Step::Next
+    }
+
+    fn mov(&mut self, dest: Register, src: Register) -> Step {
+        let value = self.get(src);
+        self.set(dest, value);
+        Step::Next
+    }
+
+    fn write_barrier(&self, addr: Address) {
+        if self.vm.gc.needs_write_barrier() {
+            let card_table_offset = self.vm.gc.card_table_offset();
+            let card = card_table_offset + (addr.to_usize() >> CARD_SIZE_BITS);
+
+            unsafe {
+                *(card as *mut u8) = 0;
+            }
+        }
+    }
+
+    fn load_field(
+        &mut self,
+        dest: Register,
+        obj: Register,
+        cls_id: ClassDefId,
+        field_id: FieldId,
+    ) -> Step {
+        let obj = self.ptr(obj);
+
+        if obj.is_null() {
+            return Step::Trap(Trap::NIL);
+        }
+
+        let offset = {
+            let cls = self.vm.class_defs.idx(cls_id);
+            let cls = cls.read();
+            cls.fields[field_id.idx()].offset
+        };
+
+        let ty = self.ty(dest);
+        let value = unsafe { load(obj.offset(offset as usize), ty.mode()) };
+        self.set(dest, value);
+        Step::Next
+    }
+
+    fn store_field(
+        &mut self,
+        src: Register,
+        obj: Register,
+        cls_id: ClassDefId,
+        field_id: FieldId,
+    ) -> Step {
+        let obj = self.ptr(obj);
+
+        if obj.is_null() {
+            return Step::Trap(Trap::NIL);
+        }
+
+        let offset = {
+            let cls = self.vm.class_defs.idx(cls_id);
+            let cls = cls.read();
+            cls.fields[field_id.idx()].offset
+        };
+
+        let ty = self.ty(src);
+        unsafe {
+            store(obj.offset(offset as usize), ty.mode(), self.get(src));
+        }
+
+        if ty == BytecodeType::Ptr {
+            self.write_barrier(obj);
+        }
+
+        Step::Next
+    }
+
+    fn global_address(&self, gid: GlobalId) -> Address {
+        let glob = self.vm.globals.idx(gid);
+        let glob = glob.lock();
+
+        glob.address_value
+    }
+
+    fn load_global(&mut self, dest: Register, gid: GlobalId) -> Step {
+        let addr = self.global_address(gid);
+        let value = unsafe { load(addr, self.ty(dest).mode()) };
+        self.set(dest, value);
+        Step::Next
+    }
+
+    fn store_global(&mut self, src: Register, gid: GlobalId) -> Step {
+        let addr = self.global_address(gid);
+        unsafe {
+            store(addr, self.ty(src).mode(), self.get(src));
+        }
+        Step::Next
+    }
+
+    // address of the array element, traps for nil or an index out of bounds
+    fn array_element(
+        &self,
+        arr: Register,
+        idx: Register,
+        mode: MachineMode,
+    ) -> Result<Address, Trap> {
+        let arr = self.ptr(arr);
+
+        if arr.is_null() {
+            return Err(Trap::NIL);
+        }
+
+        let idx = self.int(idx);
+        let length = unsafe {
+            *arr.offset(offset_of_array_length() as usize)
+                .to_ptr::<usize>()
+        };
+
+        if !self.vm.args.flag_omit_bounds_check && (idx < 0 || idx as usize >= length) {
+            return Err(Trap::INDEX_OUT_OF_BOUNDS);
+        }
+
+        let offset = offset_of_array_data() as isize + idx as isize * mode.size() as isize;
+        Ok(arr.offset(offset as usize))
+    }
+
+    fn load_array(&mut self, dest: Register, arr: Register, idx: Register) -> Step {
+        let mode = self.ty(dest).mode();
+
+        match self.array_element(arr, idx, mode) {
+            Ok(addr) => {
+                let value = unsafe { load(addr, mode) };
+                self.set(dest, value);
+                Step::Next
+            }
+
+            Err(trap) => Step::Trap(trap),
+        }
+    }
+
+    fn store_array(&mut self, src: Register, arr: Register, idx: Register) -> Step {
+        let ty = self.ty(src);
+
+        match self.array_element(arr, idx, ty.mode()) {
+            Ok(addr) => {
+                unsafe {
+                    store(addr, ty.mode(), self.get(src));
+                }
+
+                if ty == BytecodeType::Ptr {
+                    self.write_barrier(addr);
+                }
+
+                Step::Next
+            }
+
+            Err(trap) => Step::Trap(trap),
+        }
+    }
+
+    fn array_length(&mut self, dest: Register, arr: Register) -> Step {
+        let arr = self.ptr(arr);
+
+        if arr.is_null() {
+            return Step::Trap(Trap::NIL);
+        }
+
+        let length = unsafe { *arr.offset(Header::size() as usize).to_ptr::<usize>() };
+        self.set(dest, length as u64);
+        Step::Next
+    }
+
+    fn alloc(&mut self, dest: Register, cls_id: ClassDefId, length: Option<Register>) -> Step {
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+
+        let element_size = match cls.size {
+            ClassSize::Fixed(_) => None,
+            ClassSize::Array(esize) => Some(esize as usize),
+            ClassSize::ObjArray => Some(crate::mem::ptr_width_usize()),
+            ClassSize::Str => Some(1),
+            ClassSize::FreeArray => unreachable!(),
+        };
+
+        let (size, length) = match element_size {
+            None => match cls.size {
+                ClassSize::Fixed(size) => (size as usize, None),
+                _ => unreachable!(),
+            },
+
+            Some(element_size) => {
+                let length = length.expect("array allocation without length");
+                let length = match self.ty(length) {
+                    BytecodeType::Long => self.long(length),
+                    _ => self.int(length) as i64,
+                };
+
+                let size = if length < 0 {
+                    None
+                } else {
+                    (length as usize)
+                        .checked_mul(element_size)
+                        .and_then(|size| {
+                            size.checked_add(
+                                Header::size() as usize + crate::mem::ptr_width_usize(),
+                            )
+                        })
+                };
+
+                match size {
+                    Some(size) => (size, Some(length as usize)),
+                    None => return Step::Trap(Trap::OOM),
+                }
+            }
+        };
+
+        let size = crate::mem::align_usize(size, crate::mem::ptr_width_usize());
+        let array_ref = match cls.size {
+            ClassSize::ObjArray => true,
+            _ => false,
+        };
+
+        let ptr = self.vm.gc.alloc(self.vm, size, array_ref);
+
+        if ptr.is_null() {
+            return Step::Trap(Trap::OOM);
+        }
+
+        unsafe {
+            ptr::write_bytes(ptr.to_mut_ptr::<u8>(), 0, size);
+        }
+
+        let vtable: *const VTable = &**cls.vtable.as_ref().unwrap();
+        let mut obj: Ref<Obj> = ptr.into();
+        obj.header_mut().set_vtblptr(Address::from_ptr(vtable));
+
+        if let Some(length) = length {
+            unsafe {
+                *ptr.offset(offset_of_array_length() as usize)
+                    .to_mut_ptr::<usize>() = length;
+            }
+        }
+
+        self.set(dest, ptr.to_usize() as u64);
+        Step::Next
+    }
+
+    fn is_instance_of(&self, obj: Address, cls_id: ClassDefId) -> bool {
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+        let vtable: &VTable = cls.vtable.as_ref().unwrap();
+
+        let obj: Ref<Obj> = obj.into();
+        let obj_vtable = obj.header().vtbl();
+        let depth = vtable.subtype_depth as usize;
+
+        let entry = if depth >= DISPLAY_SIZE {
+            if obj_vtable.subtype_depth < vtable.subtype_depth {
+                return false;
+            }
+
+            unsafe {
+                *obj_vtable
+                    .subtype_overflow
+                    .offset((depth - DISPLAY_SIZE) as isize)
+            }
+        } else {
+            obj_vtable.subtype_display[depth]
+        };
+
+        entry == vtable as *const VTable
+    }
+
+    fn instance_of(&mut self, dest: Register, obj: Register, cls_id: ClassDefId) -> Step {
+        let obj = self.ptr(obj);
+        let value = !obj.is_null() && self.is_instance_of(obj, cls_id);
+        self.set_bool(dest, value);
+        Step::Next
+    }
+
+    fn checked_cast(&mut self, obj: Register, cls_id: ClassDefId) -> Step {
+        let obj = self.ptr(obj);
+
+        if obj.is_null() || self.is_instance_of(obj, cls_id) {
+            Step::Next
+        } else {
+            Step::Trap(Trap::CAST)
+        }
+    }
+
+    fn nil_check(&mut self, obj: Register) -> Step {
+        if self.ptr(obj).is_null() {
+            Step::Trap(Trap::NIL)
+        } else {
+            Step::Next
+        }
+    }
+
+    fn throw(&mut self, exception: Register) -> Step {
+        let exception = self.ptr(exception);
+
+        if exception.is_null() {
+            return Step::Trap(Trap::NIL);
+        }
+
+        exception_set(exception.to_ptr());
+        Step::Exception
+    }
+
+    fn spawn(&mut self, closure: Register) -> Step {
+        let closure: Ref<Obj> = self.ptr(closure).into();
+        stdlib::spawn_lambda(closure);
+        Step::Next
+    }
+
+    fn jump_if(&mut self, opnd: Register, value: bool, target: BytecodeIdx) -> Step {
+        if self.bool(opnd) == value {
+            Step::Jump(target)
+        } else {
+            Step::Next
+        }
+    }
+
+    fn invoke_direct(
+        &mut self,
+        dest: Option<Register>,
+        fct_id: FctId,
+        start: Register,
+        num: usize,
+    ) -> Step {
+        let pc = self.frame.pc;
+        let target = self.fct.call_targets[pc].load(Ordering::Relaxed);
+
+        let ptr = if target != 0 {
+            Address::from(target)
+        } else {
+            let (cls_type_params, fct_type_params) = self.fct.bytecode.type_params(BytecodeIdx(pc));
+            let ptr = fct_ptr(self.vm, fct_id, &cls_type_params, &fct_type_params);
+            self.fct.call_targets[pc].store(ptr.to_usize(), Ordering::Relaxed);
+
+            ptr
+        };
+
+        self.invoke(ptr, dest, start, num)
+    }
+
+    fn invoke_virtual(
+        &mut self,
+        dest: Option<Register>,
+        fct_id: FctId,
+        start: Register,
+        num: usize,
+    ) -> Step {
+        let vtable_index = {
+            let fct = self.vm.fcts.idx(fct_id);
+            let fct = fct.read();
+            fct.vtable_index.unwrap()
+        };
+
+        self.invoke_indirect(dest, vtable_index, start, num)
+    }
+
+    // lambdas are called through the first entry of the closure's vtable
+    fn invoke_lambda(&mut self, dest: Option<Register>, start: Register, num: usize) -> Step {
+        self.invoke_indirect(dest, 0, start, num)
+    }
+
+    fn invoke_indirect(
+        &mut self,
+        dest: Option<Register>,
+        vtable_index: u32,
+        start: Register,
+        num: usize,
+    ) -> Step {
+        let receiver = self.ptr(start);
+
+        if receiver.is_null() {
+            return Step::Trap(Trap::NIL);
+        }
+
+        let obj: Ref<Obj> = receiver.into();
+        let entry = obj.header().vtbl().table()[vtable_index as usize];
+
+        let ptr = if Address::from(entry) == self.vm.compiler_thunk() {
+            patch_vtable_call(self.vm, receiver, vtable_index, &TypeParams::empty())
+        } else {
+            Address::from(entry)
+        };
+
+        self.invoke(ptr, dest, start, num)
+    }
+
+    // calls into compiled code with the arguments in the given registers
+    fn invoke(
+        &mut self,
+        ptr: Address,
+        dest: Option<Register>,
+        start: Register,
+        num: usize,
+    ) -> Step {
+        let mut regs = [0u64; REG_PARAMS.len() + FREG_PARAMS.len()];
+        let mut stack = Vec::new();
+        let mut reg_idx = 0;
+        let mut freg_idx = 0;
+
+        for idx in 0..num {
+            let arg = start.offset(idx);
+            let value = self.get(arg);
+
+            if self.ty(arg).mode().is_float() {
+                if freg_idx < FREG_PARAMS.len() {
+                    regs[REG_PARAMS.len() + freg_idx] = value;
+                    freg_idx += 1;
+                } else {
+                    stack.push(value);
+                }
+            } else {
+                if reg_idx < REG_PARAMS.len() {
+                    regs[reg_idx] = value;
+                    reg_idx += 1;
+                } else {
+                    stack.push(value);
+                }
+            }
+        }
+
+        // the stack pointer needs to stay 16-byte aligned
+        if stack.len() % 2 != 0 {
+            stack.push(0);
+        }
+
+        let stack_size = stack.len() * mem::size_of::<u64>();
+        let dora_invoke = self.vm.dora_invoke_thunk();
+        let dest_ty = dest.map(|dest| self.ty(dest));
+
+        let value = match dest_ty {
+            Some(ty) if ty.mode().is_float() => {
+                let invoke: extern "C" fn(Address, Address, *const u64, *const u64, usize) -> f64 =
+                    unsafe { mem::transmute(dora_invoke) };
+                invoke(self.tld, ptr, regs.as_ptr(), stack.as_ptr(), stack_size).to_bits()
+            }
+
+            _ => {
+                let invoke: extern "C" fn(Address, Address, *const u64, *const u64, usize) -> u64 =
+                    unsafe { mem::transmute(dora_invoke) };
+                invoke(self.tld, ptr, regs.as_ptr(), stack.as_ptr(), stack_size)
+            }
+        };
+
+        if has_exception() {
+            return Step::Exception;
+        }
+
+        if let (Some(dest), Some(ty)) = (dest, dest_ty) {
+            self.set(dest, truncate(ty, value));
+        }
+
+        Step::Next
+    }
+
+    fn step(&mut self) -> Step {
+        let pc = self.frame.pc;
+
+        match self.fct.bytecode.code()[pc] {
+            Bytecode::AddInt(dest, lhs, rhs) => self.int_binop(dest, lhs, rhs, i32::wrapping_add),
+            Bytecode::AddLong(dest, lhs, rhs) => self.long_binop(dest, lhs, rhs, i64::wrapping_add),
+            Bytecode::AddFloat(dest, lhs, rhs) => self.float_binop(dest, lhs, rhs, |l, r| l + r),
+            Bytecode::AddDouble(dest, lhs, rhs) => self.double_binop(dest, lhs, rhs, |l, r| l + r),
+
+            Bytecode::SubInt(dest, lhs, rhs) => self.int_binop(dest, lhs, rhs, i32::wrapping_sub),
+            Bytecode::SubLong(dest, lhs, rhs) => self.long_binop(dest, lhs, rhs, i64::wrapping_sub),
+            Bytecode::SubFloat(dest, lhs, rhs) => self.float_binop(dest, lhs, rhs, |l, r| l - r),
+            Bytecode::SubDouble(dest, lhs, rhs) => self.double_binop(dest, lhs, rhs, |l, r| l - r),
+
+            Bytecode::NegInt(dest, src) => {
+                let value = self.int(src).wrapping_neg();
+                self.set_int(dest, value);
+                Step::Next
+            }
+            Bytecode::NegLong(dest, src) => {
+                let value = self.long(src).wrapping_neg();
+                self.set_long(dest, value);
+                Step::Next
+            }
+            Bytecode::NegFloat(dest, src) => {
+                let value = -self.float(src);
+                self.set_float(dest, value);
+                Step::Next
+            }
+            Bytecode::NegDouble(dest, src) => {
+                let value = -self.double(src);
+                self.set_double(dest, value);
+                Step::Next
+            }
+
+            Bytecode::MulInt(dest, lhs, rhs) => self.int_binop(dest, lhs, rhs, i32::wrapping_mul),
+            Bytecode::MulLong(dest, lhs, rhs) => self.long_binop(dest, lhs, rhs, i64::wrapping_mul),
+            Bytecode::MulFloat(dest, lhs, rhs) => self.float_binop(dest, lhs, rhs, |l, r| l * r),
+            Bytecode::MulDouble(dest, lhs, rhs) => self.double_binop(dest, lhs, rhs, |l, r| l * r),
+
+            Bytecode::DivInt(dest, lhs, rhs) => self.int_div(dest, lhs, rhs, i32::wrapping_div),
+            Bytecode::DivLong(dest, lhs, rhs) => self.long_div(dest, lhs, rhs, i64::wrapping_div),
+            Bytecode::DivFloat(dest, lhs, rhs) => self.float_binop(dest, lhs, rhs, |l, r| l / r),
+            Bytecode::DivDouble(dest, lhs, rhs) => self.double_binop(dest, lhs, rhs, |l, r| l / r),
+
+            Bytecode::ModInt(dest, lhs, rhs) => self.int_div(dest, lhs, rhs, i32::wrapping_rem),
+            Bytecode::ModLong(dest, lhs, rhs) => self.long_div(dest, lhs, rhs, i64::wrapping_rem),
+
+            Bytecode::AndInt(dest, lhs, rhs) => self.int_binop(dest, lhs, rhs, |l, r| l & r),
+            Bytecode::AndLong(dest, lhs, rhs) => self.long_binop(dest, lhs, rhs, |l, r| l & r),
+            Bytecode::OrInt(dest, lhs, rhs) => self.int_binop(dest, lhs, rhs, |l, r| l | r),
+            Bytecode::OrLong(dest, lhs, rhs) => self.long_binop(dest, lhs, rhs, |l, r| l | r),
+            Bytecode::XorInt(dest, lhs, rhs) => self.int_binop(dest, lhs, rhs, |l, r| l ^ r),
+            Bytecode::XorLong(dest, lhs, rhs) => self.long_binop(dest, lhs, rhs, |l, r| l ^ r),
+
+            Bytecode::NotBool(dest, src) => {
+                let value = !self.bool(src);
+                self.set_bool(dest, value);
+                Step::Next
+            }
+            Bytecode::NotByte(dest, src) => {
+                let value = !(self.get(src) as u8);
+                self.set(dest, value as u64);
+                Step::Next
+            }
+            Bytecode::NotInt(dest, src) => {
+                let value = !self.int(src);
+                self.set_int(dest, value);
+                Step::Next
+            }
+            Bytecode::NotLong(dest, src) => {
+                let value = !self.long(src);
+                self.set_long(dest, value);
+                Step::Next
+            }
+
+            // shift counts are masked like on x64
+            Bytecode::ShlInt(dest, lhs, rhs) => {
+                self.int_binop(dest, lhs, rhs, |l, r| l.wrapping_shl(r as u32))
+            }
+            Bytecode::ShlLong(dest, lhs, rhs) => {
+                let value = self.long(lhs).wrapping_shl(self.int(rhs) as u32);
+                self.set_long(dest, value);
+                Step::Next
+            }
+            Bytecode::ShrInt(dest, lhs, rhs) => self.int_binop(dest, lhs, rhs, |l, r| {
+                (l as u32).wrapping_shr(r as u32) as i32
+            }),
+            Bytecode::ShrLong(dest, lhs, rhs) => {
+                let value = (self.long(lhs) as u64).wrapping_shr(self.int(rhs) as u32);
+                self.set_long(dest, value as i64);
+                Step::Next
+            }
+            Bytecode::SarInt(dest, lhs, rhs) => {
+                self.int_binop(dest, lhs, rhs, |l, r| l.wrapping_shr(r as u32))
+            }
+            Bytecode::SarLong(dest, lhs, rhs) => {
+                let value = self.long(lhs).wrapping_shr(self.int(rhs) as u32);
+                self.set_long(dest, value);
+                Step::Next
+            }
+
+            Bytecode::SqrtFloat(dest, src) => {
+                let value = self.float(src).sqrt();
+                self.set_float(dest, value);
+                Step::Next
+            }
+            Bytecode::SqrtDouble(dest, src) => {
+                let value = self.double(src).sqrt();
+                self.set_double(dest, value);
+                Step::Next
+            }
+
+            Bytecode::ExtendByteToInt(dest, src)
+            | Bytecode::ExtendByteToLong(dest, src)
+            | Bytecode::ExtendCharToLong(dest, src)
+            | Bytecode::CastCharToInt(dest, src)
+            | Bytecode::CastIntToChar(dest, src)
+            | Bytecode::CastIntToByte(dest, src)
+            | Bytecode::CastLongToByte(dest, src)
+            | Bytecode::CastLongToChar(dest, src)
+            | Bytecode::CastLongToInt(dest, src) => {
+                let value = truncate(self.ty(dest), self.get(src));
+                self.set(dest, value);
+                Step::Next
+            }
+            Bytecode::ExtendIntToLong(dest, src) => {
+                let value = self.int(src) as i64;
+                self.set_long(dest, value);
+                Step::Next
+            }
+
+            Bytecode::ConvertIntToFloat(dest, src) => {
+                let value = self.int(src) as f32;
+                self.set_float(dest, value);
+                Step::Next
+            }
+            Bytecode::ConvertIntToDouble(dest, src) => {
+                let value = self.int(src) as f64;
+                self.set_double(dest, value);
+                Step::Next
+            }
+            Bytecode::ConvertLongToFloat(dest, src) => {
+                let value = self.long(src) as f32;
+                self.set_float(dest, value);
+                Step::Next
+            }
+            Bytecode::ConvertLongToDouble(dest, src) => {
+                let value = self.long(src) as f64;
+                self.set_double(dest, value);
+                Step::Next
+            }
+            Bytecode::TruncateFloatToInt(dest, src) => {
+                let value = truncate_to_int(self.float(src) as f64);
+                self.set_int(dest, value);
+                Step::Next
+            }
+            Bytecode::TruncateFloatToLong(dest, src) => {
+                let value = truncate_to_long(self.float(src) as f64);
+                self.set_long(dest, value);
+                Step::Next
+            }
+            Bytecode::TruncateDoubleToInt(dest, src) => {
+                let value = truncate_to_int(self.double(src));
+                self.set_int(dest, value);
+                Step::Next
+            }
+            Bytecode::TruncateDoubleToLong(dest, src) => {
+                let value = truncate_to_long(self.double(src));
+                self.set_long(dest, value);
+                Step::Next
+            }
+            Bytecode::PromoteFloatToDouble(dest, src) => {
+                let value = self.float(src) as f64;
+                self.set_double(dest, value);
+                Step::Next
+            }
+            Bytecode::DemoteDoubleToFloat(dest, src) => {
+                let value = self.double(src) as f32;
+                self.set_float(dest, value);
+                Step::Next
+            }
+
+            Bytecode::ReinterpretFloatAsInt(dest, src)
+            | Bytecode::ReinterpretIntAsFloat(dest, src)
+            | Bytecode::ReinterpretDoubleAsLong(dest, src)
+            | Bytecode::ReinterpretLongAsDouble(dest, src) => self.mov(dest, src),
+
+            Bytecode::MovBool(dest, src)
+            | Bytecode::MovByte(dest, src)
+            | Bytecode::MovChar(dest, src)
+            | Bytecode::MovInt(dest, src)
+            | Bytecode::MovLong(dest, src)
+            | Bytecode::MovFloat(dest, src)
+            | Bytecode::MovDouble(dest, src)
+            | Bytecode::MovPtr(dest, src) => self.mov(dest, src),
+
+            Bytecode::LoadFieldBool(dest, obj, cls_id, field_id)
+            | Bytecode::LoadFieldByte(dest, obj, cls_id, field_id)
+            | Bytecode::LoadFieldChar(dest, obj, cls_id, field_id)
+            | Bytecode::LoadFieldInt(dest, obj, cls_id, field_id)
+            | Bytecode::LoadFieldLong(dest, obj, cls_id, field_id)
+            | Bytecode::LoadFieldFloat(dest, obj, cls_id, field_id)
+            | Bytecode::LoadFieldDouble(dest, obj, cls_id, field_id)
+            | Bytecode::LoadFieldPtr(dest, obj, cls_id, field_id) => {
+                self.load_field(dest, obj, cls_id, field_id)
+            }
+
+            Bytecode::StoreFieldBool(src, obj, cls_id, field_id)
+            | Bytecode::StoreFieldByte(src, obj, cls_id, field_id)
+            | Bytecode::StoreFieldChar(src, obj, cls_id, field_id)
+            | Bytecode::StoreFieldInt(src, obj, cls_id, field_id)
+            | Bytecode::StoreFieldLong(src, obj, cls_id, field_id)
+            | Bytecode::StoreFieldFloat(src, obj, cls_id, field_id)
+            | Bytecode::StoreFieldDouble(src, obj, cls_id, field_id)
+            | Bytecode::StoreFieldPtr(src, obj, cls_id, field_id) => {
+                self.store_field(src, obj, cls_id, field_id)
+            }
+
+            Bytecode::LoadGlobalBool(dest, gid)
+            | Bytecode::LoadGlobalByte(dest, gid)
+            | Bytecode::LoadGlobalChar(dest, gid)
+            | Bytecode::LoadGlobalInt(dest, gid)
+            | Bytecode::LoadGlobalLong(dest, gid)
+            | Bytecode::LoadGlobalFloat(dest, gid)
+            | Bytecode::LoadGlobalDouble(dest, gid)
+            | Bytecode::LoadGlobalPtr(dest, gid) => self.load_global(dest, gid),
+
+            Bytecode::StoreGlobalBool(src, gid)
+            | Bytecode::StoreGlobalByte(src, gid)
+            | Bytecode::StoreGlobalChar(src, gid)
+            | Bytecode::StoreGlobalInt(src, gid)
+            | Bytecode::StoreGlobalLong(src, gid)
+            | Bytecode::StoreGlobalFloat(src, gid)
+            | Bytecode::StoreGlobalDouble(src, gid)
+            | Bytecode::StoreGlobalPtr(src, gid) => self.store_global(src, gid),
+
+            Bytecode::LoadArrayBool(dest, arr, idx)
+            | Bytecode::LoadArrayByte(dest, arr, idx)
+            | Bytecode::LoadArrayChar(dest, arr, idx)
+            | Bytecode::LoadArrayInt(dest, arr, idx)
+            | Bytecode::LoadArrayLong(dest, arr, idx)
+            | Bytecode::LoadArrayFloat(dest, arr, idx)
+            | Bytecode::LoadArrayDouble(dest, arr, idx)
+            | Bytecode::LoadArrayPtr(dest, arr, idx) => self.load_array(dest, arr, idx),
+
+            Bytecode::StoreArrayBool(src, arr, idx)
+            | Bytecode::StoreArrayByte(src, arr, idx)
+            | Bytecode::StoreArrayChar(src, arr, idx)
+            | Bytecode::StoreArrayInt(src, arr, idx)
+            | Bytecode::StoreArrayLong(src, arr, idx)
+            | Bytecode::StoreArrayFloat(src, arr, idx)
+            | Bytecode::StoreArrayDouble(src, arr, idx)
+            | Bytecode::StoreArrayPtr(src, arr, idx) => self.store_array(src, arr, idx),
+
+            Bytecode::ArrayLength(dest, arr) => self.array_length(dest, arr),
+            Bytecode::SetUint8(address, value) => {
+                let address = self.ptr(address);
+                unsafe {
+                    store(address, MachineMode::Int8, self.get(value));
+                }
+                Step::Next
+            }
+
+            Bytecode::ConstNil(dest)
+            | Bytecode::ConstFalse(dest)
+            | Bytecode::ConstZeroByte(dest)
+            | Bytecode::ConstZeroInt(dest)
+            | Bytecode::ConstZeroLong(dest)
+            | Bytecode::ConstZeroFloat(dest)
+            | Bytecode::ConstZeroDouble(dest) => {
+                self.set(dest, 0);
+                Step::Next
+            }
+            Bytecode::ConstTrue(dest) => {
+                self.set_bool(dest, true);
+                Step::Next
+            }
+            Bytecode::ConstChar(dest, value) => {
+                self.set(dest, value as u64);
+                Step::Next
+            }
+            Bytecode::ConstByte(dest, value) => {
+                self.set(dest, value as u64);
+                Step::Next
+            }
+            Bytecode::ConstInt(dest, value) => {
+                self.set(dest, value as u64);
+                Step::Next
+            }
+            Bytecode::ConstLong(dest, value) => {
+                self.set(dest, value);
+                Step::Next
+            }
+            Bytecode::ConstFloat(dest, value) => {
+                self.set_float(dest, value);
+                Step::Next
+            }
+            Bytecode::ConstDouble(dest, value) => {
+                self.set_double(dest, value);
+                Step::Next
+            }
+            Bytecode::ConstString(dest, sp) => {
+                let value = self.fct.string(sp);
+                self.set(dest, value.to_usize() as u64);
+                Step::Next
+            }
+
+            Bytecode::TestEqPtr(dest, lhs, rhs) | Bytecode::TestEqBool(dest, lhs, rhs) => {
+                self.test(dest, self.get(lhs), self.get(rhs), |l, r| l == r)
+            }
+            Bytecode::TestNePtr(dest, lhs, rhs) | Bytecode::TestNeBool(dest, lhs, rhs) => {
+                self.test(dest, self.get(lhs), self.get(rhs), |l, r| l != r)
+            }
+
+            // bytes and chars are unsigned
+            Bytecode::TestEqByte(dest, lhs, rhs) | Bytecode::TestEqChar(dest, lhs, rhs) => {
+                self.test(dest, self.get(lhs), self.get(rhs), |l, r| l == r)
+            }
+            Bytecode::TestNeByte(dest, lhs, rhs) | Bytecode::TestNeChar(dest, lhs, rhs) => {
+                self.test(dest, self.get(lhs), self.get(rhs), |l, r| l != r)
+            }
+            Bytecode::TestGtByte(dest, lhs, rhs) | Bytecode::TestGtChar(dest, lhs, rhs) => {
+                self.test(dest, self.get(lhs), self.get(rhs), |l, r| l > r)
+            }
+            Bytecode::TestGeByte(dest, lhs, rhs) | Bytecode::TestGeChar(dest, lhs, rhs) => {
+                self.test(dest, self.get(lhs), self.get(rhs), |l, r| l >= r)
+            }
+            Bytecode::TestLtByte(dest, lhs, rhs) | Bytecode::TestLtChar(dest, lhs, rhs) => {
+                self.test(dest, self.get(lhs), self.get(rhs), |l, r| l < r)
+            }
+            Bytecode::TestLeByte(dest, lhs, rhs) | Bytecode::TestLeChar(dest, lhs, rhs) => {
+                self.test(dest, self.get(lhs), self.get(rhs), |l, r| l <= r)
+            }
+
+            Bytecode::TestEqInt(dest, lhs, rhs) => {
+                self.test(dest, self.int(lhs), self.int(rhs), |l, r| l == r)
+            }
+            Bytecode::TestNeInt(dest, lhs, rhs) => {
+                self.test(dest, self.int(lhs), self.int(rhs), |l, r| l != r)
+            }
+            Bytecode::TestGtInt(dest, lhs, rhs) => {
+                self.test(dest, self.int(lhs), self.int(rhs), |l, r| l > r)
+            }
+            Bytecode::TestGeInt(dest, lhs, rhs) => {
+                self.test(dest, self.int(lhs), self.int(rhs), |l, r| l >= r)
+            }
+            Bytecode::TestLtInt(dest, lhs, rhs) => {
+                self.test(dest, self.int(lhs), self.int(rhs), |l, r| l < r)
+            }
+            Bytecode::TestLeInt(dest, lhs, rhs) => {
+                self.test(dest, self.int(lhs), self.int(rhs), |l, r| l <= r)
+            }
+
+            Bytecode::TestEqLong(dest, lhs, rhs) => {
+                self.test(dest, self.long(lhs), self.long(rhs), |l, r| l == r)
+            }
+            Bytecode::TestNeLong(dest, lhs, rhs) => {
+                self.test(dest, self.long(lhs), self.long(rhs), |l, r| l != r)
+            }
+            Bytecode::TestGtLong(dest, lhs, rhs) => {
+                self.test(dest, self.long(lhs), self.long(rhs), |l, r| l > r)
+            }
+            Bytecode::TestGeLong(dest, lhs, rhs) => {
+                self.test(dest, self.long(lhs), self.long(rhs), |l, r| l >= r)
+            }
+            Bytecode::TestLtLong(dest, lhs, rhs) => {
+                self.test(dest, self.long(lhs), self.long(rhs), |l, r| l < r)
+            }
+            Bytecode::TestLeLong(dest, lhs, rhs) => {
+                self.test(dest, self.long(lhs), self.long(rhs), |l, r| l <= r)
+            }
+
+            Bytecode::TestEqFloat(dest, lhs, rhs) => {
+                self.test(dest, self.float(lhs), self.float(rhs), |l, r| l == r)
+            }
+            Bytecode::TestNeFloat(dest, lhs, rhs) => {
+                self.test(dest, self.float(lhs), self.float(rhs), |l, r| l != r)
+            }
+            Bytecode::TestGtFloat(dest, lhs, rhs) => {
+                self.test(dest, self.float(lhs), self.float(rhs), |l, r| l > r)
+            }
+            Bytecode::TestGeFloat(dest, lhs, rhs) => {
+                self.test(dest, self.float(lhs), self.float(rhs), |l, r| l >= r)
+            }
+            Bytecode::TestLtFloat(dest, lhs, rhs) => {
+                self.test(dest, self.float(lhs), self.float(rhs), |l, r| l < r)
+            }
+            Bytecode::TestLeFloat(dest, lhs, rhs) => {
+                self.test(dest, self.float(lhs), self.float(rhs), |l, r| l <= r)
+            }
+
+            Bytecode::TestEqDouble(dest, lhs, rhs) => {
+                self.test(dest, self.double(lhs), self.double(rhs), |l, r| l == r)
+            }
+            Bytecode::TestNeDouble(dest, lhs, rhs) => {
+                self.test(dest, self.double(lhs), self.double(rhs), |l, r| l != r)
+            }
+            Bytecode::TestGtDouble(dest, lhs, rhs) => {
+                self.test(dest, self.double(lhs), self.double(rhs), |l, r| l > r)
+            }
+            Bytecode::TestGeDouble(dest, lhs, rhs) => {
+                self.test(dest, self.double(lhs), self.double(rhs), |l, r| l >= r)
+            }
+            Bytecode::TestLtDouble(dest, lhs, rhs) => {
+                self.test(dest, self.double(lhs), self.double(rhs), |l, r| l < r)
+            }
+            Bytecode::TestLeDouble(dest, lhs, rhs) => {
+                self.test(dest, self.double(lhs), self.double(rhs), |l, r| l <= r)
+            }
+
+            Bytecode::JumpIfFalse(opnd, target) => self.jump_if(opnd, false, target),
+            Bytecode::JumpIfTrue(opnd, target) => self.jump_if(opnd, true, target),
+            Bytecode::Jump(target) => Step::Jump(target),
+
+            Bytecode::InvokeDirectVoid(fct_id, start, num)
+            | Bytecode::InvokeStaticVoid(fct_id, start, num) => {
+                self.invoke_direct(None, fct_id, start, num)
+            }
+            Bytecode::InvokeDirectBool(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectByte(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectChar(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectInt(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectLong(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectFloat(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectDouble(dest, fct_id, start, num)
+            | Bytecode::InvokeDirectPtr(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticBool(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticByte(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticChar(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticInt(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticLong(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticFloat(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticDouble(dest, fct_id, start, num)
+            | Bytecode::InvokeStaticPtr(dest, fct_id, start, num) => {
+                self.invoke_direct(Some(dest), fct_id, start, num)
+            }
+
+            Bytecode::InvokeVirtualVoid(fct_id, start, num) => {
+                self.invoke_virtual(None, fct_id, start, num)
+            }
+            Bytecode::InvokeVirtualBool(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualByte(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualChar(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualInt(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualLong(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualFloat(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualDouble(dest, fct_id, start, num)
+            | Bytecode::InvokeVirtualPtr(dest, fct_id, start, num) => {
+                self.invoke_virtual(Some(dest), fct_id, start, num)
+            }
+
+            Bytecode::InvokeLambdaVoid(start, num) => self.invoke_lambda(None, start, num),
+            Bytecode::InvokeLambdaBool(dest, start, num)
+            | Bytecode::InvokeLambdaByte(dest, start, num)
+            | Bytecode::InvokeLambdaChar(dest, start, num)
+            | Bytecode::InvokeLambdaInt(dest, start, num)
+            | Bytecode::InvokeLambdaLong(dest, start, num)
+            | Bytecode::InvokeLambdaFloat(dest, start, num)
+            | Bytecode::InvokeLambdaDouble(dest, start, num)
+            | Bytecode::InvokeLambdaPtr(dest, start, num) => {
+                self.invoke_lambda(Some(dest), start, num)
+            }
+
+            Bytecode::NewObject(dest, cls_id) => self.alloc(dest, cls_id, None),
+            Bytecode::NewArray(dest, cls_id, length) => self.alloc(dest, cls_id, Some(length)),
+
+            Bytecode::NilCheck(obj) => self.nil_check(obj),
+            Bytecode::InstanceOf(dest, obj, cls_id) => self.instance_of(dest, obj, cls_id),
+            Bytecode::CheckedCast(obj, cls_id) => self.checked_cast(obj, cls_id),
+
+            Bytecode::Throw(exception) => self.throw(exception),
+            Bytecode::Trap(trap) => Step::Trap(trap),
+            Bytecode::Spawn(closure) => self.spawn(closure),
+            Bytecode::Debug => Step::Next,
+
+            Bytecode::RetBool(value)
+            | Bytecode::RetByte(value)
+            | Bytecode::RetChar(value)
+            | Bytecode::RetInt(value)
+            | Bytecode::RetLong(value)
+            | Bytecode::RetFloat(value)
+            | Bytecode::RetDouble(value)
+            | Bytecode::RetPtr(value) => Step::Return(self.get(value)),
+            Bytecode::RetVoid => Step::Return(0),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::bytecode::generate::{BytecodeIdx, Register, StrConstPoolIdx};
 use crate::class::{ClassDefId, FieldId};
+use crate::os::signal::Trap;
 use crate::vm::{FctId, GlobalId};
 
 #[derive(PartialEq, Debug)]
@@ -10,19 +11,76 @@ pub enum Bytecode {
     AddDouble(Register, Register, Register),
 
     SubInt(Register, Register, Register),
+    SubLong(Register, Register, Register),
+    SubFloat(Register, Register, Register),
+    SubDouble(Register, Register, Register),
+
     NegInt(Register, Register),
     NegLong(Register, Register),
+    NegFloat(Register, Register),
+    NegDouble(Register, Register),
+
     MulInt(Register, Register, Register),
+    MulLong(Register, Register, Register),
+    MulFloat(Register, Register, Register),
+    MulDouble(Register, Register, Register),
+
     DivInt(Register, Register, Register),
+    DivLong(Register, Register, Register),
+    DivFloat(Register, Register, Register),
+    DivDouble(Register, Register, Register),
+
     ModInt(Register, Register, Register),
+    ModLong(Register, Register, Register),
+
     AndInt(Register, Register, Register),
+    AndLong(Register, Register, Register),
     OrInt(Register, Register, Register),
+    OrLong(Register, Register, Register),
     XorInt(Register, Register, Register),
+    XorLong(Register, Register, Register),
+
     NotBool(Register, Register),
+    NotByte(Register, Register),
+    NotInt(Register, Register),
+    NotLong(Register, Register),
 
     ShlInt(Register, Register, Register),
+    ShlLong(Register, Register, Register),
     ShrInt(Register, Register, Register),
+    ShrLong(Register, Register, Register),
     SarInt(Register, Register, Register),
+    SarLong(Register, Register, Register),
+
+    SqrtFloat(Register, Register),
+    SqrtDouble(Register, Register),
+
+    ExtendByteToInt(Register, Register),
+    ExtendByteToLong(Register, Register),
+    ExtendCharToLong(Register, Register),
+    ExtendIntToLong(Register, Register),
+    CastCharToInt(Register, Register),
+    CastIntToChar(Register, Register),
+    CastIntToByte(Register, Register),
+    CastLongToByte(Register, Register),
+    CastLongToChar(Register, Register),
+    CastLongToInt(Register, Register),
+
+    ConvertIntToFloat(Register, Register),
+    ConvertIntToDouble(Register, Register),
+    ConvertLongToFloat(Register, Register),
+    ConvertLongToDouble(Register, Register),
+    TruncateFloatToInt(Register, Register),
+    TruncateFloatToLong(Register, Register),
+    TruncateDoubleToInt(Register, Register),
+    TruncateDoubleToLong(Register, Register),
+    PromoteFloatToDouble(Register, Register),
+    DemoteDoubleToFloat(Register, Register),
+
+    ReinterpretFloatAsInt(Register, Register),
+    ReinterpretIntAsFloat(Register, Register),
+    ReinterpretDoubleAsLong(Register, Register),
+    ReinterpretLongAsDouble(Register, Register),
 
     MovBool(Register, Register),
     MovByte(Register, Register),
@@ -42,6 +100,15 @@ pub enum Bytecode {
     LoadFieldDouble(Register, Register, ClassDefId, FieldId),
     LoadFieldPtr(Register, Register, ClassDefId, FieldId),
 
+    StoreFieldBool(Register, Register, ClassDefId, FieldId),
+    StoreFieldByte(Register, Register, ClassDefId, FieldId),
+    StoreFieldChar(Register, Register, ClassDefId, FieldId),
+    StoreFieldInt(Register, Register, ClassDefId, FieldId),
+    StoreFieldLong(Register, Register, ClassDefId, FieldId),
+    StoreFieldFloat(Register, Register, ClassDefId, FieldId),
+    StoreFieldDouble(Register, Register, ClassDefId, FieldId),
+    StoreFieldPtr(Register, Register, ClassDefId, FieldId),
+
     LoadGlobalBool(Register, GlobalId),
     LoadGlobalByte(Register, GlobalId),
     LoadGlobalChar(Register, GlobalId),
@@ -51,6 +118,36 @@ pub enum Bytecode {
     LoadGlobalDouble(Register, GlobalId),
     LoadGlobalPtr(Register, GlobalId),
 
+    StoreGlobalBool(Register, GlobalId),
+    StoreGlobalByte(Register, GlobalId),
+    StoreGlobalChar(Register, GlobalId),
+    StoreGlobalInt(Register, GlobalId),
+    StoreGlobalLong(Register, GlobalId),
+    StoreGlobalFloat(Register, GlobalId),
+    StoreGlobalDouble(Register, GlobalId),
+    StoreGlobalPtr(Register, GlobalId),
+
+    LoadArrayBool(Register, Register, Register),
+    LoadArrayByte(Register, Register, Register),
+    LoadArrayChar(Register, Register, Register),
+    LoadArrayInt(Register, Register, Register),
+    LoadArrayLong(Register, Register, Register),
+    LoadArrayFloat(Register, Register, Register),
+    LoadArrayDouble(Register, Register, Register),
+    LoadArrayPtr(Register, Register, Register),
+
+    StoreArrayBool(Register, Register, Register),
+    StoreArrayByte(Register, Register, Register),
+    StoreArrayChar(Register, Register, Register),
+    StoreArrayInt(Register, Register, Register),
+    StoreArrayLong(Register, Register, Register),
+    StoreArrayFloat(Register, Register, Register),
+    StoreArrayDouble(Register, Register, Register),
+    StoreArrayPtr(Register, Register, Register),
+
+    ArrayLength(Register, Register),
+    SetUint8(Register, Register),
+
     ConstNil(Register),
     ConstTrue(Register),
     ConstFalse(Register),
@@ -70,6 +167,23 @@ pub enum Bytecode {
     TestEqPtr(Register, Register, Register),
     TestNePtr(Register, Register, Register),
 
+    TestEqBool(Register, Register, Register),
+    TestNeBool(Register, Register, Register),
+
+    TestEqByte(Register, Register, Register),
+    TestNeByte(Register, Register, Register),
+    TestGtByte(Register, Register, Register),
+    TestGeByte(Register, Register, Register),
+    TestLtByte(Register, Register, Register),
+    TestLeByte(Register, Register, Register),
+
+    TestEqChar(Register, Register, Register),
+    TestNeChar(Register, Register, Register),
+    TestGtChar(Register, Register, Register),
+    TestGeChar(Register, Register, Register),
+    TestLtChar(Register, Register, Register),
+    TestLeChar(Register, Register, Register),
+
     TestEqInt(Register, Register, Register),
     TestNeInt(Register, Register, Register),
     TestGtInt(Register, Register, Register),
@@ -77,6 +191,27 @@ pub enum Bytecode {
     TestLtInt(Register, Register, Register),
     TestLeInt(Register, Register, Register),
 
+    TestEqLong(Register, Register, Register),
+    TestNeLong(Register, Register, Register),
+    TestGtLong(Register, Register, Register),
+    TestGeLong(Register, Register, Register),
+    TestLtLong(Register, Register, Register),
+    TestLeLong(Register, Register, Register),
+
+    TestEqFloat(Register, Register, Register),
+    TestNeFloat(Register, Register, Register),
+    TestGtFloat(Register, Register, Register),
+    TestGeFloat(Register, Register, Register),
+    TestLtFloat(Register, Register, Register),
+    TestLeFloat(Register, Register, Register),
+
+    TestEqDouble(Register, Register, Register),
+    TestNeDouble(Register, Register, Register),
+    TestGtDouble(Register, Register, Register),
+    TestGeDouble(Register, Register, Register),
+    TestLtDouble(Register, Register, Register),
+    TestLeDouble(Register, Register, Register),
+
     JumpIfFalse(Register, BytecodeIdx),
     JumpIfTrue(Register, BytecodeIdx),
     Jump(BytecodeIdx),
@@ -111,7 +246,27 @@ pub enum Bytecode {
     InvokeStaticDouble(Register, FctId, Register, usize),
     InvokeStaticPtr(Register, FctId, Register, usize),
 
+    InvokeLambdaVoid(Register, usize),
+    InvokeLambdaBool(Register, Register, usize),
+    InvokeLambdaByte(Register, Register, usize),
+    InvokeLambdaChar(Register, Register, usize),
+    InvokeLambdaInt(Register, Register, usize),
+    InvokeLambdaLong(Register, Register, usize),
+    InvokeLambdaFloat(Register, Register, usize),
+    InvokeLambdaDouble(Register, Register, usize),
+    InvokeLambdaPtr(Register, Register, usize),
+
     NewObject(Register, ClassDefId),
+    NewArray(Register, ClassDefId, Register),
+
+    NilCheck(Register),
+    InstanceOf(Register, Register, ClassDefId),
+    CheckedCast(Register, ClassDefId),
+
+    Throw(Register),
+    Trap(Trap),
+    Spawn(Register),
+    Debug,
 
     RetBool(Register),
     RetByte(Register),
@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+
+use crate::class::ClassId;
+use crate::vm::{Fct, FctId, FctParent, VM};
+
+// class hierarchy analysis: knows which virtual methods are overridden by any of
+// the loaded classes. All classes are loaded before code is compiled, calls of
+// methods without overrides are compiled as direct calls.
+pub struct ClassHierarchy {
+    // methods overridden by at least one loaded class
+    overridden: HashSet<FctId>,
+}
+
+impl ClassHierarchy {
+    pub fn new() -> ClassHierarchy {
+        ClassHierarchy {
+            overridden: HashSet::new(),
+        }
+    }
+}
+
+// true if any loaded class overrides the method
+pub fn has_overrides(vm: &VM, fct_id: FctId) -> bool {
+    vm.cha.lock().overridden.contains(&fct_id)
+}
+
+// true if virtual calls of `fct` can be replaced by direct calls
+pub fn can_devirtualize(vm: &VM, fct: &Fct) -> bool {
+    if !fct.is_virtual() || fct.is_abstract || !fct.type_params.is_empty() {
+        return false;
+    }
+
+    // vtables of generic classes are not specialized for the type params
+    let cls_id = match fct.parent {
+        FctParent::Class(cls_id) => cls_id,
+        _ => return false,
+    };
+
+    {
+        let cls = vm.classes.idx(cls_id);
+        let cls = cls.read();
+
+        if cls.is_generic() {
+            return false;
+        }
+    }
+
+    !has_overrides(vm, fct.id)
+}
+
+// registers the overrides of the class' methods
+pub fn add_class(vm: &VM, cls_id: ClassId) {
+    let overridden: Vec<FctId> = {
+        let cls = vm.classes.idx(cls_id);
+        let cls = cls.read();
+
+        cls.methods
+            .iter()
+            .filter_map(|&method| vm.fcts.idx(method).read().overrides)
+            .collect()
+    };
+
+    let mut cha = vm.cha.lock();
+    cha.overridden.extend(overridden);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_overridden_method() {
+        test::parse(
+            "@open class A { @open fun foo() {} }
+            class B: A { @override fun foo() {} }",
+            |vm| {
+                let foo = vm.cls_method_by_name("A", "foo", false).unwrap();
+                assert!(has_overrides(vm, foo));
+
+                let foo = vm.fcts.idx(foo);
+                assert!(!can_devirtualize(vm, &*foo.read()));
+            },
+        );
+    }
+
+    #[test]
+    fn test_open_method_without_overrides() {
+        test::parse(
+            "@open class A { @open fun foo() {} }
+            class B: A { fun bar() {} }",
+            |vm| {
+                let foo = vm.cls_method_by_name("A", "foo", false).unwrap();
+                assert!(!has_overrides(vm, foo));
+
+                let foo = vm.fcts.idx(foo);
+                assert!(can_devirtualize(vm, &*foo.read()));
+            },
+        );
+    }
+
+    #[test]
+    fn test_override_in_indirect_subclass() {
+        test::parse(
+            "@open class A { @open fun foo() {} }
+            @open class B: A { }
+            class C: B { @override fun foo() {} }",
+            |vm| {
+                let foo = vm.cls_method_by_name("A", "foo", false).unwrap();
+                assert!(has_overrides(vm, foo));
+
+                let foo = vm.cls_method_by_name("C", "foo", false).unwrap();
+                assert!(!has_overrides(vm, foo));
+            },
+        );
+    }
+
+    #[test]
+    fn test_abstract_method() {
+        test::parse("@open @abstract class A { @abstract fun foo(); }", |vm| {
+            let foo = vm.cls_method_by_name("A", "foo", false).unwrap();
+            let foo = vm.fcts.idx(foo);
+            assert!(!can_devirtualize(vm, &*foo.read()));
+        });
+    }
+}
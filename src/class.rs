@@ -338,6 +338,9 @@ pub struct ClassDef {
     pub size: ClassSize,
     pub ref_fields: Vec<i32>,
     pub vtable: Option<VTableBox>,
+
+    // for closure objects: lambda and type params it is compiled with
+    pub lambda: Option<(FctId, TypeParams, TypeParams)>,
 }
 
 impl ClassDef {
@@ -1,4 +1,7 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
 use execstate::ExecState;
+use gc::Address;
 use object::{Obj, Ref};
 
 pub use self::param::*;
@@ -48,6 +51,28 @@ pub fn flush_icache(start: *const u8, len: usize) {
     }
 }
 
+// overwrites the instruction at `from` with a branch to `to`, instructions are
+// always written atomically. Returns the overwritten code for `unpatch_jump`.
+pub fn patch_jump(from: Address, to: Address) -> u64 {
+    let diff = (to.to_usize() as isize - from.to_usize() as isize) / 4;
+    let insn = asm::b_imm(diff as i32);
+
+    let old = unsafe { (*from.to_ptr::<AtomicU32>()).swap(insn, Ordering::SeqCst) };
+
+    flush_icache(from.to_ptr(), 4);
+
+    old as u64
+}
+
+// restores the code at `from` overwritten by `patch_jump`
+pub fn unpatch_jump(from: Address, code: u64) {
+    unsafe {
+        (*from.to_ptr::<AtomicU32>()).store(code as u32, Ordering::SeqCst);
+    }
+
+    flush_icache(from.to_ptr(), 4);
+}
+
 pub fn cacheline_sizes() -> (usize, usize) {
     let value: usize;
 
@@ -65,6 +90,13 @@ pub fn fp_from_execstate(es: &ExecState) -> usize {
     es.regs[REG_FP.asm() as usize]
 }
 
+// simulates a call from the current instruction to `fct_ptr` with `arg` as first argument
+pub fn call_from_execstate(es: &mut ExecState, fct_ptr: usize, arg: usize) {
+    es.regs[REG_LR.asm() as usize] = es.pc;
+    es.regs[REG_PARAMS[0].asm() as usize] = arg;
+    es.pc = fct_ptr;
+}
+
 pub fn get_exception_object(es: &ExecState) -> Ref<Obj> {
     let obj: Ref<Obj> = es.regs[REG_RESULT.asm() as usize].into();
 
@@ -4,6 +4,10 @@ pub const REG_COUNT: usize = 31;
 pub static REG_PARAMS: [Reg; 8] = [R0, R1, R2, R3, R4, R5, R6, R7];
 pub static SCRATCH: [Reg; 5] = [R9, R12, R13, R14, R15];
 
+// registers handed out by the register allocator of the optimizing compiler,
+// untouched by the macro assembler and the calling convention
+pub static REG_ALLOCATABLE: [Reg; 9] = [R19, R20, R21, R22, R23, R24, R25, R26, R27];
+
 pub const REG_RESULT: Reg = R0;
 pub const REG_TMP1: Reg = R10;
 pub const REG_TMP2: Reg = R11;
@@ -49,6 +53,7 @@ pub const R30: Reg = Reg(30);
 pub const FREG_RESULT: FReg = F0;
 pub const FREG_TMP1: FReg = F1;
 pub static FREG_PARAMS: [FReg; 8] = [F0, F1, F2, F3, F4, F5, F6, F7];
+pub static FREG_ALLOCATABLE: [FReg; 8] = [F16, F17, F18, F19, F20, F21, F22, F23];
 
 pub const F0: FReg = FReg(0);
 pub const F1: FReg = FReg(1);
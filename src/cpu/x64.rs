@@ -1,6 +1,8 @@
-use std::sync::atomic::{compiler_fence, Ordering};
+use std::sync::atomic::{compiler_fence, AtomicU64, Ordering};
 
 use crate::execstate::ExecState;
+use crate::gc::Address;
+use crate::mem::fits_i32;
 use crate::object::{Obj, Ref};
 use crate::os::signal::Trap;
 
@@ -16,6 +18,41 @@ pub fn flush_icache(_: *const u8, _: usize) {
     compiler_fence(Ordering::SeqCst);
 }
 
+// overwrites the instruction at `from` with a jump to `to`, the jump is written
+// with a single aligned store: concurrently running threads either execute the
+// old instructions or the jump. Returns the overwritten code for `unpatch_jump`.
+pub fn patch_jump(from: Address, to: Address) -> u64 {
+    assert!(from.to_usize() % 8 == 0);
+
+    // jmp rel32 has 5 bytes
+    let diff = to.to_usize() as i64 - (from.to_usize() as i64 + 5);
+    assert!(fits_i32(diff));
+
+    let old = unsafe {
+        let insn = &*from.to_ptr::<AtomicU64>();
+        let old = insn.load(Ordering::SeqCst);
+        let mut bytes = old.to_le_bytes();
+        bytes[0] = 0xE9;
+        bytes[1..5].copy_from_slice(&(diff as i32).to_le_bytes());
+        insn.store(u64::from_le_bytes(bytes), Ordering::SeqCst);
+
+        old
+    };
+
+    flush_icache(from.to_ptr(), 8);
+
+    old
+}
+
+// restores the code at `from` overwritten by `patch_jump`
+pub fn unpatch_jump(from: Address, code: u64) {
+    unsafe {
+        (*from.to_ptr::<AtomicU64>()).store(code, Ordering::SeqCst);
+    }
+
+    flush_icache(from.to_ptr(), 8);
+}
+
 pub fn get_exception_object(es: &ExecState) -> Ref<Obj> {
     let obj: Ref<Obj> = es.regs[REG_RESULT.int() as usize].into();
 
@@ -30,6 +67,18 @@ pub fn ra_from_execstate(es: &ExecState) -> usize {
     unsafe { *(es.sp as *const usize) }
 }
 
+// simulates a call from the current instruction to `fct_ptr` with `arg` as first argument
+pub fn call_from_execstate(es: &mut ExecState, fct_ptr: usize, arg: usize) {
+    es.sp -= 8;
+    unsafe {
+        *(es.sp as *mut usize) = es.pc;
+    }
+
+    es.regs[RSP.int() as usize] = es.sp;
+    es.regs[REG_PARAMS[0].int() as usize] = arg;
+    es.pc = fct_ptr;
+}
+
 pub fn read_trap(es: &ExecState) -> Option<Trap> {
     let v1;
     let v2;
@@ -236,9 +236,8 @@ fn emit_membase(buf: &mut MacroAssembler, base: Reg, disp: i32, dest: Reg) {
 pub fn emit_cmp_imm_reg(buf: &mut MacroAssembler, mode: MachineMode, imm: i32, reg: Reg) {
     let x64 = match mode {
         MachineMode::Int8 | MachineMode::Int32 => 0,
-        MachineMode::Int64 => unimplemented!(),
+        MachineMode::Int64 | MachineMode::Ptr => 1,
         MachineMode::Float32 | MachineMode::Float64 => unreachable!(),
-        MachineMode::Ptr => 1,
     };
 
     emit_aluq_imm_reg(buf, x64, imm, reg, 0x3d, 0b111);
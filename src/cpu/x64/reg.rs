@@ -4,6 +4,10 @@ pub const REG_COUNT: usize = 16;
 pub static REG_PARAMS: [Reg; 6] = [RDI, RSI, RDX, RCX, R8, R9];
 pub static SCRATCH: [Reg; 3] = [R9, R8, RDI];
 
+// registers handed out by the register allocator of the optimizing compiler,
+// untouched by the macro assembler and the calling convention
+pub static REG_ALLOCATABLE: [Reg; 4] = [RBX, R12, R13, R14];
+
 pub const REG_RESULT: Reg = RAX;
 pub const REG_TMP1: Reg = R10;
 pub const REG_TMP2: Reg = R11;
@@ -35,6 +39,7 @@ pub const FREG_RESULT: FReg = XMM0;
 pub const FREG_TMP1: FReg = XMM1;
 
 pub static FREG_PARAMS: [FReg; 8] = [XMM0, XMM1, XMM2, XMM3, XMM4, XMM5, XMM6, XMM7];
+pub static FREG_ALLOCATABLE: [FReg; 8] = [XMM8, XMM9, XMM10, XMM11, XMM12, XMM13, XMM14, XMM15];
 
 pub const XMM0: FReg = FReg(0);
 pub const XMM1: FReg = FReg(1);
@@ -3,6 +3,7 @@ use std::cmp::{max, min};
 use std::default::Default;
 use std::ops::Deref;
 
+use crate::boots::pass::PassManager;
 use crate::gc::M;
 use docopt::Docopt;
 use rustc_serialize;
@@ -29,12 +30,24 @@ Options:
     --emit-asm=<fct>        Emits assembly code to stdout.
     --emit-asm-file         Emits assembly code into file `dora-<pid>.asm`.
     --emit-bytecode=<fct>   Emits bytecode to stdout.
+    --emit-boots=<fct>      Emits boots IR after running the optimization passes to stdout.
     --emit-stubs            Emits generated stubs.
     --emit-debug=<fct>      Emits debug instruction at beginning of functions.
     --emit-debug-compile    Emits debug instruction at beginning of compile thunk.
     --emit-debug-throw      Emits debug instruction at beginning of throw thunk.
     --emit-debug-entry      Emits debug instruction at beginning of entry thunk.
     --omit-bounds-check     Omit array index out of bounds checks.
+    --opt-passes=<list>     Comma-separated list of passes run on boots IR.
+                            Possible values: escape, sccp, gvn, licm, bce, dce, simplify-cfg.
+    --opt-verbose           Log which functions were optimized or rejected.
+    --opt-inline-size=<num>  Maximum bytecode size of inlined functions [default: 50].
+                            Inlining is disabled with 0.
+    --enable-tiering        Recompile hot functions with the optimizing compiler.
+    --tier-calls=<num>      Invocations until a function is optimized [default: 1000].
+    --tier-loops=<num>      Loop iterations until a function is optimized [default: 10000].
+                            Running loops switch to optimized code only with --bc=astcompiler.
+    --compile-threads=<num>  Number of threads compiling called and hot functions in the background [default: 0].
+                            Functions are only compiled on their first invocation with 0.
     --check                 Only type check given program.
     --asm-syntax TYPE       Emits assembly with Intel or AT&T syntax.
                             Allowed values: intel, att.
@@ -56,7 +69,7 @@ Options:
     --gc-young-appel        Use Appel dynamic resizing of young generation.
     --gc-semi-ratio=<num>   Use fixed ratio of semi space in young generation.
 
-    --bc=<name>             Switch Baseline Compiler. Possible values: cannon, astcompiler [default: astcompiler].
+    --bc=<name>             Switch Baseline Compiler. Possible values: cannon, astcompiler, interpreter [default: astcompiler].
 
     --disable-tlab          Disable tlab allocation.
     --disable-barrier       Disable barriers.
@@ -76,10 +89,18 @@ pub struct Args {
     pub flag_emit_asm: Option<String>,
     pub flag_emit_asm_file: bool,
     pub flag_emit_bytecode: Option<String>,
+    pub flag_emit_boots: Option<String>,
     pub flag_emit_llvm: bool,
     pub flag_emit_stubs: bool,
     pub flag_enable_perf: bool,
     pub flag_omit_bounds_check: bool,
+    pub flag_opt_passes: Option<String>,
+    pub flag_opt_verbose: bool,
+    pub flag_opt_inline_size: usize,
+    pub flag_enable_tiering: bool,
+    pub flag_tier_calls: usize,
+    pub flag_tier_loops: usize,
+    pub flag_compile_threads: usize,
     pub flag_version: bool,
     pub flag_emit_debug: Option<String>,
     pub flag_emit_debug_throw: bool,
@@ -166,6 +187,29 @@ impl Args {
     pub fn bc(&self) -> BaselineName {
         self.flag_bc.unwrap_or(BaselineName::AstCompiler)
     }
+
+    pub fn tier_calls(&self) -> i32 {
+        min(max(self.flag_tier_calls, 1), i32::max_value() as usize) as i32
+    }
+
+    pub fn tier_loops(&self) -> i32 {
+        min(max(self.flag_tier_loops, 1), i32::max_value() as usize) as i32
+    }
+
+    pub fn compile_threads(&self) -> usize {
+        self.flag_compile_threads
+    }
+
+    pub fn opt_inline_size(&self) -> usize {
+        self.flag_opt_inline_size
+    }
+
+    pub fn opt_passes(&self) -> Result<PassManager, String> {
+        match self.flag_opt_passes {
+            Some(ref names) => PassManager::from_names(names),
+            None => Ok(PassManager::default_pipeline()),
+        }
+    }
 }
 
 impl Default for Args {
@@ -178,6 +222,7 @@ impl Default for Args {
             flag_emit_asm: None,
             flag_emit_asm_file: false,
             flag_emit_bytecode: None,
+            flag_emit_boots: None,
             flag_emit_llvm: false,
             flag_emit_stubs: false,
             flag_emit_debug: None,
@@ -186,6 +231,13 @@ impl Default for Args {
             flag_emit_debug_entry: false,
             flag_enable_perf: false,
             flag_omit_bounds_check: false,
+            flag_opt_passes: None,
+            flag_opt_verbose: false,
+            flag_opt_inline_size: 50,
+            flag_enable_tiering: false,
+            flag_tier_calls: 1000,
+            flag_tier_loops: 10000,
+            flag_compile_threads: 0,
             flag_version: false,
             flag_asm_syntax: None,
             flag_gc_events: false,
@@ -231,6 +283,7 @@ pub enum CollectorName {
 pub enum BaselineName {
     Cannon,
     AstCompiler,
+    Interpreter,
 }
 
 #[derive(Copy, Clone, Debug, RustcDecodable)]
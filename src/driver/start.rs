@@ -6,6 +6,7 @@ use crate::vm::{exception_get_and_clear, Fct, FctId};
 use dora_parser::ast::{self, Ast};
 use dora_parser::error::msg::Msg;
 
+use crate::boots;
 use crate::driver::cmd;
 use crate::object;
 use crate::os;
@@ -33,6 +34,11 @@ pub fn start(content: Option<&str>) -> i32 {
         return 0;
     }
 
+    if let Err(msg) = args.opt_passes() {
+        println!("{}", msg);
+        return 1;
+    }
+
     let mut ast = Ast::new();
     let empty = Ast::new();
     let mut vm = VM::new(args, &empty);
@@ -71,6 +77,10 @@ pub fn start(content: Option<&str>) -> i32 {
         return 1;
     }
 
+    if vm.args.flag_emit_llvm {
+        boots::emit_llvm(&vm);
+    }
+
     // if --check given, stop after type/semantic check
     if vm.args.flag_check {
         return 0;
@@ -79,6 +89,7 @@ pub fn start(content: Option<&str>) -> i32 {
     let mut timer = Timer::new(vm.args.flag_gc_stats);
 
     vm.threads.attach_current_thread();
+    vm.compile_queue.start(vm.args.compile_threads());
 
     let code = if vm.args.cmd_test {
         run_tests(&vm)
@@ -88,6 +99,7 @@ pub fn start(content: Option<&str>) -> i32 {
 
     vm.threads.detach_current_thread();
     vm.threads.join_all();
+    vm.compile_queue.stop();
 
     os::unregister_signals();
 
@@ -1,30 +1,51 @@
+use std::mem::size_of;
 use std::ptr;
 
-use crate::baseline::fct::{CatchType, JitFctId};
+use crate::baseline::fct::CatchType;
 use crate::baseline::map::CodeDescriptor;
+use crate::bytecode::interpreter::InterpFrame;
+use crate::class::{ClassDef, ClassDefId};
 use crate::cpu::fp_from_execstate;
 use crate::execstate::ExecState;
+use crate::gc::Address;
 use crate::handle::root;
 use crate::object::{alloc, Array, IntArray, Obj, Ref, StackTraceElement, Str, Throwable};
 use crate::os::signal::Trap;
 use crate::stdlib;
 use crate::threads::THREAD;
-use crate::vm::{get_vm, FctParent, VM};
+use crate::vm::{exception_get_and_clear, get_vm, FctId, FctParent, VM};
+
+// deep recursion (e.g. a stack overflow) would otherwise produce
+// stack traces with hundreds of thousands of entries
+const MAX_STACKTRACE_DEPTH: usize = 1024;
 
 pub struct Stacktrace {
     elems: Vec<StackElem>,
+    truncated: bool,
 }
 
 impl Stacktrace {
     pub fn new() -> Stacktrace {
-        Stacktrace { elems: Vec::new() }
+        Stacktrace {
+            elems: Vec::new(),
+            truncated: false,
+        }
     }
 
     pub fn len(&self) -> usize {
         self.elems.len()
     }
 
-    pub fn push_entry(&mut self, fct_id: JitFctId, lineno: i32) {
+    fn is_full(&self) -> bool {
+        self.elems.len() >= MAX_STACKTRACE_DEPTH
+    }
+
+    pub fn push_entry(&mut self, fct_id: FctId, lineno: i32) {
+        if self.is_full() {
+            self.truncated = true;
+            return;
+        }
+
         self.elems.push(StackElem {
             fct_id: fct_id,
             lineno: lineno,
@@ -33,9 +54,7 @@ impl Stacktrace {
 
     pub fn dump(&self, vm: &VM) {
         for (ind, elem) in self.elems.iter().enumerate() {
-            let jit_fct = vm.jit_fcts.idx(elem.fct_id);
-            let fct_id = jit_fct.fct_id();
-            let fct = vm.fcts.idx(fct_id);
+            let fct = vm.fcts.idx(elem.fct_id);
             let fct = fct.read();
             let name = fct.full_name(vm);
             print!("{}: {}: ", ind, name);
@@ -46,11 +65,15 @@ impl Stacktrace {
                 println!("{}", elem.lineno);
             }
         }
+
+        if self.truncated {
+            println!("...");
+        }
     }
 }
 
 struct StackElem {
-    fct_id: JitFctId,
+    fct_id: FctId,
     lineno: i32,
 }
 
@@ -63,6 +86,9 @@ pub struct DoraToNativeInfo {
 
     // some program counter into native stub
     pub pc: usize,
+
+    // frame of the interpreter, if the native stub runs a function in the interpreter
+    pub interp_frame: *const InterpFrame,
 }
 
 impl DoraToNativeInfo {
@@ -71,6 +97,7 @@ impl DoraToNativeInfo {
             last: ptr::null(),
             fp: 0,
             pc: 0,
+            interp_frame: ptr::null(),
         }
     }
 }
@@ -97,7 +124,7 @@ fn frames_from_dtns(stacktrace: &mut Stacktrace, vm: &VM) {
         dtn
     });
 
-    while !dtn_ptr.is_null() {
+    while !dtn_ptr.is_null() && !stacktrace.truncated {
         let dtn = unsafe { &*dtn_ptr };
 
         let pc: usize = dtn.pc;
@@ -110,22 +137,21 @@ fn frames_from_dtns(stacktrace: &mut Stacktrace, vm: &VM) {
 }
 
 fn frames_from_pc(stacktrace: &mut Stacktrace, vm: &VM, pc: usize, mut fp: usize) {
-    if !determine_stack_entry(stacktrace, vm, pc) {
+    if !determine_stack_entry(stacktrace, vm, pc, fp) {
         return;
     }
 
-    while fp != 0 {
+    while fp != 0 && !stacktrace.truncated {
         let ra = unsafe { *((fp + 8) as *const usize) };
+        fp = unsafe { *(fp as *const usize) };
 
-        if !determine_stack_entry(stacktrace, vm, ra) {
+        if !determine_stack_entry(stacktrace, vm, ra, fp) {
             return;
         }
-
-        fp = unsafe { *(fp as *const usize) };
     }
 }
 
-fn determine_stack_entry(stacktrace: &mut Stacktrace, vm: &VM, pc: usize) -> bool {
+fn determine_stack_entry(stacktrace: &mut Stacktrace, vm: &VM, pc: usize, fp: usize) -> bool {
     let code_map = vm.code_map.lock();
     let data = code_map.get(pc.into());
 
@@ -134,14 +160,19 @@ fn determine_stack_entry(stacktrace: &mut Stacktrace, vm: &VM, pc: usize) -> boo
             let jit_fct = vm.jit_fcts.idx(fct_id);
 
             let offset = pc - jit_fct.fct_ptr().to_usize();
-            let jit_fct = jit_fct.to_base().expect("baseline expected");
-            let lineno = jit_fct.lineno_for_offset(offset as i32);
+            let mut lineno = jit_fct.lineno_for_offset(offset as i32);
 
             if lineno == 0 {
                 panic!("lineno not found for program point");
             }
 
-            stacktrace.push_entry(fct_id, lineno);
+            // inlined functions do not have frames of their own
+            for &(fct_id, call_lineno) in jit_fct.inlined_frames_for_offset(offset as i32) {
+                stacktrace.push_entry(fct_id, lineno);
+                lineno = call_lineno;
+            }
+
+            stacktrace.push_entry(jit_fct.fct_id(), lineno);
 
             true
         }
@@ -151,7 +182,26 @@ fn determine_stack_entry(stacktrace: &mut Stacktrace, vm: &VM, pc: usize) -> boo
             let fct = vm.fcts.idx(jit_fct.fct_id());
             let fct = fct.read();
 
-            stacktrace.push_entry(fct_id, fct.ast.pos.line as i32);
+            stacktrace.push_entry(fct.id, fct.ast.pos.line as i32);
+
+            true
+        }
+
+        Some(CodeDescriptor::InterpThunk(fct_id)) => {
+            let dtn = interp_dtn(fp);
+            let jit_fct = vm.jit_fcts.idx(fct_id);
+            let fct_id = jit_fct.fct_id();
+
+            let lineno = if dtn.interp_frame.is_null() {
+                let fct = vm.fcts.idx(fct_id);
+                let fct = fct.read();
+
+                fct.ast.pos.line as i32
+            } else {
+                unsafe { (*dtn.interp_frame).lineno() }
+            };
+
+            stacktrace.push_entry(fct_id, lineno);
 
             true
         }
@@ -159,7 +209,12 @@ fn determine_stack_entry(stacktrace: &mut Stacktrace, vm: &VM, pc: usize) -> boo
         Some(CodeDescriptor::TrapThunk) => true,
         Some(CodeDescriptor::ThrowThunk) => true,
         Some(CodeDescriptor::AllocThunk) => true,
+        Some(CodeDescriptor::SpawnThunk) => true,
+        Some(CodeDescriptor::TierUpThunk) => true,
+        Some(CodeDescriptor::OsrThunk) => true,
+        Some(CodeDescriptor::DeoptThunk) => true,
         Some(CodeDescriptor::DoraEntry) => false,
+        Some(CodeDescriptor::DoraInvoke(_)) => false,
 
         _ => {
             println!("data = {:?}", data);
@@ -168,11 +223,18 @@ fn determine_stack_entry(stacktrace: &mut Stacktrace, vm: &VM, pc: usize) -> boo
     }
 }
 
+// the DoraToNativeInfo of the interpreter stub with frame pointer `fp`
+pub fn interp_dtn<'a>(fp: usize) -> &'a DoraToNativeInfo {
+    let dtn = fp - size_of::<DoraToNativeInfo>();
+    unsafe { &*(dtn as *const DoraToNativeInfo) }
+}
+
 #[derive(PartialEq, Eq, Debug)]
 enum HandlerFound {
     Yes,
     No,
     Stop,
+    Invoke,
 }
 
 pub struct ThrowResume {
@@ -181,8 +243,19 @@ pub struct ThrowResume {
     fp: usize,
 }
 
-pub extern "C" fn throw(exception: Ref<Obj>, resume: &mut ThrowResume) {
+impl ThrowResume {
+    fn empty() -> ThrowResume {
+        ThrowResume {
+            pc: 0,
+            sp: 0,
+            fp: 0,
+        }
+    }
+}
+
+pub extern "C" fn throw(resume: &mut ThrowResume) {
     let vm = get_vm();
+    let exception: Ref<Obj> = Address::from_ptr(exception_get_and_clear()).into();
 
     let dtn = THREAD.with(|thread| {
         let thread = thread.borrow();
@@ -192,39 +265,108 @@ pub extern "C" fn throw(exception: Ref<Obj>, resume: &mut ThrowResume) {
     });
     let dtn = unsafe { &*dtn };
 
-    let mut pc: usize = dtn.pc;
-    let mut fp: usize = dtn.fp;
+    let cls_id = exception.header().vtbl().class().cls_id;
+    let trap = cls_id.and_then(|cls_id| vm.vips.trap_for_class(cls_id));
+
+    let res = find_handler_in_stack(vm, exception, trap.is_some(), dtn.pc, dtn.fp, resume);
+
+    if res != HandlerFound::Yes {
+        // no handler found
+        stdlib::fatal_trap(trap.unwrap_or(Trap::THROW));
+    }
+}
+
+// called by the interpreter before an exception leaves an interpreted function,
+// aborts while the whole stack is still available for the stack trace
+pub fn ensure_handler(vm: &VM, exception: Ref<Obj>, pc: usize, fp: usize) {
+    let cls_id = exception.header().vtbl().class().cls_id;
+    let trap = cls_id.and_then(|cls_id| vm.vips.trap_for_class(cls_id));
+
+    let mut resume = ThrowResume::empty();
+    let res = find_handler_in_stack(vm, exception, trap.is_some(), pc, fp, &mut resume);
+
+    if res != HandlerFound::Yes {
+        stdlib::fatal_trap(trap.unwrap_or(Trap::THROW));
+    }
+}
+
+// walks the stack starting at frame (pc, fp) until a handler is found. An interpreted
+// function calls out through the DoraInvoke stub: execution resumes in the stub, but the
+// search continues in the interpreted caller so that the outcome is known before any
+// frame is left.
+fn find_handler_in_stack(
+    vm: &VM,
+    exception: Ref<Obj>,
+    unchecked: bool,
+    mut pc: usize,
+    mut fp: usize,
+    resume: &mut ThrowResume,
+) -> HandlerFound {
+    let mut scratch = ThrowResume::empty();
+    let mut resume = Some(resume);
 
     while fp != 0 {
-        let res = find_handler(vm, exception, pc, fp, resume);
+        let res = match resume {
+            Some(ref mut resume) => find_handler(vm, exception, unchecked, pc, fp, resume, false),
+            None => find_handler(vm, exception, unchecked, pc, fp, &mut scratch, true),
+        };
 
         match res {
-            HandlerFound::Yes => {
-                // handler found, resume from there
-                return;
-            }
-
-            HandlerFound::Stop => {
-                // no handler found
-                stdlib::trap(Trap::THROW.int());
-            }
+            HandlerFound::Yes | HandlerFound::Stop => return res,
 
             HandlerFound::No => {
                 // try next stack frame
             }
+
+            HandlerFound::Invoke => {
+                // resume target is fixed, only check the remaining frames
+                resume = None;
+
+                let dtn = invoking_dtn(fp);
+                let frame = unsafe { &*dtn.interp_frame };
+
+                if frame.handler(vm, exception).is_some() {
+                    return HandlerFound::Yes;
+                }
+
+                // continue with the interpreter stub of the caller
+                pc = dtn.pc;
+                fp = dtn.fp;
+                continue;
+            }
         }
 
         pc = unsafe { *((fp + 8) as *const usize) };
         fp = unsafe { *(fp as *const usize) };
     }
+
+    HandlerFound::Stop
+}
+
+// the DoraToNativeInfo of the interpreter stub whose interpreter called
+// the DoraInvoke stub with frame pointer `fp`
+fn invoking_dtn<'a>(fp: usize) -> &'a DoraToNativeInfo {
+    let mut dtn = THREAD.with(|thread| thread.borrow().dtn());
+
+    while !dtn.is_null() {
+        if dtn as usize > fp {
+            return unsafe { &*dtn };
+        }
+
+        dtn = unsafe { (*dtn).last };
+    }
+
+    panic!("interpreter frame of DoraInvoke stub not found");
 }
 
 fn find_handler(
     vm: &VM,
     exception: Ref<Obj>,
+    unchecked: bool,
     pc: usize,
     fp: usize,
     resume: &mut ThrowResume,
+    dry_run: bool,
 ) -> HandlerFound {
     let data = {
         let code_map = vm.code_map.lock();
@@ -232,21 +374,33 @@ fn find_handler(
     };
 
     match data {
-        Some(CodeDescriptor::DoraFct(fct_id)) | Some(CodeDescriptor::NativeThunk(fct_id)) => {
+        Some(CodeDescriptor::DoraFct(fct_id))
+        | Some(CodeDescriptor::NativeThunk(fct_id))
+        | Some(CodeDescriptor::InterpThunk(fct_id))
+        | Some(CodeDescriptor::DoraInvoke(fct_id)) => {
             let jit_fct = vm.jit_fcts.idx(fct_id);
-            let jit_fct = jit_fct.to_base().expect("baseline expected");
             let clsptr = exception.header().vtbl().classptr();
 
-            for entry in &jit_fct.exception_handlers {
+            // handlers of inner statements are emitted before handlers of
+            // enclosing statements, so the first matching handler is the innermost one
+            for entry in jit_fct.exception_handlers() {
                 // println!("entry = {:x} to {:x} for {:?}",
                 //          entry.try_start, entry.try_end, entry.catch_type);
 
                 if entry.try_start < pc
                     && pc <= entry.try_end
-                    && (entry.catch_type == CatchType::Any
-                        || entry.catch_type == CatchType::Class(clsptr))
+                    && catches(vm, entry.catch_type, clsptr)
                 {
-                    let stacksize = jit_fct.framesize as usize;
+                    let stacksize = jit_fct.framesize() as usize;
+
+                    let found = match data {
+                        Some(CodeDescriptor::DoraInvoke(_)) => HandlerFound::Invoke,
+                        _ => HandlerFound::Yes,
+                    };
+
+                    if dry_run {
+                        return found;
+                    }
 
                     if let Some(offset) = entry.offset {
                         let arg = (fp as isize + offset as isize) as usize;
@@ -260,18 +414,14 @@ fn find_handler(
                     resume.sp = fp - stacksize;
                     resume.fp = fp;
 
-                    return HandlerFound::Yes;
-                } else if pc > entry.try_end {
-                    // exception handlers are sorted, no more possible handlers
-                    // in this function
-
-                    return HandlerFound::No;
+                    return found;
                 }
             }
 
             // exception can only bubble up in stacktrace if current function
-            // is allowed to throw exceptions
-            if !jit_fct.throws {
+            // is allowed to throw exceptions, exceptions raised by traps
+            // are unchecked and may pass every function
+            if !jit_fct.throws() && !unchecked {
                 return HandlerFound::Stop;
             }
 
@@ -280,6 +430,7 @@ fn find_handler(
 
         Some(CodeDescriptor::DoraEntry) => HandlerFound::Stop,
         Some(CodeDescriptor::ThrowThunk) => HandlerFound::No,
+        Some(CodeDescriptor::TrapThunk) => HandlerFound::No,
 
         _ => {
             println!("data = {:?}", data);
@@ -288,6 +439,44 @@ fn find_handler(
     }
 }
 
+// a handler catches instances of its class, exceptions raised by traps
+// are also caught by handlers for one of their superclasses
+pub fn catches(vm: &VM, catch_type: CatchType, clsptr: *const ClassDef) -> bool {
+    let catch_clsptr = match catch_type {
+        CatchType::Any => return true,
+        CatchType::Class(catch_clsptr) => catch_clsptr,
+    };
+
+    if clsptr == catch_clsptr {
+        return true;
+    }
+
+    let cls_def = unsafe { &*clsptr };
+    let trap = cls_def
+        .cls_id
+        .and_then(|cls_id| vm.vips.trap_for_class(cls_id));
+
+    if trap.is_none() {
+        return false;
+    }
+
+    let mut cls_def_id = cls_def.id;
+
+    loop {
+        let cls_def = vm.class_defs.idx(cls_def_id);
+        let cls_def = cls_def.read();
+
+        if &*cls_def as *const ClassDef == catch_clsptr {
+            return true;
+        }
+
+        match cls_def.parent_id {
+            Some(parent_id) => cls_def_id = parent_id,
+            None => return false,
+        }
+    }
+}
+
 pub extern "C" fn retrieve_stack_trace(obj: Ref<Throwable>) {
     let vm = get_vm();
     set_exception_backtrace(vm, obj, true);
@@ -308,9 +497,7 @@ pub extern "C" fn stack_element(obj: Ref<Throwable>, ind: i32) -> Ref<StackTrace
     let mut ste = root(ste);
     ste.line = lineno;
 
-    let jit_fct_id = JitFctId::from(fct_id as usize);
-    let jit_fct = vm.jit_fcts.idx(jit_fct_id);
-    let fct = vm.fcts.idx(jit_fct.fct_id());
+    let fct = vm.fcts.idx(FctId::from(fct_id as usize));
     let fct = fct.read();
     let name = fct.full_name(vm);
     ste.name = Str::from_buffer(vm, name.as_bytes());
@@ -318,12 +505,15 @@ pub extern "C" fn stack_element(obj: Ref<Throwable>, ind: i32) -> Ref<StackTrace
     ste.direct()
 }
 
-pub fn alloc_exception(vm: &VM, msg: Ref<Str>) -> Ref<Throwable> {
-    let cls_id = vm.vips.exception(vm);
+pub fn alloc_exception(vm: &VM, cls_id: ClassDefId, msg: Ref<Str>) -> Ref<Throwable> {
+    let msg = root(msg);
     let obj: Ref<Throwable> = alloc(vm, cls_id).cast();
     let mut obj = root(obj);
 
-    obj.msg = msg;
+    // the backtrace allocates, fields need to be nil when the GC sees the object
+    obj.msg = msg.direct();
+    obj.backtrace = Ref::null();
+    obj.elements = Ref::null();
     set_exception_backtrace(vm, obj.direct(), false);
 
     obj.direct()
@@ -340,9 +530,7 @@ fn set_exception_backtrace(vm: &VM, obj: Ref<Throwable>, via_retrieve: bool) {
     // ignore every element until first not inside susubclass of Throwable (ctor of Exception)
     if via_retrieve {
         for elem in stacktrace.elems.iter() {
-            let jit_fct_id = JitFctId::from(elem.fct_id.idx() as usize);
-            let jit_fct = vm.jit_fcts.idx(jit_fct_id);
-            let fct_id = jit_fct.fct_id();
+            let fct_id = elem.fct_id;
             let fct = vm.fcts.idx(fct_id);
             let fct = fct.read();
 
@@ -406,7 +594,7 @@ fn set_exception_backtrace(vm: &VM, obj: Ref<Throwable>, via_retrieve: bool) {
 
     for elem in stacktrace.elems.iter().skip(skip) {
         array.set_at(i, elem.lineno);
-        array.set_at(i + 1, elem.fct_id.idx() as i32);
+        array.set_at(i + 1, elem.fct_id.0 as i32);
         i += 2;
     }
     obj.backtrace = array.direct();
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::baseline::map::CodeDescriptor;
-use crate::exception::DoraToNativeInfo;
+use crate::exception::{interp_dtn, DoraToNativeInfo};
 use crate::gc::Address;
 use crate::threads::DoraThread;
 use crate::vm::VM;
@@ -11,6 +11,7 @@ pub fn get_rootset(vm: &VM, threads: &[Arc<DoraThread>]) -> Vec<Slot> {
 
     determine_rootset_from_stack(&mut rootset, vm, threads);
     determine_rootset_from_handles(&mut rootset, threads);
+    determine_rootset_from_exceptions(&mut rootset, threads);
 
     determine_rootset_from_globals(&mut rootset, vm);
 
@@ -26,6 +27,13 @@ fn determine_rootset_from_handles(rootset: &mut Vec<Slot>, threads: &[Arc<DoraTh
     }
 }
 
+fn determine_rootset_from_exceptions(rootset: &mut Vec<Slot>, threads: &[Arc<DoraThread>]) {
+    for thread in threads {
+        let slot = Slot::at(thread.tld.exception_object_address());
+        rootset.push(slot);
+    }
+}
+
 fn determine_rootset_from_globals(rootset: &mut Vec<Slot>, vm: &VM) {
     for glob in vm.globals.iter() {
         let glob = glob.lock();
@@ -85,7 +93,6 @@ fn determine_rootset(rootset: &mut Vec<Slot>, vm: &VM, fp: usize, pc: usize) ->
             let jit_fct = vm.jit_fcts.idx(fct_id);
 
             let offset = pc - jit_fct.fct_ptr().to_usize();
-            let jit_fct = jit_fct.to_base().expect("baseline expected");
             let gcpoint = jit_fct
                 .gcpoint_for_offset(offset as i32)
                 .expect("no gcpoint");
@@ -99,8 +106,29 @@ fn determine_rootset(rootset: &mut Vec<Slot>, vm: &VM, fp: usize, pc: usize) ->
         }
 
         Some(CodeDescriptor::AllocThunk) => true,
+        Some(CodeDescriptor::TrapThunk) => true,
+        Some(CodeDescriptor::SpawnThunk) => true,
+        Some(CodeDescriptor::TierUpThunk) => true,
+        Some(CodeDescriptor::OsrThunk) => true,
+        Some(CodeDescriptor::DeoptThunk) => true,
         Some(CodeDescriptor::NativeThunk(_)) => true,
+
+        Some(CodeDescriptor::InterpThunk(_)) => {
+            let dtn = interp_dtn(fp);
+
+            if !dtn.interp_frame.is_null() {
+                let frame = unsafe { &*dtn.interp_frame };
+
+                for addr in frame.roots() {
+                    rootset.push(Slot::at(addr));
+                }
+            }
+
+            true
+        }
+
         Some(CodeDescriptor::DoraEntry) => false,
+        Some(CodeDescriptor::DoraInvoke(_)) => false,
 
         _ => {
             println!("data = {:?}", data);
@@ -195,7 +195,7 @@ impl<'a, 'ast> FullCollector<'a, 'ast> {
         });
 
         if !self.fits_into_heap() {
-            stdlib::trap(Trap::OOM.int());
+            stdlib::fatal_trap(Trap::OOM);
         }
 
         self.old_protected.commit_single_region(self.old_top);
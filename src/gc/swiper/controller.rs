@@ -132,7 +132,7 @@ pub fn stop(
     let young_size = eden_size + semi_size;
 
     if old_size + young_size > config.max_heap_size {
-        stdlib::trap(Trap::OOM.int());
+        stdlib::fatal_trap(Trap::OOM);
     }
 
     young.set_limit(eden_size, semi_size);
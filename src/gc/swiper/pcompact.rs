@@ -248,7 +248,7 @@ impl<'a, 'ast> ParallelFullCollector<'a, 'ast> {
         let regions: Vec<Region> = self.regions.iter().map(|r| r.mapping).collect();
 
         if !self.fits_into_heap(&regions) {
-            stdlib::trap(Trap::OOM.int());
+            stdlib::fatal_trap(Trap::OOM);
         }
 
         self.compute_actual_forward(pool);
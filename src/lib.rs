@@ -21,6 +21,7 @@ macro_rules! offset_of {
 mod baseline;
 mod boots;
 mod bytecode;
+mod cha;
 mod class;
 mod cpu;
 mod driver;
@@ -6,16 +6,17 @@ use std::rc::Rc;
 use crate::baseline::codegen::CondCode;
 use crate::baseline::codegen::ExprStore;
 use crate::baseline::fct::{
-    BailoutInfo, Bailouts, CatchType, Comment, Comments, ExHandler, GcPoint, GcPoints,
+    BailoutInfo, Bailouts, CatchType, Comment, Comments, ExHandler, GcPoint, GcPoints, InlineTable,
     JitBaselineFct, JitDescriptor, LineNumberTable,
 };
 use crate::cpu::{Mem, Reg, SCRATCH};
 use crate::dseg::DSeg;
 use crate::mem;
 use crate::object::Header;
+use crate::opt::fct::JitOptFct;
 use crate::os::signal::Trap;
 use crate::ty::MachineMode;
-use crate::vm::VM;
+use crate::vm::{FctId, VM};
 use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
 use dora_parser::lexer::position::Position;
 
@@ -35,13 +36,15 @@ pub struct MacroAssembler {
     data: Vec<u8>,
     labels: Vec<Option<usize>>,
     jumps: Vec<ForwardJump>,
-    bailouts: Vec<(Label, Trap, Position)>,
+    bailouts: Vec<(Label, Trap, Position, usize, GcPoint, Vec<(FctId, i32)>)>,
     bailout_infos: Bailouts,
     nil_checks: HashSet<i32>,
     dseg: DSeg,
     gcpoints: GcPoints,
     comments: Comments,
     linenos: LineNumberTable,
+    inlined_frames: Vec<(FctId, i32)>,
+    inline_table: InlineTable,
     exception_handlers: Vec<ExHandler>,
     scratch_registers: ScratchRegisters,
 }
@@ -59,6 +62,8 @@ impl MacroAssembler {
             gcpoints: GcPoints::new(),
             comments: Comments::new(),
             linenos: LineNumberTable::new(),
+            inlined_frames: Vec::new(),
+            inline_table: InlineTable::new(),
             exception_handlers: Vec::new(),
             scratch_registers: ScratchRegisters::new(),
         }
@@ -93,6 +98,27 @@ impl MacroAssembler {
         )
     }
 
+    pub fn jit_opt(mut self, vm: &VM, framesize: i32, fct_id: FctId, throws: bool) -> JitOptFct {
+        self.finish();
+        self.dseg.align(16);
+
+        JitOptFct::from_buffer(
+            vm,
+            &self.dseg,
+            &self.data,
+            self.bailout_infos,
+            self.nil_checks,
+            self.gcpoints,
+            framesize,
+            self.comments,
+            self.linenos,
+            self.inline_table,
+            fct_id,
+            throws,
+            self.exception_handlers,
+        )
+    }
+
     #[cfg(test)]
     pub fn buffer(&self) -> &[u8] {
         &self.data
@@ -106,17 +132,21 @@ impl MacroAssembler {
 
     fn finish(&mut self) {
         let bailouts = self.bailouts.drain(0..).collect::<Vec<_>>();
+        let has_bailouts = !bailouts.is_empty();
 
-        for bailout in &bailouts {
-            let (lbl, trap, pos) = *bailout;
-
+        for (lbl, trap, pos, origin, gcpoint, inlined_frames) in bailouts {
+            let start = self.pos();
+            self.set_inlined_frames(inlined_frames);
             self.bind_label(lbl);
-            self.trap(trap, pos);
+            self.trap(trap, pos, gcpoint);
+            let end = self.pos();
+
+            self.copy_exception_handlers(origin, (start, end));
         }
 
         // add nop after bailout traps, so that we can't find return address
         // in code map, even though return address is at function end.
-        if bailouts.len() > 0 {
+        if has_bailouts {
             self.nop();
         }
 
@@ -131,9 +161,9 @@ impl MacroAssembler {
         self.data.len()
     }
 
-    pub fn test_if_nil_bailout(&mut self, pos: Position, reg: Reg, trap: Trap) {
+    pub fn test_if_nil_bailout(&mut self, pos: Position, reg: Reg, trap: Trap, gcpoint: GcPoint) {
         let lbl = self.test_if_nil(reg);
-        self.emit_bailout(lbl, trap, pos);
+        self.emit_bailout(lbl, trap, pos, gcpoint);
     }
 
     pub fn test_if_nil(&mut self, reg: Reg) -> Label {
@@ -157,16 +187,30 @@ impl MacroAssembler {
     pub fn emit_lineno(&mut self, lineno: i32) {
         let pos = self.pos() as i32;
         self.linenos.insert(pos, lineno);
+
+        if !self.inlined_frames.is_empty() {
+            self.inline_table.insert(pos, self.inlined_frames.clone());
+        }
     }
 
     pub fn emit_lineno_if_missing(&mut self, lineno: i32) {
         let pos = self.pos() as i32;
 
         if self.linenos.get(pos) == 0 {
-            self.linenos.insert(pos, lineno);
+            self.emit_lineno(lineno);
         }
     }
 
+    // functions inlined into the code emitted next, stored
+    // together with every line number
+    pub fn set_inlined_frames(&mut self, frames: Vec<(FctId, i32)>) {
+        self.inlined_frames = frames;
+    }
+
+    pub fn inlined_frames(&self) -> &[(FctId, i32)] {
+        &self.inlined_frames
+    }
+
     pub fn emit_gcpoint(&mut self, gcpoint: GcPoint) {
         let pos = self.pos() as i32;
         self.gcpoints.insert(pos, gcpoint);
@@ -177,9 +221,15 @@ impl MacroAssembler {
         self.bailout_infos.insert(pos, info);
     }
 
-    pub fn emit_nil_check(&mut self) {
+    // a failed nil check throws from this instruction, the gcpoint
+    // describes the frame while the exception is allocated
+    pub fn emit_nil_check(&mut self, gcpoint: GcPoint) {
         let offset = self.pos() as i32;
         self.nil_checks.insert(offset);
+
+        if self.gcpoints.get(offset).is_none() {
+            self.gcpoints.insert(offset, gcpoint);
+        }
     }
 
     pub fn create_label(&mut self) -> Label {
@@ -201,12 +251,15 @@ impl MacroAssembler {
         self.labels[lbl_idx] = Some(self.pos());
     }
 
-    pub fn emit_bailout(&mut self, lbl: Label, trap: Trap, pos: Position) {
-        self.bailouts.push((lbl, trap, pos));
+    pub fn emit_bailout(&mut self, lbl: Label, trap: Trap, pos: Position, gcpoint: GcPoint) {
+        let origin = self.pos();
+        let inlined_frames = self.inlined_frames.clone();
+        self.bailouts
+            .push((lbl, trap, pos, origin, gcpoint, inlined_frames));
     }
 
-    pub fn emit_bailout_inplace(&mut self, trap: Trap, pos: Position) {
-        self.trap(trap, pos);
+    pub fn emit_bailout_inplace(&mut self, trap: Trap, pos: Position, gcpoint: GcPoint) {
+        self.trap(trap, pos, gcpoint);
     }
 
     pub fn emit_exception_handler(
@@ -225,6 +278,26 @@ impl MacroAssembler {
         });
     }
 
+    // out-of-line code (bailouts, slow paths) needs to be covered by the same
+    // exception handlers as the instruction at `origin` it belongs to
+    pub fn copy_exception_handlers(&mut self, origin: usize, span: (usize, usize)) {
+        for idx in 0..self.exception_handlers.len() {
+            let handler = &self.exception_handlers[idx];
+
+            if handler.try_start < origin && origin <= handler.try_end {
+                let handler = ExHandler {
+                    try_start: span.0,
+                    try_end: span.1,
+                    catch: handler.catch,
+                    offset: handler.offset,
+                    catch_type: handler.catch_type,
+                };
+
+                self.exception_handlers.push(handler);
+            }
+        }
+    }
+
     pub fn get_scratch(&self) -> ScratchReg {
         self.scratch_registers.get()
     }
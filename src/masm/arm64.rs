@@ -2,6 +2,7 @@ use baseline::codegen::CondCode;
 use baseline::expr::ExprStore;
 use baseline::fct::BailoutInfo;
 use baseline::fct::GcPoint;
+use baseline::ic::{CacheState, InlineCache, POLYMORPHIC_ENTRIES};
 use byteorder::{LittleEndian, WriteBytesExt};
 use class::TypeParams;
 use cpu::asm;
@@ -15,6 +16,7 @@ use masm::{Label, MacroAssembler};
 use mem::ptr_width;
 use object::{offset_of_array_data, offset_of_array_length, Header};
 use os::signal::Trap;
+use threads::ThreadLocalData;
 use ty::MachineMode;
 use vm::{get_vm, FctId};
 use vtable::VTable;
@@ -114,7 +116,7 @@ impl MacroAssembler {
         self.emit_u32(asm::blr(*scratch));
     }
 
-    pub fn indirect_call(&mut self, line: i32, index: u32) {
+    pub fn indirect_call(&mut self, line: i32, index: u32, gcpoint: GcPoint) {
         let obj = REG_PARAMS[0];
 
         // need to use scratch register instead of REG_RESULT for calculations
@@ -122,7 +124,13 @@ impl MacroAssembler {
         let scratch = self.get_scratch();
 
         // scratch = [obj] (load vtable)
-        self.load_base(MachineMode::Ptr, scratch.reg().into(), obj, 0, Some(line));
+        self.load_base(
+            MachineMode::Ptr,
+            scratch.reg().into(),
+            obj,
+            0,
+            Some((line, gcpoint)),
+        );
 
         // calculate offset of VTable entry
         let disp = VTable::offset_of_method_table() + (index as i32) * ptr_width();
@@ -139,6 +147,115 @@ impl MacroAssembler {
         self.emit_bailout_info(BailoutInfo::VirtCompile(index, TypeParams::empty()));
     }
 
+    // calls the target of the first entry in the inline cache at `cache` if the receiver's
+    // vtable matches, otherwise `stub` with the vtable in REG_TMP1 and the state in REG_TMP2
+    pub fn inline_cache_call(
+        &mut self,
+        line: i32,
+        cache: Address,
+        stub: Address,
+        gcpoint: GcPoint,
+    ) {
+        let obj = REG_PARAMS[0];
+        let lbl_miss = self.create_label();
+        let lbl_call = self.create_label();
+
+        // REG_RESULT (x0) is also the first parameter
+        let scratch = self.get_scratch();
+
+        // REG_TMP1 = [obj] (load vtable)
+        self.load_base(
+            MachineMode::Ptr,
+            REG_TMP1.into(),
+            obj,
+            0,
+            Some((line, gcpoint)),
+        );
+
+        // REG_TMP2 = current state of the cache
+        let disp = self.add_addr(cache.to_ptr());
+        let pos = self.pos() as i32;
+        self.load_constpool(REG_TMP2, disp + pos);
+        self.load_mem(
+            MachineMode::Ptr,
+            REG_TMP2.into(),
+            Mem::Base(REG_TMP2, InlineCache::offset_of_state()),
+        );
+
+        self.load_mem(
+            MachineMode::Ptr,
+            scratch.reg().into(),
+            Mem::Base(REG_TMP2, CacheState::offset_of_vtable(0)),
+        );
+        self.cmp_reg(MachineMode::Ptr, *scratch, REG_TMP1);
+        self.jump_if(CondCode::NotEqual, lbl_miss);
+
+        self.load_mem(
+            MachineMode::Ptr,
+            scratch.reg().into(),
+            Mem::Base(REG_TMP2, CacheState::offset_of_target(0)),
+        );
+        self.jump(lbl_call);
+
+        self.bind_label(lbl_miss);
+        let disp = self.add_addr(stub.to_ptr());
+        let pos = self.pos() as i32;
+        self.load_constpool(*scratch, disp + pos);
+
+        // call *scratch
+        self.bind_label(lbl_call);
+        self.emit_u32(asm::blr(*scratch));
+        self.emit_bailout_info(BailoutInfo::InlineCache(cache));
+    }
+
+    // body of the inline cache stub: jumps to the target of the remaining entries
+    // or the vtable entry for megamorphic call sites, the compiler thunk handles all
+    // other receivers. Expects the registers set up by `inline_cache_call`.
+    pub fn inline_cache_stub(&mut self, compiler_thunk: Address) {
+        let lbl_miss = self.create_label();
+        let scratch = self.get_scratch();
+
+        for idx in 1..POLYMORPHIC_ENTRIES {
+            let lbl_next = self.create_label();
+
+            self.load_mem(
+                MachineMode::Ptr,
+                scratch.reg().into(),
+                Mem::Base(REG_TMP2, CacheState::offset_of_vtable(idx)),
+            );
+            self.cmp_reg(MachineMode::Ptr, *scratch, REG_TMP1);
+            self.jump_if(CondCode::NotEqual, lbl_next);
+
+            self.load_mem(
+                MachineMode::Ptr,
+                scratch.reg().into(),
+                Mem::Base(REG_TMP2, CacheState::offset_of_target(idx)),
+            );
+            self.jump_reg(*scratch);
+            self.bind_label(lbl_next);
+        }
+
+        self.load_mem(
+            MachineMode::Ptr,
+            scratch.reg().into(),
+            Mem::Base(REG_TMP2, CacheState::offset_of_megamorphic_offset()),
+        );
+        self.cmp_zero(MachineMode::Ptr, *scratch);
+        self.jump_if(CondCode::Equal, lbl_miss);
+
+        // scratch = [vtable + offset of method]
+        self.int_add(MachineMode::Ptr, *scratch, *scratch, REG_TMP1);
+        self.load_mem(MachineMode::Ptr, scratch.reg().into(), Mem::Base(*scratch, 0));
+        self.jump_reg(*scratch);
+
+        // the return address still points to the call site
+        self.bind_label(lbl_miss);
+        let disp = self.add_addr(compiler_thunk.to_ptr());
+        let pos = self.pos() as i32;
+        self.load_constpool(*scratch, disp + pos);
+        self.jump_reg(*scratch);
+    }
+
     pub fn load_array_elem(&mut self, mode: MachineMode, dest: ExprStore, array: Reg, index: Reg) {
         self.load_mem(
             mode,
@@ -283,17 +400,37 @@ impl MacroAssembler {
         self.emit_u32(asm::br(reg));
     }
 
-    pub fn int_div(&mut self, mode: MachineMode, dest: Reg, lhs: Reg, rhs: Reg) {
+    pub fn int_div(
+        &mut self,
+        mode: MachineMode,
+        dest: Reg,
+        lhs: Reg,
+        rhs: Reg,
+        pos: Position,
+        gcpoint: GcPoint,
+    ) {
         let x64 = match mode {
             MachineMode::Int32 => 0,
             MachineMode::Int64 => 1,
             _ => panic!("unimplemented mode {:?}", mode),
         };
 
+        self.check_div_by_zero(mode, rhs, pos, gcpoint);
+
         self.emit_u32(asm::sdiv(x64, dest, lhs, rhs));
     }
 
-    pub fn int_mod(&mut self, mode: MachineMode, dest: Reg, lhs: Reg, rhs: Reg) {
+    pub fn int_mod(
+        &mut self,
+        mode: MachineMode,
+        dest: Reg,
+        lhs: Reg,
+        rhs: Reg,
+        pos: Position,
+        gcpoint: GcPoint,
+    ) {
+        self.check_div_by_zero(mode, rhs, pos, gcpoint);
+
         let scratch = self.get_scratch();
         let x64 = match mode {
             MachineMode::Int32 => 0,
@@ -621,7 +758,13 @@ impl MacroAssembler {
         }
     }
 
-    pub fn check_index_out_of_bounds(&mut self, pos: Position, array: Reg, index: Reg) {
+    pub fn check_index_out_of_bounds(
+        &mut self,
+        pos: Position,
+        array: Reg,
+        index: Reg,
+        gcpoint: GcPoint,
+    ) {
         let scratch = self.get_scratch();
         self.load_mem(
             MachineMode::Int32,
@@ -632,7 +775,21 @@ impl MacroAssembler {
 
         let lbl = self.create_label();
         self.jump_if(CondCode::UnsignedGreaterEq, lbl);
-        self.emit_bailout(lbl, Trap::INDEX_OUT_OF_BOUNDS, pos);
+        self.emit_bailout(lbl, Trap::INDEX_OUT_OF_BOUNDS, pos, gcpoint);
+    }
+
+    fn check_div_by_zero(
+        &mut self,
+        mode: MachineMode,
+        divisor: Reg,
+        pos: Position,
+        gcpoint: GcPoint,
+    ) {
+        self.cmp_zero(mode, divisor);
+
+        let lbl = self.create_label();
+        self.jump_if(CondCode::Equal, lbl);
+        self.emit_bailout(lbl, Trap::DIV0, pos, gcpoint);
     }
 
     pub fn load_nil(&mut self, dest: Reg) {
@@ -646,8 +803,9 @@ impl MacroAssembler {
         base: Reg,
         offset: i32,
         line: i32,
+        gcpoint: GcPoint,
     ) {
-        self.load_base(mode, dest, base, offset, Some(line));
+        self.load_base(mode, dest, base, offset, Some((line, gcpoint)));
     }
 
     pub fn load_mem(&mut self, mode: MachineMode, dest: ExprStore, mem: Mem) {
@@ -719,7 +877,7 @@ impl MacroAssembler {
         dest: ExprStore,
         base: Reg,
         disp: i32,
-        check_nil: Option<i32>,
+        check_nil: Option<(i32, GcPoint)>,
     ) {
         let scratch = self.get_scratch();
         let reg = if disp == 0 {
@@ -739,8 +897,8 @@ impl MacroAssembler {
             MachineMode::Float64 => asm::ldrd_ind(dest.freg(), base, reg, LdStExtend::LSL, 0),
         };
 
-        if let Some(line) = check_nil {
-            self.emit_nil_check();
+        if let Some((line, gcpoint)) = check_nil {
+            self.emit_nil_check(gcpoint);
             self.emit_lineno_if_missing(line);
         }
 
@@ -756,8 +914,9 @@ impl MacroAssembler {
         line: i32,
         write_barrier: bool,
         card_table_offset: usize,
+        gcpoint: GcPoint,
     ) {
-        self.store_base(mode, base, disp, src, Some(line));
+        self.store_base(mode, base, disp, src, Some((line, gcpoint)));
 
         if write_barrier {
             self.emit_barrier(base, card_table_offset);
@@ -778,7 +937,7 @@ impl MacroAssembler {
         base: Reg,
         disp: i32,
         src: ExprStore,
-        check_nil: Option<i32>,
+        check_nil: Option<(i32, GcPoint)>,
     ) {
         let scratch = self.get_scratch();
         let reg = if disp == 0 {
@@ -798,8 +957,8 @@ impl MacroAssembler {
             MachineMode::Float64 => asm::strd_ind(src.freg(), base, reg, LdStExtend::LSL, 0),
         };
 
-        if let Some(line) = check_nil {
-            self.emit_nil_check();
+        if let Some((line, gcpoint)) = check_nil {
+            self.emit_nil_check(gcpoint);
             self.emit_lineno_if_missing(line);
         }
 
@@ -1020,11 +1179,12 @@ impl MacroAssembler {
         self.emit_u32(uxtb(dest, dest));
     }
 
-    pub fn trap(&mut self, trap: Trap, pos: Position) {
+    pub fn trap(&mut self, trap: Trap, pos: Position, gcpoint: GcPoint) {
         let vm = get_vm();
         self.load_int_const(MachineMode::Int32, REG_PARAMS[0], trap.int() as i64);
         self.raw_call(vm.trap_thunk().to_ptr());
         self.emit_lineno(pos.line as i32);
+        self.emit_gcpoint(gcpoint);
     }
 
     pub fn throw(&mut self, receiver: Reg, pos: Position) {
@@ -1064,6 +1224,21 @@ impl MacroAssembler {
 
         self.emit_u32(asm::ldrx_imm(*scratch, *scratch, 0));
     }
+
+    pub fn check_stack_limit(&mut self, lbl_overflow: Label) {
+        let limit = self.get_scratch();
+        self.load_mem(
+            MachineMode::Ptr,
+            ExprStore::Reg(*limit),
+            Mem::Base(REG_THREAD, ThreadLocalData::stack_limit_offset()),
+        );
+
+        // cmp can't encode sp as first operand
+        let sp = self.get_scratch();
+        self.copy_sp(*sp);
+        self.cmp_reg(MachineMode::Ptr, *sp, *limit);
+        self.jump_if(CondCode::UnsignedLess, lbl_overflow);
+    }
 }
 
 #[derive(Debug)]
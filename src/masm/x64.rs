@@ -2,6 +2,7 @@ use crate::baseline::codegen::CondCode;
 use crate::baseline::codegen::ExprStore;
 use crate::baseline::fct::BailoutInfo;
 use crate::baseline::fct::GcPoint;
+use crate::baseline::ic::{CacheState, InlineCache, POLYMORPHIC_ENTRIES};
 use crate::class::TypeParams;
 use crate::cpu::*;
 use crate::gc::swiper::CARD_SIZE_BITS;
@@ -10,6 +11,7 @@ use crate::masm::{Label, MacroAssembler};
 use crate::mem::{fits_i32, ptr_width};
 use crate::object::{offset_of_array_data, offset_of_array_length, Header};
 use crate::os::signal::Trap;
+use crate::threads::ThreadLocalData;
 use crate::ty::MachineMode;
 use crate::vm::get_vm;
 use crate::vm::FctId;
@@ -75,11 +77,11 @@ impl MacroAssembler {
         self.call_reg(REG_RESULT);
     }
 
-    pub fn indirect_call(&mut self, line: i32, index: u32) {
+    pub fn indirect_call(&mut self, line: i32, index: u32, gcpoint: GcPoint) {
         let obj = REG_PARAMS[0];
 
         self.emit_lineno(line);
-        self.emit_nil_check();
+        self.emit_nil_check(gcpoint);
 
         // REG_RESULT = [obj] (load vtable)
         self.load_mem(MachineMode::Ptr, REG_RESULT.into(), Mem::Base(obj, 0));
@@ -99,6 +101,112 @@ impl MacroAssembler {
         self.emit_bailout_info(BailoutInfo::VirtCompile(index, TypeParams::empty()));
     }
 
+    // calls the target of the first entry in the inline cache at `cache` if the receiver's
+    // vtable matches, otherwise `stub` with the vtable in REG_TMP1 and the state in REG_TMP2
+    pub fn inline_cache_call(
+        &mut self,
+        line: i32,
+        cache: Address,
+        stub: Address,
+        gcpoint: GcPoint,
+    ) {
+        let obj = REG_PARAMS[0];
+        let lbl_miss = self.create_label();
+        let lbl_call = self.create_label();
+
+        self.emit_lineno(line);
+        self.emit_nil_check(gcpoint);
+
+        // REG_TMP1 = [obj] (load vtable)
+        self.load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Base(obj, 0));
+
+        // REG_TMP2 = current state of the cache
+        let disp = self.add_addr(cache.to_ptr());
+        let pos = self.pos() as i32;
+        self.load_constpool(REG_TMP2, disp + pos);
+        self.load_mem(
+            MachineMode::Ptr,
+            REG_TMP2.into(),
+            Mem::Base(REG_TMP2, InlineCache::offset_of_state()),
+        );
+
+        self.load_mem(
+            MachineMode::Ptr,
+            REG_RESULT.into(),
+            Mem::Base(REG_TMP2, CacheState::offset_of_vtable(0)),
+        );
+        self.cmp_reg(MachineMode::Ptr, REG_RESULT, REG_TMP1);
+        self.jump_if(CondCode::NotEqual, lbl_miss);
+
+        self.load_mem(
+            MachineMode::Ptr,
+            REG_RESULT.into(),
+            Mem::Base(REG_TMP2, CacheState::offset_of_target(0)),
+        );
+        self.jump(lbl_call);
+
+        self.bind_label(lbl_miss);
+        let disp = self.add_addr(stub.to_ptr());
+        let pos = self.pos() as i32;
+        self.load_constpool(REG_RESULT, disp + pos);
+
+        // call *REG_RESULT
+        self.bind_label(lbl_call);
+        self.call_reg(REG_RESULT);
+        self.emit_bailout_info(BailoutInfo::InlineCache(cache));
+    }
+
+    // body of the inline cache stub: jumps to the target of the remaining entries
+    // or the vtable entry for megamorphic call sites, the compiler thunk handles all
+    // other receivers. Expects the registers set up by `inline_cache_call`.
+    pub fn inline_cache_stub(&mut self, compiler_thunk: Address) {
+        let lbl_miss = self.create_label();
+
+        for idx in 1..POLYMORPHIC_ENTRIES {
+            let lbl_next = self.create_label();
+
+            self.load_mem(
+                MachineMode::Ptr,
+                REG_RESULT.into(),
+                Mem::Base(REG_TMP2, CacheState::offset_of_vtable(idx)),
+            );
+            self.cmp_reg(MachineMode::Ptr, REG_RESULT, REG_TMP1);
+            self.jump_if(CondCode::NotEqual, lbl_next);
+
+            self.load_mem(
+                MachineMode::Ptr,
+                REG_RESULT.into(),
+                Mem::Base(REG_TMP2, CacheState::offset_of_target(idx)),
+            );
+            self.jump_reg(REG_RESULT);
+            self.bind_label(lbl_next);
+        }
+
+        self.load_mem(
+            MachineMode::Ptr,
+            REG_RESULT.into(),
+            Mem::Base(REG_TMP2, CacheState::offset_of_megamorphic_offset()),
+        );
+        self.cmp_reg_imm(MachineMode::Ptr, REG_RESULT, 0);
+        self.jump_if(CondCode::Equal, lbl_miss);
+
+        // REG_RESULT = [vtable + offset of method]
+        self.int_add(MachineMode::Ptr, REG_RESULT, REG_RESULT, REG_TMP1);
+        self.load_mem(
+            MachineMode::Ptr,
+            REG_RESULT.into(),
+            Mem::Base(REG_RESULT, 0),
+        );
+        self.jump_reg(REG_RESULT);
+
+        // the return address still points to the call site
+        self.bind_label(lbl_miss);
+        let disp = self.add_addr(compiler_thunk.to_ptr());
+        let pos = self.pos() as i32;
+        self.load_constpool(REG_RESULT, disp + pos);
+        self.jump_reg(REG_RESULT);
+    }
+
     pub fn load_array_elem(&mut self, mode: MachineMode, dest: ExprStore, array: Reg, index: Reg) {
         self.load_mem(
             mode,
@@ -276,21 +384,51 @@ impl MacroAssembler {
         asm::emit_jmp_reg(self, reg);
     }
 
-    pub fn int_div(&mut self, mode: MachineMode, dest: Reg, lhs: Reg, rhs: Reg) {
-        self.div_common(mode, dest, lhs, rhs, RAX);
+    pub fn int_div(
+        &mut self,
+        mode: MachineMode,
+        dest: Reg,
+        lhs: Reg,
+        rhs: Reg,
+        pos: Position,
+        gcpoint: GcPoint,
+    ) {
+        self.div_common(mode, dest, lhs, rhs, RAX, pos, gcpoint);
     }
 
-    pub fn int_mod(&mut self, mode: MachineMode, dest: Reg, lhs: Reg, rhs: Reg) {
-        self.div_common(mode, dest, lhs, rhs, RDX);
+    pub fn int_mod(
+        &mut self,
+        mode: MachineMode,
+        dest: Reg,
+        lhs: Reg,
+        rhs: Reg,
+        pos: Position,
+        gcpoint: GcPoint,
+    ) {
+        self.div_common(mode, dest, lhs, rhs, RDX, pos, gcpoint);
     }
 
-    fn div_common(&mut self, mode: MachineMode, dest: Reg, lhs: Reg, rhs: Reg, result: Reg) {
+    fn div_common(
+        &mut self,
+        mode: MachineMode,
+        dest: Reg,
+        lhs: Reg,
+        rhs: Reg,
+        result: Reg,
+        pos: Position,
+        gcpoint: GcPoint,
+    ) {
         let x64 = match mode {
             MachineMode::Int32 => 0,
             MachineMode::Int64 => 1,
             _ => unimplemented!(),
         };
 
+        self.cmp_zero(mode, rhs);
+        let lbl_zero = self.create_label();
+        self.jump_if(CondCode::Equal, lbl_zero);
+        self.emit_bailout(lbl_zero, Trap::DIV0, pos, gcpoint);
+
         if lhs != RAX {
             assert!(rhs != RAX);
             asm::emit_mov_reg_reg(self, x64, lhs, RAX);
@@ -592,7 +730,13 @@ impl MacroAssembler {
         }
     }
 
-    pub fn check_index_out_of_bounds(&mut self, pos: Position, array: Reg, index: Reg) {
+    pub fn check_index_out_of_bounds(
+        &mut self,
+        pos: Position,
+        array: Reg,
+        index: Reg,
+        gcpoint: GcPoint,
+    ) {
         let scratch = self.get_scratch();
         self.load_mem(
             MachineMode::Int32,
@@ -603,7 +747,7 @@ impl MacroAssembler {
 
         let lbl = self.create_label();
         self.jump_if(CondCode::UnsignedGreaterEq, lbl);
-        self.emit_bailout(lbl, Trap::INDEX_OUT_OF_BOUNDS, pos);
+        self.emit_bailout(lbl, Trap::INDEX_OUT_OF_BOUNDS, pos, gcpoint);
     }
 
     pub fn load_nil(&mut self, dest: Reg) {
@@ -617,8 +761,9 @@ impl MacroAssembler {
         base: Reg,
         offset: i32,
         line: i32,
+        gcpoint: GcPoint,
     ) {
-        self.emit_nil_check();
+        self.emit_nil_check(gcpoint);
         self.emit_lineno_if_missing(line);
         self.load_mem(mode, dest, Mem::Base(base, offset));
     }
@@ -672,8 +817,9 @@ impl MacroAssembler {
         line: i32,
         write_barrier: bool,
         card_table_offset: usize,
+        gcpoint: GcPoint,
     ) {
-        self.emit_nil_check();
+        self.emit_nil_check(gcpoint);
         self.emit_lineno_if_missing(line);
         self.store_mem(mode, Mem::Base(base, offset), src);
 
@@ -977,11 +1123,12 @@ impl MacroAssembler {
         }
     }
 
-    pub fn trap(&mut self, trap: Trap, pos: Position) {
+    pub fn trap(&mut self, trap: Trap, pos: Position, gcpoint: GcPoint) {
         let vm = get_vm();
         self.load_int_const(MachineMode::Int32, REG_PARAMS[0], trap.int() as i64);
         self.raw_call(vm.trap_thunk().to_ptr());
         self.emit_lineno(pos.line as i32);
+        self.emit_gcpoint(gcpoint);
     }
 
     pub fn throw(&mut self, receiver: Reg, pos: Position) {
@@ -1037,6 +1184,17 @@ impl MacroAssembler {
 
         asm::testl_reg_mem(self, RAX, Mem::Base(*scratch, 0));
     }
+
+    pub fn check_stack_limit(&mut self, lbl_overflow: Label) {
+        // compare against memory: parameter registers are still live
+        // and overlap with the scratch registers
+        self.cmp_mem(
+            MachineMode::Ptr,
+            Mem::Base(REG_THREAD, ThreadLocalData::stack_limit_offset()),
+            REG_SP,
+        );
+        self.jump_if(CondCode::UnsignedGreater, lbl_overflow);
+    }
 }
 
 #[derive(Debug)]
@@ -1 +1,81 @@
+use dora_parser::ast::NodeId;
+
+use crate::boots;
+use crate::class::TypeParams;
+use crate::opt::fct::JitOptFct;
+use crate::vm::{Fct, FctSrc, VarId, VM};
+
 pub mod fct;
+
+// compiles functions annotated with @optimize through boots, returns `None` when
+// the function should be compiled with the baseline compiler instead
+pub fn generate<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &mut FctSrc,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+) -> Option<JitOptFct> {
+    if !fct.ast.has_optimize {
+        return None;
+    }
+
+    compile(vm, fct, src, cls_type_params, fct_type_params)
+}
+
+// compiles the function through boots, returns `None` if boots cannot handle
+// the function
+pub fn compile<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &mut FctSrc,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+) -> Option<JitOptFct> {
+    match boots::compile(vm, fct, src, cls_type_params, fct_type_params) {
+        Ok(jit_fct) => {
+            if vm.args.flag_opt_verbose {
+                println!("OPT: optimized {}", fct.full_name(vm));
+            }
+
+            Some(jit_fct)
+        }
+
+        Err(msg) => {
+            if vm.args.flag_opt_verbose {
+                println!("OPT: rejected {}: {}", fct.full_name(vm), msg);
+            }
+
+            None
+        }
+    }
+}
+
+// compiles the function through boots with an entry at the loop `loop_id`, returns
+// `None` if boots cannot handle the function or the loop
+pub fn compile_osr<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &mut FctSrc,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+    loop_id: NodeId,
+) -> Option<(JitOptFct, Vec<VarId>)> {
+    match boots::compile_osr(vm, fct, src, cls_type_params, fct_type_params, loop_id) {
+        Ok(result) => {
+            if vm.args.flag_opt_verbose {
+                println!("OPT: optimized {} for OSR", fct.full_name(vm));
+            }
+
+            Some(result)
+        }
+
+        Err(msg) => {
+            if vm.args.flag_opt_verbose {
+                println!("OPT: rejected {} for OSR: {}", fct.full_name(vm), msg);
+            }
+
+            None
+        }
+    }
+}
@@ -1,12 +1,98 @@
+use std::collections::HashSet;
+
+use crate::baseline::fct::{
+    install_code, relocate_exception_handlers, Bailouts, Comment, Comments, ExHandler, GcPoint,
+    GcPoints, InlineTable, LineNumberTable,
+};
+use crate::dseg::DSeg;
 use crate::gc::Address;
-use crate::vm::FctId;
+use crate::vm::{FctId, VM};
 
 pub struct JitOptFct {
+    code_start: Address,
+    code_end: Address,
+
     pub fct_id: FctId,
+    pub throws: bool,
+
+    // pointer to beginning of function
     pub fct_start: Address,
+
+    // machine code length in bytes
+    fct_len: usize,
+
+    pub framesize: i32,
+    pub bailouts: Bailouts,
+    pub nil_checks: HashSet<i32>,
+    gcpoints: GcPoints,
+    comments: Comments,
+    linenos: LineNumberTable,
+    inlined_frames: InlineTable,
+    pub exception_handlers: Vec<ExHandler>,
 }
 
 impl JitOptFct {
+    pub fn from_buffer(
+        vm: &VM,
+        dseg: &DSeg,
+        buffer: &[u8],
+        bailouts: Bailouts,
+        nil_checks: HashSet<i32>,
+        gcpoints: GcPoints,
+        framesize: i32,
+        comments: Comments,
+        linenos: LineNumberTable,
+        inlined_frames: InlineTable,
+        fct_id: FctId,
+        throws: bool,
+        mut exception_handlers: Vec<ExHandler>,
+    ) -> JitOptFct {
+        let (code_start, code_end) = install_code(vm, dseg, buffer);
+        let fct_start = code_start.offset(dseg.size() as usize);
+        relocate_exception_handlers(&mut exception_handlers, fct_start);
+
+        JitOptFct {
+            code_start: code_start,
+            code_end: code_end,
+            fct_id: fct_id,
+            throws: throws,
+            fct_start: fct_start,
+            fct_len: buffer.len(),
+            framesize: framesize,
+            bailouts: bailouts,
+            nil_checks: nil_checks,
+            gcpoints: gcpoints,
+            comments: comments,
+            linenos: linenos,
+            inlined_frames: inlined_frames,
+            exception_handlers: exception_handlers,
+        }
+    }
+
+    pub fn lineno_for_offset(&self, offset: i32) -> i32 {
+        self.linenos.get(offset)
+    }
+
+    pub fn inlined_frames_for_offset(&self, offset: i32) -> &[(FctId, i32)] {
+        self.inlined_frames.get(offset)
+    }
+
+    pub fn gcpoint_for_offset(&self, offset: i32) -> Option<&GcPoint> {
+        self.gcpoints.get(offset)
+    }
+
+    pub fn nil_check_for_offset(&self, offset: i32) -> bool {
+        self.nil_checks.contains(&offset)
+    }
+
+    pub fn ptr_start(&self) -> Address {
+        self.code_start
+    }
+
+    pub fn ptr_end(&self) -> Address {
+        self.code_end
+    }
+
     pub fn fct_id(&self) -> FctId {
         self.fct_id
     }
@@ -14,4 +100,16 @@ impl JitOptFct {
     pub fn fct_ptr(&self) -> Address {
         self.fct_start
     }
+
+    pub fn fct_end(&self) -> Address {
+        self.fct_start.offset(self.fct_len)
+    }
+
+    pub fn fct_len(&self) -> usize {
+        self.fct_len
+    }
+
+    pub fn get_comment(&self, pos: i32) -> Option<&[Comment]> {
+        self.comments.get(pos)
+    }
 }
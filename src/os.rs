@@ -1,6 +1,8 @@
 pub use self::mem::*;
 pub use self::signal::*;
+pub use self::stack::*;
 
 pub mod mem;
 pub mod perf;
 pub mod signal;
+pub mod stack;
@@ -1,9 +1,9 @@
-use crate::baseline::fct::JitBaselineFct;
+use crate::baseline::fct::JitFct;
 use crate::vm::VM;
 use dora_parser::interner::Name;
 
 #[cfg(target_os = "linux")]
-pub fn register_with_perf(jit_fct: &JitBaselineFct, vm: &VM, name: Name) {
+pub fn register_with_perf(jit_fct: &JitFct, vm: &VM, name: Name) {
     use std::fs::OpenOptions;
     use std::io::prelude::*;
 
@@ -27,6 +27,6 @@ pub fn register_with_perf(jit_fct: &JitBaselineFct, vm: &VM, name: Name) {
 }
 
 #[cfg(not(target_os = "linux"))]
-pub fn register_with_perf(_: &JitBaselineFct, _: &VM, _: Name) {
+pub fn register_with_perf(_: &JitFct, _: &VM, _: Name) {
     // nothing to do
 }
@@ -3,7 +3,7 @@ use std;
 use std::mem::MaybeUninit;
 
 use crate::baseline::map::CodeDescriptor;
-use crate::exception::stacktrace_from_es;
+use crate::cpu::call_from_execstate;
 use crate::os;
 use crate::os_cpu::*;
 use crate::safepoint;
@@ -102,18 +102,17 @@ fn fault_handler(exception: *mut EXCEPTION_POINTERS) -> bool {
 
 #[cfg(target_family = "unix")]
 fn handler(signo: libc::c_int, info: *const siginfo_t, ucontext: *const u8) {
-    let es = read_execstate(ucontext);
+    let mut es = read_execstate(ucontext);
     let vm = get_vm();
 
     let addr = unsafe { (*info).si_addr } as *const u8;
 
     if detect_nil_check(vm, es.pc, signo, addr) {
-        println!("nil check failed");
-        let stacktrace = stacktrace_from_es(vm, &es);
-        stacktrace.dump(vm);
-        unsafe {
-            libc::_exit(104);
-        }
+        // continue in trap thunk as if the faulting instruction called it,
+        // the thunk throws a NilPointerException
+        let trap_thunk = vm.trap_thunk().to_usize();
+        call_from_execstate(&mut es, trap_thunk, Trap::NIL.int() as usize);
+        write_execstate(&es, ucontext as *mut u8);
     } else if detect_polling_page_check(vm, signo, addr) {
         // polling page read failed => enter safepoint
         safepoint::block(&es);
@@ -192,8 +191,6 @@ fn detect_nil_check(vm: &VM, pc: usize, signo: libc::c_int, addr: *const u8) ->
     if let Some(CodeDescriptor::DoraFct(fid)) = code_map.get(pc.into()) {
         let jit_fct = vm.jit_fcts.idx(fid);
         let offset = pc - jit_fct.fct_ptr().to_usize();
-
-        let jit_fct = jit_fct.to_base().expect("baseline expected");
         jit_fct.nil_check_for_offset(offset as i32)
     } else {
         false
@@ -214,6 +211,7 @@ pub enum Trap {
     CAST,
     UNEXPECTED,
     OOM,
+    STACK_OVERFLOW,
 }
 
 impl Trap {
@@ -227,6 +225,21 @@ impl Trap {
             Trap::CAST => 6,
             Trap::UNEXPECTED => 7,
             Trap::OOM => 8,
+            Trap::STACK_OVERFLOW => 9,
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            Trap::DIV0 => "division by 0",
+            Trap::ASSERT => "assert failed",
+            Trap::INDEX_OUT_OF_BOUNDS => "array index out of bounds",
+            Trap::NIL => "nil check failed",
+            Trap::CAST => "cast failed",
+            Trap::THROW => "uncaught exception",
+            Trap::UNEXPECTED => "unexpected exception",
+            Trap::OOM => "out of memory",
+            Trap::STACK_OVERFLOW => "stack overflow",
         }
     }
 
@@ -240,6 +253,7 @@ impl Trap {
             6 => Some(Trap::CAST),
             7 => Some(Trap::UNEXPECTED),
             8 => Some(Trap::OOM),
+            9 => Some(Trap::STACK_OVERFLOW),
             _ => None,
         }
     }
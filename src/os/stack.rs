@@ -0,0 +1,48 @@
+use libc;
+
+use std::mem;
+
+// space kept free below the stack limit: enough to run the
+// trap handler and allocate the StackOverflowError
+const STACK_RESERVE: usize = 256 * 1024;
+
+// lowest stack address that dora code is allowed to use
+// on the current thread
+pub fn stack_limit() -> usize {
+    let (start, size) = stack_bounds();
+    start + STACK_RESERVE.min(size / 2)
+}
+
+// returns start address and size of the current thread's stack
+#[cfg(target_os = "linux")]
+fn stack_bounds() -> (usize, usize) {
+    unsafe {
+        let mut attr: libc::pthread_attr_t = mem::zeroed();
+
+        if libc::pthread_getattr_np(libc::pthread_self(), &mut attr) != 0 {
+            panic!("could not get thread attributes.");
+        }
+
+        let mut start: *mut libc::c_void = std::ptr::null_mut();
+        let mut size: libc::size_t = 0;
+        let res = libc::pthread_attr_getstack(&attr, &mut start, &mut size);
+        libc::pthread_attr_destroy(&mut attr);
+
+        if res != 0 {
+            panic!("could not determine stack bounds.");
+        }
+
+        (start as usize, size as usize)
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn stack_bounds() -> (usize, usize) {
+    unsafe {
+        let thread = libc::pthread_self();
+        let end = libc::pthread_get_stackaddr_np(thread) as usize;
+        let size = libc::pthread_get_stacksize_np(thread) as usize;
+
+        (end - size, size)
+    }
+}
@@ -1,6 +1,9 @@
+use crate::cha;
 use crate::class::TypeParams;
 use crate::mem;
-use crate::sym::Sym::{SymClass, SymClassTypeParam, SymFctTypeParam, SymStruct, SymTrait};
+use crate::sym::Sym::{
+    SymClass, SymClassTypeParam, SymEnum, SymFctTypeParam, SymStruct, SymTrait,
+};
 use crate::ty::BuiltinType;
 use crate::typeck;
 use crate::vm::{NodeMap, VM};
@@ -11,6 +14,7 @@ use dora_parser::error::msg::Msg;
 mod abstractck;
 mod clsdefck;
 mod constdefck;
+mod enumck;
 mod fctdefck;
 mod flowck;
 mod globaldef;
@@ -40,6 +44,7 @@ pub fn check<'ast>(vm: &mut VM<'ast>) {
     let mut map_impl_defs = NodeMap::new(); // get ImplId from ast node
     let mut map_global_defs = NodeMap::new(); // get GlobalId from ast node
     let mut map_const_defs = NodeMap::new(); // get ConstId from ast node
+    let mut map_enum_defs = NodeMap::new(); // get EnumId from ast node
 
     // add user defined fcts and classes to vm
     // this check does not look into fct or class bodies
@@ -51,15 +56,17 @@ pub fn check<'ast>(vm: &mut VM<'ast>) {
         &mut map_impl_defs,
         &mut map_global_defs,
         &mut map_const_defs,
+        &mut map_enum_defs,
     );
     return_on_error!(vm);
 
     // define internal classes
     prelude::internal_classes(vm);
 
-    // checks class/struct/trait definitions/bodies
+    // checks class/struct/trait/enum definitions/bodies
     clsdefck::check(vm, &vm.ast, &map_cls_defs);
     structdefck::check(vm, &vm.ast, &map_struct_defs);
+    enumck::check(vm, &vm.ast, &map_enum_defs);
     traitdefck::check(vm, &vm.ast, &map_trait_defs);
     impldefck::check(vm, &vm.ast, &map_impl_defs);
     globaldefck::check(vm, &vm.ast, &map_global_defs);
@@ -102,6 +109,12 @@ pub fn check<'ast>(vm: &mut VM<'ast>) {
 
     abstractck::check(vm);
 
+    // register classes with the class hierarchy analysis
+    for cls in vm.classes.iter() {
+        let cls_id = cls.read().id;
+        cha::add_class(vm, cls_id);
+    }
+
     // check for internal functions or classes
     internalck(vm);
     return_on_error!(vm);
@@ -285,6 +298,15 @@ pub fn read_type<'ast>(vm: &VM<'ast>, t: &'ast Type) -> Option<BuiltinType> {
                         return Some(BuiltinType::Struct(struct_id, list_id));
                     }
 
+                    SymEnum(enum_id) => {
+                        if basic.params.len() > 0 {
+                            let msg = Msg::NoTypeParamsExpected;
+                            vm.diag.lock().report_without_path(basic.pos, msg);
+                        }
+
+                        return Some(BuiltinType::Enum(enum_id));
+                    }
+
                     SymClassTypeParam(cls_id, type_param_id) => {
                         if basic.params.len() > 0 {
                             let msg = Msg::NoTypeParamsExpected;
@@ -0,0 +1,74 @@
+use crate::vm::{EnumId, NodeMap, VM};
+
+use dora_parser::ast::visit::Visitor;
+use dora_parser::ast::{self, Ast};
+
+use dora_parser::error::msg::Msg;
+use dora_parser::lexer::position::Position;
+
+pub fn check<'ast>(vm: &mut VM<'ast>, ast: &'ast Ast, map_enum_defs: &NodeMap<EnumId>) {
+    let mut enumck = EnumCheck {
+        vm: vm,
+        ast: ast,
+        map_enum_defs: map_enum_defs,
+    };
+
+    enumck.check();
+}
+
+struct EnumCheck<'x, 'ast: 'x> {
+    vm: &'x mut VM<'ast>,
+    ast: &'ast ast::Ast,
+    map_enum_defs: &'x NodeMap<EnumId>,
+}
+
+impl<'x, 'ast> EnumCheck<'x, 'ast> {
+    fn check(&mut self) {
+        self.visit_ast(self.ast);
+    }
+}
+
+impl<'x, 'ast> Visitor<'ast> for EnumCheck<'x, 'ast> {
+    fn visit_enum(&mut self, e: &'ast ast::Enum) {
+        let id = *self.map_enum_defs.get(e.id).unwrap();
+        let mut xenum = self.vm.enums[id].write();
+
+        for &value in &e.values {
+            if xenum.name_to_value.contains_key(&value) {
+                let name = self.vm.interner.str(value).to_string();
+                report(self.vm, e.pos, Msg::ShadowEnumValue(name));
+                continue;
+            }
+
+            let idx = xenum.values.len() as u32;
+            xenum.values.push(value);
+            xenum.name_to_value.insert(value, idx);
+        }
+
+        if e.values.is_empty() {
+            report(self.vm, e.pos, Msg::NoEnumValue);
+        }
+    }
+}
+
+fn report(vm: &VM, pos: Position, msg: Msg) {
+    vm.diag.lock().report_without_path(pos, msg);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::semck::tests::*;
+    use dora_parser::error::msg::Msg;
+
+    #[test]
+    fn enum_definitions() {
+        ok("enum Foo { A, B, C }");
+        ok("enum Foo { A } enum Bar { A }");
+        err(
+            "enum Foo { A, B, A }",
+            pos(1, 1),
+            Msg::ShadowEnumValue("A".into()),
+        );
+        err("enum Foo {}", pos(1, 1), Msg::NoEnumValue);
+    }
+}
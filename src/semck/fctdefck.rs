@@ -59,6 +59,30 @@ pub fn check<'a, 'ast>(vm: &VM<'ast>) {
                 }
             }
 
+            FctParent::Function(_) => {
+                // lambdas can use all type params of their outermost function
+                let root = vm.fcts.idx(fct.root_fct_id(vm));
+                let root = root.read();
+
+                if let FctParent::Class(owner_class) = root.parent {
+                    let cls = vm.classes.idx(owner_class);
+                    let cls = cls.read();
+
+                    for (type_param_id, param) in cls.type_params.iter().enumerate() {
+                        let sym = Sym::SymClassTypeParam(cls.id, type_param_id.into());
+                        vm.sym.lock().insert(param.name, sym);
+                    }
+                }
+
+                for (type_param_id, param) in root.type_params.iter().enumerate() {
+                    let sym = Sym::SymFctTypeParam(root.id, type_param_id.into());
+                    vm.sym.lock().insert(param.name, sym);
+                }
+
+                // type of closure is only known after all params were read
+                fct.param_types.push(BuiltinType::Unit);
+            }
+
             FctParent::None => {}
         }
 
@@ -149,6 +173,18 @@ pub fn check<'a, 'ast>(vm: &VM<'ast>) {
             fct.return_type = ty;
         }
 
+        if fct.is_lambda() {
+            let params = fct.params_without_self().to_vec();
+            let ty = vm.lambda_types.lock().insert(params, fct.return_type);
+            let ty = BuiltinType::Lambda(ty);
+
+            fct.param_types[0] = ty;
+
+            let src = fct.src();
+            let mut src = src.write();
+            src.vars[0].ty = ty;
+        }
+
         fct.initialized = true;
 
         match fct.parent {
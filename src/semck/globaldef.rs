@@ -4,7 +4,9 @@ use std::sync::Arc;
 
 use crate::class::{self, ClassId};
 use crate::gc::Address;
-use crate::sym::Sym::{self, SymClass, SymConst, SymFct, SymGlobal, SymStruct, SymTrait};
+use crate::sym::Sym::{
+    self, SymClass, SymConst, SymEnum, SymFct, SymGlobal, SymStruct, SymTrait,
+};
 use crate::ty::BuiltinType;
 use crate::vm;
 use crate::vm::*;
@@ -22,6 +24,7 @@ pub fn check<'ast>(
     map_impl_defs: &mut NodeMap<ImplId>,
     map_global_defs: &mut NodeMap<GlobalId>,
     map_const_defs: &mut NodeMap<ConstId>,
+    map_enum_defs: &mut NodeMap<EnumId>,
 ) {
     let ast = vm.ast;
     let mut gdef = GlobalDef {
@@ -32,6 +35,7 @@ pub fn check<'ast>(
         map_impl_defs: map_impl_defs,
         map_global_defs: map_global_defs,
         map_const_defs: map_const_defs,
+        map_enum_defs: map_enum_defs,
     };
 
     gdef.visit_ast(ast);
@@ -45,6 +49,7 @@ struct GlobalDef<'x, 'ast: 'x> {
     map_impl_defs: &'x mut NodeMap<ImplId>,
     map_global_defs: &'x mut NodeMap<GlobalId>,
     map_const_defs: &'x mut NodeMap<ConstId>,
+    map_enum_defs: &'x mut NodeMap<EnumId>,
 }
 
 impl<'x, 'ast> Visitor<'ast> for GlobalDef<'x, 'ast> {
@@ -136,6 +141,26 @@ impl<'x, 'ast> Visitor<'ast> for GlobalDef<'x, 'ast> {
         }
     }
 
+    fn visit_enum(&mut self, e: &'ast Enum) {
+        let id: EnumId = (self.vm.enums.len() as u32).into();
+        let xenum = EnumData {
+            id: id,
+            pos: e.pos,
+            name: e.name,
+            values: Vec::new(),
+            name_to_value: HashMap::new(),
+        };
+
+        self.vm.enums.push(RwLock::new(xenum));
+        self.map_enum_defs.insert(e.id, id);
+
+        let sym = SymEnum(id);
+
+        if let Some(sym) = self.vm.sym.lock().insert(e.name, sym) {
+            report(self.vm, e.name, e.pos, sym);
+        }
+    }
+
     fn visit_class(&mut self, c: &'ast Class) {
         let id = {
             let mut classes = self.vm.classes.lock();
@@ -264,6 +289,7 @@ fn report(vm: &VM, name: Name, pos: Position, sym: Sym) {
         SymTrait(_) => Msg::ShadowTrait(name),
         SymGlobal(_) => Msg::ShadowGlobal(name),
         SymConst(_) => Msg::ShadowConst(name),
+        SymEnum(_) => Msg::ShadowEnum(name),
         _ => unimplemented!(),
     };
 
@@ -315,6 +341,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_enum() {
+        ok("enum Foo { A, B, C }");
+        err(
+            "enum Foo { A } class Foo",
+            pos(1, 16),
+            Msg::ShadowEnum("Foo".into()),
+        );
+        err(
+            "class Foo enum Foo { A }",
+            pos(1, 11),
+            Msg::ShadowClass("Foo".into()),
+        );
+        err(
+            "enum Foo { A } fun Foo() {}",
+            pos(1, 16),
+            Msg::ShadowEnum("Foo".into()),
+        );
+    }
+
     #[test]
     fn test_const() {
         ok("const foo: Int = 0;");
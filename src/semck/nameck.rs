@@ -1,3 +1,5 @@
+use parking_lot::RwLock;
+
 use crate::vm::*;
 use dora_parser::error::msg::Msg;
 
@@ -16,7 +18,8 @@ pub fn check<'ast>(vm: &VM<'ast>) {
     for fct in vm.fcts.iter() {
         let fct = fct.read();
 
-        if !fct.is_src() {
+        // lambdas are already checked together with their outer function
+        if !fct.is_src() || fct.is_lambda() {
             continue;
         }
 
@@ -29,6 +32,9 @@ pub fn check<'ast>(vm: &VM<'ast>) {
             fct: &fct,
             src: &mut src,
             ast: ast,
+            level: 0,
+            outer: Vec::new(),
+            captures: Vec::new(),
         };
 
         nameck.check();
@@ -40,11 +46,21 @@ struct NameCheck<'a, 'ast: 'a> {
     fct: &'a Fct<'ast>,
     src: &'a mut FctSrc,
     ast: &'ast Function,
+
+    // first level in symbol table that belongs to this function
+    level: usize,
+
+    // enclosing functions of a lambda together with their first level
+    outer: Vec<(FctId, usize)>,
+
+    // variables of enclosing functions used in this function or its lambdas
+    captures: Vec<(FctId, VarId)>,
 }
 
 impl<'a, 'ast> NameCheck<'a, 'ast> {
     fn check(&mut self) {
         self.vm.sym.lock().push_level();
+        self.level = self.vm.sym.lock().levels() - 1;
 
         if self.fct.has_self() {
             // add hidden this parameter for ctors and methods
@@ -77,6 +93,9 @@ impl<'a, 'ast> NameCheck<'a, 'ast> {
                 cls.ty
             }
 
+            // closure type is set as soon as parameter types are known
+            FctParent::Function(_) => BuiltinType::Unit,
+
             _ => unreachable!(),
         };
 
@@ -223,13 +242,30 @@ impl<'a, 'ast> NameCheck<'a, 'ast> {
     }
 
     fn check_expr_ident(&mut self, ident: &'ast ExprIdentType) {
-        let sym = self.vm.sym.lock().get(ident.name);
+        let sym = self.vm.sym.lock().get_with_level(ident.name);
+
+        if let Some((SymVar(id), level)) = sym {
+            let ident_type = if level >= self.level {
+                IdentType::Var(id)
+            } else {
+                // variable belongs to the innermost enclosing function whose
+                // levels contain the variable
+                let &(owner, _) = self
+                    .outer
+                    .iter()
+                    .rev()
+                    .find(|&&(_, start)| start <= level)
+                    .unwrap();
+                self.add_capture(owner, id);
+
+                IdentType::Context(owner, id)
+            };
 
-        match sym {
-            Some(SymVar(id)) => {
-                self.src.map_idents.insert(ident.id, IdentType::Var(id));
-            }
+            self.src.map_idents.insert(ident.id, ident_type);
+            return;
+        }
 
+        match sym.map(|(sym, _)| sym) {
             Some(SymGlobal(id)) => {
                 self.src.map_idents.insert(ident.id, IdentType::Global(id));
             }
@@ -242,6 +278,10 @@ impl<'a, 'ast> NameCheck<'a, 'ast> {
                 self.src.map_idents.insert(ident.id, IdentType::Const(id));
             }
 
+            Some(SymEnum(id)) => {
+                self.src.map_idents.insert(ident.id, IdentType::Enum(id));
+            }
+
             Some(SymFct(id)) => {
                 self.src.map_idents.insert(ident.id, IdentType::Fct(id));
             }
@@ -261,6 +301,95 @@ impl<'a, 'ast> NameCheck<'a, 'ast> {
         self.visit_expr(&path.lhs);
         // do not check right hand site of path
     }
+
+    fn check_expr_self(&mut self, e: &'ast ExprSelfType) {
+        if !self.fct.is_lambda() {
+            return;
+        }
+
+        // `self` in lambda refers to `self` of the outermost method
+        let (owner, _) = self.outer[0];
+        let has_self = self.vm.fcts.idx(owner).read().has_self();
+
+        if has_self {
+            self.add_capture(owner, VarId(0));
+            self.src
+                .map_idents
+                .insert(e.id, IdentType::Context(owner, VarId(0)));
+        }
+    }
+
+    fn check_expr_lambda(&mut self, e: &'ast ExprLambdaType) {
+        let fct = Fct {
+            id: FctId(0),
+            pos: e.pos,
+            ast: &e.fct,
+            name: e.fct.name,
+            param_types: Vec::new(),
+            return_type: BuiltinType::Unit,
+            parent: FctParent::Function(self.fct.id),
+            has_override: false,
+            has_open: false,
+            has_final: false,
+            is_pub: false,
+            is_static: false,
+            is_abstract: false,
+            internal: false,
+            internal_resolved: false,
+            overrides: None,
+            throws: false,
+            is_constructor: false,
+            vtable_index: None,
+            initialized: false,
+            impl_for: None,
+
+            type_params: Vec::new(),
+            kind: FctKind::Source(RwLock::new(FctSrc::new())),
+        };
+
+        let lambda_id = self.vm.add_fct(fct);
+        self.src.map_lambdas.insert(e.id, lambda_id);
+
+        let lambda = self.vm.fcts.idx(lambda_id);
+        let lambda = lambda.read();
+        let src = lambda.src();
+        let mut src = src.write();
+
+        let mut outer = self.outer.clone();
+        outer.push((self.fct.id, self.level));
+
+        let mut nameck = NameCheck {
+            vm: self.vm,
+            fct: &lambda,
+            src: &mut src,
+            ast: lambda.ast,
+            level: 0,
+            outer: outer,
+            captures: Vec::new(),
+        };
+
+        nameck.check();
+
+        for (owner, var) in nameck.captures {
+            // the lambda needs the context of this function, either for
+            // variables of this function or to reach variables further out
+            self.src.has_context = true;
+
+            if owner == self.fct.id {
+                if !self.src.context_vars.contains(&var) {
+                    self.src.context_vars.push(var);
+                }
+            } else {
+                self.add_capture(owner, var);
+            }
+        }
+    }
+
+    fn add_capture(&mut self, owner: FctId, var: VarId) {
+        if !self.captures.contains(&(owner, var)) {
+            self.captures.push((owner, var));
+        }
+    }
 }
 
 impl<'a, 'ast> Visitor<'ast> for NameCheck<'a, 'ast> {
@@ -273,9 +402,15 @@ impl<'a, 'ast> Visitor<'ast> for NameCheck<'a, 'ast> {
             node_id: p.id,
         };
 
+        // lambda params can shadow variables of outer functions
+        let outer_var = match self.vm.sym.lock().get_with_level(p.name) {
+            Some((SymVar(_), level)) => level < self.level,
+            _ => false,
+        };
+
         // params are only allowed to replace functions,
         // types and vars cannot be replaced
-        match self.add_var(var_ctxt, |sym| sym.is_fct()) {
+        match self.add_var(var_ctxt, |sym| sym.is_fct() || outer_var) {
             Ok(var_id) => {
                 self.src.map_vars.insert(p.id, var_id);
             }
@@ -309,6 +444,8 @@ impl<'a, 'ast> Visitor<'ast> for NameCheck<'a, 'ast> {
         match e {
             &ExprIdent(ref ident) => self.check_expr_ident(ident),
             &ExprPath(ref path) => self.check_expr_path(path),
+            &ExprSelf(ref e) => self.check_expr_self(e),
+            &ExprLambda(ref e) => self.check_expr_lambda(e),
 
             // no need to handle rest of expressions
             _ => visit::walk_expr(self, e),
@@ -452,4 +589,18 @@ mod tests {
     fn for_var() {
         ok("fun f() { for i in range(0, 4) { i; } }");
     }
+
+    #[test]
+    fn lambda_captures() {
+        ok("fun f(a: Int) { || -> Int { return a; }; }");
+        ok("fun f() { var a = 1; || { a = 2; }; }");
+        ok("fun f() { let a = 1; || { || -> Int { return a; }; }; }");
+        ok("fun f(a: Int) { |a: Int| -> Int { return a; }; }");
+
+        err(
+            "fun f() { || { let a = 1; }; a; }",
+            pos(1, 30),
+            Msg::UnknownIdentifier("a".into()),
+        );
+    }
 }
@@ -41,6 +41,14 @@ pub fn internal_classes<'ast>(vm: &mut VM<'ast>) {
     vm.vips.exception_class = internal_class(vm, "Exception", None);
     vm.vips.stack_trace_element_class = internal_class(vm, "StackTraceElement", None);
 
+    vm.vips.arithmetic_exception_class = internal_class(vm, "ArithmeticException", None);
+    vm.vips.index_out_of_bounds_exception_class =
+        internal_class(vm, "IndexOutOfBoundsException", None);
+    vm.vips.nil_pointer_exception_class = internal_class(vm, "NilPointerException", None);
+    vm.vips.cast_exception_class = internal_class(vm, "CastException", None);
+    vm.vips.out_of_memory_error_class = internal_class(vm, "OutOfMemoryError", None);
+    vm.vips.stack_overflow_error_class = internal_class(vm, "StackOverflowError", None);
+
     *vm.vips.iterator_trait.lock() = Some(find_trait(vm, "Iterator"));
 
     internal_free_classes(vm);
@@ -66,6 +74,7 @@ fn internal_free_classes<'ast>(vm: &mut VM<'ast>) {
             fields: Vec::new(),
             ref_fields: Vec::new(),
             vtable: None,
+            lambda: None,
         })));
 
         class_defs.push(Arc::new(RwLock::new(ClassDef {
@@ -77,6 +86,7 @@ fn internal_free_classes<'ast>(vm: &mut VM<'ast>) {
             fields: Vec::new(),
             ref_fields: Vec::new(),
             vtable: None,
+            lambda: None,
         })));
 
         {
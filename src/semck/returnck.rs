@@ -187,6 +187,17 @@ mod tests {
         ok("fun f(x: Int) -> Int { if x == 0 { throw \"abc\"; } else { return -x; } }");
     }
 
+    #[test]
+    fn defer_returns() {
+        ok("fun g() {} fun f() -> Int { defer g(); return 1; }");
+        ok("fun g() {} fun f() -> Int { defer g(); defer g(); throw \"abc\"; }");
+        err(
+            "fun g() {} fun f() -> Int { defer g(); }",
+            pos(1, 29),
+            Msg::NoReturnValue,
+        );
+    }
+
     #[test]
     fn do_returns() {
         ok("fun f() -> Int { do { return 1; } catch x: String { return 2; } }");
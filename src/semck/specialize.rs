@@ -7,7 +7,7 @@ use crate::class::{self, ClassDef, ClassDefId, ClassId, ClassSize, FieldDef, Typ
 use crate::mem;
 use crate::object::Header;
 use crate::ty::BuiltinType;
-use crate::vm::{StructData, StructDef, StructDefId, StructFieldDef, StructId, VM};
+use crate::vm::{FctId, FctSrc, StructData, StructDef, StructDefId, StructFieldDef, StructId, VM};
 use crate::vtable::{VTableBox, DISPLAY_SIZE};
 
 pub fn specialize_type(
@@ -48,7 +48,20 @@ pub fn specialize_type(
             BuiltinType::Class(cls_id, list_id)
         }
 
-        BuiltinType::Lambda(_) => unimplemented!(),
+        BuiltinType::Lambda(lambda_id) => {
+            let lambda = vm.lambda_types.lock().get(lambda_id);
+
+            let params: Vec<_> = lambda
+                .params
+                .iter()
+                .map(|&t| specialize_type(vm, t, cls_type_params, fct_type_params))
+                .collect();
+            let ret = specialize_type(vm, lambda.ret, cls_type_params, fct_type_params);
+
+            let lambda_id = vm.lambda_types.lock().insert(params, ret);
+
+            BuiltinType::Lambda(lambda_id)
+        }
 
         _ => ty,
     }
@@ -193,6 +206,7 @@ fn create_specialized_class(vm: &VM, cls: &class::Class, type_params: &TypeParam
             fields: Vec::new(),
             ref_fields: Vec::new(),
             vtable: None,
+            lambda: None,
         })));
 
         id
@@ -286,6 +300,113 @@ fn create_specialized_class(vm: &VM, cls: &class::Class, type_params: &TypeParam
     id
 }
 
+// creates class for the context object of a function: slot 0 points to the
+// context of the outer function, all other slots store captured variables.
+pub fn specialize_context(
+    vm: &VM,
+    src: &FctSrc,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+) -> ClassDefId {
+    let mut fields = Vec::with_capacity(src.context_vars.len() + 1);
+    let mut ref_fields = Vec::new();
+
+    let outer = FieldDef {
+        offset: context_offset(0),
+        ty: BuiltinType::Ptr,
+    };
+
+    ref_fields.push(outer.offset);
+    fields.push(outer);
+
+    for (idx, &var) in src.context_vars.iter().enumerate() {
+        let ty = specialize_type(vm, src.vars[var].ty, cls_type_params, fct_type_params);
+        debug_assert!(!ty.contains_type_param(vm));
+
+        let offset = context_offset(idx + 1);
+
+        if ty.reference_type() {
+            ref_fields.push(offset);
+        }
+
+        fields.push(FieldDef {
+            offset: offset,
+            ty: ty,
+        });
+    }
+
+    let size = context_offset(fields.len());
+
+    create_internal_class(vm, fields, ref_fields, size, &[], None)
+}
+
+// offset of slot in context object
+pub fn context_offset(slot: usize) -> i32 {
+    Header::size() + (slot as i32) * mem::ptr_width()
+}
+
+// creates class for closure objects of a lambda: its only field is the context
+// of the function the lambda was created in, the vtable has a single entry for
+// the lambda itself.
+pub fn specialize_lambda(
+    vm: &VM,
+    fct_id: FctId,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+) -> ClassDefId {
+    let context = FieldDef {
+        offset: Header::size(),
+        ty: BuiltinType::Ptr,
+    };
+
+    let ref_fields = vec![context.offset];
+    let size = Header::size() + mem::ptr_width();
+
+    let stub = vm.compiler_thunk().to_usize();
+    let lambda = (fct_id, cls_type_params.clone(), fct_type_params.clone());
+
+    create_internal_class(vm, vec![context], ref_fields, size, &[stub], Some(lambda))
+}
+
+fn create_internal_class(
+    vm: &VM,
+    fields: Vec<FieldDef>,
+    ref_fields: Vec<i32>,
+    size: i32,
+    vtable_entries: &[usize],
+    lambda: Option<(FctId, TypeParams, TypeParams)>,
+) -> ClassDefId {
+    let id = {
+        let mut class_defs = vm.class_defs.lock();
+        let id: ClassDefId = class_defs.len().into();
+
+        class_defs.push(Arc::new(RwLock::new(ClassDef {
+            id: id,
+            cls_id: None,
+            type_params: TypeParams::empty(),
+            parent_id: None,
+            size: ClassSize::Fixed(size),
+            fields: fields,
+            ref_fields: ref_fields,
+            vtable: None,
+            lambda: lambda,
+        })));
+
+        id
+    };
+
+    let cls_def = vm.class_defs.idx(id);
+    let mut cls_def = cls_def.write();
+
+    let clsptr = (&*cls_def) as *const class::ClassDef as *mut class::ClassDef;
+    let vtable = VTableBox::new(clsptr, vtable_entries);
+    cls_def.vtable = Some(vtable);
+
+    ensure_display(vm, &mut cls_def);
+
+    id
+}
+
 fn ensure_display<'ast>(vm: &VM<'ast>, cls_def: &mut ClassDef) -> usize {
     let vtable = cls_def.vtable.as_mut().unwrap();
 
@@ -13,11 +13,13 @@ use crate::exception::{alloc_exception, stacktrace_from_last_dtn};
 use crate::gc::{Address, GcReason};
 use crate::handle::root;
 use crate::object::{ByteArray, Obj, Ref, Str};
-use crate::os::signal::Trap;
+use crate::os::{self, signal::Trap};
+use crate::semck::specialize::specialize_class_id;
 use crate::sym::Sym::SymFct;
 use crate::threads::{DoraThread, THREAD};
 use crate::vm::exception_set;
 use crate::vm::get_vm;
+use crate::vm::{FctId, VM};
 
 pub extern "C" fn byte_to_string(val: u8) -> Ref<Str> {
     let buffer = val.to_string();
@@ -106,7 +108,8 @@ pub extern "C" fn sleep(seconds: i32) {
 pub extern "C" fn throw_native(val: bool) {
     if val {
         let vm = get_vm();
-        let obj = alloc_exception(vm, Ref::null());
+        let cls_def_id = vm.vips.exception(vm);
+        let obj = alloc_exception(vm, cls_def_id, Ref::null());
         let obj = root(obj);
 
         exception_set(obj.direct().raw() as *const u8);
@@ -289,48 +292,77 @@ pub extern "C" fn trap(trap_id: u32) {
     let vm = get_vm();
     let trap = Trap::from(trap_id).expect("invalid trap id!");
 
-    let msg = match trap {
-        Trap::DIV0 => "division by 0",
-        Trap::ASSERT => "assert failed",
-        Trap::INDEX_OUT_OF_BOUNDS => "array index out of bounds",
-        Trap::NIL => "nil check failed",
-        Trap::CAST => "cast failed",
-        Trap::THROW => "uncaught exception",
-        Trap::UNEXPECTED => "unexpected exception",
-        Trap::OOM => "out of memory",
-    };
-
-    println!("{}", msg);
+    if let Some(cls_id) = vm.vips.trap_class(trap) {
+        // the native stub throws the pending exception after returning
+        let msg = Str::from_buffer(vm, trap.message().as_bytes());
+        let cls_def_id = specialize_class_id(vm, cls_id);
+        let obj = alloc_exception(vm, cls_def_id, msg);
+        let obj = root(obj);
+
+        exception_set(obj.direct().raw() as *const u8);
+    } else {
+        fatal_trap(trap);
+    }
+}
+
+pub fn fatal_trap(trap: Trap) -> ! {
+    let vm = get_vm();
+    println!("{}", trap.message());
     let stacktrace = stacktrace_from_last_dtn(vm);
     stacktrace.dump(vm);
     unsafe {
-        libc::_exit(100 + trap_id as i32);
+        libc::_exit(100 + trap.int() as i32);
     }
 }
 
 pub extern "C" fn spawn_thread(obj: Ref<Obj>) {
+    start_thread(obj, |vm, obj| {
+        let cls_id = obj.header().vtbl().class().cls_id;
+        let cls_id = cls_id.expect("no corresponding class");
+        let cls = vm.classes.idx(cls_id);
+        let cls = cls.read();
+        let name = vm.interner.intern("run");
+        let main = cls
+            .find_method(vm, name, false)
+            .expect("run() method not found");
+
+        (main, TypeParams::empty(), TypeParams::empty())
+    });
+}
+
+// spawn statements compile their block into a lambda, the new thread invokes the closure
+pub extern "C" fn spawn_lambda(obj: Ref<Obj>) {
+    start_thread(obj, |_, obj| {
+        let cls_def = obj.header().vtbl().class();
+        cls_def.lambda.clone().expect("closure expected")
+    });
+}
+
+// runs the function returned by `main` with `obj` as its only argument on a new thread
+fn start_thread<F>(obj: Ref<Obj>, main: F)
+where
+    F: FnOnce(&VM, Ref<Obj>) -> (FctId, TypeParams, TypeParams) + Send + 'static,
+{
     use crate::baseline;
     use crate::exception::DoraToNativeInfo;
 
     let vm = get_vm();
     let thread = DoraThread::new();
 
+    // a collection might move the object before the new thread enters dora code,
+    // it stays rooted in the handles of the new thread until the thread finishes
+    let slot = Address::from_ptr(thread.handles.root(obj).raw());
+    let rooted = move || unsafe { *slot.to_ptr::<Ref<Obj>>() };
+
     vm.threads.attach_thread(thread.clone());
 
     thread::spawn(move || {
         THREAD.with(|tld| {
+            thread.tld.set_stack_limit(os::stack_limit().into());
             *tld.borrow_mut() = thread;
         });
 
-        let main = {
-            let cls_id = obj.header().vtbl().class().cls_id;
-            let cls_id = cls_id.expect("no corresponding class");
-            let cls = vm.classes.idx(cls_id);
-            let cls = cls.read();
-            let name = vm.interner.intern("run");
-            cls.find_method(vm, name, false)
-                .expect("run() method not found")
-        };
+        let (main, cls_type_params, fct_type_params) = main(vm, rooted());
 
         let tld = THREAD.with(|thread| {
             let thread = thread.borrow();
@@ -341,20 +373,19 @@ pub extern "C" fn spawn_thread(obj: Ref<Obj>) {
 
         let fct_ptr = {
             let mut dtn = DoraToNativeInfo::new();
-            let type_params = TypeParams::empty();
 
             THREAD.with(|thread| {
                 thread.borrow().use_dtn(&mut dtn, || {
-                    baseline::generate(vm, main, &type_params, &type_params)
+                    baseline::generate(vm, main, &cls_type_params, &fct_type_params)
                 })
             })
         };
 
-        // execute the thread object's run-method
+        // execute the thread object's run-method or the spawned lambda
         let dora_entry_thunk = vm.dora_entry_thunk();
         let fct: extern "C" fn(Address, Address, Ref<Obj>) =
             unsafe { mem::transmute(dora_entry_thunk) };
-        fct(tld, fct_ptr, obj);
+        fct(tld, fct_ptr, rooted());
 
         // remove thread from list of all threads
         vm.threads.detach_current_thread();
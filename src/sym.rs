@@ -42,6 +42,17 @@ impl SymTable {
         None
     }
 
+    // like `get` but also returns the index of the level the symbol was found in
+    pub fn get_with_level(&self, name: Name) -> Option<(Sym, usize)> {
+        for (idx, level) in self.levels.iter().enumerate().rev() {
+            if let Some(val) = level.get(name) {
+                return Some((val.clone(), idx));
+            }
+        }
+
+        None
+    }
+
     pub fn get_var(&self, name: Name) -> Option<VarId> {
         self.get(name).and_then(|n| n.to_var())
     }
@@ -105,6 +116,7 @@ pub enum Sym {
     SymClassTypeParam(ClassId, TypeParamId),
     SymFctTypeParam(FctId, TypeParamId),
     SymConst(ConstId),
+    SymEnum(EnumId),
 }
 
 impl Sym {
@@ -213,4 +225,18 @@ impl Sym {
             _ => None,
         }
     }
+
+    pub fn is_enum(&self) -> bool {
+        match *self {
+            SymEnum(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn to_enum(&self) -> Option<EnumId> {
+        match *self {
+            SymEnum(id) => Some(id),
+            _ => None,
+        }
+    }
 }
@@ -6,6 +6,7 @@ use std::sync::Arc;
 use crate::exception::DoraToNativeInfo;
 use crate::gc::{Address, Region};
 use crate::handle::HandleMemory;
+use crate::os;
 
 thread_local! {
     pub static THREAD: RefCell<Arc<DoraThread>> = RefCell::new(DoraThread::new());
@@ -26,8 +27,11 @@ impl Threads {
 
     pub fn attach_current_thread(&self) {
         THREAD.with(|thread| {
+            let thread = thread.borrow();
+            thread.tld.set_stack_limit(os::stack_limit().into());
+
             let mut threads = self.threads.lock();
-            threads.push(thread.borrow().clone());
+            threads.push(thread.clone());
         });
     }
 
@@ -157,6 +161,8 @@ pub struct ThreadLocalData {
     tlab_top: AtomicUsize,
     tlab_end: AtomicUsize,
     concurrent_marking: AtomicBool,
+    exception_object: AtomicUsize,
+    stack_limit: AtomicUsize,
 }
 
 impl ThreadLocalData {
@@ -165,6 +171,8 @@ impl ThreadLocalData {
             tlab_top: AtomicUsize::new(0),
             tlab_end: AtomicUsize::new(0),
             concurrent_marking: AtomicBool::new(false),
+            exception_object: AtomicUsize::new(0),
+            stack_limit: AtomicUsize::new(0),
         }
     }
 
@@ -189,6 +197,32 @@ impl ThreadLocalData {
         Region::new(tlab_top.into(), tlab_end.into())
     }
 
+    // pending exception thrown in native code, needs to be
+    // rethrown when returning to dora code
+    pub fn exception_object(&self) -> Address {
+        self.exception_object.load(Ordering::Relaxed).into()
+    }
+
+    pub fn set_exception_object(&self, exception: Address) {
+        self.exception_object
+            .store(exception.to_usize(), Ordering::Relaxed);
+    }
+
+    // address of the pending exception, used as root by the GC
+    pub fn exception_object_address(&self) -> Address {
+        Address::from_ptr(&self.exception_object as *const _)
+    }
+
+    // dora code traps with a stack overflow when the stack
+    // pointer goes below this address
+    pub fn stack_limit(&self) -> Address {
+        self.stack_limit.load(Ordering::Relaxed).into()
+    }
+
+    pub fn set_stack_limit(&self, limit: Address) {
+        self.stack_limit.store(limit.to_usize(), Ordering::Relaxed);
+    }
+
     pub fn tlab_top_offset() -> i32 {
         offset_of!(ThreadLocalData, tlab_top) as i32
     }
@@ -200,4 +234,12 @@ impl ThreadLocalData {
     pub fn concurrent_marking_offset() -> i32 {
         offset_of!(ThreadLocalData, concurrent_marking) as i32
     }
+
+    pub fn exception_object_offset() -> i32 {
+        offset_of!(ThreadLocalData, exception_object) as i32
+    }
+
+    pub fn stack_limit_offset() -> i32 {
+        offset_of!(ThreadLocalData, stack_limit) as i32
+    }
 }
@@ -5,7 +5,7 @@ use crate::class::{ClassId, TypeParamId, TypeParams};
 use crate::mem;
 use crate::semck;
 use crate::vm::VM;
-use crate::vm::{FctId, StructId, TraitId};
+use crate::vm::{EnumId, FctId, StructId, TraitId};
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 pub enum BuiltinType {
@@ -44,6 +44,9 @@ pub enum BuiltinType {
     // some trait
     Trait(TraitId),
 
+    // some enum
+    Enum(EnumId),
+
     // some type variable
     FctTypeParam(FctId, TypeParamId),
     ClassTypeParam(ClassId, TypeParamId),
@@ -81,6 +84,20 @@ impl BuiltinType {
         }
     }
 
+    pub fn is_lambda(&self) -> bool {
+        match *self {
+            BuiltinType::Lambda(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_enum(&self) -> bool {
+        match *self {
+            BuiltinType::Enum(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn is_float(&self) -> bool {
         match self {
             &BuiltinType::Float | &BuiltinType::Double => true,
@@ -145,7 +162,11 @@ impl BuiltinType {
                 params.iter().any(|t| t.contains_type_param(vm))
             }
 
-            &BuiltinType::Lambda(_) => unimplemented!(),
+            &BuiltinType::Lambda(id) => {
+                let lambda = vm.lambda_types.lock().get(id);
+                lambda.params.iter().any(|t| t.contains_type_param(vm))
+                    || lambda.ret.contains_type_param(vm)
+            }
 
             _ => false,
         }
@@ -163,7 +184,8 @@ impl BuiltinType {
             | BuiltinType::Int
             | BuiltinType::Long
             | BuiltinType::Float
-            | BuiltinType::Double => true,
+            | BuiltinType::Double
+            | BuiltinType::Enum(_) => true,
             _ => false,
         }
     }
@@ -238,6 +260,10 @@ impl BuiltinType {
                 let xtrait = vm.traits[tid].read();
                 vm.interner.str(xtrait.name).to_string()
             }
+            BuiltinType::Enum(id) => {
+                let xenum = vm.enums[id].read();
+                vm.interner.str(xenum.name).to_string()
+            }
             BuiltinType::ClassTypeParam(cid, id) => {
                 let cls = vm.classes.idx(cid);
                 let cls = cls.read();
@@ -275,7 +301,8 @@ impl BuiltinType {
             | BuiltinType::Bool
             | BuiltinType::Byte
             | BuiltinType::Char
-            | BuiltinType::Struct(_, _) => *self == other,
+            | BuiltinType::Struct(_, _)
+            | BuiltinType::Enum(_) => *self == other,
             BuiltinType::Int => *self == other,
             BuiltinType::Long => *self == other,
             BuiltinType::Float | BuiltinType::Double => *self == other,
@@ -294,7 +321,7 @@ impl BuiltinType {
                 // for now expect the exact same params and return types
                 // possible improvement: allow super classes for params,
                 //                             sub class for return type
-                *self == other
+                *self == other || other.is_nil()
             }
         }
     }
@@ -332,6 +359,7 @@ impl BuiltinType {
                 struc.size
             }
             BuiltinType::Trait(_) => 2 * mem::ptr_width(),
+            BuiltinType::Enum(_) => 4,
             BuiltinType::ClassTypeParam(_, _) | BuiltinType::FctTypeParam(_, _) => {
                 panic!("no size for type variable.")
             }
@@ -363,6 +391,7 @@ impl BuiltinType {
                 struc.align
             }
             BuiltinType::Trait(_) => mem::ptr_width(),
+            BuiltinType::Enum(_) => 4,
             BuiltinType::ClassTypeParam(_, _) | BuiltinType::FctTypeParam(_, _) => {
                 panic!("no alignment for type variable.")
             }
@@ -387,6 +416,7 @@ impl BuiltinType {
             }
             BuiltinType::Struct(_, _) => panic!("no machine mode for struct."),
             BuiltinType::Trait(_) => unimplemented!(),
+            BuiltinType::Enum(_) => MachineMode::Int32,
             BuiltinType::ClassTypeParam(_, _) | BuiltinType::FctTypeParam(_, _) => {
                 panic!("no machine mode for type variable.")
             }
@@ -462,9 +492,27 @@ impl TypeLists {
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct TypeListId(usize);
 
+impl TypeListId {
+    pub fn idx(self) -> usize {
+        self.0
+    }
+}
+
+impl From<usize> for TypeListId {
+    fn from(data: usize) -> TypeListId {
+        TypeListId(data)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct LambdaId(usize);
 
+impl LambdaId {
+    pub fn idx(self) -> usize {
+        self.0
+    }
+}
+
 impl From<usize> for LambdaId {
     fn from(val: usize) -> LambdaId {
         LambdaId(val)
@@ -518,8 +566,8 @@ impl LambdaTypes {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct LambdaType {
-    params: Vec<BuiltinType>,
-    ret: BuiltinType,
+    pub params: Vec<BuiltinType>,
+    pub ret: BuiltinType,
 }
 
 #[cfg(test)]
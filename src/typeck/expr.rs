@@ -7,7 +7,10 @@ use crate::sym::Sym::SymClass;
 use crate::ty::BuiltinType;
 use crate::typeck::lookup::MethodLookup;
 use crate::vm;
-use crate::vm::{CallType, ConvInfo, Fct, FctId, FctParent, FctSrc, ForTypeInfo, IdentType, VM};
+use crate::vm::{
+    CallType, ConvInfo, EnumId, Fct, FctId, FctParent, FctSrc, ForTypeInfo, IdentType, Var, VarId,
+    VM,
+};
 use dora_parser::error::msg::Msg;
 
 use dora_parser::ast::visit::Visitor;
@@ -272,6 +275,12 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                 self.expr_type = ty;
             }
 
+            &IdentType::Context(fct_id, varid) => {
+                let ty = self.context_var(fct_id, varid).ty;
+                self.src.set_ty(e.id, ty);
+                self.expr_type = ty;
+            }
+
             &IdentType::Global(globalid) => {
                 let glob = self.vm.globals.idx(globalid);
                 let ty = glob.lock().ty;
@@ -328,6 +337,18 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                 self.expr_type = BuiltinType::Error;
             }
 
+            &IdentType::Enum(_) => {
+                self.vm
+                    .diag
+                    .lock()
+                    .report_without_path(e.pos, Msg::EnumUsedAsIdentifier);
+
+                self.src.set_ty(e.id, BuiltinType::Error);
+                self.expr_type = BuiltinType::Error;
+            }
+
+            &IdentType::EnumValue(_, _) => unreachable!(),
+
             &IdentType::FctType(_, _) | &IdentType::ClassType(_, _) => unreachable!(),
             &IdentType::Method(_, _) | &IdentType::MethodType(_, _, _) => unreachable!(),
             &IdentType::StaticMethod(_, _) | &IdentType::StaticMethodType(_, _, _) => {
@@ -363,6 +384,19 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                         lhs_type = self.src.vars[varid].ty;
                     }
 
+                    &IdentType::Context(fct_id, varid) => {
+                        let var = self.context_var(fct_id, varid);
+
+                        if !var.reassignable {
+                            self.vm
+                                .diag
+                                .lock()
+                                .report_without_path(e.pos, Msg::LetReassigned);
+                        }
+
+                        lhs_type = var.ty;
+                    }
+
                     &IdentType::Global(gid) => {
                         let glob = self.vm.globals.idx(gid);
                         let glob = glob.lock();
@@ -412,6 +446,17 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                         return;
                     }
 
+                    &IdentType::Enum(_) => {
+                        self.vm
+                            .diag
+                            .lock()
+                            .report_without_path(e.pos, Msg::EnumReassigned);
+
+                        return;
+                    }
+
+                    &IdentType::EnumValue(_, _) => unreachable!(),
+
                     &IdentType::Method(_, _) | &IdentType::MethodType(_, _, _) => unreachable!(),
                     &IdentType::StaticMethod(_, _) | &IdentType::StaticMethodType(_, _, _) => {
                         unreachable!()
@@ -742,7 +787,11 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
             }
 
             CmpOp::Eq | CmpOp::Ne => {
-                self.check_expr_bin_method(e, e.op, "equals", lhs_type, rhs_type)
+                if lhs_type.is_enum() || rhs_type.is_enum() {
+                    self.check_type(e, e.op, lhs_type, rhs_type, lhs_type);
+                } else {
+                    self.check_expr_bin_method(e, e.op, "equals", lhs_type, rhs_type)
+                }
             }
 
             _ => self.check_expr_bin_method(e, e.op, "compareTo", lhs_type, rhs_type),
@@ -852,11 +901,68 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                     return;
                 }
 
+                if expr_type.is_lambda() {
+                    self.check_expr_call_lambda(e, expr_type, &arg_types);
+                    return;
+                }
+
                 self.check_expr_call_expr(e, expr_type, &arg_types, in_try);
             }
         }
     }
 
+    fn check_expr_call_lambda(
+        &mut self,
+        e: &'ast ExprCallType,
+        expr_type: BuiltinType,
+        arg_types: &[BuiltinType],
+    ) {
+        let lambda_id = match expr_type {
+            BuiltinType::Lambda(lambda_id) => lambda_id,
+            _ => unreachable!(),
+        };
+
+        let lambda = self.vm.lambda_types.lock().get(lambda_id);
+
+        let compatible = lambda.params.len() == arg_types.len()
+            && lambda
+                .params
+                .iter()
+                .zip(arg_types)
+                .all(|(def, &arg)| def.allows(self.vm, arg));
+
+        if !compatible {
+            let name = expr_type.name(self.vm);
+            let def = lambda
+                .params
+                .iter()
+                .map(|ty| ty.name(self.vm))
+                .collect::<Vec<_>>();
+            let expr = arg_types
+                .iter()
+                .map(|ty| ty.name(self.vm))
+                .collect::<Vec<_>>();
+            let msg = Msg::ParamTypesIncompatible(name, def, expr);
+            self.vm.diag.lock().report_without_path(e.pos, msg);
+        }
+
+        let call_type = CallType::Lambda(lambda_id);
+        self.src.map_calls.insert(e.id, Arc::new(call_type));
+
+        self.src.set_ty(e.id, lambda.ret);
+        self.expr_type = lambda.ret;
+    }
+
+    // returns variable of an outer function captured by this lambda
+    fn context_var(&self, fct_id: FctId, var_id: VarId) -> Var {
+        let fct = self.vm.fcts.idx(fct_id);
+        let fct = fct.read();
+        let src = fct.src();
+        let src = src.read();
+
+        src.vars[var_id].clone()
+    }
+
     fn check_expr_call_expr(
         &mut self,
         e: &'ast ExprCallType,
@@ -1307,6 +1413,11 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
     fn check_expr_path(&mut self, e: &'ast ExprPathType) {
         let ident_type = self.src.map_idents.get(e.lhs.id());
 
+        if let Some(&IdentType::Enum(enum_id)) = ident_type {
+            self.check_expr_path_enum(e, enum_id);
+            return;
+        }
+
         let cls_ty = match ident_type {
             Some(&IdentType::Class(cls_id)) => {
                 let list = self.vm.lists.lock().insert(TypeParams::empty());
@@ -1346,6 +1457,39 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
             .report_without_path(e.pos, Msg::FctUsedAsIdentifier);
     }
 
+    fn check_expr_path_enum(&mut self, e: &'ast ExprPathType, enum_id: EnumId) {
+        let name = if let Some(ident) = e.rhs.to_ident() {
+            ident.name
+        } else {
+            let msg = Msg::EnumValueExpected;
+            self.vm.diag.lock().report_without_path(e.rhs.pos(), msg);
+
+            self.src.set_ty(e.id, BuiltinType::Error);
+            self.expr_type = BuiltinType::Error;
+            return;
+        };
+
+        let xenum = self.vm.enums[enum_id].read();
+
+        if let Some(value) = xenum.value(name) {
+            self.src
+                .map_idents
+                .insert(e.id, IdentType::EnumValue(enum_id, value));
+
+            let ty = BuiltinType::Enum(enum_id);
+            self.src.set_ty(e.id, ty);
+            self.expr_type = ty;
+        } else {
+            let xenum_name = self.vm.interner.str(xenum.name).to_string();
+            let name = self.vm.interner.str(name).to_string();
+            let msg = Msg::UnknownEnumValue(xenum_name, name);
+            self.vm.diag.lock().report_without_path(e.rhs.pos(), msg);
+
+            self.src.set_ty(e.id, BuiltinType::Error);
+            self.expr_type = BuiltinType::Error;
+        }
+    }
+
     fn check_expr_type_param(&mut self, e: &'ast ExprTypeParamType) {
         if self.used_in_call.contains(&e.id) {
             self.used_in_call.insert(e.callee.id());
@@ -1463,6 +1607,19 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                 self.expr_type = ty;
             }
 
+            FctParent::Function(_) => {
+                if let Some(&IdentType::Context(fct_id, var_id)) = self.src.map_idents.get(e.id) {
+                    let ty = self.context_var(fct_id, var_id).ty;
+                    self.src.set_ty(e.id, ty);
+                    self.expr_type = ty;
+                } else {
+                    let msg = Msg::ThisUnavailable;
+                    self.vm.diag.lock().report_without_path(e.pos, msg);
+                    self.src.set_ty(e.id, BuiltinType::Unit);
+                    self.expr_type = BuiltinType::Unit;
+                }
+            }
+
             _ => {
                 let msg = Msg::ThisUnavailable;
                 self.vm.diag.lock().report_without_path(e.pos, msg);
@@ -1543,20 +1700,12 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
     }
 
     fn check_expr_lambda(&mut self, e: &'ast ExprLambdaType) {
-        let ret = if let Some(ref ty) = e.ret {
-            self.src.ty(ty.id())
-        } else {
-            BuiltinType::Unit
-        };
+        let lambda_id = *self.src.map_lambdas.get(e.id).unwrap();
+        let lambda = self.vm.fcts.idx(lambda_id);
+        let lambda = lambda.read();
 
-        let params = e
-            .params
-            .iter()
-            .map(|p| self.src.ty(p.data_type.id()))
-            .collect::<Vec<_>>();
-
-        let ty = self.vm.lambda_types.lock().insert(params, ret);
-        let ty = BuiltinType::Lambda(ty);
+        // type of hidden closure parameter is the type of the lambda
+        let ty = lambda.param_types[0];
 
         self.expr_type = ty;
         self.src.set_ty(e.id, ty);
@@ -1664,7 +1813,6 @@ impl<'a, 'ast> Visitor<'ast> for TypeCheck<'a, 'ast> {
             StmtIf(ref stmt) => self.check_stmt_if(stmt),
             StmtReturn(ref stmt) => self.check_stmt_return(stmt),
             StmtThrow(ref stmt) => self.check_stmt_throw(stmt),
-            StmtSpawn(_) => unimplemented!(),
             StmtDefer(ref stmt) => self.check_stmt_defer(stmt),
             StmtDo(ref stmt) => self.check_stmt_do(stmt),
 
@@ -1674,6 +1822,7 @@ impl<'a, 'ast> Visitor<'ast> for TypeCheck<'a, 'ast> {
             StmtLoop(_) => visit::walk_stmt(self, s),
             StmtExpr(_) => visit::walk_stmt(self, s),
             StmtBlock(_) => visit::walk_stmt(self, s),
+            StmtSpawn(_) => visit::walk_stmt(self, s),
         }
     }
 }
@@ -1719,7 +1868,8 @@ fn arg_allows(
         | BuiltinType::Int
         | BuiltinType::Long
         | BuiltinType::Float
-        | BuiltinType::Double => def == arg,
+        | BuiltinType::Double
+        | BuiltinType::Enum(_) => def == arg,
         BuiltinType::Nil => panic!("nil should not occur in fct definition."),
         BuiltinType::Ptr => panic!("ptr should not occur in fct definition."),
         BuiltinType::This => panic!("this should not occur in fct definition."),
@@ -1807,7 +1957,7 @@ fn arg_allows(
             // for now expect the exact same params and return types
             // possible improvement: allow super classes for params,
             //                             sub class for return type
-            def == arg
+            def == arg || arg.is_nil()
         }
     }
 }
@@ -1988,7 +2138,19 @@ pub fn replace_type_param(
             BuiltinType::Class(cls_id, list_id)
         }
 
-        BuiltinType::Lambda(_) => unimplemented!(),
+        BuiltinType::Lambda(lambda_id) => {
+            let lambda = vm.lambda_types.lock().get(lambda_id);
+
+            let params = lambda
+                .params
+                .iter()
+                .map(|&p| replace_type_param(vm, p, cls_tp, fct_tp))
+                .collect::<Vec<_>>();
+            let ret = replace_type_param(vm, lambda.ret, cls_tp, fct_tp);
+
+            let lambda_id = vm.lambda_types.lock().insert(params, ret);
+            BuiltinType::Lambda(lambda_id)
+        }
 
         _ => ty,
     }
@@ -2818,6 +2980,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn enum_use() {
+        ok("enum Foo { A, B } fun f() -> Foo { return Foo::A; }");
+        ok("enum Foo { A, B } fun f(x: Foo) -> Bool { return x == Foo::B; }");
+        ok("enum Foo { A, B } fun f(x: Foo) -> Bool { return x != Foo::B; }");
+        ok("enum Foo { A, B } fun f(x: Foo, y: Foo) -> Bool { return x === y; }");
+        ok("enum Foo { A, B } fun f() { var x = Foo::A; x = Foo::B; }");
+        ok("enum Foo { A, B } class Bar(let x: Foo)");
+        err(
+            "enum Foo { A, B } fun f() -> Foo { return Foo::C; }",
+            pos(1, 48),
+            Msg::UnknownEnumValue("Foo".into(), "C".into()),
+        );
+        err(
+            "enum Foo { A } enum Bar { A } fun f() -> Bool { return Foo::A == Bar::A; }",
+            pos(1, 63),
+            Msg::BinOpType("==".into(), "Foo".into(), "Bar".into()),
+        );
+        err(
+            "enum Foo { A } fun f(x: Foo) -> Int { return x; }",
+            pos(1, 39),
+            Msg::ReturnType("Int".into(), "Foo".into()),
+        );
+        err(
+            "enum Foo { A } fun f() { let x = Foo; }",
+            pos(1, 34),
+            Msg::EnumUsedAsIdentifier,
+        );
+        err(
+            "enum Foo { A } fun f() { Foo = 1; }",
+            pos(1, 30),
+            Msg::EnumReassigned,
+        );
+    }
+
     #[test]
     fn struct_lit() {
         ok("struct Foo {} fun foo() -> Foo { return Foo; }");
@@ -3332,6 +3529,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lambda_call() {
+        ok("fun f(x: () -> Int) -> Int { return x(); }");
+        ok("fun f(x: (Int, Bool) -> ()) { x(1, true); }");
+        ok("fun f() -> Int { let x = |a: Int| -> Int { return a; }; return x(1); }");
+        ok("fun f() { var a = 1; let x = || { a = a + 1; }; x(); }");
+        ok("class Foo(let a: Int) { fun f() -> () -> Int { return || -> Int { return self.a; }; } }");
+
+        err(
+            "fun f(x: (Int) -> ()) { x(true); }",
+            pos(1, 26),
+            Msg::ParamTypesIncompatible(
+                "(Int) -> ()".into(),
+                vec!["Int".into()],
+                vec!["Bool".into()],
+            ),
+        );
+        err(
+            "fun f(x: (Int) -> ()) { x(); }",
+            pos(1, 26),
+            Msg::ParamTypesIncompatible("(Int) -> ()".into(), vec!["Int".into()], Vec::new()),
+        );
+        err(
+            "fun f() { let a = 1; || { a = 2; }; }",
+            pos(1, 29),
+            Msg::LetReassigned,
+        );
+        err(
+            "fun f() { || { self; }; }",
+            pos(1, 16),
+            Msg::ThisUnavailable,
+        );
+    }
+
+    #[test]
+    fn type_spawn() {
+        ok("fun f() { spawn { } }");
+        ok("fun f(a: Int) { spawn { let b = a + 1; } }");
+        ok("fun f() { var a = 1; spawn { a = 2; } }");
+        err(
+            "fun f() { spawn { return 1; } }",
+            pos(1, 19),
+            Msg::ReturnType("()".into(), "Int".into()),
+        );
+    }
+
     #[test]
     fn method_call_with_multiple_matching_traits() {
         err(
@@ -395,7 +395,17 @@ impl<'a, 'ast> MethodLookup<'a, 'ast> {
             }
         }
 
-        let cls_id = ty.cls_id(self.vm).unwrap();
+        let cls_id = if let Some(cls_id) = ty.cls_id(self.vm) {
+            cls_id
+        } else {
+            for &trait_bound in &tp.trait_bounds {
+                self.fail_trait_bound(trait_bound, ty);
+                succeeded = false;
+            }
+
+            return succeeded;
+        };
+
         let cls = self.vm.classes.idx(cls_id);
         let cls = cls.read();
 
@@ -13,21 +13,29 @@ use dora_parser::error::diag::Diagnostic;
 use crate::baseline;
 use crate::baseline::dora_compile;
 use crate::baseline::dora_entry;
+use crate::baseline::dora_ic;
+use crate::baseline::dora_invoke;
 use crate::baseline::dora_native::{self, InternalFct, InternalFctDescriptor, NativeThunks};
 use crate::baseline::dora_throw;
 use crate::baseline::fct::{JitFct, JitFctId};
+use crate::baseline::ic::InlineCache;
 use crate::baseline::map::{CodeDescriptor, CodeMap};
+use crate::baseline::queue::CompileQueue;
+use crate::baseline::tier::HotnessCounters;
+use crate::bytecode::interpreter::InterpFct;
+use crate::cha::ClassHierarchy;
 use crate::class::{Class, ClassDef, ClassDefId, ClassId, FieldId, TypeParams};
 use crate::exception::DoraToNativeInfo;
 use crate::gc::{Address, Gc};
 use crate::object::{Ref, Testing};
+use crate::os::signal::Trap;
 use crate::safepoint::{PollingPage, Safepoint};
 use crate::semck::specialize::{specialize_class_id, specialize_class_id_params};
 use crate::stdlib;
 use crate::sym::Sym::*;
 use crate::sym::*;
 use crate::threads::{Threads, THREAD};
-use crate::ty::{BuiltinType, LambdaTypes, TypeLists};
+use crate::ty::{BuiltinType, LambdaId, LambdaTypes, TypeLists};
 use crate::utils::GrowableVec;
 use dora_parser::ast;
 use dora_parser::interner::*;
@@ -35,28 +43,30 @@ use dora_parser::lexer::position::Position;
 use dora_parser::lexer::File;
 use dora_parser::parser::NodeIdGenerator;
 
-pub static mut EXCEPTION_OBJECT: *const u8 = 0 as *const u8;
-
 pub fn has_exception() -> bool {
-    unsafe { !EXCEPTION_OBJECT.is_null() }
+    THREAD.with(|thread| !thread.borrow().tld.exception_object().is_null())
 }
 
 pub fn exception_get_and_clear() -> *const u8 {
-    unsafe {
-        let val = EXCEPTION_OBJECT;
+    THREAD.with(|thread| {
+        let tld = &thread.borrow().tld;
+        let val = tld.exception_object();
 
         if !val.is_null() {
-            EXCEPTION_OBJECT = ptr::null();
+            tld.set_exception_object(Address::null());
         }
 
-        val
-    }
+        val.to_ptr()
+    })
 }
 
 pub fn exception_set(val: *const u8) {
-    unsafe {
-        EXCEPTION_OBJECT = val;
-    }
+    THREAD.with(|thread| {
+        thread
+            .borrow()
+            .tld
+            .set_exception_object(Address::from_ptr(val));
+    });
 }
 
 static mut VM_GLOBAL: *const u8 = ptr::null();
@@ -89,7 +99,9 @@ pub struct VM<'ast> {
     pub class_defs: GrowableVec<RwLock<ClassDef>>, // stores all class definitions
     pub fcts: GrowableVec<RwLock<Fct<'ast>>>,  // stores all function definitions
     pub jit_fcts: GrowableVec<JitFct>,         // stores all function implementations
+    pub interp_fcts: GrowableVec<InterpFct>,   // stores all functions run by the interpreter
     pub traits: Vec<RwLock<TraitData>>,        // stores all trait definitions
+    pub enums: Vec<RwLock<EnumData>>,          // stores all enum definitions
     pub impls: Vec<RwLock<ImplData>>,          // stores all impl definitions
     pub code_map: Mutex<CodeMap>,              // stores all compiled functions
     pub globals: GrowableVec<Mutex<GlobalData>>, // stores all global variables
@@ -98,8 +110,12 @@ pub struct VM<'ast> {
     pub polling_page: PollingPage,
     pub lists: Mutex<TypeLists>,
     pub lambda_types: Mutex<LambdaTypes>,
+    pub cha: Mutex<ClassHierarchy>,
+    pub compile_queue: CompileQueue,
     pub compiler_thunk: Mutex<Address>,
+    pub inline_cache_stub: Mutex<Address>,
     pub dora_entry: Mutex<Address>,
+    pub dora_invoke: Mutex<Address>,
     pub trap_thunk: Mutex<Address>,
     pub throw_thunk: Mutex<Address>,
     pub threads: Threads,
@@ -122,6 +138,7 @@ impl<'ast> VM<'ast> {
             files: Vec::new(),
             class_defs: GrowableVec::new(),
             traits: Vec::new(),
+            enums: Vec::new(),
             impls: Vec::new(),
             globals: GrowableVec::new(),
             interner: Interner::new(),
@@ -144,6 +161,13 @@ impl<'ast> VM<'ast> {
                 exception_class: empty_class_id,
                 stack_trace_element_class: empty_class_id,
 
+                arithmetic_exception_class: empty_class_id,
+                index_out_of_bounds_exception_class: empty_class_id,
+                nil_pointer_exception_class: empty_class_id,
+                cast_exception_class: empty_class_id,
+                out_of_memory_error_class: empty_class_id,
+                stack_overflow_error_class: empty_class_id,
+
                 equals_trait: empty_trait_id,
                 comparable_trait: empty_trait_id,
                 iterator_trait: Mutex::new(None),
@@ -164,13 +188,18 @@ impl<'ast> VM<'ast> {
             sym: Mutex::new(SymTable::new()),
             fcts: GrowableVec::new(),
             jit_fcts: GrowableVec::new(),
+            interp_fcts: GrowableVec::new(),
             code_map: Mutex::new(CodeMap::new()),
             polling_page: PollingPage::new(),
             lists: Mutex::new(TypeLists::new()),
             lambda_types: Mutex::new(LambdaTypes::new()),
+            cha: Mutex::new(ClassHierarchy::new()),
+            compile_queue: CompileQueue::new(),
             native_thunks: Mutex::new(NativeThunks::new()),
             compiler_thunk: Mutex::new(Address::null()),
+            inline_cache_stub: Mutex::new(Address::null()),
             dora_entry: Mutex::new(Address::null()),
+            dora_invoke: Mutex::new(Address::null()),
             trap_thunk: Mutex::new(Address::null()),
             throw_thunk: Mutex::new(Address::null()),
             threads: Threads::new(),
@@ -230,7 +259,7 @@ impl<'ast> VM<'ast> {
         code_map.insert(start, end, desc);
     }
 
-    pub fn add_fct(&mut self, mut fct: Fct<'ast>) -> FctId {
+    pub fn add_fct(&self, mut fct: Fct<'ast>) -> FctId {
         let mut fcts = self.fcts.lock();
         let fctid = FctId(fcts.len());
 
@@ -358,6 +387,16 @@ impl<'ast> VM<'ast> {
         *dora_entry_thunk
     }
 
+    pub fn dora_invoke_thunk(&self) -> Address {
+        let mut dora_invoke_thunk = self.dora_invoke.lock();
+
+        if dora_invoke_thunk.is_null() {
+            *dora_invoke_thunk = dora_invoke::generate(self);
+        }
+
+        *dora_invoke_thunk
+    }
+
     pub fn throw_thunk(&self) -> Address {
         let mut throw_thunk = self.throw_thunk.lock();
 
@@ -378,6 +417,16 @@ impl<'ast> VM<'ast> {
         *compiler_thunk
     }
 
+    pub fn inline_cache_stub(&self) -> Address {
+        let mut inline_cache_stub = self.inline_cache_stub.lock();
+
+        if inline_cache_stub.is_null() {
+            *inline_cache_stub = dora_ic::generate(self);
+        }
+
+        *inline_cache_stub
+    }
+
     pub fn trap_thunk(&self) -> Address {
         let mut trap_thunk = self.trap_thunk.lock();
 
@@ -438,6 +487,12 @@ pub struct StructFieldDef {
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct GlobalId(u32);
 
+impl GlobalId {
+    pub fn idx(self) -> usize {
+        self.0 as usize
+    }
+}
+
 impl From<u32> for GlobalId {
     fn from(data: u32) -> GlobalId {
         GlobalId(data)
@@ -514,6 +569,12 @@ impl Index<ImplId> for Vec<RwLock<ImplData>> {
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct TraitId(u32);
 
+impl TraitId {
+    pub fn idx(self) -> usize {
+        self.0 as usize
+    }
+}
+
 impl From<u32> for TraitId {
     fn from(data: u32) -> TraitId {
         TraitId(data)
@@ -587,6 +648,44 @@ impl Index<TraitId> for Vec<RwLock<TraitData>> {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EnumId(u32);
+
+impl EnumId {
+    pub fn idx(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl From<u32> for EnumId {
+    fn from(data: u32) -> EnumId {
+        EnumId(data)
+    }
+}
+
+impl Index<EnumId> for Vec<RwLock<EnumData>> {
+    type Output = RwLock<EnumData>;
+
+    fn index(&self, index: EnumId) -> &RwLock<EnumData> {
+        &self[index.0 as usize]
+    }
+}
+
+#[derive(Debug)]
+pub struct EnumData {
+    pub id: EnumId,
+    pub pos: Position,
+    pub name: Name,
+    pub values: Vec<Name>,
+    pub name_to_value: HashMap<Name, u32>,
+}
+
+impl EnumData {
+    pub fn value(&self, name: Name) -> Option<u32> {
+        self.name_to_value.get(&name).cloned()
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct StructId(u32);
 
@@ -596,6 +695,12 @@ impl GrowableVec<Mutex<StructData>> {
     }
 }
 
+impl StructId {
+    pub fn idx(self) -> usize {
+        self.0 as usize
+    }
+}
+
 impl From<u32> for StructId {
     fn from(data: u32) -> StructId {
         StructId(data)
@@ -647,6 +752,13 @@ pub struct KnownElements {
     pub exception_class: ClassId,
     pub stack_trace_element_class: ClassId,
 
+    pub arithmetic_exception_class: ClassId,
+    pub index_out_of_bounds_exception_class: ClassId,
+    pub nil_pointer_exception_class: ClassId,
+    pub cast_exception_class: ClassId,
+    pub out_of_memory_error_class: ClassId,
+    pub stack_overflow_error_class: ClassId,
+
     pub equals_trait: TraitId,
     pub comparable_trait: TraitId,
     pub iterator_trait: Mutex<Option<TraitId>>,
@@ -727,6 +839,33 @@ impl KnownElements {
         }
     }
 
+    pub fn trap_class(&self, trap: Trap) -> Option<ClassId> {
+        match trap {
+            Trap::DIV0 => Some(self.arithmetic_exception_class),
+            Trap::INDEX_OUT_OF_BOUNDS => Some(self.index_out_of_bounds_exception_class),
+            Trap::NIL => Some(self.nil_pointer_exception_class),
+            Trap::CAST => Some(self.cast_exception_class),
+            Trap::OOM => Some(self.out_of_memory_error_class),
+            Trap::STACK_OVERFLOW => Some(self.stack_overflow_error_class),
+            Trap::ASSERT | Trap::THROW | Trap::UNEXPECTED => None,
+        }
+    }
+
+    // inverse of `trap_class`: the trap an exception of this class was thrown for
+    pub fn trap_for_class(&self, cls_id: ClassId) -> Option<Trap> {
+        [
+            Trap::DIV0,
+            Trap::INDEX_OUT_OF_BOUNDS,
+            Trap::NIL,
+            Trap::CAST,
+            Trap::OOM,
+            Trap::STACK_OVERFLOW,
+        ]
+        .iter()
+        .cloned()
+        .find(|&trap| self.trap_class(trap) == Some(cls_id))
+    }
+
     pub fn find_class(&self, ty: BuiltinType) -> Option<ClassId> {
         match ty {
             BuiltinType::Bool => Some(self.bool_class),
@@ -755,6 +894,7 @@ pub enum FctParent {
     Class(ClassId),
     Trait(TraitId),
     Impl(ImplId),
+    Function(FctId),
     None,
 }
 
@@ -853,6 +993,34 @@ impl<'ast> Fct<'ast> {
         }
     }
 
+    pub fn is_lambda(&self) -> bool {
+        match self.parent {
+            FctParent::Function(_) => true,
+            _ => false,
+        }
+    }
+
+    // returns the function a lambda is defined in
+    pub fn outer_fct_id(&self) -> FctId {
+        match self.parent {
+            FctParent::Function(fct_id) => fct_id,
+            _ => unreachable!(),
+        }
+    }
+
+    // returns the outermost function of a lambda, which is not a lambda itself
+    pub fn root_fct_id(&self, vm: &VM) -> FctId {
+        let mut fct_id = self.id;
+        let mut parent = self.parent.clone();
+
+        while let FctParent::Function(outer_id) = parent {
+            fct_id = outer_id;
+            parent = vm.fcts.idx(outer_id).read().parent.clone();
+        }
+
+        fct_id
+    }
+
     pub fn full_name(&self, vm: &VM) -> String {
         let mut repr = String::new();
 
@@ -930,6 +1098,9 @@ impl<'ast> Fct<'ast> {
         match self.parent {
             FctParent::Class(_) | FctParent::Trait(_) | FctParent::Impl(_) => !self.is_static,
 
+            // lambdas get the closure object as hidden first parameter
+            FctParent::Function(_) => true,
+
             _ => false,
         }
     }
@@ -1124,11 +1295,26 @@ pub struct FctSrc {
     pub map_convs: NodeMap<ConvInfo>,
     pub map_cls: NodeMap<ClassId>,
     pub map_fors: NodeMap<ForTypeInfo>,
+    pub map_lambdas: NodeMap<FctId>,
 
     pub always_returns: bool, // true if function is always exited via return statement
     // false if function execution could reach the closing } of this function
     pub specializations: RwLock<HashMap<(TypeParams, TypeParams), JitFctId>>,
     pub vars: Vec<Var>, // variables in functions
+
+    // true if function allocates a context object on entry, either for its
+    // own captured variables or to pass the outer context on to lambdas
+    pub has_context: bool,
+    pub context_vars: Vec<VarId>, // variables stored in the context instead of the stack
+
+    // counters referenced by baseline code, boxed since machine code embeds their address
+    pub hotness_counters: Vec<Box<HotnessCounters>>,
+
+    // caches of virtual call sites in baseline code, boxed like the counters
+    pub inline_caches: Vec<Box<InlineCache>>,
+
+    // baseline code entered when deoptimizing at the given offset of optimized code
+    pub deopt_entries: HashMap<(JitFctId, i32), Address>,
 }
 
 impl Clone for FctSrc {
@@ -1141,10 +1327,18 @@ impl Clone for FctSrc {
             map_convs: self.map_convs.clone(),
             map_cls: self.map_cls.clone(),
             map_fors: self.map_fors.clone(),
+            map_lambdas: self.map_lambdas.clone(),
 
             vars: self.vars.clone(),
             always_returns: self.always_returns,
             specializations: RwLock::new(HashMap::new()),
+
+            has_context: self.has_context,
+            context_vars: self.context_vars.clone(),
+
+            hotness_counters: Vec::new(),
+            inline_caches: Vec::new(),
+            deopt_entries: HashMap::new(),
         }
     }
 }
@@ -1159,10 +1353,18 @@ impl FctSrc {
             map_convs: NodeMap::new(),
             map_cls: NodeMap::new(),
             map_fors: NodeMap::new(),
+            map_lambdas: NodeMap::new(),
 
             vars: Vec::new(),
             always_returns: false,
             specializations: RwLock::new(HashMap::new()),
+
+            has_context: false,
+            context_vars: Vec::new(),
+
+            hotness_counters: Vec::new(),
+            inline_caches: Vec::new(),
+            deopt_entries: HashMap::new(),
         }
     }
 
@@ -1181,6 +1383,14 @@ impl FctSrc {
     pub fn var_self_mut(&mut self) -> &mut Var {
         &mut self.vars[0]
     }
+
+    // slot of captured variable in context object, slot 0 stores the outer context
+    pub fn context_slot(&self, var: VarId) -> Option<usize> {
+        self.context_vars
+            .iter()
+            .position(|&v| v == var)
+            .map(|idx| idx + 1)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -1256,9 +1466,12 @@ impl Store {
 #[derive(Debug, Clone)]
 pub enum IdentType {
     Var(VarId),
+    Context(FctId, VarId),
     Global(GlobalId),
     Field(BuiltinType, FieldId),
     Struct(StructId),
+    Enum(EnumId),
+    EnumValue(EnumId, u32),
     Const(ConstId),
     Fct(FctId),
     FctType(FctId, TypeParams),
@@ -1331,6 +1544,7 @@ pub enum CallType {
     CtorNew(ClassId, FctId, TypeParams),
     Ctor(ClassId, FctId, TypeParams),
     Expr(BuiltinType, FctId),
+    Lambda(LambdaId),
 }
 
 impl CallType {
@@ -1362,6 +1576,13 @@ impl CallType {
         }
     }
 
+    pub fn is_lambda(&self) -> bool {
+        match *self {
+            CallType::Lambda(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn fct_id(&self) -> FctId {
         match *self {
             CallType::Fct(fctid, _, _) => fctid,
@@ -1369,6 +1590,7 @@ impl CallType {
             CallType::CtorNew(_, fctid, _) => fctid,
             CallType::Ctor(_, fctid, _) => fctid,
             CallType::Expr(_, fctid) => fctid,
+            CallType::Lambda(_) => panic!("lambda call without callee"),
         }
     }
 }
@@ -1383,6 +1605,13 @@ pub struct CallSite<'ast> {
     pub return_type: BuiltinType,
 }
 
+// call of a lambda: first argument is the closure object
+#[derive(Clone, Debug)]
+pub struct LambdaCallSite<'ast> {
+    pub args: Vec<Arg<'ast>>,
+    pub return_type: BuiltinType,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ConstId(usize);
 
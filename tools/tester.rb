@@ -7,7 +7,8 @@ require 'open3'
 
 $config = {
   :main => '',
-  :cannon => '--bc=cannon'
+  :cannon => '--bc=cannon',
+  :interpreter => '--bc=interpreter'
 }
 
 $ARGS = ARGV.clone
@@ -349,6 +350,7 @@ def parse_test_file(file)
         when "cast" then test_case.expectation.code = 106
         when "unexpected" then test_case.expectation.code = 107
         when "oom" then test_case.expectation.code = 108
+        when "stack-overflow" then test_case.expectation.code = 109
         when "fail"
           # do nothing
         else
@@ -378,6 +380,9 @@ def parse_test_file(file)
       when "cannon"
         test_case.optional_configs.push(:cannon)
 
+      when "interpreter"
+        test_case.optional_configs.push(:interpreter)
+
       else
         raise "unkown expectation in #{file}: #{line}"
 